@@ -0,0 +1,99 @@
+use std::sync::{atomic::AtomicBool, atomic::Ordering, Arc};
+
+use ctrlc;
+use rpkt_dpdk::*;
+use rpkt_stack::{L2Switch, SwitchConf};
+use rpkt_time::{Duration, Instant};
+
+// A learning switch between the ports 0 and 1, running on lcore 1.
+
+fn init_port(port_id: u16, mp_name: &'static str) {
+    let port_info = &service().port_info(port_id).unwrap();
+    let socket_id = port_info.socket_id;
+
+    let mut mpconf = MempoolConf::default();
+    mpconf.nb_mbufs = 8192 * 4;
+    mpconf.per_core_caches = 256;
+    mpconf.socket_id = socket_id;
+    service().mempool_create(mp_name, &mpconf).unwrap();
+
+    let mut pconf = PortConf::from_port_info(port_info).unwrap();
+    // The switch forwards every frame, no matter its destination.
+    pconf.enable_promiscuous = true;
+
+    let mut rxq_conf = RxQueueConf::default();
+    rxq_conf.nb_rx_desc = 1024;
+    rxq_conf.mp_name = mp_name.to_string();
+    rxq_conf.socket_id = socket_id;
+    let mut txq_conf = TxQueueConf::default();
+    txq_conf.nb_tx_desc = 1024;
+    txq_conf.socket_id = socket_id;
+
+    service()
+        .port_configure(port_id, &pconf, &vec![rxq_conf], &vec![txq_conf])
+        .unwrap();
+
+    println!("finish configuring p{}", port_id);
+}
+
+fn main() {
+    DpdkOption::new().init().unwrap();
+
+    let ports = [(0, "p0_mp"), (1, "p1_mp")];
+    for (port_id, mp_name) in ports {
+        init_port(port_id, mp_name);
+    }
+
+    let run = Arc::new(AtomicBool::new(true));
+    let run_clone = run.clone();
+    ctrlc::set_handler(move || {
+        run_clone.store(false, Ordering::Release);
+    })
+    .unwrap();
+
+    let jh = std::thread::spawn(move || {
+        service().lcore_bind(1).unwrap();
+
+        let mut switch = L2Switch::new(SwitchConf::default());
+        for (port_id, mp_name) in ports {
+            switch.add_port(
+                service().rx_queue(port_id, 0).unwrap(),
+                service().tx_queue(port_id, 0).unwrap(),
+                service().mempool(mp_name).unwrap(),
+            );
+        }
+
+        let mut next_report = Instant::now() + Duration::from_secs(1);
+        while run.load(Ordering::Acquire) {
+            switch.poll();
+
+            let now = Instant::now();
+            if now >= next_report {
+                next_report = now + Duration::from_secs(1);
+                println!("learned {} mac addresses", switch.table().len());
+                for port in 0..switch.nb_ports() {
+                    let stats = switch.port_stats(port).unwrap();
+                    println!(
+                        "port {}: rx {} tx {} flooded {} filtered {} tx_dropped {}",
+                        port,
+                        stats.rx_packets,
+                        stats.tx_packets,
+                        stats.flooded,
+                        stats.filtered,
+                        stats.tx_dropped
+                    );
+                }
+            }
+        }
+    });
+    jh.join().unwrap();
+
+    for (port_id, mp_name) in ports {
+        service().port_close(port_id).unwrap();
+        service().mempool_free(mp_name).unwrap();
+    }
+    println!("port closed and mempool freed");
+
+    service().service_close().unwrap();
+    println!("dpdk service shutdown gracefully");
+}
@@ -0,0 +1,116 @@
+use std::net::{Ipv4Addr, Ipv6Addr};
+use std::sync::{atomic::AtomicBool, atomic::Ordering, Arc};
+
+use ctrlc;
+use rpkt::ether::MacAddr;
+use rpkt_dpdk::*;
+use rpkt_stack::{NextHop, Router, RouterPortConf};
+use rpkt_time::{Duration, Instant};
+
+// A router between the subnets 10.0.0.0/24 on port 0 and 10.0.1.0/24 on port 1,
+// running on lcore 1. The default route points to 10.0.1.254.
+
+fn init_port(port_id: u16, mp_name: &'static str) -> MacAddr {
+    let port_info = &service().port_info(port_id).unwrap();
+    let socket_id = port_info.socket_id;
+
+    let mut mpconf = MempoolConf::default();
+    mpconf.nb_mbufs = 8192 * 4;
+    mpconf.per_core_caches = 256;
+    mpconf.socket_id = socket_id;
+    service().mempool_create(mp_name, &mpconf).unwrap();
+
+    let pconf = PortConf::from_port_info(port_info).unwrap();
+
+    let mut rxq_conf = RxQueueConf::default();
+    rxq_conf.nb_rx_desc = 1024;
+    rxq_conf.mp_name = mp_name.to_string();
+    rxq_conf.socket_id = socket_id;
+    let mut txq_conf = TxQueueConf::default();
+    txq_conf.nb_tx_desc = 1024;
+    txq_conf.socket_id = socket_id;
+
+    service()
+        .port_configure(port_id, &pconf, &vec![rxq_conf], &vec![txq_conf])
+        .unwrap();
+
+    println!("finish configuring p{}", port_id);
+    MacAddr(port_info.eth_addr)
+}
+
+fn main() {
+    DpdkOption::new().init().unwrap();
+
+    let ports = [(0, "p0_mp"), (1, "p1_mp")];
+    let mut confs = Vec::new();
+    for (port_id, mp_name) in ports {
+        let mut conf = RouterPortConf::new(init_port(port_id, mp_name));
+        conf.ipv4 = Some((Ipv4Addr::new(10, 0, port_id as u8, 1), 24));
+        conf.ipv6 = Some((Ipv6Addr::new(0x2001, 0xdb8, port_id, 0, 0, 0, 0, 1), 64));
+        confs.push(conf);
+    }
+
+    let run = Arc::new(AtomicBool::new(true));
+    let run_clone = run.clone();
+    ctrlc::set_handler(move || {
+        run_clone.store(false, Ordering::Release);
+    })
+    .unwrap();
+
+    let jh = std::thread::spawn(move || {
+        service().lcore_bind(1).unwrap();
+
+        let mut router = Router::new();
+        for ((port_id, mp_name), conf) in ports.into_iter().zip(confs) {
+            router.add_port(
+                conf,
+                service().rx_queue(port_id, 0).unwrap(),
+                service().tx_queue(port_id, 0).unwrap(),
+                service().mempool(mp_name).unwrap(),
+            );
+        }
+        router.add_route_v4(
+            Ipv4Addr::UNSPECIFIED,
+            0,
+            NextHop {
+                port: 1,
+                gateway: Some(Ipv4Addr::new(10, 0, 1, 254)),
+            },
+        );
+
+        let mut next_report = Instant::now() + Duration::from_secs(1);
+        while run.load(Ordering::Acquire) {
+            router.poll();
+
+            let now = Instant::now();
+            if now >= next_report {
+                next_report = now + Duration::from_secs(1);
+                for port in 0..router.nb_ports() {
+                    let stats = router.port_stats(port).unwrap();
+                    println!(
+                        "port {}: rx {} tx {} forwarded {} ttl_exceeded {} no_route {} too_big {} filtered {} tx_dropped {}",
+                        port,
+                        stats.rx_packets,
+                        stats.tx_packets,
+                        stats.forwarded,
+                        stats.ttl_exceeded,
+                        stats.no_route,
+                        stats.too_big,
+                        stats.filtered,
+                        stats.tx_dropped
+                    );
+                }
+            }
+        }
+    });
+    jh.join().unwrap();
+
+    for (port_id, mp_name) in ports {
+        service().port_close(port_id).unwrap();
+        service().mempool_free(mp_name).unwrap();
+    }
+    println!("port closed and mempool freed");
+
+    service().service_close().unwrap();
+    println!("dpdk service shutdown gracefully");
+}
@@ -0,0 +1,113 @@
+use std::sync::{atomic::AtomicBool, atomic::Ordering, Arc};
+
+use ctrlc;
+use rpkt::ether::MacAddr;
+use rpkt_dpdk::*;
+use rpkt_stack::{SniBalancer, SniBalancerConf};
+use rpkt_time::{Duration, Instant};
+
+// A TLS load balancer on lcore 1. The clients reach the virtual IP address
+// through port 0, and the backends are behind port 1, each of them served by
+// a tx queue of port 1.
+
+const BACKENDS: [[u8; 6]; 4] = [
+    [0x02, 0x00, 0x00, 0x00, 0x00, 0x01],
+    [0x02, 0x00, 0x00, 0x00, 0x00, 0x02],
+    [0x02, 0x00, 0x00, 0x00, 0x00, 0x03],
+    [0x02, 0x00, 0x00, 0x00, 0x00, 0x04],
+];
+
+fn init_port(port_id: u16, mp_name: &'static str, nb_txqs: usize) {
+    let port_info = &service().port_info(port_id).unwrap();
+    let socket_id = port_info.socket_id;
+
+    let mut mpconf = MempoolConf::default();
+    mpconf.nb_mbufs = 8192 * 4;
+    mpconf.per_core_caches = 256;
+    mpconf.socket_id = socket_id;
+    service().mempool_create(mp_name, &mpconf).unwrap();
+
+    let mut pconf = PortConf::from_port_info(port_info).unwrap();
+    // The frames are sent to the virtual IP address, not to the balancer.
+    pconf.enable_promiscuous = true;
+
+    let mut rxq_conf = RxQueueConf::default();
+    rxq_conf.nb_rx_desc = 1024;
+    rxq_conf.mp_name = mp_name.to_string();
+    rxq_conf.socket_id = socket_id;
+    let mut txq_conf = TxQueueConf::default();
+    txq_conf.nb_tx_desc = 1024;
+    txq_conf.socket_id = socket_id;
+
+    service()
+        .port_configure(port_id, &pconf, &vec![rxq_conf], &vec![txq_conf; nb_txqs])
+        .unwrap();
+
+    println!("finish configuring p{}", port_id);
+}
+
+fn main() {
+    DpdkOption::new().init().unwrap();
+
+    init_port(0, "p0_mp", 1);
+    init_port(1, "p1_mp", BACKENDS.len());
+
+    let run = Arc::new(AtomicBool::new(true));
+    let run_clone = run.clone();
+    ctrlc::set_handler(move || {
+        run_clone.store(false, Ordering::Release);
+    })
+    .unwrap();
+
+    let jh = std::thread::spawn(move || {
+        service().lcore_bind(1).unwrap();
+
+        let mut balancer = SniBalancer::new(
+            service().rx_queue(0, 0).unwrap(),
+            SniBalancerConf::default(),
+        );
+        for (queue_id, mac) in BACKENDS.iter().enumerate() {
+            balancer.add_backend(
+                service().tx_queue(1, queue_id as u16).unwrap(),
+                MacAddr(*mac),
+            );
+        }
+
+        let mut next_report = Instant::now() + Duration::from_secs(1);
+        while run.load(Ordering::Acquire) {
+            balancer.poll();
+
+            let now = Instant::now();
+            if now >= next_report {
+                next_report = now + Duration::from_secs(1);
+                let stats = balancer.stats();
+                println!(
+                    "rx {} pinned {} classified {} unclassified {} filtered {}, {} connections",
+                    stats.rx_packets,
+                    stats.pinned,
+                    stats.classified,
+                    stats.unclassified,
+                    stats.filtered,
+                    balancer.flows().len()
+                );
+                for backend in 0..balancer.nb_backends() {
+                    let stats = balancer.backend_stats(backend).unwrap();
+                    println!(
+                        "backend {}: tx {} tx_dropped {}",
+                        backend, stats.tx_packets, stats.tx_dropped
+                    );
+                }
+            }
+        }
+    });
+    jh.join().unwrap();
+
+    for (port_id, mp_name) in [(0, "p0_mp"), (1, "p1_mp")] {
+        service().port_close(port_id).unwrap();
+        service().mempool_free(mp_name).unwrap();
+    }
+    println!("port closed and mempool freed");
+
+    service().service_close().unwrap();
+    println!("dpdk service shutdown gracefully");
+}
@@ -0,0 +1,29 @@
+use std::collections::BTreeMap;
+use std::fs::File;
+use std::io::BufWriter;
+use std::path::PathBuf;
+
+use rpkt::corpus::{self, Sample};
+
+// Write the packet corpus into `<dir>/<protocol>.pcap`, with the label of each
+// frame in `<dir>/<protocol>.labels`.
+//
+// cargo run --example gen_corpus -- /tmp/corpus
+
+fn main() -> std::io::Result<()> {
+    let dir = PathBuf::from(std::env::args().nth(1).unwrap_or_else(|| "corpus".into()));
+    std::fs::create_dir_all(&dir)?;
+
+    let mut protocols: BTreeMap<&str, Vec<Sample>> = BTreeMap::new();
+    for sample in corpus::generate() {
+        protocols.entry(sample.protocol).or_default().push(sample);
+    }
+
+    for (protocol, samples) in protocols.iter() {
+        let pcap = BufWriter::new(File::create(dir.join(format!("{protocol}.pcap")))?);
+        let labels = BufWriter::new(File::create(dir.join(format!("{protocol}.labels")))?);
+        corpus::write_pcap(samples, pcap, labels)?;
+        println!("{}: {} frames", protocol, samples.len());
+    }
+    Ok(())
+}
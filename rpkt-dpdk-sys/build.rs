@@ -56,6 +56,10 @@ fn build_dpdk_ffi() {
         .allowlist_function("rte_eth_dev_count_avail")
         .allowlist_function("rte_eth_macaddr_get")
         .allowlist_function("rte_eth_stats_get")
+        .allowlist_function("rte_eth_dev_set_rx_queue_stats_mapping")
+        .allowlist_function("rte_eth_dev_set_tx_queue_stats_mapping")
+        .allowlist_function("rte_eth_xstats_get")
+        .allowlist_function("rte_eth_xstats_get_names")
         .allowlist_function("rte_eth_dev_socket_id")
         .allowlist_function("rte_eth_dev_configure")
         .allowlist_function("rte_eth_dev_start")
@@ -65,8 +69,14 @@ fn build_dpdk_ffi() {
         .allowlist_function("rte_eth_tx_queue_setup")
         .allowlist_function("rte_eth_promiscuous_enable")
         .allowlist_function("rte_eth_promiscuous_disable")
+        .allowlist_function("rte_eth_dev_rx_intr_enable")
+        .allowlist_function("rte_eth_dev_rx_intr_disable")
+        .allowlist_function("rte_eth_dev_rx_intr_ctl_q_get_fd")
+        .allowlist_function("rte_eth_rx_queue_info_get")
+        .allowlist_function("rte_eth_tx_queue_info_get")
         .allowlist_function("rte_eal_init")
         .allowlist_function("rte_eal_cleanup")
+        .allowlist_function("rte_mbuf_dynfield_register")
         // generate useful dpdk types
         .allowlist_type("rte_eth_conf")
         .allowlist_type("rte_eth_dev_info")
@@ -74,6 +84,11 @@ fn build_dpdk_ffi() {
         .allowlist_type("rte_mempool")
         .allowlist_type("rte_mbuf")
         .allowlist_type("rte_eth_stats")
+        .allowlist_type("rte_eth_xstat")
+        .allowlist_type("rte_eth_xstat_name")
+        .allowlist_type("rte_mbuf_dynfield")
+        .allowlist_type("rte_eth_rxq_info")
+        .allowlist_type("rte_eth_txq_info")
         // generate useful dpdk macros defined in rte_build_config.h.
         .allowlist_var("RTE_MAX_LCORE")
         .allowlist_var("RTE_MAX_NUMA_NODES")
@@ -81,6 +96,9 @@ fn build_dpdk_ffi() {
         .allowlist_var("RTE_MBUF_DEFAULT_DATAROOM")
         .allowlist_var("RTE_PKTMBUF_HEADROOM")
         .allowlist_var("RTE_ETHDEV_QUEUE_STAT_CNTRS")
+        .allowlist_var("RTE_ETH_RX_DESC_.*")
+        .allowlist_var("RTE_ETH_TX_DESC_.*")
+        .allowlist_var("RTE_ETH_QUEUE_STATE_.*")
         .header("csrc/header.h");
     for cflag in cflags_iter {
         bgbuilder = bgbuilder.clang_arg(cflag);
@@ -2,6 +2,7 @@
 #define _GNU_SOURCE
 #include <rte_eal.h>
 #include <rte_ethdev.h>
+#include <rte_mbuf_dyn.h>
 
 // Add wrapper definitions for functions that bindgen can not generate.
 //
@@ -22,6 +23,8 @@ unsigned rte_lcore_id_();
 
 int rte_mempool_full_(const struct rte_mempool *mp);
 
+uint16_t rte_pktmbuf_data_room_size_(struct rte_mempool *mp);
+
 struct rte_mbuf *rte_pktmbuf_alloc_(struct rte_mempool *mp);
 
 int rte_pktmbuf_alloc_bulk_(struct rte_mempool *pool,
@@ -35,4 +38,12 @@ uint16_t rte_eth_rx_burst_(uint16_t port_id, uint16_t queue_id,
 uint16_t rte_eth_tx_burst_(uint16_t port_id, uint16_t queue_id,
 						   struct rte_mbuf **tx_pkts, uint16_t nb_pkts);
 
+int rte_eth_rx_queue_count_(uint16_t port_id, uint16_t queue_id);
+
+int rte_eth_rx_descriptor_status_(uint16_t port_id, uint16_t queue_id,
+								  uint16_t offset);
+
+int rte_eth_tx_descriptor_status_(uint16_t port_id, uint16_t queue_id,
+								  uint16_t offset);
+
 int rte_errno_();
\ No newline at end of file
@@ -13,6 +13,11 @@ int rte_mempool_full_(const struct rte_mempool *mp)
     return rte_mempool_full(mp);
 }
 
+uint16_t rte_pktmbuf_data_room_size_(struct rte_mempool *mp)
+{
+    return rte_pktmbuf_data_room_size(mp);
+}
+
 struct rte_mbuf *rte_pktmbuf_alloc_(struct rte_mempool *mp)
 {
     return rte_pktmbuf_alloc(mp);
@@ -41,6 +46,23 @@ uint16_t rte_eth_tx_burst_(uint16_t port_id, uint16_t queue_id,
     return rte_eth_tx_burst(port_id, queue_id, tx_pkts, nb_pkts);
 }
 
+int rte_eth_rx_queue_count_(uint16_t port_id, uint16_t queue_id)
+{
+    return rte_eth_rx_queue_count(port_id, queue_id);
+}
+
+int rte_eth_rx_descriptor_status_(uint16_t port_id, uint16_t queue_id,
+                                  uint16_t offset)
+{
+    return rte_eth_rx_descriptor_status(port_id, queue_id, offset);
+}
+
+int rte_eth_tx_descriptor_status_(uint16_t port_id, uint16_t queue_id,
+                                  uint16_t offset)
+{
+    return rte_eth_tx_descriptor_status(port_id, queue_id, offset);
+}
+
 int rte_errno_()
 {
     return rte_errno;
@@ -328,9 +328,8 @@ fn init_port(
 
     // get the default port conf
     let mut port_conf = PortConf::from_port_info(&port_info)?;
-    port_conf.mtu = 9000;
-    port_conf.rx_offloads.enable_scatter();
-    port_conf.tx_offloads.enable_multi_segs();
+    // 9000-byte frames do not fit in the default mbuf, so they are scattered
+    port_conf.set_jumbo_mtu(&port_info, 9000, MempoolConf::DATAROOM)?;
 
     // configure rxq
     let mut rxq_conf = RxQueueConf::default();
@@ -0,0 +1,262 @@
+use std::os::unix::io::RawFd;
+use std::pin::Pin;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::task::{Context, Poll, Waker};
+use std::thread::JoinHandle;
+
+use arrayvec::ArrayVec;
+use futures_core::Stream;
+use futures_sink::Sink;
+
+use crate::error::*;
+use crate::{Mbuf, RxQueue, TxQueue};
+
+/// The number of mbufs received or sent by a single burst.
+pub const BURST_SIZE: usize = 32;
+
+type Burst = ArrayVec<Mbuf, BURST_SIZE>;
+
+/// How an idle queue wakes up the task that polls it.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum WakeStrategy {
+    /// Wake the task immediately, so that the executor polls the queue again after it
+    /// runs the other ready tasks. It has the lowest latency, but keeps a core busy.
+    Spin,
+    /// Wait for the rx interrupt of the queue on a helper thread, which wakes the task
+    /// when a packet arrives. The port must be created with
+    /// `PortConf::enable_rx_intr`.
+    Interrupt,
+}
+
+/// Receive the mbufs of a `RxQueue` as a `Stream`.
+///
+/// The stream never ends, it yields the mbufs one by one from a burst buffer, and
+/// receives a new burst from the queue when the buffer is drained.
+///
+/// # Examples
+/// ```no_run
+/// use std::future::poll_fn;
+/// use std::pin::Pin;
+///
+/// use futures_core::Stream;
+/// use rpkt_dpdk::async_queue::{RxStream, WakeStrategy};
+/// use rpkt_dpdk::*;
+///
+/// async fn recv_loop() {
+///     let rxq = service().rx_queue(0, 0).unwrap();
+///     let mut stream = RxStream::new(rxq, WakeStrategy::Interrupt).unwrap();
+///     while let Some(mbuf) = poll_fn(|cx| Pin::new(&mut stream).poll_next(cx)).await {
+///         println!("received {} bytes", mbuf.len());
+///     }
+/// }
+/// ```
+pub struct RxStream {
+    rxq: RxQueue,
+    pending: arrayvec::IntoIter<Mbuf, BURST_SIZE>,
+    intr: Option<IntrWaiter>,
+}
+
+impl RxStream {
+    /// Create a stream that receives from `rxq`, and wakes up with `strategy` when the
+    /// queue is idle.
+    pub fn new(rxq: RxQueue, strategy: WakeStrategy) -> Result<Self> {
+        let intr = match strategy {
+            WakeStrategy::Spin => None,
+            WakeStrategy::Interrupt => Some(IntrWaiter::new(rxq.rx_intr_fd()?)),
+        };
+        Ok(Self {
+            rxq,
+            pending: Burst::new().into_iter(),
+            intr,
+        })
+    }
+
+    /// Consume the stream and return the rx queue.
+    ///
+    /// The mbufs that are received but not yet yielded are dropped.
+    pub fn into_inner(self) -> RxQueue {
+        if self.intr.is_some() {
+            let _ = self.rxq.disable_rx_intr();
+        }
+        self.rxq
+    }
+
+    fn try_rx(&mut self) -> bool {
+        let mut burst = Burst::new();
+        if self.rxq.rx(&mut burst) == 0 {
+            return false;
+        }
+        self.pending = burst.into_iter();
+        true
+    }
+}
+
+impl Stream for RxStream {
+    type Item = Mbuf;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Mbuf>> {
+        let this = self.get_mut();
+        if let Some(mbuf) = this.pending.next() {
+            return Poll::Ready(Some(mbuf));
+        }
+
+        if let Some(intr) = this.intr.as_ref() {
+            if intr.armed.swap(false, Ordering::AcqRel) {
+                let _ = this.rxq.disable_rx_intr();
+            }
+        }
+        if this.try_rx() {
+            return Poll::Ready(this.pending.next());
+        }
+
+        match this.intr.as_ref() {
+            None => cx.waker().wake_by_ref(),
+            Some(intr) => {
+                // Register the waker before enabling the interrupt, and check the queue
+                // again, so that a packet arriving in between is not missed.
+                *intr.waker.lock().unwrap() = Some(cx.waker().clone());
+                if this.rxq.enable_rx_intr().is_err() {
+                    cx.waker().wake_by_ref();
+                    return Poll::Pending;
+                }
+                intr.armed.store(true, Ordering::Release);
+                if this.try_rx() {
+                    return Poll::Ready(this.pending.next());
+                }
+            }
+        }
+        Poll::Pending
+    }
+}
+
+// A helper thread that waits on the rx interrupt fd, and wakes up the registered waker.
+struct IntrWaiter {
+    waker: Arc<Mutex<Option<Waker>>>,
+    armed: AtomicBool,
+    stop: Arc<AtomicBool>,
+    handle: Option<JoinHandle<()>>,
+}
+
+impl IntrWaiter {
+    // The interval for checking whether the helper thread should stop.
+    const POLL_TIMEOUT_MS: i32 = 100;
+
+    fn new(fd: RawFd) -> Self {
+        let waker: Arc<Mutex<Option<Waker>>> = Arc::new(Mutex::new(None));
+        let stop = Arc::new(AtomicBool::new(false));
+
+        let handle = {
+            let waker = waker.clone();
+            let stop = stop.clone();
+            std::thread::spawn(move || {
+                let mut pfd = libc::pollfd {
+                    fd,
+                    events: libc::POLLIN,
+                    revents: 0,
+                };
+                while !stop.load(Ordering::Acquire) {
+                    let res = unsafe { libc::poll(&mut pfd, 1, Self::POLL_TIMEOUT_MS) };
+                    if res <= 0 || pfd.revents & libc::POLLIN == 0 {
+                        continue;
+                    }
+                    // Clear the event of the fd.
+                    let mut buf = [0u8; 8];
+                    unsafe { libc::read(fd, buf.as_mut_ptr() as *mut libc::c_void, buf.len()) };
+                    if let Some(waker) = waker.lock().unwrap().take() {
+                        waker.wake();
+                    }
+                }
+            })
+        };
+
+        Self {
+            waker,
+            armed: AtomicBool::new(false),
+            stop,
+            handle: Some(handle),
+        }
+    }
+}
+
+impl Drop for IntrWaiter {
+    fn drop(&mut self) {
+        self.stop.store(true, Ordering::Release);
+        if let Some(handle) = self.handle.take() {
+            let _ = handle.join();
+        }
+    }
+}
+
+/// Send mbufs to a `TxQueue` as a `Sink`.
+///
+/// The mbufs are buffered into a burst, which is sent when it is full or when the sink
+/// is flushed. The tx queue has no interrupt, so a full queue is always polled with
+/// the `WakeStrategy::Spin` strategy.
+pub struct TxSink {
+    txq: TxQueue,
+    burst: Burst,
+}
+
+impl TxSink {
+    pub fn new(txq: TxQueue) -> Self {
+        Self {
+            txq,
+            burst: Burst::new(),
+        }
+    }
+
+    /// Consume the sink and return the tx queue.
+    ///
+    /// The buffered mbufs that are not yet sent are dropped.
+    pub fn into_inner(self) -> TxQueue {
+        self.txq
+    }
+
+    // Send the buffered mbufs, returns true if the burst is empty.
+    fn try_tx(&mut self) -> bool {
+        if !self.burst.is_empty() {
+            self.txq.tx(&mut self.burst);
+        }
+        self.burst.is_empty()
+    }
+}
+
+impl Sink<Mbuf> for TxSink {
+    type Error = Error;
+
+    fn poll_ready(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<()>> {
+        let this = self.get_mut();
+        if !this.burst.is_full() {
+            return Poll::Ready(Ok(()));
+        }
+        this.try_tx();
+        if this.burst.is_full() {
+            cx.waker().wake_by_ref();
+            Poll::Pending
+        } else {
+            Poll::Ready(Ok(()))
+        }
+    }
+
+    fn start_send(self: Pin<&mut Self>, mbuf: Mbuf) -> Result<()> {
+        let this = self.get_mut();
+        if this.burst.try_push(mbuf).is_err() {
+            return Error::service_err("the tx burst is full").to_err();
+        }
+        Ok(())
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<()>> {
+        if self.get_mut().try_tx() {
+            Poll::Ready(Ok(()))
+        } else {
+            cx.waker().wake_by_ref();
+            Poll::Pending
+        }
+    }
+
+    fn poll_close(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<()>> {
+        self.poll_flush(cx)
+    }
+}
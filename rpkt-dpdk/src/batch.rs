@@ -0,0 +1,327 @@
+use std::mem::MaybeUninit;
+use std::ptr;
+use std::slice;
+
+use arrayvec::ArrayVec;
+
+use crate::Mbuf;
+
+/// A fixed-capacity batch of `Mbuf`s, which is filled by `RxQueue::rx` and drained by
+/// `TxQueue::tx`.
+///
+/// The mbufs of a batch are stored contiguously, so that the burst functions of dpdk can
+/// write into and read from the batch directly. The trait is implemented for `ArrayVec`,
+/// `Vec`, `SmallVec` (with the `smallvec` feature) and [`SliceBatch`], which turns a
+/// raw array into a batch.
+///
+/// # Safety
+/// `as_mut_ptr` must point to a buffer that holds at least `capacity` mbufs, of which
+/// the first `len` are initialized. The buffer must not be moved or reallocated as long
+/// as the length is only changed through `set_len`.
+pub unsafe trait Batch {
+    /// Returns the number of mbufs that the batch can hold.
+    fn capacity(&self) -> usize;
+
+    /// Returns the number of mbufs in the batch.
+    fn len(&self) -> usize;
+
+    /// Returns a pointer to the first mbuf of the batch.
+    fn as_ptr(&self) -> *const Mbuf;
+
+    /// Returns a mutable pointer to the first mbuf of the batch.
+    fn as_mut_ptr(&mut self) -> *mut Mbuf;
+
+    /// Set the number of mbufs in the batch.
+    ///
+    /// # Safety
+    /// `len` must not exceed the capacity, and the first `len` mbufs must be initialized.
+    /// The mbufs beyond `len` are forgotten without being dropped.
+    unsafe fn set_len(&mut self, len: usize);
+
+    #[inline]
+    fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    #[inline]
+    fn is_full(&self) -> bool {
+        self.len() == self.capacity()
+    }
+
+    #[inline]
+    fn remaining_capacity(&self) -> usize {
+        self.capacity() - self.len()
+    }
+
+    #[inline]
+    fn as_slice(&self) -> &[Mbuf] {
+        unsafe { slice::from_raw_parts(self.as_ptr(), self.len()) }
+    }
+
+    #[inline]
+    fn as_mut_slice(&mut self) -> &mut [Mbuf] {
+        unsafe { slice::from_raw_parts_mut(self.as_mut_ptr(), self.len()) }
+    }
+
+    #[inline]
+    fn iter(&self) -> slice::Iter<'_, Mbuf> {
+        self.as_slice().iter()
+    }
+
+    #[inline]
+    fn iter_mut(&mut self) -> slice::IterMut<'_, Mbuf> {
+        self.as_mut_slice().iter_mut()
+    }
+
+    /// Append `mbuf` to the batch, or return it back if the batch is full.
+    #[inline]
+    fn try_push(&mut self, mbuf: Mbuf) -> Result<(), Mbuf> {
+        let len = self.len();
+        if len == self.capacity() {
+            return Err(mbuf);
+        }
+        unsafe {
+            ptr::write(self.as_mut_ptr().add(len), mbuf);
+            self.set_len(len + 1);
+        }
+        Ok(())
+    }
+
+    /// Append `mbuf` to the batch.
+    ///
+    /// # Panics
+    /// The batch must not be full.
+    #[inline]
+    fn push(&mut self, mbuf: Mbuf) {
+        if self.try_push(mbuf).is_err() {
+            panic!("the batch is full");
+        }
+    }
+
+    /// Drop all the mbufs in the batch.
+    #[inline]
+    fn clear(&mut self) {
+        let len = self.len();
+        unsafe {
+            self.set_len(0);
+            ptr::drop_in_place(ptr::slice_from_raw_parts_mut(self.as_mut_ptr(), len));
+        }
+    }
+}
+
+unsafe impl<const N: usize> Batch for ArrayVec<Mbuf, N> {
+    #[inline]
+    fn capacity(&self) -> usize {
+        N
+    }
+
+    #[inline]
+    fn len(&self) -> usize {
+        ArrayVec::len(self)
+    }
+
+    #[inline]
+    fn as_ptr(&self) -> *const Mbuf {
+        ArrayVec::as_ptr(self)
+    }
+
+    #[inline]
+    fn as_mut_ptr(&mut self) -> *mut Mbuf {
+        ArrayVec::as_mut_ptr(self)
+    }
+
+    #[inline]
+    unsafe fn set_len(&mut self, len: usize) {
+        ArrayVec::set_len(self, len)
+    }
+}
+
+/// The capacity of a `Vec` is the capacity that it is allocated with, the batch never
+/// grows.
+unsafe impl Batch for Vec<Mbuf> {
+    #[inline]
+    fn capacity(&self) -> usize {
+        Vec::capacity(self)
+    }
+
+    #[inline]
+    fn len(&self) -> usize {
+        Vec::len(self)
+    }
+
+    #[inline]
+    fn as_ptr(&self) -> *const Mbuf {
+        Vec::as_ptr(self)
+    }
+
+    #[inline]
+    fn as_mut_ptr(&mut self) -> *mut Mbuf {
+        Vec::as_mut_ptr(self)
+    }
+
+    #[inline]
+    unsafe fn set_len(&mut self, len: usize) {
+        Vec::set_len(self, len)
+    }
+}
+
+/// The capacity of a `SmallVec` is its current capacity, the batch never spills.
+#[cfg(feature = "smallvec")]
+unsafe impl<const N: usize> Batch for smallvec::SmallVec<[Mbuf; N]> {
+    #[inline]
+    fn capacity(&self) -> usize {
+        smallvec::SmallVec::capacity(self)
+    }
+
+    #[inline]
+    fn len(&self) -> usize {
+        smallvec::SmallVec::len(self)
+    }
+
+    #[inline]
+    fn as_ptr(&self) -> *const Mbuf {
+        smallvec::SmallVec::as_ptr(self)
+    }
+
+    #[inline]
+    fn as_mut_ptr(&mut self) -> *mut Mbuf {
+        smallvec::SmallVec::as_mut_ptr(self)
+    }
+
+    #[inline]
+    unsafe fn set_len(&mut self, len: usize) {
+        smallvec::SmallVec::set_len(self, len)
+    }
+}
+
+/// A batch backed by a borrowed array of uninitialized mbufs.
+///
+/// The mbufs remaining in the batch are dropped when the `SliceBatch` is dropped.
+///
+/// # Examples
+/// ```no_run
+/// use std::mem::MaybeUninit;
+/// use rpkt_dpdk::*;
+///
+/// let mut rxq = service().rx_queue(0, 0).unwrap();
+/// let mut buf: [MaybeUninit<Mbuf>; 32] = unsafe { MaybeUninit::uninit().assume_init() };
+/// let mut batch = SliceBatch::new(&mut buf);
+/// rxq.rx(&mut batch);
+/// ```
+pub struct SliceBatch<'a> {
+    buf: &'a mut [MaybeUninit<Mbuf>],
+    len: usize,
+}
+
+impl<'a> SliceBatch<'a> {
+    /// Create an empty batch that stores the mbufs in `buf`.
+    #[inline]
+    pub fn new(buf: &'a mut [MaybeUninit<Mbuf>]) -> Self {
+        Self { buf, len: 0 }
+    }
+}
+
+unsafe impl<'a> Batch for SliceBatch<'a> {
+    #[inline]
+    fn capacity(&self) -> usize {
+        self.buf.len()
+    }
+
+    #[inline]
+    fn len(&self) -> usize {
+        self.len
+    }
+
+    #[inline]
+    fn as_ptr(&self) -> *const Mbuf {
+        self.buf.as_ptr() as *const Mbuf
+    }
+
+    #[inline]
+    fn as_mut_ptr(&mut self) -> *mut Mbuf {
+        self.buf.as_mut_ptr() as *mut Mbuf
+    }
+
+    #[inline]
+    unsafe fn set_len(&mut self, len: usize) {
+        self.len = len;
+    }
+}
+
+impl<'a> Drop for SliceBatch<'a> {
+    fn drop(&mut self) {
+        self.clear();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::mem::MaybeUninit;
+
+    use crate::*;
+
+    // Fill `batch` through the default methods of `Batch`, then clear it and check
+    // that every mbuf of the batch is returned to the drained mempool `mp`.
+    fn push_and_clear<B: Batch>(batch: &mut B, mbufs: &mut Vec<Mbuf>, mp: &Mempool) {
+        let capacity = batch.capacity();
+        assert!(batch.is_empty());
+        assert_eq!(batch.remaining_capacity(), capacity);
+
+        for _ in 0..capacity {
+            batch.try_push(mbufs.pop().unwrap()).unwrap();
+        }
+        assert!(batch.is_full());
+        assert_eq!(batch.len(), capacity);
+        assert_eq!(batch.remaining_capacity(), 0);
+
+        // A full batch hands the mbuf back.
+        let mbuf = mbufs.pop().unwrap();
+        let raw = mbuf.as_ptr();
+        let mbuf = batch.try_push(mbuf).unwrap_err();
+        assert_eq!(mbuf.as_ptr(), raw);
+        assert_eq!(batch.len(), capacity);
+        mbufs.push(mbuf);
+
+        assert!(mp.try_alloc().is_none());
+        batch.clear();
+        assert!(batch.is_empty());
+        let mut freed = Vec::new();
+        while let Some(mbuf) = mp.try_alloc() {
+            freed.push(mbuf);
+        }
+        assert_eq!(freed.len(), capacity);
+        mbufs.append(&mut freed);
+    }
+
+    #[test]
+    fn batch_push_and_clear() {
+        DpdkOption::new().init().unwrap();
+
+        {
+            let mut config = MempoolConf::new();
+            config.set_nb_mbufs(128);
+            let mp = service().mempool_create("wtf", &config).unwrap();
+
+            let mut mbufs = Vec::new();
+            while let Some(mbuf) = mp.try_alloc() {
+                mbufs.push(mbuf);
+            }
+
+            let mut batch: Vec<Mbuf> = Vec::with_capacity(32);
+            push_and_clear(&mut batch, &mut mbufs, &mp);
+
+            let mut buf: [MaybeUninit<Mbuf>; 32] = unsafe { MaybeUninit::uninit().assume_init() };
+            let mut batch = SliceBatch::new(&mut buf);
+            push_and_clear(&mut batch, &mut mbufs, &mp);
+
+            // The mbufs remaining in a `SliceBatch` are dropped with it.
+            batch.push(mbufs.pop().unwrap());
+            batch.push(mbufs.pop().unwrap());
+            drop(batch);
+            let freed: Vec<Mbuf> = std::iter::from_fn(|| mp.try_alloc()).collect();
+            assert_eq!(freed.len(), 2);
+        }
+
+        service().mempool_free("wtf").unwrap();
+    }
+}
@@ -0,0 +1,527 @@
+//! Bootstrap the dpdk service from a TOML configuration file.
+//!
+//! The file describes the eal arguments, the mempools and the ports to create,
+//! all the omitted fields take the defaults of the corresponding `*Conf`:
+//!
+//! ```toml
+//! eal_args = ["-l", "0-3", "-n", "4", "-a", "0000:3b:00.0"]
+//!
+//! [[mempool]]
+//! name = "mp"
+//! nb_mbufs = 8192
+//! per_core_caches = 256
+//!
+//! [[port]]
+//! port_id = 0
+//! mtu = 1500
+//! tx_offloads = ["ipv4_cksum", "udp_cksum"]
+//! rx_queues = [{ mempool = "mp" }, { mempool = "mp", nb_desc = 1024 }]
+//! tx_queues = [{}, {}]
+//! ```
+//!
+//! The known offloads are the ones of [`DevTxOffload`] and [`DevRxOffload`],
+//! named after their getters, e.g. `ipv4_cksum`. When the offloads of a port
+//! are omitted, the checksum offloads supported by the port are enabled, as
+//! [`PortConf::from_port_info`] does.
+
+use std::collections::HashSet;
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::Path;
+
+use serde::{Deserialize, Serialize};
+
+use crate::error::Error;
+use crate::offload::{DevRxOffload, DevTxOffload};
+use crate::port::DescLim;
+use crate::{service, DpdkOption, DpdkService, MempoolConf, PortConf, RxQueueConf, TxQueueConf};
+
+#[cfg(not(feature = "multiseg"))]
+const TX_OFFLOADS: [&str; 3] = ["ipv4_cksum", "udp_cksum", "tcp_cksum"];
+#[cfg(feature = "multiseg")]
+const TX_OFFLOADS: [&str; 5] = [
+    "ipv4_cksum",
+    "udp_cksum",
+    "tcp_cksum",
+    "tcp_tso",
+    "multi_segs",
+];
+
+#[cfg(not(feature = "multiseg"))]
+const RX_OFFLOADS: [&str; 4] = ["ipv4_cksum", "udp_cksum", "tcp_cksum", "rss_hash"];
+#[cfg(feature = "multiseg")]
+const RX_OFFLOADS: [&str; 6] = [
+    "ipv4_cksum",
+    "udp_cksum",
+    "tcp_cksum",
+    "rss_hash",
+    "tcp_lro",
+    "scatter",
+];
+
+/// The errors of loading, validating and applying a [`DpdkConfig`].
+#[derive(Debug)]
+pub enum ConfigError {
+    /// The file can not be read.
+    Io(io::Error),
+    /// The file is not a valid TOML description of a [`DpdkConfig`].
+    Parse(toml::de::Error),
+    /// The configuration is inconsistent, or not supported by the devices.
+    Invalid(String),
+    /// The dpdk service fails to apply the configuration.
+    Dpdk(Error),
+}
+
+impl fmt::Display for ConfigError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ConfigError::Io(err) => write!(f, "fail to read the config: {}", err),
+            ConfigError::Parse(err) => write!(f, "fail to parse the config: {}", err),
+            ConfigError::Invalid(msg) => write!(f, "invalid config: {}", msg),
+            ConfigError::Dpdk(err) => write!(f, "fail to apply the config: {}", err),
+        }
+    }
+}
+
+impl std::error::Error for ConfigError {}
+
+impl From<io::Error> for ConfigError {
+    fn from(err: io::Error) -> Self {
+        ConfigError::Io(err)
+    }
+}
+
+impl From<Error> for ConfigError {
+    fn from(err: Error) -> Self {
+        ConfigError::Dpdk(err)
+    }
+}
+
+/// A mempool to create.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct MempoolConfig {
+    pub name: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub nb_mbufs: Option<u32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub per_core_caches: Option<u32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub dataroom: Option<u16>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub socket_id: Option<u32>,
+}
+
+/// An rx queue of a port, receiving the mbufs from the mempool named
+/// `mempool`.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct RxQueueConfig {
+    pub mempool: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub nb_desc: Option<u16>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub socket_id: Option<u32>,
+}
+
+/// A tx queue of a port.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct TxQueueConfig {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub nb_desc: Option<u16>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub socket_id: Option<u32>,
+}
+
+/// A port to configure, the queues are configured in order of their ids.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct PortConfig {
+    pub port_id: u16,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub mtu: Option<u32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub promiscuous: Option<bool>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tx_offloads: Option<Vec<String>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub rx_offloads: Option<Vec<String>>,
+    pub rx_queues: Vec<RxQueueConfig>,
+    pub tx_queues: Vec<TxQueueConfig>,
+}
+
+/// The description of the dpdk service.
+///
+/// [`DpdkService::from_config`] returns the effective configuration, with all
+/// the defaults filled in. Its [`Display`](fmt::Display) implementation dumps
+/// it back to TOML, which can be logged or saved to reproduce the setup.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct DpdkConfig {
+    /// The eal arguments, excluding the program name. The
+    /// [`DEFAULT_EAL_ARGS`](DpdkOption::DEFAULT_EAL_ARGS) are used if it is
+    /// empty.
+    #[serde(default)]
+    pub eal_args: Vec<String>,
+    #[serde(default, rename = "mempool")]
+    pub mempools: Vec<MempoolConfig>,
+    #[serde(default, rename = "port")]
+    pub ports: Vec<PortConfig>,
+}
+
+impl DpdkConfig {
+    /// Read and validate the configuration file at `path`.
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Self, ConfigError> {
+        fs::read_to_string(path)?.parse()
+    }
+
+    /// Check the consistency of the configuration, without the devices.
+    pub fn validate(&self) -> Result<(), ConfigError> {
+        let mut mempools = HashSet::new();
+        for mempool in self.mempools.iter() {
+            if mempool.name.is_empty() {
+                return invalid("empty mempool name".to_string());
+            }
+            if !mempools.insert(mempool.name.as_str()) {
+                return invalid(format!("duplicated mempool {:?}", mempool.name));
+            }
+            if mempool.nb_mbufs == Some(0) {
+                return invalid(format!("mempool {:?} has no mbufs", mempool.name));
+            }
+        }
+
+        let mut ports = HashSet::new();
+        for port in self.ports.iter() {
+            if !ports.insert(port.port_id) {
+                return invalid(format!("duplicated port {}", port.port_id));
+            }
+            if port.rx_queues.is_empty() || port.tx_queues.is_empty() {
+                return invalid(format!("port {} has no rx or tx queue", port.port_id));
+            }
+            for rxq in port.rx_queues.iter() {
+                if !mempools.contains(rxq.mempool.as_str()) {
+                    return invalid(format!(
+                        "port {} uses an unknown mempool {:?}",
+                        port.port_id, rxq.mempool
+                    ));
+                }
+            }
+            for name in port.tx_offloads.iter().flatten() {
+                if tx_offload(name).is_none() {
+                    return invalid(format!("unknown tx offload {:?}", name));
+                }
+            }
+            for name in port.rx_offloads.iter().flatten() {
+                if rx_offload(name).is_none() {
+                    return invalid(format!("unknown rx offload {:?}", name));
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Initialize the dpdk service, create the mempools and configure the
+    /// ports, returns the effective configuration.
+    ///
+    /// The configuration is validated against the capabilities of each port
+    /// before it is configured. On error, the resources created so far are
+    /// left in the service.
+    pub fn bootstrap(&self) -> Result<DpdkConfig, ConfigError> {
+        self.validate()?;
+
+        let mut option = DpdkOption::new();
+        let eal_args = if self.eal_args.is_empty() {
+            DpdkOption::DEFAULT_EAL_ARGS.map(String::from).to_vec()
+        } else {
+            self.eal_args.clone()
+        };
+        option.set_eal_args(&eal_args);
+        option.init()?;
+
+        let mut effective = DpdkConfig {
+            eal_args,
+            mempools: Vec::new(),
+            ports: Vec::new(),
+        };
+        for mempool in self.mempools.iter() {
+            effective.mempools.push(create_mempool(mempool)?);
+        }
+        for port in self.ports.iter() {
+            effective.ports.push(configure_port(port)?);
+        }
+
+        Ok(effective)
+    }
+}
+
+impl std::str::FromStr for DpdkConfig {
+    type Err = ConfigError;
+
+    /// Parse and validate a configuration.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let config: DpdkConfig = toml::from_str(s).map_err(ConfigError::Parse)?;
+        config.validate()?;
+        Ok(config)
+    }
+}
+
+impl fmt::Display for DpdkConfig {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let s = toml::to_string(self).map_err(|_| fmt::Error)?;
+        f.write_str(&s)
+    }
+}
+
+impl DpdkService {
+    /// Bootstrap the dpdk service from the TOML configuration file at `path`,
+    /// see the [`config`](crate::config) module for the format.
+    ///
+    /// Returns the effective configuration, with all the defaults filled in.
+    pub fn from_config<P: AsRef<Path>>(path: P) -> Result<DpdkConfig, ConfigError> {
+        DpdkConfig::load(path)?.bootstrap()
+    }
+}
+
+fn invalid<T>(msg: String) -> Result<T, ConfigError> {
+    Err(ConfigError::Invalid(msg))
+}
+
+fn tx_offload(name: &str) -> Option<DevTxOffload> {
+    let mut offload = DevTxOffload::ALL_DISABLED;
+    match name {
+        "ipv4_cksum" => offload.enable_ipv4_cksum(),
+        "udp_cksum" => offload.enable_udp_cksum(),
+        "tcp_cksum" => offload.enable_tcp_cksum(),
+        #[cfg(feature = "multiseg")]
+        "tcp_tso" => offload.enable_tcp_tso(),
+        #[cfg(feature = "multiseg")]
+        "multi_segs" => offload.enable_multi_segs(),
+        _ => return None,
+    }
+    Some(offload)
+}
+
+fn rx_offload(name: &str) -> Option<DevRxOffload> {
+    let mut offload = DevRxOffload::ALL_DISABLED;
+    match name {
+        "ipv4_cksum" => offload.enable_ipv4_cksum(),
+        "udp_cksum" => offload.enable_udp_cksum(),
+        "tcp_cksum" => offload.enable_tcp_cksum(),
+        "rss_hash" => offload.enable_rss_hash(),
+        #[cfg(feature = "multiseg")]
+        "tcp_lro" => offload.enable_tcp_lro(),
+        #[cfg(feature = "multiseg")]
+        "scatter" => offload.enable_scatter(),
+        _ => return None,
+    }
+    Some(offload)
+}
+
+fn create_mempool(config: &MempoolConfig) -> Result<MempoolConfig, ConfigError> {
+    let mut conf = MempoolConf::new();
+    conf.nb_mbufs = config.nb_mbufs.unwrap_or(conf.nb_mbufs);
+    conf.per_core_caches = config.per_core_caches.unwrap_or(conf.per_core_caches);
+    conf.dataroom = config.dataroom.unwrap_or(conf.dataroom);
+    conf.socket_id = config.socket_id.unwrap_or(conf.socket_id);
+    service().mempool_create(&config.name, &conf)?;
+
+    Ok(MempoolConfig {
+        name: config.name.clone(),
+        nb_mbufs: Some(conf.nb_mbufs),
+        per_core_caches: Some(conf.per_core_caches),
+        dataroom: Some(conf.dataroom),
+        socket_id: Some(conf.socket_id),
+    })
+}
+
+fn configure_port(config: &PortConfig) -> Result<PortConfig, ConfigError> {
+    let port_id = config.port_id;
+    let port_info = service().port_info(port_id)?;
+    let mut port_conf = PortConf::from_port_info(&port_info)?;
+
+    if let Some(mtu) = config.mtu {
+        let (min, max) = (port_info.min_mtu(), port_info.max_mtu());
+        if mtu < u32::from(min) || mtu > u32::from(max) {
+            return invalid(format!(
+                "port {} supports mtu in [{}, {}], not {}",
+                port_id, min, max, mtu
+            ));
+        }
+        port_conf.mtu = mtu;
+    }
+    if let Some(promiscuous) = config.promiscuous {
+        port_conf.enable_promiscuous = promiscuous;
+    }
+
+    if let Some(names) = config.tx_offloads.as_ref() {
+        let capa = port_info.tx_offload_capa();
+        let mut offloads = DevTxOffload::ALL_DISABLED;
+        for name in names {
+            // The names are checked by `validate`.
+            let offload = tx_offload(name).unwrap();
+            if offload.0 & capa.0 != offload.0 {
+                return invalid(format!(
+                    "port {} does not support tx offload {}",
+                    port_id, name
+                ));
+            }
+            offloads.0 |= offload.0;
+        }
+        port_conf.tx_offloads = offloads;
+    }
+    if let Some(names) = config.rx_offloads.as_ref() {
+        let capa = port_info.rx_offload_capa();
+        let mut offloads = DevRxOffload::ALL_DISABLED;
+        for name in names {
+            let offload = rx_offload(name).unwrap();
+            if offload.0 & capa.0 != offload.0 {
+                return invalid(format!(
+                    "port {} does not support rx offload {}",
+                    port_id, name
+                ));
+            }
+            offloads.0 |= offload.0;
+        }
+        port_conf.rx_offloads = offloads;
+    }
+
+    if config.rx_queues.len() > usize::from(port_info.max_rx_queues())
+        || config.tx_queues.len() > usize::from(port_info.max_tx_queues())
+    {
+        return invalid(format!(
+            "port {} supports at most {} rx queues and {} tx queues",
+            port_id,
+            port_info.max_rx_queues(),
+            port_info.max_tx_queues()
+        ));
+    }
+
+    let rx_desc_lim = port_info.rx_desc_lim();
+    let mut rxq_confs = Vec::new();
+    for rxq in config.rx_queues.iter() {
+        let mut rxq_conf = RxQueueConf::new();
+        rxq_conf.mp_name = rxq.mempool.clone();
+        rxq_conf.nb_rx_desc = rxq.nb_desc.unwrap_or(rxq_conf.nb_rx_desc);
+        rxq_conf.socket_id = rxq.socket_id.unwrap_or(port_info.socket_id);
+        check_nb_desc(port_id, "rx", rxq_conf.nb_rx_desc, &rx_desc_lim)?;
+        rxq_confs.push(rxq_conf);
+    }
+
+    let tx_desc_lim = port_info.tx_desc_lim();
+    let mut txq_confs = Vec::new();
+    for txq in config.tx_queues.iter() {
+        let mut txq_conf = TxQueueConf::new();
+        txq_conf.nb_tx_desc = txq.nb_desc.unwrap_or(txq_conf.nb_tx_desc);
+        txq_conf.socket_id = txq.socket_id.unwrap_or(port_info.socket_id);
+        check_nb_desc(port_id, "tx", txq_conf.nb_tx_desc, &tx_desc_lim)?;
+        txq_confs.push(txq_conf);
+    }
+
+    service().port_configure(port_id, &port_conf, &rxq_confs, &txq_confs)?;
+
+    Ok(PortConfig {
+        port_id,
+        mtu: Some(port_conf.mtu),
+        promiscuous: Some(port_conf.enable_promiscuous),
+        tx_offloads: Some(
+            TX_OFFLOADS
+                .iter()
+                .filter(|name| tx_offload(name).unwrap().0 & port_conf.tx_offloads.0 != 0)
+                .map(|name| name.to_string())
+                .collect(),
+        ),
+        rx_offloads: Some(
+            RX_OFFLOADS
+                .iter()
+                .filter(|name| rx_offload(name).unwrap().0 & port_conf.rx_offloads.0 != 0)
+                .map(|name| name.to_string())
+                .collect(),
+        ),
+        rx_queues: rxq_confs
+            .iter()
+            .map(|rxq_conf| RxQueueConfig {
+                mempool: rxq_conf.mp_name.clone(),
+                nb_desc: Some(rxq_conf.nb_rx_desc),
+                socket_id: Some(rxq_conf.socket_id),
+            })
+            .collect(),
+        tx_queues: txq_confs
+            .iter()
+            .map(|txq_conf| TxQueueConfig {
+                nb_desc: Some(txq_conf.nb_tx_desc),
+                socket_id: Some(txq_conf.socket_id),
+            })
+            .collect(),
+    })
+}
+
+fn check_nb_desc(port_id: u16, dir: &str, nb_desc: u16, lim: &DescLim) -> Result<(), ConfigError> {
+    let align = lim.nb_align().max(1);
+    if nb_desc < lim.nb_min() || nb_desc > lim.nb_max() || nb_desc % align != 0 {
+        return invalid(format!(
+            "port {} supports {} descriptors in [{}, {}] aligned to {}, not {}",
+            port_id,
+            dir,
+            lim.nb_min(),
+            lim.nb_max(),
+            align,
+            nb_desc
+        ));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const CONFIG: &str = r#"
+eal_args = ["-l", "0-1"]
+
+[[mempool]]
+name = "mp"
+nb_mbufs = 8192
+
+[[port]]
+port_id = 0
+tx_offloads = ["ipv4_cksum", "udp_cksum"]
+rx_queues = [{ mempool = "mp" }, { mempool = "mp", nb_desc = 1024 }]
+tx_queues = [{}, {}]
+"#;
+
+    #[test]
+    fn parse_and_dump() {
+        let config: DpdkConfig = CONFIG.parse().unwrap();
+        assert_eq!(config.eal_args, ["-l", "0-1"]);
+        assert_eq!(config.mempools[0].name, "mp");
+        assert_eq!(config.mempools[0].nb_mbufs, Some(8192));
+        assert_eq!(config.mempools[0].dataroom, None);
+        let port = &config.ports[0];
+        assert_eq!(port.mtu, None);
+        assert_eq!(port.rx_offloads, None);
+        assert_eq!(port.rx_queues[1].nb_desc, Some(1024));
+        assert_eq!(port.tx_queues.len(), 2);
+
+        let dumped: DpdkConfig = config.to_string().parse().unwrap();
+        assert_eq!(dumped, config);
+    }
+
+    #[test]
+    fn invalid_config() {
+        for (from, to) in [
+            ("name = \"mp\"", "name = \"\""),
+            ("nb_mbufs = 8192", "nb_mbufs = 0"),
+            ("{ mempool = \"mp\" },", "{ mempool = \"pool\" },"),
+            ("\"udp_cksum\"", "\"udp_checksum\""),
+            ("tx_queues = [{}, {}]", "tx_queues = []"),
+            ("port_id = 0", "port_id = 0\nqueues = 1"),
+            ("[[port]]", "[[port]]\nport_id = 0\nrx_queues = [{ mempool = \"mp\" }]\ntx_queues = [{}]\n[[port]]"),
+        ] {
+            let s = CONFIG.replace(from, to);
+            assert!(s.parse::<DpdkConfig>().is_err(), "{}", s);
+        }
+    }
+}
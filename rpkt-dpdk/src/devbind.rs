@@ -0,0 +1,387 @@
+//! List the PCI network devices and bind them to the drivers of DPDK, like
+//! `dpdk-devbind.py`.
+//!
+//! The devices are read from and bound through sysfs, which requires root
+//! privileges. A device is bound to a driver by setting its
+//! `driver_override`, unbinding it from its current driver and asking the
+//! kernel to probe it again, so that the driver does not need to know the
+//! vendor and device IDs of the device.
+//!
+//! # Examples
+//! ```ignore
+//! use rpkt_dpdk::devbind::DevBind;
+//!
+//! let devbind = DevBind::new();
+//! for device in devbind.net_devices()? {
+//!     println!("{}", device);
+//! }
+//! devbind.bind_vfio("0000:3b:00.0")?;
+//! ```
+
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use crate::preflight::DPDK_DRIVERS;
+
+/// The PCI class of the network controllers.
+const NET_CLASS: u32 = 0x02;
+
+/// The errors of listing and binding the devices.
+#[derive(Debug)]
+pub enum BindError {
+    /// A sysfs file can not be read or written.
+    Io(io::Error),
+    /// There is no PCI device of the address.
+    NoDevice(String),
+    /// The driver is not loaded.
+    NoDriver(String),
+    /// The device has a network interface that is up, and may carry the
+    /// traffic of the host.
+    Active { addr: String, interface: String },
+    /// The device is not bound to the driver after the probing.
+    Refused { addr: String, driver: String },
+}
+
+impl fmt::Display for BindError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            BindError::Io(err) => write!(f, "fail to access sysfs: {}", err),
+            BindError::NoDevice(addr) => write!(f, "no PCI device {}", addr),
+            BindError::NoDriver(driver) => {
+                write!(
+                    f,
+                    "driver {} is not loaded, try `modprobe {}`",
+                    driver, driver
+                )
+            }
+            BindError::Active { addr, interface } => write!(
+                f,
+                "device {} has the interface {} up, bring it down first",
+                addr, interface
+            ),
+            BindError::Refused { addr, driver } => {
+                write!(f, "driver {} refuses to bind device {}", driver, addr)
+            }
+        }
+    }
+}
+
+impl std::error::Error for BindError {}
+
+impl From<io::Error> for BindError {
+    fn from(err: io::Error) -> Self {
+        BindError::Io(err)
+    }
+}
+
+/// A PCI device and the driver it is bound to.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PciDevice {
+    /// The address of the device, like `0000:3b:00.0`.
+    pub addr: String,
+    pub vendor_id: u16,
+    pub device_id: u16,
+    /// The PCI class code, the base class in the high byte.
+    pub class: u32,
+    pub driver: Option<String>,
+    pub numa_node: Option<u32>,
+    /// The network interfaces of the device, only when bound to a kernel
+    /// driver.
+    pub interfaces: Vec<String>,
+    /// The interfaces that are up.
+    pub active_interfaces: Vec<String>,
+}
+
+impl PciDevice {
+    /// Returns whether the device is bound to one of the
+    /// [`DPDK_DRIVERS`].
+    pub fn is_dpdk_bound(&self) -> bool {
+        self.driver
+            .as_deref()
+            .is_some_and(|driver| DPDK_DRIVERS.contains(&driver))
+    }
+
+    /// Returns whether the device is a network controller.
+    pub fn is_network(&self) -> bool {
+        self.class >> 16 == NET_CLASS
+    }
+}
+
+impl fmt::Display for PciDevice {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{} [{:04x}:{:04x}] driver={}",
+            self.addr,
+            self.vendor_id,
+            self.device_id,
+            self.driver.as_deref().unwrap_or("none")
+        )?;
+        if let Some(node) = self.numa_node {
+            write!(f, " numa_node={}", node)?;
+        }
+        if !self.interfaces.is_empty() {
+            write!(f, " if={}", self.interfaces.join(","))?;
+        }
+        if !self.active_interfaces.is_empty() {
+            write!(f, " *active*")?;
+        }
+        Ok(())
+    }
+}
+
+/// Lists and binds the PCI devices through sysfs.
+#[derive(Debug, Clone)]
+pub struct DevBind {
+    root: PathBuf,
+}
+
+impl Default for DevBind {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl DevBind {
+    /// Access the sysfs mounted on `/sys`.
+    pub fn new() -> Self {
+        Self::with_root("/")
+    }
+
+    /// Access the sysfs mounted on `root/sys` instead.
+    pub fn with_root<P: AsRef<Path>>(root: P) -> Self {
+        Self {
+            root: root.as_ref().to_path_buf(),
+        }
+    }
+
+    /// Returns the network controllers, sorted by their addresses.
+    pub fn net_devices(&self) -> Result<Vec<PciDevice>, BindError> {
+        let mut devices = Vec::new();
+        for entry in fs::read_dir(self.pci_path("devices"))? {
+            let addr = entry?.file_name().to_string_lossy().into_owned();
+            let device = self.device(&addr)?;
+            if device.is_network() {
+                devices.push(device);
+            }
+        }
+        devices.sort_by(|a, b| a.addr.cmp(&b.addr));
+        Ok(devices)
+    }
+
+    /// Returns the device of `addr`.
+    pub fn device(&self, addr: &str) -> Result<PciDevice, BindError> {
+        let dir = self.device_path(addr)?;
+        let read_hex = |name: &str| -> Result<u32, BindError> {
+            let content = fs::read_to_string(dir.join(name))?;
+            let content = content.trim();
+            u32::from_str_radix(content.trim_start_matches("0x"), 16).map_err(|_| {
+                BindError::Io(io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    format!("invalid {} of device {}: {}", name, addr, content),
+                ))
+            })
+        };
+
+        let mut interfaces = Vec::new();
+        let mut active_interfaces = Vec::new();
+        if let Ok(entries) = fs::read_dir(dir.join("net")) {
+            for entry in entries {
+                let entry = entry?;
+                let name = entry.file_name().to_string_lossy().into_owned();
+                let operstate =
+                    fs::read_to_string(entry.path().join("operstate")).unwrap_or_default();
+                if operstate.trim() == "up" {
+                    active_interfaces.push(name.clone());
+                }
+                interfaces.push(name);
+            }
+        }
+        interfaces.sort();
+        active_interfaces.sort();
+
+        Ok(PciDevice {
+            addr: addr.to_string(),
+            vendor_id: read_hex("vendor")? as u16,
+            device_id: read_hex("device")? as u16,
+            class: read_hex("class")?,
+            driver: self.driver_of(&dir),
+            // The node is -1 on the machines without NUMA.
+            numa_node: fs::read_to_string(dir.join("numa_node"))
+                .ok()
+                .and_then(|node| node.trim().parse().ok()),
+            interfaces,
+            active_interfaces,
+        })
+    }
+
+    /// Bind the device of `addr` to vfio-pci.
+    pub fn bind_vfio(&self, addr: &str) -> Result<(), BindError> {
+        self.bind(addr, "vfio-pci")
+    }
+
+    /// Bind the device of `addr` to `driver`, unbinding it from its current
+    /// driver first.
+    ///
+    /// This function refuses to unbind a device with an interface that is
+    /// up, as it may be the management interface of the host.
+    pub fn bind(&self, addr: &str, driver: &str) -> Result<(), BindError> {
+        let device = self.device(addr)?;
+        if device.driver.as_deref() == Some(driver) {
+            return Ok(());
+        }
+        if !self.pci_path("drivers").join(driver).exists() {
+            return Err(BindError::NoDriver(driver.to_string()));
+        }
+        if let Some(interface) = device.active_interfaces.first() {
+            return Err(BindError::Active {
+                addr: addr.to_string(),
+                interface: interface.clone(),
+            });
+        }
+
+        self.unbind(addr)?;
+        let dir = self.device_path(addr)?;
+        fs::write(dir.join("driver_override"), driver)?;
+        let probed = fs::write(self.pci_path("drivers_probe"), addr);
+        // Clear the override, so that a later probe picks the default driver
+        // again.
+        fs::write(dir.join("driver_override"), "\n")?;
+        probed?;
+
+        if self.driver_of(&dir).as_deref() == Some(driver) {
+            Ok(())
+        } else {
+            Err(BindError::Refused {
+                addr: addr.to_string(),
+                driver: driver.to_string(),
+            })
+        }
+    }
+
+    /// Unbind the device of `addr` from its driver, if any.
+    pub fn unbind(&self, addr: &str) -> Result<(), BindError> {
+        let dir = self.device_path(addr)?;
+        if self.driver_of(&dir).is_some() {
+            fs::write(dir.join("driver/unbind"), addr)?;
+        }
+        Ok(())
+    }
+
+    fn pci_path(&self, name: &str) -> PathBuf {
+        self.root.join("sys/bus/pci").join(name)
+    }
+
+    fn device_path(&self, addr: &str) -> Result<PathBuf, BindError> {
+        let dir = self.pci_path("devices").join(addr);
+        if addr.is_empty() || addr.contains('/') || !dir.exists() {
+            return Err(BindError::NoDevice(addr.to_string()));
+        }
+        Ok(dir)
+    }
+
+    fn driver_of(&self, dir: &Path) -> Option<String> {
+        let link = fs::read_link(dir.join("driver")).ok()?;
+        Some(link.file_name()?.to_string_lossy().into_owned())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::os::unix::fs::symlink;
+
+    // A fake root with the NIC 0000:01:00.0 bound to ixgbe with the interface
+    // eth1, the NIC 0000:02:00.0 bound to vfio-pci, and a NVMe disk.
+    fn fake_root(name: &str) -> PathBuf {
+        let root = std::env::temp_dir().join(format!("rpkt-devbind-{}", name));
+        let _ = fs::remove_dir_all(&root);
+        let drivers = root.join("sys/bus/pci/drivers");
+        for (addr, class, driver) in [
+            ("0000:01:00.0", "0x020000", "ixgbe"),
+            ("0000:02:00.0", "0x020000", "vfio-pci"),
+            ("0000:03:00.0", "0x010802", "nvme"),
+        ] {
+            let dir = root.join("sys/bus/pci/devices").join(addr);
+            fs::create_dir_all(&dir).unwrap();
+            fs::create_dir_all(drivers.join(driver)).unwrap();
+            symlink(drivers.join(driver), dir.join("driver")).unwrap();
+            fs::write(dir.join("vendor"), "0x8086\n").unwrap();
+            fs::write(dir.join("device"), "0x10fb\n").unwrap();
+            fs::write(dir.join("class"), format!("{}\n", class)).unwrap();
+            fs::write(dir.join("numa_node"), "-1\n").unwrap();
+        }
+        let eth1 = root.join("sys/bus/pci/devices/0000:01:00.0/net/eth1");
+        fs::create_dir_all(&eth1).unwrap();
+        fs::write(eth1.join("operstate"), "down\n").unwrap();
+        root
+    }
+
+    #[test]
+    fn list_devices() {
+        let root = fake_root("list");
+        let devbind = DevBind::with_root(&root);
+        let devices = devbind.net_devices().unwrap();
+        assert_eq!(devices.len(), 2);
+
+        assert_eq!(devices[0].addr, "0000:01:00.0");
+        assert_eq!(
+            (devices[0].vendor_id, devices[0].device_id),
+            (0x8086, 0x10fb)
+        );
+        assert_eq!(devices[0].driver.as_deref(), Some("ixgbe"));
+        assert_eq!(devices[0].numa_node, None);
+        assert_eq!(devices[0].interfaces, ["eth1"]);
+        assert!(!devices[0].is_dpdk_bound());
+        assert_eq!(
+            devices[0].to_string(),
+            "0000:01:00.0 [8086:10fb] driver=ixgbe if=eth1"
+        );
+        assert!(devices[1].is_dpdk_bound());
+
+        assert!(matches!(
+            devbind.device("0000:09:00.0"),
+            Err(BindError::NoDevice(_))
+        ));
+        fs::remove_dir_all(root).unwrap();
+    }
+
+    #[test]
+    fn bind_device() {
+        let root = fake_root("bind");
+        let devbind = DevBind::with_root(&root);
+        let addr = "0000:01:00.0";
+
+        // Already bound.
+        devbind.bind_vfio("0000:02:00.0").unwrap();
+        assert!(matches!(
+            devbind.bind(addr, "igb_uio"),
+            Err(BindError::NoDriver(_))
+        ));
+
+        let operstate = root.join("sys/bus/pci/devices/0000:01:00.0/net/eth1/operstate");
+        fs::write(&operstate, "up\n").unwrap();
+        match devbind.bind_vfio(addr) {
+            Err(BindError::Active { interface, .. }) => assert_eq!(interface, "eth1"),
+            res => panic!("{:?}", res),
+        }
+        fs::write(&operstate, "down\n").unwrap();
+
+        // The fake sysfs does not rebind the device, but records the writes.
+        assert!(matches!(
+            devbind.bind_vfio(addr),
+            Err(BindError::Refused { .. })
+        ));
+        let read = |path: &str| fs::read_to_string(root.join(path)).unwrap();
+        assert_eq!(read("sys/bus/pci/drivers/ixgbe/unbind"), addr);
+        assert_eq!(read("sys/bus/pci/drivers_probe"), addr);
+        assert_eq!(
+            read("sys/bus/pci/devices/0000:01:00.0/driver_override"),
+            "\n"
+        );
+        fs::remove_dir_all(root).unwrap();
+    }
+}
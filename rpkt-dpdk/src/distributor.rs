@@ -0,0 +1,278 @@
+use std::sync::mpsc::{self, Receiver, SyncSender, TrySendError};
+
+use arrayvec::ArrayVec;
+
+use crate::offload::DEFAULT_RSS_KEY_40B;
+use crate::{Batch, Mbuf, RxQueue};
+
+/// The number of mbufs received and forwarded to the workers at a time.
+pub const BURST_SIZE: usize = 32;
+
+type Burst = ArrayVec<Mbuf, BURST_SIZE>;
+
+/// Computes the Toeplitz hash of `input` with `key`, as the rss of the NICs.
+///
+/// # Panics
+/// The key must be at least 4 bytes longer than the input.
+pub fn toeplitz_hash(key: &[u8], input: &[u8]) -> u32 {
+    assert!(key.len() >= input.len() + 4);
+    let mut hash = 0;
+    let mut window = u32::from_be_bytes([key[0], key[1], key[2], key[3]]);
+    for (i, byte) in input.iter().enumerate() {
+        let next = key[i + 4];
+        for bit in 0..8 {
+            if byte & (0x80 >> bit) != 0 {
+                hash ^= window;
+            }
+            window = (window << 1) | u32::from((next >> (7 - bit)) & 1);
+        }
+    }
+    hash
+}
+
+// Extract the rss input from an Ethernet frame: the addresses, followed by the ports
+// of unfragmented TCP and UDP packets.
+fn rss_input(frame: &[u8], input: &mut [u8; 36]) -> usize {
+    let mut offset = 12;
+    let mut ethertype = match frame.get(offset..offset + 2) {
+        Some(b) => u16::from_be_bytes([b[0], b[1]]),
+        None => return 0,
+    };
+    // Skip the vlan tags.
+    while ethertype == 0x8100 || ethertype == 0x88a8 {
+        offset += 4;
+        ethertype = match frame.get(offset..offset + 2) {
+            Some(b) => u16::from_be_bytes([b[0], b[1]]),
+            None => return 0,
+        };
+    }
+    let l3 = &frame[offset + 2..];
+
+    let (len, protocol, l4) = match ethertype {
+        0x0800 if l3.len() >= 20 => {
+            let ihl = usize::from(l3[0] & 0x0f) * 4;
+            input[..8].copy_from_slice(&l3[12..20]);
+            // A fragment has its more fragments bit or fragment offset set.
+            let is_frag = u16::from_be_bytes([l3[6], l3[7]]) & 0x3fff != 0;
+            if is_frag || ihl < 20 {
+                return 8;
+            }
+            (8, l3[9], l3.get(ihl..))
+        }
+        0x86dd if l3.len() >= 40 => {
+            input[..32].copy_from_slice(&l3[8..40]);
+            (32, l3[6], l3.get(40..))
+        }
+        _ => return 0,
+    };
+    match (protocol, l4) {
+        (6 | 17, Some(l4)) if l4.len() >= 4 => {
+            input[len..len + 4].copy_from_slice(&l4[..4]);
+            len + 4
+        }
+        _ => len,
+    }
+}
+
+/// Spreads the packets received from a single rx queue over several workers.
+///
+/// Some NICs only expose a single rx queue, so the packets can not be spread by the
+/// NIC rss. The `Distributor` polls the only rx queue on a dispatcher lcore, and
+/// forwards every packet to a worker selected by the rss hash of the packet. It uses
+/// the hash computed by the NIC if it is available, and the Toeplitz hash of the
+/// addresses and ports otherwise.
+///
+/// Each worker receives its packets from a [`DistributorRxQueue`], which has the same
+/// `rx` method as the `RxQueue`. When the ring of a worker is full, the packets are
+/// dropped.
+///
+/// # Examples
+/// ```no_run
+/// use arrayvec::ArrayVec;
+/// use rpkt_dpdk::distributor::Distributor;
+/// use rpkt_dpdk::*;
+///
+/// let rxq = service().rx_queue(0, 0).unwrap();
+/// let (mut distributor, worker_rxqs) = Distributor::new(rxq, 4, 1024);
+///
+/// for mut worker_rxq in worker_rxqs {
+///     std::thread::spawn(move || {
+///         let mut batch = ArrayVec::<_, 32>::new();
+///         loop {
+///             worker_rxq.rx(&mut batch);
+///             batch.clear();
+///         }
+///     });
+/// }
+///
+/// loop {
+///     distributor.poll();
+/// }
+/// ```
+pub struct Distributor {
+    rxq: RxQueue,
+    senders: Vec<SyncSender<Burst>>,
+    bursts: Vec<Burst>,
+    rss_key: [u8; 40],
+    dropped: u64,
+}
+
+impl Distributor {
+    /// Create a distributor of the packets of `rxq` over `nb_workers` workers.
+    ///
+    /// Each worker buffers at most `ring_size` packets, rounded up to a multiple of
+    /// `BURST_SIZE`.
+    ///
+    /// # Panics
+    /// There must be at least one worker.
+    pub fn new(
+        rxq: RxQueue,
+        nb_workers: usize,
+        ring_size: usize,
+    ) -> (Self, Vec<DistributorRxQueue>) {
+        assert!(nb_workers > 0);
+        let nb_bursts = (ring_size + BURST_SIZE - 1) / BURST_SIZE;
+
+        let mut senders = Vec::with_capacity(nb_workers);
+        let mut worker_rxqs = Vec::with_capacity(nb_workers);
+        for _ in 0..nb_workers {
+            let (sender, receiver) = mpsc::sync_channel(nb_bursts);
+            senders.push(sender);
+            worker_rxqs.push(DistributorRxQueue {
+                receiver,
+                pending: Burst::new().into_iter(),
+            });
+        }
+
+        let distributor = Self {
+            rxq,
+            senders,
+            bursts: (0..nb_workers).map(|_| Burst::new()).collect(),
+            rss_key: DEFAULT_RSS_KEY_40B,
+            dropped: 0,
+        };
+        (distributor, worker_rxqs)
+    }
+
+    /// Set the key of the software Toeplitz hash. The default key is symmetric, which
+    /// keeps both directions of a flow on the same worker.
+    pub fn set_rss_key(&mut self, key: &[u8; 40]) {
+        self.rss_key = *key;
+    }
+
+    /// Receive a burst from the rx queue and forward it to the workers, returns the
+    /// number of received packets.
+    pub fn poll(&mut self) -> usize {
+        let mut batch = Burst::new();
+        let nb_rx = self.rxq.rx(&mut batch);
+
+        let nb_workers = self.senders.len();
+        for mbuf in batch.drain(..) {
+            let hash = self.hash(&mbuf);
+            self.bursts[hash as usize % nb_workers].push(mbuf);
+        }
+
+        for (sender, burst) in self.senders.iter().zip(self.bursts.iter_mut()) {
+            if burst.is_empty() {
+                continue;
+            }
+            let burst = std::mem::take(burst);
+            match sender.try_send(burst) {
+                Ok(()) => {}
+                Err(TrySendError::Full(burst)) | Err(TrySendError::Disconnected(burst)) => {
+                    // The mbufs are freed when the burst is dropped.
+                    self.dropped += burst.len() as u64;
+                }
+            }
+        }
+
+        nb_rx
+    }
+
+    /// Returns the number of packets dropped because the ring of a worker is full.
+    pub fn dropped(&self) -> u64 {
+        self.dropped
+    }
+
+    fn hash(&self, mbuf: &Mbuf) -> u32 {
+        if mbuf.rx_offload().rss_hash() {
+            return mbuf.rss();
+        }
+        let mut input = [0; 36];
+        let len = rss_input(mbuf.data(), &mut input);
+        toeplitz_hash(&self.rss_key, &input[..len])
+    }
+}
+
+/// The rx queue of a worker of a [`Distributor`].
+pub struct DistributorRxQueue {
+    receiver: Receiver<Burst>,
+    pending: arrayvec::IntoIter<Mbuf, BURST_SIZE>,
+}
+
+impl DistributorRxQueue {
+    /// Receive the packets forwarded by the distributor into `batch`, returns the
+    /// number of received packets.
+    #[inline]
+    pub fn rx<B: Batch>(&mut self, batch: &mut B) -> usize {
+        let len = batch.len();
+        while !batch.is_full() {
+            match self.pending.next() {
+                Some(mbuf) => batch.push(mbuf),
+                None => match self.receiver.try_recv() {
+                    Ok(burst) => self.pending = burst.into_iter(),
+                    Err(_) => break,
+                },
+            }
+        }
+        batch.len() - len
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn toeplitz_hash_vectors() {
+        // The verification suite of the Microsoft rss specification.
+        let key = [
+            0x6d, 0x5a, 0x56, 0xda, 0x25, 0x5b, 0x0e, 0xc2, 0x41, 0x67, 0x25, 0x3d, 0x43, 0xa3,
+            0x8f, 0xb0, 0xd0, 0xca, 0x2b, 0xcb, 0xae, 0x7b, 0x30, 0xb4, 0x77, 0xcb, 0x2d, 0xa3,
+            0x80, 0x30, 0xf2, 0x0c, 0x6a, 0x42, 0xb7, 0x3b, 0xbe, 0xac, 0x01, 0xfa,
+        ];
+        let input = [66, 9, 149, 187, 161, 142, 100, 80, 0x0a, 0xea, 0x06, 0xe6];
+        assert_eq!(toeplitz_hash(&key, &input[..8]), 0x323e8fc2);
+        assert_eq!(toeplitz_hash(&key, &input), 0x51ccc178);
+
+        // The default key is symmetric.
+        let reversed = [161, 142, 100, 80, 66, 9, 149, 187, 0x06, 0xe6, 0x0a, 0xea];
+        assert_eq!(
+            toeplitz_hash(&DEFAULT_RSS_KEY_40B, &input),
+            toeplitz_hash(&DEFAULT_RSS_KEY_40B, &reversed)
+        );
+    }
+
+    #[test]
+    fn rss_input_of_frames() {
+        let mut frame = [0; 14 + 20 + 8];
+        frame[12..14].copy_from_slice(&[0x08, 0x00]);
+        frame[14] = 0x45;
+        frame[14 + 9] = 17;
+        frame[14 + 12..14 + 20].copy_from_slice(&[10, 0, 0, 1, 10, 0, 0, 2]);
+        frame[34..38].copy_from_slice(&[0x30, 0x39, 0x00, 0x35]);
+
+        let mut input = [0; 36];
+        assert_eq!(rss_input(&frame, &mut input), 12);
+        assert_eq!(
+            input[..12],
+            [10, 0, 0, 1, 10, 0, 0, 2, 0x30, 0x39, 0x00, 0x35]
+        );
+
+        // A fragment is hashed by its addresses.
+        frame[14 + 6] = 0x20;
+        assert_eq!(rss_input(&frame, &mut input), 8);
+
+        assert_eq!(rss_input(&frame[..13], &mut input), 0);
+    }
+}
@@ -13,7 +13,7 @@ thread_local! {
     pub(crate) static LCORE: RefCell<Option<Lcore>> = RefCell::new(None);
 }
 
-#[derive(Copy, Clone)]
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
 pub struct Lcore {
     pub lcore_id: u32,
     pub cpu_id: u32,
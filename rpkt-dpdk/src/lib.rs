@@ -11,6 +11,9 @@ pub use lcore::Lcore;
 mod service;
 pub use service::{service, try_service, DpdkOption, DpdkService};
 
+mod batch;
+pub use batch::{Batch, SliceBatch};
+
 mod mempool;
 pub use mempool::{Mempool, MempoolConf};
 
@@ -30,9 +33,35 @@ pub use pbuf::Pbuf;
 
 mod port;
 pub use port::{
-    PortConf, PortInfo, PortStats, RxQueue, RxQueueConf, StatsQueryContext, TxQueue, TxQueueConf,
+    DescCounts, PortConf, PortInfo, PortStats, QueueInfo, RingThresh, RxDescStatus, RxQueue,
+    RxQueueConf, StatsQueryContext, TxDescStatus, TxQueue, TxQueueConf, XStats,
 };
 
 pub mod offload;
 
+pub mod distributor;
+
+pub mod planner;
+
+pub mod preflight;
+
+pub mod soft_offload;
+
+#[cfg(feature = "async")]
+pub mod async_queue;
+
+#[cfg(feature = "mirror")]
+pub mod mirror;
+
+#[cfg(feature = "config")]
+pub mod config;
+
+#[cfg(feature = "devbind")]
+pub mod devbind;
+
+#[cfg(feature = "timestamp")]
+mod timestamp;
+#[cfg(feature = "timestamp")]
+pub use timestamp::{MbufTimestamp, ResidenceStats};
+
 pub mod utils;
\ No newline at end of file
@@ -2,7 +2,7 @@ use std::ptr::NonNull;
 
 use rpkt_dpdk_sys as ffi;
 
-use crate::offload::{MbufRxOffload, MbufTxOffload};
+use crate::offload::{MbufPacketType, MbufRxOffload, MbufTxOffload};
 
 #[derive(Debug)]
 pub struct Mbuf {
@@ -114,6 +114,52 @@ impl Mbuf {
         unsafe { self.ptr.as_ref().__bindgen_anon_2.hash.rss }
     }
 
+    /// Returns the raw `ol_flags` of the mbuf.
+    #[inline]
+    pub fn ol_flags(&self) -> u64 {
+        unsafe { self.ptr.as_ref().ol_flags }
+    }
+
+    /// Returns the packet type recognized by the NIC.
+    #[inline]
+    pub fn packet_type(&self) -> MbufPacketType {
+        MbufPacketType(unsafe { self.ptr.as_ref().__bindgen_anon_1.packet_type })
+    }
+
+    /// Returns the rss hash computed by the NIC, if it is reported.
+    #[inline]
+    pub fn rss_hash(&self) -> Option<u32> {
+        if self.rx_offload().rss_hash() {
+            Some(self.rss())
+        } else {
+            None
+        }
+    }
+
+    /// Returns the TCI of the vlan tag, if it is reported by the NIC.
+    ///
+    /// If `rx_offload().vlan_stripped()` is true, the tag is removed from the packet
+    /// data. For a QinQ packet, this is the inner tag.
+    #[inline]
+    pub fn vlan_tci(&self) -> Option<u16> {
+        if self.rx_offload().vlan() {
+            Some(unsafe { self.ptr.as_ref().vlan_tci })
+        } else {
+            None
+        }
+    }
+
+    /// Returns the TCI of the outer vlan tag of a QinQ packet, if it is reported by
+    /// the NIC.
+    #[inline]
+    pub fn vlan_tci_outer(&self) -> Option<u16> {
+        if self.rx_offload().qinq() {
+            Some(unsafe { self.ptr.as_ref().vlan_tci_outer })
+        } else {
+            None
+        }
+    }
+
     #[inline]
     pub fn set_tx_offload(&mut self, tx_offload: MbufTxOffload) {
         unsafe {
@@ -143,13 +189,55 @@ impl Mbuf {
         }
     }
 
-    // modified to pub for netbricks_port
+    #[inline]
+    pub fn l2_len(&self) -> u64 {
+        unsafe { self.ptr.as_ref().__bindgen_anon_3.__bindgen_anon_1.l2_len() }
+    }
+
+    #[inline]
+    pub fn l3_len(&self) -> u64 {
+        unsafe { self.ptr.as_ref().__bindgen_anon_3.__bindgen_anon_1.l3_len() }
+    }
+
+    /// Take the ownership of a raw mbuf, e.g. an mbuf received by C code.
+    ///
+    /// # Safety
+    /// `ptr` must be a non-null pointer to a valid mbuf allocated from a pktmbuf pool,
+    /// and no one else may access or free the mbuf afterwards, as it is freed when the
+    /// returned `Mbuf` is dropped. The mbuf must have a single segment.
     #[inline]
     pub unsafe fn from_raw(ptr: *mut ffi::rte_mbuf) -> Self {
         Self {
             ptr: NonNull::new_unchecked(ptr),
         }
     }
+
+    /// Release the ownership of the mbuf, e.g. to pass it to C code.
+    ///
+    /// The caller becomes responsible for freeing the mbuf, either with
+    /// `rte_pktmbuf_free`, or by turning it back with [`Mbuf::from_raw`].
+    #[inline]
+    pub fn into_raw(self) -> *mut ffi::rte_mbuf {
+        let ptr = self.ptr;
+        std::mem::forget(self);
+        ptr.as_ptr()
+    }
+
+    /// Leak the mbuf, and return its data with the `'static` lifetime.
+    ///
+    /// The mbuf is never freed, so the mempool can not be freed either. This is meant
+    /// for buffers that live as long as the program, e.g. packet templates that are
+    /// shared with C code.
+    #[inline]
+    pub fn leak_slice(self) -> &'static mut [u8] {
+        let mbuf = unsafe { &*self.into_raw() };
+        unsafe { std::slice::from_raw_parts_mut(data_addr(mbuf), usize::from(mbuf.data_len)) }
+    }
+
+    #[inline]
+    pub(crate) fn as_ptr(&self) -> *const ffi::rte_mbuf {
+        self.ptr.as_ptr()
+    }
 }
 
 impl Drop for Mbuf {
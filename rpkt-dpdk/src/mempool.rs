@@ -4,11 +4,10 @@ use std::os::raw::c_char;
 use std::ptr::NonNull;
 use std::sync::Arc;
 
-use arrayvec::ArrayVec;
 use rpkt_dpdk_sys as ffi;
 
 use crate::error::*;
-use crate::Mbuf;
+use crate::{Batch, Mbuf, PortConf};
 
 #[derive(Clone, Copy, Debug)]
 pub struct MempoolConf {
@@ -39,6 +38,16 @@ impl MempoolConf {
         self.dataroom = val;
     }
 
+    /// Set the dataroom so that a frame of `mtu` fits in a single mbuf.
+    ///
+    /// Returns an error if the frame exceeds the maximum dataroom, in which case the
+    /// frame has to be scattered over multiple mbufs.
+    pub fn set_dataroom_for_mtu(&mut self, mtu: u32) -> Result<()> {
+        let err = Error::service_err("frame does not fit in a single mbuf");
+        self.dataroom = PortConf::dataroom_for_mtu(mtu).ok_or(err)?;
+        Ok(())
+    }
+
     pub fn set_socket_id(&mut self, val: u32) {
         self.socket_id = val;
     }
@@ -59,6 +68,7 @@ impl Default for MempoolConf {
 pub struct Mempool {
     ptr: NonNull<ffi::rte_mempool>,
     counter: Arc<()>,
+    dataroom: u16,
 }
 
 unsafe impl Send for Mempool {}
@@ -78,24 +88,26 @@ impl Mempool {
     }
 
     #[inline]
-    pub fn fill_batch<const N: usize>(&self, batch: &mut ArrayVec<Mbuf, N>) {
-        assert!(N <= usize::from(u16::MAX));
+    pub fn fill_batch<B: Batch>(&self, batch: &mut B) {
         let batch_len = batch.len();
+        let capacity = batch.capacity();
         unsafe {
             let mbufs = std::mem::transmute::<*mut Mbuf, *mut *mut ffi::rte_mbuf>(
                 batch.as_mut_ptr().add(batch_len),
             );
-            let alloc_nb =
-                ffi::rte_pktmbuf_alloc_bulk_(self.ptr.as_ptr(), mbufs, (N - batch_len) as u32);
+            let alloc_nb = ffi::rte_pktmbuf_alloc_bulk_(
+                self.ptr.as_ptr(),
+                mbufs,
+                (capacity - batch_len) as u32,
+            );
             if alloc_nb == 0 {
-                batch.set_len(N);
+                batch.set_len(capacity);
             }
         }
     }
 
     #[inline]
-    pub fn free_batch<const N: usize>(batch: &mut ArrayVec<Mbuf, N>) {
-        assert!(N <= usize::from(u16::MAX));
+    pub fn free_batch<B: Batch>(batch: &mut B) {
         let batch_len = batch.len();
         if batch_len == 0 {
             return;
@@ -108,6 +120,13 @@ impl Mempool {
         }
     }
 
+    /// Returns the number of bytes that an mbuf of this mempool can hold after the
+    /// headroom, i.e. the `dataroom` of the `MempoolConf`.
+    #[inline]
+    pub fn dataroom(&self) -> u16 {
+        self.dataroom
+    }
+
     #[inline]
     pub fn nb_mbufs(&self) -> u32 {
         unsafe { ffi::rte_mempool_avail_count(self.as_ptr()) }
@@ -118,6 +137,25 @@ impl Mempool {
         self.ptr.as_ptr()
     }
 
+    /// Wrap a mempool that is created and owned by C code.
+    ///
+    /// The returned `Mempool` does not own the mempool: it is not registered in the
+    /// dpdk service, and it never frees the mempool. The dataroom is derived from the
+    /// data room size of the pktmbuf pool.
+    ///
+    /// # Safety
+    /// `ptr` must be a non-null pointer to a pktmbuf pool, e.g. one created with
+    /// `rte_pktmbuf_pool_create`, and the pool must not be freed while the returned
+    /// `Mempool`, any of its clones, or any `Mbuf` allocated from it is alive.
+    pub unsafe fn from_raw(ptr: *mut ffi::rte_mempool) -> Self {
+        let data_room_size = ffi::rte_pktmbuf_data_room_size_(ptr);
+        Self {
+            ptr: NonNull::new_unchecked(ptr),
+            counter: Arc::new(()),
+            dataroom: data_room_size.saturating_sub(Self::MBUF_HEADROOM),
+        }
+    }
+
     pub(crate) fn try_create(mpool_name: String, conf: &MempoolConf) -> Result<Self> {
         let err = Error::service_err("invalid mempool config");
         let data_room_size = conf.dataroom.checked_add(Self::MBUF_HEADROOM).ok_or(err)?;
@@ -144,6 +182,7 @@ impl Mempool {
         Ok(Self {
             ptr,
             counter: Arc::new(()),
+            dataroom: conf.dataroom,
         })
     }
 
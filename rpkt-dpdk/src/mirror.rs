@@ -0,0 +1,281 @@
+use std::io::{self, Write};
+use std::sync::atomic::{AtomicU32, AtomicU64, Ordering};
+use std::sync::mpsc::{self, Receiver, SyncSender, TrySendError};
+use std::sync::Arc;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use rpkt::pcap::PcapWriter;
+
+use crate::Mbuf;
+
+/// A packet copied by a [`MirrorTap`].
+#[derive(Debug, Clone)]
+pub struct MirroredPacket {
+    /// The time at which the packet is copied, in microseconds since the Unix epoch.
+    pub ts_micros: u64,
+    /// The length of the packet before truncation.
+    pub orig_len: u32,
+    /// The first bytes of the packet.
+    pub data: Vec<u8>,
+}
+
+struct Shared {
+    rate: AtomicU32,
+    snaplen: AtomicU32,
+    sampled: AtomicU64,
+    dropped: AtomicU64,
+}
+
+/// Mirror a sample of the packets of the rx and tx paths.
+///
+/// A [`MirrorTap`] is created for each lcore that processes packets. It copies 1 in N
+/// packets of a batch, truncated to the snap length, into a bounded channel. The copies
+/// are consumed on another thread through the `Mirror`, either one by one with
+/// [`Mirror::try_recv`], or into a pcap file with [`Mirror::write_pcap`].
+///
+/// The fast path is never blocked: the copy is dropped if the channel is full. The
+/// sampling rate and the snap length are changed at runtime through a [`MirrorControl`],
+/// and the mirror is disabled until a rate is set.
+///
+/// # Examples
+/// ```no_run
+/// use arrayvec::ArrayVec;
+/// use rpkt::pcap::PcapWriter;
+/// use rpkt_dpdk::mirror::Mirror;
+/// use rpkt_dpdk::*;
+///
+/// let mirror = Mirror::new(1024);
+/// mirror.control().set_rate(100);
+///
+/// let mut tap = mirror.tap();
+/// let mut rxq = service().rx_queue(0, 0).unwrap();
+/// let mut batch = ArrayVec::<_, 32>::new();
+/// rxq.rx(&mut batch);
+/// tap.mirror(&batch);
+///
+/// let mut pcap = PcapWriter::new(std::fs::File::create("mirror.pcap").unwrap()).unwrap();
+/// mirror.write_pcap(&mut pcap).unwrap();
+/// ```
+pub struct Mirror {
+    shared: Arc<Shared>,
+    sender: SyncSender<MirroredPacket>,
+    receiver: Receiver<MirroredPacket>,
+}
+
+impl Mirror {
+    /// The default snap length, enough for the headers of most packets.
+    pub const DEFAULT_SNAPLEN: u32 = 128;
+
+    /// Create a disabled mirror, which buffers at most `capacity` copied packets.
+    pub fn new(capacity: usize) -> Self {
+        let (sender, receiver) = mpsc::sync_channel(capacity);
+        Self {
+            shared: Arc::new(Shared {
+                rate: AtomicU32::new(0),
+                snaplen: AtomicU32::new(Self::DEFAULT_SNAPLEN),
+                sampled: AtomicU64::new(0),
+                dropped: AtomicU64::new(0),
+            }),
+            sender,
+            receiver,
+        }
+    }
+
+    /// Returns a handle to configure the mirror at runtime.
+    pub fn control(&self) -> MirrorControl {
+        MirrorControl {
+            shared: self.shared.clone(),
+        }
+    }
+
+    /// Create a new tap, which is moved to the lcore that processes the packets.
+    pub fn tap(&self) -> MirrorTap {
+        MirrorTap {
+            shared: self.shared.clone(),
+            sender: self.sender.clone(),
+            countdown: 0,
+        }
+    }
+
+    /// Receive a copied packet without blocking.
+    pub fn try_recv(&self) -> Option<MirroredPacket> {
+        self.receiver.try_recv().ok()
+    }
+
+    /// Write all the buffered packets into `writer`, and return the number of packets
+    /// written.
+    pub fn write_pcap<W: Write>(&self, writer: &mut PcapWriter<W>) -> io::Result<usize> {
+        let mut count = 0;
+        while let Some(packet) = self.try_recv() {
+            writer.write_truncated(packet.ts_micros, &packet.data, packet.orig_len)?;
+            count += 1;
+        }
+        Ok(count)
+    }
+}
+
+/// Configures a [`Mirror`] at runtime, from any thread.
+#[derive(Clone)]
+pub struct MirrorControl {
+    shared: Arc<Shared>,
+}
+
+impl MirrorControl {
+    /// Mirror 1 in `rate` packets, 0 disables the mirror.
+    pub fn set_rate(&self, rate: u32) {
+        self.shared.rate.store(rate, Ordering::Relaxed);
+    }
+
+    pub fn rate(&self) -> u32 {
+        self.shared.rate.load(Ordering::Relaxed)
+    }
+
+    /// Truncate the copied packets to `snaplen` bytes.
+    pub fn set_snaplen(&self, snaplen: u32) {
+        self.shared.snaplen.store(snaplen, Ordering::Relaxed);
+    }
+
+    pub fn snaplen(&self) -> u32 {
+        self.shared.snaplen.load(Ordering::Relaxed)
+    }
+
+    /// Returns the number of packets that are copied into the channel.
+    pub fn sampled(&self) -> u64 {
+        self.shared.sampled.load(Ordering::Relaxed)
+    }
+
+    /// Returns the number of copies that are dropped because the channel is full.
+    pub fn dropped(&self) -> u64 {
+        self.shared.dropped.load(Ordering::Relaxed)
+    }
+}
+
+/// Copies a sample of the packets that pass through an lcore into a [`Mirror`].
+pub struct MirrorTap {
+    shared: Arc<Shared>,
+    sender: SyncSender<MirroredPacket>,
+    // The number of packets to skip before the next copy.
+    countdown: u32,
+}
+
+impl MirrorTap {
+    /// Copy the sampled packets of `batch`.
+    ///
+    /// It is called on the received batch after `RxQueue::rx`, or on the batch to send
+    /// before `TxQueue::tx`. The packets themselves are left untouched.
+    #[inline]
+    pub fn mirror(&mut self, batch: &[Mbuf]) {
+        let rate = self.shared.rate.load(Ordering::Relaxed);
+        if rate == 0 {
+            return;
+        }
+        if self.countdown as usize >= batch.len() {
+            self.countdown -= batch.len() as u32;
+            return;
+        }
+        self.mirror_slow(batch, rate);
+    }
+
+    #[cold]
+    fn mirror_slow(&mut self, batch: &[Mbuf], rate: u32) {
+        let snaplen = self.shared.snaplen.load(Ordering::Relaxed) as usize;
+        let ts_micros = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |d| d.as_micros() as u64);
+
+        let mut idx = self.countdown as usize;
+        while idx < batch.len() {
+            let mbuf = &batch[idx];
+            let packet = MirroredPacket {
+                ts_micros,
+                orig_len: mbuf.len() as u32,
+                data: copy_prefix(mbuf, snaplen),
+            };
+            match self.sender.try_send(packet) {
+                Ok(()) => self.shared.sampled.fetch_add(1, Ordering::Relaxed),
+                Err(TrySendError::Full(_)) | Err(TrySendError::Disconnected(_)) => {
+                    self.shared.dropped.fetch_add(1, Ordering::Relaxed)
+                }
+            };
+            idx += rate as usize;
+        }
+        self.countdown = (idx - batch.len()) as u32;
+    }
+}
+
+#[cfg(not(feature = "multiseg"))]
+fn copy_prefix(mbuf: &Mbuf, snaplen: usize) -> Vec<u8> {
+    let data = mbuf.data();
+    data[..data.len().min(snaplen)].to_vec()
+}
+
+#[cfg(feature = "multiseg")]
+fn copy_prefix(mbuf: &Mbuf, snaplen: usize) -> Vec<u8> {
+    let mut data = Vec::with_capacity(mbuf.len().min(snaplen));
+    for seg in mbuf.seg_iter() {
+        let remaining = snaplen - data.len();
+        if remaining == 0 {
+            break;
+        }
+        data.extend_from_slice(&seg[..seg.len().min(remaining)]);
+    }
+    data
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::*;
+
+    #[test]
+    fn mirror_sampling() {
+        DpdkOption::new().init().unwrap();
+
+        {
+            let mut config = MempoolConf::default();
+            config.nb_mbufs = 128;
+            let mp = service().mempool_create("mirror", &config).unwrap();
+
+            let mut batch = Vec::new();
+            for i in 0..10u8 {
+                let mut mbuf = mp.try_alloc().unwrap();
+                mbuf.extend_from_slice(&[i; 200]);
+                batch.push(mbuf);
+            }
+
+            let mirror = Mirror::new(4);
+            let control = mirror.control();
+            let mut tap = mirror.tap();
+
+            // Disabled by default.
+            tap.mirror(&batch);
+            assert!(mirror.try_recv().is_none());
+
+            control.set_rate(3);
+            control.set_snaplen(64);
+            tap.mirror(&batch);
+            let copies: Vec<_> = std::iter::from_fn(|| mirror.try_recv()).collect();
+            assert_eq!(copies.len(), 4);
+            for (copy, i) in copies.iter().zip([0, 3, 6, 9]) {
+                assert_eq!(copy.orig_len, 200);
+                assert_eq!(copy.data, [i; 64]);
+            }
+
+            // The sampling continues across the batches.
+            tap.mirror(&batch[..4]);
+            assert_eq!(mirror.try_recv().unwrap().data[0], 2);
+
+            // Copies are dropped when the channel is full.
+            control.set_rate(1);
+            tap.mirror(&batch);
+            assert_eq!(control.sampled(), 9);
+            assert_eq!(control.dropped(), 5);
+
+            let mut pcap = PcapWriter::new(Vec::new()).unwrap();
+            assert_eq!(mirror.write_pcap(&mut pcap).unwrap(), 4);
+            assert_eq!(pcap.into_inner().len(), 24 + 4 * (16 + 64));
+        }
+
+        service().mempool_free("mirror").unwrap();
+    }
+}
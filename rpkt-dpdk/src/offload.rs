@@ -71,6 +71,80 @@ dpdk_offload_conf!(
     }
 );
 
+// The link speed capabilities are extracted from dpdk/lib/ethdev/rte_ethdev.h
+
+dpdk_offload_conf!(
+    /// The link speeds supported by a port. The speeds without the `_hd` suffix are
+    /// full duplex.
+    pub struct LinkSpeedCapa(u32) {
+        /// #define RTE_ETH_LINK_SPEED_FIXED    RTE_BIT32(0)
+        /// Autonegotiation is disabled.
+        fixed,        _do_not_use_1,  1 << 0,
+        speed_10m_hd, _do_not_use_2,  1 << 1,
+        speed_10m,    _do_not_use_3,  1 << 2,
+        speed_100m_hd, _do_not_use_4, 1 << 3,
+        speed_100m,   _do_not_use_5,  1 << 4,
+        speed_1g,     _do_not_use_6,  1 << 5,
+        speed_2_5g,   _do_not_use_7,  1 << 6,
+        speed_5g,     _do_not_use_8,  1 << 7,
+        speed_10g,    _do_not_use_9,  1 << 8,
+        speed_20g,    _do_not_use_10, 1 << 9,
+        speed_25g,    _do_not_use_11, 1 << 10,
+        speed_40g,    _do_not_use_12, 1 << 11,
+        speed_50g,    _do_not_use_13, 1 << 12,
+        speed_56g,    _do_not_use_14, 1 << 13,
+        speed_100g,   _do_not_use_15, 1 << 14,
+        speed_200g,   _do_not_use_16, 1 << 15,
+        speed_400g,   _do_not_use_17, 1 << 16,
+    }
+);
+
+impl LinkSpeedCapa {
+    const NAMES: [(u32, &'static str); 16] = [
+        (1 << 1, "10M-HD"),
+        (1 << 2, "10M"),
+        (1 << 3, "100M-HD"),
+        (1 << 4, "100M"),
+        (1 << 5, "1G"),
+        (1 << 6, "2.5G"),
+        (1 << 7, "5G"),
+        (1 << 8, "10G"),
+        (1 << 9, "20G"),
+        (1 << 10, "25G"),
+        (1 << 11, "40G"),
+        (1 << 12, "50G"),
+        (1 << 13, "56G"),
+        (1 << 14, "100G"),
+        (1 << 15, "200G"),
+        (1 << 16, "400G"),
+    ];
+
+    /// Returns whether a half duplex link is supported.
+    pub fn half_duplex(&self) -> bool {
+        self.speed_10m_hd() || self.speed_100m_hd()
+    }
+}
+
+impl std::fmt::Display for LinkSpeedCapa {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        let mut names = Self::NAMES
+            .iter()
+            .filter(|(bit, _)| self.0 & bit != 0)
+            .map(|(_, name)| *name);
+        match names.next() {
+            Some(name) => write!(f, "{}", name)?,
+            None => write!(f, "unknown")?,
+        }
+        for name in names {
+            write!(f, " {}", name)?;
+        }
+        if self.fixed() {
+            write!(f, " (fixed)")?;
+        }
+        Ok(())
+    }
+}
+
 // The offload bit fields for the mbuf are extracted from dpdk/lib/mbuf/rte_mbuf_core.h
 
 dpdk_offload_conf!(
@@ -103,6 +177,29 @@ dpdk_offload_conf!(
 
         /// #define RTE_MBUF_F_RX_L4_CKSUM_GOOD    (1ULL << 8)
         l4_cksum_good, _do_not_use_5, 1 << 8,
+
+        /// #define RTE_MBUF_F_RX_VLAN          (1ULL << 0)
+        /// The `vlan_tci` field of the mbuf is valid.
+        vlan,          _do_not_use_6, 1 << 0,
+
+        /// #define RTE_MBUF_F_RX_FDIR          (1ULL << 2)
+        fdir,          _do_not_use_7, 1 << 2,
+
+        /// #define RTE_MBUF_F_RX_OUTER_IP_CKSUM_BAD (1ULL << 5)
+        outer_ip_cksum_bad, _do_not_use_8, 1 << 5,
+
+        /// #define RTE_MBUF_F_RX_VLAN_STRIPPED (1ULL << 6)
+        vlan_stripped, _do_not_use_9, 1 << 6,
+
+        /// #define RTE_MBUF_F_RX_QINQ_STRIPPED (1ULL << 15)
+        qinq_stripped, _do_not_use_10, 1 << 15,
+
+        /// #define RTE_MBUF_F_RX_LRO           (1ULL << 16)
+        lro,           _do_not_use_11, 1 << 16,
+
+        /// #define RTE_MBUF_F_RX_QINQ          (1ULL << 20)
+        /// The `vlan_tci` and `vlan_tci_outer` fields of the mbuf are valid.
+        qinq,          _do_not_use_12, 1 << 20,
     }
 );
 
@@ -182,3 +279,220 @@ dpdk_offload_conf!(
         
     }
 );
+
+// The packet types are extracted from dpdk/lib/mbuf/rte_mbuf_ptype.h
+
+/// The L2 type of a packet recognized by the NIC.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum PtypeL2 {
+    Unknown,
+    Ether,
+    EtherTimesync,
+    EtherArp,
+    EtherLldp,
+    EtherNsh,
+    EtherVlan,
+    EtherQinq,
+    EtherPppoe,
+    EtherFcoe,
+    EtherMpls,
+    Other(u32),
+}
+
+/// The L3 type of a packet recognized by the NIC.
+///
+/// `Ipv4Ext` and `Ipv6Ext` mean that the header has options or extension headers,
+/// while the `ExtUnknown` variants mean that the NIC does not tell.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum PtypeL3 {
+    Unknown,
+    Ipv4,
+    Ipv4Ext,
+    Ipv4ExtUnknown,
+    Ipv6,
+    Ipv6Ext,
+    Ipv6ExtUnknown,
+    Other(u32),
+}
+
+impl PtypeL3 {
+    #[inline]
+    pub fn is_ipv4(&self) -> bool {
+        matches!(self, Self::Ipv4 | Self::Ipv4Ext | Self::Ipv4ExtUnknown)
+    }
+
+    #[inline]
+    pub fn is_ipv6(&self) -> bool {
+        matches!(self, Self::Ipv6 | Self::Ipv6Ext | Self::Ipv6ExtUnknown)
+    }
+}
+
+/// The L4 type of a packet recognized by the NIC.
+///
+/// `Frag` is a fragment, `NonFrag` is an unfragmented packet of another protocol.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum PtypeL4 {
+    Unknown,
+    Tcp,
+    Udp,
+    Frag,
+    Sctp,
+    Icmp,
+    NonFrag,
+    Igmp,
+    Other(u32),
+}
+
+/// The tunnel type of a packet recognized by the NIC.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum PtypeTunnel {
+    None,
+    Ip,
+    Gre,
+    Vxlan,
+    Nvgre,
+    Geneve,
+    Grenat,
+    Gtpc,
+    Gtpu,
+    Esp,
+    L2tp,
+    VxlanGpe,
+    MplsInGre,
+    MplsInUdp,
+    Other(u32),
+}
+
+/// The packet type of a received mbuf, as recognized by the NIC.
+///
+/// The packet type is only reported by the drivers that support it, the types
+/// that are not recognized are `Unknown`.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub struct MbufPacketType(pub(crate) u32);
+
+impl MbufPacketType {
+    const L2_MASK: u32 = 0x0000000f;
+    const L3_MASK: u32 = 0x000000f0;
+    const L4_MASK: u32 = 0x00000f00;
+    const TUNNEL_MASK: u32 = 0x0000f000;
+    const INNER_L2_MASK: u32 = 0x000f0000;
+    const INNER_L3_MASK: u32 = 0x00f00000;
+    const INNER_L4_MASK: u32 = 0x0f000000;
+
+    /// Returns the raw `RTE_PTYPE_*` bits.
+    #[inline]
+    pub fn raw(&self) -> u32 {
+        self.0
+    }
+
+    #[inline]
+    pub fn l2(&self) -> PtypeL2 {
+        match self.0 & Self::L2_MASK {
+            0x0 => PtypeL2::Unknown,
+            0x1 => PtypeL2::Ether,
+            0x2 => PtypeL2::EtherTimesync,
+            0x3 => PtypeL2::EtherArp,
+            0x4 => PtypeL2::EtherLldp,
+            0x5 => PtypeL2::EtherNsh,
+            0x6 => PtypeL2::EtherVlan,
+            0x7 => PtypeL2::EtherQinq,
+            0x8 => PtypeL2::EtherPppoe,
+            0x9 => PtypeL2::EtherFcoe,
+            0xa => PtypeL2::EtherMpls,
+            other => PtypeL2::Other(other),
+        }
+    }
+
+    #[inline]
+    pub fn l3(&self) -> PtypeL3 {
+        match self.0 & Self::L3_MASK {
+            0x00 => PtypeL3::Unknown,
+            0x10 => PtypeL3::Ipv4,
+            0x30 => PtypeL3::Ipv4Ext,
+            0x40 => PtypeL3::Ipv6,
+            0x90 => PtypeL3::Ipv4ExtUnknown,
+            0xc0 => PtypeL3::Ipv6Ext,
+            0xe0 => PtypeL3::Ipv6ExtUnknown,
+            other => PtypeL3::Other(other),
+        }
+    }
+
+    #[inline]
+    pub fn l4(&self) -> PtypeL4 {
+        Self::to_l4(self.0 & Self::L4_MASK)
+    }
+
+    #[inline]
+    pub fn tunnel(&self) -> PtypeTunnel {
+        match self.0 & Self::TUNNEL_MASK {
+            0x0000 => PtypeTunnel::None,
+            0x1000 => PtypeTunnel::Ip,
+            0x2000 => PtypeTunnel::Gre,
+            0x3000 => PtypeTunnel::Vxlan,
+            0x4000 => PtypeTunnel::Nvgre,
+            0x5000 => PtypeTunnel::Geneve,
+            0x6000 => PtypeTunnel::Grenat,
+            0x7000 => PtypeTunnel::Gtpc,
+            0x8000 => PtypeTunnel::Gtpu,
+            0x9000 => PtypeTunnel::Esp,
+            0xa000 => PtypeTunnel::L2tp,
+            0xb000 => PtypeTunnel::VxlanGpe,
+            0xc000 => PtypeTunnel::MplsInGre,
+            0xd000 => PtypeTunnel::MplsInUdp,
+            other => PtypeTunnel::Other(other),
+        }
+    }
+
+    /// Returns the L2 type of the tunneled packet, only `Ether`, `EtherVlan` and
+    /// `EtherQinq` are defined.
+    #[inline]
+    pub fn inner_l2(&self) -> PtypeL2 {
+        match self.0 & Self::INNER_L2_MASK {
+            0x00000 => PtypeL2::Unknown,
+            0x10000 => PtypeL2::Ether,
+            0x20000 => PtypeL2::EtherVlan,
+            0x30000 => PtypeL2::EtherQinq,
+            other => PtypeL2::Other(other),
+        }
+    }
+
+    /// Returns the L3 type of the tunneled packet, which is encoded differently from
+    /// the outer L3 type.
+    #[inline]
+    pub fn inner_l3(&self) -> PtypeL3 {
+        match self.0 & Self::INNER_L3_MASK {
+            0x000000 => PtypeL3::Unknown,
+            0x100000 => PtypeL3::Ipv4,
+            0x200000 => PtypeL3::Ipv4Ext,
+            0x300000 => PtypeL3::Ipv6,
+            0x400000 => PtypeL3::Ipv4ExtUnknown,
+            0x500000 => PtypeL3::Ipv6Ext,
+            0x600000 => PtypeL3::Ipv6ExtUnknown,
+            other => PtypeL3::Other(other),
+        }
+    }
+
+    #[inline]
+    pub fn inner_l4(&self) -> PtypeL4 {
+        // The inner L4 types are encoded as the outer ones, except for IGMP.
+        match Self::to_l4((self.0 & Self::INNER_L4_MASK) >> 16) {
+            PtypeL4::Igmp | PtypeL4::Other(_) => PtypeL4::Other(self.0 & Self::INNER_L4_MASK),
+            l4 => l4,
+        }
+    }
+
+    #[inline]
+    fn to_l4(bits: u32) -> PtypeL4 {
+        match bits {
+            0x000 => PtypeL4::Unknown,
+            0x100 => PtypeL4::Tcp,
+            0x200 => PtypeL4::Udp,
+            0x300 => PtypeL4::Frag,
+            0x400 => PtypeL4::Sctp,
+            0x500 => PtypeL4::Icmp,
+            0x600 => PtypeL4::NonFrag,
+            0x700 => PtypeL4::Igmp,
+            other => PtypeL4::Other(other),
+        }
+    }
+}
@@ -0,0 +1,296 @@
+//! A planner that assigns the queues of the ports to the lcores on the same
+//! NUMA socket.
+//!
+//! A queue polled from an lcore of another socket than the one of its port
+//! pays for the cross-socket accesses to the descriptors and the mbufs, which
+//! may halve the throughput without any error. The planner assigns the queue
+//! `i` of a port, i.e. both the rx queue `i` and the tx queue `i` in a
+//! run-to-completion design, to an lcore of the socket of the port, and
+//! either reports the queues that can only be placed on another socket as
+//! [`CrossSocket`] warnings, or refuses to place them.
+//!
+//! The lcores of distinct physical cores are used first, so that two queues
+//! only share the hyper-threads of a core when there are not enough cores.
+//!
+//! # Examples
+//! ```ignore
+//! use rpkt_dpdk::planner::PlacementPolicy;
+//! use rpkt_dpdk::service;
+//!
+//! let plan = service().plan_queues(&[(0, 4), (1, 4)], PlacementPolicy::Prefer)?;
+//! for warning in plan.warnings() {
+//!     eprintln!("{}", warning);
+//! }
+//! for assignment in plan.assignments() {
+//!     let (port_id, queue_id) = (assignment.port_id, assignment.queue_id);
+//!     let lcore_id = assignment.lcore.lcore_id;
+//!     std::thread::spawn(move || {
+//!         service().lcore_bind(lcore_id).unwrap();
+//!         let rxq = service().rx_queue(port_id, queue_id).unwrap();
+//!         // ...
+//!     });
+//! }
+//! ```
+
+use std::collections::HashSet;
+use std::fmt;
+
+use crate::error::*;
+use crate::{DpdkService, Lcore};
+
+/// What the planner does with a queue that has no free lcore on the socket of
+/// its port.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PlacementPolicy {
+    /// Place the queue on an lcore of another socket and report a
+    /// [`CrossSocket`] warning.
+    Prefer,
+    /// Fail the planning.
+    Enforce,
+}
+
+/// The queues of a port to be placed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PortQueues {
+    pub port_id: u16,
+    /// The socket of the port, see [`PortInfo::socket_id`](crate::PortInfo).
+    pub socket_id: u32,
+    pub nb_queues: u16,
+}
+
+/// The lcore assigned to the queue `queue_id` of a port.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct QueueAssignment {
+    pub port_id: u16,
+    pub queue_id: u16,
+    pub lcore: Lcore,
+}
+
+/// A queue placed on an lcore of another socket than the one of its port.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CrossSocket {
+    pub port_id: u16,
+    pub queue_id: u16,
+    pub port_socket_id: u32,
+    pub lcore_id: u32,
+    pub lcore_socket_id: u32,
+}
+
+impl fmt::Display for CrossSocket {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "queue {} of port {} on socket {} is polled from lcore {} on socket {}",
+            self.queue_id, self.port_id, self.port_socket_id, self.lcore_id, self.lcore_socket_id
+        )
+    }
+}
+
+/// The assignments of the queues to the lcores.
+#[derive(Debug, Clone, Default)]
+pub struct Plan {
+    assignments: Vec<QueueAssignment>,
+    warnings: Vec<CrossSocket>,
+}
+
+impl Plan {
+    /// Returns the assignments, in the order of the ports and of the queues.
+    pub fn assignments(&self) -> &[QueueAssignment] {
+        &self.assignments
+    }
+
+    /// Returns the queues placed on another socket than the one of their
+    /// port.
+    pub fn warnings(&self) -> &[CrossSocket] {
+        &self.warnings
+    }
+
+    /// Returns the lcore assigned to the queue `queue_id` of `port_id`.
+    pub fn lcore_of(&self, port_id: u16, queue_id: u16) -> Option<Lcore> {
+        self.assignments
+            .iter()
+            .find(|a| a.port_id == port_id && a.queue_id == queue_id)
+            .map(|a| a.lcore)
+    }
+}
+
+/// Assigns the queues of the ports to a set of lcores, one queue per lcore.
+#[derive(Debug, Clone)]
+pub struct Planner {
+    lcores: Vec<Lcore>,
+}
+
+impl Planner {
+    /// Create a planner over `lcores`.
+    pub fn new(lcores: Vec<Lcore>) -> Self {
+        Self { lcores }
+    }
+
+    /// Leave the lcore `lcore_id` out of the plans, e.g. the lcore of the
+    /// main thread.
+    pub fn exclude(&mut self, lcore_id: u32) -> &mut Self {
+        self.lcores.retain(|lcore| lcore.lcore_id != lcore_id);
+        self
+    }
+
+    /// Assign the queues of `ports`, in order, to the lcores.
+    ///
+    /// This function fails if there are fewer lcores than queues, or if a
+    /// queue can only be placed on another socket with
+    /// [`PlacementPolicy::Enforce`].
+    pub fn plan(&self, ports: &[PortQueues], policy: PlacementPolicy) -> Result<Plan> {
+        let mut used = vec![false; self.lcores.len()];
+        let mut plan = Plan::default();
+
+        for port in ports {
+            for queue_id in 0..port.nb_queues {
+                let idx = match self.pick(&used, Some(port.socket_id)) {
+                    Some(idx) => idx,
+                    None if policy == PlacementPolicy::Enforce => {
+                        return Error::service_err("no free lcore on the socket of the port")
+                            .to_err();
+                    }
+                    None => self
+                        .pick(&used, None)
+                        .ok_or(Error::service_err("not enough lcores for the queues"))?,
+                };
+                used[idx] = true;
+                let lcore = self.lcores[idx];
+                if lcore.socket_id != port.socket_id {
+                    plan.warnings.push(CrossSocket {
+                        port_id: port.port_id,
+                        queue_id,
+                        port_socket_id: port.socket_id,
+                        lcore_id: lcore.lcore_id,
+                        lcore_socket_id: lcore.socket_id,
+                    });
+                }
+                plan.assignments.push(QueueAssignment {
+                    port_id: port.port_id,
+                    queue_id,
+                    lcore,
+                });
+            }
+        }
+        Ok(plan)
+    }
+
+    // Returns the index of a free lcore on `socket_id`, or on any socket,
+    // preferring the physical cores that no queue uses yet.
+    fn pick(&self, used: &[bool], socket_id: Option<u32>) -> Option<usize> {
+        let busy_cores: HashSet<(u32, u32)> = self
+            .lcores
+            .iter()
+            .zip(used)
+            .filter(|(_, used)| **used)
+            .map(|(lcore, _)| (lcore.socket_id, lcore.cpu_id))
+            .collect();
+        let free = || {
+            self.lcores.iter().enumerate().filter(|(idx, lcore)| {
+                !used[*idx] && socket_id.map_or(true, |socket_id| lcore.socket_id == socket_id)
+            })
+        };
+        free()
+            .find(|(_, lcore)| !busy_cores.contains(&(lcore.socket_id, lcore.cpu_id)))
+            .or_else(|| free().next())
+            .map(|(idx, _)| idx)
+    }
+}
+
+impl DpdkService {
+    /// Assign `nb_queues` queues of every port of `ports`, given as
+    /// `(port_id, nb_queues)`, to the lcores of the machine, except the lcore
+    /// of the calling thread.
+    ///
+    /// See [`Planner::plan`] for the placement.
+    pub fn plan_queues(&self, ports: &[(u16, u16)], policy: PlacementPolicy) -> Result<Plan> {
+        let ports = ports
+            .iter()
+            .map(|&(port_id, nb_queues)| {
+                self.port_info(port_id).map(|info| PortQueues {
+                    port_id,
+                    socket_id: info.socket_id,
+                    nb_queues,
+                })
+            })
+            .collect::<Result<Vec<_>>>()?;
+
+        let mut planner = Planner::new(self.lcores().clone());
+        if let Some(lcore) = Lcore::current() {
+            planner.exclude(lcore.lcore_id);
+        }
+        planner.plan(&ports, policy)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Two sockets of two cores with two hyper-threads each, numbered like
+    // Linux: the lcores of socket 0 are 0 and 1, and their siblings 4 and 5.
+    fn lcores() -> Vec<Lcore> {
+        (0..8)
+            .map(|lcore_id| Lcore {
+                lcore_id,
+                cpu_id: lcore_id % 2,
+                socket_id: (lcore_id / 2) % 2,
+            })
+            .collect()
+    }
+
+    fn port(port_id: u16, socket_id: u32, nb_queues: u16) -> PortQueues {
+        PortQueues {
+            port_id,
+            socket_id,
+            nb_queues,
+        }
+    }
+
+    #[test]
+    fn same_socket_and_distinct_cores() {
+        let mut planner = Planner::new(lcores());
+        planner.exclude(0);
+
+        let plan = planner
+            .plan(&[port(0, 0, 2), port(1, 1, 2)], PlacementPolicy::Enforce)
+            .unwrap();
+        let lcore_ids: Vec<u32> = plan
+            .assignments()
+            .iter()
+            .map(|a| a.lcore.lcore_id)
+            .collect();
+        // Lcore 5 shares the core of lcore 1.
+        assert_eq!(lcore_ids, [1, 4, 2, 3]);
+        assert!(plan.warnings().is_empty());
+        assert_eq!(plan.lcore_of(1, 1).unwrap().lcore_id, 3);
+        assert!(plan.lcore_of(1, 2).is_none());
+    }
+
+    #[test]
+    fn cross_socket() {
+        let planner = Planner::new(lcores());
+        let ports = [port(0, 0, 5)];
+
+        let plan = planner.plan(&ports, PlacementPolicy::Prefer).unwrap();
+        assert_eq!(
+            plan.warnings(),
+            &[CrossSocket {
+                port_id: 0,
+                queue_id: 4,
+                port_socket_id: 0,
+                lcore_id: 2,
+                lcore_socket_id: 1,
+            }]
+        );
+        assert_eq!(
+            plan.warnings()[0].to_string(),
+            "queue 4 of port 0 on socket 0 is polled from lcore 2 on socket 1"
+        );
+
+        assert!(planner.plan(&ports, PlacementPolicy::Enforce).is_err());
+        assert!(planner
+            .plan(&[port(0, 0, 9)], PlacementPolicy::Prefer)
+            .is_err());
+    }
+}
@@ -1,12 +1,16 @@
 use std::ffi::CStr;
+use std::fmt;
+use std::os::unix::io::RawFd;
 use std::sync::Arc;
 
-use arrayvec::ArrayVec;
 use rpkt_dpdk_sys as ffi;
 
 use crate::error::*;
 use crate::offload::*;
+use crate::Batch;
 use crate::Mbuf;
+#[cfg(feature = "timestamp")]
+use crate::MbufTimestamp;
 use crate::Mempool;
 
 pub struct DescLim(ffi::rte_eth_desc_lim);
@@ -105,6 +109,21 @@ impl PortInfo {
         DevTxOffload(self.raw.tx_offload_capa & DevTxOffload::ALL_ENABLED.0)
     }
 
+    /// The rx offloads that can be enabled per queue, a subset of `rx_offload_capa`.
+    pub fn rx_queue_offload_capa(&self) -> DevRxOffload {
+        DevRxOffload(self.raw.rx_queue_offload_capa & DevRxOffload::ALL_ENABLED.0)
+    }
+
+    /// The tx offloads that can be enabled per queue, a subset of `tx_offload_capa`.
+    pub fn tx_queue_offload_capa(&self) -> DevTxOffload {
+        DevTxOffload(self.raw.tx_queue_offload_capa & DevTxOffload::ALL_ENABLED.0)
+    }
+
+    // link info
+    pub fn speed_capa(&self) -> LinkSpeedCapa {
+        LinkSpeedCapa(self.raw.speed_capa & LinkSpeedCapa::ALL_ENABLED.0)
+    }
+
     // rss info
     pub fn reta_size(&self) -> u16 {
         self.raw.reta_size
@@ -127,6 +146,61 @@ impl PortInfo {
     }
 }
 
+impl fmt::Display for PortInfo {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let mac = self.eth_addr;
+        writeln!(
+            f,
+            "port {}: driver {}, socket {}, mac {:02x}:{:02x}:{:02x}:{:02x}:{:02x}:{:02x}",
+            self.port_id,
+            self.driver_name,
+            self.socket_id,
+            mac[0],
+            mac[1],
+            mac[2],
+            mac[3],
+            mac[4],
+            mac[5]
+        )?;
+        writeln!(f, "  link speeds: {}", self.speed_capa())?;
+        writeln!(
+            f,
+            "  mtu: {}-{}, max rx pktlen: {}, min rx bufsize: {}",
+            self.min_mtu(),
+            self.max_mtu(),
+            self.max_rx_pktlen(),
+            self.min_rx_bufsize()
+        )?;
+        writeln!(
+            f,
+            "  queues: {} rx ({}-{} desc), {} tx ({}-{} desc)",
+            self.max_rx_queues(),
+            self.raw.rx_desc_lim.nb_min,
+            self.raw.rx_desc_lim.nb_max,
+            self.max_tx_queues(),
+            self.raw.tx_desc_lim.nb_min,
+            self.raw.tx_desc_lim.nb_max
+        )?;
+        writeln!(
+            f,
+            "  rx offloads: port 0x{:x}, queue 0x{:x}",
+            self.raw.rx_offload_capa, self.raw.rx_queue_offload_capa
+        )?;
+        writeln!(
+            f,
+            "  tx offloads: port 0x{:x}, queue 0x{:x}",
+            self.raw.tx_offload_capa, self.raw.tx_queue_offload_capa
+        )?;
+        write!(
+            f,
+            "  rss: reta size {}, hash key size {}, hash functions 0x{:x}",
+            self.reta_size(),
+            self.hash_key_size(),
+            self.raw.flow_type_rss_offloads
+        )
+    }
+}
+
 #[derive(Clone)]
 pub struct PortConf {
     pub mtu: u32, // packet length except ethernet overhead
@@ -135,6 +209,7 @@ pub struct PortConf {
     pub rss_hf: RssHashFunc,
     pub rss_hash_key: Vec<u8>,
     pub enable_promiscuous: bool,
+    pub enable_rx_intr: bool,
 }
 
 impl PortConf {
@@ -214,6 +289,7 @@ impl PortConf {
             rss_hf: port_info.flow_type_rss_offloads(),
             rss_hash_key: DEFAULT_RSS_KEY_40B.to_vec(),
             enable_promiscuous: true,
+            enable_rx_intr: false,
         })
     }
 
@@ -243,6 +319,76 @@ impl PortConf {
         self.enable_promiscuous = val;
     }
 
+    /// Enable the rx interrupts of the port, so that an idle rx queue can wait for
+    /// packets on the event fd returned by [`RxQueue::rx_intr_fd`].
+    pub fn set_enable_rx_intr(&mut self, val: bool) {
+        self.enable_rx_intr = val;
+    }
+
+    /// Returns the length of the largest frame received by the port, i.e. the mtu
+    /// plus the ethernet overhead.
+    pub fn max_frame_len(&self) -> u32 {
+        self.mtu + u32::from(Self::RTE_ETHER_OVERHEAD)
+    }
+
+    /// Returns the mbuf dataroom that holds a frame of `mtu` in a single mbuf, or
+    /// `None` if the frame does not fit in the largest mbuf.
+    pub fn dataroom_for_mtu(mtu: u32) -> Option<u16> {
+        let frame_len = mtu.checked_add(u32::from(Self::RTE_ETHER_OVERHEAD))?;
+        let dataroom = u16::try_from(frame_len).ok()?;
+        dataroom.checked_add(Mempool::MBUF_HEADROOM)?;
+        Some(dataroom)
+    }
+
+    /// Set the mtu for sending and receiving jumbo frames.
+    ///
+    /// The mtu is checked against the limits of the port. If a frame of `mtu` does
+    /// not fit in an mbuf with `dataroom` bytes, the rx scatter and tx multi-segment
+    /// offloads are enabled, so that the frame is carried by a chain of mbufs. This
+    /// requires the `multiseg` feature.
+    pub fn set_jumbo_mtu(&mut self, port_info: &PortInfo, mtu: u32, dataroom: u16) -> Result<()> {
+        if mtu < u32::from(port_info.min_mtu()) || mtu > u32::from(port_info.max_mtu()) {
+            return Error::service_err("invalid port mtu").to_err();
+        }
+
+        let frame_len = mtu + u32::from(Self::RTE_ETHER_OVERHEAD);
+        if frame_len > port_info.max_rx_pktlen() {
+            return Error::service_err("frame exceeds the max rx packet length").to_err();
+        }
+        if frame_len > u32::from(dataroom) {
+            self.enable_scatter(port_info)?;
+        }
+
+        self.mtu = mtu;
+        Ok(())
+    }
+
+    #[cfg(feature = "multiseg")]
+    fn enable_scatter(&mut self, port_info: &PortInfo) -> Result<()> {
+        if !port_info.rx_offload_capa().scatter() || !port_info.tx_offload_capa().multi_segs() {
+            return Error::service_err("multi-segment frames are not supported").to_err();
+        }
+        self.rx_offloads.enable_scatter();
+        self.tx_offloads.enable_multi_segs();
+        Ok(())
+    }
+
+    #[cfg(not(feature = "multiseg"))]
+    fn enable_scatter(&mut self, _port_info: &PortInfo) -> Result<()> {
+        Error::service_err("frame exceeds the mbuf dataroom, enable the multiseg feature").to_err()
+    }
+
+    // Whether a received frame can be scattered over a chain of mbufs.
+    #[cfg(feature = "multiseg")]
+    fn rx_scatter(&self) -> bool {
+        self.rx_offloads.scatter()
+    }
+
+    #[cfg(not(feature = "multiseg"))]
+    fn rx_scatter(&self) -> bool {
+        false
+    }
+
     // Safety: The returned `rte_eth_conf` must not live past `PortConf`.
     unsafe fn rte_eth_conf(&self, nb_rxq: u16, _nb_txq: u16) -> ffi::rte_eth_conf {
         let mut rx_mode: ffi::rte_eth_rxmode = std::mem::zeroed();
@@ -271,6 +417,9 @@ impl PortConf {
         eth_conf.rxmode = rx_mode;
         eth_conf.txmode = tx_mode;
         eth_conf.rx_adv_conf.rss_conf = rss_conf;
+        if self.enable_rx_intr {
+            eth_conf.intr_conf.set_rxq(1);
+        }
 
         eth_conf
     }
@@ -285,6 +434,7 @@ impl Default for PortConf {
             rss_hf: RssHashFunc::ALL_DISABLED,
             rss_hash_key: DEFAULT_RSS_KEY_40B.to_vec(),
             enable_promiscuous: true,
+            enable_rx_intr: false,
         }
     }
 }
@@ -300,8 +450,8 @@ impl Port {
     pub(crate) fn try_create(
         port_id: u16,
         port_conf: &PortConf,
-        rxq_confs: &Vec<(u16, u32, Mempool)>,
-        txq_confs: &Vec<(u16, u32)>,
+        rxq_confs: &Vec<(RxQueueConf, Mempool)>,
+        txq_confs: &Vec<TxQueueConf>,
     ) -> Result<Self> {
         // This check is only required for converting rxq/txq length to u16.
         if rxq_confs.len() > usize::from(u16::MAX)
@@ -312,6 +462,15 @@ impl Port {
             return Error::service_err("invalid rx/tx queues").to_err();
         }
 
+        // Without rx scatter, a received frame must fit in a single mbuf.
+        if !port_conf.rx_scatter()
+            && rxq_confs
+                .iter()
+                .any(|(_, mp)| port_conf.max_frame_len() > u32::from(mp.dataroom()))
+        {
+            return Error::service_err("mtu exceeds the dataroom of the rx mempool").to_err();
+        }
+
         // Safety: The `rte_eth_dev_configure` only copies the payload.
         let eth_conf =
             unsafe { port_conf.rte_eth_conf(rxq_confs.len() as u16, txq_confs.len() as u16) };
@@ -327,16 +486,20 @@ impl Port {
             return Error::ffi_err(res, "fail to configure eth dev").to_err();
         }
 
+        // The driver defaults of the ring thresholds.
+        let port_info = unsafe { PortInfo::try_get(port_id)? };
+
         let rxq_cts = rxq_confs
             .iter()
             .enumerate()
-            .map(move |(rx_queue_id, (nb_rx_desc, socket_id, mp))| unsafe {
+            .map(|(rx_queue_id, (rxq_conf, mp))| unsafe {
                 // Safety: rxq lives as long as mp
                 RxQueue::try_create(
                     port_id,
                     rx_queue_id as u16,
-                    *nb_rx_desc,
-                    *socket_id,
+                    rxq_conf.nb_rx_desc,
+                    rxq_conf.socket_id,
+                    rxq_conf.rte_eth_rxconf(&port_info),
                     mp.as_ptr() as *mut ffi::rte_mempool,
                 )
                 .map(|rxq| (rxq, mp.clone()))
@@ -344,13 +507,56 @@ impl Port {
             .collect::<Result<Vec<(RxQueue, Mempool)>>>()?;
 
         let txqs = txq_confs
-            .into_iter()
+            .iter()
             .enumerate()
-            .map(move |(tx_queue_id, (nb_tx_desc, socket_id))| {
-                TxQueue::try_create(port_id, tx_queue_id as u16, *nb_tx_desc, *socket_id)
+            .map(|(tx_queue_id, txq_conf)| {
+                TxQueue::try_create(
+                    port_id,
+                    tx_queue_id as u16,
+                    txq_conf.nb_tx_desc,
+                    txq_conf.socket_id,
+                    txq_conf.rte_eth_txconf(&port_info),
+                )
             })
             .collect::<Result<Vec<TxQueue>>>()?;
 
+        // Map the queues to the per-queue stats counters.
+        let stat_idx_err = Error::service_err("invalid queue stats counter");
+        for (rx_queue_id, (rxq_conf, _)) in rxq_confs.iter().enumerate() {
+            if let Some(stat_idx) = rxq_conf.stat_idx {
+                if usize::from(stat_idx) >= PortStats::QUEUE_STAT_CNTRS {
+                    return stat_idx_err.to_err();
+                }
+                let res = unsafe {
+                    ffi::rte_eth_dev_set_rx_queue_stats_mapping(
+                        port_id,
+                        rx_queue_id as u16,
+                        stat_idx,
+                    )
+                };
+                if res != 0 {
+                    return Error::ffi_err(res, "fail to set rx queue stats mapping").to_err();
+                }
+            }
+        }
+        for (tx_queue_id, txq_conf) in txq_confs.iter().enumerate() {
+            if let Some(stat_idx) = txq_conf.stat_idx {
+                if usize::from(stat_idx) >= PortStats::QUEUE_STAT_CNTRS {
+                    return stat_idx_err.to_err();
+                }
+                let res = unsafe {
+                    ffi::rte_eth_dev_set_tx_queue_stats_mapping(
+                        port_id,
+                        tx_queue_id as u16,
+                        stat_idx,
+                    )
+                };
+                if res != 0 {
+                    return Error::ffi_err(res, "fail to set tx queue stats mapping").to_err();
+                }
+            }
+        }
+
         let res = match port_conf.enable_promiscuous {
             true => unsafe { ffi::rte_eth_promiscuous_enable(port_id) },
             false => unsafe { ffi::rte_eth_promiscuous_disable(port_id) },
@@ -429,11 +635,39 @@ impl Port {
     }
 }
 
+/// The prefetch, host and write-back thresholds of a descriptor ring.
+///
+/// Their meaning and valid ranges depend on the NIC, see the datasheet of the NIC.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Default)]
+pub struct RingThresh {
+    pub pthresh: u8,
+    pub hthresh: u8,
+    pub wthresh: u8,
+}
+
+impl From<RingThresh> for ffi::rte_eth_thresh {
+    fn from(val: RingThresh) -> Self {
+        ffi::rte_eth_thresh {
+            pthresh: val.pthresh,
+            hthresh: val.hthresh,
+            wthresh: val.wthresh,
+        }
+    }
+}
+
 #[derive(Clone)]
 pub struct RxQueueConf {
     pub nb_rx_desc: u16,
     pub socket_id: u32,
     pub mp_name: String,
+    /// Overrides the driver default of the prefetch, host and write-back thresholds.
+    pub rx_thresh: Option<RingThresh>,
+    /// Overrides the driver default of the number of free descriptors that triggers
+    /// the replenishment of the rx ring.
+    pub rx_free_thresh: Option<u16>,
+    /// Map the queue to a per-queue stats counter of `PortStats`, which must be less
+    /// than `PortStats::QUEUE_STAT_CNTRS`.
+    pub stat_idx: Option<u8>,
 }
 
 impl RxQueueConf {
@@ -454,6 +688,33 @@ impl RxQueueConf {
     pub fn set_mp_name<S: AsRef<str>>(&mut self, val: S) {
         self.mp_name = val.as_ref().to_string();
     }
+
+    pub fn set_rx_thresh(&mut self, val: RingThresh) {
+        self.rx_thresh = Some(val);
+    }
+
+    pub fn set_rx_free_thresh(&mut self, val: u16) {
+        self.rx_free_thresh = Some(val);
+    }
+
+    pub fn set_stat_idx(&mut self, val: u8) {
+        self.stat_idx = Some(val);
+    }
+
+    // Returns `None` to use the driver defaults.
+    fn rte_eth_rxconf(&self, port_info: &PortInfo) -> Option<ffi::rte_eth_rxconf> {
+        if self.rx_thresh.is_none() && self.rx_free_thresh.is_none() {
+            return None;
+        }
+        let mut rxconf = port_info.raw.default_rxconf;
+        if let Some(thresh) = self.rx_thresh {
+            rxconf.rx_thresh = thresh.into();
+        }
+        if let Some(rx_free_thresh) = self.rx_free_thresh {
+            rxconf.rx_free_thresh = rx_free_thresh;
+        }
+        Some(rxconf)
+    }
 }
 
 impl Default for RxQueueConf {
@@ -462,6 +723,9 @@ impl Default for RxQueueConf {
             nb_rx_desc: Self::NB_RX_DESC,
             socket_id: 0,
             mp_name: "".to_string(),
+            rx_thresh: None,
+            rx_free_thresh: None,
+            stat_idx: None,
         }
     }
 }
@@ -470,12 +734,14 @@ pub struct RxQueue {
     port_id: u16,
     qid: u16,
     counter: Arc<()>,
+    #[cfg(feature = "timestamp")]
+    timestamp: Option<MbufTimestamp>,
 }
 
 impl RxQueue {
     #[inline]
-    pub fn rx<const N: usize>(&mut self, batch: &mut ArrayVec<Mbuf, N>) -> usize {
-        assert!(N <= usize::from(u16::MAX));
+    pub fn rx<B: Batch>(&mut self, batch: &mut B) -> usize {
+        let nb_pkts = batch.remaining_capacity().min(usize::from(u16::MAX));
         unsafe {
             let mbufs = std::mem::transmute::<*mut Mbuf, *mut *mut ffi::rte_mbuf>(
                 batch.as_mut_ptr().add(batch.len()),
@@ -484,19 +750,110 @@ impl RxQueue {
                 self.port_id,
                 self.qid,
                 mbufs,
-                (N - batch.len()) as u16,
+                nb_pkts as u16,
             ));
             batch.set_len(batch.len() + nb_rx);
+
+            #[cfg(feature = "timestamp")]
+            if let Some(ts) = self.timestamp {
+                let now = rpkt_time::Instant::now();
+                let len = batch.len();
+                for mbuf in batch.as_mut_slice()[len - nb_rx..].iter_mut() {
+                    ts.stamp(mbuf, now);
+                }
+            }
+
             nb_rx
         }
     }
 
+    /// Stamp every received mbuf with the instant at which it is received.
+    ///
+    /// The timestamp setting is bound to this `RxQueue` handle. Pass `None` to stop
+    /// stamping the received mbufs.
+    #[cfg(feature = "timestamp")]
+    pub fn set_timestamp(&mut self, timestamp: Option<MbufTimestamp>) {
+        self.timestamp = timestamp;
+    }
+
+    /// Enable the rx interrupt of the queue, which is fired when a packet is received.
+    ///
+    /// The port must be created with `PortConf::enable_rx_intr`. The interrupt is
+    /// usually disabled again once the queue is polled, so that the busy queue does not
+    /// fire an interrupt for every packet.
+    pub fn enable_rx_intr(&self) -> Result<()> {
+        let res = unsafe { ffi::rte_eth_dev_rx_intr_enable(self.port_id, self.qid) };
+        if res != 0 {
+            Error::ffi_err(res, "fail to enable rx interrupt").to_err()
+        } else {
+            Ok(())
+        }
+    }
+
+    /// Disable the rx interrupt of the queue.
+    pub fn disable_rx_intr(&self) -> Result<()> {
+        let res = unsafe { ffi::rte_eth_dev_rx_intr_disable(self.port_id, self.qid) };
+        if res != 0 {
+            Error::ffi_err(res, "fail to disable rx interrupt").to_err()
+        } else {
+            Ok(())
+        }
+    }
+
+    /// Returns the event fd that becomes readable when the rx interrupt is fired.
+    ///
+    /// The fd is owned by dpdk, and must be read to clear the event.
+    pub fn rx_intr_fd(&self) -> Result<RawFd> {
+        let fd = unsafe { ffi::rte_eth_dev_rx_intr_ctl_q_get_fd(self.port_id, self.qid) };
+        if fd < 0 {
+            Error::service_err("fail to get rx interrupt fd").to_err()
+        } else {
+            Ok(fd)
+        }
+    }
+
+    /// Returns the status of the descriptor at `offset` from the next one to be
+    /// received, for debugging a queue that stops receiving.
+    pub fn desc_status(&self, offset: u16) -> Result<RxDescStatus> {
+        let res = unsafe { ffi::rte_eth_rx_descriptor_status_(self.port_id, self.qid, offset) };
+        RxDescStatus::from_res(res)
+    }
+
+    /// Returns the statuses of all the `nb_desc` descriptors of the queue, see
+    /// `QueueInfo::nb_desc`.
+    ///
+    /// A queue whose descriptors are all `Unavail` is not refilled with mbufs, e.g.
+    /// because its mempool is empty, while `Done` descriptors are waiting to be
+    /// received.
+    pub fn desc_counts(&self, nb_desc: u16) -> Result<DescCounts> {
+        let mut counts = DescCounts::default();
+        for offset in 0..nb_desc {
+            match self.desc_status(offset)? {
+                RxDescStatus::Avail => counts.pending += 1,
+                RxDescStatus::Done => counts.done += 1,
+                RxDescStatus::Unavail => counts.unavail += 1,
+            }
+        }
+        Ok(counts)
+    }
+
+    /// Returns the number of descriptors filled with received packets.
+    pub fn used_desc_count(&self) -> Result<u16> {
+        let res = unsafe { ffi::rte_eth_rx_queue_count_(self.port_id, self.qid) };
+        if res < 0 {
+            Error::ffi_err(res, "fail to count used rx descriptors").to_err()
+        } else {
+            Ok(res as u16)
+        }
+    }
+
     // Safety: the mp must be a valid pointer throughout the lifetime of the RxQueue
     unsafe fn try_create(
         port_id: u16,
         rx_queue_id: u16,
         nb_rx_desc: u16,
         socket_id: u32,
+        rxconf: Option<ffi::rte_eth_rxconf>,
         mp: *mut ffi::rte_mempool,
     ) -> Result<Self> {
         let res = ffi::rte_eth_rx_queue_setup(
@@ -504,7 +861,9 @@ impl RxQueue {
             rx_queue_id,
             nb_rx_desc,
             socket_id,
-            std::ptr::null(),
+            rxconf
+                .as_ref()
+                .map_or(std::ptr::null(), |rxconf| rxconf as *const _),
             mp,
         );
 
@@ -515,6 +874,8 @@ impl RxQueue {
                 port_id,
                 qid: rx_queue_id,
                 counter: Arc::new(()),
+                #[cfg(feature = "timestamp")]
+                timestamp: None,
             })
         }
     }
@@ -528,6 +889,8 @@ impl RxQueue {
             port_id: self.port_id,
             qid: self.qid,
             counter: self.counter.clone(),
+            #[cfg(feature = "timestamp")]
+            timestamp: None,
         })
     }
 
@@ -540,6 +903,17 @@ impl RxQueue {
 pub struct TxQueueConf {
     pub nb_tx_desc: u16,
     pub socket_id: u32,
+    /// Overrides the driver default of the prefetch, host and write-back thresholds.
+    pub tx_thresh: Option<RingThresh>,
+    /// Overrides the driver default of the number of descriptors used before the
+    /// report status bit is set.
+    pub tx_rs_thresh: Option<u16>,
+    /// Overrides the driver default of the number of used descriptors that triggers
+    /// the freeing of the transmitted mbufs.
+    pub tx_free_thresh: Option<u16>,
+    /// Map the queue to a per-queue stats counter of `PortStats`, which must be less
+    /// than `PortStats::QUEUE_STAT_CNTRS`.
+    pub stat_idx: Option<u8>,
 }
 
 impl TxQueueConf {
@@ -556,6 +930,41 @@ impl TxQueueConf {
     pub fn set_socket_id(&mut self, val: u32) {
         self.socket_id = val;
     }
+
+    pub fn set_tx_thresh(&mut self, val: RingThresh) {
+        self.tx_thresh = Some(val);
+    }
+
+    pub fn set_tx_rs_thresh(&mut self, val: u16) {
+        self.tx_rs_thresh = Some(val);
+    }
+
+    pub fn set_tx_free_thresh(&mut self, val: u16) {
+        self.tx_free_thresh = Some(val);
+    }
+
+    pub fn set_stat_idx(&mut self, val: u8) {
+        self.stat_idx = Some(val);
+    }
+
+    // Returns `None` to use the driver defaults.
+    fn rte_eth_txconf(&self, port_info: &PortInfo) -> Option<ffi::rte_eth_txconf> {
+        if self.tx_thresh.is_none() && self.tx_rs_thresh.is_none() && self.tx_free_thresh.is_none()
+        {
+            return None;
+        }
+        let mut txconf = port_info.raw.default_txconf;
+        if let Some(thresh) = self.tx_thresh {
+            txconf.tx_thresh = thresh.into();
+        }
+        if let Some(tx_rs_thresh) = self.tx_rs_thresh {
+            txconf.tx_rs_thresh = tx_rs_thresh;
+        }
+        if let Some(tx_free_thresh) = self.tx_free_thresh {
+            txconf.tx_free_thresh = tx_free_thresh;
+        }
+        Some(txconf)
+    }
 }
 
 impl Default for TxQueueConf {
@@ -563,6 +972,10 @@ impl Default for TxQueueConf {
         Self {
             nb_tx_desc: Self::NB_TX_DESC,
             socket_id: 0,
+            tx_thresh: None,
+            tx_rs_thresh: None,
+            tx_free_thresh: None,
+            stat_idx: None,
         }
     }
 }
@@ -575,8 +988,8 @@ pub struct TxQueue {
 
 impl TxQueue {
     #[inline]
-    pub fn tx<const N: usize>(&mut self, batch: &mut ArrayVec<Mbuf, N>) -> usize {
-        assert!(N <= usize::from(u16::MAX));
+    pub fn tx<B: Batch>(&mut self, batch: &mut B) -> usize {
+        let nb_pkts = batch.len().min(usize::from(u16::MAX));
         unsafe {
             let mbufs =
                 std::mem::transmute::<*mut Mbuf, *mut *mut ffi::rte_mbuf>(batch.as_mut_ptr());
@@ -584,7 +997,7 @@ impl TxQueue {
                 self.port_id,
                 self.qid,
                 mbufs,
-                batch.len() as u16,
+                nb_pkts as u16,
             ));
             let remaining = batch.len() - nb_tx;
             std::ptr::copy(mbufs.add(nb_tx), mbufs, remaining);
@@ -594,14 +1007,46 @@ impl TxQueue {
         }
     }
 
-    fn try_create(port_id: u16, tx_queue_id: u16, nb_tx_desc: u16, socket_id: u32) -> Result<Self> {
+    /// Returns the status of the descriptor at `offset` from the next one to be
+    /// used for sending, for debugging a queue that stops sending.
+    pub fn desc_status(&self, offset: u16) -> Result<TxDescStatus> {
+        let res = unsafe { ffi::rte_eth_tx_descriptor_status_(self.port_id, self.qid, offset) };
+        TxDescStatus::from_res(res)
+    }
+
+    /// Returns the statuses of all the `nb_desc` descriptors of the queue, see
+    /// `QueueInfo::nb_desc`.
+    ///
+    /// A queue whose descriptors are all `Full` is not drained by the NIC, e.g.
+    /// because the link is down.
+    pub fn desc_counts(&self, nb_desc: u16) -> Result<DescCounts> {
+        let mut counts = DescCounts::default();
+        for offset in 0..nb_desc {
+            match self.desc_status(offset)? {
+                TxDescStatus::Full => counts.pending += 1,
+                TxDescStatus::Done => counts.done += 1,
+                TxDescStatus::Unavail => counts.unavail += 1,
+            }
+        }
+        Ok(counts)
+    }
+
+    fn try_create(
+        port_id: u16,
+        tx_queue_id: u16,
+        nb_tx_desc: u16,
+        socket_id: u32,
+        txconf: Option<ffi::rte_eth_txconf>,
+    ) -> Result<Self> {
         let res = unsafe {
             ffi::rte_eth_tx_queue_setup(
                 port_id,
                 tx_queue_id,
                 nb_tx_desc,
                 socket_id,
-                std::ptr::null(),
+                txconf
+                    .as_ref()
+                    .map_or(std::ptr::null(), |txconf| txconf as *const _),
             )
         };
 
@@ -667,6 +1112,11 @@ impl PortStats {
         self.0.rx_nombuf
     }
 
+    // per-queue counters
+    //
+    // Without a stats mapping, the counter `qid` belongs to the queue `qid`. Otherwise,
+    // it accumulates all the queues mapped to it through `RxQueueConf::stat_idx` and
+    // `TxQueueConf::stat_idx`.
     pub fn q_ipackets(&self, qid: usize) -> u64 {
         self.0.q_ipackets[qid]
     }
@@ -722,6 +1172,72 @@ impl StatsQueryContext {
         }
     }
 
+    /// Query the extended stats of the port, including the per-queue counters that the
+    /// driver reports beyond `PortStats::QUEUE_STAT_CNTRS`.
+    pub fn query_xstats(&mut self) -> Result<XStats> {
+        unsafe {
+            let nb = ffi::rte_eth_xstats_get_names(self.port_id, std::ptr::null_mut(), 0);
+            if nb < 0 {
+                return Error::ffi_err(nb, "fail to get xstats names").to_err();
+            }
+
+            let mut names: Vec<ffi::rte_eth_xstat_name> = vec![std::mem::zeroed(); nb as usize];
+            let mut values: Vec<ffi::rte_eth_xstat> = vec![std::mem::zeroed(); nb as usize];
+            let res = ffi::rte_eth_xstats_get_names(self.port_id, names.as_mut_ptr(), nb as u32);
+            if res != nb {
+                return Error::ffi_err(res, "fail to get xstats names").to_err();
+            }
+            let res = ffi::rte_eth_xstats_get(self.port_id, values.as_mut_ptr(), nb as u32);
+            if res != nb {
+                return Error::ffi_err(res, "fail to get xstats").to_err();
+            }
+
+            let stats = values
+                .iter()
+                .filter_map(|xstat| {
+                    let name = names.get(xstat.id as usize)?;
+                    let name = CStr::from_ptr(name.name.as_ptr()).to_str().ok()?;
+                    Some((name.to_owned(), xstat.value))
+                })
+                .collect();
+            Ok(XStats { stats })
+        }
+    }
+
+    /// Query the information of the rx queue `qid`.
+    pub fn rx_queue_info(&mut self, qid: u16) -> Result<QueueInfo> {
+        unsafe {
+            let mut info: ffi::rte_eth_rxq_info = std::mem::zeroed();
+            let res = ffi::rte_eth_rx_queue_info_get(self.port_id, qid, &mut info);
+            if res != 0 {
+                return Error::ffi_err(res, "fail to get rx queue info").to_err();
+            }
+            Ok(QueueInfo {
+                nb_desc: info.nb_desc,
+                started: u32::from(info.queue_state) == ffi::RTE_ETH_QUEUE_STATE_STARTED,
+                scattered_rx: info.scattered_rx != 0,
+                rx_buf_size: info.rx_buf_size,
+            })
+        }
+    }
+
+    /// Query the information of the tx queue `qid`.
+    pub fn tx_queue_info(&mut self, qid: u16) -> Result<QueueInfo> {
+        unsafe {
+            let mut info: ffi::rte_eth_txq_info = std::mem::zeroed();
+            let res = ffi::rte_eth_tx_queue_info_get(self.port_id, qid, &mut info);
+            if res != 0 {
+                return Error::ffi_err(res, "fail to get tx queue info").to_err();
+            }
+            Ok(QueueInfo {
+                nb_desc: info.nb_desc,
+                started: u32::from(info.queue_state) == ffi::RTE_ETH_QUEUE_STATE_STARTED,
+                scattered_rx: false,
+                rx_buf_size: 0,
+            })
+        }
+    }
+
     fn clone_once(&self) -> Result<Self> {
         if self.in_use() {
             return Error::service_err("port stats query is in use").to_err();
@@ -737,3 +1253,115 @@ impl StatsQueryContext {
         Arc::strong_count(&self.counter) != 1
     }
 }
+
+/// The status of an rx descriptor.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum RxDescStatus {
+    /// The descriptor is owned by the NIC, ready to receive a packet.
+    Avail,
+    /// The descriptor holds a received packet, not yet retrieved by `RxQueue::rx`.
+    Done,
+    /// The descriptor is not refilled with an mbuf, or is reserved by the driver.
+    Unavail,
+}
+
+impl RxDescStatus {
+    fn from_res(res: i32) -> Result<Self> {
+        match res {
+            res if res < 0 => Error::ffi_err(res, "fail to get rx descriptor status").to_err(),
+            res if res as u32 == ffi::RTE_ETH_RX_DESC_AVAIL => Ok(RxDescStatus::Avail),
+            res if res as u32 == ffi::RTE_ETH_RX_DESC_DONE => Ok(RxDescStatus::Done),
+            _ => Ok(RxDescStatus::Unavail),
+        }
+    }
+}
+
+/// The status of a tx descriptor.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TxDescStatus {
+    /// The descriptor holds a packet not yet sent by the NIC.
+    Full,
+    /// The packet of the descriptor is sent, the descriptor can be reused.
+    Done,
+    /// The descriptor is reserved by the driver.
+    Unavail,
+}
+
+impl TxDescStatus {
+    fn from_res(res: i32) -> Result<Self> {
+        match res {
+            res if res < 0 => Error::ffi_err(res, "fail to get tx descriptor status").to_err(),
+            res if res as u32 == ffi::RTE_ETH_TX_DESC_FULL => Ok(TxDescStatus::Full),
+            res if res as u32 == ffi::RTE_ETH_TX_DESC_DONE => Ok(TxDescStatus::Done),
+            _ => Ok(TxDescStatus::Unavail),
+        }
+    }
+}
+
+/// The numbers of descriptors of a queue in every status.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct DescCounts {
+    /// The descriptors owned by the NIC, `RxDescStatus::Avail` or
+    /// `TxDescStatus::Full`.
+    pub pending: u16,
+    pub done: u16,
+    pub unavail: u16,
+}
+
+impl fmt::Display for DescCounts {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "pending: {}, done: {}, unavail: {}",
+            self.pending, self.done, self.unavail
+        )
+    }
+}
+
+/// The information of an rx or tx queue.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct QueueInfo {
+    /// The number of descriptors of the ring.
+    pub nb_desc: u16,
+    /// Whether the queue is started.
+    pub started: bool,
+    /// Whether the received packets may span several mbufs, rx queues only.
+    pub scattered_rx: bool,
+    /// The size of the buffer of the rx descriptors, rx queues only.
+    pub rx_buf_size: u16,
+}
+
+/// The extended stats of a port, as pairs of counter name and value.
+#[derive(Clone, Debug, Default)]
+pub struct XStats {
+    stats: Vec<(String, u64)>,
+}
+
+impl XStats {
+    pub fn get(&self, name: &str) -> Option<u64> {
+        self.stats.iter().find(|(n, _)| n == name).map(|(_, v)| *v)
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (&str, u64)> {
+        self.stats.iter().map(|(n, v)| (n.as_str(), *v))
+    }
+
+    /// Returns the received packets, bytes and errors of rx queue `qid`, from the
+    /// generic `rx_q<qid>_*` counters.
+    pub fn rx_queue(&self, qid: u16) -> Option<(u64, u64, u64)> {
+        Some((
+            self.get(&format!("rx_q{}_packets", qid))?,
+            self.get(&format!("rx_q{}_bytes", qid))?,
+            self.get(&format!("rx_q{}_errors", qid))?,
+        ))
+    }
+
+    /// Returns the sent packets and bytes of tx queue `qid`, from the generic
+    /// `tx_q<qid>_*` counters.
+    pub fn tx_queue(&self, qid: u16) -> Option<(u64, u64)> {
+        Some((
+            self.get(&format!("tx_q{}_packets", qid))?,
+            self.get(&format!("tx_q{}_bytes", qid))?,
+        ))
+    }
+}
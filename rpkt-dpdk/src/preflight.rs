@@ -0,0 +1,447 @@
+//! Checks of the environment of DPDK, run before the initialization of the
+//! EAL.
+//!
+//! When the hugepages, the drivers of the devices or the IOMMU are not set
+//! up, the initialization of the EAL fails with messages that hardly point
+//! to the cause, or the ports are silently missing. [`DpdkService::preflight`]
+//! reads the sysfs and procfs files that DPDK depends on and returns a
+//! [`PreflightReport`] with a [`Diagnostic`] and a hint for every problem.
+//!
+//! # Examples
+//! ```ignore
+//! use rpkt_dpdk::{DpdkOption, DpdkService};
+//!
+//! let report = DpdkService::preflight(&["0000:3b:00.0"]);
+//! if !report.is_ok() {
+//!     eprintln!("{}", report);
+//!     std::process::exit(1);
+//! }
+//! DpdkOption::new().init().unwrap();
+//! ```
+
+use std::fmt;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::DpdkService;
+
+/// The drivers that give DPDK access to a PCI device through UIO or VFIO.
+pub const DPDK_DRIVERS: [&str; 3] = ["vfio-pci", "uio_pci_generic", "igb_uio"];
+
+/// The kernel drivers of the devices that DPDK drives while they stay bound
+/// to the kernel, e.g. the Mellanox NICs.
+pub const BIFURCATED_DRIVERS: [&str; 2] = ["mlx5_core", "mlx4_core"];
+
+/// The severity of a [`Diagnostic`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Severity {
+    /// DPDK may run, but likely not as intended.
+    Warning,
+    /// The initialization of DPDK or of a port will fail.
+    Error,
+}
+
+/// A problem found by the preflight checks, with a hint to solve it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Diagnostic {
+    pub severity: Severity,
+    pub message: String,
+    pub hint: String,
+}
+
+impl fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let severity = match self.severity {
+            Severity::Warning => "warning",
+            Severity::Error => "error",
+        };
+        write!(f, "{}: {}\n  hint: {}", severity, self.message, self.hint)
+    }
+}
+
+/// The hugepages of a size on a NUMA node.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Hugepages {
+    pub node: u32,
+    pub size_kb: u64,
+    pub total: u64,
+    pub free: u64,
+}
+
+/// The result of the preflight checks.
+#[derive(Debug, Clone, Default)]
+pub struct PreflightReport {
+    /// The hugepages of every size on every NUMA node.
+    pub hugepages: Vec<Hugepages>,
+    /// The PCI devices and the drivers they are bound to, if any.
+    pub drivers: Vec<(String, Option<String>)>,
+    /// Whether the kernel has IOMMU groups.
+    pub iommu_enabled: bool,
+    pub diagnostics: Vec<Diagnostic>,
+}
+
+impl PreflightReport {
+    /// Returns whether no check failed with [`Severity::Error`].
+    pub fn is_ok(&self) -> bool {
+        self.diagnostics
+            .iter()
+            .all(|diag| diag.severity < Severity::Error)
+    }
+
+    /// Returns the free hugepage memory of `node` in kilobytes.
+    pub fn free_hugepage_kb(&self, node: u32) -> u64 {
+        self.hugepages
+            .iter()
+            .filter(|pages| pages.node == node)
+            .map(|pages| pages.free * pages.size_kb)
+            .sum()
+    }
+
+    fn push(&mut self, severity: Severity, message: String, hint: String) {
+        self.diagnostics.push(Diagnostic {
+            severity,
+            message,
+            hint,
+        });
+    }
+}
+
+impl fmt::Display for PreflightReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for pages in self.hugepages.iter() {
+            writeln!(
+                f,
+                "node {}: {} of {} hugepages of {} kB free",
+                pages.node, pages.free, pages.total, pages.size_kb
+            )?;
+        }
+        for (device, driver) in self.drivers.iter() {
+            writeln!(
+                f,
+                "device {}: driver {}",
+                device,
+                driver.as_deref().unwrap_or("none")
+            )?;
+        }
+        writeln!(
+            f,
+            "iommu: {}",
+            if self.iommu_enabled {
+                "enabled"
+            } else {
+                "disabled"
+            }
+        )?;
+        for diag in self.diagnostics.iter() {
+            writeln!(f, "{}", diag)?;
+        }
+        Ok(())
+    }
+}
+
+impl DpdkService {
+    /// Check the hugepages, the drivers of the PCI `devices`, given as
+    /// addresses like `0000:3b:00.0`, and the IOMMU, before the
+    /// initialization of the EAL.
+    pub fn preflight<S: AsRef<str>>(devices: &[S]) -> PreflightReport {
+        preflight_at(Path::new("/"), devices)
+    }
+}
+
+/// Run the checks of [`DpdkService::preflight`] on the sysfs and procfs
+/// files under `root` instead of `/`.
+pub fn preflight_at<S: AsRef<str>>(root: &Path, devices: &[S]) -> PreflightReport {
+    let mut report = PreflightReport::default();
+    check_hugepages(root, &mut report);
+
+    let iommu_groups = root.join("sys/kernel/iommu_groups");
+    report.iommu_enabled = fs::read_dir(&iommu_groups)
+        .map(|mut groups| groups.next().is_some())
+        .unwrap_or(false);
+    let noiommu = read_trimmed(&root.join("sys/module/vfio/parameters/enable_unsafe_noiommu_mode"))
+        .is_some_and(|mode| mode == "Y" || mode == "1");
+
+    for device in devices {
+        check_device(root, device.as_ref(), noiommu, &mut report);
+    }
+    report
+}
+
+fn check_hugepages(root: &Path, report: &mut PreflightReport) {
+    let nodes = root.join("sys/devices/system/node");
+    let mut node_dirs: Vec<(u32, PathBuf)> = match fs::read_dir(&nodes) {
+        Ok(entries) => entries
+            .filter_map(|entry| {
+                let entry = entry.ok()?;
+                let node = entry
+                    .file_name()
+                    .to_str()?
+                    .strip_prefix("node")?
+                    .parse()
+                    .ok()?;
+                Some((node, entry.path().join("hugepages")))
+            })
+            .collect(),
+        // A kernel without NUMA support only has the global counters.
+        Err(_) => vec![(0, root.join("sys/kernel/mm/hugepages"))],
+    };
+    node_dirs.sort();
+
+    for (node, dir) in node_dirs {
+        let Ok(entries) = fs::read_dir(&dir) else {
+            continue;
+        };
+        let mut sizes: Vec<Hugepages> = entries
+            .filter_map(|entry| {
+                let entry = entry.ok()?;
+                let size_kb = entry
+                    .file_name()
+                    .to_str()?
+                    .strip_prefix("hugepages-")?
+                    .strip_suffix("kB")?
+                    .parse()
+                    .ok()?;
+                let read = |name| read_trimmed(&entry.path().join(name))?.parse().ok();
+                Some(Hugepages {
+                    node,
+                    size_kb,
+                    total: read("nr_hugepages")?,
+                    free: read("free_hugepages")?,
+                })
+            })
+            .collect();
+        sizes.sort_by_key(|pages| pages.size_kb);
+        report.hugepages.extend(sizes);
+    }
+
+    if report.hugepages.iter().all(|pages| pages.total == 0) {
+        report.push(
+            Severity::Error,
+            "no hugepages are reserved".to_string(),
+            "reserve them, e.g. `echo 1024 > /sys/kernel/mm/hugepages/hugepages-2048kB/nr_hugepages`, \
+             or with the `hugepages=` kernel parameter"
+                .to_string(),
+        );
+    } else if report.hugepages.iter().all(|pages| pages.free == 0) {
+        report.push(
+            Severity::Error,
+            "all the reserved hugepages are in use".to_string(),
+            "stop the other DPDK processes, or remove their stale files from the hugetlbfs \
+             mount point"
+                .to_string(),
+        );
+    } else {
+        let mut empty_nodes: Vec<u32> = report
+            .hugepages
+            .iter()
+            .map(|pages| pages.node)
+            .filter(|&node| report.free_hugepage_kb(node) == 0)
+            .collect();
+        empty_nodes.dedup();
+        for node in empty_nodes {
+            report.push(
+                Severity::Warning,
+                format!("node {} has no free hugepages", node),
+                format!(
+                    "the mempools of the ports on node {} will fail, or use remote memory; \
+                     reserve the hugepages under /sys/devices/system/node/node{}/hugepages",
+                    node, node
+                ),
+            );
+        }
+    }
+
+    let mounted = fs::read_to_string(root.join("proc/mounts"))
+        .map(|mounts| {
+            mounts
+                .lines()
+                .any(|line| line.split_whitespace().nth(2) == Some("hugetlbfs"))
+        })
+        .unwrap_or(false);
+    if !mounted {
+        report.push(
+            Severity::Error,
+            "no hugetlbfs is mounted".to_string(),
+            "mount one, e.g. `mount -t hugetlbfs nodev /dev/hugepages`".to_string(),
+        );
+    }
+}
+
+fn check_device(root: &Path, device: &str, noiommu: bool, report: &mut PreflightReport) {
+    let dir = root.join("sys/bus/pci/devices").join(device);
+    if !dir.exists() {
+        report.drivers.push((device.to_string(), None));
+        report.push(
+            Severity::Error,
+            format!("no PCI device {}", device),
+            "list the network devices with `lspci -D | grep -i ethernet`".to_string(),
+        );
+        return;
+    }
+
+    let driver = fs::read_link(dir.join("driver")).ok().and_then(|link| {
+        link.file_name()
+            .and_then(|name| name.to_str())
+            .map(str::to_string)
+    });
+    report.drivers.push((device.to_string(), driver.clone()));
+
+    let bind_hint = format!(
+        "bind it with `dpdk-devbind.py --bind=vfio-pci {}`, after `modprobe vfio-pci`",
+        device
+    );
+    match driver.as_deref() {
+        None => report.push(
+            Severity::Error,
+            format!("device {} is not bound to any driver", device),
+            bind_hint,
+        ),
+        Some(driver) if BIFURCATED_DRIVERS.contains(&driver) => {}
+        Some("vfio-pci") => {
+            if !root.join("dev/vfio/vfio").exists() {
+                report.push(
+                    Severity::Error,
+                    "/dev/vfio/vfio does not exist".to_string(),
+                    "load the vfio-pci module with `modprobe vfio-pci`".to_string(),
+                );
+            }
+            if !dir.join("iommu_group").exists() && !noiommu {
+                report.push(
+                    Severity::Error,
+                    format!("device {} has no IOMMU group", device),
+                    "enable the IOMMU with the `intel_iommu=on iommu=pt` or `amd_iommu=on` \
+                     kernel parameters, or the unsafe no-IOMMU mode of vfio"
+                        .to_string(),
+                );
+            }
+        }
+        Some(driver) if DPDK_DRIVERS.contains(&driver) => {
+            if report.iommu_enabled {
+                report.push(
+                    Severity::Warning,
+                    format!(
+                        "device {} uses {} while the IOMMU is enabled",
+                        device, driver
+                    ),
+                    "DMA may be blocked by the IOMMU, prefer vfio-pci".to_string(),
+                );
+            }
+        }
+        Some(driver) => report.push(
+            Severity::Error,
+            format!(
+                "device {} is bound to the kernel driver {}, which DPDK can not use",
+                device, driver
+            ),
+            bind_hint,
+        ),
+    }
+}
+
+fn read_trimmed(path: &Path) -> Option<String> {
+    fs::read_to_string(path)
+        .ok()
+        .map(|content| content.trim().to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::os::unix::fs::symlink;
+
+    // A fake root with 2 nodes, the devices 0000:01:00.0 bound to vfio-pci and
+    // 0000:02:00.0 bound to ixgbe.
+    fn fake_root(name: &str, free: [u64; 2]) -> PathBuf {
+        let root = std::env::temp_dir().join(format!("rpkt-preflight-{}", name));
+        let _ = fs::remove_dir_all(&root);
+        for (node, free) in free.iter().enumerate() {
+            let dir = root.join(format!(
+                "sys/devices/system/node/node{}/hugepages/hugepages-2048kB",
+                node
+            ));
+            fs::create_dir_all(&dir).unwrap();
+            fs::write(dir.join("nr_hugepages"), "512\n").unwrap();
+            fs::write(dir.join("free_hugepages"), format!("{}\n", free)).unwrap();
+        }
+        fs::create_dir_all(root.join("proc")).unwrap();
+        fs::write(
+            root.join("proc/mounts"),
+            "nodev /dev/hugepages hugetlbfs rw,relatime,pagesize=2M 0 0\n",
+        )
+        .unwrap();
+        fs::create_dir_all(root.join("sys/kernel/iommu_groups/7")).unwrap();
+        fs::create_dir_all(root.join("dev/vfio")).unwrap();
+        fs::write(root.join("dev/vfio/vfio"), "").unwrap();
+
+        for (device, driver) in [("0000:01:00.0", "vfio-pci"), ("0000:02:00.0", "ixgbe")] {
+            let drivers = root.join("sys/bus/pci/drivers");
+            fs::create_dir_all(drivers.join(driver)).unwrap();
+            let dir = root.join("sys/bus/pci/devices").join(device);
+            fs::create_dir_all(&dir).unwrap();
+            symlink(drivers.join(driver), dir.join("driver")).unwrap();
+            symlink(
+                root.join("sys/kernel/iommu_groups/7"),
+                dir.join("iommu_group"),
+            )
+            .unwrap();
+        }
+        root
+    }
+
+    #[test]
+    fn ready_environment() {
+        let root = fake_root("ready", [100, 512]);
+        let report = preflight_at(&root, &["0000:01:00.0"]);
+        assert!(report.is_ok(), "{}", report);
+        assert!(report.diagnostics.is_empty());
+        assert!(report.iommu_enabled);
+        assert_eq!(report.hugepages.len(), 2);
+        assert_eq!(report.free_hugepage_kb(1), 512 * 2048);
+        assert_eq!(
+            report.drivers,
+            [("0000:01:00.0".to_string(), Some("vfio-pci".to_string()))]
+        );
+        fs::remove_dir_all(root).unwrap();
+    }
+
+    #[test]
+    fn broken_environment() {
+        let root = fake_root("broken", [0, 512]);
+        fs::remove_file(root.join("sys/bus/pci/devices/0000:01:00.0/iommu_group")).unwrap();
+        let report = preflight_at(&root, &["0000:01:00.0", "0000:02:00.0", "0000:03:00.0"]);
+        assert!(!report.is_ok());
+
+        let messages: Vec<(Severity, &str)> = report
+            .diagnostics
+            .iter()
+            .map(|diag| (diag.severity, diag.message.as_str()))
+            .collect();
+        assert_eq!(
+            messages,
+            [
+                (Severity::Warning, "node 0 has no free hugepages"),
+                (Severity::Error, "device 0000:01:00.0 has no IOMMU group"),
+                (
+                    Severity::Error,
+                    "device 0000:02:00.0 is bound to the kernel driver ixgbe, which DPDK can not use"
+                ),
+                (Severity::Error, "no PCI device 0000:03:00.0"),
+            ]
+        );
+
+        fs::write(root.join("proc/mounts"), "").unwrap();
+        fs::remove_dir_all(root.join("sys/devices/system/node")).unwrap();
+        let report = preflight_at::<&str>(&root, &[]);
+        let messages: Vec<&str> = report
+            .diagnostics
+            .iter()
+            .map(|diag| diag.message.as_str())
+            .collect();
+        assert_eq!(
+            messages,
+            ["no hugepages are reserved", "no hugetlbfs is mounted"]
+        );
+        fs::remove_dir_all(root).unwrap();
+    }
+}
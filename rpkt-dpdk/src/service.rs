@@ -13,24 +13,46 @@ use super::port::*;
 
 pub(crate) static SERVICE: OnceCell<DpdkService> = OnceCell::new();
 
-pub struct DpdkOption {}
+pub struct DpdkOption {
+    eal_args: Option<Vec<String>>,
+}
 
 impl DpdkOption {
+    /// The eal arguments used when none are set.
+    pub const DEFAULT_EAL_ARGS: [&'static str; 6] =
+        ["-c", "1", "-n", "4", "--proc-type", "primary"];
+
     /// Create a new EalOption.
     pub fn new() -> Self {
-        DpdkOption {}
+        DpdkOption { eal_args: None }
+    }
+
+    /// Set the eal arguments, excluding the program name.
+    ///
+    /// The [`DEFAULT_EAL_ARGS`](DpdkOption::DEFAULT_EAL_ARGS) are used if they
+    /// are not set.
+    pub fn set_eal_args<S: AsRef<str>>(&mut self, args: &[S]) {
+        self.eal_args = Some(args.iter().map(|arg| arg.as_ref().to_string()).collect());
     }
 
     pub fn init(self) -> Result<()> {
         SERVICE.get_or_try_init(|| {
-            // prepare the eal paramters, "-c 1 -n 4 --proc-type primary"
+            // prepare the eal paramters, "-c 1 -n 4 --proc-type primary" by default
             let mut args: Vec<CString> = vec![CString::new("./prefix").unwrap()];
-            args.push(CString::new("-c").unwrap());
-            args.push(CString::new("1").unwrap());
-            args.push(CString::new("-n").unwrap());
-            args.push(CString::new("4").unwrap());
-            args.push(CString::new("--proc-type").unwrap());
-            args.push(CString::new("primary").unwrap());
+            match self.eal_args.as_ref() {
+                Some(eal_args) => {
+                    for arg in eal_args {
+                        let arg = CString::new(arg.as_str())
+                            .map_err(|_| Error::service_err("invalid eal argument"))?;
+                        args.push(arg);
+                    }
+                }
+                None => {
+                    for arg in Self::DEFAULT_EAL_ARGS {
+                        args.push(CString::new(arg).unwrap());
+                    }
+                }
+            }
 
             // let potential errors panic early
             let lcores = lcore::detect_lcores();
@@ -187,16 +209,11 @@ impl DpdkService {
                     .mpools
                     .get(rxq_conf.mp_name.as_str())
                     .ok_or(Error::service_err("no such mempool"))
-                    .map(|mp| (rxq_conf.nb_rx_desc, rxq_conf.socket_id, mp.clone()))
+                    .map(|mp| (rxq_conf.clone(), mp.clone()))
             })
-            .collect::<Result<Vec<(u16, u32, Mempool)>>>()?;
-
-        let txq_confs = txq_confs
-            .iter()
-            .map(|txq_conf| (txq_conf.nb_tx_desc, txq_conf.socket_id))
-            .collect::<Vec<(u16, u32)>>();
+            .collect::<Result<Vec<(RxQueueConf, Mempool)>>>()?;
 
-        let port = Port::try_create(port_id, port_conf, &rxq_confs, &txq_confs)?;
+        let port = Port::try_create(port_id, port_conf, &rxq_confs, txq_confs)?;
         inner.ports.insert(port_id, port);
 
         Ok(())
@@ -0,0 +1,281 @@
+use crate::offload::{DevTxOffload, MbufTxOffload};
+use crate::{Batch, Mbuf, TxQueue};
+
+// The tx offload flags are extracted from dpdk/lib/mbuf/rte_mbuf_core.h
+const TX_IP_CKSUM: u64 = 1 << 54;
+const TX_L4_MASK: u64 = 3 << 52;
+const TX_TCP_CKSUM: u64 = 1 << 52;
+const TX_UDP_CKSUM: u64 = 3 << 52;
+
+const IPV4_MIN_HEADER_LEN: usize = 20;
+const IPV6_HEADER_LEN: usize = 40;
+const TCP_CKSUM_OFFSET: usize = 16;
+const UDP_CKSUM_OFFSET: usize = 6;
+
+/// A tx queue that computes the checksums requested by the offload flags of the mbufs
+/// in software, when the port can not compute them in hardware.
+///
+/// The virtual devices of dpdk, e.g. `net_af_packet`, `net_pcap` and `net_tap`, do not
+/// support the checksum offloads. Wrapping their tx queues with a `SoftTxQueue` lets the
+/// application set the same offload flags, `l2_len` and `l3_len` as on a physical NIC.
+/// The checksums that are enabled on the port are left to the hardware.
+///
+/// The checksums are computed over the first segment of the mbuf only, the offload flags
+/// of a chained mbuf are cleared without computing them. TSO is not emulated.
+///
+/// # Examples
+/// ```no_run
+/// use arrayvec::ArrayVec;
+/// use rpkt_dpdk::soft_offload::SoftTxQueue;
+/// use rpkt_dpdk::*;
+///
+/// let port_info = service().port_info(0).unwrap();
+/// let port_conf = PortConf::from_port_info(&port_info).unwrap();
+///
+/// let txq = service().tx_queue(0, 0).unwrap();
+/// let mut txq = SoftTxQueue::new(txq, port_conf.tx_offloads);
+///
+/// let mut batch = ArrayVec::<_, 32>::new();
+/// txq.tx(&mut batch);
+/// ```
+pub struct SoftTxQueue {
+    txq: TxQueue,
+    hw_offloads: DevTxOffload,
+    emulated: u64,
+    malformed: u64,
+}
+
+impl SoftTxQueue {
+    /// Wrap `txq`, whose port is configured with the tx offloads of `hw_offloads`.
+    pub fn new(txq: TxQueue, hw_offloads: DevTxOffload) -> Self {
+        Self {
+            txq,
+            hw_offloads,
+            emulated: 0,
+            malformed: 0,
+        }
+    }
+
+    /// Compute the missing checksums of the mbufs in `batch` and send them, returns the
+    /// number of sent mbufs.
+    ///
+    /// As `TxQueue::tx`, the mbufs that are not sent are left in `batch`. Their checksums
+    /// are already computed, so they can be sent again with this method or the inner
+    /// queue.
+    #[inline]
+    pub fn tx<B: Batch>(&mut self, batch: &mut B) -> usize {
+        for mbuf in batch.iter_mut() {
+            match emulate_tx_offload(mbuf, self.hw_offloads) {
+                Some(true) => self.emulated += 1,
+                Some(false) => {}
+                None => self.malformed += 1,
+            }
+        }
+        self.txq.tx(batch)
+    }
+
+    /// Returns the number of mbufs whose checksums are computed in software.
+    pub fn emulated(&self) -> u64 {
+        self.emulated
+    }
+
+    /// Returns the number of mbufs whose offload flags are cleared without computing
+    /// the checksums, because their headers do not match `l2_len` and `l3_len`.
+    pub fn malformed(&self) -> u64 {
+        self.malformed
+    }
+
+    /// Returns the wrapped tx queue.
+    pub fn into_inner(self) -> TxQueue {
+        self.txq
+    }
+}
+
+/// Compute the checksums requested by the offload flags of `mbuf` that are not enabled
+/// in `hw_offloads`, and clear their flags.
+///
+/// Returns whether a checksum is computed, or `None` if the headers of the mbuf do not
+/// match its `l2_len` and `l3_len`, in which case the flags are cleared as well.
+pub fn emulate_tx_offload(mbuf: &mut Mbuf, hw_offloads: DevTxOffload) -> Option<bool> {
+    let ol_flags = mbuf.ol_flags();
+    let sw_flags = sw_flags(ol_flags, hw_offloads);
+    if sw_flags == 0 {
+        return Some(false);
+    }
+
+    let (l2_len, l3_len) = (mbuf.l2_len() as usize, mbuf.l3_len() as usize);
+    mbuf.set_tx_offload(MbufTxOffload(ol_flags & !sw_flags));
+    #[cfg(feature = "multiseg")]
+    if mbuf.num_segs() > 1 {
+        return None;
+    }
+    compute_cksums(mbuf.data_mut(), sw_flags, l2_len, l3_len).then_some(true)
+}
+
+// Returns the offload flags of `ol_flags` that are not supported by `hw_offloads`.
+fn sw_flags(ol_flags: u64, hw_offloads: DevTxOffload) -> u64 {
+    let mut sw_flags = 0;
+    if ol_flags & TX_IP_CKSUM != 0 && !hw_offloads.ipv4_cksum() {
+        sw_flags |= TX_IP_CKSUM;
+    }
+    match ol_flags & TX_L4_MASK {
+        TX_TCP_CKSUM if !hw_offloads.tcp_cksum() => sw_flags |= TX_TCP_CKSUM,
+        TX_UDP_CKSUM if !hw_offloads.udp_cksum() => sw_flags |= TX_UDP_CKSUM,
+        _ => {}
+    }
+    sw_flags
+}
+
+// Computes the checksums of `sw_flags` over the frame in `data`.
+fn compute_cksums(data: &mut [u8], sw_flags: u64, l2_len: usize, l3_len: usize) -> bool {
+    let l4_start = l2_len + l3_len;
+    if data.len() < l4_start || l3_len < IPV4_MIN_HEADER_LEN {
+        return false;
+    }
+    let ip = &mut data[l2_len..];
+
+    // The ip header tells the end of the l4 segment, so that the padding of the frame is
+    // excluded.
+    let (l4_end, pseudo_sum) = match ip[0] >> 4 {
+        4 if usize::from(ip[0] & 0x0f) * 4 == l3_len => {
+            if sw_flags & TX_IP_CKSUM != 0 {
+                ip[10..12].copy_from_slice(&[0, 0]);
+                let cksum = !fold(sum(&ip[..l3_len], 0));
+                ip[10..12].copy_from_slice(&cksum.to_be_bytes());
+            }
+            let total_len = usize::from(u16::from_be_bytes([ip[2], ip[3]]));
+            (total_len, sum(&ip[12..20], 0))
+        }
+        6 if l3_len >= IPV6_HEADER_LEN && sw_flags & TX_IP_CKSUM == 0 => {
+            let payload_len = usize::from(u16::from_be_bytes([ip[4], ip[5]]));
+            (IPV6_HEADER_LEN + payload_len, sum(&ip[8..40], 0))
+        }
+        _ => return false,
+    };
+
+    let (proto, cksum_offset) = match sw_flags & TX_L4_MASK {
+        TX_TCP_CKSUM => (6, TCP_CKSUM_OFFSET),
+        TX_UDP_CKSUM => (17, UDP_CKSUM_OFFSET),
+        _ => return true,
+    };
+    if l4_end > ip.len() || l4_end < l3_len + cksum_offset + 2 {
+        return false;
+    }
+
+    let l4 = &mut ip[l3_len..l4_end];
+    l4[cksum_offset..cksum_offset + 2].copy_from_slice(&[0, 0]);
+    let pseudo_sum = pseudo_sum + proto + l4.len() as u32;
+    let mut cksum = !fold(sum(l4, pseudo_sum));
+    if proto == 17 && cksum == 0 {
+        // A zero udp checksum means that the checksum is not computed.
+        cksum = 0xffff;
+    }
+    l4[cksum_offset..cksum_offset + 2].copy_from_slice(&cksum.to_be_bytes());
+    true
+}
+
+// Adds the 16-bit words of `data` to `initial`, the last odd byte is padded with zero.
+fn sum(data: &[u8], initial: u32) -> u32 {
+    let mut chunks = data.chunks_exact(2);
+    let mut sum = initial;
+    for chunk in &mut chunks {
+        sum += u32::from(u16::from_be_bytes([chunk[0], chunk[1]]));
+    }
+    if let [last] = chunks.remainder() {
+        sum += u32::from(*last) << 8;
+    }
+    // Fold in advance, so that the sum of a jumbo frame never overflows.
+    (sum & 0xffff) + (sum >> 16)
+}
+
+fn fold(mut sum: u32) -> u16 {
+    while sum >> 16 != 0 {
+        sum = (sum & 0xffff) + (sum >> 16);
+    }
+    sum as u16
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // An ethernet frame carrying an ipv4/udp packet with 2 bytes of payload, followed by
+    // 2 bytes of padding.
+    fn udp_frame() -> Vec<u8> {
+        let mut frame = vec![0; 14];
+        frame[12..14].copy_from_slice(&[0x08, 0x00]);
+        frame.extend_from_slice(&[
+            0x45, 0x00, 0x00, 0x1e, 0x00, 0x01, 0x00, 0x00, 0x40, 0x11, 0x00, 0x00, 192, 168, 0, 1,
+            192, 168, 0, 2,
+        ]);
+        frame.extend_from_slice(&[0x04, 0xd2, 0x16, 0x2e, 0x00, 0x0a, 0x00, 0x00, b'h', b'i']);
+        frame.extend_from_slice(&[0xff, 0xff]);
+        frame
+    }
+
+    fn verify(data: &[u8], initial: u32) -> bool {
+        fold(sum(data, initial)) == 0xffff
+    }
+
+    #[test]
+    fn ipv4_udp_cksums() {
+        let mut frame = udp_frame();
+        assert!(compute_cksums(
+            &mut frame,
+            TX_IP_CKSUM | TX_UDP_CKSUM,
+            14,
+            20
+        ));
+        assert!(verify(&frame[14..34], 0));
+        let pseudo_sum = sum(&frame[26..34], 0) + 17 + 10;
+        assert!(verify(&frame[34..44], pseudo_sum));
+        // The padding is excluded.
+        assert_eq!(&frame[44..], &[0xff, 0xff]);
+
+        // The ip checksum is left to the hardware.
+        let mut frame = udp_frame();
+        assert!(compute_cksums(&mut frame, TX_UDP_CKSUM, 14, 20));
+        assert_eq!(&frame[24..26], &[0, 0]);
+        assert!(verify(&frame[34..44], pseudo_sum));
+    }
+
+    #[test]
+    fn ipv6_tcp_cksum() {
+        let mut frame = vec![0; 14];
+        frame.extend_from_slice(&[0x60, 0, 0, 0, 0x00, 0x15, 0x06, 0x40]);
+        frame.extend((0..32).map(|i| i as u8));
+        let mut tcp = vec![0; 20];
+        tcp[12] = 0x50;
+        frame.extend_from_slice(&tcp);
+        frame.push(b'!');
+
+        assert!(compute_cksums(&mut frame, TX_TCP_CKSUM, 14, 40));
+        let pseudo_sum = sum(&frame[22..54], 0) + 6 + 21;
+        assert!(verify(&frame[54..], pseudo_sum));
+
+        // An ipv6 header has no checksum.
+        assert!(!compute_cksums(&mut frame, TX_IP_CKSUM, 14, 40));
+    }
+
+    #[test]
+    fn offload_flags() {
+        let mut hw_offloads = DevTxOffload::ALL_DISABLED;
+        let flags = TX_IP_CKSUM | TX_TCP_CKSUM;
+        assert_eq!(sw_flags(flags, hw_offloads), flags);
+        hw_offloads.enable_ipv4_cksum();
+        hw_offloads.enable_udp_cksum();
+        assert_eq!(sw_flags(flags, hw_offloads), TX_TCP_CKSUM);
+        assert_eq!(sw_flags(TX_IP_CKSUM | TX_UDP_CKSUM, hw_offloads), 0);
+
+        // Wrong header lengths and a truncated frame.
+        let frame = udp_frame();
+        assert!(!compute_cksums(&mut frame.clone(), TX_UDP_CKSUM, 14, 16));
+        assert!(!compute_cksums(&mut frame.clone(), TX_UDP_CKSUM, 18, 20));
+        assert!(!compute_cksums(
+            &mut frame[..40].to_vec(),
+            TX_UDP_CKSUM,
+            14,
+            20
+        ));
+    }
+}
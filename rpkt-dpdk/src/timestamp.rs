@@ -0,0 +1,159 @@
+use std::os::raw::c_char;
+
+use rpkt_dpdk_sys as ffi;
+use rpkt_time::{Duration, Instant};
+
+use crate::error::*;
+use crate::{Batch, Mbuf};
+
+/// A timestamp stored in a dynamic field of the `Mbuf`.
+///
+/// The timestamp is the raw tsc counter of an `rpkt_time::Instant`. Once the dynamic
+/// field is registered, it can be attached to a `RxQueue` through
+/// [`RxQueue::set_timestamp`](crate::RxQueue::set_timestamp), so that every received
+/// `Mbuf` is stamped on the rx path.
+///
+/// The timestamp is refreshed with [`MbufTimestamp::stamp`] whenever a packet enters a
+/// new stage of a pipeline, and the time a packet spends in a stage is computed with
+/// [`MbufTimestamp::residence`].
+#[derive(Clone, Copy, Debug)]
+pub struct MbufTimestamp {
+    offset: usize,
+}
+
+impl MbufTimestamp {
+    /// The name of the dynamic field.
+    pub const DYNFIELD_NAME: &'static [u8] = b"rpkt_dynfield_timestamp\0";
+
+    /// Register the dynamic field for storing the timestamp.
+    ///
+    /// The registration is idempotent, calling it multiple times returns the same
+    /// dynamic field. It must be called after the dpdk service is initialized.
+    pub fn register() -> Result<Self> {
+        let mut params: ffi::rte_mbuf_dynfield = unsafe { std::mem::zeroed() };
+        for (dst, src) in params.name.iter_mut().zip(Self::DYNFIELD_NAME.iter()) {
+            *dst = *src as c_char;
+        }
+        params.size = std::mem::size_of::<u64>();
+        params.align = std::mem::align_of::<u64>();
+
+        let offset = unsafe { ffi::rte_mbuf_dynfield_register(&params as *const _) };
+        if offset < 0 {
+            return Error::ffi_err(
+                unsafe { ffi::rte_errno_() },
+                "fail to register timestamp dynfield",
+            )
+            .to_err();
+        }
+
+        Ok(Self {
+            offset: offset as usize,
+        })
+    }
+
+    /// Stamp the mbuf with `instant`.
+    #[inline]
+    pub fn stamp(&self, mbuf: &mut Mbuf, instant: Instant) {
+        unsafe { *self.field_ptr(mbuf) = instant.raw() };
+    }
+
+    /// Stamp all the mbufs in the batch with the current instant.
+    #[inline]
+    pub fn stamp_batch<B: Batch>(&self, batch: &mut B) {
+        let now = Instant::now();
+        for mbuf in batch.iter_mut() {
+            self.stamp(mbuf, now);
+        }
+    }
+
+    /// Returns the timestamp stored in the mbuf.
+    #[inline]
+    pub fn get(&self, mbuf: &Mbuf) -> Instant {
+        Instant::from_raw(unsafe { *self.field_ptr(mbuf) })
+    }
+
+    /// Returns the time elapsed from the timestamp of the mbuf to `now`.
+    #[inline]
+    pub fn residence(&self, mbuf: &Mbuf, now: Instant) -> Duration {
+        now.saturating_cycles_since(self.get(mbuf))
+    }
+
+    // Safety: the mbuf must be allocated after the dynamic field is registered.
+    #[inline]
+    unsafe fn field_ptr(&self, mbuf: &Mbuf) -> *mut u64 {
+        (mbuf.as_ptr() as *mut u8).add(self.offset) as *mut u64
+    }
+}
+
+/// Statistics of the residence times of a pipeline stage.
+///
+/// Each lcore records the residence times of the packets that pass through its
+/// stage, and the statistics of different lcores can be combined with
+/// [`ResidenceStats::merge`].
+#[derive(Clone, Copy, Debug)]
+pub struct ResidenceStats {
+    count: u64,
+    total: u64,
+    min: u64,
+    max: u64,
+}
+
+impl ResidenceStats {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Record the residence time of a single packet.
+    #[inline]
+    pub fn record(&mut self, residence: Duration) {
+        let cycles = residence.raw();
+        self.count += 1;
+        self.total = self.total.saturating_add(cycles);
+        self.min = self.min.min(cycles);
+        self.max = self.max.max(cycles);
+    }
+
+    /// Merge the statistics of another stage instance, e.g. from another lcore.
+    pub fn merge(&mut self, other: &ResidenceStats) {
+        self.count += other.count;
+        self.total = self.total.saturating_add(other.total);
+        self.min = self.min.min(other.min);
+        self.max = self.max.max(other.max);
+    }
+
+    /// Returns the number of recorded packets.
+    pub fn count(&self) -> u64 {
+        self.count
+    }
+
+    /// Returns the minimum residence time, or `None` if no packet is recorded.
+    pub fn min(&self) -> Option<Duration> {
+        (self.count > 0).then(|| Duration::from_raw(self.min))
+    }
+
+    /// Returns the maximum residence time, or `None` if no packet is recorded.
+    pub fn max(&self) -> Option<Duration> {
+        (self.count > 0).then(|| Duration::from_raw(self.max))
+    }
+
+    /// Returns the mean residence time, or `None` if no packet is recorded.
+    pub fn mean(&self) -> Option<Duration> {
+        (self.count > 0).then(|| Duration::from_raw(self.total / self.count))
+    }
+
+    /// Clear the recorded statistics.
+    pub fn reset(&mut self) {
+        *self = Self::default();
+    }
+}
+
+impl Default for ResidenceStats {
+    fn default() -> Self {
+        Self {
+            count: 0,
+            total: 0,
+            min: u64::MAX,
+            max: 0,
+        }
+    }
+}
@@ -0,0 +1,395 @@
+use std::collections::HashSet;
+use std::fmt;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+
+use rpkt_dpdk::Mbuf;
+
+use crate::node::{Dispatch, Node};
+
+/// Identifies a node added to a [`GraphBuilder`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct NodeId(usize);
+
+/// The error of building a [`Graph`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum GraphError {
+    /// Two nodes have the same name.
+    DuplicateName(String),
+    /// A source node is the next node of another node.
+    SourceWithInput(String),
+    /// The edges form a cycle that contains the node.
+    Cycle(String),
+}
+
+impl fmt::Display for GraphError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            GraphError::DuplicateName(name) => write!(f, "duplicate node name {}", name),
+            GraphError::SourceWithInput(name) => write!(f, "source node {} has an input", name),
+            GraphError::Cycle(name) => write!(f, "node {} is in a cycle", name),
+        }
+    }
+}
+
+impl std::error::Error for GraphError {}
+
+struct Entry<P> {
+    name: String,
+    node: Box<dyn Node<P>>,
+    source: bool,
+    nexts: Vec<usize>,
+}
+
+/// Builds a [`Graph`] from nodes and edges.
+///
+/// The edges must form a directed acyclic graph, so that every node is processed at
+/// most once for each walk of the graph.
+pub struct GraphBuilder<P = Mbuf> {
+    entries: Vec<Entry<P>>,
+}
+
+impl<P> GraphBuilder<P> {
+    pub fn new() -> Self {
+        Self {
+            entries: Vec::new(),
+        }
+    }
+
+    /// Add a node that processes the packets dispatched by other nodes.
+    pub fn add_node<N: Node<P> + 'static>(&mut self, name: &str, node: N) -> NodeId {
+        self.add(name, Box::new(node), false)
+    }
+
+    /// Add a source node, which is called with an empty input on every walk of the
+    /// graph.
+    pub fn add_source<N: Node<P> + 'static>(&mut self, name: &str, node: N) -> NodeId {
+        self.add(name, Box::new(node), true)
+    }
+
+    /// Add an edge from `from` to `to`, and returns the index of `to` among the next
+    /// nodes of `from`.
+    ///
+    /// # Panics
+    /// The nodes must be added to this builder.
+    pub fn connect(&mut self, from: NodeId, to: NodeId) -> usize {
+        assert!(to.0 < self.entries.len());
+        let nexts = &mut self.entries[from.0].nexts;
+        nexts.push(to.0);
+        nexts.len() - 1
+    }
+
+    /// Build the graph.
+    pub fn build(self) -> Result<Graph<P>, GraphError> {
+        let mut names = HashSet::new();
+        for entry in self.entries.iter() {
+            if !names.insert(entry.name.as_str()) {
+                return Err(GraphError::DuplicateName(entry.name.clone()));
+            }
+        }
+
+        let mut indegrees = vec![0; self.entries.len()];
+        for entry in self.entries.iter() {
+            for next in entry.nexts.iter() {
+                if self.entries[*next].source {
+                    return Err(GraphError::SourceWithInput(
+                        self.entries[*next].name.clone(),
+                    ));
+                }
+                indegrees[*next] += 1;
+            }
+        }
+
+        // Sort the nodes in topological order, and keep the order in which the nodes
+        // are added when there are several candidates.
+        let mut order = Vec::with_capacity(self.entries.len());
+        let mut visited = vec![false; self.entries.len()];
+        while order.len() < self.entries.len() {
+            let idx = (0..self.entries.len())
+                .find(|idx| !visited[*idx] && indegrees[*idx] == 0)
+                .ok_or_else(|| {
+                    let idx = (0..self.entries.len()).find(|idx| !visited[*idx]).unwrap();
+                    GraphError::Cycle(self.entries[idx].name.clone())
+                })?;
+            visited[idx] = true;
+            for next in self.entries[idx].nexts.iter() {
+                indegrees[*next] -= 1;
+            }
+            order.push(idx);
+        }
+
+        let mut positions = vec![0; self.entries.len()];
+        for (pos, idx) in order.iter().enumerate() {
+            positions[*idx] = pos;
+        }
+        let mut entries: Vec<_> = self.entries.into_iter().map(Some).collect();
+
+        let mut graph = Graph {
+            nodes: Vec::with_capacity(order.len()),
+            sources: Vec::with_capacity(order.len()),
+            nexts: Vec::with_capacity(order.len()),
+            inputs: (0..order.len()).map(|_| Vec::new()).collect(),
+            stats: Arc::new(GraphStats { nodes: Vec::new() }),
+        };
+        let mut stats = Vec::with_capacity(order.len());
+        for idx in order {
+            let entry = entries[idx].take().unwrap();
+            graph.nodes.push(entry.node);
+            graph.sources.push(entry.source);
+            graph
+                .nexts
+                .push(entry.nexts.iter().map(|next| positions[*next]).collect());
+            stats.push((entry.name, NodeCounters::default()));
+        }
+        graph.stats = Arc::new(GraphStats { nodes: stats });
+        Ok(graph)
+    }
+
+    fn add(&mut self, name: &str, node: Box<dyn Node<P>>, source: bool) -> NodeId {
+        self.entries.push(Entry {
+            name: name.to_string(),
+            node,
+            source,
+            nexts: Vec::new(),
+        });
+        NodeId(self.entries.len() - 1)
+    }
+}
+
+impl<P> Default for GraphBuilder<P> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// A packet processing graph, which is walked on a single thread.
+///
+/// Each walk calls the nodes in topological order. A node is called when it is a
+/// source node or when some packets are dispatched to it, and it receives all the
+/// packets dispatched to it during the walk as a single batch.
+pub struct Graph<P = Mbuf> {
+    nodes: Vec<Box<dyn Node<P>>>,
+    sources: Vec<bool>,
+    nexts: Vec<Vec<usize>>,
+    inputs: Vec<Vec<P>>,
+    stats: Arc<GraphStats>,
+}
+
+impl<P> Graph<P> {
+    /// Walk the graph once, returns the number of packets produced by the source
+    /// nodes.
+    pub fn walk(&mut self) -> usize {
+        let mut produced = 0;
+        for idx in 0..self.nodes.len() {
+            if !self.sources[idx] && self.inputs[idx].is_empty() {
+                continue;
+            }
+
+            let mut input = std::mem::take(&mut self.inputs[idx]);
+            let nb_pkts = input.len();
+            let mut out = Dispatch::new(&self.nexts[idx], &mut self.inputs);
+            self.nodes[idx].process(&mut input, &mut out);
+            let (enqueued, mut dropped) = out.counters();
+
+            // The packets that are not consumed by the node are dropped.
+            dropped += input.len() as u64;
+            input.clear();
+            self.inputs[idx] = input;
+
+            if self.sources[idx] {
+                produced += enqueued as usize;
+            }
+            let counters = &self.stats.nodes[idx].1;
+            counters.calls.fetch_add(1, Ordering::Relaxed);
+            counters
+                .packets
+                .fetch_add(nb_pkts as u64, Ordering::Relaxed);
+            counters.enqueued.fetch_add(enqueued, Ordering::Relaxed);
+            counters.dropped.fetch_add(dropped, Ordering::Relaxed);
+        }
+        produced
+    }
+
+    /// Returns the counters of the nodes, which can be read from other threads while
+    /// the graph is walked.
+    pub fn stats(&self) -> Arc<GraphStats> {
+        self.stats.clone()
+    }
+}
+
+#[derive(Default)]
+struct NodeCounters {
+    calls: AtomicU64,
+    packets: AtomicU64,
+    enqueued: AtomicU64,
+    dropped: AtomicU64,
+}
+
+/// A snapshot of the counters of a node.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct NodeStats {
+    /// The number of times that the node is called.
+    pub calls: u64,
+    /// The number of input packets.
+    pub packets: u64,
+    /// The number of packets dispatched to the next nodes.
+    pub enqueued: u64,
+    /// The number of dropped packets.
+    pub dropped: u64,
+}
+
+/// The counters of the nodes of a [`Graph`].
+pub struct GraphStats {
+    nodes: Vec<(String, NodeCounters)>,
+}
+
+impl GraphStats {
+    /// Returns the counters of the node named `name`.
+    pub fn get(&self, name: &str) -> Option<NodeStats> {
+        self.nodes
+            .iter()
+            .find(|(node_name, _)| node_name == name)
+            .map(|(_, counters)| Self::snapshot(counters))
+    }
+
+    /// Iterates over the names and the counters of the nodes, in the order that the
+    /// nodes are walked.
+    pub fn iter(&self) -> impl Iterator<Item = (&str, NodeStats)> + '_ {
+        self.nodes
+            .iter()
+            .map(|(name, counters)| (name.as_str(), Self::snapshot(counters)))
+    }
+
+    fn snapshot(counters: &NodeCounters) -> NodeStats {
+        NodeStats {
+            calls: counters.calls.load(Ordering::Relaxed),
+            packets: counters.packets.load(Ordering::Relaxed),
+            enqueued: counters.enqueued.load(Ordering::Relaxed),
+            dropped: counters.dropped.load(Ordering::Relaxed),
+        }
+    }
+}
+
+impl fmt::Display for GraphStats {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(
+            f,
+            "{:<16} {:>12} {:>12} {:>12} {:>12}",
+            "node", "calls", "packets", "enqueued", "dropped"
+        )?;
+        for (name, stats) in self.iter() {
+            writeln!(
+                f,
+                "{:<16} {:>12} {:>12} {:>12} {:>12}",
+                name, stats.calls, stats.packets, stats.enqueued, stats.dropped
+            )?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::FnNode;
+
+    struct Counter {
+        next: u32,
+        count: u32,
+    }
+
+    impl Node<u32> for Counter {
+        fn process(&mut self, _input: &mut Vec<u32>, out: &mut Dispatch<'_, u32>) {
+            for _ in 0..self.count {
+                out.enqueue(0, self.next);
+                self.next += 1;
+            }
+        }
+    }
+
+    #[test]
+    fn walk_graph() {
+        let mut builder = GraphBuilder::new();
+        // Add the sink first, the walk order follows the edges.
+        let sink_out = std::rc::Rc::new(std::cell::RefCell::new(Vec::new()));
+        let sink = {
+            let sink_out = sink_out.clone();
+            builder.add_node(
+                "sink",
+                FnNode::new(move |pkt: u32, _: &mut Dispatch<'_, u32>| {
+                    sink_out.borrow_mut().push(pkt)
+                }),
+            )
+        };
+        let src = builder.add_source("src", Counter { next: 0, count: 4 });
+        let classify = builder.add_node(
+            "classify",
+            FnNode::new(|pkt: u32, out: &mut Dispatch<'_, u32>| match pkt % 3 {
+                0 => out.enqueue(0, pkt),
+                1 => out.enqueue(1, pkt),
+                _ => out.drop(pkt),
+            }),
+        );
+        let double = builder.add_node(
+            "double",
+            FnNode::new(|pkt: u32, out: &mut Dispatch<'_, u32>| out.enqueue(0, pkt * 2)),
+        );
+        assert_eq!(builder.connect(src, classify), 0);
+        assert_eq!(builder.connect(classify, sink), 0);
+        assert_eq!(builder.connect(classify, double), 1);
+        builder.connect(double, sink);
+
+        let mut graph = builder.build().unwrap();
+        let stats = graph.stats();
+        let names: Vec<_> = stats.iter().map(|(name, _)| name).collect();
+        assert_eq!(names, ["src", "classify", "double", "sink"]);
+
+        assert_eq!(graph.walk(), 4);
+        assert_eq!(graph.walk(), 4);
+        let mut out = sink_out.borrow().clone();
+        out.sort();
+        assert_eq!(out, [0, 2, 3, 6, 8, 14]);
+
+        assert_eq!(
+            stats.get("classify").unwrap(),
+            NodeStats {
+                calls: 2,
+                packets: 8,
+                enqueued: 6,
+                dropped: 2
+            }
+        );
+        assert_eq!(stats.get("sink").unwrap().dropped, 0);
+        assert!(stats.get("none").is_none());
+    }
+
+    #[test]
+    fn invalid_graphs() {
+        let mut builder: GraphBuilder<u32> = GraphBuilder::new();
+        let a = builder.add_node("a", FnNode::new(|_, _: &mut Dispatch<'_, u32>| {}));
+        let b = builder.add_node("b", FnNode::new(|_, _: &mut Dispatch<'_, u32>| {}));
+        builder.connect(a, b);
+        builder.connect(b, a);
+        assert_eq!(
+            builder.build().err(),
+            Some(GraphError::Cycle("a".to_string()))
+        );
+
+        let mut builder: GraphBuilder<u32> = GraphBuilder::new();
+        let a = builder.add_node("a", FnNode::new(|_, _: &mut Dispatch<'_, u32>| {}));
+        let src = builder.add_source("src", Counter { next: 0, count: 1 });
+        builder.connect(a, src);
+        assert_eq!(
+            builder.build().err(),
+            Some(GraphError::SourceWithInput("src".to_string()))
+        );
+
+        let mut builder: GraphBuilder<u32> = GraphBuilder::new();
+        builder.add_node("a", FnNode::new(|_, _: &mut Dispatch<'_, u32>| {}));
+        builder.add_node("a", FnNode::new(|_, _: &mut Dispatch<'_, u32>| {}));
+        assert_eq!(
+            builder.build().err(),
+            Some(GraphError::DuplicateName("a".to_string()))
+        );
+    }
+}
@@ -0,0 +1,66 @@
+use arrayvec::ArrayVec;
+use rpkt_dpdk::{Mbuf, RxQueue, TxQueue};
+
+use crate::node::{Dispatch, Node};
+
+/// The number of mbufs received or sent in a burst.
+const BATCH_SIZE: usize = 32;
+
+/// A source node that receives a burst of mbufs from a `RxQueue`, and dispatches all
+/// of them to its first next node.
+pub struct RxNode {
+    rxq: RxQueue,
+    batch: ArrayVec<Mbuf, BATCH_SIZE>,
+}
+
+impl RxNode {
+    pub fn new(rxq: RxQueue) -> Self {
+        Self {
+            rxq,
+            batch: ArrayVec::new(),
+        }
+    }
+}
+
+impl Node<Mbuf> for RxNode {
+    fn process(&mut self, _input: &mut Vec<Mbuf>, out: &mut Dispatch<'_, Mbuf>) {
+        self.rxq.rx(&mut self.batch);
+        for mbuf in self.batch.drain(..) {
+            out.enqueue(0, mbuf);
+        }
+    }
+}
+
+/// A node that sends its input mbufs to a `TxQueue`.
+///
+/// The node does not wait for a full tx queue, the mbufs that can not be sent are
+/// dropped.
+pub struct TxNode {
+    txq: TxQueue,
+    batch: ArrayVec<Mbuf, BATCH_SIZE>,
+}
+
+impl TxNode {
+    pub fn new(txq: TxQueue) -> Self {
+        Self {
+            txq,
+            batch: ArrayVec::new(),
+        }
+    }
+}
+
+impl Node<Mbuf> for TxNode {
+    fn process(&mut self, input: &mut Vec<Mbuf>, out: &mut Dispatch<'_, Mbuf>) {
+        let mut pkts = input.drain(..);
+        loop {
+            self.batch.extend(pkts.by_ref().take(BATCH_SIZE));
+            if self.batch.is_empty() {
+                break;
+            }
+            self.txq.tx(&mut self.batch);
+            for mbuf in self.batch.drain(..) {
+                out.drop(mbuf);
+            }
+        }
+    }
+}
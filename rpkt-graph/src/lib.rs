@@ -0,0 +1,55 @@
+//! A packet processing graph built on top of `rpkt-dpdk`.
+//!
+//! A graph is made of nodes connected by edges. Each node implements [`Node`], which
+//! processes a batch of packets and dispatches every packet to one of its next nodes,
+//! e.g. a parse node forwards the valid packets to a classify node and drops the rest.
+//! Source nodes, such as [`RxNode`], have no input and produce packets every time the
+//! graph is walked.
+//!
+//! The framework moves the packets between the nodes in batches, and maintains the
+//! counters of every node in [`GraphStats`]. A [`Graph`] is walked on a single thread,
+//! and [`Pipeline::launch`] runs a copy of the graph on each of a set of lcores.
+//!
+//! # Examples
+//! ```no_run
+//! use rpkt_dpdk::{service, Mbuf};
+//! use rpkt_graph::{Dispatch, FnNode, GraphBuilder, Pipeline, RxNode, TxNode};
+//!
+//! let pipeline = Pipeline::launch(&[1, 2], |idx| {
+//!     let mut builder = GraphBuilder::new();
+//!     let rx = builder.add_source("rx", RxNode::new(service().rx_queue(0, idx as u16).unwrap()));
+//!     let filter = builder.add_node(
+//!         "filter",
+//!         FnNode::new(|mbuf: Mbuf, out: &mut Dispatch<'_, Mbuf>| {
+//!             if mbuf.len() >= 64 {
+//!                 out.enqueue(0, mbuf);
+//!             } else {
+//!                 out.drop(mbuf);
+//!             }
+//!         }),
+//!     );
+//!     let tx = builder.add_node("tx", TxNode::new(service().tx_queue(0, idx as u16).unwrap()));
+//!     builder.connect(rx, filter);
+//!     builder.connect(filter, tx);
+//!     builder.build().unwrap()
+//! })
+//! .unwrap();
+//!
+//! std::thread::sleep(std::time::Duration::from_secs(10));
+//! for stats in pipeline.stats() {
+//!     println!("{}", stats);
+//! }
+//! pipeline.stop();
+//! ```
+
+mod node;
+pub use node::{Dispatch, FnNode, Node};
+
+mod graph;
+pub use graph::{Graph, GraphBuilder, GraphError, GraphStats, NodeId, NodeStats};
+
+mod io;
+pub use io::{RxNode, TxNode};
+
+mod pipeline;
+pub use pipeline::Pipeline;
@@ -0,0 +1,100 @@
+use std::marker::PhantomData;
+
+use rpkt_dpdk::Mbuf;
+
+/// A node of the packet processing graph.
+///
+/// The packets of the input batch are moved out of `input` and dispatched to the next
+/// nodes of this node through `out`. The packets that are left in `input` after
+/// `process` returns are dropped and counted as the drops of the node.
+pub trait Node<P = Mbuf> {
+    /// Process a batch of packets.
+    ///
+    /// A source node is called with an empty input every time the graph is walked.
+    fn process(&mut self, input: &mut Vec<P>, out: &mut Dispatch<'_, P>);
+}
+
+/// Dispatches the packets processed by a node to its next nodes.
+///
+/// The next nodes are identified by their indexes, which are assigned in the order
+/// that the edges are added by [`GraphBuilder::connect`](crate::GraphBuilder::connect).
+pub struct Dispatch<'a, P> {
+    nexts: &'a [usize],
+    inputs: &'a mut [Vec<P>],
+    enqueued: u64,
+    dropped: u64,
+}
+
+impl<'a, P> Dispatch<'a, P> {
+    pub(crate) fn new(nexts: &'a [usize], inputs: &'a mut [Vec<P>]) -> Self {
+        Self {
+            nexts,
+            inputs,
+            enqueued: 0,
+            dropped: 0,
+        }
+    }
+
+    /// Returns the number of next nodes.
+    #[inline]
+    pub fn nb_nexts(&self) -> usize {
+        self.nexts.len()
+    }
+
+    /// Send `pkt` to the next node indexed by `next`.
+    ///
+    /// The packet is dropped if `next` is not a valid index.
+    #[inline]
+    pub fn enqueue(&mut self, next: usize, pkt: P) {
+        match self.nexts.get(next) {
+            Some(target) => {
+                self.inputs[*target].push(pkt);
+                self.enqueued += 1;
+            }
+            None => self.drop(pkt),
+        }
+    }
+
+    /// Drop `pkt`, and count it as a drop of the node.
+    #[inline]
+    pub fn drop(&mut self, pkt: P) {
+        std::mem::drop(pkt);
+        self.dropped += 1;
+    }
+
+    pub(crate) fn counters(&self) -> (u64, u64) {
+        (self.enqueued, self.dropped)
+    }
+}
+
+/// A node that processes the packets one by one with a closure.
+///
+/// The closure takes the packet and the [`Dispatch`] of the node, and either
+/// enqueues the packet to a next node or drops it.
+pub struct FnNode<P, F> {
+    f: F,
+    _marker: PhantomData<fn(P)>,
+}
+
+impl<P, F> FnNode<P, F>
+where
+    F: FnMut(P, &mut Dispatch<'_, P>),
+{
+    pub fn new(f: F) -> Self {
+        Self {
+            f,
+            _marker: PhantomData,
+        }
+    }
+}
+
+impl<P, F> Node<P> for FnNode<P, F>
+where
+    F: FnMut(P, &mut Dispatch<'_, P>),
+{
+    fn process(&mut self, input: &mut Vec<P>, out: &mut Dispatch<'_, P>) {
+        for pkt in input.drain(..) {
+            (self.f)(pkt, out);
+        }
+    }
+}
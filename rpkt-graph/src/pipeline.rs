@@ -0,0 +1,96 @@
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{mpsc, Arc};
+use std::thread::JoinHandle;
+
+use rpkt_dpdk::error::{Error, Result};
+use rpkt_dpdk::service;
+
+use crate::graph::{Graph, GraphStats};
+
+/// Runs a copy of a graph on each of a set of lcores.
+///
+/// Each lcore is served by a thread that is bound to the lcore, and walks its own graph
+/// until the pipeline is stopped. The graphs share nothing, so a graph usually polls
+/// the rx and tx queues dedicated to its lcore.
+pub struct Pipeline {
+    stop: Arc<AtomicBool>,
+    workers: Vec<JoinHandle<()>>,
+    stats: Vec<Arc<GraphStats>>,
+}
+
+impl Pipeline {
+    /// Launch a graph on each lcore of `lcores`.
+    ///
+    /// The graph of the i-th lcore is built by `build(i)` on the thread bound to that
+    /// lcore, so that the nodes and their packet buffers are allocated on the local
+    /// numa node.
+    ///
+    /// If an lcore can not be bound, the launched threads are stopped and the error is
+    /// returned.
+    pub fn launch<P, F>(lcores: &[u32], build: F) -> Result<Self>
+    where
+        P: 'static,
+        F: Fn(usize) -> Graph<P> + Send + Sync + 'static,
+    {
+        let build = Arc::new(build);
+        let mut pipeline = Self {
+            stop: Arc::new(AtomicBool::new(false)),
+            workers: Vec::with_capacity(lcores.len()),
+            stats: Vec::with_capacity(lcores.len()),
+        };
+
+        for (idx, lcore_id) in lcores.iter().copied().enumerate() {
+            let (sender, receiver) = mpsc::channel();
+            let stop = pipeline.stop.clone();
+            let build = build.clone();
+            let worker = std::thread::spawn(move || {
+                if let Err(err) = service().lcore_bind(lcore_id) {
+                    let _ = sender.send(Err(err));
+                    return;
+                }
+                let mut graph = build(idx);
+                let _ = sender.send(Ok(graph.stats()));
+                while !stop.load(Ordering::Relaxed) {
+                    graph.walk();
+                }
+            });
+            pipeline.workers.push(worker);
+
+            let res = receiver
+                .recv()
+                .unwrap_or_else(|_| Error::service_err("fail to build the graph").to_err());
+            match res {
+                Ok(stats) => pipeline.stats.push(stats),
+                Err(err) => {
+                    pipeline.shutdown();
+                    return Err(err);
+                }
+            }
+        }
+
+        Ok(pipeline)
+    }
+
+    /// Returns the counters of the graphs, in the order of the lcores.
+    pub fn stats(&self) -> &[Arc<GraphStats>] {
+        &self.stats
+    }
+
+    /// Stop all the graphs and wait for the threads to exit.
+    pub fn stop(mut self) {
+        self.shutdown();
+    }
+
+    fn shutdown(&mut self) {
+        self.stop.store(true, Ordering::Relaxed);
+        for worker in self.workers.drain(..) {
+            let _ = worker.join();
+        }
+    }
+}
+
+impl Drop for Pipeline {
+    fn drop(&mut self) {
+        self.shutdown();
+    }
+}
@@ -0,0 +1,182 @@
+use std::ffi::CString;
+use std::io;
+use std::mem;
+use std::os::fd::{AsRawFd, FromRawFd, OwnedFd};
+
+use crate::{invalid_qid, PortBackend, RxQueueLike, TxQueueLike, BURST_SIZE};
+
+// The largest frame received by an rx queue, which covers the jumbo frames and
+// the frames merged by GRO.
+const RX_BUF_LEN: usize = 65536;
+
+/// A port backed by `AF_PACKET` raw sockets bound to a Linux interface.
+///
+/// The port has a single queue pair, each queue opens its own socket. The rx
+/// queue receives the frames coming in from the interface, not the ones sent
+/// by the host.
+#[derive(Debug, Clone)]
+pub struct AfPacketBackend {
+    ifname: String,
+    ifindex: i32,
+}
+
+impl AfPacketBackend {
+    /// Look up the interface named `ifname`.
+    pub fn new(ifname: &str) -> io::Result<Self> {
+        let name = CString::new(ifname)
+            .map_err(|_| io::Error::new(io::ErrorKind::InvalidInput, "invalid interface name"))?;
+        let ifindex = unsafe { libc::if_nametoindex(name.as_ptr()) };
+        if ifindex == 0 {
+            return Err(io::Error::new(
+                io::ErrorKind::NotFound,
+                format!("no interface named {:?}", ifname),
+            ));
+        }
+        Ok(Self {
+            ifname: ifname.to_string(),
+            ifindex: ifindex as i32,
+        })
+    }
+
+    // Open a non-blocking raw socket bound to the interface.
+    fn open_socket(&self) -> io::Result<OwnedFd> {
+        let protocol = (libc::ETH_P_ALL as u16).to_be();
+        let fd = unsafe {
+            libc::socket(
+                libc::AF_PACKET,
+                libc::SOCK_RAW | libc::SOCK_NONBLOCK | libc::SOCK_CLOEXEC,
+                i32::from(protocol),
+            )
+        };
+        if fd < 0 {
+            return Err(io::Error::last_os_error());
+        }
+        let fd = unsafe { OwnedFd::from_raw_fd(fd) };
+
+        let mut addr: libc::sockaddr_ll = unsafe { mem::zeroed() };
+        addr.sll_family = libc::AF_PACKET as u16;
+        addr.sll_protocol = protocol;
+        addr.sll_ifindex = self.ifindex;
+        let res = unsafe {
+            libc::bind(
+                fd.as_raw_fd(),
+                &addr as *const libc::sockaddr_ll as *const libc::sockaddr,
+                mem::size_of::<libc::sockaddr_ll>() as libc::socklen_t,
+            )
+        };
+        if res < 0 {
+            return Err(io::Error::last_os_error());
+        }
+        Ok(fd)
+    }
+}
+
+impl PortBackend for AfPacketBackend {
+    fn name(&self) -> String {
+        format!("af_packet:{}", self.ifname)
+    }
+
+    fn nb_queues(&self) -> u16 {
+        1
+    }
+
+    fn rx_queue(&mut self, qid: u16) -> io::Result<Box<dyn RxQueueLike>> {
+        if qid != 0 {
+            return Err(invalid_qid(qid));
+        }
+        Ok(Box::new(AfPacketRxQueue {
+            fd: self.open_socket()?,
+            buf: vec![0; RX_BUF_LEN],
+        }))
+    }
+
+    fn tx_queue(&mut self, qid: u16) -> io::Result<Box<dyn TxQueueLike>> {
+        if qid != 0 {
+            return Err(invalid_qid(qid));
+        }
+        Ok(Box::new(AfPacketTxQueue {
+            fd: self.open_socket()?,
+        }))
+    }
+}
+
+/// The rx queue of an [`AfPacketBackend`].
+pub struct AfPacketRxQueue {
+    fd: OwnedFd,
+    buf: Vec<u8>,
+}
+
+impl RxQueueLike for AfPacketRxQueue {
+    fn rx(&mut self, f: &mut dyn FnMut(&[u8])) -> io::Result<usize> {
+        let mut nb_rx = 0;
+        for _ in 0..BURST_SIZE {
+            let mut addr: libc::sockaddr_ll = unsafe { mem::zeroed() };
+            let mut addr_len = mem::size_of::<libc::sockaddr_ll>() as libc::socklen_t;
+            let len = unsafe {
+                libc::recvfrom(
+                    self.fd.as_raw_fd(),
+                    self.buf.as_mut_ptr() as *mut libc::c_void,
+                    self.buf.len(),
+                    0,
+                    &mut addr as *mut libc::sockaddr_ll as *mut libc::sockaddr,
+                    &mut addr_len,
+                )
+            };
+            if len < 0 {
+                let err = io::Error::last_os_error();
+                if err.kind() == io::ErrorKind::WouldBlock {
+                    break;
+                }
+                return Err(err);
+            }
+            // The socket also sees the frames sent by the host.
+            if addr.sll_pkttype == libc::PACKET_OUTGOING {
+                continue;
+            }
+            f(&self.buf[..len as usize]);
+            nb_rx += 1;
+        }
+        Ok(nb_rx)
+    }
+}
+
+/// The tx queue of an [`AfPacketBackend`].
+pub struct AfPacketTxQueue {
+    fd: OwnedFd,
+}
+
+impl TxQueueLike for AfPacketTxQueue {
+    fn tx(&mut self, frames: &[&[u8]]) -> io::Result<usize> {
+        for (nb_tx, frame) in frames.iter().enumerate() {
+            let len = unsafe {
+                libc::send(
+                    self.fd.as_raw_fd(),
+                    frame.as_ptr() as *const libc::c_void,
+                    frame.len(),
+                    0,
+                )
+            };
+            if len < 0 {
+                let err = io::Error::last_os_error();
+                if err.kind() == io::ErrorKind::WouldBlock {
+                    return Ok(nb_tx);
+                }
+                return Err(err);
+            }
+        }
+        Ok(frames.len())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn unknown_interface() {
+        let err = AfPacketBackend::new("rpkt-io-none").unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::NotFound);
+        let err = AfPacketBackend::new("eth\0").unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidInput);
+    }
+}
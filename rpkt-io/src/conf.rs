@@ -0,0 +1,126 @@
+use std::fmt;
+use std::io;
+use std::path::PathBuf;
+use std::str::FromStr;
+
+use crate::{AfPacketBackend, PcapBackend, PortBackend};
+
+/// The selection of a backend and of its port.
+///
+/// It is parsed from and displayed as `<backend>:<args>`, see the
+/// [crate documentation](crate) for the formats.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum BackendConf {
+    Dpdk {
+        port_id: u16,
+        /// The mempool allocating the mbufs of the sent frames.
+        mempool: String,
+    },
+    AfPacket {
+        ifname: String,
+    },
+    Pcap {
+        rx_path: PathBuf,
+        tx_path: Option<PathBuf>,
+    },
+}
+
+impl BackendConf {
+    /// The mempool of the DPDK backend, when it is not specified.
+    pub const DEFAULT_MEMPOOL: &'static str = "mp";
+
+    /// Open the port.
+    ///
+    /// The DPDK backend requires the `dpdk` feature, and a port configured by
+    /// the application.
+    pub fn open(&self) -> io::Result<Box<dyn PortBackend>> {
+        match self {
+            #[cfg(feature = "dpdk")]
+            BackendConf::Dpdk { port_id, mempool } => {
+                Ok(Box::new(crate::DpdkBackend::new(*port_id, mempool)?))
+            }
+            #[cfg(not(feature = "dpdk"))]
+            BackendConf::Dpdk { .. } => Err(io::Error::new(
+                io::ErrorKind::Unsupported,
+                "the dpdk backend requires the `dpdk` feature",
+            )),
+            BackendConf::AfPacket { ifname } => Ok(Box::new(AfPacketBackend::new(ifname)?)),
+            BackendConf::Pcap { rx_path, tx_path } => {
+                Ok(Box::new(PcapBackend::new(rx_path.clone(), tx_path.clone())))
+            }
+        }
+    }
+}
+
+impl FromStr for BackendConf {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (backend, args) = s
+            .split_once(':')
+            .ok_or_else(|| format!("missing the backend of {:?}", s))?;
+        let mut args = args.split(',');
+        let arg = args.next().filter(|arg| !arg.is_empty());
+        let arg = arg.ok_or_else(|| format!("missing the port of {:?}", s))?;
+        let conf = match backend {
+            "dpdk" => BackendConf::Dpdk {
+                port_id: arg
+                    .parse()
+                    .map_err(|_| format!("invalid dpdk port id {:?}", arg))?,
+                mempool: args.next().unwrap_or(Self::DEFAULT_MEMPOOL).to_string(),
+            },
+            "af_packet" => BackendConf::AfPacket {
+                ifname: arg.to_string(),
+            },
+            "pcap" => BackendConf::Pcap {
+                rx_path: arg.into(),
+                tx_path: args.next().map(PathBuf::from),
+            },
+            _ => return Err(format!("unknown backend {:?}", backend)),
+        };
+        match args.next() {
+            Some(extra) => Err(format!("unexpected argument {:?} of {:?}", extra, s)),
+            None => Ok(conf),
+        }
+    }
+}
+
+impl fmt::Display for BackendConf {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            BackendConf::Dpdk { port_id, mempool } => write!(f, "dpdk:{},{}", port_id, mempool),
+            BackendConf::AfPacket { ifname } => write!(f, "af_packet:{}", ifname),
+            BackendConf::Pcap { rx_path, tx_path } => {
+                write!(f, "pcap:{}", rx_path.display())?;
+                if let Some(tx_path) = tx_path {
+                    write!(f, ",{}", tx_path.display())?;
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_and_display() {
+        for (s, display) in [
+            ("dpdk:1", "dpdk:1,mp"),
+            ("dpdk:0,pool", "dpdk:0,pool"),
+            ("af_packet:eth0", "af_packet:eth0"),
+            ("pcap:in.pcap", "pcap:in.pcap"),
+            ("pcap:in.pcap,out.pcap", "pcap:in.pcap,out.pcap"),
+        ] {
+            let conf: BackendConf = s.parse().unwrap();
+            assert_eq!(conf.to_string(), display);
+            assert_eq!(display.parse::<BackendConf>().unwrap(), conf);
+        }
+
+        for s in ["eth0", "dpdk:", "dpdk:x", "xdp:eth0", "af_packet:eth0,eth1"] {
+            assert!(s.parse::<BackendConf>().is_err(), "{}", s);
+        }
+    }
+}
@@ -0,0 +1,110 @@
+use std::io;
+
+use arrayvec::ArrayVec;
+use rpkt_dpdk::{service, Mbuf, Mempool, RxQueue, TxQueue};
+
+use crate::{PortBackend, RxQueueLike, TxQueueLike, BURST_SIZE};
+
+/// A DPDK port, which must be configured and started by the application with
+/// `rpkt_dpdk::service()` before it is opened.
+pub struct DpdkBackend {
+    port_id: u16,
+    mempool: Mempool,
+    mempool_name: String,
+    nb_queues: u16,
+}
+
+impl DpdkBackend {
+    /// Open `port_id`, the mbufs of the sent frames are allocated from the
+    /// mempool named `mempool`.
+    pub fn new(port_id: u16, mempool: &str) -> io::Result<Self> {
+        let port_info = service().port_info(port_id).map_err(dpdk_err)?;
+        Ok(Self {
+            port_id,
+            mempool: service().mempool(mempool).map_err(dpdk_err)?,
+            mempool_name: mempool.to_string(),
+            nb_queues: port_info.max_rx_queues().min(port_info.max_tx_queues()),
+        })
+    }
+}
+
+impl PortBackend for DpdkBackend {
+    fn name(&self) -> String {
+        format!("dpdk:{},{}", self.port_id, self.mempool_name)
+    }
+
+    /// Returns the number of queue pairs supported by the device, which may be
+    /// more than the configured ones.
+    fn nb_queues(&self) -> u16 {
+        self.nb_queues
+    }
+
+    fn rx_queue(&mut self, qid: u16) -> io::Result<Box<dyn RxQueueLike>> {
+        let rxq = service().rx_queue(self.port_id, qid).map_err(dpdk_err)?;
+        Ok(Box::new(DpdkRxQueue { rxq }))
+    }
+
+    fn tx_queue(&mut self, qid: u16) -> io::Result<Box<dyn TxQueueLike>> {
+        let txq = service().tx_queue(self.port_id, qid).map_err(dpdk_err)?;
+        Ok(Box::new(DpdkTxQueue {
+            txq,
+            mempool: self.mempool.clone(),
+        }))
+    }
+}
+
+/// The rx queue of a [`DpdkBackend`].
+pub struct DpdkRxQueue {
+    rxq: RxQueue,
+}
+
+impl RxQueueLike for DpdkRxQueue {
+    fn rx(&mut self, f: &mut dyn FnMut(&[u8])) -> io::Result<usize> {
+        let mut batch = ArrayVec::<Mbuf, BURST_SIZE>::new();
+        let nb_rx = self.rxq.rx(&mut batch);
+        for mbuf in batch.iter() {
+            f(mbuf.data());
+        }
+        Ok(nb_rx)
+    }
+}
+
+/// The tx queue of a [`DpdkBackend`].
+pub struct DpdkTxQueue {
+    txq: TxQueue,
+    mempool: Mempool,
+}
+
+impl TxQueueLike for DpdkTxQueue {
+    fn tx(&mut self, frames: &[&[u8]]) -> io::Result<usize> {
+        let mut nb_tx = 0;
+        for chunk in frames.chunks(BURST_SIZE) {
+            let mut batch = ArrayVec::<Mbuf, BURST_SIZE>::new();
+            for frame in chunk {
+                let mut mbuf = self.mempool.try_alloc().ok_or_else(|| {
+                    io::Error::new(io::ErrorKind::OutOfMemory, "the mempool is exhausted")
+                })?;
+                if frame.len() > mbuf.capacity() {
+                    return Err(io::Error::new(
+                        io::ErrorKind::InvalidInput,
+                        "the frame exceeds the dataroom of the mbuf",
+                    ));
+                }
+                mbuf.extend_from_slice(frame);
+                batch.push(mbuf);
+            }
+
+            // The mbufs left in the batch are freed.
+            let sent = self.txq.tx(&mut batch);
+            nb_tx += sent;
+            if sent < chunk.len() {
+                break;
+            }
+        }
+        Ok(nb_tx)
+    }
+}
+
+fn dpdk_err(err: rpkt_dpdk::error::Error) -> io::Error {
+    io::Error::new(io::ErrorKind::Other, err)
+}
@@ -0,0 +1,101 @@
+//! A backend abstraction for receiving and sending Ethernet frames.
+//!
+//! An application is written against [`PortBackend`], [`RxQueueLike`] and
+//! [`TxQueueLike`], and the backend is selected at runtime with a
+//! [`BackendConf`], e.g. parsed from a command line argument or a config file:
+//!
+//! - `dpdk:<port_id>[,<mempool>]`, a DPDK port configured by the application,
+//!   available with the `dpdk` feature. AF_XDP sockets are reached through
+//!   DPDK, with the `net_af_xdp` virtual device.
+//! - `af_packet:<ifname>`, an `AF_PACKET` raw socket bound to a Linux
+//!   interface, which requires `CAP_NET_RAW`.
+//! - `pcap:<rx_path>[,<tx_path>]`, replaying the frames of a pcap file and
+//!   recording the sent frames into another one.
+//!
+//! The queues work on byte slices, so the frames are copied in and out of the
+//! DPDK mbufs. Applications that need the zero-copy path of DPDK should use
+//! `rpkt-dpdk` directly.
+//!
+//! # Examples
+//! ```no_run
+//! use rpkt_io::BackendConf;
+//!
+//! let conf: BackendConf = std::env::args().nth(1).unwrap().parse().unwrap();
+//! let mut port = conf.open().unwrap();
+//! let mut rxq = port.rx_queue(0).unwrap();
+//! let mut txq = port.tx_queue(0).unwrap();
+//!
+//! let mut frames = Vec::new();
+//! loop {
+//!     rxq.rx(&mut |frame| frames.push(frame.to_vec())).unwrap();
+//!     let refs: Vec<&[u8]> = frames.iter().map(|frame| &frame[..]).collect();
+//!     txq.tx(&refs).unwrap();
+//!     frames.clear();
+//! }
+//! ```
+
+use std::io;
+
+mod conf;
+pub use conf::BackendConf;
+
+mod af_packet;
+pub use af_packet::{AfPacketBackend, AfPacketRxQueue, AfPacketTxQueue};
+
+mod pcap;
+pub use pcap::{PcapBackend, PcapRxQueue, PcapTxQueue};
+
+#[cfg(feature = "dpdk")]
+mod dpdk;
+#[cfg(feature = "dpdk")]
+pub use dpdk::{DpdkBackend, DpdkRxQueue, DpdkTxQueue};
+
+/// The maximum number of frames received by a call to [`RxQueueLike::rx`].
+pub const BURST_SIZE: usize = 32;
+
+/// A port of a backend, which hands out its rx and tx queues.
+pub trait PortBackend {
+    /// Returns the name of the port, in the format of [`BackendConf`].
+    fn name(&self) -> String;
+
+    /// Returns the number of rx and tx queue pairs.
+    fn nb_queues(&self) -> u16;
+
+    /// Returns the rx queue of `qid`.
+    fn rx_queue(&mut self, qid: u16) -> io::Result<Box<dyn RxQueueLike>>;
+
+    /// Returns the tx queue of `qid`.
+    fn tx_queue(&mut self, qid: u16) -> io::Result<Box<dyn TxQueueLike>>;
+}
+
+/// An rx queue of a [`PortBackend`].
+pub trait RxQueueLike {
+    /// Receive at most [`BURST_SIZE`] frames and pass them to `f` one by one,
+    /// returns the number of received frames.
+    ///
+    /// The queue is polled without blocking, 0 is returned if no frame is
+    /// ready.
+    fn rx(&mut self, f: &mut dyn FnMut(&[u8])) -> io::Result<usize>;
+}
+
+/// A tx queue of a [`PortBackend`].
+pub trait TxQueueLike {
+    /// Send the frames in order, returns the number of sent frames.
+    ///
+    /// The queue does not block, the frames behind the first one that can not
+    /// be sent are left to the caller.
+    fn tx(&mut self, frames: &[&[u8]]) -> io::Result<usize>;
+
+    /// Flush the frames buffered by the queue, if any.
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+// Returns the error of a queue id that the port does not have.
+fn invalid_qid(qid: u16) -> io::Error {
+    io::Error::new(
+        io::ErrorKind::InvalidInput,
+        format!("invalid queue id {}", qid),
+    )
+}
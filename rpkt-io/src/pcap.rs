@@ -0,0 +1,213 @@
+use std::fs::File;
+use std::io::{self, BufReader, BufWriter, Read};
+use std::path::PathBuf;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use rpkt::pcap::PcapWriter;
+
+use crate::{invalid_qid, PortBackend, RxQueueLike, TxQueueLike, BURST_SIZE};
+
+const MAGIC_MICROS: u32 = 0xa1b2c3d4;
+const MAGIC_NANOS: u32 = 0xa1b23c4d;
+const GLOBAL_HEADER_LEN: usize = 24;
+const RECORD_HEADER_LEN: usize = 16;
+
+/// A port that replays the frames of a pcap file, and records the sent frames
+/// into another one.
+///
+/// The port has a single queue pair. The rx queue reads the file as fast as it
+/// is polled, regardless of the timestamps, and receives nothing once the
+/// file is exhausted. Without a tx file, the sent frames are discarded.
+#[derive(Debug, Clone)]
+pub struct PcapBackend {
+    rx_path: PathBuf,
+    tx_path: Option<PathBuf>,
+}
+
+impl PcapBackend {
+    pub fn new(rx_path: PathBuf, tx_path: Option<PathBuf>) -> Self {
+        Self { rx_path, tx_path }
+    }
+}
+
+impl PortBackend for PcapBackend {
+    fn name(&self) -> String {
+        match &self.tx_path {
+            Some(tx_path) => format!("pcap:{},{}", self.rx_path.display(), tx_path.display()),
+            None => format!("pcap:{}", self.rx_path.display()),
+        }
+    }
+
+    fn nb_queues(&self) -> u16 {
+        1
+    }
+
+    fn rx_queue(&mut self, qid: u16) -> io::Result<Box<dyn RxQueueLike>> {
+        if qid != 0 {
+            return Err(invalid_qid(qid));
+        }
+        let file = File::open(&self.rx_path)?;
+        Ok(Box::new(PcapRxQueue::new(BufReader::new(file))?))
+    }
+
+    fn tx_queue(&mut self, qid: u16) -> io::Result<Box<dyn TxQueueLike>> {
+        if qid != 0 {
+            return Err(invalid_qid(qid));
+        }
+        let writer = match &self.tx_path {
+            Some(tx_path) => Some(PcapWriter::new(BufWriter::new(File::create(tx_path)?))?),
+            None => None,
+        };
+        Ok(Box::new(PcapTxQueue { writer }))
+    }
+}
+
+/// Reads the frames of a pcap file with microsecond or nanosecond
+/// timestamps, in either byte order.
+pub struct PcapRxQueue<R: Read> {
+    inner: R,
+    big_endian: bool,
+    buf: Vec<u8>,
+}
+
+impl<R: Read> PcapRxQueue<R> {
+    /// Read the global header of the file.
+    ///
+    /// Returns an error if `inner` is not a classic pcap file, e.g. a pcapng
+    /// file.
+    pub fn new(mut inner: R) -> io::Result<Self> {
+        let mut header = [0; GLOBAL_HEADER_LEN];
+        inner.read_exact(&mut header)?;
+        let magic = [header[0], header[1], header[2], header[3]];
+        let big_endian = match (u32::from_le_bytes(magic), u32::from_be_bytes(magic)) {
+            (MAGIC_MICROS | MAGIC_NANOS, _) => false,
+            (_, MAGIC_MICROS | MAGIC_NANOS) => true,
+            _ => {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    "not a classic pcap file",
+                ))
+            }
+        };
+        Ok(Self {
+            inner,
+            big_endian,
+            buf: Vec::new(),
+        })
+    }
+
+    // Read the next record into `buf`, returns `false` at the end of the file.
+    fn read_record(&mut self) -> io::Result<bool> {
+        let mut header = [0; RECORD_HEADER_LEN];
+        match self.inner.read_exact(&mut header) {
+            Ok(()) => {}
+            Err(err) if err.kind() == io::ErrorKind::UnexpectedEof => return Ok(false),
+            Err(err) => return Err(err),
+        }
+        let incl_len = [header[8], header[9], header[10], header[11]];
+        let incl_len = if self.big_endian {
+            u32::from_be_bytes(incl_len)
+        } else {
+            u32::from_le_bytes(incl_len)
+        };
+        self.buf.resize(incl_len as usize, 0);
+        self.inner.read_exact(&mut self.buf)?;
+        Ok(true)
+    }
+}
+
+impl<R: Read> RxQueueLike for PcapRxQueue<R> {
+    fn rx(&mut self, f: &mut dyn FnMut(&[u8])) -> io::Result<usize> {
+        let mut nb_rx = 0;
+        while nb_rx < BURST_SIZE && self.read_record()? {
+            f(&self.buf);
+            nb_rx += 1;
+        }
+        Ok(nb_rx)
+    }
+}
+
+/// Records the sent frames into a pcap file, with the time at which they are
+/// sent.
+pub struct PcapTxQueue {
+    writer: Option<PcapWriter<BufWriter<File>>>,
+}
+
+impl TxQueueLike for PcapTxQueue {
+    fn tx(&mut self, frames: &[&[u8]]) -> io::Result<usize> {
+        if let Some(writer) = self.writer.as_mut() {
+            let ts_micros = SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map_or(0, |ts| ts.as_micros() as u64);
+            for frame in frames {
+                writer.write_frame(ts_micros, frame)?;
+            }
+        }
+        Ok(frames.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        match self.writer.as_mut() {
+            Some(writer) => writer.flush(),
+            None => Ok(()),
+        }
+    }
+}
+
+impl Drop for PcapTxQueue {
+    fn drop(&mut self) {
+        let _ = self.flush();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::BackendConf;
+
+    #[test]
+    fn replay_and_record() {
+        let dir = std::env::temp_dir().join(format!("rpkt-io-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let (in_path, out_path) = (dir.join("in.pcap"), dir.join("out.pcap"));
+
+        let mut writer = PcapWriter::new(File::create(&in_path).unwrap()).unwrap();
+        for i in 0..BURST_SIZE + 3 {
+            writer.write_frame(0, &[i as u8; 60]).unwrap();
+        }
+        drop(writer);
+
+        let conf = BackendConf::Pcap {
+            rx_path: in_path.clone(),
+            tx_path: Some(out_path.clone()),
+        };
+        let mut port = conf.open().unwrap();
+        assert_eq!(port.name(), conf.to_string());
+        assert!(port.rx_queue(1).is_err());
+        let mut rxq = port.rx_queue(0).unwrap();
+        let mut txq = port.tx_queue(0).unwrap();
+
+        // Forward the frames from the rx file into the tx file.
+        let mut frames = Vec::new();
+        let mut nb_rx = Vec::new();
+        loop {
+            let n = rxq.rx(&mut |frame| frames.push(frame.to_vec())).unwrap();
+            if n == 0 {
+                break;
+            }
+            nb_rx.push(n);
+        }
+        assert_eq!(nb_rx, [BURST_SIZE, 3]);
+        let refs: Vec<&[u8]> = frames.iter().map(|frame| &frame[..]).collect();
+        assert_eq!(txq.tx(&refs).unwrap(), frames.len());
+        drop(txq);
+
+        let mut rxq = PcapRxQueue::new(File::open(&out_path).unwrap()).unwrap();
+        let mut recorded = Vec::new();
+        while rxq.rx(&mut |frame| recorded.push(frame.to_vec())).unwrap() > 0 {}
+        assert_eq!(recorded, frames);
+
+        std::fs::remove_dir_all(&dir).unwrap();
+        assert!(PcapRxQueue::new(&[0u8; 24][..]).is_err());
+    }
+}
@@ -0,0 +1,424 @@
+use std::collections::HashMap;
+use std::net::{Ipv4Addr, Ipv6Addr};
+
+use rpkt::dns::{
+    encode_name, DnsQuery, DnsRcode, DnsRdata, DnsResponse, DnsType, DNS_CLASS_ANY, DNS_CLASS_IN,
+    DNS_NAME_LEN_MAX,
+};
+
+use crate::udp::UdpSocket;
+
+/// The maximum number of queries answered by a single call to
+/// [`DnsResponder::poll`].
+const BATCH_SIZE: usize = 32;
+
+/// The maximum length of a received query or a sent response.
+const MAX_MSG_LEN: usize = 4096;
+
+/// The configuration of a [`DnsResponder`].
+#[derive(Debug, Clone, Copy)]
+pub struct DnsResponderConf {
+    /// The address returned for the A queries of unknown names. If it is
+    /// `None`, the queries of unknown names are answered with NXDOMAIN.
+    pub wildcard_v4: Option<Ipv4Addr>,
+    /// The address returned for the AAAA queries of unknown names.
+    pub wildcard_v6: Option<Ipv6Addr>,
+    /// The TTL of the wildcard answers.
+    pub wildcard_ttl: u32,
+}
+
+impl Default for DnsResponderConf {
+    fn default() -> Self {
+        Self {
+            wildcard_v4: None,
+            wildcard_v6: None,
+            wildcard_ttl: 300,
+        }
+    }
+}
+
+/// The counters of a [`DnsResponder`].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct DnsResponderStats {
+    /// The number of received queries, including the malformed ones.
+    pub queries: u64,
+    /// The number of responses with at least one answer.
+    pub answered: u64,
+    /// The number of NOERROR responses without answers.
+    pub nodata: u64,
+    /// The number of NXDOMAIN responses.
+    pub nxdomain: u64,
+    /// The number of responses with the TC bit set.
+    pub truncated: u64,
+    /// The number of queries that are refused or not implemented.
+    pub refused: u64,
+    /// The number of malformed queries, which are dropped.
+    pub malformed: u64,
+    /// The number of responses that fail to be sent.
+    pub tx_errors: u64,
+}
+
+// A record of the table, with the data owned by the responder.
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Record {
+    A(Ipv4Addr),
+    Aaaa(Ipv6Addr),
+    Ns(String),
+    Cname(String),
+    Txt(Vec<u8>),
+}
+
+impl Record {
+    fn from_rdata(rdata: &DnsRdata<'_>) -> Option<Self> {
+        let mut name = [0; DNS_NAME_LEN_MAX];
+        match rdata {
+            DnsRdata::A(addr) => Some(Record::A(*addr)),
+            DnsRdata::Aaaa(addr) => Some(Record::Aaaa(*addr)),
+            DnsRdata::Ns(ns) => encode_name(ns, &mut name).map(|_| Record::Ns(ns.to_string())),
+            DnsRdata::Cname(cname) => {
+                encode_name(cname, &mut name).map(|_| Record::Cname(cname.to_string()))
+            }
+            DnsRdata::Txt(text) => Some(Record::Txt(text.to_vec())),
+            DnsRdata::Soa(_) => None,
+        }
+    }
+
+    fn rdata(&self) -> DnsRdata<'_> {
+        match self {
+            Record::A(addr) => DnsRdata::A(*addr),
+            Record::Aaaa(addr) => DnsRdata::Aaaa(*addr),
+            Record::Ns(ns) => DnsRdata::Ns(ns),
+            Record::Cname(cname) => DnsRdata::Cname(cname),
+            Record::Txt(text) => DnsRdata::Txt(text),
+        }
+    }
+}
+
+/// A DNS server that answers queries from a table of records, without zone
+/// files.
+///
+/// The responder is meant to be the target of DNS load tests. The records are
+/// added with [`DnsResponder::add_record`], and the unknown names can be
+/// answered with a wildcard address instead of NXDOMAIN. The responder is
+/// authoritative for every name, does not follow CNAME records, and does not
+/// support DNS over TCP, so a truncated response can not be retried.
+///
+/// The queries are received from a [`UdpSocket`], which is usually bound to
+/// port 53.
+///
+/// # Examples
+/// ```no_run
+/// use std::net::Ipv4Addr;
+///
+/// use rpkt::dns::DnsRdata;
+/// use rpkt_stack::{DnsResponder, DnsResponderConf, UdpSocket};
+///
+/// # fn socket() -> UdpSocket { unimplemented!() }
+/// let mut responder = DnsResponder::new(socket(), DnsResponderConf::default());
+/// responder.add_record("www.example.com", 60, &DnsRdata::A(Ipv4Addr::new(192, 0, 2, 1)));
+/// loop {
+///     responder.poll();
+/// }
+/// ```
+pub struct DnsResponder {
+    socket: UdpSocket,
+    table: RecordTable,
+    rx_buf: Vec<u8>,
+    tx_buf: Vec<u8>,
+}
+
+impl DnsResponder {
+    /// Create a responder without records that serves the queries received by
+    /// `socket`.
+    pub fn new(socket: UdpSocket, conf: DnsResponderConf) -> Self {
+        Self {
+            socket,
+            table: RecordTable::new(conf),
+            rx_buf: vec![0; MAX_MSG_LEN],
+            tx_buf: vec![0; MAX_MSG_LEN],
+        }
+    }
+
+    /// Returns the socket of the responder.
+    #[inline]
+    pub fn socket(&self) -> &UdpSocket {
+        &self.socket
+    }
+
+    /// Returns the configuration of the responder.
+    #[inline]
+    pub fn conf(&self) -> &DnsResponderConf {
+        &self.table.conf
+    }
+
+    /// Returns the counters of the responder.
+    #[inline]
+    pub fn stats(&self) -> DnsResponderStats {
+        self.table.stats
+    }
+
+    /// Add a record owned by `name`.
+    ///
+    /// Returns `false` if a name is invalid, or if `rdata` is a SOA record,
+    /// which is not supported.
+    pub fn add_record(&mut self, name: &str, ttl: u32, rdata: &DnsRdata<'_>) -> bool {
+        self.table.add_record(name, ttl, rdata)
+    }
+
+    /// Remove all the records owned by `name`, returning whether any record is
+    /// removed.
+    pub fn remove_name(&mut self, name: &str) -> bool {
+        wire_name(name).is_some_and(|key| self.table.records.remove(&key).is_some())
+    }
+
+    /// Remove all the records.
+    pub fn clear(&mut self) {
+        self.table.records.clear();
+    }
+
+    /// Answer the queries received by the socket, returns the number of
+    /// received queries.
+    pub fn poll(&mut self) -> usize {
+        let mut received = 0;
+        while received < BATCH_SIZE {
+            let (len, src) = match self.socket.recv_from(&mut self.rx_buf) {
+                Ok(res) => res,
+                Err(_) => break,
+            };
+            received += 1;
+            if let Some(resp_len) = self.table.respond(&self.rx_buf[..len], &mut self.tx_buf) {
+                if self.socket.send_to(&self.tx_buf[..resp_len], src).is_err() {
+                    self.table.stats.tx_errors += 1;
+                }
+            }
+        }
+        received
+    }
+
+    /// Write the response to the query `msg` into `buf`, returning the length
+    /// of the response, or `None` if the query is malformed and should be
+    /// dropped.
+    ///
+    /// `poll` calls this method for every received query. It can also be used
+    /// to serve the queries received by another socket.
+    pub fn respond(&mut self, msg: &[u8], buf: &mut [u8]) -> Option<usize> {
+        self.table.respond(msg, buf)
+    }
+}
+
+// The records and the counters of a responder.
+struct RecordTable {
+    conf: DnsResponderConf,
+    records: HashMap<Vec<u8>, Vec<(u32, Record)>>,
+    stats: DnsResponderStats,
+}
+
+impl RecordTable {
+    fn new(conf: DnsResponderConf) -> Self {
+        Self {
+            conf,
+            records: HashMap::new(),
+            stats: DnsResponderStats::default(),
+        }
+    }
+
+    fn add_record(&mut self, name: &str, ttl: u32, rdata: &DnsRdata<'_>) -> bool {
+        match (wire_name(name), Record::from_rdata(rdata)) {
+            (Some(key), Some(record)) => {
+                self.records.entry(key).or_default().push((ttl, record));
+                true
+            }
+            _ => false,
+        }
+    }
+
+    fn respond(&mut self, msg: &[u8], buf: &mut [u8]) -> Option<usize> {
+        self.stats.queries += 1;
+        let query = match DnsQuery::parse(msg) {
+            Some(query) => query,
+            None => {
+                self.stats.malformed += 1;
+                return None;
+            }
+        };
+        let rcode = if query.opcode() != 0 {
+            DnsRcode::NOTIMP
+        } else if query.qclass() != DNS_CLASS_IN && query.qclass() != DNS_CLASS_ANY {
+            DnsRcode::REFUSED
+        } else {
+            DnsRcode::NOERROR
+        };
+        let mut resp = match DnsResponse::new(buf, &query, rcode) {
+            Some(resp) => resp,
+            None => {
+                self.stats.malformed += 1;
+                return None;
+            }
+        };
+        if rcode != DnsRcode::NOERROR {
+            self.stats.refused += 1;
+            return Some(resp.finish());
+        }
+        resp.set_authoritative(true);
+
+        let qtype = query.qtype();
+        let mut key = [0; DNS_NAME_LEN_MAX];
+        let key = &mut key[..query.qname().len()];
+        key.copy_from_slice(query.qname());
+        key.make_ascii_lowercase();
+        match self.records.get(&key[..]) {
+            Some(records) => {
+                for (ttl, record) in records {
+                    let rdata = record.rdata();
+                    if (qtype == DnsType::ANY || qtype == rdata.rtype())
+                        && !resp.add_answer(*ttl, &rdata)
+                    {
+                        break;
+                    }
+                }
+            }
+            None if self.conf.wildcard_v4.is_none() && self.conf.wildcard_v6.is_none() => {
+                resp.set_rcode(DnsRcode::NXDOMAIN);
+                self.stats.nxdomain += 1;
+                return Some(resp.finish());
+            }
+            None => {
+                let wildcard = match qtype {
+                    DnsType::A => self.conf.wildcard_v4.map(DnsRdata::A),
+                    DnsType::AAAA => self.conf.wildcard_v6.map(DnsRdata::Aaaa),
+                    _ => None,
+                };
+                // The other types of the wildcard names have no data.
+                if let Some(rdata) = wildcard {
+                    resp.add_answer(self.conf.wildcard_ttl, &rdata);
+                }
+            }
+        }
+
+        if resp.is_truncated() {
+            self.stats.truncated += 1;
+        }
+        if resp.is_empty() {
+            self.stats.nodata += 1;
+        } else {
+            self.stats.answered += 1;
+        }
+        Some(resp.finish())
+    }
+}
+
+// Encode `name` in lowercase wire format, the key of the record table.
+fn wire_name(name: &str) -> Option<Vec<u8>> {
+    let mut buf = [0; DNS_NAME_LEN_MAX];
+    let len = encode_name(name, &mut buf)?;
+    let mut key = buf[..len].to_vec();
+    key.make_ascii_lowercase();
+    Some(key)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn query(id: u16, name: &str, qtype: DnsType) -> Vec<u8> {
+        let mut buf = id.to_be_bytes().to_vec();
+        buf.extend_from_slice(&[0x01, 0x00, 0, 1, 0, 0, 0, 0, 0, 0]);
+        buf.extend_from_slice(&wire_name(name).unwrap());
+        buf.extend_from_slice(&u16::from(qtype).to_be_bytes());
+        buf.extend_from_slice(&DNS_CLASS_IN.to_be_bytes());
+        buf
+    }
+
+    // Returns the rcode and the number of answers of a response.
+    fn respond(table: &mut RecordTable, msg: &[u8]) -> Option<(u8, u16)> {
+        let mut buf = [0; 512];
+        let len = table.respond(msg, &mut buf)?;
+        assert!(len >= 12);
+        assert_eq!(&buf[..2], &msg[..2]);
+        Some((buf[3] & 0xf, u16::from_be_bytes([buf[6], buf[7]])))
+    }
+
+    #[test]
+    fn answers() {
+        let mut table = RecordTable::new(DnsResponderConf::default());
+        let a = DnsRdata::A(Ipv4Addr::new(192, 0, 2, 1));
+        assert!(table.add_record("www.Example.com.", 60, &a));
+        assert!(table.add_record("www.example.com", 60, &DnsRdata::Txt(b"hello")));
+        assert!(table.add_record("example.com", 60, &DnsRdata::Ns("ns.example.com")));
+        assert!(!table.add_record("bad..name", 60, &a));
+        assert!(!table.add_record("example.com", 60, &DnsRdata::Ns("bad..name")));
+
+        // The names are matched regardless of the case.
+        assert_eq!(
+            respond(&mut table, &query(1, "WWW.example.COM", DnsType::A)),
+            Some((0, 1))
+        );
+        assert_eq!(
+            respond(&mut table, &query(2, "www.example.com", DnsType::ANY)),
+            Some((0, 2))
+        );
+        assert_eq!(
+            respond(&mut table, &query(3, "example.com", DnsType::NS)),
+            Some((0, 1))
+        );
+        assert_eq!(
+            respond(&mut table, &query(4, "example.com", DnsType::A)),
+            Some((0, 0))
+        );
+        assert_eq!(
+            respond(&mut table, &query(5, "nx.example.com", DnsType::A)),
+            Some((3, 0))
+        );
+
+        let mut msg = query(6, "www.example.com", DnsType::A);
+        msg[2] |= 2 << 3;
+        assert_eq!(respond(&mut table, &msg), Some((4, 0)));
+        let mut msg = query(7, "www.example.com", DnsType::A);
+        let len = msg.len();
+        msg[len - 1] = 3;
+        assert_eq!(respond(&mut table, &msg), Some((5, 0)));
+        assert_eq!(respond(&mut table, &msg[..len - 1]), None);
+
+        assert_eq!(
+            table.stats,
+            DnsResponderStats {
+                queries: 8,
+                answered: 3,
+                nodata: 1,
+                nxdomain: 1,
+                refused: 2,
+                malformed: 1,
+                ..Default::default()
+            }
+        );
+    }
+
+    #[test]
+    fn wildcard_and_truncation() {
+        let mut table = RecordTable::new(DnsResponderConf {
+            wildcard_v4: Some(Ipv4Addr::new(10, 0, 0, 1)),
+            ..Default::default()
+        });
+        assert_eq!(
+            respond(&mut table, &query(1, "any.test", DnsType::A)),
+            Some((0, 1))
+        );
+        assert_eq!(
+            respond(&mut table, &query(2, "any.test", DnsType::AAAA)),
+            Some((0, 0))
+        );
+
+        for _ in 0..40 {
+            table.add_record("many.test", 1, &DnsRdata::Aaaa(Ipv6Addr::LOCALHOST));
+        }
+        let mut buf = [0; 512];
+        let len = table
+            .respond(&query(3, "many.test", DnsType::AAAA), &mut buf)
+            .unwrap();
+        assert!(len <= 512);
+        assert_eq!(buf[2] & 0x02, 0x02);
+        assert_eq!(table.stats.truncated, 1);
+        assert_eq!(table.stats.answered, 2);
+        assert_eq!(table.stats.nodata, 1);
+    }
+}
@@ -0,0 +1,776 @@
+use std::collections::HashMap;
+
+use rpkt::ether::{EtherType, VlanStack};
+use rpkt::flow::FiveTuple;
+use rpkt_time::{Anchor, Duration, Instant};
+
+/// The prefix of the IPv4-mapped IPv6 addresses stored in a [`FiveTuple`].
+const IPV4_MAPPED_PREFIX: [u8; 12] = [0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0xff, 0xff];
+
+const NETFLOW_V9_VERSION: u16 = 9;
+const NETFLOW_V9_HEADER_LEN: usize = 20;
+const NETFLOW_V9_TEMPLATE_SET_ID: u16 = 0;
+const IPFIX_VERSION: u16 = 10;
+const IPFIX_HEADER_LEN: usize = 16;
+const IPFIX_TEMPLATE_SET_ID: u16 = 2;
+const SET_HEADER_LEN: usize = 4;
+
+const TEMPLATE_ID_IPV4: u16 = 256;
+const TEMPLATE_ID_IPV6: u16 = 257;
+
+// The information elements shared by NetFlow v9 and IPFIX.
+const IE_OCTET_DELTA_COUNT: u16 = 1;
+const IE_PACKET_DELTA_COUNT: u16 = 2;
+const IE_PROTOCOL_IDENTIFIER: u16 = 4;
+const IE_SOURCE_TRANSPORT_PORT: u16 = 7;
+const IE_SOURCE_IPV4_ADDRESS: u16 = 8;
+const IE_DESTINATION_TRANSPORT_PORT: u16 = 11;
+const IE_DESTINATION_IPV4_ADDRESS: u16 = 12;
+const IE_SOURCE_IPV6_ADDRESS: u16 = 27;
+const IE_DESTINATION_IPV6_ADDRESS: u16 = 28;
+// NetFlow v9 timestamps, in milliseconds of system uptime.
+const IE_LAST_SWITCHED: u16 = 21;
+const IE_FIRST_SWITCHED: u16 = 22;
+// IPFIX timestamps, in milliseconds since the UNIX epoch.
+const IE_FLOW_START_MILLISECONDS: u16 = 152;
+const IE_FLOW_END_MILLISECONDS: u16 = 153;
+
+/// The format of the messages emitted by a [`FlowExporter`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ExportFormat {
+    /// NetFlow version 9 (RFC 3954).
+    NetflowV9,
+    /// IPFIX (RFC 7011).
+    Ipfix,
+}
+
+/// The configuration of a [`FlowExporter`].
+#[derive(Debug, Clone, Copy)]
+pub struct FlowExporterConf {
+    /// The format of the exported messages.
+    pub format: ExportFormat,
+    /// The observation domain ID of IPFIX, or the source ID of NetFlow v9.
+    pub observation_domain: u32,
+    /// Account one packet out of every `sampling` packets. The exported
+    /// counters are multiplied by `sampling`, so that they estimate the whole
+    /// traffic.
+    pub sampling: u32,
+    /// A flow that lasts longer than the active timeout is exported, and its
+    /// next packets start a new record.
+    pub active_timeout: Duration,
+    /// A flow without packets for the idle timeout is exported.
+    pub idle_timeout: Duration,
+    /// The interval between two transmissions of the templates.
+    pub template_interval: Duration,
+    /// The maximum number of flows in the cache. The packets of new flows are
+    /// not accounted while the cache is full.
+    pub max_flows: usize,
+    /// The maximum length of an exported message, which should fit in a UDP
+    /// datagram without fragmentation.
+    pub max_msg_len: usize,
+}
+
+impl Default for FlowExporterConf {
+    fn default() -> Self {
+        Self {
+            format: ExportFormat::Ipfix,
+            observation_domain: 0,
+            sampling: 1,
+            active_timeout: Duration::from_secs(60),
+            idle_timeout: Duration::from_secs(15),
+            template_interval: Duration::from_secs(60),
+            max_flows: 65536,
+            max_msg_len: 1400,
+        }
+    }
+}
+
+/// A flow in the cache of a [`FlowExporter`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FlowRecord {
+    /// The 5-tuple of the flow.
+    pub tuple: FiveTuple,
+    /// The number of sampled packets.
+    pub packets: u64,
+    /// The number of bytes of the sampled packets, counted from the IP header.
+    pub bytes: u64,
+    /// The instant of the first packet.
+    pub first: Instant,
+    /// The instant of the last packet.
+    pub last: Instant,
+}
+
+impl FlowRecord {
+    #[inline]
+    fn is_ipv4(&self) -> bool {
+        self.tuple.src_addr[..12] == IPV4_MAPPED_PREFIX
+            && self.tuple.dst_addr[..12] == IPV4_MAPPED_PREFIX
+    }
+}
+
+/// A flow exporter that aggregates packets into flows, and emits the expired
+/// flows as NetFlow v9 or IPFIX messages.
+///
+/// The exporter does not own a socket. [`FlowExporter::export`] passes every
+/// message to a hook, which sends it to the collector, e.g. with a
+/// [`UdpSocket`](crate::UdpSocket) on a DPDK port or with a
+/// `std::net::UdpSocket` through the kernel. The hook is supposed to be called
+/// periodically, e.g. once a second from the rx loop that observes the packets.
+///
+/// # Examples
+/// ```no_run
+/// use rpkt_stack::{FlowExporter, FlowExporterConf};
+/// use rpkt_time::Instant;
+///
+/// let socket = std::net::UdpSocket::bind("0.0.0.0:0").unwrap();
+/// let mut exporter = FlowExporter::new(FlowExporterConf::default());
+///
+/// # let frames: Vec<Vec<u8>> = Vec::new();
+/// for frame in frames.iter() {
+///     exporter.observe(frame, Instant::now());
+/// }
+/// exporter.export(Instant::now(), |msg| {
+///     let _ = socket.send_to(msg, "192.0.2.1:4739");
+/// });
+/// ```
+pub struct FlowExporter {
+    conf: FlowExporterConf,
+    flows: HashMap<FiveTuple, FlowRecord>,
+    anchor: Anchor,
+    start: Instant,
+    next_template: Instant,
+    sample_skip: u32,
+    sequence: u32,
+    dropped: u64,
+    buf: Vec<u8>,
+}
+
+impl FlowExporter {
+    /// Create an exporter with an empty flow cache.
+    ///
+    /// # Panics
+    ///
+    /// This function panics if `conf.sampling` is zero, or if `conf.max_msg_len`
+    /// can not hold the templates.
+    pub fn new(conf: FlowExporterConf) -> Self {
+        assert!(conf.sampling > 0, "invalid sampling interval");
+        assert!(
+            conf.max_msg_len >= header_len(conf.format) + templates_len(),
+            "invalid maximum message length"
+        );
+        let anchor = Anchor::new();
+        Self {
+            conf,
+            flows: HashMap::new(),
+            anchor,
+            start: anchor.instant(),
+            next_template: anchor.instant(),
+            sample_skip: 0,
+            sequence: 0,
+            dropped: 0,
+            buf: Vec::with_capacity(conf.max_msg_len),
+        }
+    }
+
+    /// Returns the configuration of the exporter.
+    #[inline]
+    pub fn conf(&self) -> &FlowExporterConf {
+        &self.conf
+    }
+
+    /// Returns the number of flows in the cache.
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.flows.len()
+    }
+
+    /// Returns whether the flow cache is empty.
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.flows.is_empty()
+    }
+
+    /// Returns an iterator over the flows in the cache.
+    pub fn flows(&self) -> impl Iterator<Item = &FlowRecord> {
+        self.flows.values()
+    }
+
+    /// Returns the number of sampled packets that are not accounted because the
+    /// flow cache is full.
+    #[inline]
+    pub fn dropped(&self) -> u64 {
+        self.dropped
+    }
+
+    /// Observe an Ethernet frame received at `now`, skipping the VLAN tags.
+    ///
+    /// Returns `false` if the frame is not an IP packet.
+    pub fn observe(&mut self, frame: &[u8], now: Instant) -> bool {
+        let stack = match VlanStack::parse(frame) {
+            Some(stack) => stack,
+            None => return false,
+        };
+        match stack.ethertype() {
+            EtherType::IPV4 | EtherType::IPV6 => self.observe_ip(&frame[stack.header_len()..], now),
+            _ => false,
+        }
+    }
+
+    /// Observe an IPv4 or IPv6 packet received at `now`.
+    ///
+    /// Returns `false` if the IP header is malformed.
+    pub fn observe_ip(&mut self, packet: &[u8], now: Instant) -> bool {
+        match FiveTuple::from_ip(packet) {
+            Some(tuple) => {
+                self.account(&tuple, packet.len(), now);
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Account a packet of `len` bytes in the flow `tuple`, subject to sampling.
+    pub fn account(&mut self, tuple: &FiveTuple, len: usize, now: Instant) {
+        if self.sample_skip > 0 {
+            self.sample_skip -= 1;
+            return;
+        }
+        self.sample_skip = self.conf.sampling - 1;
+
+        let full = self.flows.len() >= self.conf.max_flows;
+        let record = match self.flows.get_mut(tuple) {
+            Some(record) => record,
+            None if full => {
+                self.dropped += 1;
+                return;
+            }
+            None => self.flows.entry(*tuple).or_insert(FlowRecord {
+                tuple: *tuple,
+                packets: 0,
+                bytes: 0,
+                first: now,
+                last: now,
+            }),
+        };
+        record.packets += 1;
+        record.bytes += len as u64;
+        record.last = record.last.max(now);
+    }
+
+    /// Export the flows that have reached the active or the idle timeout, and
+    /// the templates if they are due. Returns the number of exported flows.
+    ///
+    /// `sink` is called for every message.
+    pub fn export<F: FnMut(&[u8])>(&mut self, now: Instant, sink: F) -> usize {
+        let (active_timeout, idle_timeout) = (self.conf.active_timeout, self.conf.idle_timeout);
+        let mut expired = Vec::new();
+        self.flows.retain(|_, record| {
+            let keep = now.saturating_cycles_since(record.first) < active_timeout
+                && now.saturating_cycles_since(record.last) < idle_timeout;
+            if !keep {
+                expired.push(*record);
+            }
+            keep
+        });
+        self.send(expired, now, sink)
+    }
+
+    /// Export all the flows in the cache, e.g. before the exporter is shut down.
+    /// Returns the number of exported flows.
+    pub fn flush<F: FnMut(&[u8])>(&mut self, now: Instant, sink: F) -> usize {
+        let flows = self.flows.drain().map(|(_, record)| record).collect();
+        self.send(flows, now, sink)
+    }
+
+    fn send<F: FnMut(&[u8])>(
+        &mut self,
+        mut records: Vec<FlowRecord>,
+        now: Instant,
+        mut sink: F,
+    ) -> usize {
+        let mut templates = now >= self.next_template;
+        if templates {
+            self.next_template = now + self.conf.template_interval;
+        } else if records.is_empty() {
+            return 0;
+        }
+        // Group the records by template to reduce the number of sets.
+        records.sort_by_key(|record| !record.is_ipv4());
+
+        let clock = ExportClock {
+            anchor: self.anchor,
+            start: self.start,
+            now,
+        };
+        let format = self.conf.format;
+        let mut rest = &records[..];
+        while templates || !rest.is_empty() {
+            let mut msg = MsgWriter::new(&mut self.buf, format);
+            if templates {
+                msg.write_templates();
+                templates = false;
+            }
+            let written =
+                msg.write_records(rest, self.conf.sampling, &clock, self.conf.max_msg_len);
+            rest = &rest[written..];
+
+            let sequence = self.sequence;
+            self.sequence = match format {
+                ExportFormat::NetflowV9 => sequence.wrapping_add(1),
+                ExportFormat::Ipfix => sequence.wrapping_add(written as u32),
+            };
+            sink(msg.finish(&clock, sequence, self.conf.observation_domain));
+        }
+        records.len()
+    }
+}
+
+// The time references of an exported message.
+struct ExportClock {
+    anchor: Anchor,
+    start: Instant,
+    now: Instant,
+}
+
+impl ExportClock {
+    fn unix_millis(&self, instant: Instant) -> u64 {
+        self.anchor.instant_to_unix_nanos(instant) / 1_000_000
+    }
+
+    fn uptime_millis(&self, instant: Instant) -> u32 {
+        instant.saturating_cycles_since(self.start).as_millis() as u32
+    }
+}
+
+// Writes a NetFlow v9 or IPFIX message into a reused buffer.
+struct MsgWriter<'a> {
+    buf: &'a mut Vec<u8>,
+    format: ExportFormat,
+    // The number of template and data records, reported by NetFlow v9.
+    count: u16,
+}
+
+impl<'a> MsgWriter<'a> {
+    fn new(buf: &'a mut Vec<u8>, format: ExportFormat) -> Self {
+        buf.clear();
+        buf.resize(header_len(format), 0);
+        Self {
+            buf,
+            format,
+            count: 0,
+        }
+    }
+
+    fn write_templates(&mut self) {
+        let set_id = match self.format {
+            ExportFormat::NetflowV9 => NETFLOW_V9_TEMPLATE_SET_ID,
+            ExportFormat::Ipfix => IPFIX_TEMPLATE_SET_ID,
+        };
+        let set = self.begin_set(set_id);
+        for template_id in [TEMPLATE_ID_IPV4, TEMPLATE_ID_IPV6] {
+            let fields = template_fields(template_id, self.format);
+            self.put_u16(template_id);
+            self.put_u16(fields.len() as u16);
+            for (ie, len) in fields {
+                self.put_u16(ie);
+                self.put_u16(len);
+            }
+            self.count += 1;
+        }
+        self.end_set(set);
+    }
+
+    // Write as many records as possible without exceeding `max_len`, returns the
+    // number of written records.
+    fn write_records(
+        &mut self,
+        records: &[FlowRecord],
+        sampling: u32,
+        clock: &ExportClock,
+        max_len: usize,
+    ) -> usize {
+        let mut set: Option<(u16, usize)> = None;
+        for (idx, record) in records.iter().enumerate() {
+            let template_id = if record.is_ipv4() {
+                TEMPLATE_ID_IPV4
+            } else {
+                TEMPLATE_ID_IPV6
+            };
+            let mut needed = record_len(template_id, self.format) + set_padding_max(self.format);
+            if set.map(|(id, _)| id) != Some(template_id) {
+                needed += SET_HEADER_LEN;
+            }
+            if self.buf.len() + needed > max_len {
+                if let Some((_, start)) = set {
+                    self.end_set(start);
+                }
+                return idx;
+            }
+
+            match set {
+                Some((id, _)) if id == template_id => {}
+                Some((_, start)) => {
+                    self.end_set(start);
+                    set = Some((template_id, self.begin_set(template_id)));
+                }
+                None => set = Some((template_id, self.begin_set(template_id))),
+            }
+            self.write_record(record, sampling, clock);
+        }
+        if let Some((_, start)) = set {
+            self.end_set(start);
+        }
+        records.len()
+    }
+
+    fn write_record(&mut self, record: &FlowRecord, sampling: u32, clock: &ExportClock) {
+        let tuple = &record.tuple;
+        if record.is_ipv4() {
+            self.buf.extend_from_slice(&tuple.src_addr[12..]);
+            self.buf.extend_from_slice(&tuple.dst_addr[12..]);
+        } else {
+            self.buf.extend_from_slice(&tuple.src_addr);
+            self.buf.extend_from_slice(&tuple.dst_addr);
+        }
+        self.put_u16(tuple.src_port);
+        self.put_u16(tuple.dst_port);
+        self.buf.push(tuple.protocol);
+        self.put_u64(record.packets.saturating_mul(u64::from(sampling)));
+        self.put_u64(record.bytes.saturating_mul(u64::from(sampling)));
+        match self.format {
+            ExportFormat::NetflowV9 => {
+                self.put_u32(clock.uptime_millis(record.first));
+                self.put_u32(clock.uptime_millis(record.last));
+            }
+            ExportFormat::Ipfix => {
+                self.put_u64(clock.unix_millis(record.first));
+                self.put_u64(clock.unix_millis(record.last));
+            }
+        }
+        self.count += 1;
+    }
+
+    fn begin_set(&mut self, set_id: u16) -> usize {
+        let start = self.buf.len();
+        self.put_u16(set_id);
+        self.put_u16(0);
+        start
+    }
+
+    fn end_set(&mut self, start: usize) {
+        // NetFlow v9 pads the flowsets to 4-byte boundaries.
+        if self.format == ExportFormat::NetflowV9 {
+            let padded = (self.buf.len() - start + 3) / 4 * 4;
+            self.buf.resize(start + padded, 0);
+        }
+        let len = (self.buf.len() - start) as u16;
+        self.buf[start + 2..start + 4].copy_from_slice(&len.to_be_bytes());
+    }
+
+    fn finish(self, clock: &ExportClock, sequence: u32, domain: u32) -> &'a [u8] {
+        let unix_secs = (clock.anchor.instant_to_unix_nanos(clock.now) / 1_000_000_000) as u32;
+        match self.format {
+            ExportFormat::NetflowV9 => {
+                let header = &mut self.buf[..NETFLOW_V9_HEADER_LEN];
+                header[0..2].copy_from_slice(&NETFLOW_V9_VERSION.to_be_bytes());
+                header[2..4].copy_from_slice(&self.count.to_be_bytes());
+                header[4..8].copy_from_slice(&clock.uptime_millis(clock.now).to_be_bytes());
+                header[8..12].copy_from_slice(&unix_secs.to_be_bytes());
+                header[12..16].copy_from_slice(&sequence.to_be_bytes());
+                header[16..20].copy_from_slice(&domain.to_be_bytes());
+            }
+            ExportFormat::Ipfix => {
+                let len = self.buf.len() as u16;
+                let header = &mut self.buf[..IPFIX_HEADER_LEN];
+                header[0..2].copy_from_slice(&IPFIX_VERSION.to_be_bytes());
+                header[2..4].copy_from_slice(&len.to_be_bytes());
+                header[4..8].copy_from_slice(&unix_secs.to_be_bytes());
+                header[8..12].copy_from_slice(&sequence.to_be_bytes());
+                header[12..16].copy_from_slice(&domain.to_be_bytes());
+            }
+        }
+        self.buf
+    }
+
+    fn put_u16(&mut self, value: u16) {
+        self.buf.extend_from_slice(&value.to_be_bytes());
+    }
+
+    fn put_u32(&mut self, value: u32) {
+        self.buf.extend_from_slice(&value.to_be_bytes());
+    }
+
+    fn put_u64(&mut self, value: u64) {
+        self.buf.extend_from_slice(&value.to_be_bytes());
+    }
+}
+
+fn header_len(format: ExportFormat) -> usize {
+    match format {
+        ExportFormat::NetflowV9 => NETFLOW_V9_HEADER_LEN,
+        ExportFormat::Ipfix => IPFIX_HEADER_LEN,
+    }
+}
+
+// The (information element, length) pairs of a template.
+fn template_fields(template_id: u16, format: ExportFormat) -> [(u16, u16); 9] {
+    let (src, dst, addr_len) = match template_id {
+        TEMPLATE_ID_IPV4 => (IE_SOURCE_IPV4_ADDRESS, IE_DESTINATION_IPV4_ADDRESS, 4),
+        _ => (IE_SOURCE_IPV6_ADDRESS, IE_DESTINATION_IPV6_ADDRESS, 16),
+    };
+    let (first, last, time_len) = match format {
+        ExportFormat::NetflowV9 => (IE_FIRST_SWITCHED, IE_LAST_SWITCHED, 4),
+        ExportFormat::Ipfix => (IE_FLOW_START_MILLISECONDS, IE_FLOW_END_MILLISECONDS, 8),
+    };
+    [
+        (src, addr_len),
+        (dst, addr_len),
+        (IE_SOURCE_TRANSPORT_PORT, 2),
+        (IE_DESTINATION_TRANSPORT_PORT, 2),
+        (IE_PROTOCOL_IDENTIFIER, 1),
+        (IE_PACKET_DELTA_COUNT, 8),
+        (IE_OCTET_DELTA_COUNT, 8),
+        (first, time_len),
+        (last, time_len),
+    ]
+}
+
+fn record_len(template_id: u16, format: ExportFormat) -> usize {
+    template_fields(template_id, format)
+        .iter()
+        .map(|(_, len)| usize::from(*len))
+        .sum()
+}
+
+// The length of the template set holding both templates.
+fn templates_len() -> usize {
+    SET_HEADER_LEN + 2 * (4 + 9 * 4)
+}
+
+fn set_padding_max(format: ExportFormat) -> usize {
+    match format {
+        ExportFormat::NetflowV9 => 3,
+        ExportFormat::Ipfix => 0,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn tuple(src: [u8; 4], dst: [u8; 4]) -> FiveTuple {
+        let mut tuple = FiveTuple {
+            src_port: 1234,
+            dst_port: 53,
+            protocol: 17,
+            ..Default::default()
+        };
+        tuple.src_addr[..12].copy_from_slice(&IPV4_MAPPED_PREFIX);
+        tuple.src_addr[12..].copy_from_slice(&src);
+        tuple.dst_addr[..12].copy_from_slice(&IPV4_MAPPED_PREFIX);
+        tuple.dst_addr[12..].copy_from_slice(&dst);
+        tuple
+    }
+
+    fn tuple_v6(last: u8) -> FiveTuple {
+        let mut tuple = FiveTuple {
+            src_port: 443,
+            dst_port: 50000,
+            protocol: 6,
+            ..Default::default()
+        };
+        tuple.src_addr[..2].copy_from_slice(&[0x20, 0x01]);
+        tuple.src_addr[15] = last;
+        tuple.dst_addr[..2].copy_from_slice(&[0x20, 0x01]);
+        tuple.dst_addr[15] = 1;
+        tuple
+    }
+
+    fn u16_at(buf: &[u8], off: usize) -> u16 {
+        u16::from_be_bytes([buf[off], buf[off + 1]])
+    }
+
+    fn u32_at(buf: &[u8], off: usize) -> u32 {
+        u32::from_be_bytes(buf[off..off + 4].try_into().unwrap())
+    }
+
+    fn u64_at(buf: &[u8], off: usize) -> u64 {
+        u64::from_be_bytes(buf[off..off + 8].try_into().unwrap())
+    }
+
+    // Split a message into its (set ID, set body) pairs.
+    fn sets(msg: &[u8], header_len: usize) -> Vec<(u16, &[u8])> {
+        let mut res = Vec::new();
+        let mut off = header_len;
+        while off < msg.len() {
+            let len = usize::from(u16_at(msg, off + 2));
+            res.push((u16_at(msg, off), &msg[off + SET_HEADER_LEN..off + len]));
+            off += len;
+        }
+        assert_eq!(off, msg.len());
+        res
+    }
+
+    fn collect(exporter: &mut FlowExporter, now: Instant, flush: bool) -> Vec<Vec<u8>> {
+        let mut msgs = Vec::new();
+        let sink = |msg: &[u8]| msgs.push(msg.to_vec());
+        if flush {
+            exporter.flush(now, sink);
+        } else {
+            exporter.export(now, sink);
+        }
+        msgs
+    }
+
+    #[test]
+    fn ipfix_messages() {
+        let mut exporter = FlowExporter::new(FlowExporterConf {
+            observation_domain: 7,
+            sampling: 2,
+            ..Default::default()
+        });
+        let now = Instant::now();
+        let a = tuple([10, 0, 0, 1], [10, 0, 0, 2]);
+        let b = tuple_v6(2);
+        for _ in 0..4 {
+            exporter.account(&a, 100, now);
+            exporter.account(&b, 1000, now);
+        }
+        // The sampling picks every other packet, which are all in flow `a`.
+        assert_eq!(exporter.len(), 1);
+        exporter.account(&b, 1000, now);
+        assert_eq!(exporter.len(), 2);
+
+        // The templates are sent without any flows at the start.
+        let msgs = collect(&mut exporter, now, false);
+        assert_eq!(msgs.len(), 1);
+        let msg = &msgs[0];
+        assert_eq!(u16_at(msg, 0), IPFIX_VERSION);
+        assert_eq!(usize::from(u16_at(msg, 2)), msg.len());
+        assert_eq!(u32_at(msg, 8), 0);
+        assert_eq!(u32_at(msg, 12), 7);
+        let res = sets(msg, IPFIX_HEADER_LEN);
+        assert_eq!(res.len(), 1);
+        assert_eq!(res[0].0, IPFIX_TEMPLATE_SET_ID);
+        assert_eq!(u16_at(res[0].1, 0), TEMPLATE_ID_IPV4);
+        assert_eq!(u16_at(res[0].1, 2), 9);
+        assert_eq!(u16_at(res[0].1, 4), IE_SOURCE_IPV4_ADDRESS);
+
+        // Nothing is due before the idle timeout.
+        assert!(collect(&mut exporter, now + Duration::from_secs(1), false).is_empty());
+
+        let t = now + Duration::from_secs(15);
+        let msgs = collect(&mut exporter, t, false);
+        assert!(exporter.is_empty());
+        assert_eq!(msgs.len(), 1);
+        let msg = &msgs[0];
+        assert_eq!(u32_at(msg, 8), 0);
+        let res = sets(msg, IPFIX_HEADER_LEN);
+        assert_eq!(res.len(), 2);
+        assert_eq!(res[0].0, TEMPLATE_ID_IPV4);
+        assert_eq!(
+            res[0].1.len(),
+            record_len(TEMPLATE_ID_IPV4, ExportFormat::Ipfix)
+        );
+        let rec = res[0].1;
+        assert_eq!(&rec[0..8], &[10, 0, 0, 1, 10, 0, 0, 2]);
+        assert_eq!((u16_at(rec, 8), u16_at(rec, 10), rec[12]), (1234, 53, 17));
+        // The counters are scaled by the sampling interval.
+        assert_eq!(u64_at(rec, 13), 8);
+        assert_eq!(u64_at(rec, 21), 800);
+        let start = exporter.anchor.instant_to_unix_nanos(now) / 1_000_000;
+        assert_eq!(u64_at(rec, 29), start);
+        assert_eq!(u64_at(rec, 37), start);
+
+        assert_eq!(res[1].0, TEMPLATE_ID_IPV6);
+        assert_eq!(
+            res[1].1.len(),
+            record_len(TEMPLATE_ID_IPV6, ExportFormat::Ipfix)
+        );
+        assert_eq!(&res[1].1[..16], &b.src_addr);
+        assert_eq!(u64_at(res[1].1, 45), 2000);
+
+        // The sequence number counts the data records.
+        exporter.account(&a, 100, t);
+        exporter.account(&a, 100, t);
+        let msgs = collect(&mut exporter, t, true);
+        assert_eq!(u32_at(&msgs[0], 8), 2);
+    }
+
+    #[test]
+    fn netflow_v9_messages() {
+        let mut exporter = FlowExporter::new(FlowExporterConf {
+            format: ExportFormat::NetflowV9,
+            max_msg_len: 200,
+            ..Default::default()
+        });
+        let now = exporter.start;
+        for i in 0..10 {
+            exporter.account(&tuple([10, 0, 0, i], [10, 0, 1, 1]), 64, now);
+        }
+        let t = now + Duration::from_secs(60);
+        exporter.account(&tuple_v6(9), 1500, t);
+
+        // The active timeout expires the first flows.
+        let msgs = collect(&mut exporter, t, false);
+        assert_eq!(exporter.len(), 1);
+        let uptime = Duration::from_secs(60).as_millis() as u32;
+        let mut records = 0;
+        for (seq, msg) in msgs.iter().enumerate() {
+            assert!(msg.len() <= 200);
+            assert_eq!(u16_at(msg, 0), NETFLOW_V9_VERSION);
+            assert_eq!(u32_at(msg, 4), uptime);
+            assert_eq!(u32_at(msg, 12), seq as u32);
+            let mut count = 0;
+            for (id, body) in sets(msg, NETFLOW_V9_HEADER_LEN) {
+                assert_eq!((body.len() + SET_HEADER_LEN) % 4, 0);
+                let rec_len = record_len(TEMPLATE_ID_IPV4, ExportFormat::NetflowV9);
+                match id {
+                    NETFLOW_V9_TEMPLATE_SET_ID => count += 2,
+                    TEMPLATE_ID_IPV4 => {
+                        let n = body.len() / rec_len;
+                        for rec in body.chunks_exact(rec_len).take(n) {
+                            assert_eq!(u64_at(rec, 13), 1);
+                            assert_eq!(u32_at(rec, 29), 0);
+                            assert_eq!(u32_at(rec, 33), 0);
+                        }
+                        count += n;
+                        records += n;
+                    }
+                    id => panic!("unexpected set {}", id),
+                }
+            }
+            assert_eq!(usize::from(u16_at(msg, 2)), count);
+        }
+        assert_eq!(records, 10);
+        assert_eq!(u32_at(&msgs[0], 16), 0);
+        assert_eq!(
+            sets(&msgs[0], NETFLOW_V9_HEADER_LEN)[0].0,
+            NETFLOW_V9_TEMPLATE_SET_ID
+        );
+
+        let msgs = collect(&mut exporter, t, true);
+        assert_eq!(msgs.len(), 1);
+        let res = sets(&msgs[0], NETFLOW_V9_HEADER_LEN);
+        assert_eq!(res.len(), 1);
+        assert_eq!(res[0].0, TEMPLATE_ID_IPV6);
+        let rec = res[0].1;
+        assert_eq!(u64_at(rec, 45), 1500);
+        assert_eq!(u32_at(rec, 53), uptime);
+    }
+
+    #[test]
+    fn full_cache() {
+        let mut exporter = FlowExporter::new(FlowExporterConf {
+            max_flows: 1,
+            ..Default::default()
+        });
+        let now = Instant::now();
+        exporter.account(&tuple([1, 1, 1, 1], [2, 2, 2, 2]), 64, now);
+        exporter.account(&tuple([1, 1, 1, 2], [2, 2, 2, 2]), 64, now);
+        exporter.account(&tuple([1, 1, 1, 1], [2, 2, 2, 2]), 64, now);
+        assert_eq!(exporter.len(), 1);
+        assert_eq!(exporter.dropped(), 1);
+        assert_eq!(exporter.flows().next().unwrap().packets, 2);
+    }
+}
@@ -0,0 +1,208 @@
+use std::collections::HashMap;
+use std::net::Ipv4Addr;
+
+use rpkt_time::{Duration, Instant};
+
+/// The maximum size of a reassembled IPv4 payload.
+const MAX_PAYLOAD_LEN: usize = 65535 - 20;
+
+/// The maximum number of datagrams that are reassembled at the same time.
+const MAX_FLOWS: usize = 64;
+
+/// A fragmented datagram is identified by its addresses, protocol and
+/// identification field.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub(crate) struct FragKey {
+    pub src: Ipv4Addr,
+    pub dst: Ipv4Addr,
+    pub protocol: u8,
+    pub ident: u16,
+}
+
+struct Flow {
+    data: Vec<u8>,
+    // Sorted and non-overlapping byte ranges that have been received.
+    ranges: Vec<(usize, usize)>,
+    // The payload length, known once the last fragment arrives.
+    total: Option<usize>,
+    deadline: Instant,
+}
+
+impl Flow {
+    fn insert(&mut self, offset: usize, data: &[u8]) {
+        let end = offset + data.len();
+        if self.data.len() < end {
+            self.data.resize(end, 0);
+        }
+        self.data[offset..end].copy_from_slice(data);
+
+        let (mut start, mut end) = (offset, end);
+        self.ranges.retain(|&(s, e)| {
+            if e < start || s > end {
+                true
+            } else {
+                start = start.min(s);
+                end = end.max(e);
+                false
+            }
+        });
+        let pos = self.ranges.partition_point(|&(s, _)| s < start);
+        self.ranges.insert(pos, (start, end));
+    }
+
+    fn is_complete(&self) -> bool {
+        match self.total {
+            Some(total) => self.ranges.len() == 1 && self.ranges[0] == (0, total),
+            None => false,
+        }
+    }
+}
+
+/// Reassembles fragmented IPv4 payloads.
+pub(crate) struct Reassembler {
+    flows: HashMap<FragKey, Flow>,
+    timeout: Duration,
+}
+
+impl Reassembler {
+    pub fn new(timeout: Duration) -> Self {
+        Self {
+            flows: HashMap::new(),
+            timeout,
+        }
+    }
+
+    /// Feed a fragment carrying `data` at byte `offset` of the payload.
+    ///
+    /// Returns the reassembled payload once all the fragments are received.
+    /// Malformed fragments, and fragments that exceed the flow limit, are
+    /// dropped.
+    pub fn insert(
+        &mut self,
+        key: FragKey,
+        offset: usize,
+        more_frags: bool,
+        data: &[u8],
+        now: Instant,
+    ) -> Option<Vec<u8>> {
+        let end = offset + data.len();
+        if end > MAX_PAYLOAD_LEN || (more_frags && data.len() & 7 != 0) {
+            return None;
+        }
+        if !self.flows.contains_key(&key) && self.flows.len() >= MAX_FLOWS {
+            return None;
+        }
+
+        let timeout = self.timeout;
+        let flow = self.flows.entry(key).or_insert_with(|| Flow {
+            data: Vec::new(),
+            ranges: Vec::new(),
+            total: None,
+            deadline: now + timeout,
+        });
+
+        if !more_frags {
+            if flow.total.is_some_and(|total| total != end) {
+                // Conflicting last fragments, give up the datagram.
+                self.flows.remove(&key);
+                return None;
+            }
+            flow.total = Some(end);
+        }
+        if flow.total.is_some_and(|total| end > total) {
+            self.flows.remove(&key);
+            return None;
+        }
+
+        flow.insert(offset, data);
+        if flow.is_complete() {
+            self.flows.remove(&key).map(|flow| flow.data)
+        } else {
+            None
+        }
+    }
+
+    /// Drop the datagrams that are not completed before their deadline.
+    pub fn expire(&mut self, now: Instant) {
+        self.flows.retain(|_, flow| now < flow.deadline);
+    }
+}
+
+/// Returns the `(offset, len)` of each fragment when an IPv4 payload of
+/// `payload_len` bytes is sent over a link of `mtu` bytes.
+pub(crate) fn fragments(payload_len: usize, mtu: usize) -> impl Iterator<Item = (usize, usize)> {
+    // Every fragment except the last one carries a multiple of 8 bytes.
+    let frag_len = (mtu - rpkt::ipv4::IPV4_HEADER_LEN) & !7;
+    let count = payload_len.saturating_sub(1) / frag_len + 1;
+    (0..count).map(move |i| {
+        let offset = i * frag_len;
+        (offset, frag_len.min(payload_len - offset))
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const KEY: FragKey = FragKey {
+        src: Ipv4Addr::new(10, 0, 0, 1),
+        dst: Ipv4Addr::new(10, 0, 0, 2),
+        protocol: 17,
+        ident: 7,
+    };
+
+    #[test]
+    fn fragment_offsets() {
+        assert_eq!(fragments(100, 1500).collect::<Vec<_>>(), [(0, 100)]);
+        assert_eq!(fragments(0, 1500).collect::<Vec<_>>(), [(0, 0)]);
+        assert_eq!(
+            fragments(3000, 1500).collect::<Vec<_>>(),
+            [(0, 1480), (1480, 1480), (2960, 40)]
+        );
+        // 1000 - 20 is rounded down to a multiple of 8.
+        assert_eq!(
+            fragments(2000, 1000).collect::<Vec<_>>(),
+            [(0, 976), (976, 976), (1952, 48)]
+        );
+    }
+
+    #[test]
+    fn reassemble_out_of_order() {
+        let payload: Vec<u8> = (0..3000).map(|i| i as u8).collect();
+        let now = Instant::now();
+        let mut reasm = Reassembler::new(Duration::from_secs(1));
+
+        let frags: Vec<_> = fragments(payload.len(), 1500).collect();
+        for &(offset, len) in frags[1..].iter().rev() {
+            let more = offset + len < payload.len();
+            let res = reasm.insert(KEY, offset, more, &payload[offset..offset + len], now);
+            assert_eq!(res, None);
+        }
+        // Duplicated fragments are tolerated.
+        assert_eq!(
+            reasm.insert(KEY, 1480, true, &payload[1480..2960], now),
+            None
+        );
+        assert_eq!(reasm.flows.len(), 1);
+
+        let res = reasm.insert(KEY, 0, true, &payload[..1480], now);
+        assert_eq!(res.as_deref(), Some(&payload[..]));
+        assert_eq!(reasm.flows.len(), 0);
+    }
+
+    #[test]
+    fn reassemble_timeout() {
+        let now = Instant::now();
+        let mut reasm = Reassembler::new(Duration::from_secs(1));
+
+        assert_eq!(reasm.insert(KEY, 0, true, &[0; 8], now), None);
+        // A fragment whose length is not a multiple of 8 is dropped.
+        assert_eq!(reasm.insert(KEY, 8, true, &[0; 5], now), None);
+        reasm.expire(now);
+        assert_eq!(reasm.flows.len(), 1);
+
+        reasm.expire(now + Duration::from_secs(2));
+        assert_eq!(reasm.flows.len(), 0);
+        assert_eq!(reasm.insert(KEY, 8, false, &[0; 4], now), None);
+    }
+}
@@ -0,0 +1,61 @@
+use std::net::Ipv4Addr;
+
+use rpkt::ether::MacAddr;
+use rpkt::neigh::NeighConfig;
+use rpkt_time::Duration;
+
+/// The configuration of the network interface that a socket is bound to.
+#[derive(Debug, Clone, Copy)]
+pub struct IfaceConf {
+    /// The MAC address of the DPDK port.
+    pub mac: MacAddr,
+    /// The IPv4 address of the interface.
+    pub ip: Ipv4Addr,
+    /// The prefix length of the directly connected subnet.
+    pub prefix_len: u8,
+    /// The default gateway for destinations outside of the subnet.
+    pub gateway: Option<Ipv4Addr>,
+    /// The IP MTU, excluding the Ethernet header.
+    pub mtu: usize,
+    /// The time-to-live of the sent packets.
+    pub ttl: u8,
+    /// How long an incomplete fragmented datagram is kept for reassembly.
+    pub reassembly_timeout: Duration,
+    /// The configuration of the ARP cache.
+    pub neigh: NeighConfig,
+}
+
+impl IfaceConf {
+    /// Create a configuration with the default MTU of 1500 bytes, no gateway
+    /// and default timers.
+    pub fn new(mac: MacAddr, ip: Ipv4Addr, prefix_len: u8) -> Self {
+        Self {
+            mac,
+            ip,
+            prefix_len,
+            gateway: None,
+            mtu: 1500,
+            ttl: 64,
+            reassembly_timeout: Duration::from_secs(30),
+            neigh: NeighConfig::default(),
+        }
+    }
+
+    /// Returns whether `addr` is in the directly connected subnet.
+    pub fn is_local(&self, addr: Ipv4Addr) -> bool {
+        let mask = u32::MAX
+            .checked_shl(32 - u32::from(self.prefix_len.min(32)))
+            .unwrap_or(0);
+        u32::from(addr) & mask == u32::from(self.ip) & mask
+    }
+
+    /// Returns the IP address whose MAC address is used to reach `dst`, or
+    /// `None` if `dst` is not reachable.
+    pub fn next_hop(&self, dst: Ipv4Addr) -> Option<Ipv4Addr> {
+        if self.is_local(dst) {
+            Some(dst)
+        } else {
+            self.gateway
+        }
+    }
+}
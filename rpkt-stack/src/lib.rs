@@ -0,0 +1,52 @@
+//! An experimental network stack built on top of `rpkt` and `rpkt-dpdk`.
+//!
+//! The stack provides a [`UdpSocket`] that sends and receives UDP datagrams
+//! through a pair of DPDK rx/tx queues. ARP resolution, IPv4 checksums and
+//! IPv4 fragmentation are handled internally, so the socket can be used
+//! similarly to `std::net::UdpSocket` in non-blocking mode.
+//!
+//! The stack is single-threaded and poll-driven: no background thread is
+//! spawned, and the socket only makes progress when [`UdpSocket::poll`],
+//! [`UdpSocket::recv_from`] or [`UdpSocket::send_to`] is called.
+//!
+//! This crate is experimental, and its API may change at any time.
+
+mod iface;
+pub use iface::IfaceConf;
+
+mod frag;
+mod wire;
+
+mod udp;
+pub use udp::UdpSocket;
+
+mod switch;
+pub use switch::{L2Switch, MacEntry, MacTable, SwitchConf, SwitchPortStats, Verdict};
+
+mod snoop;
+pub use snoop::{GroupEntry, GroupTable, SnoopConf};
+
+mod lpm;
+pub use lpm::{Lpm, LpmAddr};
+
+mod router;
+pub use router::{NextHop, Router, RouterPortConf, RouterPortStats};
+
+mod export;
+pub use export::{ExportFormat, FlowExporter, FlowExporterConf, FlowRecord};
+
+mod dns;
+pub use dns::{DnsResponder, DnsResponderConf, DnsResponderStats};
+
+mod trace;
+pub use trace::{TraceConf, TraceHop, TraceProto, TraceReply, TraceReplyKind, Traceroute};
+
+mod sni;
+pub use sni::{
+    FlowEntry, FlowTable, HashRing, SniBackendStats, SniBalancer, SniBalancerConf, SniBalancerStats,
+};
+
+mod topology;
+pub use topology::{
+    DiscoveryProto, Neighbor, TopologyChange, TopologyCollector, TopologyConf, TopologyTable,
+};
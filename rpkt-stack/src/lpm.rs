@@ -0,0 +1,235 @@
+use std::marker::PhantomData;
+use std::net::{Ipv4Addr, Ipv6Addr};
+
+/// An address type that can be used as the key of an [`Lpm`] table.
+pub trait LpmAddr: Copy {
+    /// The number of bits of the address.
+    const BITS: u8;
+
+    /// Returns the bit at `idx`, counting from the most significant bit.
+    fn bit(&self, idx: u8) -> bool;
+}
+
+impl LpmAddr for Ipv4Addr {
+    const BITS: u8 = 32;
+
+    #[inline]
+    fn bit(&self, idx: u8) -> bool {
+        (u32::from(*self) >> (31 - idx)) & 1 == 1
+    }
+}
+
+impl LpmAddr for Ipv6Addr {
+    const BITS: u8 = 128;
+
+    #[inline]
+    fn bit(&self, idx: u8) -> bool {
+        (u128::from(*self) >> (127 - idx)) & 1 == 1
+    }
+}
+
+struct TrieNode<V> {
+    children: [Option<u32>; 2],
+    value: Option<V>,
+}
+
+impl<V> TrieNode<V> {
+    fn new() -> Self {
+        Self {
+            children: [None, None],
+            value: None,
+        }
+    }
+}
+
+/// A longest prefix match table, mapping IP prefixes to values of type `V`.
+///
+/// The table is a binary trie whose nodes are stored in a single vector, so a lookup
+/// walks at most `A::BITS` nodes without chasing heap pointers. The nodes of removed
+/// prefixes are kept for reuse by later insertions.
+///
+/// # Examples
+/// ```
+/// use std::net::Ipv4Addr;
+///
+/// use rpkt_stack::Lpm;
+///
+/// let mut lpm = Lpm::new();
+/// lpm.insert(Ipv4Addr::new(10, 0, 0, 0), 8, "default");
+/// lpm.insert(Ipv4Addr::new(10, 1, 0, 0), 16, "specific");
+///
+/// assert_eq!(lpm.lookup(Ipv4Addr::new(10, 1, 2, 3)), Some(&"specific"));
+/// assert_eq!(lpm.lookup(Ipv4Addr::new(10, 2, 0, 1)), Some(&"default"));
+/// assert_eq!(lpm.lookup(Ipv4Addr::new(11, 0, 0, 1)), None);
+/// ```
+pub struct Lpm<A, V> {
+    nodes: Vec<TrieNode<V>>,
+    len: usize,
+    _addr: PhantomData<A>,
+}
+
+impl<A: LpmAddr, V> Lpm<A, V> {
+    /// Create an empty table.
+    pub fn new() -> Self {
+        Self {
+            nodes: vec![TrieNode::new()],
+            len: 0,
+            _addr: PhantomData,
+        }
+    }
+
+    /// Returns the number of prefixes in the table.
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    /// Returns whether the table is empty.
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Insert the prefix `addr/prefix_len`, returning the value previously associated
+    /// with the prefix.
+    ///
+    /// The bits of `addr` beyond `prefix_len` are ignored.
+    ///
+    /// # Panics
+    ///
+    /// This function panics if `prefix_len` exceeds the number of bits of `A`.
+    pub fn insert(&mut self, addr: A, prefix_len: u8, value: V) -> Option<V> {
+        assert!(
+            prefix_len <= A::BITS,
+            "invalid prefix length: {}",
+            prefix_len
+        );
+        let mut idx = 0;
+        for bit in 0..prefix_len {
+            let branch = usize::from(addr.bit(bit));
+            idx = match self.nodes[idx].children[branch] {
+                Some(child) => child as usize,
+                None => {
+                    let child = self.nodes.len();
+                    self.nodes.push(TrieNode::new());
+                    self.nodes[idx].children[branch] = Some(child as u32);
+                    child
+                }
+            };
+        }
+
+        let prev = self.nodes[idx].value.replace(value);
+        if prev.is_none() {
+            self.len += 1;
+        }
+        prev
+    }
+
+    /// Remove the prefix `addr/prefix_len`, returning its value.
+    pub fn remove(&mut self, addr: A, prefix_len: u8) -> Option<V> {
+        let idx = self.find(addr, prefix_len)?;
+        let prev = self.nodes[idx].value.take();
+        if prev.is_some() {
+            self.len -= 1;
+        }
+        prev
+    }
+
+    /// Returns the value of the prefix `addr/prefix_len`, which must match exactly.
+    pub fn get(&self, addr: A, prefix_len: u8) -> Option<&V> {
+        let idx = self.find(addr, prefix_len)?;
+        self.nodes[idx].value.as_ref()
+    }
+
+    /// Returns the value of the longest prefix that contains `addr`.
+    pub fn lookup(&self, addr: A) -> Option<&V> {
+        let mut node = &self.nodes[0];
+        let mut found = node.value.as_ref();
+        for bit in 0..A::BITS {
+            match node.children[usize::from(addr.bit(bit))] {
+                Some(child) => node = &self.nodes[child as usize],
+                None => break,
+            }
+            if node.value.is_some() {
+                found = node.value.as_ref();
+            }
+        }
+        found
+    }
+
+    /// Remove all the prefixes.
+    pub fn clear(&mut self) {
+        self.nodes.truncate(1);
+        self.nodes[0] = TrieNode::new();
+        self.len = 0;
+    }
+
+    fn find(&self, addr: A, prefix_len: u8) -> Option<usize> {
+        if prefix_len > A::BITS {
+            return None;
+        }
+        let mut idx = 0;
+        for bit in 0..prefix_len {
+            idx = self.nodes[idx].children[usize::from(addr.bit(bit))]? as usize;
+        }
+        Some(idx)
+    }
+}
+
+impl<A: LpmAddr, V> Default for Lpm<A, V> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn ipv4_longest_match() {
+        let mut lpm = Lpm::new();
+        assert!(lpm.is_empty());
+        assert_eq!(lpm.insert(Ipv4Addr::UNSPECIFIED, 0, 0), None);
+        assert_eq!(lpm.insert(Ipv4Addr::new(192, 168, 0, 0), 16, 16), None);
+        // The host bits are ignored.
+        assert_eq!(lpm.insert(Ipv4Addr::new(192, 168, 1, 77), 24, 24), None);
+        assert_eq!(lpm.insert(Ipv4Addr::new(192, 168, 1, 1), 32, 32), None);
+        assert_eq!(lpm.len(), 4);
+
+        assert_eq!(lpm.lookup(Ipv4Addr::new(192, 168, 1, 1)), Some(&32));
+        assert_eq!(lpm.lookup(Ipv4Addr::new(192, 168, 1, 2)), Some(&24));
+        assert_eq!(lpm.lookup(Ipv4Addr::new(192, 168, 2, 1)), Some(&16));
+        assert_eq!(lpm.lookup(Ipv4Addr::new(8, 8, 8, 8)), Some(&0));
+        assert_eq!(lpm.get(Ipv4Addr::new(192, 168, 1, 0), 24), Some(&24));
+        assert_eq!(lpm.get(Ipv4Addr::new(192, 168, 1, 0), 23), None);
+
+        // Replacing a prefix keeps the length.
+        assert_eq!(lpm.insert(Ipv4Addr::new(192, 168, 1, 0), 24, 240), Some(24));
+        assert_eq!(lpm.len(), 4);
+
+        assert_eq!(lpm.remove(Ipv4Addr::new(192, 168, 1, 0), 24), Some(240));
+        assert_eq!(lpm.remove(Ipv4Addr::new(192, 168, 1, 0), 24), None);
+        assert_eq!(lpm.lookup(Ipv4Addr::new(192, 168, 1, 2)), Some(&16));
+        assert_eq!(lpm.lookup(Ipv4Addr::new(192, 168, 1, 1)), Some(&32));
+        assert_eq!(lpm.len(), 3);
+
+        lpm.clear();
+        assert!(lpm.is_empty());
+        assert_eq!(lpm.lookup(Ipv4Addr::new(8, 8, 8, 8)), None);
+    }
+
+    #[test]
+    fn ipv6_longest_match() {
+        let mut lpm = Lpm::new();
+        lpm.insert("2001:db8::".parse::<Ipv6Addr>().unwrap(), 32, 'a');
+        lpm.insert("2001:db8:1::".parse().unwrap(), 48, 'b');
+        lpm.insert("2001:db8:1::1".parse().unwrap(), 128, 'c');
+
+        let lookup = |addr: &str| lpm.lookup(addr.parse().unwrap()).copied();
+        assert_eq!(lookup("2001:db8:1::1"), Some('c'));
+        assert_eq!(lookup("2001:db8:1::2"), Some('b'));
+        assert_eq!(lookup("2001:db8:2::1"), Some('a'));
+        assert_eq!(lookup("2001:db9::1"), None);
+    }
+}
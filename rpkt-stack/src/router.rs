@@ -0,0 +1,1262 @@
+use std::collections::VecDeque;
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr};
+
+use arrayvec::ArrayVec;
+use rpkt::arp::Operation;
+use rpkt::ether::*;
+use rpkt::icmpv4::{IcmpType, Icmpv4Packet, ICMPV4_ERROR_QUOTE_MAX, ICMPV4_HEADER_LEN};
+use rpkt::icmpv6::{Icmpv6Msg, Icmpv6Packet, ICMPV6_ERROR_QUOTE_MAX};
+use rpkt::ipv4::{IpProtocol, Ipv4Header, Ipv4Packet, IPV4_HEADER_LEN, IPV4_HEADER_TEMPLATE};
+use rpkt::ipv6::{Ipv6Header, Ipv6Packet, IPV6_HEADER_LEN};
+use rpkt::neigh::{NeighCache, NeighConfig};
+use rpkt::{Buf, ChecksumAccumulator, Cursor, CursorMut};
+use rpkt_dpdk::{Mbuf, Mempool, RxQueue, TxQueue};
+use rpkt_time::Instant;
+
+use crate::lpm::Lpm;
+use crate::wire::{self, ArpInfo, ARP_FRAME_LEN};
+
+/// The number of mbufs received or sent in a burst.
+const BATCH_SIZE: usize = 32;
+
+/// The maximum number of frames of a port waiting for neighbor resolution.
+const MAX_PENDING: usize = 256;
+
+/// The time-to-live and hop limit of the ICMP errors sent by the router.
+const ICMP_TTL: u8 = 64;
+
+/// The hop limit of NDP messages, which must not be forwarded by routers.
+const NDP_HOP_LIMIT: u8 = 255;
+
+/// The length of a neighbor solicitation or advertisement carrying a link-layer
+/// address option.
+const NDP_MSG_LEN: usize = 32;
+
+/// The length of a frame carrying an NDP message.
+const NDP_FRAME_LEN: usize = ETHER_HEADER_LEN + IPV6_HEADER_LEN + NDP_MSG_LEN;
+
+/// The NDP option types of the source and target link-layer addresses.
+const NDP_OPT_SRC_LINK_ADDR: u8 = 1;
+const NDP_OPT_DST_LINK_ADDR: u8 = 2;
+
+/// The configuration of a port of a [`Router`].
+#[derive(Debug, Clone, Copy)]
+pub struct RouterPortConf {
+    /// The MAC address of the DPDK port.
+    pub mac: MacAddr,
+    /// The IPv4 address of the port and the prefix length of the attached subnet.
+    pub ipv4: Option<(Ipv4Addr, u8)>,
+    /// The IPv6 address of the port and the prefix length of the attached subnet.
+    pub ipv6: Option<(Ipv6Addr, u8)>,
+    /// The IP MTU, excluding the Ethernet header.
+    pub mtu: usize,
+    /// The configuration of the ARP and NDP caches.
+    pub neigh: NeighConfig,
+}
+
+impl RouterPortConf {
+    /// Create a configuration without IP addresses, with the default MTU of 1500
+    /// bytes and default timers.
+    pub fn new(mac: MacAddr) -> Self {
+        Self {
+            mac,
+            ipv4: None,
+            ipv6: None,
+            mtu: 1500,
+            neigh: NeighConfig::default(),
+        }
+    }
+}
+
+/// The next hop of a route.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct NextHop<A> {
+    /// The index of the egress port.
+    pub port: usize,
+    /// The gateway that forwards the packets, or `None` if the destinations are
+    /// directly attached to the egress port.
+    pub gateway: Option<A>,
+}
+
+/// The counters of a port of a [`Router`].
+///
+/// The drop counters are updated on the ingress port of the dropped packets.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct RouterPortStats {
+    /// The number of received frames.
+    pub rx_packets: u64,
+    /// The number of bytes of the received frames.
+    pub rx_bytes: u64,
+    /// The number of sent frames, including the ones generated by the router.
+    pub tx_packets: u64,
+    /// The number of bytes of the sent frames.
+    pub tx_bytes: u64,
+    /// The number of received packets that are forwarded.
+    pub forwarded: u64,
+    /// The number of received packets whose time-to-live or hop limit is exhausted.
+    pub ttl_exceeded: u64,
+    /// The number of received packets without a route to their destinations.
+    pub no_route: u64,
+    /// The number of received packets that exceed the MTU of the egress port.
+    pub too_big: u64,
+    /// The number of received frames that are not forwarded, because they are
+    /// malformed, not addressed to the router, or addressed to the router itself
+    /// without being ARP or NDP messages.
+    pub filtered: u64,
+    /// The number of frames that are dropped because the tx queue is full, the
+    /// mempool is exhausted or the next hop fails to be resolved.
+    pub tx_dropped: u64,
+}
+
+/// An IPv4 and IPv6 router over a set of DPDK ports.
+///
+/// Each port of the router is a pair of rx/tx queues with its own MAC address and
+/// optional IPv4 and IPv6 addresses. The router answers ARP and NDP solicitations
+/// for the addresses of its ports, and forwards the other unicast packets sent to
+/// its MAC addresses:
+///
+/// * The egress port and the next hop are found by a longest prefix match in the
+///   routing table of the address family. Adding a port with an address installs
+///   a route to the attached subnet.
+/// * The time-to-live of IPv4 packets is decremented with an incremental update of
+///   the header checksum, and the hop limit of IPv6 packets is decremented.
+/// * The Ethernet header is rewritten with the MAC addresses of the egress port and
+///   the next hop. Packets to an unresolved next hop are queued until the ARP or NDP
+///   resolution completes.
+///
+/// When a packet can not be forwarded because its time-to-live is exhausted, no
+/// route matches its destination, or it exceeds the MTU of the egress port and can
+/// not be fragmented, an ICMP or ICMPv6 error is sent back to the previous hop,
+/// sourced from the address of the ingress port. The router does not fragment
+/// packets, oversized IPv4 packets without the don't fragment flag are dropped.
+///
+/// The router is single-threaded and poll-driven, it only makes progress when
+/// [`Router::poll`] is called.
+///
+/// # Examples
+/// ```no_run
+/// use std::net::Ipv4Addr;
+///
+/// use rpkt::ether::MacAddr;
+/// use rpkt_dpdk::service;
+/// use rpkt_stack::{NextHop, Router, RouterPortConf};
+///
+/// let mut router = Router::new();
+/// for port_id in 0..2 {
+///     let mut conf = RouterPortConf::new(MacAddr([0x02, 0, 0, 0, 0, port_id as u8]));
+///     conf.ipv4 = Some((Ipv4Addr::new(10, 0, port_id as u8, 1), 24));
+///     router.add_port(
+///         conf,
+///         service().rx_queue(port_id, 0).unwrap(),
+///         service().tx_queue(port_id, 0).unwrap(),
+///         service().mempool("mp").unwrap(),
+///     );
+/// }
+/// router.add_route_v4(
+///     Ipv4Addr::UNSPECIFIED,
+///     0,
+///     NextHop {
+///         port: 1,
+///         gateway: Some(Ipv4Addr::new(10, 0, 1, 254)),
+///     },
+/// );
+///
+/// loop {
+///     router.poll();
+/// }
+/// ```
+pub struct Router {
+    ports: Vec<RouterPort>,
+    routes_v4: Lpm<Ipv4Addr, NextHop<Ipv4Addr>>,
+    routes_v6: Lpm<Ipv6Addr, NextHop<Ipv6Addr>>,
+}
+
+impl Router {
+    /// Create a router without ports and routes.
+    pub fn new() -> Self {
+        Self {
+            ports: Vec::new(),
+            routes_v4: Lpm::new(),
+            routes_v6: Lpm::new(),
+        }
+    }
+
+    /// Add a port, and returns the index of the port.
+    ///
+    /// The routes to the subnets of the addresses in `conf` are added to the routing
+    /// tables. The ICMP errors and the ARP and NDP messages sent to the port are
+    /// allocated from `mp`.
+    pub fn add_port(
+        &mut self,
+        conf: RouterPortConf,
+        rxq: RxQueue,
+        txq: TxQueue,
+        mp: Mempool,
+    ) -> usize {
+        let port = self.ports.len();
+        if let Some((addr, prefix_len)) = conf.ipv4 {
+            self.add_route_v4(
+                addr,
+                prefix_len,
+                NextHop {
+                    port,
+                    gateway: None,
+                },
+            );
+        }
+        if let Some((addr, prefix_len)) = conf.ipv6 {
+            self.add_route_v6(
+                addr,
+                prefix_len,
+                NextHop {
+                    port,
+                    gateway: None,
+                },
+            );
+        }
+        self.ports.push(RouterPort {
+            conf,
+            rxq,
+            txq,
+            mp,
+            neigh_v4: NeighCache::new(conf.neigh),
+            neigh_v6: NeighCache::new(conf.neigh),
+            pending: VecDeque::new(),
+            tx_batch: ArrayVec::new(),
+            stats: RouterPortStats::default(),
+        });
+        port
+    }
+
+    /// Returns the number of ports.
+    #[inline]
+    pub fn nb_ports(&self) -> usize {
+        self.ports.len()
+    }
+
+    /// Returns the configuration of `port`.
+    #[inline]
+    pub fn port_conf(&self, port: usize) -> Option<&RouterPortConf> {
+        self.ports.get(port).map(|port| &port.conf)
+    }
+
+    /// Returns the counters of `port`.
+    #[inline]
+    pub fn port_stats(&self, port: usize) -> Option<RouterPortStats> {
+        self.ports.get(port).map(|port| port.stats)
+    }
+
+    /// Returns the ARP cache of `port`.
+    #[inline]
+    pub fn neigh_cache_v4(&self, port: usize) -> Option<&NeighCache<Ipv4Addr>> {
+        self.ports.get(port).map(|port| &port.neigh_v4)
+    }
+
+    /// Returns the NDP cache of `port`.
+    #[inline]
+    pub fn neigh_cache_v6(&self, port: usize) -> Option<&NeighCache<Ipv6Addr>> {
+        self.ports.get(port).map(|port| &port.neigh_v6)
+    }
+
+    /// Returns the IPv4 routing table.
+    #[inline]
+    pub fn routes_v4(&self) -> &Lpm<Ipv4Addr, NextHop<Ipv4Addr>> {
+        &self.routes_v4
+    }
+
+    /// Returns the IPv6 routing table.
+    #[inline]
+    pub fn routes_v6(&self) -> &Lpm<Ipv6Addr, NextHop<Ipv6Addr>> {
+        &self.routes_v6
+    }
+
+    /// Add a route to `prefix/prefix_len`, returning the next hop of the replaced
+    /// route.
+    ///
+    /// Packets matching a route whose port does not exist are dropped as if no route
+    /// matched.
+    pub fn add_route_v4(
+        &mut self,
+        prefix: Ipv4Addr,
+        prefix_len: u8,
+        next_hop: NextHop<Ipv4Addr>,
+    ) -> Option<NextHop<Ipv4Addr>> {
+        self.routes_v4.insert(prefix, prefix_len, next_hop)
+    }
+
+    /// Remove the route to `prefix/prefix_len`, returning its next hop.
+    pub fn remove_route_v4(
+        &mut self,
+        prefix: Ipv4Addr,
+        prefix_len: u8,
+    ) -> Option<NextHop<Ipv4Addr>> {
+        self.routes_v4.remove(prefix, prefix_len)
+    }
+
+    /// Add a route to `prefix/prefix_len`, returning the next hop of the replaced
+    /// route.
+    ///
+    /// Packets matching a route whose port does not exist are dropped as if no route
+    /// matched.
+    pub fn add_route_v6(
+        &mut self,
+        prefix: Ipv6Addr,
+        prefix_len: u8,
+        next_hop: NextHop<Ipv6Addr>,
+    ) -> Option<NextHop<Ipv6Addr>> {
+        self.routes_v6.insert(prefix, prefix_len, next_hop)
+    }
+
+    /// Remove the route to `prefix/prefix_len`, returning its next hop.
+    pub fn remove_route_v6(
+        &mut self,
+        prefix: Ipv6Addr,
+        prefix_len: u8,
+    ) -> Option<NextHop<Ipv6Addr>> {
+        self.routes_v6.remove(prefix, prefix_len)
+    }
+
+    /// Receive a burst from every port, forward the packets and drive the neighbor
+    /// timers, returns the number of received frames.
+    pub fn poll(&mut self) -> usize {
+        let now = Instant::now();
+
+        let mut total = 0;
+        let mut resolved = false;
+        let mut batch = ArrayVec::<Mbuf, BATCH_SIZE>::new();
+        for in_port in 0..self.ports.len() {
+            total += self.ports[in_port].rxq.rx(&mut batch);
+            for mbuf in batch.drain(..) {
+                let stats = &mut self.ports[in_port].stats;
+                stats.rx_packets += 1;
+                stats.rx_bytes += mbuf.len() as u64;
+                resolved |= self.handle(in_port, mbuf, now);
+            }
+        }
+
+        for port in self.ports.iter_mut() {
+            if resolved {
+                port.flush_pending();
+            }
+            port.poll_neigh(now);
+            port.flush();
+        }
+        total
+    }
+
+    // Handle a received frame, returning whether a neighbor cache is updated.
+    fn handle(&mut self, in_port: usize, mbuf: Mbuf, now: Instant) -> bool {
+        let port = &mut self.ports[in_port];
+        match classify(mbuf.data(), &port.conf) {
+            Some(Incoming::Arp(arp)) => {
+                if arp.op == Operation::REPLY {
+                    port.neigh_v4.confirm(arp.sender_ip, arp.sender_mac, now);
+                } else if arp.op == Operation::REQUEST {
+                    port.neigh_v4.update(arp.sender_ip, arp.sender_mac, now);
+                    port.send_arp(Operation::REPLY, Some(arp.sender_mac), arp.sender_ip);
+                }
+                true
+            }
+            Some(Incoming::NeighSolicit { src, src_mac }) => {
+                port.neigh_v6.update(src, src_mac, now);
+                port.send_neigh_advert(src, src_mac);
+                true
+            }
+            Some(Incoming::NeighAdvert {
+                target,
+                target_mac,
+                solicited,
+            }) => {
+                if solicited {
+                    port.neigh_v6.confirm(target, target_mac, now);
+                } else {
+                    port.neigh_v6.update(target, target_mac, now);
+                }
+                true
+            }
+            Some(Incoming::Ipv4 { dst, ttl, len }) => {
+                self.forward_v4(in_port, mbuf, dst, ttl, len, now);
+                false
+            }
+            Some(Incoming::Ipv6 {
+                dst,
+                hop_limit,
+                len,
+            }) => {
+                self.forward_v6(in_port, mbuf, dst, hop_limit, len, now);
+                false
+            }
+            None => {
+                port.stats.filtered += 1;
+                false
+            }
+        }
+    }
+
+    fn forward_v4(
+        &mut self,
+        in_port: usize,
+        mut mbuf: Mbuf,
+        dst: Ipv4Addr,
+        ttl: u8,
+        len: usize,
+        now: Instant,
+    ) {
+        if ttl <= 1 {
+            let port = &mut self.ports[in_port];
+            port.stats.ttl_exceeded += 1;
+            port.send_icmpv4_error(mbuf.data(), len, IcmpError::TimeExceeded);
+            return;
+        }
+        let next_hop = match self.routes_v4.lookup(dst) {
+            Some(next_hop) if next_hop.port < self.ports.len() => *next_hop,
+            _ => {
+                let port = &mut self.ports[in_port];
+                port.stats.no_route += 1;
+                port.send_icmpv4_error(mbuf.data(), len, IcmpError::NoRoute);
+                return;
+            }
+        };
+        let mtu = self.ports[next_hop.port].conf.mtu;
+        if len > mtu {
+            let port = &mut self.ports[in_port];
+            port.stats.too_big += 1;
+            let orig = &mbuf.data()[ETHER_HEADER_LEN..];
+            if Ipv4Header::new_unchecked(orig).dont_frag() {
+                port.send_icmpv4_error(mbuf.data(), len, IcmpError::TooBig(mtu));
+            }
+            return;
+        }
+
+        self.ports[in_port].stats.forwarded += 1;
+        let out = &mut self.ports[next_hop.port];
+        let frame = mbuf.data_mut();
+        decrement_ttl(&mut frame[ETHER_HEADER_LEN..]);
+        set_source_mac(frame, out.conf.mac);
+        out.send_to(IpAddr::V4(next_hop.gateway.unwrap_or(dst)), mbuf, now);
+    }
+
+    fn forward_v6(
+        &mut self,
+        in_port: usize,
+        mut mbuf: Mbuf,
+        dst: Ipv6Addr,
+        hop_limit: u8,
+        len: usize,
+        now: Instant,
+    ) {
+        if hop_limit <= 1 {
+            let port = &mut self.ports[in_port];
+            port.stats.ttl_exceeded += 1;
+            port.send_icmpv6_error(mbuf.data(), len, IcmpError::TimeExceeded);
+            return;
+        }
+        let next_hop = match self.routes_v6.lookup(dst) {
+            Some(next_hop) if next_hop.port < self.ports.len() => *next_hop,
+            _ => {
+                let port = &mut self.ports[in_port];
+                port.stats.no_route += 1;
+                port.send_icmpv6_error(mbuf.data(), len, IcmpError::NoRoute);
+                return;
+            }
+        };
+        let mtu = self.ports[next_hop.port].conf.mtu;
+        if len > mtu {
+            let port = &mut self.ports[in_port];
+            port.stats.too_big += 1;
+            port.send_icmpv6_error(mbuf.data(), len, IcmpError::TooBig(mtu));
+            return;
+        }
+
+        self.ports[in_port].stats.forwarded += 1;
+        let out = &mut self.ports[next_hop.port];
+        let frame = mbuf.data_mut();
+        decrement_hop_limit(&mut frame[ETHER_HEADER_LEN..]);
+        set_source_mac(frame, out.conf.mac);
+        out.send_to(IpAddr::V6(next_hop.gateway.unwrap_or(dst)), mbuf, now);
+    }
+}
+
+impl Default for Router {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+struct RouterPort {
+    conf: RouterPortConf,
+    rxq: RxQueue,
+    txq: TxQueue,
+    mp: Mempool,
+    neigh_v4: NeighCache<Ipv4Addr>,
+    neigh_v6: NeighCache<Ipv6Addr>,
+    pending: VecDeque<(IpAddr, Mbuf)>,
+    tx_batch: ArrayVec<Mbuf, BATCH_SIZE>,
+    stats: RouterPortStats,
+}
+
+impl RouterPort {
+    // Send `mbuf` to the neighbor `next_hop`, or queue it until the MAC address of
+    // the neighbor is resolved.
+    fn send_to(&mut self, next_hop: IpAddr, mut mbuf: Mbuf, now: Instant) {
+        let mac = match next_hop {
+            IpAddr::V4(addr) => self.neigh_v4.lookup(addr, now),
+            IpAddr::V6(addr) => self.neigh_v6.lookup(addr, now),
+        };
+        match mac {
+            Some(mac) => {
+                wire::set_dest_mac(mbuf.data_mut(), mac);
+                self.enqueue(mbuf);
+            }
+            None => {
+                if self.pending.len() >= MAX_PENDING {
+                    self.pending.pop_front();
+                    self.stats.tx_dropped += 1;
+                }
+                self.pending.push_back((next_hop, mbuf));
+            }
+        }
+    }
+
+    // Send the pending frames whose next hop is resolved.
+    fn flush_pending(&mut self) {
+        let pending = std::mem::take(&mut self.pending);
+        for (next_hop, mut mbuf) in pending {
+            let entry = match next_hop {
+                IpAddr::V4(addr) => self.neigh_v4.get(addr),
+                IpAddr::V6(addr) => self.neigh_v6.get(addr),
+            };
+            match entry.and_then(|entry| entry.mac()) {
+                Some(mac) => {
+                    wire::set_dest_mac(mbuf.data_mut(), mac);
+                    self.enqueue(mbuf);
+                }
+                None => self.pending.push_back((next_hop, mbuf)),
+            }
+        }
+    }
+
+    // Send the ARP requests and neighbor solicitations that are due, and drop the
+    // frames whose next hop fails to be resolved.
+    fn poll_neigh(&mut self, now: Instant) {
+        let mut probes = Vec::new();
+        self.neigh_v4.poll(now, |probe| probes.push(probe));
+        for probe in probes {
+            self.send_arp(Operation::REQUEST, probe.target, probe.addr);
+        }
+        let mut probes = Vec::new();
+        self.neigh_v6.poll(now, |probe| probes.push(probe));
+        for probe in probes {
+            self.send_neigh_solicit(probe.addr, probe.target);
+        }
+
+        let (neigh_v4, neigh_v6) = (&self.neigh_v4, &self.neigh_v6);
+        let nb_pending = self.pending.len();
+        self.pending.retain(|(next_hop, _)| match next_hop {
+            IpAddr::V4(addr) => neigh_v4.get(*addr).is_some(),
+            IpAddr::V6(addr) => neigh_v6.get(*addr).is_some(),
+        });
+        self.stats.tx_dropped += (nb_pending - self.pending.len()) as u64;
+    }
+
+    fn send_arp(&mut self, op: Operation, target_mac: Option<MacAddr>, target_ip: Ipv4Addr) {
+        let (ip, _) = match self.conf.ipv4 {
+            Some(ipv4) => ipv4,
+            None => return,
+        };
+        if let Some(mut mbuf) = self.alloc(ARP_FRAME_LEN) {
+            wire::write_arp(
+                mbuf.data_mut(),
+                op,
+                self.conf.mac,
+                ip,
+                target_mac,
+                target_ip,
+            );
+            self.enqueue(mbuf);
+        }
+    }
+
+    fn send_neigh_solicit(&mut self, target: Ipv6Addr, target_mac: Option<MacAddr>) {
+        let (ip, _) = match self.conf.ipv6 {
+            Some(ipv6) => ipv6,
+            None => return,
+        };
+        if let Some(mut mbuf) = self.alloc(NDP_FRAME_LEN) {
+            write_neigh_solicit(mbuf.data_mut(), self.conf.mac, ip, target, target_mac);
+            self.enqueue(mbuf);
+        }
+    }
+
+    fn send_neigh_advert(&mut self, dst: Ipv6Addr, dst_mac: MacAddr) {
+        let (ip, _) = match self.conf.ipv6 {
+            Some(ipv6) => ipv6,
+            None => return,
+        };
+        if let Some(mut mbuf) = self.alloc(NDP_FRAME_LEN) {
+            write_neigh_advert(mbuf.data_mut(), self.conf.mac, ip, dst_mac, dst);
+            self.enqueue(mbuf);
+        }
+    }
+
+    // Send an ICMP error about the IPv4 packet of `frame` back to the sender of the
+    // frame. `ip_len` is the length of the IPv4 packet.
+    fn send_icmpv4_error(&mut self, frame: &[u8], ip_len: usize, error: IcmpError) {
+        let (ip, _) = match self.conf.ipv4 {
+            Some(ipv4) => ipv4,
+            None => return,
+        };
+        let orig = &frame[ETHER_HEADER_LEN..ETHER_HEADER_LEN + ip_len];
+        if !icmpv4_error_allowed(orig) {
+            return;
+        }
+        if let Some(mut mbuf) = self.alloc(icmpv4_error_len(orig)) {
+            let dst_mac = MacAddr::from_bytes(&frame[6..12]);
+            write_icmpv4_error(mbuf.data_mut(), self.conf.mac, dst_mac, ip, error, orig);
+            self.enqueue(mbuf);
+        }
+    }
+
+    // Send an ICMPv6 error about the IPv6 packet of `frame` back to the sender of
+    // the frame. `ip_len` is the length of the IPv6 packet.
+    fn send_icmpv6_error(&mut self, frame: &[u8], ip_len: usize, error: IcmpError) {
+        let (ip, _) = match self.conf.ipv6 {
+            Some(ipv6) => ipv6,
+            None => return,
+        };
+        let orig = &frame[ETHER_HEADER_LEN..ETHER_HEADER_LEN + ip_len];
+        if !icmpv6_error_allowed(orig) {
+            return;
+        }
+        if let Some(mut mbuf) = self.alloc(icmpv6_error_len(orig)) {
+            let dst_mac = MacAddr::from_bytes(&frame[6..12]);
+            write_icmpv6_error(mbuf.data_mut(), self.conf.mac, dst_mac, ip, error, orig);
+            self.enqueue(mbuf);
+        }
+    }
+
+    fn alloc(&mut self, len: usize) -> Option<Mbuf> {
+        match self.mp.try_alloc() {
+            Some(mut mbuf) => {
+                unsafe { mbuf.extend(len) };
+                Some(mbuf)
+            }
+            None => {
+                self.stats.tx_dropped += 1;
+                None
+            }
+        }
+    }
+
+    fn enqueue(&mut self, mbuf: Mbuf) {
+        if self.tx_batch.is_full() {
+            self.flush();
+        }
+        self.tx_batch.push(mbuf);
+    }
+
+    fn flush(&mut self) {
+        if self.tx_batch.is_empty() {
+            return;
+        }
+        let bytes: usize = self.tx_batch.iter().map(|mbuf| mbuf.len()).sum();
+        let nb_tx = self.txq.tx(&mut self.tx_batch);
+        let unsent: usize = self.tx_batch.iter().map(|mbuf| mbuf.len()).sum();
+
+        self.stats.tx_packets += nb_tx as u64;
+        self.stats.tx_bytes += (bytes - unsent) as u64;
+        self.stats.tx_dropped += self.tx_batch.len() as u64;
+        self.tx_batch.clear();
+    }
+}
+
+/// The reason for sending an ICMP or ICMPv6 error.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum IcmpError {
+    TimeExceeded,
+    NoRoute,
+    /// The packet exceeds the MTU of the egress port.
+    TooBig(usize),
+}
+
+/// A frame received by a port of the router.
+#[derive(Debug, PartialEq, Eq)]
+enum Incoming {
+    /// An ARP request or reply for the IPv4 address of the port.
+    Arp(ArpInfo),
+    /// A neighbor solicitation for the IPv6 address of the port.
+    NeighSolicit { src: Ipv6Addr, src_mac: MacAddr },
+    /// A neighbor advertisement sent to the IPv6 address of the port.
+    NeighAdvert {
+        target: Ipv6Addr,
+        target_mac: MacAddr,
+        solicited: bool,
+    },
+    /// An IPv4 packet to forward, whose length is `len`.
+    Ipv4 { dst: Ipv4Addr, ttl: u8, len: usize },
+    /// An IPv6 packet to forward, whose length is `len`.
+    Ipv6 {
+        dst: Ipv6Addr,
+        hop_limit: u8,
+        len: usize,
+    },
+}
+
+/// Classify the frame received by the port configured with `conf`.
+///
+/// Returns `None` for the frames that the router does not handle, including the
+/// malformed ones and the ones with a bad IPv4 header checksum.
+fn classify(frame: &[u8], conf: &RouterPortConf) -> Option<Incoming> {
+    let ethpkt = EtherPacket::parse(Cursor::new(frame)).ok()?;
+    let to_port = ethpkt.dest_mac() == conf.mac;
+    let src_mac = ethpkt.source_mac();
+    match ethpkt.ethertype() {
+        EtherType::ARP => {
+            let arp = wire::parse_arp(ethpkt.payload())?;
+            let (local, _) = conf.ipv4?;
+            if arp.target_ip != local || arp.sender_ip.is_unspecified() {
+                return None;
+            }
+            Some(Incoming::Arp(arp))
+        }
+        EtherType::IPV4 => {
+            let ippkt = Ipv4Packet::parse(ethpkt.payload()).ok()?;
+            if !to_port || !ippkt.check_version() || !ippkt.verify_checksum() {
+                return None;
+            }
+            // The packets sent to the router itself are not forwarded.
+            let dst = Ipv4Addr::from(ippkt.dest_ip());
+            if conf.ipv4.map(|(local, _)| local) == Some(dst)
+                || dst.is_broadcast()
+                || dst.is_multicast()
+            {
+                return None;
+            }
+            Some(Incoming::Ipv4 {
+                dst,
+                ttl: ippkt.time_to_live(),
+                len: usize::from(ippkt.packet_len()),
+            })
+        }
+        EtherType::IPV6 => {
+            let ippkt = Ipv6Packet::parse(ethpkt.payload()).ok()?;
+            if !ippkt.check_version() {
+                return None;
+            }
+            let src = ippkt.source_ip();
+            let dst = ippkt.dest_ip();
+            let local = conf.ipv6.map(|(local, _)| local);
+            if Some(Ipv6Addr::from(dst)) == local || dst.is_multicast() {
+                if ippkt.next_header() != IpProtocol::ICMPV6 || ippkt.hop_limit() != NDP_HOP_LIMIT {
+                    return None;
+                }
+                return classify_ndp(src, dst, ippkt.payload(), local?, src_mac);
+            }
+
+            // Link-local packets stay on their link.
+            if !to_port || src.is_unspecified() || src.is_link_local() || dst.is_link_local() {
+                return None;
+            }
+            Some(Incoming::Ipv6 {
+                dst: dst.into(),
+                hop_limit: ippkt.hop_limit(),
+                len: IPV6_HEADER_LEN + usize::from(ippkt.payload_len()),
+            })
+        }
+        _ => None,
+    }
+}
+
+// Classify an NDP message carried by an IPv6 packet from `src` to `dst`.
+fn classify_ndp(
+    src: rpkt::ipv6::Ipv6Addr,
+    dst: rpkt::ipv6::Ipv6Addr,
+    payload: Cursor<'_>,
+    local: Ipv6Addr,
+    src_mac: MacAddr,
+) -> Option<Incoming> {
+    let icmppkt = Icmpv6Packet::parse(payload).ok()?;
+    if !icmppkt.verify_checksum(src, dst) {
+        return None;
+    }
+    match icmppkt.msg() {
+        Icmpv6Msg::NdpNeighborSolicit(msg) => {
+            // Duplicate address detection probes are not answered.
+            if Ipv6Addr::from(rpkt::ipv6::Ipv6Addr::from_bytes(msg.target_addr())) != local
+                || src.is_unspecified()
+            {
+                return None;
+            }
+            Some(Incoming::NeighSolicit {
+                src: src.into(),
+                src_mac: link_addr_option(msg.option_bytes(), NDP_OPT_SRC_LINK_ADDR)
+                    .unwrap_or(src_mac),
+            })
+        }
+        Icmpv6Msg::NdpNeighborAdv(msg) => Some(Incoming::NeighAdvert {
+            target: rpkt::ipv6::Ipv6Addr::from_bytes(msg.target_addr()).into(),
+            target_mac: link_addr_option(msg.option_bytes(), NDP_OPT_DST_LINK_ADDR)
+                .unwrap_or(src_mac),
+            solicited: msg.s_flag(),
+        }),
+        _ => None,
+    }
+}
+
+// Find the link-layer address option of type `kind` in the NDP options.
+fn link_addr_option(mut options: &[u8], kind: u8) -> Option<MacAddr> {
+    while options.len() >= 8 {
+        let len = usize::from(options[1]) * 8;
+        if len == 0 || len > options.len() {
+            return None;
+        }
+        if options[0] == kind && len == 8 {
+            return Some(MacAddr::from_bytes(&options[2..8]));
+        }
+        options = &options[len..];
+    }
+    None
+}
+
+/// Decrement the time-to-live of an IPv4 packet, updating the header checksum
+/// incrementally (RFC 1624).
+fn decrement_ttl(packet: &mut [u8]) {
+    let mut header = Ipv4Header::new_unchecked(packet);
+    let ttl = header.time_to_live();
+    let protocol = u8::from(header.protocol());
+
+    // The time-to-live and the protocol share a 16-bit word of the checksum.
+    let mut accum = ChecksumAccumulator::new();
+    accum.add_checksum(!header.checksum());
+    accum.add_checksum(!u16::from_be_bytes([ttl, protocol]));
+    accum.add_checksum(u16::from_be_bytes([ttl - 1, protocol]));
+    header.set_time_to_live(ttl - 1);
+    header.set_checksum(!accum.finish());
+}
+
+/// Decrement the hop limit of an IPv6 packet.
+fn decrement_hop_limit(packet: &mut [u8]) {
+    let mut header = Ipv6Header::new_unchecked(packet);
+    let hop_limit = header.hop_limit();
+    header.set_hop_limit(hop_limit - 1);
+}
+
+#[inline]
+fn set_source_mac(frame: &mut [u8], mac: MacAddr) {
+    frame[6..12].copy_from_slice(mac.as_bytes());
+}
+
+// Returns whether an ICMP error may be sent about the IPv4 packet `orig`
+// (RFC 1812, section 4.3.2.7).
+fn icmpv4_error_allowed(orig: &[u8]) -> bool {
+    let header = Ipv4Header::new_unchecked(orig);
+    let src = Ipv4Addr::from(header.source_ip());
+    if src.is_unspecified() || src.is_broadcast() || src.is_multicast() || header.frag_offset() != 0
+    {
+        return false;
+    }
+    if header.protocol() != IpProtocol::ICMP {
+        return true;
+    }
+    // No error is sent about an ICMP error.
+    match orig.get(usize::from(header.header_len())) {
+        Some(icmp_type) => !matches!(
+            IcmpType::from(*icmp_type),
+            IcmpType::DST_UNREACHABLE
+                | IcmpType::REDIRECT_MESSAGE
+                | IcmpType::TIME_EXCEEDED
+                | IcmpType::PARAMETER_PROBLEM
+        ),
+        None => false,
+    }
+}
+
+// Returns whether an ICMPv6 error may be sent about the IPv6 packet `orig`
+// (RFC 4443, section 2.4).
+fn icmpv6_error_allowed(orig: &[u8]) -> bool {
+    let header = Ipv6Header::new_unchecked(orig);
+    let src = header.source_ip();
+    if src.is_unspecified() || src.is_multicast() {
+        return false;
+    }
+    if header.next_header() != IpProtocol::ICMPV6 {
+        return true;
+    }
+    // The error messages have types below 128.
+    match orig.get(IPV6_HEADER_LEN) {
+        Some(msg_type) => *msg_type >= 128,
+        None => false,
+    }
+}
+
+/// Returns the length of the frame carrying an ICMP error about `orig`.
+fn icmpv4_error_len(orig: &[u8]) -> usize {
+    ETHER_HEADER_LEN + IPV4_HEADER_LEN + ICMPV4_HEADER_LEN + orig.len().min(ICMPV4_ERROR_QUOTE_MAX)
+}
+
+/// Returns the length of the frame carrying an ICMPv6 error about `orig`.
+fn icmpv6_error_len(orig: &[u8]) -> usize {
+    ETHER_HEADER_LEN + IPV6_HEADER_LEN + 8 + orig.len().min(ICMPV6_ERROR_QUOTE_MAX)
+}
+
+/// Write an ICMP error about the IPv4 packet `orig` into `frame`, whose length is
+/// `icmpv4_error_len(orig)`. The error is sent from `src` to the source of `orig`.
+fn write_icmpv4_error(
+    frame: &mut [u8],
+    src_mac: MacAddr,
+    dst_mac: MacAddr,
+    src: Ipv4Addr,
+    error: IcmpError,
+    orig: &[u8],
+) {
+    let dst = Ipv4Header::new_unchecked(orig).source_ip();
+    let frame_len = frame.len();
+    let mut buf = CursorMut::new(frame);
+    buf.advance(frame_len);
+
+    let icmppkt = match error {
+        IcmpError::TimeExceeded => Icmpv4Packet::prepend_time_exceeded(buf, 0, orig),
+        IcmpError::NoRoute => Icmpv4Packet::prepend_dst_unreachable(buf, 0, 0, orig),
+        // Fragmentation needed and the don't fragment flag is set.
+        IcmpError::TooBig(mtu) => {
+            let mtu = u16::try_from(mtu).unwrap_or(u16::MAX);
+            Icmpv4Packet::prepend_dst_unreachable(buf, 4, mtu, orig)
+        }
+    };
+
+    let mut ippkt = Ipv4Packet::prepend_header(icmppkt.release(), &IPV4_HEADER_TEMPLATE);
+    ippkt.clear_flags();
+    ippkt.set_time_to_live(ICMP_TTL);
+    ippkt.set_protocol(IpProtocol::ICMP);
+    ippkt.set_source_ip(src.into());
+    ippkt.set_dest_ip(dst);
+    ippkt.adjust_checksum();
+
+    let mut ethpkt = EtherPacket::prepend_header(ippkt.release(), &ETHER_HEADER_TEMPLATE);
+    ethpkt.set_source_mac(src_mac);
+    ethpkt.set_dest_mac(dst_mac);
+    ethpkt.set_ethertype(EtherType::IPV4);
+}
+
+/// Write an ICMPv6 error about the IPv6 packet `orig` into `frame`, whose length
+/// is `icmpv6_error_len(orig)`. The error is sent from `src` to the source of
+/// `orig`.
+fn write_icmpv6_error(
+    frame: &mut [u8],
+    src_mac: MacAddr,
+    dst_mac: MacAddr,
+    src: Ipv6Addr,
+    error: IcmpError,
+    orig: &[u8],
+) {
+    let src = rpkt::ipv6::Ipv6Addr::from(src);
+    let dst = Ipv6Header::new_unchecked(orig).source_ip();
+    let frame_len = frame.len();
+    let mut buf = CursorMut::new(frame);
+    buf.advance(frame_len);
+
+    let icmppkt = match error {
+        IcmpError::TimeExceeded => Icmpv6Packet::prepend_time_exceeded(buf, 0, orig, src, dst),
+        IcmpError::NoRoute => Icmpv6Packet::prepend_dst_unreachable(buf, 0, orig, src, dst),
+        IcmpError::TooBig(mtu) => {
+            let mtu = u32::try_from(mtu).unwrap_or(u32::MAX);
+            Icmpv6Packet::prepend_pkt_too_big(buf, mtu, orig, src, dst)
+        }
+    };
+    write_ipv6_ether(icmppkt.release(), src_mac, dst_mac, src, dst, ICMP_TTL);
+}
+
+/// Write a neighbor solicitation for `target` into the first `NDP_FRAME_LEN` bytes
+/// of `frame`.
+///
+/// The solicitation is sent to `target_mac`, or to the solicited-node multicast
+/// address of `target` if `target_mac` is `None`.
+fn write_neigh_solicit(
+    frame: &mut [u8],
+    src_mac: MacAddr,
+    src: Ipv6Addr,
+    target: Ipv6Addr,
+    target_mac: Option<MacAddr>,
+) {
+    let src = rpkt::ipv6::Ipv6Addr::from(src);
+    let target = rpkt::ipv6::Ipv6Addr::from(target);
+    let (dst, dst_mac) = match target_mac {
+        Some(mac) => (target, mac),
+        None => {
+            let dst = target.solicited_node();
+            let mut mac = [0x33, 0x33, 0, 0, 0, 0];
+            mac[2..].copy_from_slice(&dst.0[12..]);
+            (dst, MacAddr(mac))
+        }
+    };
+
+    let mut buf = CursorMut::new(&mut frame[..NDP_FRAME_LEN]);
+    buf.advance(NDP_FRAME_LEN);
+    let mut msg = Icmpv6Packet::prepend_msg_ndp_neighbor_solicit(&mut buf, NDP_MSG_LEN);
+    msg.set_target_addr(target.as_bytes());
+    write_link_addr_option(msg.option_bytes_mut(), NDP_OPT_SRC_LINK_ADDR, src_mac);
+
+    let mut icmppkt = Icmpv6Packet::parse_unchecked(buf);
+    icmppkt.adjust_checksum(src, dst);
+    write_ipv6_ether(icmppkt.release(), src_mac, dst_mac, src, dst, NDP_HOP_LIMIT);
+}
+
+/// Write a solicited neighbor advertisement for `src` into the first
+/// `NDP_FRAME_LEN` bytes of `frame`.
+fn write_neigh_advert(
+    frame: &mut [u8],
+    src_mac: MacAddr,
+    src: Ipv6Addr,
+    dst_mac: MacAddr,
+    dst: Ipv6Addr,
+) {
+    let src = rpkt::ipv6::Ipv6Addr::from(src);
+    let dst = rpkt::ipv6::Ipv6Addr::from(dst);
+
+    let mut buf = CursorMut::new(&mut frame[..NDP_FRAME_LEN]);
+    buf.advance(NDP_FRAME_LEN);
+    let mut msg = Icmpv6Packet::prepend_msg_ndp_neighbor_adv(&mut buf, NDP_MSG_LEN);
+    msg.set_r_flag(true);
+    msg.set_s_flag(true);
+    msg.set_o_flag(true);
+    msg.set_target_addr(src.as_bytes());
+    write_link_addr_option(msg.option_bytes_mut(), NDP_OPT_DST_LINK_ADDR, src_mac);
+
+    let mut icmppkt = Icmpv6Packet::parse_unchecked(buf);
+    icmppkt.adjust_checksum(src, dst);
+    write_ipv6_ether(icmppkt.release(), src_mac, dst_mac, src, dst, NDP_HOP_LIMIT);
+}
+
+fn write_link_addr_option(options: &mut [u8], kind: u8, mac: MacAddr) {
+    options[0] = kind;
+    options[1] = 1;
+    options[2..8].copy_from_slice(mac.as_bytes());
+}
+
+// Prepend the IPv6 and Ethernet headers in front of the ICMPv6 message in `buf`.
+fn write_ipv6_ether(
+    buf: CursorMut<'_>,
+    src_mac: MacAddr,
+    dst_mac: MacAddr,
+    src: rpkt::ipv6::Ipv6Addr,
+    dst: rpkt::ipv6::Ipv6Addr,
+    hop_limit: u8,
+) {
+    let mut header = Ipv6Header::new_unchecked([0; IPV6_HEADER_LEN]);
+    header.adjust_version();
+    header.set_next_header(IpProtocol::ICMPV6);
+    header.set_hop_limit(hop_limit);
+    header.set_source_ip(&src);
+    header.set_dest_ip(&dst);
+    let ippkt = Ipv6Packet::prepend_header(buf, &header);
+
+    let mut ethpkt = EtherPacket::prepend_header(ippkt.release(), &ETHER_HEADER_TEMPLATE);
+    ethpkt.set_source_mac(src_mac);
+    ethpkt.set_dest_mac(dst_mac);
+    ethpkt.set_ethertype(EtherType::IPV6);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::wire::{Ipv4Info, FRAME_OVERHEAD};
+    use rpkt::icmpv6::Icmpv6MsgType;
+
+    const MAC_R: MacAddr = MacAddr([0x02, 0, 0, 0, 0, 0x01]);
+    const MAC_A: MacAddr = MacAddr([0x02, 0, 0, 0, 0, 0x0a]);
+    const IP_R: Ipv4Addr = Ipv4Addr::new(10, 0, 0, 1);
+    const IP_A: Ipv4Addr = Ipv4Addr::new(10, 0, 0, 2);
+    const IP_B: Ipv4Addr = Ipv4Addr::new(10, 0, 1, 2);
+
+    fn port_conf() -> RouterPortConf {
+        let mut conf = RouterPortConf::new(MAC_R);
+        conf.ipv4 = Some((IP_R, 24));
+        conf.ipv6 = Some(("2001:db8::1".parse().unwrap(), 64));
+        conf
+    }
+
+    fn ipv4_frame(ttl: u8, payload_len: usize) -> Vec<u8> {
+        let mut frame = vec![0; FRAME_OVERHEAD + payload_len];
+        let info = Ipv4Info {
+            src_mac: MAC_A,
+            src: IP_A,
+            dst: IP_B,
+            protocol: IpProtocol::UDP,
+            ident: 1,
+            ttl,
+        };
+        wire::write_ipv4(&mut frame, &info, 0, false);
+        wire::set_dest_mac(&mut frame, MAC_R);
+        frame
+    }
+
+    #[test]
+    fn forward_ipv4() {
+        let conf = port_conf();
+        let mut frame = ipv4_frame(64, 100);
+        assert_eq!(
+            classify(&frame, &conf),
+            Some(Incoming::Ipv4 {
+                dst: IP_B,
+                ttl: 64,
+                len: IPV4_HEADER_LEN + 100,
+            })
+        );
+
+        decrement_ttl(&mut frame[ETHER_HEADER_LEN..]);
+        let ippkt = Ipv4Packet::parse(Cursor::new(&frame[ETHER_HEADER_LEN..])).unwrap();
+        assert_eq!(ippkt.time_to_live(), 63);
+        assert!(ippkt.verify_checksum());
+
+        // The frames to other MAC addresses and to the router itself are filtered.
+        let mut other = frame.clone();
+        wire::set_dest_mac(&mut other, MAC_A);
+        assert_eq!(classify(&other, &conf), None);
+        let info = Ipv4Info {
+            src_mac: MAC_A,
+            src: IP_A,
+            dst: IP_R,
+            protocol: IpProtocol::UDP,
+            ident: 1,
+            ttl: 64,
+        };
+        wire::write_ipv4(&mut other, &info, 0, false);
+        wire::set_dest_mac(&mut other, MAC_R);
+        assert_eq!(classify(&other, &conf), None);
+    }
+
+    #[test]
+    fn icmpv4_errors() {
+        let frame = ipv4_frame(1, 1000);
+        let orig = &frame[ETHER_HEADER_LEN..];
+        assert!(icmpv4_error_allowed(orig));
+
+        let mut error = vec![0; icmpv4_error_len(orig)];
+        write_icmpv4_error(
+            &mut error,
+            MAC_R,
+            MAC_A,
+            IP_R,
+            IcmpError::TimeExceeded,
+            orig,
+        );
+        assert_eq!(
+            error.len(),
+            FRAME_OVERHEAD + ICMPV4_HEADER_LEN + ICMPV4_ERROR_QUOTE_MAX
+        );
+
+        let ethpkt = EtherPacket::parse(Cursor::new(&error[..])).unwrap();
+        assert_eq!(ethpkt.dest_mac(), MAC_A);
+        let ippkt = Ipv4Packet::parse(ethpkt.payload()).unwrap();
+        assert!(ippkt.verify_checksum());
+        assert_eq!(Ipv4Addr::from(ippkt.source_ip()), IP_R);
+        assert_eq!(Ipv4Addr::from(ippkt.dest_ip()), IP_A);
+        assert_eq!(ippkt.protocol(), IpProtocol::ICMP);
+        let mut icmppkt = Icmpv4Packet::parse(ippkt.payload()).unwrap();
+        assert!(icmppkt.verify_checksum());
+        assert_eq!(icmppkt.icmp_type(), IcmpType::TIME_EXCEEDED);
+
+        // No error is sent about the error.
+        assert!(!icmpv4_error_allowed(&error[ETHER_HEADER_LEN..]));
+    }
+
+    #[test]
+    fn ndp_roundtrip() {
+        let conf = port_conf();
+        let (local, _) = conf.ipv6.unwrap();
+        let peer: Ipv6Addr = "2001:db8::2".parse().unwrap();
+
+        // A multicast solicitation from the peer is answered.
+        let mut frame = [0; NDP_FRAME_LEN];
+        write_neigh_solicit(&mut frame, MAC_A, peer, local, None);
+        assert_eq!(&frame[..6], &[0x33, 0x33, 0xff, 0, 0, 0x01]);
+        assert_eq!(
+            classify(&frame, &conf),
+            Some(Incoming::NeighSolicit {
+                src: peer,
+                src_mac: MAC_A,
+            })
+        );
+
+        let mut frame = [0; NDP_FRAME_LEN];
+        write_neigh_advert(&mut frame, MAC_A, peer, MAC_R, local);
+        assert_eq!(
+            classify(&frame, &conf),
+            Some(Incoming::NeighAdvert {
+                target: peer,
+                target_mac: MAC_A,
+                solicited: true,
+            })
+        );
+
+        // A corrupted message fails the checksum.
+        frame[NDP_FRAME_LEN - 1] ^= 0xff;
+        assert_eq!(classify(&frame, &conf), None);
+    }
+
+    #[test]
+    fn icmpv6_errors() {
+        let conf = port_conf();
+        let (local, _) = conf.ipv6.unwrap();
+        let peer: Ipv6Addr = "2001:db8::2".parse().unwrap();
+        let remote: Ipv6Addr = "2001:db8:1::2".parse().unwrap();
+
+        let mut frame = vec![0; ETHER_HEADER_LEN + IPV6_HEADER_LEN + 1400];
+        let frame_len = frame.len();
+        let mut buf = CursorMut::new(&mut frame[..]);
+        buf.advance(ETHER_HEADER_LEN + IPV6_HEADER_LEN);
+        let mut header = Ipv6Header::new_unchecked([0; IPV6_HEADER_LEN]);
+        header.adjust_version();
+        header.set_next_header(IpProtocol::UDP);
+        header.set_hop_limit(64);
+        header.set_source_ip(&peer.into());
+        header.set_dest_ip(&remote.into());
+        let ippkt = Ipv6Packet::prepend_header(buf, &header);
+        let mut ethpkt = EtherPacket::prepend_header(ippkt.release(), &ETHER_HEADER_TEMPLATE);
+        ethpkt.set_source_mac(MAC_A);
+        ethpkt.set_dest_mac(MAC_R);
+        ethpkt.set_ethertype(EtherType::IPV6);
+
+        assert_eq!(
+            classify(&frame, &conf),
+            Some(Incoming::Ipv6 {
+                dst: remote,
+                hop_limit: 64,
+                len: frame_len - ETHER_HEADER_LEN,
+            })
+        );
+
+        let orig = &frame[ETHER_HEADER_LEN..];
+        assert!(icmpv6_error_allowed(orig));
+        let mut error = vec![0; icmpv6_error_len(orig)];
+        write_icmpv6_error(
+            &mut error,
+            MAC_R,
+            MAC_A,
+            local,
+            IcmpError::TooBig(1280),
+            orig,
+        );
+        assert_eq!(error.len(), ETHER_HEADER_LEN + 1280);
+
+        let ethpkt = EtherPacket::parse(Cursor::new(&error[..])).unwrap();
+        let ippkt = Ipv6Packet::parse(ethpkt.payload()).unwrap();
+        assert_eq!(Ipv6Addr::from(ippkt.dest_ip()), peer);
+        let icmppkt = Icmpv6Packet::parse(ippkt.payload()).unwrap();
+        assert!(icmppkt.verify_checksum(local.into(), peer.into()));
+        assert_eq!(icmppkt.msg_type(), Icmpv6MsgType::PKT_TOO_BIG);
+        assert!(!icmpv6_error_allowed(&error[ETHER_HEADER_LEN..]));
+    }
+
+    #[test]
+    fn link_addr_options() {
+        let options = [
+            3, 1, 0, 0, 0, 0, 0, 0, // an unknown option
+            2, 1, 0x02, 0, 0, 0, 0, 0x0a,
+        ];
+        assert_eq!(link_addr_option(&options, 2), Some(MAC_A));
+        assert_eq!(link_addr_option(&options, 1), None);
+        // A zero length option is invalid.
+        assert_eq!(link_addr_option(&[2, 0, 0, 0, 0, 0, 0, 0], 2), None);
+    }
+}
@@ -0,0 +1,799 @@
+use std::collections::HashMap;
+
+use arrayvec::ArrayVec;
+use rpkt::ether::{EtherType, MacAddr, VlanStack};
+use rpkt::flow::FiveTuple;
+use rpkt::ipv4::{IpProtocol, Ipv4Packet};
+use rpkt::ipv6::Ipv6Packet;
+use rpkt::tcp::TcpPacket;
+use rpkt::tls::TlsClientHello;
+use rpkt::Cursor;
+use rpkt_dpdk::{Mbuf, RxQueue, TxQueue};
+use rpkt_time::{Duration, Instant};
+
+use crate::wire;
+
+/// The number of mbufs received or sent in a burst.
+const BATCH_SIZE: usize = 32;
+
+/// The interval of the aging timer of the flow table, in seconds.
+const AGING_INTERVAL_SECS: u64 = 1;
+
+/// The maximum length of a server name, as a DNS name in text form.
+const SERVER_NAME_LEN_MAX: usize = 253;
+
+/// The configuration of an [`SniBalancer`].
+#[derive(Debug, Clone, Copy)]
+pub struct SniBalancerConf {
+    /// The TCP port of the TLS service. The other packets are dropped.
+    pub port: u16,
+    /// The number of points of each backend on the [`HashRing`]. More points
+    /// spread the server names more evenly.
+    pub vnodes: usize,
+    /// How long a connection is pinned to its backend after its last packet.
+    pub idle_timeout: Duration,
+    /// The maximum number of pinned connections. Once the table is full, the
+    /// packets of new connections are forwarded by the hash of their 5-tuple
+    /// instead of their server name.
+    pub max_flows: usize,
+}
+
+impl Default for SniBalancerConf {
+    fn default() -> Self {
+        Self {
+            port: 443,
+            vnodes: 160,
+            idle_timeout: Duration::from_secs(300),
+            max_flows: 65536,
+        }
+    }
+}
+
+/// A consistent hash ring that maps keys to backends.
+///
+/// Each backend owns `vnodes` points of the ring, and a key belongs to the
+/// backend of the first point following its hash. Adding or removing a
+/// backend only moves the keys of the points it gains or loses, so the other
+/// keys keep their backends.
+#[derive(Debug, Clone)]
+pub struct HashRing {
+    points: Vec<(u32, usize)>,
+    vnodes: usize,
+}
+
+impl HashRing {
+    /// Create a ring without backends.
+    ///
+    /// # Panics
+    /// This function panics if `vnodes` is 0.
+    pub fn new(vnodes: usize) -> Self {
+        assert!(vnodes > 0);
+        Self {
+            points: Vec::new(),
+            vnodes,
+        }
+    }
+
+    /// Returns the number of backends on the ring.
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.points.len() / self.vnodes
+    }
+
+    /// Returns whether the ring has no backends.
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.points.is_empty()
+    }
+
+    /// Returns whether `backend` is on the ring.
+    pub fn contains(&self, backend: usize) -> bool {
+        self.points.iter().any(|(_, b)| *b == backend)
+    }
+
+    /// Add the points of `backend`, returns `false` if it is already on the
+    /// ring.
+    pub fn add(&mut self, backend: usize) -> bool {
+        if self.contains(backend) {
+            return false;
+        }
+        for vnode in 0..self.vnodes {
+            let mut point = [0; 16];
+            point[..8].copy_from_slice(&(backend as u64).to_be_bytes());
+            point[8..].copy_from_slice(&(vnode as u64).to_be_bytes());
+            self.points.push((hash32(&point), backend));
+        }
+        // Ties are broken by the backend, so the ring does not depend on the
+        // order of the insertions.
+        self.points.sort_unstable();
+        true
+    }
+
+    /// Remove the points of `backend`, returns `false` if it is not on the
+    /// ring.
+    pub fn remove(&mut self, backend: usize) -> bool {
+        let len = self.points.len();
+        self.points.retain(|(_, b)| *b != backend);
+        self.points.len() != len
+    }
+
+    /// Returns the backend of `key`.
+    #[inline]
+    pub fn lookup(&self, key: &[u8]) -> Option<usize> {
+        self.lookup_hash(hash32(key))
+    }
+
+    /// Returns the backend of a key whose hash is `hash`.
+    pub fn lookup_hash(&self, hash: u32) -> Option<usize> {
+        if self.points.is_empty() {
+            return None;
+        }
+        let idx = self.points.partition_point(|(point, _)| *point < hash);
+        Some(self.points[idx % self.points.len()].1)
+    }
+}
+
+// FNV-1a, followed by the finalizer of MurmurHash3 so that the close keys,
+// e.g. the points of a backend, are spread over the ring.
+fn hash32(data: &[u8]) -> u32 {
+    let mut hash: u32 = 0x811c9dc5;
+    for b in data {
+        hash ^= u32::from(*b);
+        hash = hash.wrapping_mul(0x01000193);
+    }
+    hash ^= hash >> 16;
+    hash = hash.wrapping_mul(0x85ebca6b);
+    hash ^= hash >> 13;
+    hash = hash.wrapping_mul(0xc2b2ae35);
+    hash ^ (hash >> 16)
+}
+
+/// An entry of the [`FlowTable`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FlowEntry {
+    backend: usize,
+    updated: Instant,
+}
+
+impl FlowEntry {
+    /// Returns the backend that the connection is pinned to.
+    #[inline]
+    pub fn backend(&self) -> usize {
+        self.backend
+    }
+
+    /// Returns the instant of the last packet of the connection.
+    #[inline]
+    pub fn updated(&self) -> Instant {
+        self.updated
+    }
+}
+
+/// A table that pins connections to backends, with an idle timeout.
+///
+/// The table is keyed on the symmetric 5-tuple, so that both directions of a
+/// connection find the same entry.
+pub struct FlowTable {
+    entries: HashMap<FiveTuple, FlowEntry>,
+    max_flows: usize,
+    idle_timeout: Duration,
+}
+
+impl FlowTable {
+    /// Create an empty table.
+    pub fn new(max_flows: usize, idle_timeout: Duration) -> Self {
+        Self {
+            entries: HashMap::new(),
+            max_flows,
+            idle_timeout,
+        }
+    }
+
+    /// Returns the number of pinned connections.
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    /// Returns whether the table is empty.
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// Returns the entry of the connection `tuple`.
+    #[inline]
+    pub fn get(&self, tuple: &FiveTuple) -> Option<&FlowEntry> {
+        self.entries.get(&tuple.symmetric())
+    }
+
+    /// Returns an iterator over the 5-tuples and entries.
+    pub fn iter(&self) -> impl Iterator<Item = (&FiveTuple, &FlowEntry)> {
+        self.entries.iter()
+    }
+
+    /// Pin the connection `tuple` to `backend`.
+    ///
+    /// A pinned connection is moved to `backend`. Returns `false` if the
+    /// connection is new and the table is full.
+    pub fn pin(&mut self, tuple: &FiveTuple, backend: usize, now: Instant) -> bool {
+        let len = self.entries.len();
+        match self.entries.get_mut(&tuple.symmetric()) {
+            Some(entry) => {
+                entry.backend = backend;
+                entry.updated = now;
+                true
+            }
+            None if len < self.max_flows => {
+                self.entries.insert(
+                    tuple.symmetric(),
+                    FlowEntry {
+                        backend,
+                        updated: now,
+                    },
+                );
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Look up the backend of the connection `tuple`, ignoring the expired
+    /// entries, and refresh the entry.
+    #[inline]
+    pub fn lookup(&mut self, tuple: &FiveTuple, now: Instant) -> Option<usize> {
+        let idle_timeout = self.idle_timeout;
+        let entry = self
+            .entries
+            .get_mut(&tuple.symmetric())
+            .filter(|entry| now.saturating_cycles_since(entry.updated) < idle_timeout)?;
+        entry.updated = entry.updated.max(now);
+        Some(entry.backend)
+    }
+
+    /// Remove the expired entries, returns the number of removed entries.
+    ///
+    /// This method walks through the whole table, so it is supposed to be called
+    /// periodically.
+    pub fn age(&mut self, now: Instant) -> usize {
+        let len = self.entries.len();
+        let idle_timeout = self.idle_timeout;
+        self.entries
+            .retain(|_, entry| now.saturating_cycles_since(entry.updated) < idle_timeout);
+        len - self.entries.len()
+    }
+
+    /// Remove the entries pinned to `backend`, returns the number of removed
+    /// entries.
+    pub fn flush_backend(&mut self, backend: usize) -> usize {
+        let len = self.entries.len();
+        self.entries.retain(|_, entry| entry.backend != backend);
+        len - self.entries.len()
+    }
+
+    /// Remove all the entries.
+    #[inline]
+    pub fn clear(&mut self) {
+        self.entries.clear()
+    }
+}
+
+/// The counters of an [`SniBalancer`].
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct SniBalancerStats {
+    /// The number of received frames.
+    pub rx_packets: u64,
+    /// The number of bytes of the received frames.
+    pub rx_bytes: u64,
+    /// The number of frames of the pinned connections.
+    pub pinned: u64,
+    /// The number of connections pinned by the server name of their
+    /// ClientHello.
+    pub classified: u64,
+    /// The number of frames forwarded by the hash of their 5-tuple, either
+    /// because their connection has not sent its ClientHello yet, or because
+    /// its first segment is not a ClientHello with a server name.
+    pub unclassified: u64,
+    /// The number of new connections that are not pinned because the flow
+    /// table is full.
+    pub table_full: u64,
+    /// The number of received frames that are dropped, either because they are
+    /// not sent to the TLS port, or because there is no backend.
+    pub filtered: u64,
+}
+
+/// The counters of a backend of an [`SniBalancer`].
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct SniBackendStats {
+    /// The number of sent frames.
+    pub tx_packets: u64,
+    /// The number of bytes of the sent frames.
+    pub tx_bytes: u64,
+    /// The number of frames that are dropped because the tx queue is full.
+    pub tx_dropped: u64,
+}
+
+struct SniBackend {
+    txq: TxQueue,
+    mac: MacAddr,
+    tx_batch: ArrayVec<Mbuf, BATCH_SIZE>,
+    stats: SniBackendStats,
+}
+
+impl SniBackend {
+    fn enqueue(&mut self, mut mbuf: Mbuf) {
+        if self.tx_batch.is_full() {
+            self.flush();
+        }
+        wire::set_dest_mac(mbuf.data_mut(), self.mac);
+        self.tx_batch.push(mbuf);
+    }
+
+    fn flush(&mut self) {
+        if self.tx_batch.is_empty() {
+            return;
+        }
+        let bytes: usize = self.tx_batch.iter().map(|mbuf| mbuf.len()).sum();
+        let nb_tx = self.txq.tx(&mut self.tx_batch);
+        let unsent: usize = self.tx_batch.iter().map(|mbuf| mbuf.len()).sum();
+
+        self.stats.tx_packets += nb_tx as u64;
+        self.stats.tx_bytes += (bytes - unsent) as u64;
+        self.stats.tx_dropped += self.tx_batch.len() as u64;
+        self.tx_batch.clear();
+    }
+}
+
+/// A TLS load balancer that forwards the connections to backends by their
+/// server name, without terminating TLS.
+///
+/// The balancer receives the frames sent by the clients to a virtual IP
+/// address, and forwards them to the backends by rewriting their destination
+/// MAC address, so the backends must accept the virtual IP address and reply
+/// to the clients directly. The server name of the ClientHello selects the
+/// backend through a [`HashRing`], so that the connections to a name share
+/// the session caches of a backend, and the connection is then pinned to the
+/// backend in a [`FlowTable`] until it is idle for `idle_timeout`.
+///
+/// The balancer does not take part in the TCP handshake. The frames sent
+/// before the ClientHello are forwarded by the hash of their 5-tuple, so the
+/// backends must be able to take over the handshake of each other, e.g. with
+/// a shared SYN cookie secret.
+///
+/// The balancer is single-threaded and poll-driven, it only makes progress when
+/// [`SniBalancer::poll`] is called.
+///
+/// # Examples
+/// ```no_run
+/// use rpkt::ether::MacAddr;
+/// use rpkt_dpdk::service;
+/// use rpkt_stack::{SniBalancer, SniBalancerConf};
+///
+/// let mut balancer = SniBalancer::new(
+///     service().rx_queue(0, 0).unwrap(),
+///     SniBalancerConf::default(),
+/// );
+/// for (queue_id, mac) in [[0x02, 0, 0, 0, 0, 1], [0x02, 0, 0, 0, 0, 2]].iter().enumerate() {
+///     balancer.add_backend(service().tx_queue(1, queue_id as u16).unwrap(), MacAddr(*mac));
+/// }
+///
+/// loop {
+///     balancer.poll();
+/// }
+/// ```
+pub struct SniBalancer {
+    conf: SniBalancerConf,
+    rxq: RxQueue,
+    backends: Vec<SniBackend>,
+    ring: HashRing,
+    flows: FlowTable,
+    stats: SniBalancerStats,
+    next_aging: Option<Instant>,
+}
+
+impl SniBalancer {
+    /// Create a balancer without backends that serves the frames received by
+    /// `rxq`.
+    pub fn new(rxq: RxQueue, conf: SniBalancerConf) -> Self {
+        Self {
+            conf,
+            rxq,
+            backends: Vec::new(),
+            ring: HashRing::new(conf.vnodes),
+            flows: FlowTable::new(conf.max_flows, conf.idle_timeout),
+            stats: SniBalancerStats::default(),
+            next_aging: None,
+        }
+    }
+
+    /// Returns the configuration of the balancer.
+    #[inline]
+    pub fn conf(&self) -> &SniBalancerConf {
+        &self.conf
+    }
+
+    /// Add a backend reached through `txq` at `mac`, and returns the index of
+    /// the backend.
+    pub fn add_backend(&mut self, txq: TxQueue, mac: MacAddr) -> usize {
+        let backend = self.backends.len();
+        self.backends.push(SniBackend {
+            txq,
+            mac,
+            tx_batch: ArrayVec::new(),
+            stats: SniBackendStats::default(),
+        });
+        self.ring.add(backend);
+        backend
+    }
+
+    /// Stop forwarding new connections to `backend`.
+    ///
+    /// If `drain` is `false`, the connections pinned to the backend are also
+    /// removed, and move to other backends. Otherwise they stay on the backend
+    /// until they are idle. Returns `false` if the backend is already disabled.
+    pub fn disable_backend(&mut self, backend: usize, drain: bool) -> bool {
+        if !self.ring.remove(backend) {
+            return false;
+        }
+        if !drain {
+            self.flows.flush_backend(backend);
+        }
+        true
+    }
+
+    /// Forward new connections to a disabled `backend` again.
+    ///
+    /// Returns `false` if the backend does not exist or is already enabled.
+    pub fn enable_backend(&mut self, backend: usize) -> bool {
+        backend < self.backends.len() && self.ring.add(backend)
+    }
+
+    /// Returns the number of backends, including the disabled ones.
+    #[inline]
+    pub fn nb_backends(&self) -> usize {
+        self.backends.len()
+    }
+
+    /// Returns the hash ring of the enabled backends.
+    #[inline]
+    pub fn ring(&self) -> &HashRing {
+        &self.ring
+    }
+
+    /// Returns the table of the pinned connections.
+    #[inline]
+    pub fn flows(&self) -> &FlowTable {
+        &self.flows
+    }
+
+    /// Returns the counters of the balancer.
+    #[inline]
+    pub fn stats(&self) -> SniBalancerStats {
+        self.stats
+    }
+
+    /// Returns the counters of `backend`.
+    #[inline]
+    pub fn backend_stats(&self, backend: usize) -> Option<SniBackendStats> {
+        self.backends.get(backend).map(|backend| backend.stats)
+    }
+
+    /// Receive a burst of frames and forward them to the backends, returns the
+    /// number of received frames.
+    pub fn poll(&mut self) -> usize {
+        let now = Instant::now();
+        match self.next_aging {
+            Some(next_aging) if now < next_aging => {}
+            _ => {
+                self.flows.age(now);
+                self.next_aging = Some(now + Duration::from_secs(AGING_INTERVAL_SECS));
+            }
+        }
+
+        let mut batch = ArrayVec::<Mbuf, BATCH_SIZE>::new();
+        let nb_rx = self.rxq.rx(&mut batch);
+        for mbuf in batch.drain(..) {
+            self.stats.rx_packets += 1;
+            self.stats.rx_bytes += mbuf.len() as u64;
+            if let Some(backend) = self.route(mbuf.data(), now) {
+                self.backends[backend].enqueue(mbuf);
+            }
+        }
+
+        for backend in self.backends.iter_mut() {
+            backend.flush();
+        }
+        nb_rx
+    }
+
+    /// Decide the backend of `frame`, and pin its connection if the frame
+    /// carries a ClientHello. Returns `None` if the frame should be dropped.
+    ///
+    /// `poll` calls this method for every received frame. It can also be used
+    /// to drive the balancer with a custom rx/tx loop.
+    pub fn route(&mut self, frame: &[u8], now: Instant) -> Option<usize> {
+        let Some((tuple, payload)) = parse_tcp(frame)
+            .filter(|(tuple, _)| tuple.dst_port == self.conf.port && !self.ring.is_empty())
+        else {
+            self.stats.filtered += 1;
+            return None;
+        };
+
+        if let Some(backend) = self.flows.lookup(&tuple, now) {
+            self.stats.pinned += 1;
+            return Some(backend);
+        }
+
+        let flow_hash = hash32(&tuple_bytes(&tuple));
+        if payload.is_empty() {
+            self.stats.unclassified += 1;
+            return self.ring.lookup_hash(flow_hash);
+        }
+
+        // The first segment with data pins the connection, by its server name
+        // if it is a ClientHello.
+        let mut name = [0; SERVER_NAME_LEN_MAX];
+        let backend = match TlsClientHello::parse(payload)
+            .and_then(|hello| normalize_name(hello.server_name()?, &mut name))
+        {
+            Some(name) => {
+                self.stats.classified += 1;
+                self.ring.lookup(name)
+            }
+            None => {
+                self.stats.unclassified += 1;
+                self.ring.lookup_hash(flow_hash)
+            }
+        }?;
+        if !self.flows.pin(&tuple, backend, now) {
+            self.stats.table_full += 1;
+        }
+        Some(backend)
+    }
+}
+
+// Parse the 5-tuple and the payload of the TCP segment of an Ethernet frame.
+fn parse_tcp(frame: &[u8]) -> Option<(FiveTuple, &[u8])> {
+    let stack = VlanStack::parse(frame)?;
+    let packet = &frame[stack.header_len()..];
+    let segment = match stack.ethertype() {
+        EtherType::IPV4 => {
+            let ippkt = Ipv4Packet::parse(Cursor::new(packet)).ok()?;
+            if ippkt.protocol() != IpProtocol::TCP || ippkt.more_frags() || ippkt.frag_offset() != 0
+            {
+                return None;
+            }
+            ippkt.payload()
+        }
+        EtherType::IPV6 => {
+            let ippkt = Ipv6Packet::parse(Cursor::new(packet)).ok()?;
+            if ippkt.next_header() != IpProtocol::TCP {
+                return None;
+            }
+            ippkt.payload()
+        }
+        _ => return None,
+    };
+    let tuple = FiveTuple::from_ip(packet)?;
+    let tcppkt = TcpPacket::parse(segment).ok()?;
+    Some((tuple, tcppkt.payload().chunk_shared_lifetime()))
+}
+
+fn tuple_bytes(tuple: &FiveTuple) -> [u8; 37] {
+    let mut bytes = [0; 37];
+    bytes[..16].copy_from_slice(&tuple.src_addr);
+    bytes[16..32].copy_from_slice(&tuple.dst_addr);
+    bytes[32..34].copy_from_slice(&tuple.src_port.to_be_bytes());
+    bytes[34..36].copy_from_slice(&tuple.dst_port.to_be_bytes());
+    bytes[36] = tuple.protocol;
+    bytes
+}
+
+// Write `name` in lowercase without the trailing dot into `buf`, so that the
+// spellings of a name select the same backend.
+fn normalize_name<'a>(name: &str, buf: &'a mut [u8; SERVER_NAME_LEN_MAX]) -> Option<&'a [u8]> {
+    let name = name.strip_suffix('.').unwrap_or(name).as_bytes();
+    let buf = buf.get_mut(..name.len())?;
+    buf.copy_from_slice(name);
+    buf.make_ascii_lowercase();
+    Some(buf)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const CLIENT: [u8; 4] = [192, 0, 2, 1];
+
+    fn client_hello(name: &str) -> Vec<u8> {
+        let mut sni = ((name.len() + 3) as u16).to_be_bytes().to_vec();
+        sni.push(0);
+        sni.extend_from_slice(&(name.len() as u16).to_be_bytes());
+        sni.extend_from_slice(name.as_bytes());
+
+        let mut body = vec![0x03, 0x03];
+        body.extend_from_slice(&[0; 33]);
+        body.extend_from_slice(&[0x00, 0x02, 0x13, 0x01, 0x01, 0x00]);
+        body.extend_from_slice(&((sni.len() + 4) as u16).to_be_bytes());
+        body.extend_from_slice(&[0x00, 0x00]);
+        body.extend_from_slice(&(sni.len() as u16).to_be_bytes());
+        body.extend_from_slice(&sni);
+
+        let mut record = vec![0x16, 0x03, 0x01];
+        record.extend_from_slice(&((body.len() + 4) as u16).to_be_bytes());
+        record.extend_from_slice(&[0x01, 0x00]);
+        record.extend_from_slice(&(body.len() as u16).to_be_bytes());
+        record.extend_from_slice(&body);
+        record
+    }
+
+    // An Ethernet frame with a TCP segment from port `src_port` of the client.
+    fn frame(src_port: u16, dst_port: u16, payload: &[u8]) -> Vec<u8> {
+        let mut frame = vec![0; 12];
+        frame.extend_from_slice(&[0x08, 0x00]);
+        let total_len = (20 + 20 + payload.len()) as u16;
+        frame.extend_from_slice(&[0x45, 0x00]);
+        frame.extend_from_slice(&total_len.to_be_bytes());
+        frame.extend_from_slice(&[0, 0, 0x40, 0x00, 64, 6, 0, 0]);
+        frame.extend_from_slice(&CLIENT);
+        frame.extend_from_slice(&[198, 51, 100, 1]);
+        frame.extend_from_slice(&src_port.to_be_bytes());
+        frame.extend_from_slice(&dst_port.to_be_bytes());
+        frame.extend_from_slice(&[0, 0, 0, 1, 0, 0, 0, 0, 0x50, 0x18, 0xff, 0xff, 0, 0, 0, 0]);
+        frame.extend_from_slice(payload);
+        frame
+    }
+
+    fn balancer(nb_backends: usize, conf: SniBalancerConf) -> SniBalancer {
+        let mut balancer = SniBalancer::new(RxQueue, conf);
+        for backend in 0..nb_backends {
+            balancer.add_backend(TxQueue, MacAddr([0x02, 0, 0, 0, 0, backend as u8]));
+        }
+        balancer
+    }
+
+    #[test]
+    fn hash_ring() {
+        let mut ring = HashRing::new(100);
+        assert_eq!(ring.lookup(b"example.com"), None);
+        for backend in 0..4 {
+            assert!(ring.add(backend));
+        }
+        assert!(!ring.add(3));
+        assert_eq!(ring.len(), 4);
+
+        let keys: Vec<String> = (0..1000)
+            .map(|i| format!("host{}.example.com", i))
+            .collect();
+        let before: Vec<usize> = keys
+            .iter()
+            .map(|k| ring.lookup(k.as_bytes()).unwrap())
+            .collect();
+        let mut counts = [0; 4];
+        before.iter().for_each(|backend| counts[*backend] += 1);
+        assert!(counts.iter().all(|count| *count > 150), "{:?}", counts);
+
+        // Only the keys of the removed backend move.
+        assert!(ring.remove(2));
+        assert!(!ring.remove(2));
+        for (key, backend) in keys.iter().zip(before.iter()) {
+            let after = ring.lookup(key.as_bytes()).unwrap();
+            if *backend == 2 {
+                assert_ne!(after, 2);
+            } else {
+                assert_eq!(after, *backend);
+            }
+        }
+
+        // The ring does not depend on the order of the insertions.
+        let mut other = HashRing::new(100);
+        for backend in [3, 1, 0, 2] {
+            other.add(backend);
+        }
+        ring.add(2);
+        assert!(keys
+            .iter()
+            .all(|key| ring.lookup(key.as_bytes()) == other.lookup(key.as_bytes())));
+    }
+
+    #[test]
+    fn flow_table() {
+        let mut table = FlowTable::new(1, Duration::from_secs(10));
+        let now = Instant::now();
+        let tuple = parse_tcp(&frame(1000, 443, &[])).unwrap().0;
+        let other = parse_tcp(&frame(1001, 443, &[])).unwrap().0;
+
+        assert!(table.pin(&tuple, 1, now));
+        assert!(!table.pin(&other, 0, now));
+        assert!(table.pin(&tuple, 2, now));
+
+        // The reverse direction finds the same entry.
+        let reverse = FiveTuple {
+            src_addr: tuple.dst_addr,
+            dst_addr: tuple.src_addr,
+            src_port: tuple.dst_port,
+            dst_port: tuple.src_port,
+            protocol: tuple.protocol,
+        };
+        assert_eq!(table.lookup(&reverse, now), Some(2));
+
+        let later = now + Duration::from_secs(10);
+        assert_eq!(table.lookup(&tuple, later), None);
+        assert_eq!(table.age(later), 1);
+        assert!(table.is_empty());
+
+        assert!(table.pin(&other, 0, later));
+        assert_eq!(table.flush_backend(0), 1);
+    }
+
+    #[test]
+    fn route_by_server_name() {
+        let mut balancer = balancer(4, SniBalancerConf::default());
+        let now = Instant::now();
+
+        // The connections to a name go to the same backend, whatever the case
+        // of the name.
+        let syn = frame(1000, 443, &[]);
+        assert!(balancer.route(&syn, now).is_some());
+        let backend = balancer
+            .route(&frame(1000, 443, &client_hello("www.example.com")), now)
+            .unwrap();
+        assert_eq!(balancer.ring().lookup(b"www.example.com"), Some(backend));
+        let other = balancer
+            .route(&frame(2000, 443, &client_hello("WWW.Example.com.")), now)
+            .unwrap();
+        assert_eq!(other, backend);
+
+        // The following segments are pinned.
+        assert_eq!(
+            balancer.route(&frame(1000, 443, b"data"), now),
+            Some(backend)
+        );
+        assert_eq!(balancer.route(&syn, now), Some(backend));
+        assert_eq!(balancer.flows().len(), 2);
+
+        // A connection without a ClientHello is pinned by its 5-tuple.
+        let plain = balancer.route(&frame(3000, 443, b"GET /"), now).unwrap();
+        assert_eq!(balancer.route(&frame(3000, 443, b"HTTP"), now), Some(plain));
+
+        // Other ports and non-TCP frames are dropped.
+        assert_eq!(balancer.route(&frame(1000, 80, &[]), now), None);
+        assert_eq!(balancer.route(&[0; 14], now), None);
+
+        assert_eq!(
+            balancer.stats(),
+            SniBalancerStats {
+                pinned: 3,
+                classified: 2,
+                unclassified: 2,
+                filtered: 2,
+                ..Default::default()
+            }
+        );
+    }
+
+    #[test]
+    fn disable_backend() {
+        let mut balancer = balancer(2, SniBalancerConf::default());
+        let now = Instant::now();
+        let hello = frame(1000, 443, &client_hello("example.com"));
+        let backend = balancer.route(&hello, now).unwrap();
+
+        // A draining backend keeps its connections, but gets no new ones.
+        assert!(balancer.disable_backend(backend, true));
+        assert!(!balancer.disable_backend(backend, true));
+        assert_eq!(balancer.route(&hello, now), Some(backend));
+        let new_conn = frame(1001, 443, &client_hello("example.com"));
+        assert_eq!(balancer.route(&new_conn, now), Some(1 - backend));
+
+        assert!(balancer.enable_backend(backend));
+        assert!(balancer.disable_backend(backend, false));
+        assert_eq!(balancer.route(&hello, now), Some(1 - backend));
+
+        // Without backends, all the frames are dropped.
+        assert!(balancer.disable_backend(1 - backend, false));
+        assert!(balancer.flows().is_empty());
+        assert_eq!(balancer.route(&hello, now), None);
+        assert!(!balancer.enable_backend(2));
+    }
+}
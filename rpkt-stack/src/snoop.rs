@@ -0,0 +1,685 @@
+use std::collections::HashMap;
+use std::net::IpAddr;
+
+use rpkt::ether::{EtherType, MacAddr, VlanStack};
+use rpkt::ipv4::{IpProtocol, Ipv4Header, IPV4_HEADER_LEN};
+use rpkt::ipv6::{Ipv6Header, IPV6_HEADER_LEN};
+use rpkt_time::{Duration, Instant};
+
+// The IGMP message types (RFC 2236, RFC 3376).
+const IGMP_QUERY: u8 = 0x11;
+const IGMP_V1_REPORT: u8 = 0x12;
+const IGMP_V2_REPORT: u8 = 0x16;
+const IGMP_LEAVE: u8 = 0x17;
+const IGMP_V3_REPORT: u8 = 0x22;
+
+// The MLD message types (RFC 2710, RFC 3810).
+const MLD_QUERY: u8 = 130;
+const MLD_V1_REPORT: u8 = 131;
+const MLD_DONE: u8 = 132;
+const MLD_V2_REPORT: u8 = 143;
+
+// The group record types of IGMPv3 and MLDv2 reports.
+const MODE_IS_INCLUDE: u8 = 1;
+const CHANGE_TO_INCLUDE: u8 = 3;
+const BLOCK_OLD_SOURCES: u8 = 6;
+
+/// The configuration of a [`GroupTable`].
+#[derive(Debug, Clone, Copy)]
+pub struct SnoopConf {
+    /// How long a port stays a member of a group after the last report received on
+    /// it.
+    pub membership_time: Duration,
+    /// How long a port stays a router port after the last query received on it.
+    pub querier_time: Duration,
+    /// The maximum number of groups. Once the table is full, the reports of new
+    /// groups are ignored, and the frames sent to them are flooded.
+    pub max_groups: usize,
+}
+
+impl Default for SnoopConf {
+    fn default() -> Self {
+        // The default group membership interval and other querier present interval
+        // of RFC 3376 and RFC 3810.
+        Self {
+            membership_time: Duration::from_secs(260),
+            querier_time: Duration::from_secs(255),
+            max_groups: 1024,
+        }
+    }
+}
+
+/// An entry of the [`GroupTable`], the member ports of a group.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct GroupEntry {
+    // The member ports, and the instants of the last reports received on them.
+    members: Vec<(usize, Instant)>,
+}
+
+impl GroupEntry {
+    /// Returns an iterator over the member ports.
+    pub fn ports(&self) -> impl Iterator<Item = usize> + '_ {
+        self.members.iter().map(|(port, _)| *port)
+    }
+
+    /// Returns the instant of the last report received on `port`.
+    pub fn updated(&self, port: usize) -> Option<Instant> {
+        self.members
+            .iter()
+            .find(|(member, _)| *member == port)
+            .map(|(_, updated)| *updated)
+    }
+}
+
+/// A multicast group membership table for IGMP and MLD snooping (RFC 4541).
+///
+/// The table learns the member ports of each group from the IGMP and MLD reports,
+/// and the router ports from the queries. The multicast frames sent to a known group
+/// are then forwarded to the member ports and the router ports only, instead of
+/// being flooded. The memberships are aged out after `membership_time` unless
+/// they are refreshed by new reports.
+///
+/// The table is keyed on the VLAN ID and the group address, so that each VLAN is a
+/// separate snooping domain. Untagged frames belong to VLAN 0.
+pub struct GroupTable {
+    groups: HashMap<(u16, IpAddr), GroupEntry>,
+    routers: HashMap<(u16, usize), Instant>,
+    conf: SnoopConf,
+}
+
+impl GroupTable {
+    /// Create an empty table.
+    pub fn new(conf: SnoopConf) -> Self {
+        Self {
+            groups: HashMap::new(),
+            routers: HashMap::new(),
+            conf,
+        }
+    }
+
+    /// Returns the number of groups.
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.groups.len()
+    }
+
+    /// Returns whether the table is empty.
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.groups.is_empty()
+    }
+
+    /// Returns the entry of `group` in VLAN `vid`.
+    #[inline]
+    pub fn get(&self, vid: u16, group: IpAddr) -> Option<&GroupEntry> {
+        self.groups.get(&(vid, group))
+    }
+
+    /// Returns an iterator over the VLAN IDs, group addresses and entries.
+    pub fn iter(&self) -> impl Iterator<Item = (u16, IpAddr, &GroupEntry)> {
+        self.groups
+            .iter()
+            .map(|((vid, group), entry)| (*vid, *group, entry))
+    }
+
+    /// Add `port` to `group` in VLAN `vid`, or refresh its membership.
+    ///
+    /// Returns `false` if the group is new and the table is full.
+    pub fn join(&mut self, vid: u16, group: IpAddr, port: usize, now: Instant) -> bool {
+        let len = self.groups.len();
+        match self.groups.get_mut(&(vid, group)) {
+            Some(entry) => {
+                match entry.members.iter_mut().find(|(member, _)| *member == port) {
+                    Some((_, updated)) => *updated = now,
+                    None => entry.members.push((port, now)),
+                }
+                true
+            }
+            None if len < self.conf.max_groups => {
+                let members = vec![(port, now)];
+                self.groups.insert((vid, group), GroupEntry { members });
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Remove `port` from `group` in VLAN `vid`, the group is removed with its last
+    /// member.
+    pub fn leave(&mut self, vid: u16, group: IpAddr, port: usize) {
+        if let Some(entry) = self.groups.get_mut(&(vid, group)) {
+            entry.members.retain(|(member, _)| *member != port);
+            if entry.members.is_empty() {
+                self.groups.remove(&(vid, group));
+            }
+        }
+    }
+
+    /// Mark `port` as a router port of VLAN `vid`, or refresh it.
+    pub fn add_router_port(&mut self, vid: u16, port: usize, now: Instant) {
+        self.routers.insert((vid, port), now);
+    }
+
+    /// Returns whether `port` is a router port of VLAN `vid`, ignoring the expired
+    /// router ports.
+    pub fn is_router_port(&self, vid: u16, port: usize, now: Instant) -> bool {
+        self.routers
+            .get(&(vid, port))
+            .is_some_and(|updated| now.saturating_cycles_since(*updated) < self.conf.querier_time)
+    }
+
+    /// Remove the expired memberships and router ports, returns the number of removed
+    /// groups.
+    ///
+    /// This method walks through the whole table, so it is supposed to be called
+    /// periodically.
+    pub fn age(&mut self, now: Instant) -> usize {
+        let len = self.groups.len();
+        let membership_time = self.conf.membership_time;
+        self.groups.retain(|_, entry| {
+            entry
+                .members
+                .retain(|(_, updated)| now.saturating_cycles_since(*updated) < membership_time);
+            !entry.members.is_empty()
+        });
+        let querier_time = self.conf.querier_time;
+        self.routers
+            .retain(|_, updated| now.saturating_cycles_since(*updated) < querier_time);
+        len - self.groups.len()
+    }
+
+    /// Remove the memberships and the router port of `port`, e.g. when the link of the
+    /// port is down.
+    pub fn flush_port(&mut self, port: usize) {
+        self.groups.retain(|_, entry| {
+            entry.members.retain(|(member, _)| *member != port);
+            !entry.members.is_empty()
+        });
+        self.routers.retain(|(_, router), _| *router != port);
+    }
+
+    /// Remove all the groups and router ports.
+    pub fn clear(&mut self) {
+        self.groups.clear();
+        self.routers.clear();
+    }
+
+    /// Snoop `frame` received on `in_port`, and decide to which ports it is forwarded.
+    ///
+    /// The IGMP and MLD messages update the table: a report adds `in_port` to the
+    /// reported groups, a leave or done message removes it right away, and a query
+    /// marks `in_port` as a router port. The reports and leaves are forwarded to the
+    /// router ports only, while the queries are flooded.
+    ///
+    /// Returns `true` if the frame is forwarded to `out_ports` only, which may be
+    /// empty. Returns `false` if the frame is flooded, e.g. because it is not a
+    /// multicast frame, or because it is sent to an unknown group or to a link-local
+    /// group.
+    pub fn snoop(
+        &mut self,
+        in_port: usize,
+        frame: &[u8],
+        now: Instant,
+        out_ports: &mut Vec<usize>,
+    ) -> bool {
+        let (vid, payload) = match parse_multicast(frame) {
+            Some(res) => res,
+            None => return false,
+        };
+        let is_query = match payload {
+            Multicast::Data(group) => {
+                if !is_snooped(group) {
+                    return false;
+                }
+                let entry = match self.groups.get(&(vid, group)) {
+                    Some(entry) => entry,
+                    None => return false,
+                };
+                let membership_time = self.conf.membership_time;
+                out_ports.extend(
+                    entry
+                        .members
+                        .iter()
+                        .filter(|(port, updated)| {
+                            *port != in_port
+                                && now.saturating_cycles_since(*updated) < membership_time
+                        })
+                        .map(|(port, _)| *port),
+                );
+                self.push_router_ports(vid, in_port, now, out_ports);
+                return true;
+            }
+            Multicast::Igmp(msg) => self.snoop_igmp(vid, in_port, msg, now),
+            Multicast::Mld(msg) => self.snoop_mld(vid, in_port, msg, now),
+        };
+        if is_query {
+            self.add_router_port(vid, in_port, now);
+            return false;
+        }
+        self.push_router_ports(vid, in_port, now, out_ports);
+        !out_ports.is_empty()
+    }
+
+    // Push the router ports of VLAN `vid` except `in_port` that are not in `out_ports`
+    // yet.
+    fn push_router_ports(
+        &self,
+        vid: u16,
+        in_port: usize,
+        now: Instant,
+        out_ports: &mut Vec<usize>,
+    ) {
+        for (&(router_vid, port), updated) in self.routers.iter() {
+            if router_vid == vid
+                && port != in_port
+                && now.saturating_cycles_since(*updated) < self.conf.querier_time
+                && !out_ports.contains(&port)
+            {
+                out_ports.push(port);
+            }
+        }
+    }
+
+    // Update the table with the IGMP message `msg`, returns whether it is a query.
+    fn snoop_igmp(&mut self, vid: u16, port: usize, msg: &[u8], now: Instant) -> bool {
+        if msg.len() < 8 {
+            return false;
+        }
+        let group = IpAddr::from([msg[4], msg[5], msg[6], msg[7]]);
+        match msg[0] {
+            IGMP_QUERY => return true,
+            IGMP_V1_REPORT | IGMP_V2_REPORT if is_snooped(group) => {
+                self.join(vid, group, port, now);
+            }
+            IGMP_LEAVE => self.leave(vid, group, port),
+            IGMP_V3_REPORT => for_each_record(msg, 4, |kind, nb_sources, addr| {
+                let mut bytes = [0; 4];
+                bytes.copy_from_slice(addr);
+                self.update_record(vid, IpAddr::from(bytes), kind, nb_sources, port, now);
+            }),
+            _ => {}
+        }
+        false
+    }
+
+    // Update the table with the MLD message `msg`, returns whether it is a query.
+    fn snoop_mld(&mut self, vid: u16, port: usize, msg: &[u8], now: Instant) -> bool {
+        if msg.len() < 8 {
+            return false;
+        }
+        if msg[0] == MLD_QUERY {
+            return true;
+        }
+        if msg[0] == MLD_V2_REPORT {
+            for_each_record(msg, 16, |kind, nb_sources, addr| {
+                let mut bytes = [0; 16];
+                bytes.copy_from_slice(addr);
+                self.update_record(vid, IpAddr::from(bytes), kind, nb_sources, port, now);
+            });
+            return false;
+        }
+        if msg.len() < 24 {
+            return false;
+        }
+        let mut bytes = [0; 16];
+        bytes.copy_from_slice(&msg[8..24]);
+        let group = IpAddr::from(bytes);
+        match msg[0] {
+            MLD_V1_REPORT if is_snooped(group) => {
+                self.join(vid, group, port, now);
+            }
+            MLD_DONE => self.leave(vid, group, port),
+            _ => {}
+        }
+        false
+    }
+
+    // Update the membership of `port` with a group record of an IGMPv3 or MLDv2
+    // report. The source lists are ignored, except that an include mode with no
+    // sources means leaving the group.
+    fn update_record(
+        &mut self,
+        vid: u16,
+        group: IpAddr,
+        kind: u8,
+        nb_sources: usize,
+        port: usize,
+        now: Instant,
+    ) {
+        if !is_snooped(group) {
+            return;
+        }
+        match kind {
+            MODE_IS_INCLUDE | CHANGE_TO_INCLUDE if nb_sources == 0 => self.leave(vid, group, port),
+            BLOCK_OLD_SOURCES => {}
+            _ => {
+                self.join(vid, group, port, now);
+            }
+        }
+    }
+}
+
+// The IP payload of a multicast frame.
+enum Multicast<'a> {
+    // An IGMP message.
+    Igmp(&'a [u8]),
+    // An MLD message.
+    Mld(&'a [u8]),
+    // A packet sent to the group.
+    Data(IpAddr),
+}
+
+// Parse the VLAN ID and the IP payload of a multicast frame.
+fn parse_multicast(frame: &[u8]) -> Option<(u16, Multicast<'_>)> {
+    let stack = VlanStack::parse(frame)?;
+    let dst = MacAddr::from_bytes(&frame[0..6]);
+    if !dst.is_multicast() || dst.is_broadcast() {
+        return None;
+    }
+    let vid = stack.outer_vid().unwrap_or(0);
+    let payload = &frame[stack.header_len()..];
+
+    match stack.ethertype() {
+        EtherType::IPV4 => {
+            let header = Ipv4Header::new(payload).ok()?;
+            let header_len = usize::from(header.header_len());
+            if !header.check_version() || header_len < IPV4_HEADER_LEN || payload.len() < header_len
+            {
+                return None;
+            }
+            if header.protocol() == IpProtocol::IGMP {
+                Some((vid, Multicast::Igmp(&payload[header_len..])))
+            } else {
+                let group = std::net::Ipv4Addr::from(header.dest_ip());
+                Some((vid, Multicast::Data(IpAddr::V4(group))))
+            }
+        }
+        EtherType::IPV6 => {
+            let header = Ipv6Header::new(payload).ok()?;
+            if !header.check_version() {
+                return None;
+            }
+            let mut next_header = header.next_header();
+            let mut rest = &payload[IPV6_HEADER_LEN..];
+            // MLD messages are sent with a router alert in a hop-by-hop options header.
+            if next_header == IpProtocol::HOPOPT {
+                let len = (usize::from(*rest.get(1)?) + 1) * 8;
+                if rest.len() < len {
+                    return None;
+                }
+                next_header = rest[0].into();
+                rest = &rest[len..];
+            }
+            let is_mld = next_header == IpProtocol::ICMPV6
+                && rest.first().is_some_and(|msg_type| {
+                    (MLD_QUERY..=MLD_DONE).contains(msg_type) || *msg_type == MLD_V2_REPORT
+                });
+            if is_mld {
+                Some((vid, Multicast::Mld(rest)))
+            } else {
+                let group = std::net::Ipv6Addr::from(header.dest_ip());
+                Some((vid, Multicast::Data(IpAddr::V6(group))))
+            }
+        }
+        _ => None,
+    }
+}
+
+// Returns whether the frames sent to `group` are forwarded to the members only. The
+// groups of the link-local scope, e.g. 224.0.0.1 or ff02::1, are always flooded.
+fn is_snooped(group: IpAddr) -> bool {
+    match group {
+        IpAddr::V4(group) => group.is_multicast() && group.octets()[..3] != [224, 0, 0],
+        IpAddr::V6(group) => group.is_multicast() && group.octets()[1] & 0x0f > 2,
+    }
+}
+
+// Call `f` with the record type, the number of sources and the group address of every
+// group record of an IGMPv3 or MLDv2 report, whose addresses are `addr_len` long.
+fn for_each_record<F: FnMut(u8, usize, &[u8])>(msg: &[u8], addr_len: usize, mut f: F) {
+    let nb_records = u16::from_be_bytes([msg[6], msg[7]]);
+    let mut records = &msg[8..];
+    for _ in 0..nb_records {
+        if records.len() < 4 + addr_len {
+            return;
+        }
+        let aux_len = usize::from(records[1]) * 4;
+        let nb_sources = usize::from(u16::from_be_bytes([records[2], records[3]]));
+        let len = 4 + addr_len * (1 + nb_sources) + aux_len;
+        if records.len() < len {
+            return;
+        }
+        f(records[0], nb_sources, &records[4..4 + addr_len]);
+        records = &records[len..];
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rpkt::ether::VlanTag;
+    use std::net::{Ipv4Addr, Ipv6Addr};
+
+    const MAC_A: MacAddr = MacAddr([0x02, 0, 0, 0, 0, 0x0a]);
+    const GROUP_V4: Ipv4Addr = Ipv4Addr::new(239, 1, 1, 1);
+    const GROUP_V6: Ipv6Addr = Ipv6Addr::new(0xff0e, 0, 0, 0, 0, 0, 0, 0x101);
+
+    fn ipv4_frame(dst: Ipv4Addr, protocol: u8, payload: &[u8], vid: Option<u16>) -> Vec<u8> {
+        let mut frame = Vec::new();
+        let o = dst.octets();
+        frame.extend_from_slice(&[0x01, 0x00, 0x5e, o[1] & 0x7f, o[2], o[3]]);
+        frame.extend_from_slice(&MAC_A.0);
+        if let Some(vid) = vid {
+            frame.extend_from_slice(&VlanTag::customer(vid).to_bytes());
+        }
+        frame.extend_from_slice(&[0x08, 0x00]);
+        let total_len = (IPV4_HEADER_LEN + payload.len()) as u16;
+        frame.extend_from_slice(&[0x45, 0, (total_len >> 8) as u8, total_len as u8]);
+        frame.extend_from_slice(&[0, 0, 0, 0, 1, protocol, 0, 0, 10, 0, 0, 1]);
+        frame.extend_from_slice(&o);
+        frame.extend_from_slice(payload);
+        frame
+    }
+
+    fn igmp(msg_type: u8, group: Ipv4Addr) -> Vec<u8> {
+        let mut msg = vec![msg_type, 0, 0, 0];
+        msg.extend_from_slice(&group.octets());
+        msg
+    }
+
+    fn mld_frame(msg: &[u8]) -> Vec<u8> {
+        let mut frame = vec![0x33, 0x33, 0, 0, 0, 0x16];
+        frame.extend_from_slice(&MAC_A.0);
+        frame.extend_from_slice(&[0x86, 0xdd]);
+        let payload_len = (8 + msg.len()) as u16;
+        frame.extend_from_slice(&[0x60, 0, 0, 0, (payload_len >> 8) as u8, payload_len as u8]);
+        frame.extend_from_slice(&[0, 1]);
+        frame.extend_from_slice(&[0xfe, 0x80, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 1]);
+        frame.extend_from_slice(&[0xff, 0x02, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0x16]);
+        // A hop-by-hop options header with a router alert.
+        frame.extend_from_slice(&[58, 0, 5, 2, 0, 0, 1, 0]);
+        frame.extend_from_slice(msg);
+        frame
+    }
+
+    fn ipv6_data_frame(dst: Ipv6Addr) -> Vec<u8> {
+        let o = dst.octets();
+        let mut frame = vec![0x33, 0x33, o[12], o[13], o[14], o[15]];
+        frame.extend_from_slice(&MAC_A.0);
+        frame.extend_from_slice(&[0x86, 0xdd, 0x60, 0, 0, 0, 0, 8, 17, 64]);
+        frame.extend_from_slice(&[0x20, 0x01, 0x0d, 0xb8, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 1]);
+        frame.extend_from_slice(&o);
+        frame.extend_from_slice(&[0; 8]);
+        frame
+    }
+
+    fn snoop(
+        table: &mut GroupTable,
+        in_port: usize,
+        frame: &[u8],
+        now: Instant,
+    ) -> Option<Vec<usize>> {
+        let mut out_ports = Vec::new();
+        if table.snoop(in_port, frame, now, &mut out_ports) {
+            out_ports.sort_unstable();
+            Some(out_ports)
+        } else {
+            assert!(out_ports.is_empty());
+            None
+        }
+    }
+
+    #[test]
+    fn igmp_snooping() {
+        let mut table = GroupTable::new(SnoopConf::default());
+        let now = Instant::now();
+        let data = ipv4_frame(GROUP_V4, 17, &[0; 8], None);
+
+        // An unknown group is flooded.
+        assert_eq!(snoop(&mut table, 0, &data, now), None);
+
+        // The query marks the router port, and is flooded.
+        let query = ipv4_frame(
+            Ipv4Addr::new(224, 0, 0, 1),
+            2,
+            &igmp(IGMP_QUERY, Ipv4Addr::UNSPECIFIED),
+            None,
+        );
+        assert_eq!(snoop(&mut table, 0, &query, now), None);
+        assert!(table.is_router_port(0, 0, now));
+
+        // The reports are sent to the router port only.
+        let report = ipv4_frame(GROUP_V4, 2, &igmp(IGMP_V2_REPORT, GROUP_V4), None);
+        assert_eq!(snoop(&mut table, 1, &report, now), Some(vec![0]));
+        assert_eq!(snoop(&mut table, 2, &report, now), Some(vec![0]));
+        let entry = table.get(0, GROUP_V4.into()).unwrap();
+        assert_eq!(entry.ports().collect::<Vec<_>>(), vec![1, 2]);
+        assert_eq!(entry.updated(1), Some(now));
+
+        // The data is sent to the members and the router port.
+        assert_eq!(snoop(&mut table, 1, &data, now), Some(vec![0, 2]));
+        assert_eq!(snoop(&mut table, 0, &data, now), Some(vec![1, 2]));
+
+        // The same group in another VLAN is unknown.
+        let tagged = ipv4_frame(GROUP_V4, 17, &[0; 8], Some(100));
+        assert_eq!(snoop(&mut table, 1, &tagged, now), None);
+
+        // A leave removes the member right away.
+        let leave = ipv4_frame(
+            Ipv4Addr::new(224, 0, 0, 2),
+            2,
+            &igmp(IGMP_LEAVE, GROUP_V4),
+            None,
+        );
+        assert_eq!(snoop(&mut table, 2, &leave, now), Some(vec![0]));
+        assert_eq!(snoop(&mut table, 0, &data, now), Some(vec![1]));
+
+        // The link-local groups are always flooded.
+        let local = ipv4_frame(Ipv4Addr::new(224, 0, 0, 251), 17, &[0; 8], None);
+        let report = ipv4_frame(
+            Ipv4Addr::new(224, 0, 0, 251),
+            2,
+            &igmp(IGMP_V2_REPORT, Ipv4Addr::new(224, 0, 0, 251)),
+            None,
+        );
+        snoop(&mut table, 1, &report, now);
+        assert_eq!(snoop(&mut table, 0, &local, now), None);
+        assert_eq!(table.len(), 1);
+
+        table.flush_port(1);
+        assert!(table.is_empty());
+        assert_eq!(snoop(&mut table, 0, &data, now), None);
+    }
+
+    #[test]
+    fn igmpv3_report() {
+        let mut table = GroupTable::new(SnoopConf::default());
+        let now = Instant::now();
+        let group_b = Ipv4Addr::new(239, 2, 2, 2);
+
+        // An exclude record with no sources joins GROUP_V4, and an include record with
+        // a source and an auxiliary word joins group_b.
+        let mut msg = vec![IGMP_V3_REPORT, 0, 0, 0, 0, 0, 0, 2];
+        msg.extend_from_slice(&[4, 0, 0, 0]);
+        msg.extend_from_slice(&GROUP_V4.octets());
+        msg.extend_from_slice(&[1, 1, 0, 1]);
+        msg.extend_from_slice(&group_b.octets());
+        msg.extend_from_slice(&[10, 0, 0, 9, 0, 0, 0, 0]);
+        let report = ipv4_frame(Ipv4Addr::new(224, 0, 0, 22), 2, &msg, None);
+        assert_eq!(snoop(&mut table, 3, &report, now), None);
+        assert_eq!(table.len(), 2);
+        assert!(table.get(0, group_b.into()).is_some());
+
+        // A change to include mode with no sources leaves the group.
+        let mut msg = vec![IGMP_V3_REPORT, 0, 0, 0, 0, 0, 0, 1, 3, 0, 0, 0];
+        msg.extend_from_slice(&GROUP_V4.octets());
+        let report = ipv4_frame(Ipv4Addr::new(224, 0, 0, 22), 2, &msg, None);
+        snoop(&mut table, 3, &report, now);
+        assert!(table.get(0, GROUP_V4.into()).is_none());
+
+        // A truncated record is ignored.
+        let mut msg = vec![IGMP_V3_REPORT, 0, 0, 0, 0, 0, 0, 1, 2, 0, 0, 1];
+        msg.extend_from_slice(&GROUP_V4.octets());
+        let report = ipv4_frame(Ipv4Addr::new(224, 0, 0, 22), 2, &msg, None);
+        snoop(&mut table, 3, &report, now);
+        assert!(table.get(0, GROUP_V4.into()).is_none());
+    }
+
+    #[test]
+    fn mld_snooping() {
+        let mut table = GroupTable::new(SnoopConf::default());
+        let now = Instant::now();
+
+        let mut query = vec![MLD_QUERY, 0, 0, 0, 0, 0, 0, 0];
+        query.extend_from_slice(&[0; 16]);
+        assert_eq!(snoop(&mut table, 0, &mld_frame(&query), now), None);
+        assert!(table.is_router_port(0, 0, now));
+
+        let mut report = vec![MLD_V2_REPORT, 0, 0, 0, 0, 0, 0, 1, 2, 0, 0, 0];
+        report.extend_from_slice(&GROUP_V6.octets());
+        assert_eq!(
+            snoop(&mut table, 1, &mld_frame(&report), now),
+            Some(vec![0])
+        );
+
+        let data = ipv6_data_frame(GROUP_V6);
+        assert_eq!(snoop(&mut table, 0, &data, now), Some(vec![1]));
+        assert_eq!(snoop(&mut table, 2, &data, now), Some(vec![0, 1]));
+
+        let mut done = vec![MLD_DONE, 0, 0, 0, 0, 0, 0, 0];
+        done.extend_from_slice(&GROUP_V6.octets());
+        snoop(&mut table, 1, &mld_frame(&done), now);
+        assert!(table.is_empty());
+
+        // ff02::1 is link-local.
+        let all_nodes = ipv6_data_frame(Ipv6Addr::new(0xff02, 0, 0, 0, 0, 0, 0, 1));
+        assert_eq!(snoop(&mut table, 0, &all_nodes, now), None);
+    }
+
+    #[test]
+    fn aging() {
+        let conf = SnoopConf {
+            membership_time: Duration::from_secs(10),
+            querier_time: Duration::from_secs(20),
+            max_groups: 1,
+        };
+        let mut table = GroupTable::new(conf);
+        let now = Instant::now();
+
+        assert!(table.join(0, GROUP_V4.into(), 1, now));
+        assert!(!table.join(0, GROUP_V6.into(), 1, now));
+        table.add_router_port(0, 0, now);
+
+        // An expired member no longer receives the data.
+        let later = now + Duration::from_secs(10);
+        let data = ipv4_frame(GROUP_V4, 17, &[0; 8], None);
+        assert_eq!(snoop(&mut table, 2, &data, now), Some(vec![0, 1]));
+        assert_eq!(snoop(&mut table, 2, &data, later), Some(vec![0]));
+
+        assert_eq!(table.age(later), 1);
+        assert!(table.is_empty());
+        assert!(table.is_router_port(0, 0, later));
+        assert!(!table.is_router_port(0, 0, now + Duration::from_secs(20)));
+        assert!(table.join(0, GROUP_V6.into(), 1, later));
+    }
+}
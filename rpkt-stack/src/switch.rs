@@ -0,0 +1,527 @@
+use std::collections::HashMap;
+
+use arrayvec::ArrayVec;
+use rpkt::ether::{MacAddr, VlanStack};
+use rpkt_dpdk::{Mbuf, Mempool, RxQueue, TxQueue};
+use rpkt_time::{Duration, Instant};
+
+use crate::snoop::{GroupTable, SnoopConf};
+
+/// The number of mbufs received or sent in a burst.
+const BATCH_SIZE: usize = 32;
+
+/// The interval of the aging timer of the MAC table, in seconds.
+const AGING_INTERVAL_SECS: u64 = 1;
+
+/// The configuration of an [`L2Switch`].
+#[derive(Debug, Clone, Copy)]
+pub struct SwitchConf {
+    /// How long a learned MAC address is kept after the last frame sent by it.
+    pub aging_time: Duration,
+    /// The maximum number of learned MAC addresses. Once the table is full, new
+    /// addresses are not learned, and the frames sent to them are flooded.
+    pub max_entries: usize,
+}
+
+impl Default for SwitchConf {
+    fn default() -> Self {
+        Self {
+            aging_time: Duration::from_secs(300),
+            max_entries: 8192,
+        }
+    }
+}
+
+/// An entry of the [`MacTable`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MacEntry {
+    port: usize,
+    updated: Instant,
+}
+
+impl MacEntry {
+    /// Returns the port on which the MAC address is learned.
+    #[inline]
+    pub fn port(&self) -> usize {
+        self.port
+    }
+
+    /// Returns the instant of the last frame sent by the MAC address.
+    #[inline]
+    pub fn updated(&self) -> Instant {
+        self.updated
+    }
+}
+
+/// A MAC learning table with aging.
+///
+/// The table is keyed on the VLAN ID and the MAC address, so that each VLAN is a
+/// separate learning domain. Untagged frames belong to VLAN 0.
+pub struct MacTable {
+    entries: HashMap<(u16, MacAddr), MacEntry>,
+    conf: SwitchConf,
+}
+
+impl MacTable {
+    /// Create an empty table.
+    pub fn new(conf: SwitchConf) -> Self {
+        Self {
+            entries: HashMap::new(),
+            conf,
+        }
+    }
+
+    /// Returns the number of learned MAC addresses.
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    /// Returns whether the table is empty.
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// Returns the entry of `mac` in VLAN `vid`.
+    #[inline]
+    pub fn get(&self, vid: u16, mac: MacAddr) -> Option<&MacEntry> {
+        self.entries.get(&(vid, mac))
+    }
+
+    /// Returns an iterator over the VLAN IDs, MAC addresses and entries.
+    pub fn iter(&self) -> impl Iterator<Item = (u16, MacAddr, &MacEntry)> {
+        self.entries
+            .iter()
+            .map(|((vid, mac), entry)| (*vid, *mac, entry))
+    }
+
+    /// Learn that `mac` in VLAN `vid` is reachable through `port`.
+    ///
+    /// A known address is moved to `port`. Returns `false` if the address is new
+    /// and the table is full.
+    pub fn learn(&mut self, vid: u16, mac: MacAddr, port: usize, now: Instant) -> bool {
+        let len = self.entries.len();
+        match self.entries.get_mut(&(vid, mac)) {
+            Some(entry) => {
+                entry.port = port;
+                entry.updated = now;
+                true
+            }
+            None if len < self.conf.max_entries => {
+                self.entries
+                    .insert((vid, mac), MacEntry { port, updated: now });
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Look up the port of `mac` in VLAN `vid`, ignoring the expired entries.
+    #[inline]
+    pub fn lookup(&self, vid: u16, mac: MacAddr, now: Instant) -> Option<usize> {
+        self.entries
+            .get(&(vid, mac))
+            .filter(|entry| !self.is_expired(entry, now))
+            .map(|entry| entry.port)
+    }
+
+    /// Remove the expired entries, returns the number of removed entries.
+    ///
+    /// This method walks through the whole table, so it is supposed to be called
+    /// periodically.
+    pub fn age(&mut self, now: Instant) -> usize {
+        let len = self.entries.len();
+        let aging_time = self.conf.aging_time;
+        self.entries
+            .retain(|_, entry| now.saturating_cycles_since(entry.updated) < aging_time);
+        len - self.entries.len()
+    }
+
+    /// Remove the entries learned on `port`, e.g. when the link of the port is down.
+    pub fn flush_port(&mut self, port: usize) {
+        self.entries.retain(|_, entry| entry.port != port);
+    }
+
+    /// Remove all the entries.
+    #[inline]
+    pub fn clear(&mut self) {
+        self.entries.clear()
+    }
+
+    #[inline]
+    fn is_expired(&self, entry: &MacEntry, now: Instant) -> bool {
+        now.saturating_cycles_since(entry.updated) >= self.conf.aging_time
+    }
+}
+
+/// The forwarding decision of a frame.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Verdict {
+    /// Send the frame to the port.
+    Forward(usize),
+    /// Send the frame to all the ports except the ingress port.
+    Flood,
+    /// Drop the frame, either because it is malformed, or because its destination
+    /// is on the ingress port.
+    Drop,
+}
+
+/// The counters of a port of an [`L2Switch`].
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct SwitchPortStats {
+    /// The number of received frames.
+    pub rx_packets: u64,
+    /// The number of bytes of the received frames.
+    pub rx_bytes: u64,
+    /// The number of sent frames.
+    pub tx_packets: u64,
+    /// The number of bytes of the sent frames.
+    pub tx_bytes: u64,
+    /// The number of received frames that are flooded.
+    pub flooded: u64,
+    /// The number of received multicast frames that are forwarded to the member
+    /// ports and router ports of their groups only, see [`L2Switch::enable_snooping`].
+    pub snooped: u64,
+    /// The number of received frames that are dropped, either because they are
+    /// malformed, or because their destinations are on the same port.
+    pub filtered: u64,
+    /// The number of frames that are dropped because the tx queue is full or the
+    /// mempool is exhausted.
+    pub tx_dropped: u64,
+}
+
+struct SwitchPort {
+    rxq: RxQueue,
+    txq: TxQueue,
+    mp: Mempool,
+    tx_batch: ArrayVec<Mbuf, BATCH_SIZE>,
+    stats: SwitchPortStats,
+}
+
+impl SwitchPort {
+    fn enqueue(&mut self, mbuf: Mbuf) {
+        if self.tx_batch.is_full() {
+            self.flush();
+        }
+        self.tx_batch.push(mbuf);
+    }
+
+    fn flush(&mut self) {
+        if self.tx_batch.is_empty() {
+            return;
+        }
+        let bytes: usize = self.tx_batch.iter().map(|mbuf| mbuf.len()).sum();
+        let nb_tx = self.txq.tx(&mut self.tx_batch);
+        let unsent: usize = self.tx_batch.iter().map(|mbuf| mbuf.len()).sum();
+
+        self.stats.tx_packets += nb_tx as u64;
+        self.stats.tx_bytes += (bytes - unsent) as u64;
+        self.stats.tx_dropped += self.tx_batch.len() as u64;
+        self.tx_batch.clear();
+    }
+}
+
+/// A learning Ethernet switch over a set of DPDK ports.
+///
+/// Each port of the switch is a pair of rx/tx queues. The switch learns the source
+/// MAC address of every received frame, forwards the frames to known unicast
+/// addresses, and floods the broadcast, multicast and unknown unicast frames to the
+/// other ports. The learned addresses are aged out after `aging_time`. With IGMP and
+/// MLD snooping enabled, the multicast frames of the known groups are only sent to
+/// the ports that joined them.
+///
+/// The switch is single-threaded and poll-driven, it only makes progress when
+/// [`L2Switch::poll`] is called.
+///
+/// # Examples
+/// ```no_run
+/// use rpkt_dpdk::service;
+/// use rpkt_stack::{L2Switch, SwitchConf};
+///
+/// let mut switch = L2Switch::new(SwitchConf::default());
+/// for port_id in 0..2 {
+///     switch.add_port(
+///         service().rx_queue(port_id, 0).unwrap(),
+///         service().tx_queue(port_id, 0).unwrap(),
+///         service().mempool("mp").unwrap(),
+///     );
+/// }
+///
+/// loop {
+///     switch.poll();
+/// }
+/// ```
+pub struct L2Switch {
+    table: MacTable,
+    groups: Option<GroupTable>,
+    ports: Vec<SwitchPort>,
+    out_ports: Vec<usize>,
+    next_aging: Option<Instant>,
+}
+
+impl L2Switch {
+    /// Create a switch without ports.
+    pub fn new(conf: SwitchConf) -> Self {
+        Self {
+            table: MacTable::new(conf),
+            groups: None,
+            ports: Vec::new(),
+            out_ports: Vec::new(),
+            next_aging: None,
+        }
+    }
+
+    /// Add a port, and returns the index of the port.
+    ///
+    /// The copies of the flooded frames sent to the port are allocated from `mp`.
+    pub fn add_port(&mut self, rxq: RxQueue, txq: TxQueue, mp: Mempool) -> usize {
+        self.ports.push(SwitchPort {
+            rxq,
+            txq,
+            mp,
+            tx_batch: ArrayVec::new(),
+            stats: SwitchPortStats::default(),
+        });
+        self.ports.len() - 1
+    }
+
+    /// Returns the number of ports.
+    #[inline]
+    pub fn nb_ports(&self) -> usize {
+        self.ports.len()
+    }
+
+    /// Returns the MAC table.
+    #[inline]
+    pub fn table(&self) -> &MacTable {
+        &self.table
+    }
+
+    /// Returns the MAC table for adding or removing entries.
+    #[inline]
+    pub fn table_mut(&mut self) -> &mut MacTable {
+        &mut self.table
+    }
+
+    /// Enable IGMP and MLD snooping.
+    ///
+    /// The switch then learns the group memberships from the IGMP and MLD messages,
+    /// and forwards the multicast frames of the known groups to their member ports
+    /// and the router ports only. See [`GroupTable::snoop`].
+    pub fn enable_snooping(&mut self, conf: SnoopConf) {
+        self.groups = Some(GroupTable::new(conf));
+    }
+
+    /// Returns the group table, if snooping is enabled.
+    #[inline]
+    pub fn groups(&self) -> Option<&GroupTable> {
+        self.groups.as_ref()
+    }
+
+    /// Returns the group table for adding or removing groups, if snooping is enabled.
+    #[inline]
+    pub fn groups_mut(&mut self) -> Option<&mut GroupTable> {
+        self.groups.as_mut()
+    }
+
+    /// Returns the counters of `port`.
+    #[inline]
+    pub fn port_stats(&self, port: usize) -> Option<SwitchPortStats> {
+        self.ports.get(port).map(|port| port.stats)
+    }
+
+    /// Receive a burst from every port and forward the frames, returns the number of
+    /// received frames.
+    pub fn poll(&mut self) -> usize {
+        let now = Instant::now();
+        match self.next_aging {
+            Some(next_aging) if now < next_aging => {}
+            _ => {
+                self.table.age(now);
+                if let Some(groups) = self.groups.as_mut() {
+                    groups.age(now);
+                }
+                self.next_aging = Some(now + Duration::from_secs(AGING_INTERVAL_SECS));
+            }
+        }
+
+        let mut total = 0;
+        let mut batch = ArrayVec::<Mbuf, BATCH_SIZE>::new();
+        for in_port in 0..self.ports.len() {
+            total += self.ports[in_port].rxq.rx(&mut batch);
+            for mbuf in batch.drain(..) {
+                let stats = &mut self.ports[in_port].stats;
+                stats.rx_packets += 1;
+                stats.rx_bytes += mbuf.len() as u64;
+
+                match classify(&mut self.table, in_port, mbuf.data(), now) {
+                    Verdict::Forward(out_port) if out_port < self.ports.len() => {
+                        self.ports[out_port].enqueue(mbuf);
+                    }
+                    Verdict::Forward(_) | Verdict::Drop => {
+                        self.ports[in_port].stats.filtered += 1;
+                    }
+                    Verdict::Flood => {
+                        let mut out_ports = std::mem::take(&mut self.out_ports);
+                        out_ports.clear();
+                        let snooped = self.groups.as_mut().is_some_and(|groups| {
+                            groups.snoop(in_port, mbuf.data(), now, &mut out_ports)
+                        });
+                        if snooped {
+                            self.ports[in_port].stats.snooped += 1;
+                            out_ports.retain(|port| *port < self.ports.len());
+                        } else {
+                            self.ports[in_port].stats.flooded += 1;
+                            out_ports.extend((0..self.ports.len()).filter(|port| *port != in_port));
+                        }
+                        self.replicate(mbuf, &out_ports);
+                        self.out_ports = out_ports;
+                    }
+                }
+            }
+        }
+
+        for port in self.ports.iter_mut() {
+            port.flush();
+        }
+        total
+    }
+
+    /// Learn the source address of `frame` received on `in_port`, and decide how to
+    /// forward the frame.
+    ///
+    /// `poll` calls this method for every received frame. It can also be used to
+    /// drive the switch with a custom rx/tx loop.
+    pub fn classify(&mut self, in_port: usize, frame: &[u8], now: Instant) -> Verdict {
+        classify(&mut self.table, in_port, frame, now)
+    }
+
+    // Send a copy of `mbuf` to every port of `out_ports`, the last port receives the
+    // original mbuf.
+    fn replicate(&mut self, mbuf: Mbuf, out_ports: &[usize]) {
+        let (last, others) = match out_ports.split_last() {
+            Some(res) => res,
+            None => return,
+        };
+        for out_port in others {
+            let port = &mut self.ports[*out_port];
+            match port.mp.try_alloc() {
+                Some(mut copy) => {
+                    copy.extend_from_slice(mbuf.data());
+                    port.enqueue(copy);
+                }
+                None => port.stats.tx_dropped += 1,
+            }
+        }
+        self.ports[*last].enqueue(mbuf);
+    }
+}
+
+fn classify(table: &mut MacTable, in_port: usize, frame: &[u8], now: Instant) -> Verdict {
+    let stack = match VlanStack::parse(frame) {
+        Some(stack) => stack,
+        None => return Verdict::Drop,
+    };
+    let vid = stack.outer_vid().unwrap_or(0);
+    let dst = MacAddr::from_bytes(&frame[0..6]);
+    let src = MacAddr::from_bytes(&frame[6..12]);
+
+    // A multicast source address is invalid.
+    if src.is_multicast() {
+        return Verdict::Drop;
+    }
+    table.learn(vid, src, in_port, now);
+
+    if dst.is_multicast() {
+        return Verdict::Flood;
+    }
+    match table.lookup(vid, dst, now) {
+        Some(port) if port == in_port => Verdict::Drop,
+        Some(port) => Verdict::Forward(port),
+        None => Verdict::Flood,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rpkt::ether::VlanTag;
+
+    const MAC_A: MacAddr = MacAddr([0x02, 0, 0, 0, 0, 0x0a]);
+    const MAC_B: MacAddr = MacAddr([0x02, 0, 0, 0, 0, 0x0b]);
+
+    fn frame(dst: MacAddr, src: MacAddr, vid: Option<u16>) -> Vec<u8> {
+        let mut frame = Vec::new();
+        frame.extend_from_slice(&dst.0);
+        frame.extend_from_slice(&src.0);
+        if let Some(vid) = vid {
+            frame.extend_from_slice(&VlanTag::customer(vid).to_bytes());
+        }
+        frame.extend_from_slice(&[0x08, 0x00]);
+        frame.resize(64, 0);
+        frame
+    }
+
+    #[test]
+    fn learn_and_forward() {
+        let mut table = MacTable::new(SwitchConf::default());
+        let now = Instant::now();
+
+        // Unknown destination.
+        let verdict = classify(&mut table, 0, &frame(MAC_B, MAC_A, None), now);
+        assert_eq!(verdict, Verdict::Flood);
+        assert_eq!(table.get(0, MAC_A).unwrap().port(), 0);
+
+        // The reply is forwarded to the learned port.
+        let verdict = classify(&mut table, 1, &frame(MAC_A, MAC_B, None), now);
+        assert_eq!(verdict, Verdict::Forward(0));
+        let verdict = classify(&mut table, 0, &frame(MAC_B, MAC_A, None), now);
+        assert_eq!(verdict, Verdict::Forward(1));
+
+        // The destination on the ingress port is filtered.
+        let verdict = classify(&mut table, 1, &frame(MAC_B, MAC_B, None), now);
+        assert_eq!(verdict, Verdict::Drop);
+
+        // Broadcast is flooded, and a multicast source is invalid.
+        let verdict = classify(&mut table, 0, &frame(MacAddr::BROADCAST, MAC_A, None), now);
+        assert_eq!(verdict, Verdict::Flood);
+        let verdict = classify(&mut table, 0, &frame(MAC_A, MacAddr::BROADCAST, None), now);
+        assert_eq!(verdict, Verdict::Drop);
+
+        // Each VLAN is a separate learning domain.
+        let verdict = classify(&mut table, 0, &frame(MAC_B, MAC_A, Some(100)), now);
+        assert_eq!(verdict, Verdict::Flood);
+        assert_eq!(table.len(), 3);
+
+        // A station that moves is learned on the new port.
+        classify(&mut table, 2, &frame(MAC_B, MAC_A, None), now);
+        assert_eq!(table.lookup(0, MAC_A, now), Some(2));
+
+        table.flush_port(2);
+        assert_eq!(table.lookup(0, MAC_A, now), None);
+
+        // A truncated frame is dropped.
+        assert_eq!(classify(&mut table, 0, &[0; 10], now), Verdict::Drop);
+    }
+
+    #[test]
+    fn aging() {
+        let conf = SwitchConf {
+            aging_time: Duration::from_secs(10),
+            max_entries: 1,
+        };
+        let mut table = MacTable::new(conf);
+        let now = Instant::now();
+
+        assert!(table.learn(0, MAC_A, 0, now));
+        assert!(!table.learn(0, MAC_B, 1, now));
+
+        let later = now + Duration::from_secs(10);
+        assert_eq!(table.lookup(0, MAC_A, now), Some(0));
+        assert_eq!(table.lookup(0, MAC_A, later), None);
+        assert_eq!(table.age(later), 1);
+        assert!(table.is_empty());
+        assert!(table.learn(0, MAC_B, 1, later));
+    }
+}
@@ -0,0 +1,551 @@
+use std::collections::HashMap;
+use std::net::Ipv4Addr;
+
+use arrayvec::ArrayVec;
+use rpkt::cdp::{CdpPdu, CDP_MULTICAST};
+use rpkt::ether::{EtherType, MacAddr, VlanStack};
+use rpkt::lldp::LldpPdu;
+use rpkt_dpdk::{Mbuf, RxQueue};
+use rpkt_time::{Duration, Instant};
+
+/// The number of mbufs received in a burst.
+const BATCH_SIZE: usize = 32;
+
+/// The interval of the aging timer of the topology table, in seconds.
+const AGING_INTERVAL_SECS: u64 = 1;
+
+// The largest value of an 802.3 length field, larger values are ethertypes.
+const MAX_8023_LEN: u16 = 1500;
+
+// The LLDP management address family of IPv4.
+const IANA_FAMILY_IPV4: u8 = 1;
+
+/// The configuration of a [`TopologyTable`].
+#[derive(Debug, Clone, Copy)]
+pub struct TopologyConf {
+    /// The maximum number of neighbors. Once the table is full, the
+    /// advertisements of new neighbors are ignored.
+    pub max_neighbors: usize,
+}
+
+impl Default for TopologyConf {
+    fn default() -> Self {
+        Self {
+            max_neighbors: 4096,
+        }
+    }
+}
+
+/// The protocol that advertises a [`Neighbor`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum DiscoveryProto {
+    Lldp,
+    Cdp,
+}
+
+/// A neighbor learned from its LLDP or CDP advertisements.
+///
+/// A neighbor is identified on a port by its protocol, its chassis ID and its
+/// port ID. The device ID of CDP is used as the chassis ID.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Neighbor {
+    proto: DiscoveryProto,
+    chassis_id: Vec<u8>,
+    port_id: Vec<u8>,
+    system_name: Option<Vec<u8>>,
+    port_desc: Option<Vec<u8>>,
+    mgmt_ipv4: Option<Ipv4Addr>,
+    capabilities: u32,
+    src_mac: MacAddr,
+    vid: u16,
+    updated: Instant,
+    expires: Instant,
+}
+
+impl Neighbor {
+    #[inline]
+    pub fn proto(&self) -> DiscoveryProto {
+        self.proto
+    }
+
+    /// Returns the chassis ID without its LLDP subtype, or the CDP device ID.
+    #[inline]
+    pub fn chassis_id(&self) -> &[u8] {
+        &self.chassis_id
+    }
+
+    /// Returns the port ID without its LLDP subtype.
+    #[inline]
+    pub fn port_id(&self) -> &[u8] {
+        &self.port_id
+    }
+
+    /// Returns the system name of LLDP, or the platform of CDP.
+    #[inline]
+    pub fn system_name(&self) -> Option<&[u8]> {
+        self.system_name.as_deref()
+    }
+
+    /// Returns the port description of LLDP.
+    #[inline]
+    pub fn port_desc(&self) -> Option<&[u8]> {
+        self.port_desc.as_deref()
+    }
+
+    /// Returns the IPv4 management address.
+    #[inline]
+    pub fn mgmt_ipv4(&self) -> Option<Ipv4Addr> {
+        self.mgmt_ipv4
+    }
+
+    /// Returns the enabled capabilities of LLDP, or the capabilities of CDP.
+    #[inline]
+    pub fn capabilities(&self) -> u32 {
+        self.capabilities
+    }
+
+    /// Returns the source MAC address of the last advertisement.
+    #[inline]
+    pub fn src_mac(&self) -> MacAddr {
+        self.src_mac
+    }
+
+    /// Returns the VLAN ID of the last advertisement, 0 if it is untagged.
+    #[inline]
+    pub fn vid(&self) -> u16 {
+        self.vid
+    }
+
+    /// Returns the instant of the last advertisement.
+    #[inline]
+    pub fn updated(&self) -> Instant {
+        self.updated
+    }
+
+    /// Returns the instant at which the neighbor expires, unless it is
+    /// advertised again.
+    #[inline]
+    pub fn expires(&self) -> Instant {
+        self.expires
+    }
+
+    fn is_same(&self, other: &Neighbor) -> bool {
+        self.proto == other.proto
+            && self.chassis_id == other.chassis_id
+            && self.port_id == other.port_id
+    }
+
+    // Returns whether the advertised information differs from `other`,
+    // regardless of the timestamps.
+    fn is_changed(&self, other: &Neighbor) -> bool {
+        self.system_name != other.system_name
+            || self.port_desc != other.port_desc
+            || self.mgmt_ipv4 != other.mgmt_ipv4
+            || self.capabilities != other.capabilities
+            || self.src_mac != other.src_mac
+            || self.vid != other.vid
+    }
+}
+
+/// A change of the [`TopologyTable`], passed to the change callbacks.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TopologyChange {
+    /// A new neighbor is advertised on the port.
+    Added,
+    /// The information advertised by a known neighbor has changed.
+    Updated,
+    /// The neighbor has expired, or has advertised a TTL of 0.
+    Removed,
+}
+
+/// A table of the neighbors of each port, learned from LLDP and CDP.
+///
+/// Each advertisement refreshes its neighbor for the TTL carried by it. The
+/// neighbors that are not advertised again are removed by
+/// [`TopologyTable::age`]. Every change of the table is reported to a
+/// callback with the port and the neighbor.
+pub struct TopologyTable {
+    ports: HashMap<usize, Vec<Neighbor>>,
+    len: usize,
+    conf: TopologyConf,
+}
+
+impl TopologyTable {
+    /// Create an empty table.
+    pub fn new(conf: TopologyConf) -> Self {
+        Self {
+            ports: HashMap::new(),
+            len: 0,
+            conf,
+        }
+    }
+
+    /// Returns the number of neighbors.
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    /// Returns whether the table is empty.
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Returns the neighbors of `port`.
+    #[inline]
+    pub fn neighbors(&self, port: usize) -> &[Neighbor] {
+        self.ports
+            .get(&port)
+            .map_or(&[], |neighbors| &neighbors[..])
+    }
+
+    /// Returns an iterator over the ports and their neighbors.
+    pub fn iter(&self) -> impl Iterator<Item = (usize, &Neighbor)> {
+        self.ports
+            .iter()
+            .flat_map(|(port, neighbors)| neighbors.iter().map(move |n| (*port, n)))
+    }
+
+    /// Learn the neighbor advertised by `frame` received on `port`, and report
+    /// the change to `on_change`.
+    ///
+    /// Returns `false` if `frame` is not a well-formed LLDP or CDP frame.
+    pub fn observe<F: FnMut(TopologyChange, usize, &Neighbor)>(
+        &mut self,
+        port: usize,
+        frame: &[u8],
+        now: Instant,
+        mut on_change: F,
+    ) -> bool {
+        let neighbor = match parse_neighbor(frame, now) {
+            Some(neighbor) => neighbor,
+            None => return false,
+        };
+
+        let len = self.len;
+        let neighbors = self.ports.entry(port).or_default();
+        let pos = neighbors.iter().position(|n| n.is_same(&neighbor));
+        match pos {
+            // A TTL of 0 withdraws the neighbor.
+            Some(pos) if neighbor.expires == now => {
+                let old = neighbors.swap_remove(pos);
+                self.len -= 1;
+                on_change(TopologyChange::Removed, port, &old);
+            }
+            Some(pos) => {
+                let changed = neighbors[pos].is_changed(&neighbor);
+                neighbors[pos] = neighbor;
+                if changed {
+                    on_change(TopologyChange::Updated, port, &neighbors[pos]);
+                }
+            }
+            None if neighbor.expires != now && len < self.conf.max_neighbors => {
+                neighbors.push(neighbor);
+                self.len += 1;
+                on_change(TopologyChange::Added, port, neighbors.last().unwrap());
+            }
+            None => {}
+        }
+        true
+    }
+
+    /// Remove the expired neighbors and report them to `on_change`, returns the
+    /// number of removed neighbors.
+    ///
+    /// This method walks through the whole table, so it is supposed to be called
+    /// periodically.
+    pub fn age<F: FnMut(TopologyChange, usize, &Neighbor)>(
+        &mut self,
+        now: Instant,
+        mut on_change: F,
+    ) -> usize {
+        let len = self.len;
+        for (port, neighbors) in self.ports.iter_mut() {
+            neighbors.retain(|neighbor| {
+                let alive = now < neighbor.expires;
+                if !alive {
+                    on_change(TopologyChange::Removed, *port, neighbor);
+                }
+                alive
+            });
+        }
+        self.ports.retain(|_, neighbors| !neighbors.is_empty());
+        self.len = self.ports.values().map(|neighbors| neighbors.len()).sum();
+        len - self.len
+    }
+
+    /// Remove the neighbors of `port`, e.g. when the link of the port is down.
+    pub fn flush_port(&mut self, port: usize) {
+        if let Some(neighbors) = self.ports.remove(&port) {
+            self.len -= neighbors.len();
+        }
+    }
+
+    /// Remove all the neighbors.
+    pub fn clear(&mut self) {
+        self.ports.clear();
+        self.len = 0;
+    }
+}
+
+/// A collector of the LLDP and CDP advertisements received on a set of DPDK
+/// ports, for building network inventory tools.
+///
+/// Each port of the collector is an rx queue, which should only receive the
+/// LLDP and CDP frames, e.g. with a flow rule steering them to a dedicated
+/// queue. The other frames are dropped.
+///
+/// The collector is single-threaded and poll-driven, it only makes progress when
+/// [`TopologyCollector::poll`] is called.
+///
+/// # Examples
+/// ```no_run
+/// use rpkt_dpdk::service;
+/// use rpkt_stack::{TopologyChange, TopologyCollector, TopologyConf};
+///
+/// let mut collector = TopologyCollector::new(TopologyConf::default());
+/// for port_id in 0..2 {
+///     collector.add_port(service().rx_queue(port_id, 0).unwrap());
+/// }
+///
+/// loop {
+///     collector.poll(|change, port, neighbor| {
+///         if change == TopologyChange::Added {
+///             println!("port {}: {:?}", port, neighbor.system_name());
+///         }
+///     });
+/// }
+/// ```
+pub struct TopologyCollector {
+    table: TopologyTable,
+    ports: Vec<RxQueue>,
+    next_aging: Option<Instant>,
+}
+
+impl TopologyCollector {
+    /// Create a collector without ports.
+    pub fn new(conf: TopologyConf) -> Self {
+        Self {
+            table: TopologyTable::new(conf),
+            ports: Vec::new(),
+            next_aging: None,
+        }
+    }
+
+    /// Add a port, and returns the index of the port.
+    pub fn add_port(&mut self, rxq: RxQueue) -> usize {
+        self.ports.push(rxq);
+        self.ports.len() - 1
+    }
+
+    /// Returns the number of ports.
+    #[inline]
+    pub fn nb_ports(&self) -> usize {
+        self.ports.len()
+    }
+
+    /// Returns the topology table.
+    #[inline]
+    pub fn table(&self) -> &TopologyTable {
+        &self.table
+    }
+
+    /// Returns the topology table for removing neighbors.
+    #[inline]
+    pub fn table_mut(&mut self) -> &mut TopologyTable {
+        &mut self.table
+    }
+
+    /// Receive a burst from every port and learn the advertised neighbors,
+    /// returns the number of received frames.
+    ///
+    /// Every change of the table, including the expiry of the neighbors, is
+    /// reported to `on_change`.
+    pub fn poll<F: FnMut(TopologyChange, usize, &Neighbor)>(&mut self, mut on_change: F) -> usize {
+        let now = Instant::now();
+        match self.next_aging {
+            Some(next_aging) if now < next_aging => {}
+            _ => {
+                self.table.age(now, &mut on_change);
+                self.next_aging = Some(now + Duration::from_secs(AGING_INTERVAL_SECS));
+            }
+        }
+
+        let mut total = 0;
+        let mut batch = ArrayVec::<Mbuf, BATCH_SIZE>::new();
+        for (port, rxq) in self.ports.iter_mut().enumerate() {
+            total += rxq.rx(&mut batch);
+            for mbuf in batch.drain(..) {
+                self.table.observe(port, mbuf.data(), now, &mut on_change);
+            }
+        }
+        total
+    }
+}
+
+fn parse_neighbor(frame: &[u8], now: Instant) -> Option<Neighbor> {
+    let stack = VlanStack::parse(frame)?;
+    let payload = &frame[stack.header_len()..];
+    let mut neighbor = Neighbor {
+        proto: DiscoveryProto::Lldp,
+        chassis_id: Vec::new(),
+        port_id: Vec::new(),
+        system_name: None,
+        port_desc: None,
+        mgmt_ipv4: None,
+        capabilities: 0,
+        src_mac: MacAddr::from_bytes(&frame[6..12]),
+        vid: stack.inner_vid().unwrap_or(0),
+        updated: now,
+        expires: now,
+    };
+
+    let ttl = if stack.ethertype() == EtherType::LLDP {
+        let pdu = LldpPdu::parse(payload)?;
+        neighbor.chassis_id = pdu.chassis_id().1.to_vec();
+        neighbor.port_id = pdu.port_id().1.to_vec();
+        neighbor.system_name = pdu.system_name().map(|name| name.to_vec());
+        neighbor.port_desc = pdu.port_desc().map(|desc| desc.to_vec());
+        neighbor.mgmt_ipv4 = pdu.mgmt_addr().and_then(|(family, addr)| {
+            let addr: [u8; 4] = addr.try_into().ok()?;
+            (family == IANA_FAMILY_IPV4).then(|| Ipv4Addr::from(addr))
+        });
+        neighbor.capabilities = pdu.capabilities().map_or(0, |(_, enabled)| enabled.into());
+        pdu.ttl()
+    } else if MacAddr::from_bytes(&frame[0..6]) == CDP_MULTICAST
+        && u16::from(stack.ethertype()) <= MAX_8023_LEN
+    {
+        // The length field excludes the padding of the frame.
+        let len = usize::from(u16::from(stack.ethertype())).min(payload.len());
+        let pdu = CdpPdu::parse(&payload[..len])?;
+        neighbor.proto = DiscoveryProto::Cdp;
+        neighbor.chassis_id = pdu.device_id()?.to_vec();
+        neighbor.port_id = pdu.port_id().unwrap_or_default().to_vec();
+        neighbor.system_name = pdu.platform().map(|platform| platform.to_vec());
+        neighbor.mgmt_ipv4 = pdu.ipv4_addr().map(Ipv4Addr::from);
+        neighbor.capabilities = pdu.capabilities().unwrap_or(0);
+        pdu.ttl().into()
+    } else {
+        return None;
+    };
+    neighbor.expires = now + Duration::from_secs(ttl.into());
+    Some(neighbor)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rpkt::cdp::CDP_SNAP_HEADER;
+    use rpkt::lldp::LLDP_MULTICAST;
+
+    const MAC_A: MacAddr = MacAddr([0x02, 0, 0, 0, 0, 0x0a]);
+
+    fn lldp_frame(port_id: &[u8], ttl: u16, system_name: &[u8]) -> Vec<u8> {
+        let mut frame = LLDP_MULTICAST.0.to_vec();
+        frame.extend_from_slice(&MAC_A.0);
+        frame.extend_from_slice(&[0x88, 0xcc, 0x02, 0x07, 0x04]);
+        frame.extend_from_slice(&MAC_A.0);
+        frame.extend_from_slice(&[0x04, port_id.len() as u8 + 1, 0x05]);
+        frame.extend_from_slice(port_id);
+        frame.extend_from_slice(&[0x06, 0x02]);
+        frame.extend_from_slice(&ttl.to_be_bytes());
+        frame.extend_from_slice(&[0x0a, system_name.len() as u8]);
+        frame.extend_from_slice(system_name);
+        frame.extend_from_slice(&[0x0e, 0x04, 0x00, 0x14, 0x00, 0x04, 0x00, 0x00]);
+        frame
+    }
+
+    fn cdp_frame() -> Vec<u8> {
+        let mut pdu = CDP_SNAP_HEADER.to_vec();
+        pdu.extend_from_slice(&[0x02, 0xb4, 0x00, 0x00]);
+        pdu.extend_from_slice(&[0x00, 0x01, 0x00, 0x07, b'r', b't', b'1']);
+        pdu.extend_from_slice(&[0x00, 0x03, 0x00, 0x07, b'G', b'i', b'0']);
+        let mut frame = CDP_MULTICAST.0.to_vec();
+        frame.extend_from_slice(&MAC_A.0);
+        frame.extend_from_slice(&(pdu.len() as u16).to_be_bytes());
+        frame.extend_from_slice(&pdu);
+        // The padding of the frame.
+        frame.resize(60, 0);
+        frame
+    }
+
+    fn observe(
+        table: &mut TopologyTable,
+        port: usize,
+        frame: &[u8],
+        now: Instant,
+    ) -> Vec<TopologyChange> {
+        let mut changes = Vec::new();
+        assert!(table.observe(port, frame, now, |change, _, _| changes.push(change)));
+        changes
+    }
+
+    #[test]
+    fn lldp_neighbors() {
+        let mut table = TopologyTable::new(TopologyConf::default());
+        let now = Instant::now();
+
+        let frame = lldp_frame(b"e1", 120, b"sw1");
+        assert_eq!(observe(&mut table, 0, &frame, now), [TopologyChange::Added]);
+        assert!(observe(&mut table, 0, &frame, now).is_empty());
+        let neighbor = &table.neighbors(0)[0];
+        assert_eq!(neighbor.proto(), DiscoveryProto::Lldp);
+        assert_eq!(neighbor.chassis_id(), &MAC_A.0[..]);
+        assert_eq!(neighbor.port_id(), b"e1");
+        assert_eq!(neighbor.system_name(), Some(&b"sw1"[..]));
+        assert_eq!(neighbor.capabilities(), 0x04);
+
+        // A renamed system, another port of the same device, and the same
+        // port of the device seen on another port.
+        let renamed = lldp_frame(b"e1", 120, b"sw2");
+        assert_eq!(
+            observe(&mut table, 0, &renamed, now),
+            [TopologyChange::Updated]
+        );
+        let other = lldp_frame(b"e2", 120, b"sw2");
+        assert_eq!(observe(&mut table, 0, &other, now), [TopologyChange::Added]);
+        assert_eq!(observe(&mut table, 1, &other, now), [TopologyChange::Added]);
+        assert_eq!(table.len(), 3);
+
+        // A TTL of 0 withdraws the neighbor.
+        let shutdown = lldp_frame(b"e2", 0, b"sw2");
+        assert_eq!(
+            observe(&mut table, 1, &shutdown, now),
+            [TopologyChange::Removed]
+        );
+        assert!(observe(&mut table, 1, &shutdown, now).is_empty());
+        assert_eq!(table.len(), 2);
+
+        assert!(!table.observe(0, &frame[..20], now, |_, _, _| {}));
+    }
+
+    #[test]
+    fn cdp_and_aging() {
+        let conf = TopologyConf { max_neighbors: 2 };
+        let mut table = TopologyTable::new(conf);
+        let now = Instant::now();
+
+        assert_eq!(
+            observe(&mut table, 0, &cdp_frame(), now),
+            [TopologyChange::Added]
+        );
+        let neighbor = &table.neighbors(0)[0];
+        assert_eq!(neighbor.proto(), DiscoveryProto::Cdp);
+        assert_eq!(neighbor.chassis_id(), b"rt1");
+        assert_eq!(neighbor.port_id(), b"Gi0");
+
+        let lldp = lldp_frame(b"e1", 60, b"sw1");
+        assert_eq!(observe(&mut table, 1, &lldp, now), [TopologyChange::Added]);
+        // The table is full.
+        assert!(observe(&mut table, 2, &lldp, now).is_empty());
+
+        let mut removed = Vec::new();
+        let later = now + Duration::from_secs(60);
+        assert_eq!(table.age(later, |_, port, _| removed.push(port)), 1);
+        assert_eq!(removed, [1]);
+        assert_eq!(table.len(), 1);
+        assert_eq!(table.age(now + Duration::from_secs(180), |_, _, _| {}), 1);
+        assert!(table.is_empty());
+    }
+}
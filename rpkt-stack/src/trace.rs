@@ -0,0 +1,814 @@
+use std::io;
+use std::net::Ipv4Addr;
+use std::ops::Range;
+
+use arrayvec::ArrayVec;
+use rpkt::arp::Operation;
+use rpkt::ether::{EtherPacket, EtherType, MacAddr};
+use rpkt::icmpv4::{
+    IcmpType, Icmpv4Header, Icmpv4Packet, ICMPV4_HEADER_LEN, ICMPV4_HEADER_TEMPLATE,
+};
+use rpkt::ipv4::{IpProtocol, Ipv4Header, Ipv4Packet, IPV4_HEADER_LEN};
+use rpkt::neigh::NeighCache;
+use rpkt::udp::{UdpHeader, UDP_HEADER_LEN};
+use rpkt::{Buf, Cursor, CursorMut};
+use rpkt_dpdk::{Mbuf, Mempool, RxQueue, TxQueue};
+use rpkt_time::{Duration, Instant};
+
+use crate::iface::IfaceConf;
+use crate::wire::{self, ArpInfo, Incoming, Ipv4Info, ARP_FRAME_LEN, FRAME_OVERHEAD};
+
+/// The number of mbufs received or sent in a burst.
+const BATCH_SIZE: usize = 32;
+
+/// The number of zero bytes carried by a probe after the UDP or ICMP header.
+const PROBE_DATA_LEN: usize = 32;
+
+/// The length of a probe frame. The UDP and ICMP headers have the same length.
+const PROBE_FRAME_LEN: usize = FRAME_OVERHEAD + UDP_HEADER_LEN + PROBE_DATA_LEN;
+
+/// The code of a Time Exceeded message sent by a router.
+const CODE_TTL_EXCEEDED: u8 = 0;
+
+/// The code of a Destination Unreachable message sent by the destination of a
+/// UDP probe.
+const CODE_PORT_UNREACHABLE: u8 = 3;
+
+/// The protocol of the probes sent by a [`Traceroute`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TraceProto {
+    /// UDP datagrams to unused ports, answered by an ICMP Port Unreachable
+    /// message at the destination.
+    Udp,
+    /// ICMP Echo Requests, answered by an Echo Reply at the destination.
+    Icmp,
+}
+
+/// The configuration of a [`Traceroute`].
+#[derive(Debug, Clone, Copy)]
+pub struct TraceConf {
+    /// The protocol of the probes.
+    pub proto: TraceProto,
+    /// The TTL of the first hop.
+    pub first_ttl: u8,
+    /// The TTL of the last hop, where the trace stops if the destination is
+    /// not reached.
+    pub max_ttl: u8,
+    /// The number of probes sent to each hop.
+    pub probes: u8,
+    /// The destination port of the first UDP probe of a trace, incremented for
+    /// every probe.
+    pub port: u16,
+    /// The source port of the UDP probes, or the identifier of the ICMP
+    /// probes.
+    pub ident: u16,
+    /// How long the replies of a hop are waited for.
+    pub timeout: Duration,
+}
+
+impl Default for TraceConf {
+    /// Create the configuration of the classic UDP traceroute, with 3 probes
+    /// to each of the first 30 hops, sent to the ports from 33434.
+    fn default() -> Self {
+        Self {
+            proto: TraceProto::Udp,
+            first_ttl: 1,
+            max_ttl: 30,
+            probes: 3,
+            port: 33434,
+            ident: std::process::id() as u16 | 0x8000,
+            timeout: Duration::from_secs(3),
+        }
+    }
+}
+
+/// The kind of a reply to a traceroute probe.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TraceReplyKind {
+    /// The TTL of the probe is exhausted at an intermediate router.
+    TimeExceeded,
+    /// The probe reaches the destination.
+    Reached,
+    /// The probe is rejected with a Destination Unreachable message, whose
+    /// code is given, e.g. 1 for an unreachable host.
+    Unreachable(u8),
+}
+
+/// A reply to a traceroute probe.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TraceReply {
+    /// The source address of the reply.
+    pub addr: Ipv4Addr,
+    pub kind: TraceReplyKind,
+    /// The round-trip time of the probe.
+    pub rtt: std::time::Duration,
+}
+
+/// The probes sent to a hop, with their replies.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TraceHop {
+    /// The TTL of the probes.
+    pub ttl: u8,
+    /// The reply to every probe, or `None` if the probe times out.
+    pub replies: Vec<Option<TraceReply>>,
+}
+
+impl TraceHop {
+    /// Returns whether the probes of the hop reach the destination, or are
+    /// rejected before reaching it, so that no further hop is probed.
+    pub fn is_last(&self) -> bool {
+        self.replies
+            .iter()
+            .flatten()
+            .any(|reply| reply.kind != TraceReplyKind::TimeExceeded)
+    }
+}
+
+/// A traceroute over a pair of DPDK rx/tx queues.
+///
+/// The traceroute discovers the routers on the path to a destination: the
+/// probes of each hop are sent with an increasing TTL, and the routers where
+/// the TTL is exhausted reply with ICMP Time Exceeded messages. A reply is
+/// matched to its probe by the IPv4 header and the UDP or ICMP header quoted by
+/// the message, so the replies of other traces and other hosts are ignored.
+///
+/// The hops are probed one after the other. The next hop is probed when all
+/// the probes of the current one are answered, or when the timeout expires.
+/// The trace stops at the hop that reaches the destination or is rejected by
+/// a Destination Unreachable message, or at the maximum TTL.
+///
+/// Like [`UdpSocket`](crate::UdpSocket), the traceroute owns the queues
+/// exclusively and resolves the MAC address of the next hop with ARP. The
+/// probes are held until the next hop is resolved.
+///
+/// # Examples
+/// ```no_run
+/// use std::net::Ipv4Addr;
+///
+/// use rpkt::ether::MacAddr;
+/// use rpkt_dpdk::service;
+/// use rpkt_stack::{IfaceConf, TraceConf, Traceroute};
+///
+/// let mut iface = IfaceConf::new(
+///     MacAddr([0x02, 0x00, 0x00, 0x00, 0x00, 0x01]),
+///     Ipv4Addr::new(192, 168, 1, 2),
+///     24,
+/// );
+/// iface.gateway = Some(Ipv4Addr::new(192, 168, 1, 1));
+/// let mut traceroute = Traceroute::new(
+///     iface,
+///     TraceConf::default(),
+///     service().rx_queue(0, 0).unwrap(),
+///     service().tx_queue(0, 0).unwrap(),
+///     service().mempool("mp").unwrap(),
+/// );
+///
+/// traceroute.start(Ipv4Addr::new(198, 51, 100, 7)).unwrap();
+/// while !traceroute.is_done() {
+///     traceroute.poll();
+/// }
+/// for hop in traceroute.hops() {
+///     println!("{} {:?}", hop.ttl, hop.replies);
+/// }
+/// ```
+pub struct Traceroute {
+    iface: IfaceConf,
+    conf: TraceConf,
+    rxq: RxQueue,
+    txq: TxQueue,
+    mp: Mempool,
+    neigh: NeighCache<Ipv4Addr>,
+    tracer: Option<Tracer>,
+    next_seq: u16,
+    rx_batch: ArrayVec<Mbuf, BATCH_SIZE>,
+    tx_batch: ArrayVec<Mbuf, BATCH_SIZE>,
+}
+
+impl Traceroute {
+    /// Create a traceroute that sends the probes from the interface described
+    /// by `iface`.
+    ///
+    /// The mbufs of the sent frames are allocated from `mp`.
+    ///
+    /// # Panics
+    ///
+    /// This function panics if `conf` has no probe per hop, or if its first TTL
+    /// is 0 or larger than its maximum TTL.
+    pub fn new(iface: IfaceConf, conf: TraceConf, rxq: RxQueue, txq: TxQueue, mp: Mempool) -> Self {
+        assert!(conf.probes > 0, "no probe is sent to each hop");
+        assert!(
+            conf.first_ttl > 0 && conf.first_ttl <= conf.max_ttl,
+            "invalid ttl range: {}..={}",
+            conf.first_ttl,
+            conf.max_ttl
+        );
+        Self {
+            iface,
+            conf,
+            rxq,
+            txq,
+            mp,
+            neigh: NeighCache::new(iface.neigh),
+            tracer: None,
+            next_seq: 0,
+            rx_batch: ArrayVec::new(),
+            tx_batch: ArrayVec::new(),
+        }
+    }
+
+    /// Returns the interface configuration.
+    #[inline]
+    pub fn iface(&self) -> &IfaceConf {
+        &self.iface
+    }
+
+    /// Returns the traceroute configuration.
+    #[inline]
+    pub fn conf(&self) -> &TraceConf {
+        &self.conf
+    }
+
+    /// Returns the ARP cache of the traceroute.
+    #[inline]
+    pub fn neigh_cache(&self) -> &NeighCache<Ipv4Addr> {
+        &self.neigh
+    }
+
+    /// Start a trace to `dst`, discarding the previous trace.
+    ///
+    /// The probes are sent by [`Traceroute::poll`]. An error of kind
+    /// `AddrNotAvailable` is returned if `dst` is not reachable from the
+    /// interface.
+    pub fn start(&mut self, dst: Ipv4Addr) -> io::Result<()> {
+        if self.iface.next_hop(dst).is_none() {
+            return Err(io::Error::new(
+                io::ErrorKind::AddrNotAvailable,
+                "network is unreachable",
+            ));
+        }
+        let tracer = Tracer::new(self.conf, self.iface.mac, self.iface.ip, dst, self.next_seq);
+        // The probes of the next trace have other sequence numbers, so the late
+        // replies of this trace are ignored.
+        self.next_seq = self.next_seq.wrapping_add(tracer.nb_probes());
+        self.tracer = Some(tracer);
+        Ok(())
+    }
+
+    /// Returns the destination of the current trace.
+    #[inline]
+    pub fn target(&self) -> Option<Ipv4Addr> {
+        self.tracer.as_ref().map(|tracer| tracer.dst)
+    }
+
+    /// Returns the hops probed by the current trace, the last one may still be
+    /// waiting for replies.
+    #[inline]
+    pub fn hops(&self) -> &[TraceHop] {
+        self.tracer.as_ref().map_or(&[], |tracer| &tracer.hops[..])
+    }
+
+    /// Returns whether the current trace is complete, or `true` if no trace is
+    /// started.
+    #[inline]
+    pub fn is_done(&self) -> bool {
+        self.tracer.as_ref().map_or(true, |tracer| tracer.done)
+    }
+
+    /// Returns whether the current trace reaches its destination.
+    pub fn reached(&self) -> bool {
+        self.hops()
+            .iter()
+            .flat_map(|hop| hop.replies.iter().flatten())
+            .any(|reply| reply.kind == TraceReplyKind::Reached)
+    }
+
+    /// Process the received frames, send the probes of the next hop when they
+    /// are due, and drive the ARP timers.
+    ///
+    /// This method should be called regularly until the trace is complete.
+    pub fn poll(&mut self) {
+        let now = Instant::now();
+
+        let mut batch = std::mem::take(&mut self.rx_batch);
+        self.rxq.rx(&mut batch);
+        for mbuf in batch.drain(..) {
+            self.handle(mbuf.data(), now);
+        }
+        self.rx_batch = batch;
+
+        self.send_probes(now);
+        self.poll_neigh(now);
+        self.flush();
+    }
+
+    fn handle(&mut self, frame: &[u8], now: Instant) {
+        match wire::classify(frame, self.iface.ip) {
+            Some(Incoming::Arp(ArpInfo {
+                op,
+                sender_mac,
+                sender_ip,
+                target_ip,
+            })) => {
+                if target_ip != self.iface.ip || sender_ip.is_unspecified() {
+                    return;
+                }
+                if op == Operation::REPLY {
+                    self.neigh.confirm(sender_ip, sender_mac, now);
+                } else if op == Operation::REQUEST {
+                    self.neigh.update(sender_ip, sender_mac, now);
+                    self.send_arp(Operation::REPLY, Some(sender_mac), sender_ip);
+                }
+            }
+            Some(_) => {}
+            None => {
+                if let Some(tracer) = self.tracer.as_mut() {
+                    tracer.handle(frame, now);
+                }
+            }
+        }
+    }
+
+    fn send_probes(&mut self, now: Instant) {
+        let tracer = match self.tracer.as_mut() {
+            Some(tracer) => tracer,
+            None => return,
+        };
+        if !tracer.poll(now) {
+            return;
+        }
+        let mac = match self.iface.next_hop(tracer.dst) {
+            Some(next_hop) => match self.neigh.lookup(next_hop, now) {
+                Some(mac) => mac,
+                None => return,
+            },
+            None => return,
+        };
+
+        let (ttl, probes) = tracer.start_hop(now);
+        let mut frames = Vec::with_capacity(probes.len());
+        for n in probes {
+            // A probe that fails to be allocated times out.
+            if let Some(mut mbuf) = self.mp.try_alloc() {
+                unsafe { mbuf.extend(PROBE_FRAME_LEN) };
+                tracer.write_probe(mbuf.data_mut(), ttl, n);
+                wire::set_dest_mac(mbuf.data_mut(), mac);
+                frames.push(mbuf);
+            }
+        }
+        for mbuf in frames {
+            self.enqueue_tx(mbuf);
+        }
+    }
+
+    fn poll_neigh(&mut self, now: Instant) {
+        let mut probes = Vec::new();
+        self.neigh.poll(now, |probe| probes.push(probe));
+        for probe in probes {
+            self.send_arp(Operation::REQUEST, probe.target, probe.addr);
+        }
+    }
+
+    fn send_arp(&mut self, op: Operation, target_mac: Option<MacAddr>, target_ip: Ipv4Addr) {
+        if let Some(mut mbuf) = self.mp.try_alloc() {
+            unsafe { mbuf.extend(ARP_FRAME_LEN) };
+            wire::write_arp(
+                mbuf.data_mut(),
+                op,
+                self.iface.mac,
+                self.iface.ip,
+                target_mac,
+                target_ip,
+            );
+            self.enqueue_tx(mbuf);
+        }
+    }
+
+    fn enqueue_tx(&mut self, mbuf: Mbuf) {
+        if self.tx_batch.is_full() {
+            self.flush();
+        }
+        // The frame is dropped if the tx queue is still congested.
+        let _ = self.tx_batch.try_push(mbuf);
+    }
+
+    fn flush(&mut self) {
+        if !self.tx_batch.is_empty() {
+            self.txq.tx(&mut self.tx_batch);
+        }
+    }
+}
+
+// The state of a trace, which writes the probes and matches the replies.
+//
+// The probes of a trace are numbered from 0. The IPv4 identification of a
+// probe is its sequence number, which is its number plus the sequence number
+// of the first probe. The destination port of a UDP probe is its number plus
+// the base port, and the sequence number of an ICMP probe is its sequence
+// number.
+struct Tracer {
+    conf: TraceConf,
+    src_mac: MacAddr,
+    src: Ipv4Addr,
+    dst: Ipv4Addr,
+    first_seq: u16,
+    hops: Vec<TraceHop>,
+    sent: Instant,
+    deadline: Instant,
+    done: bool,
+}
+
+impl Tracer {
+    fn new(
+        conf: TraceConf,
+        src_mac: MacAddr,
+        src: Ipv4Addr,
+        dst: Ipv4Addr,
+        first_seq: u16,
+    ) -> Self {
+        let now = Instant::now();
+        Self {
+            conf,
+            src_mac,
+            src,
+            dst,
+            first_seq,
+            hops: Vec::new(),
+            sent: now,
+            deadline: now,
+            done: false,
+        }
+    }
+
+    // The maximum number of probes of the trace, which is at most 255 * 255.
+    fn nb_probes(&self) -> u16 {
+        u16::from(self.conf.max_ttl - self.conf.first_ttl + 1) * u16::from(self.conf.probes)
+    }
+
+    // Returns whether the probes of the next hop are due, and marks the trace as
+    // complete after the last hop.
+    fn poll(&mut self, now: Instant) -> bool {
+        if self.done {
+            return false;
+        }
+        let hop = match self.hops.last() {
+            Some(hop) => hop,
+            None => return true,
+        };
+        if now < self.deadline && hop.replies.iter().any(Option::is_none) {
+            return false;
+        }
+        if hop.is_last() || hop.ttl >= self.conf.max_ttl {
+            self.done = true;
+            return false;
+        }
+        true
+    }
+
+    // Start probing the next hop, returning its TTL and the numbers of its
+    // probes.
+    fn start_hop(&mut self, now: Instant) -> (u8, Range<u16>) {
+        let ttl = self
+            .hops
+            .last()
+            .map_or(self.conf.first_ttl, |hop| hop.ttl + 1);
+        let probes = u16::from(self.conf.probes);
+        let start = self.hops.len() as u16 * probes;
+        self.hops.push(TraceHop {
+            ttl,
+            replies: vec![None; usize::from(self.conf.probes)],
+        });
+        self.sent = now;
+        self.deadline = now + self.conf.timeout;
+        (ttl, start..start + probes)
+    }
+
+    // Write the probe numbered `n` into the first `PROBE_FRAME_LEN` bytes of
+    // `frame`. The destination MAC address is left empty.
+    fn write_probe(&self, frame: &mut [u8], ttl: u8, n: u16) {
+        let frame = &mut frame[..PROBE_FRAME_LEN];
+        let seq = self.first_seq.wrapping_add(n);
+        let protocol = match self.conf.proto {
+            TraceProto::Udp => {
+                let data = [0; PROBE_DATA_LEN];
+                let dst_port = self.conf.port.wrapping_add(n);
+                let header = wire::udp_header(self.src, self.conf.ident, self.dst, dst_port, &data);
+                wire::gather(&mut frame[FRAME_OVERHEAD..], &[header.as_bytes(), &data], 0);
+                IpProtocol::UDP
+            }
+            TraceProto::Icmp => {
+                frame[FRAME_OVERHEAD..].fill(0);
+                let mut buf = CursorMut::new(frame);
+                buf.advance(FRAME_OVERHEAD + ICMPV4_HEADER_LEN);
+                let mut icmppkt = Icmpv4Packet::prepend_header(buf, &ICMPV4_HEADER_TEMPLATE);
+                icmppkt.set_icmp_type(IcmpType::ECHO_REQUEST);
+                icmppkt.set_ident(self.conf.ident);
+                icmppkt.set_seq_num(seq);
+                icmppkt.adjust_checksum();
+                IpProtocol::ICMP
+            }
+        };
+        let info = Ipv4Info {
+            src_mac: self.src_mac,
+            src: self.src,
+            dst: self.dst,
+            protocol,
+            ident: seq,
+            ttl,
+        };
+        wire::write_ipv4(frame, &info, 0, false);
+    }
+
+    // Record the reply carried by `frame`, returning whether it is the first
+    // reply to a probe of the current hop.
+    fn handle(&mut self, frame: &[u8], now: Instant) -> bool {
+        let (addr, kind, n) = match self.parse_reply(frame) {
+            Some(reply) => reply,
+            None => return false,
+        };
+        let probes = usize::from(self.conf.probes);
+        let (hop, index) = (usize::from(n) / probes, usize::from(n) % probes);
+        if self.done || hop + 1 != self.hops.len() {
+            return false;
+        }
+        let reply = &mut self.hops[hop].replies[index];
+        if reply.is_some() {
+            return false;
+        }
+        *reply = Some(TraceReply {
+            addr,
+            kind,
+            rtt: now.saturating_duration_since(self.sent),
+        });
+        true
+    }
+
+    // Parse an ICMP message sent to the source of the probes, returning its
+    // source address, its kind and the number of the probe it replies to.
+    fn parse_reply(&self, frame: &[u8]) -> Option<(Ipv4Addr, TraceReplyKind, u16)> {
+        let ethpkt = EtherPacket::parse(Cursor::new(frame)).ok()?;
+        if ethpkt.ethertype() != EtherType::IPV4 {
+            return None;
+        }
+        let ippkt = Ipv4Packet::parse(ethpkt.payload()).ok()?;
+        if !ippkt.check_version()
+            || !ippkt.verify_checksum()
+            || ippkt.protocol() != IpProtocol::ICMP
+            || ippkt.more_frags()
+            || ippkt.frag_offset() != 0
+            || Ipv4Addr::from(ippkt.dest_ip()) != self.src
+        {
+            return None;
+        }
+        let addr = Ipv4Addr::from(ippkt.source_ip());
+
+        let mut icmppkt = Icmpv4Packet::parse(ippkt.payload()).ok()?;
+        if !icmppkt.verify_checksum() {
+            return None;
+        }
+        let (icmp_type, code) = (icmppkt.icmp_type(), icmppkt.code());
+        match icmp_type {
+            IcmpType::ECHO_REPLY => {
+                if self.conf.proto != TraceProto::Icmp
+                    || icmppkt.ident() != self.conf.ident
+                    || addr != self.dst
+                {
+                    return None;
+                }
+                let n = icmppkt.seq_num().wrapping_sub(self.first_seq);
+                (n < self.nb_probes()).then_some((addr, TraceReplyKind::Reached, n))
+            }
+            IcmpType::TIME_EXCEEDED if code == CODE_TTL_EXCEEDED => {
+                let n = self.match_quote(icmppkt.data().chunk())?;
+                Some((addr, TraceReplyKind::TimeExceeded, n))
+            }
+            IcmpType::DST_UNREACHABLE => {
+                let n = self.match_quote(icmppkt.data().chunk())?;
+                let kind = if self.conf.proto == TraceProto::Udp
+                    && code == CODE_PORT_UNREACHABLE
+                    && addr == self.dst
+                {
+                    TraceReplyKind::Reached
+                } else {
+                    TraceReplyKind::Unreachable(code)
+                };
+                Some((addr, kind, n))
+            }
+            _ => None,
+        }
+    }
+
+    // Match the offending packet quoted by an ICMP error to a probe, returning
+    // the number of the probe. The quote holds at least the IPv4 header and the
+    // first 8 bytes of the payload (RFC 792).
+    fn match_quote(&self, quote: &[u8]) -> Option<u16> {
+        let header = Ipv4Header::new(quote).ok()?;
+        let header_len = usize::from(header.header_len());
+        if !header.check_version()
+            || header_len < IPV4_HEADER_LEN
+            || quote.len() < header_len + UDP_HEADER_LEN
+            || Ipv4Addr::from(header.source_ip()) != self.src
+            || Ipv4Addr::from(header.dest_ip()) != self.dst
+        {
+            return None;
+        }
+        let seq = header.ident();
+        let n = seq.wrapping_sub(self.first_seq);
+        if n >= self.nb_probes() {
+            return None;
+        }
+
+        let payload = &quote[header_len..];
+        let matched = match self.conf.proto {
+            TraceProto::Udp => {
+                let udp = UdpHeader::new_unchecked(payload);
+                header.protocol() == IpProtocol::UDP
+                    && udp.source_port() == self.conf.ident
+                    && udp.dest_port() == self.conf.port.wrapping_add(n)
+            }
+            TraceProto::Icmp => {
+                let icmp = Icmpv4Header::new_unchecked(payload);
+                header.protocol() == IpProtocol::ICMP
+                    && icmp.icmp_type() == IcmpType::ECHO_REQUEST
+                    && icmp.ident() == self.conf.ident
+                    && icmp.seq_num() == seq
+            }
+        };
+        matched.then_some(n)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rpkt::ether::{ETHER_HEADER_LEN, ETHER_HEADER_TEMPLATE};
+    use rpkt::ipv4::IPV4_HEADER_TEMPLATE;
+
+    use super::*;
+
+    const MAC: MacAddr = MacAddr([0x02, 0, 0, 0, 0, 0x01]);
+    const LOCAL: Ipv4Addr = Ipv4Addr::new(192, 168, 1, 2);
+    const HOP1: Ipv4Addr = Ipv4Addr::new(192, 168, 1, 1);
+    const HOP2: Ipv4Addr = Ipv4Addr::new(10, 0, 0, 1);
+    const TARGET: Ipv4Addr = Ipv4Addr::new(198, 51, 100, 7);
+
+    fn probes(tracer: &mut Tracer, now: Instant) -> (u8, Vec<Vec<u8>>) {
+        assert!(tracer.poll(now));
+        let (ttl, probes) = tracer.start_hop(now);
+        let frames = probes
+            .map(|n| {
+                let mut frame = vec![0; PROBE_FRAME_LEN];
+                tracer.write_probe(&mut frame, ttl, n);
+                frame
+            })
+            .collect();
+        (ttl, frames)
+    }
+
+    // An ICMP error sent by `from`, quoting the IPv4 packet of `probe`.
+    fn error(from: Ipv4Addr, icmp_type: IcmpType, code: u8, probe: &[u8]) -> Vec<u8> {
+        let mut bytes = [0; 256];
+        let mut buf = CursorMut::new(&mut bytes[..]);
+        buf.advance(256);
+
+        let icmppkt =
+            Icmpv4Packet::prepend_error(buf, icmp_type, code, [0; 4], &probe[ETHER_HEADER_LEN..]);
+        let mut ippkt = Ipv4Packet::prepend_header(icmppkt.release(), &IPV4_HEADER_TEMPLATE);
+        ippkt.set_protocol(IpProtocol::ICMP);
+        ippkt.set_source_ip(from.into());
+        ippkt.set_dest_ip(LOCAL.into());
+        ippkt.adjust_checksum();
+        let ethpkt = EtherPacket::prepend_header(ippkt.release(), &ETHER_HEADER_TEMPLATE);
+        ethpkt.release().chunk().to_vec()
+    }
+
+    // The Echo Reply to an ICMP probe.
+    fn echo_reply(probe: &[u8]) -> Vec<u8> {
+        let mut frame = probe.to_vec();
+        let mut ippkt = Ipv4Packet::parse(CursorMut::new(&mut frame[ETHER_HEADER_LEN..])).unwrap();
+        ippkt.set_source_ip(TARGET.into());
+        ippkt.set_dest_ip(LOCAL.into());
+        ippkt.adjust_checksum();
+        let mut icmppkt = Icmpv4Packet::parse(ippkt.payload()).unwrap();
+        icmppkt.set_icmp_type(IcmpType::ECHO_REPLY);
+        icmppkt.adjust_checksum();
+        frame
+    }
+
+    #[test]
+    fn udp_trace() {
+        let conf = TraceConf {
+            probes: 2,
+            max_ttl: 3,
+            ident: 40000,
+            ..TraceConf::default()
+        };
+        let mut tracer = Tracer::new(conf, MAC, LOCAL, TARGET, 100);
+        let now = Instant::now();
+        let later = now + Duration::from_millis(5);
+
+        let (ttl, frames) = probes(&mut tracer, now);
+        assert_eq!((ttl, frames.len()), (1, 2));
+        let ippkt = Ipv4Packet::parse(Cursor::new(&frames[1][ETHER_HEADER_LEN..])).unwrap();
+        assert!(ippkt.verify_checksum());
+        assert_eq!(ippkt.time_to_live(), 1);
+        assert_eq!(ippkt.ident(), 101);
+        let datagram = &frames[1][FRAME_OVERHEAD..];
+        let (src_port, dst_port, _) = wire::parse_udp(LOCAL, TARGET, datagram).unwrap();
+        assert_eq!((src_port, dst_port), (40000, 33435));
+
+        // The error quotes the probe, a quote that does not match is ignored.
+        let mut other = frames[1].clone();
+        other[FRAME_OVERHEAD] ^= 1;
+        assert!(!tracer.handle(&error(HOP1, IcmpType::TIME_EXCEEDED, 0, &other), later));
+        let reply = error(HOP1, IcmpType::TIME_EXCEEDED, 0, &frames[1]);
+        assert!(tracer.handle(&reply, later));
+        assert!(!tracer.handle(&reply, later));
+        let late = error(HOP1, IcmpType::TIME_EXCEEDED, 0, &frames[0]);
+
+        // The first probe times out.
+        assert!(!tracer.poll(later));
+        let (ttl, frames) = probes(&mut tracer, now + conf.timeout);
+        assert_eq!(ttl, 2);
+        assert!(!tracer.handle(&late, later));
+        for frame in frames.iter() {
+            let reply = error(TARGET, IcmpType::DST_UNREACHABLE, 3, frame);
+            assert!(tracer.handle(&reply, now + conf.timeout));
+        }
+        assert!(!tracer.poll(now + conf.timeout));
+        assert!(tracer.done);
+
+        let reached = Some(TraceReply {
+            addr: TARGET,
+            kind: TraceReplyKind::Reached,
+            rtt: std::time::Duration::ZERO,
+        });
+        assert_eq!(
+            tracer.hops,
+            [
+                TraceHop {
+                    ttl: 1,
+                    replies: vec![
+                        None,
+                        Some(TraceReply {
+                            addr: HOP1,
+                            kind: TraceReplyKind::TimeExceeded,
+                            rtt: later.saturating_duration_since(now),
+                        })
+                    ],
+                },
+                TraceHop {
+                    ttl: 2,
+                    replies: vec![reached, reached],
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn icmp_trace() {
+        let conf = TraceConf {
+            proto: TraceProto::Icmp,
+            first_ttl: 2,
+            max_ttl: 4,
+            probes: 1,
+            ident: 7,
+            ..TraceConf::default()
+        };
+        // The sequence numbers wrap around.
+        let mut tracer = Tracer::new(conf, MAC, LOCAL, TARGET, u16::MAX);
+        let now = Instant::now();
+
+        let (ttl, frames) = probes(&mut tracer, now);
+        assert_eq!(ttl, 2);
+        let ippkt = Ipv4Packet::parse(Cursor::new(&frames[0][ETHER_HEADER_LEN..])).unwrap();
+        assert_eq!(ippkt.protocol(), IpProtocol::ICMP);
+        let mut icmppkt = Icmpv4Packet::parse(ippkt.payload()).unwrap();
+        assert!(icmppkt.verify_checksum());
+        assert_eq!(icmppkt.icmp_type(), IcmpType::ECHO_REQUEST);
+        assert_eq!((icmppkt.ident(), icmppkt.seq_num()), (7, u16::MAX));
+
+        // Only the Time Exceeded messages in transit are matched.
+        assert!(!tracer.handle(&error(HOP2, IcmpType::TIME_EXCEEDED, 1, &frames[0]), now));
+        assert!(tracer.handle(&error(HOP2, IcmpType::TIME_EXCEEDED, 0, &frames[0]), now));
+        let (ttl, frames) = probes(&mut tracer, now);
+        assert_eq!(ttl, 3);
+        assert!(tracer.handle(&echo_reply(&frames[0]), now));
+        assert!(!tracer.poll(now));
+
+        assert_eq!(tracer.hops.len(), 2);
+        assert!(tracer.hops[1].is_last());
+        let reply = tracer.hops[1].replies[0].unwrap();
+        assert_eq!((reply.addr, reply.kind), (TARGET, TraceReplyKind::Reached));
+
+        // The trace stops at the maximum TTL without reaching the destination.
+        let mut tracer = Tracer::new(conf, MAC, LOCAL, TARGET, 0);
+        let mut now = now;
+        for ttl in 2..=4 {
+            assert_eq!(probes(&mut tracer, now).0, ttl);
+            assert!(!tracer.poll(now));
+            now += conf.timeout;
+        }
+        assert!(!tracer.poll(now));
+        assert!(tracer.done);
+        assert_eq!(tracer.hops.len(), 3);
+    }
+}
@@ -0,0 +1,412 @@
+use std::collections::VecDeque;
+use std::io;
+use std::net::{Ipv4Addr, SocketAddrV4};
+use std::ops::Range;
+
+use arrayvec::ArrayVec;
+use rpkt::arp::Operation;
+use rpkt::ether::MacAddr;
+use rpkt::ipv4::IpProtocol;
+use rpkt::neigh::NeighCache;
+use rpkt::udp::UDP_HEADER_LEN;
+use rpkt_dpdk::{Mbuf, Mempool, RxQueue, TxQueue};
+use rpkt_time::Instant;
+
+use crate::frag::{self, Reassembler};
+use crate::iface::IfaceConf;
+use crate::wire::{self, ArpInfo, Incoming, Ipv4Info, ARP_FRAME_LEN, FRAME_OVERHEAD};
+
+/// The maximum payload of a UDP datagram over IPv4.
+const MAX_PAYLOAD_LEN: usize = 65535 - 20 - UDP_HEADER_LEN;
+
+/// The minimum MTU that every IPv4 link must support.
+const MIN_MTU: usize = 68;
+
+/// The number of mbufs received or sent in a burst.
+const BATCH_SIZE: usize = 32;
+
+/// The maximum number of frames waiting for ARP resolution.
+const MAX_PENDING: usize = 256;
+
+/// The maximum number of received datagrams that are not read yet.
+const MAX_RECEIVED: usize = 1024;
+
+enum Payload {
+    Mbuf(Mbuf),
+    Owned(Vec<u8>),
+}
+
+struct Datagram {
+    data: Payload,
+    range: Range<usize>,
+    src: SocketAddrV4,
+}
+
+impl Datagram {
+    fn payload(&self) -> &[u8] {
+        match &self.data {
+            Payload::Mbuf(mbuf) => &mbuf.data()[self.range.clone()],
+            Payload::Owned(data) => &data[self.range.clone()],
+        }
+    }
+}
+
+/// A UDP socket bound to a pair of DPDK rx/tx queues.
+///
+/// The socket owns the queues exclusively: the frames received from the rx
+/// queue that are not ARP frames or UDP datagrams sent to the socket are
+/// dropped.
+///
+/// The socket behaves like a non-blocking `std::net::UdpSocket`. It resolves
+/// the MAC address of the next hop with ARP, computes the IPv4 and UDP
+/// checksums, and fragments and reassembles datagrams that exceed the MTU.
+/// Datagrams sent to an unresolved next hop are queued until the ARP reply
+/// arrives, or dropped if the resolution fails.
+///
+/// # Examples
+/// ```no_run
+/// use std::net::{Ipv4Addr, SocketAddrV4};
+///
+/// use rpkt::ether::MacAddr;
+/// use rpkt_dpdk::service;
+/// use rpkt_stack::{IfaceConf, UdpSocket};
+///
+/// let conf = IfaceConf::new(
+///     MacAddr([0x02, 0x00, 0x00, 0x00, 0x00, 0x01]),
+///     Ipv4Addr::new(192, 168, 1, 1),
+///     24,
+/// );
+/// let mut socket = UdpSocket::bind(
+///     conf,
+///     5000,
+///     service().rx_queue(0, 0).unwrap(),
+///     service().tx_queue(0, 0).unwrap(),
+///     service().mempool("mp").unwrap(),
+/// );
+///
+/// let peer = SocketAddrV4::new(Ipv4Addr::new(192, 168, 1, 2), 5000);
+/// socket.send_to(b"hello", peer).unwrap();
+///
+/// let mut buf = [0; 2048];
+/// loop {
+///     if let Ok((len, src)) = socket.recv_from(&mut buf) {
+///         println!("{} bytes from {}", len, src);
+///         break;
+///     }
+/// }
+/// ```
+pub struct UdpSocket {
+    conf: IfaceConf,
+    port: u16,
+    rxq: RxQueue,
+    txq: TxQueue,
+    mp: Mempool,
+    neigh: NeighCache<Ipv4Addr>,
+    pending: VecDeque<(Ipv4Addr, Mbuf)>,
+    reasm: Reassembler,
+    received: VecDeque<Datagram>,
+    ident: u16,
+    rx_batch: ArrayVec<Mbuf, BATCH_SIZE>,
+    tx_batch: ArrayVec<Mbuf, BATCH_SIZE>,
+}
+
+impl UdpSocket {
+    /// Bind a socket to `port` of the interface described by `conf`.
+    ///
+    /// The mbufs of the sent frames are allocated from `mp`.
+    ///
+    /// # Panics
+    ///
+    /// This function panics if the MTU of `conf` is smaller than 68 bytes.
+    pub fn bind(conf: IfaceConf, port: u16, rxq: RxQueue, txq: TxQueue, mp: Mempool) -> Self {
+        assert!(conf.mtu >= MIN_MTU, "the mtu is too small: {}", conf.mtu);
+        Self {
+            conf,
+            port,
+            rxq,
+            txq,
+            mp,
+            neigh: NeighCache::new(conf.neigh),
+            pending: VecDeque::new(),
+            reasm: Reassembler::new(conf.reassembly_timeout),
+            received: VecDeque::new(),
+            ident: 0,
+            rx_batch: ArrayVec::new(),
+            tx_batch: ArrayVec::new(),
+        }
+    }
+
+    /// Returns the local address of the socket.
+    #[inline]
+    pub fn local_addr(&self) -> SocketAddrV4 {
+        SocketAddrV4::new(self.conf.ip, self.port)
+    }
+
+    /// Returns the interface configuration.
+    #[inline]
+    pub fn iface(&self) -> &IfaceConf {
+        &self.conf
+    }
+
+    /// Returns the ARP cache of the socket.
+    #[inline]
+    pub fn neigh_cache(&self) -> &NeighCache<Ipv4Addr> {
+        &self.neigh
+    }
+
+    /// Send `buf` as a single datagram to `addr`.
+    ///
+    /// The datagram is fragmented if it exceeds the MTU. If the MAC address of
+    /// the next hop is unknown, the datagram is queued and an ARP request is
+    /// sent, and the datagram goes out once the next hop is resolved by
+    /// [`UdpSocket::poll`].
+    ///
+    /// Returns the number of bytes sent, which is always `buf.len()`.
+    pub fn send_to(&mut self, buf: &[u8], addr: SocketAddrV4) -> io::Result<usize> {
+        if buf.len() > MAX_PAYLOAD_LEN {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "datagram is too large",
+            ));
+        }
+        let dst = *addr.ip();
+        let next_hop = if dst.is_broadcast() {
+            dst
+        } else {
+            self.conf.next_hop(dst).ok_or_else(|| {
+                io::Error::new(io::ErrorKind::AddrNotAvailable, "network is unreachable")
+            })?
+        };
+
+        let header = wire::udp_header(self.conf.ip, self.port, dst, addr.port(), buf);
+        let info = Ipv4Info {
+            src_mac: self.conf.mac,
+            src: self.conf.ip,
+            dst,
+            protocol: IpProtocol::UDP,
+            ident: self.ident,
+            ttl: self.conf.ttl,
+        };
+        self.ident = self.ident.wrapping_add(1);
+
+        // Allocate all the fragments first, so that a datagram is either sent
+        // entirely or not at all.
+        let ip_payload_len = UDP_HEADER_LEN + buf.len();
+        let mut frames = Vec::new();
+        for (offset, len) in frag::fragments(ip_payload_len, self.conf.mtu) {
+            let mut mbuf = self.mp.try_alloc().ok_or_else(|| {
+                io::Error::new(io::ErrorKind::OutOfMemory, "mempool is exhausted")
+            })?;
+            if mbuf.capacity() < FRAME_OVERHEAD + len {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidInput,
+                    "the mtu exceeds the mbuf capacity",
+                ));
+            }
+            unsafe { mbuf.extend(FRAME_OVERHEAD + len) };
+            let frame = mbuf.data_mut();
+            wire::gather(
+                &mut frame[FRAME_OVERHEAD..],
+                &[header.as_bytes(), buf],
+                offset,
+            );
+            wire::write_ipv4(frame, &info, offset, offset + len < ip_payload_len);
+            frames.push(mbuf);
+        }
+
+        let now = Instant::now();
+        let mac = if dst.is_broadcast() {
+            Some(MacAddr::BROADCAST)
+        } else {
+            self.neigh.lookup(next_hop, now)
+        };
+        match mac {
+            Some(mac) => {
+                for mut mbuf in frames {
+                    wire::set_dest_mac(mbuf.data_mut(), mac);
+                    self.enqueue_tx(mbuf);
+                }
+            }
+            None => {
+                for mbuf in frames {
+                    if self.pending.len() >= MAX_PENDING {
+                        self.pending.pop_front();
+                    }
+                    self.pending.push_back((next_hop, mbuf));
+                }
+                self.poll_neigh(now);
+            }
+        }
+        self.flush();
+
+        Ok(buf.len())
+    }
+
+    /// Receive a single datagram, returning the number of bytes read and the
+    /// source address.
+    ///
+    /// If the datagram is larger than `buf`, the excess bytes are discarded.
+    /// The socket is polled if no datagram is buffered, and an error of kind
+    /// `WouldBlock` is returned if no datagram is available.
+    pub fn recv_from(&mut self, buf: &mut [u8]) -> io::Result<(usize, SocketAddrV4)> {
+        if self.received.is_empty() {
+            self.poll();
+        }
+        match self.received.pop_front() {
+            Some(datagram) => {
+                let payload = datagram.payload();
+                let len = payload.len().min(buf.len());
+                buf[..len].copy_from_slice(&payload[..len]);
+                Ok((len, datagram.src))
+            }
+            None => Err(io::ErrorKind::WouldBlock.into()),
+        }
+    }
+
+    /// Process the received frames, drive the ARP timers and flush the frames
+    /// waiting to be sent.
+    ///
+    /// This method should be called regularly, even if the socket is not read.
+    pub fn poll(&mut self) {
+        let now = Instant::now();
+
+        let mut batch = std::mem::take(&mut self.rx_batch);
+        self.rxq.rx(&mut batch);
+        let mut resolved = false;
+        for mbuf in batch.drain(..) {
+            resolved |= self.handle(mbuf, now);
+        }
+        self.rx_batch = batch;
+
+        if resolved {
+            self.flush_pending();
+        }
+        self.poll_neigh(now);
+        self.reasm.expire(now);
+        self.flush();
+    }
+
+    // Handle a received frame, returning whether the ARP cache is updated.
+    fn handle(&mut self, mbuf: Mbuf, now: Instant) -> bool {
+        match wire::classify(mbuf.data(), self.conf.ip) {
+            Some(Incoming::Arp(ArpInfo {
+                op,
+                sender_mac,
+                sender_ip,
+                target_ip,
+            })) => {
+                if target_ip != self.conf.ip || sender_ip.is_unspecified() {
+                    return false;
+                }
+                if op == Operation::REPLY {
+                    self.neigh.confirm(sender_ip, sender_mac, now);
+                } else if op == Operation::REQUEST {
+                    self.neigh.update(sender_ip, sender_mac, now);
+                    self.send_arp(Operation::REPLY, Some(sender_mac), sender_ip);
+                }
+                true
+            }
+            Some(Incoming::Udp { src, dst, datagram }) => {
+                let data = &mbuf.data()[datagram.clone()];
+                if let Some((src_port, dst_port, payload)) = wire::parse_udp(src, dst, data) {
+                    if dst_port == self.port {
+                        let start = datagram.start;
+                        self.deliver(Datagram {
+                            data: Payload::Mbuf(mbuf),
+                            range: start + payload.start..start + payload.end,
+                            src: SocketAddrV4::new(src, src_port),
+                        });
+                    }
+                }
+                false
+            }
+            Some(Incoming::Fragment {
+                key,
+                offset,
+                more_frags,
+                data,
+            }) => {
+                let data = &mbuf.data()[data];
+                if let Some(data) = self.reasm.insert(key, offset, more_frags, data, now) {
+                    if let Some((src_port, dst_port, payload)) =
+                        wire::parse_udp(key.src, key.dst, &data)
+                    {
+                        if dst_port == self.port {
+                            self.deliver(Datagram {
+                                data: Payload::Owned(data),
+                                range: payload,
+                                src: SocketAddrV4::new(key.src, src_port),
+                            });
+                        }
+                    }
+                }
+                false
+            }
+            None => false,
+        }
+    }
+
+    fn deliver(&mut self, datagram: Datagram) {
+        // Like a full socket buffer, the new datagram is dropped.
+        if self.received.len() < MAX_RECEIVED {
+            self.received.push_back(datagram);
+        }
+    }
+
+    // Send the ARP requests that are due, and drop the frames whose next hop
+    // fails to be resolved.
+    fn poll_neigh(&mut self, now: Instant) {
+        let mut probes = Vec::new();
+        self.neigh.poll(now, |probe| probes.push(probe));
+        for probe in probes {
+            self.send_arp(Operation::REQUEST, probe.target, probe.addr);
+        }
+
+        let neigh = &self.neigh;
+        self.pending
+            .retain(|(next_hop, _)| neigh.get(*next_hop).is_some());
+    }
+
+    // Send the pending frames whose next hop is resolved.
+    fn flush_pending(&mut self) {
+        let pending = std::mem::take(&mut self.pending);
+        for (next_hop, mut mbuf) in pending {
+            match self.neigh.get(next_hop).and_then(|entry| entry.mac()) {
+                Some(mac) => {
+                    wire::set_dest_mac(mbuf.data_mut(), mac);
+                    self.enqueue_tx(mbuf);
+                }
+                None => self.pending.push_back((next_hop, mbuf)),
+            }
+        }
+    }
+
+    fn send_arp(&mut self, op: Operation, target_mac: Option<MacAddr>, target_ip: Ipv4Addr) {
+        if let Some(mut mbuf) = self.mp.try_alloc() {
+            unsafe { mbuf.extend(ARP_FRAME_LEN) };
+            wire::write_arp(
+                mbuf.data_mut(),
+                op,
+                self.conf.mac,
+                self.conf.ip,
+                target_mac,
+                target_ip,
+            );
+            self.enqueue_tx(mbuf);
+        }
+    }
+
+    fn enqueue_tx(&mut self, mbuf: Mbuf) {
+        if self.tx_batch.is_full() {
+            self.flush();
+        }
+        // The frame is dropped if the tx queue is still congested.
+        let _ = self.tx_batch.try_push(mbuf);
+    }
+
+    fn flush(&mut self) {
+        if !self.tx_batch.is_empty() {
+            self.txq.tx(&mut self.tx_batch);
+        }
+    }
+}
@@ -0,0 +1,368 @@
+use std::net::Ipv4Addr;
+use std::ops::Range;
+
+use rpkt::arp::*;
+use rpkt::ether::*;
+use rpkt::ipv4::{IpProtocol, Ipv4Packet, IPV4_HEADER_LEN, IPV4_HEADER_TEMPLATE};
+use rpkt::udp::{UdpHeader, UdpPacket, UDP_HEADER_LEN, UDP_HEADER_TEMPLATE};
+use rpkt::{Buf, ChecksumAccumulator, Cursor, CursorMut};
+
+use crate::frag::FragKey;
+
+/// The length of the Ethernet and IPv4 headers in front of the IPv4 payload.
+pub(crate) const FRAME_OVERHEAD: usize = ETHER_HEADER_LEN + IPV4_HEADER_LEN;
+
+/// The length of an ARP frame, padded to the minimum Ethernet frame size.
+pub(crate) const ARP_FRAME_LEN: usize = ETHER_MIN_FRAME_LEN;
+
+/// The header fields shared by all the fragments of an IPv4 datagram.
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct Ipv4Info {
+    pub src_mac: MacAddr,
+    pub src: Ipv4Addr,
+    pub dst: Ipv4Addr,
+    pub protocol: IpProtocol,
+    pub ident: u16,
+    pub ttl: u8,
+}
+
+/// The fields of an ARP packet that resolves IPv4 addresses to MAC addresses.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct ArpInfo {
+    pub op: Operation,
+    pub sender_mac: MacAddr,
+    pub sender_ip: Ipv4Addr,
+    pub target_ip: Ipv4Addr,
+}
+
+/// A frame received from the wire.
+#[derive(Debug, PartialEq, Eq)]
+pub(crate) enum Incoming {
+    /// An ARP request or reply.
+    Arp(ArpInfo),
+    /// An unfragmented UDP datagram, located at `datagram` of the frame.
+    Udp {
+        src: Ipv4Addr,
+        dst: Ipv4Addr,
+        datagram: Range<usize>,
+    },
+    /// A fragment of an IPv4 datagram, whose data is located at `data` of the
+    /// frame.
+    Fragment {
+        key: FragKey,
+        offset: usize,
+        more_frags: bool,
+        data: Range<usize>,
+    },
+}
+
+/// Classify the frame received by the interface whose address is `local`.
+///
+/// Returns `None` for the frames that the stack does not handle, including
+/// the malformed ones and the ones with a bad IPv4 header checksum.
+pub(crate) fn classify(frame: &[u8], local: Ipv4Addr) -> Option<Incoming> {
+    let ethpkt = EtherPacket::parse(Cursor::new(frame)).ok()?;
+    match ethpkt.ethertype() {
+        EtherType::ARP => parse_arp(ethpkt.payload()).map(Incoming::Arp),
+        EtherType::IPV4 => {
+            let ippkt = Ipv4Packet::parse(ethpkt.payload()).ok()?;
+            let dst = Ipv4Addr::from(ippkt.dest_ip());
+            if !ippkt.check_version()
+                || !ippkt.verify_checksum()
+                || ippkt.protocol() != IpProtocol::UDP
+                || (dst != local && dst != Ipv4Addr::BROADCAST)
+            {
+                return None;
+            }
+
+            let src = Ipv4Addr::from(ippkt.source_ip());
+            let start = ETHER_HEADER_LEN + usize::from(ippkt.header_len());
+            let end = ETHER_HEADER_LEN + usize::from(ippkt.packet_len());
+            if ippkt.more_frags() || ippkt.frag_offset() != 0 {
+                Some(Incoming::Fragment {
+                    key: FragKey {
+                        src,
+                        dst,
+                        protocol: ippkt.protocol().into(),
+                        ident: ippkt.ident(),
+                    },
+                    offset: ippkt.frag_offset().into(),
+                    more_frags: ippkt.more_frags(),
+                    data: start..end,
+                })
+            } else {
+                Some(Incoming::Udp {
+                    src,
+                    dst,
+                    datagram: start..end,
+                })
+            }
+        }
+        _ => None,
+    }
+}
+
+/// Parse the ARP packet carried by an Ethernet frame.
+///
+/// Returns `None` if the packet is malformed, or if it does not resolve IPv4
+/// addresses to MAC addresses.
+pub(crate) fn parse_arp(buf: Cursor<'_>) -> Option<ArpInfo> {
+    let arppkt = ArpPacket::parse(buf).ok()?;
+    if arppkt.hardware_type() != Hardware::ETHERNET
+        || arppkt.protocol_type() != EtherType::IPV4
+        || arppkt.hardware_len() != 6
+        || arppkt.protocol_len() != 4
+    {
+        return None;
+    }
+    Some(ArpInfo {
+        op: arppkt.operation(),
+        sender_mac: MacAddr::from_bytes(arppkt.sender_hardware_addr()),
+        sender_ip: ip_from_bytes(arppkt.sender_protocol_addr()),
+        target_ip: ip_from_bytes(arppkt.target_protocol_addr()),
+    })
+}
+
+fn ip_from_bytes(data: &[u8]) -> Ipv4Addr {
+    rpkt::ipv4::Ipv4Addr::from_bytes(data).into()
+}
+
+fn pseudo_header(src: Ipv4Addr, dst: Ipv4Addr, udp_len: u16) -> ChecksumAccumulator {
+    let mut accum = ChecksumAccumulator::new();
+    accum.update(&src.octets());
+    accum.update(&dst.octets());
+    accum.update(&[0, IpProtocol::UDP.into()]);
+    accum.update(&udp_len.to_be_bytes());
+    accum
+}
+
+/// Build the UDP header carrying `payload`, including the checksum.
+///
+/// The caller guarantees that the datagram length fits in a `u16`.
+pub(crate) fn udp_header(
+    src: Ipv4Addr,
+    src_port: u16,
+    dst: Ipv4Addr,
+    dst_port: u16,
+    payload: &[u8],
+) -> UdpHeader<[u8; UDP_HEADER_LEN]> {
+    let udp_len = u16::try_from(UDP_HEADER_LEN + payload.len()).unwrap();
+    let mut header = UDP_HEADER_TEMPLATE
+        .with_source_port(src_port)
+        .with_dest_port(dst_port);
+    header.set_packet_len(udp_len);
+
+    let mut accum = pseudo_header(src, dst, udp_len);
+    accum.update(header.as_bytes());
+    accum.update(payload);
+    let checksum = !accum.finish();
+    // An all-zero checksum means no checksum, so it is sent as all-one.
+    header.set_checksum(if checksum == 0 { !0 } else { checksum });
+    header
+}
+
+/// Parse the UDP datagram sent from `src` to `dst`, verifying its checksum.
+///
+/// Returns the source port, the destination port and the range of the payload
+/// in `datagram`.
+pub(crate) fn parse_udp(
+    src: Ipv4Addr,
+    dst: Ipv4Addr,
+    datagram: &[u8],
+) -> Option<(u16, u16, Range<usize>)> {
+    let udppkt = UdpPacket::parse(Cursor::new(datagram)).ok()?;
+    let udp_len = udppkt.packet_len();
+    if udppkt.checksum() != 0 {
+        let mut accum = pseudo_header(src, dst, udp_len);
+        accum.update(&datagram[..usize::from(udp_len)]);
+        if accum.finish() != !0 {
+            return None;
+        }
+    }
+    Some((
+        udppkt.source_port(),
+        udppkt.dest_port(),
+        UDP_HEADER_LEN..usize::from(udp_len),
+    ))
+}
+
+/// Copy the bytes starting at `offset` of the concatenation of `parts` into
+/// `dst`, until `dst` is full.
+pub(crate) fn gather(dst: &mut [u8], parts: &[&[u8]], mut offset: usize) {
+    let mut written = 0;
+    for part in parts {
+        if written == dst.len() {
+            break;
+        }
+        if offset >= part.len() {
+            offset -= part.len();
+            continue;
+        }
+        let len = (part.len() - offset).min(dst.len() - written);
+        dst[written..written + len].copy_from_slice(&part[offset..offset + len]);
+        written += len;
+        offset = 0;
+    }
+    assert_eq!(written, dst.len(), "not enough data to gather");
+}
+
+/// Write the Ethernet and IPv4 headers of a fragment into the first
+/// `FRAME_OVERHEAD` bytes of `frame`. The rest of `frame` holds the fragment
+/// data, which starts at byte `offset` of the IPv4 payload.
+///
+/// The destination MAC address is left empty, see [`set_dest_mac`].
+pub(crate) fn write_ipv4(frame: &mut [u8], info: &Ipv4Info, offset: usize, more_frags: bool) {
+    let mut buf = CursorMut::new(frame);
+    buf.advance(FRAME_OVERHEAD);
+
+    let mut ippkt = Ipv4Packet::prepend_header(buf, &IPV4_HEADER_TEMPLATE);
+    ippkt.set_ident(info.ident);
+    ippkt.clear_flags();
+    ippkt.set_more_frags(more_frags);
+    ippkt.set_frag_offset(u16::try_from(offset).unwrap());
+    ippkt.set_time_to_live(info.ttl);
+    ippkt.set_protocol(info.protocol);
+    ippkt.set_source_ip(info.src.into());
+    ippkt.set_dest_ip(info.dst.into());
+    ippkt.adjust_checksum();
+
+    let mut ethpkt = EtherPacket::prepend_header(ippkt.release(), &ETHER_HEADER_TEMPLATE);
+    ethpkt.set_source_mac(info.src_mac);
+    ethpkt.set_dest_mac(MacAddr([0; 6]));
+    ethpkt.set_ethertype(EtherType::IPV4);
+}
+
+/// Set the destination MAC address of an Ethernet frame.
+#[inline]
+pub(crate) fn set_dest_mac(frame: &mut [u8], mac: MacAddr) {
+    frame[..6].copy_from_slice(mac.as_bytes());
+}
+
+/// Write an ARP frame into the first `ARP_FRAME_LEN` bytes of `frame`.
+///
+/// The frame is sent to `target_mac`, or broadcast if `target_mac` is `None`.
+pub(crate) fn write_arp(
+    frame: &mut [u8],
+    op: Operation,
+    sender_mac: MacAddr,
+    sender_ip: Ipv4Addr,
+    target_mac: Option<MacAddr>,
+    target_ip: Ipv4Addr,
+) {
+    let frame = &mut frame[..ARP_FRAME_LEN];
+    frame.fill(0);
+    let mut buf = CursorMut::new(frame);
+    buf.advance(ETHER_HEADER_LEN + ARP_HEADER_LEN);
+
+    let mut arppkt = ArpPacket::prepend_header(buf, &ARP_HEADER_TEMPLATE);
+    arppkt.set_operation(op);
+    arppkt.set_sender_hardware_addr(sender_mac.as_bytes());
+    arppkt.set_sender_protocol_addr(&sender_ip.octets());
+    arppkt.set_target_hardware_addr(target_mac.unwrap_or(MacAddr([0; 6])).as_bytes());
+    arppkt.set_target_protocol_addr(&target_ip.octets());
+
+    let mut ethpkt = EtherPacket::prepend_header(arppkt.release(), &ETHER_HEADER_TEMPLATE);
+    ethpkt.set_source_mac(sender_mac);
+    ethpkt.set_dest_mac(target_mac.unwrap_or(MacAddr::BROADCAST));
+    ethpkt.set_ethertype(EtherType::ARP);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const MAC_A: MacAddr = MacAddr([0x02, 0, 0, 0, 0, 0x0a]);
+    const MAC_B: MacAddr = MacAddr([0x02, 0, 0, 0, 0, 0x0b]);
+    const IP_A: Ipv4Addr = Ipv4Addr::new(10, 0, 0, 1);
+    const IP_B: Ipv4Addr = Ipv4Addr::new(10, 0, 0, 2);
+
+    #[test]
+    fn arp_roundtrip() {
+        let mut frame = [0xff; ARP_FRAME_LEN];
+        write_arp(&mut frame, Operation::REQUEST, MAC_A, IP_A, None, IP_B);
+        assert_eq!(&frame[..6], MacAddr::BROADCAST.as_bytes());
+        assert_eq!(
+            classify(&frame, IP_B),
+            Some(Incoming::Arp(ArpInfo {
+                op: Operation::REQUEST,
+                sender_mac: MAC_A,
+                sender_ip: IP_A,
+                target_ip: IP_B,
+            }))
+        );
+    }
+
+    #[test]
+    fn udp_roundtrip() {
+        let payload: Vec<u8> = (0..101).map(|i| i as u8).collect();
+        let header = udp_header(IP_A, 1024, IP_B, 53, &payload);
+
+        let mut frame = vec![0; FRAME_OVERHEAD + UDP_HEADER_LEN + payload.len()];
+        gather(
+            &mut frame[FRAME_OVERHEAD..],
+            &[header.as_bytes(), &payload],
+            0,
+        );
+        let info = Ipv4Info {
+            src_mac: MAC_A,
+            src: IP_A,
+            dst: IP_B,
+            protocol: IpProtocol::UDP,
+            ident: 1,
+            ttl: 64,
+        };
+        write_ipv4(&mut frame, &info, 0, false);
+        set_dest_mac(&mut frame, MAC_B);
+
+        // Frames sent to other hosts are ignored.
+        assert_eq!(classify(&frame, IP_A), None);
+        let datagram = match classify(&frame, IP_B) {
+            Some(Incoming::Udp { src, dst, datagram }) => {
+                assert_eq!((src, dst), (IP_A, IP_B));
+                datagram
+            }
+            res => panic!("unexpected frame: {:?}", res),
+        };
+        let datagram = &frame[datagram];
+        let (src_port, dst_port, range) = parse_udp(IP_A, IP_B, datagram).unwrap();
+        assert_eq!((src_port, dst_port), (1024, 53));
+        assert_eq!(&datagram[range], &payload[..]);
+
+        // A corrupted payload fails the checksum.
+        let mut corrupted = datagram.to_vec();
+        corrupted[UDP_HEADER_LEN] ^= 0xff;
+        assert_eq!(parse_udp(IP_A, IP_B, &corrupted), None);
+    }
+
+    #[test]
+    fn fragment_frame() {
+        let data = [7; 16];
+        let mut frame = [0; FRAME_OVERHEAD + 16];
+        gather(&mut frame[FRAME_OVERHEAD..], &[&[7; 8], &[7; 40]], 24);
+        assert_eq!(&frame[FRAME_OVERHEAD..], &data[..]);
+
+        let info = Ipv4Info {
+            src_mac: MAC_A,
+            src: IP_A,
+            dst: IP_B,
+            protocol: IpProtocol::UDP,
+            ident: 9,
+            ttl: 64,
+        };
+        write_ipv4(&mut frame, &info, 1480, true);
+        assert_eq!(
+            classify(&frame, IP_B),
+            Some(Incoming::Fragment {
+                key: FragKey {
+                    src: IP_A,
+                    dst: IP_B,
+                    protocol: IpProtocol::UDP.into(),
+                    ident: 9,
+                },
+                offset: 1480,
+                more_frags: true,
+                data: FRAME_OVERHEAD..FRAME_OVERHEAD + 16,
+            })
+        );
+    }
+}
@@ -0,0 +1,211 @@
+use crate::tsc;
+
+use std::ops::{Add, AddAssign, Div, Mul, Sub, SubAssign};
+
+/// A span of time measured in raw tsc cycles. Similar to
+/// [`std::time::Duration`](std::time::Duration), but it stays on the cycle
+/// counter internally, so arithmetic with [`Instant`](crate::Instant) never
+/// goes through a floating-point conversion.
+///
+/// Conversion from/to [`std::time::Duration`](std::time::Duration) is provided
+/// through the `From` trait and relies on the calibrated tsc frequency.
+#[derive(Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Default)]
+pub struct Duration(u64);
+
+impl Duration {
+    /// A duration of zero cycles.
+    pub const ZERO: Duration = Duration(0);
+
+    /// The maximum duration that can be represented.
+    pub const MAX: Duration = Duration(u64::MAX);
+
+    /// Create a new `Duration` from the raw number of tsc cycles.
+    ///
+    /// # Examples
+    /// ```
+    /// use rpkt_time::Duration;
+    ///
+    /// let one_sec = Duration::from_raw(rpkt_time::cycles_per_sec());
+    /// assert_eq!(one_sec, Duration::from_secs(1));
+    /// ```
+    #[inline]
+    pub const fn from_raw(cycles: u64) -> Duration {
+        Duration(cycles)
+    }
+
+    /// Returns the number of tsc cycles contained in this duration.
+    #[inline]
+    pub const fn raw(&self) -> u64 {
+        self.0
+    }
+
+    /// Create a new `Duration` from the specified number of whole seconds.
+    #[inline]
+    pub fn from_secs(secs: u64) -> Duration {
+        Duration(secs.saturating_mul(tsc::cycles_per_sec()))
+    }
+
+    /// Create a new `Duration` from the specified number of milliseconds.
+    #[inline]
+    pub fn from_millis(millis: u64) -> Duration {
+        Duration::from_nanos(millis.saturating_mul(1_000_000))
+    }
+
+    /// Create a new `Duration` from the specified number of microseconds.
+    #[inline]
+    pub fn from_micros(micros: u64) -> Duration {
+        Duration::from_nanos(micros.saturating_mul(1_000))
+    }
+
+    /// Create a new `Duration` from the specified number of nanoseconds.
+    #[inline]
+    pub fn from_nanos(nanos: u64) -> Duration {
+        Duration((nanos as f64 / tsc::nanos_per_cycle()) as u64)
+    }
+
+    /// Returns the total number of nanoseconds contained in this duration.
+    #[inline]
+    pub fn as_nanos(&self) -> u64 {
+        (self.0 as f64 * tsc::nanos_per_cycle()) as u64
+    }
+
+    /// Returns the total number of whole microseconds contained in this duration.
+    #[inline]
+    pub fn as_micros(&self) -> u64 {
+        self.as_nanos() / 1_000
+    }
+
+    /// Returns the total number of whole milliseconds contained in this duration.
+    #[inline]
+    pub fn as_millis(&self) -> u64 {
+        self.as_nanos() / 1_000_000
+    }
+
+    /// Returns the number of seconds contained in this duration as `f64`.
+    #[inline]
+    pub fn as_secs_f64(&self) -> f64 {
+        self.0 as f64 / tsc::cycles_per_sec() as f64
+    }
+
+    /// Returns true if this duration spans no cycles.
+    #[inline]
+    pub const fn is_zero(&self) -> bool {
+        self.0 == 0
+    }
+
+    /// Checked duration addition. Returns `None` if overflow occurred.
+    #[inline]
+    pub const fn checked_add(self, rhs: Duration) -> Option<Duration> {
+        match self.0.checked_add(rhs.0) {
+            Some(cycles) => Some(Duration(cycles)),
+            None => None,
+        }
+    }
+
+    /// Checked duration subtraction. Returns `None` if the result would be negative.
+    #[inline]
+    pub const fn checked_sub(self, rhs: Duration) -> Option<Duration> {
+        match self.0.checked_sub(rhs.0) {
+            Some(cycles) => Some(Duration(cycles)),
+            None => None,
+        }
+    }
+
+    /// Checked duration multiplication. Returns `None` if overflow occurred.
+    #[inline]
+    pub const fn checked_mul(self, rhs: u64) -> Option<Duration> {
+        match self.0.checked_mul(rhs) {
+            Some(cycles) => Some(Duration(cycles)),
+            None => None,
+        }
+    }
+
+    /// Saturating duration addition. Returns [`Duration::MAX`] if overflow occurred.
+    #[inline]
+    pub const fn saturating_add(self, rhs: Duration) -> Duration {
+        Duration(self.0.saturating_add(rhs.0))
+    }
+
+    /// Saturating duration subtraction. Returns [`Duration::ZERO`] if the result
+    /// would be negative.
+    #[inline]
+    pub const fn saturating_sub(self, rhs: Duration) -> Duration {
+        Duration(self.0.saturating_sub(rhs.0))
+    }
+}
+
+impl From<std::time::Duration> for Duration {
+    /// Convert a [`std::time::Duration`](std::time::Duration) into tsc cycles.
+    /// Durations that do not fit into a `u64` cycle counter saturate to
+    /// [`Duration::MAX`].
+    #[inline]
+    fn from(d: std::time::Duration) -> Self {
+        Duration::from_nanos(u64::try_from(d.as_nanos()).unwrap_or(u64::MAX))
+    }
+}
+
+impl From<Duration> for std::time::Duration {
+    #[inline]
+    fn from(d: Duration) -> Self {
+        std::time::Duration::from_nanos(d.as_nanos())
+    }
+}
+
+impl Add for Duration {
+    type Output = Duration;
+
+    #[inline]
+    fn add(self, rhs: Duration) -> Duration {
+        self.checked_add(rhs)
+            .expect("overflow when adding durations")
+    }
+}
+
+impl AddAssign for Duration {
+    #[inline]
+    fn add_assign(&mut self, rhs: Duration) {
+        *self = *self + rhs;
+    }
+}
+
+impl Sub for Duration {
+    type Output = Duration;
+
+    #[inline]
+    fn sub(self, rhs: Duration) -> Duration {
+        self.checked_sub(rhs)
+            .expect("overflow when subtracting durations")
+    }
+}
+
+impl SubAssign for Duration {
+    #[inline]
+    fn sub_assign(&mut self, rhs: Duration) {
+        *self = *self - rhs;
+    }
+}
+
+impl Mul<u64> for Duration {
+    type Output = Duration;
+
+    #[inline]
+    fn mul(self, rhs: u64) -> Duration {
+        self.checked_mul(rhs)
+            .expect("overflow when multiplying duration by scalar")
+    }
+}
+
+impl Div<u64> for Duration {
+    type Output = Duration;
+
+    #[inline]
+    fn div(self, rhs: u64) -> Duration {
+        Duration(self.0 / rhs)
+    }
+}
+
+impl std::fmt::Debug for Duration {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        std::time::Duration::from(*self).fmt(f)
+    }
+}
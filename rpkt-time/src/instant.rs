@@ -3,7 +3,7 @@
 
 // Copyright 2021 TiKV Project Authors. Licensed under Apache-2.0.
 
-use crate::tsc;
+use crate::{duration, tsc};
 
 use std::{
     ops::{Add, AddAssign, Sub, SubAssign},
@@ -33,6 +33,38 @@ impl Instant {
         self.0
     }
 
+    /// Create an instant from the raw value returned by [`Instant::raw`].
+    #[inline]
+    pub fn from_raw(raw: u64) -> Instant {
+        Instant(raw)
+    }
+
+    /// Returns the absolute value of the tsc counter that this instant corresponds to.
+    ///
+    /// Unlike [`Instant::raw`], which is relative to a reference point picked during
+    /// the initialization of each process, the absolute tsc counter is shared by all
+    /// the processes running on the same machine. It can be stored in shared memory
+    /// and converted back with [`Instant::from_tsc`] in another process.
+    #[inline]
+    pub fn tsc(&self) -> u64 {
+        self.0.wrapping_add(tsc::ref_tsc())
+    }
+
+    /// Create an instant from an absolute tsc counter value, e.g. one that is
+    /// obtained from [`Instant::tsc`] in another process.
+    ///
+    /// # Examples
+    /// ```
+    /// use rpkt_time::Instant;
+    ///
+    /// let now = Instant::now();
+    /// assert_eq!(Instant::from_tsc(now.tsc()), now);
+    /// ```
+    #[inline]
+    pub fn from_tsc(tsc: u64) -> Instant {
+        Instant(tsc.wrapping_sub(tsc::ref_tsc()))
+    }
+
     /// Returns an instant corresponding to "now".
     ///
     /// # Examples
@@ -94,9 +126,41 @@ impl Instant {
     /// ```
     #[inline]
     pub fn checked_duration_since(&self, earlier: Instant) -> Option<Duration> {
-        Some(Duration::from_nanos(
-            (self.0.checked_sub(earlier.0)? as f64 * tsc::nanos_per_cycle()) as u64,
-        ))
+        self.checked_cycles_since(earlier).map(Duration::from)
+    }
+
+    /// Returns the number of tsc cycles elapsed from another instant to this one,
+    /// or None if that instant is later than this one.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use rpkt_time::{Duration, Instant};
+    ///
+    /// let now = Instant::now();
+    /// let later = now + Duration::from_raw(100);
+    /// assert_eq!(later.checked_cycles_since(now), Some(Duration::from_raw(100)));
+    /// assert_eq!(now.checked_cycles_since(later), None);
+    /// ```
+    #[inline]
+    pub fn checked_cycles_since(&self, earlier: Instant) -> Option<duration::Duration> {
+        self.0.checked_sub(earlier.0).map(duration::Duration::from_raw)
+    }
+
+    /// Returns the number of tsc cycles elapsed from another instant to this one,
+    /// or zero cycles if that instant is later than this one.
+    #[inline]
+    pub fn saturating_cycles_since(&self, earlier: Instant) -> duration::Duration {
+        duration::Duration::from_raw(self.0.saturating_sub(earlier.0))
+    }
+
+    /// Returns the number of tsc cycles elapsed since this instant was created.
+    ///
+    /// Unlike [`Instant::elapsed`], no conversion into nanoseconds is performed,
+    /// which makes it suitable for the fast path.
+    #[inline]
+    pub fn elapsed_cycles(&self) -> duration::Duration {
+        Instant::now().saturating_cycles_since(*self)
     }
 
     /// Returns the amount of time elapsed from another instant to this one,
@@ -231,6 +295,44 @@ impl SubAssign<Duration> for Instant {
     }
 }
 
+impl Add<duration::Duration> for Instant {
+    type Output = Instant;
+
+    #[inline]
+    fn add(self, other: duration::Duration) -> Instant {
+        self.0
+            .checked_add(other.raw())
+            .map(Instant)
+            .expect("overflow when adding duration to instant")
+    }
+}
+
+impl AddAssign<duration::Duration> for Instant {
+    #[inline]
+    fn add_assign(&mut self, other: duration::Duration) {
+        *self = *self + other;
+    }
+}
+
+impl Sub<duration::Duration> for Instant {
+    type Output = Instant;
+
+    #[inline]
+    fn sub(self, other: duration::Duration) -> Instant {
+        self.0
+            .checked_sub(other.raw())
+            .map(Instant)
+            .expect("overflow when subtracting duration from instant")
+    }
+}
+
+impl SubAssign<duration::Duration> for Instant {
+    #[inline]
+    fn sub_assign(&mut self, other: duration::Duration) {
+        *self = *self - other;
+    }
+}
+
 impl Sub<Instant> for Instant {
     type Output = Duration;
 
@@ -256,8 +358,13 @@ impl std::fmt::Debug for Instant {
 
 /// An anchor which can be used to convert internal clocking counter into system timestamp.
 ///
+/// The anchor can also be passed to another process, e.g. through shared memory or a log
+/// file, using the byte representation returned by [`Anchor::to_bytes`]. The byte
+/// representation records the absolute tsc counter, so the restored anchor can correlate
+/// the timestamps of the other process with the wall-clock time.
+///
 /// *[See also the `Instant::as_unix_nanos()`](Instant::as_unix_nanos).*
-#[derive(Copy, Clone)]
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
 pub struct Anchor {
     unix_time_ns: u64,
     cycle: u64,
@@ -270,6 +377,9 @@ impl Default for Anchor {
 }
 
 impl Anchor {
+    /// The length of the byte representation of the anchor.
+    pub const BYTES_LEN: usize = 16;
+
     pub fn new() -> Anchor {
         let unix_time_ns = SystemTime::now()
             .duration_since(UNIX_EPOCH)
@@ -280,4 +390,81 @@ impl Anchor {
             cycle: tsc::tsc_from_ref(),
         }
     }
+
+    /// Returns the UNIX timestamp in nanoseconds at which the anchor is created.
+    #[inline]
+    pub fn unix_nanos(&self) -> u64 {
+        self.unix_time_ns
+    }
+
+    /// Returns the instant at which the anchor is created.
+    #[inline]
+    pub fn instant(&self) -> Instant {
+        Instant(self.cycle)
+    }
+
+    /// Convert an instant into a UNIX timestamp represented as the nanoseconds
+    /// elapsed from [UNIX_EPOCH](UNIX_EPOCH).
+    ///
+    /// It is equivalent to [`Instant::as_unix_nanos`].
+    #[inline]
+    pub fn instant_to_unix_nanos(&self, instant: Instant) -> u64 {
+        instant.as_unix_nanos(self)
+    }
+
+    /// Convert an instant into a [`SystemTime`](SystemTime).
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::time::{Duration, SystemTime};
+    /// use rpkt_time::{Anchor, Instant};
+    ///
+    /// let anchor = Anchor::new();
+    /// let instant = Instant::now();
+    ///
+    /// let st = anchor.instant_to_system_time(instant);
+    /// let now = SystemTime::now();
+    /// assert!(now.duration_since(st).unwrap_or_default() < Duration::from_millis(1));
+    /// ```
+    #[inline]
+    pub fn instant_to_system_time(&self, instant: Instant) -> SystemTime {
+        UNIX_EPOCH + Duration::from_nanos(self.instant_to_unix_nanos(instant))
+    }
+
+    /// Serialize the anchor into bytes.
+    ///
+    /// The first 8 bytes store the UNIX timestamp in nanoseconds and the last 8
+    /// bytes store the absolute tsc counter, both in little endian.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use rpkt_time::Anchor;
+    ///
+    /// let anchor = Anchor::new();
+    /// let bytes = anchor.to_bytes();
+    /// assert_eq!(Anchor::from_bytes(bytes), anchor);
+    /// ```
+    pub fn to_bytes(&self) -> [u8; Self::BYTES_LEN] {
+        let mut bytes = [0; Self::BYTES_LEN];
+        bytes[..8].copy_from_slice(&self.unix_time_ns.to_le_bytes());
+        bytes[8..].copy_from_slice(&self.instant().tsc().to_le_bytes());
+        bytes
+    }
+
+    /// Deserialize the anchor from the bytes created by [`Anchor::to_bytes`].
+    ///
+    /// The deserializing process must run on the same machine as the serializing
+    /// process, as the tsc counter is not shared across machines.
+    pub fn from_bytes(bytes: [u8; Self::BYTES_LEN]) -> Anchor {
+        let mut unix_time_ns = [0; 8];
+        unix_time_ns.copy_from_slice(&bytes[..8]);
+        let mut tsc = [0; 8];
+        tsc.copy_from_slice(&bytes[8..]);
+        Anchor {
+            unix_time_ns: u64::from_le_bytes(unix_time_ns),
+            cycle: Instant::from_tsc(u64::from_le_bytes(tsc)).raw(),
+        }
+    }
 }
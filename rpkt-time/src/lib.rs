@@ -24,6 +24,27 @@ mod instant;
 #[cfg(all(target_os = "linux", any(target_arch = "x86", target_arch = "x86_64")))]
 pub use instant::{Anchor, Instant};
 
+#[cfg(all(target_os = "linux", any(target_arch = "x86", target_arch = "x86_64")))]
+mod duration;
+
+#[cfg(all(target_os = "linux", any(target_arch = "x86", target_arch = "x86_64")))]
+pub use duration::Duration;
+
+#[cfg(all(target_os = "linux", any(target_arch = "x86", target_arch = "x86_64")))]
+pub mod timer;
+
+#[cfg(all(target_os = "linux", any(target_arch = "x86", target_arch = "x86_64")))]
+mod rate_limiter;
+
+#[cfg(all(target_os = "linux", any(target_arch = "x86", target_arch = "x86_64")))]
+pub use rate_limiter::{LeakyBucket, RateLimiter, TokenBucket};
+
+#[cfg(all(target_os = "linux", any(target_arch = "x86", target_arch = "x86_64")))]
+mod offset;
+
+#[cfg(all(target_os = "linux", any(target_arch = "x86", target_arch = "x86_64")))]
+pub use offset::{detect_tsc_offsets, TscOffset};
+
 #[cfg(all(target_os = "linux", any(target_arch = "x86", target_arch = "x86_64")))]
 mod tsc;
 
@@ -62,3 +83,46 @@ pub fn tsc_stable() -> bool {
 pub fn cycles_per_sec() -> u64 {
     tsc::cycles_per_sec()
 }
+
+#[cfg(all(target_os = "linux", any(target_arch = "x86", target_arch = "x86_64")))]
+/// Refine the calibrated tsc frequency against `CLOCK_MONOTONIC`.
+///
+/// The tsc frequency is measured over a short interval when this library is loaded,
+/// so the measurement error may accumulate into a noticeable drift in long-running
+/// processes. Calling this method periodically, e.g. from a management thread, measures
+/// the tsc frequency over the whole lifetime of the process instead, which bounds the
+/// drift as the process runs longer.
+///
+/// It returns the relative error of the previous calibration result, or `None` if
+/// tsc is not stable or the library is initialized less than 10ms ago. It also
+/// returns `None` and keeps the previous result if the tsc readings go backwards,
+/// which happens when the calling thread migrates to a core with a skewed tsc, so
+/// the caller may simply retry later.
+///
+/// Note that the recalibration changes the result of converting tsc cycles into time,
+/// so the values derived from the old calibration result, like [`cycles_per_sec`],
+/// should be refreshed by the caller.
+///
+/// # Examples
+/// ```
+/// use std::time::Duration;
+///
+/// std::thread::sleep(Duration::from_millis(100));
+/// let error = rpkt_time::recalibrate().unwrap();
+/// println!("the tsc frequency was off by {} ppm", error * 1_000_000.0);
+/// assert_eq!(rpkt_time::calibration_error(), error.abs());
+/// ```
+pub fn recalibrate() -> Option<f64> {
+    tsc::recalibrate()
+}
+
+#[cfg(all(target_os = "linux", any(target_arch = "x86", target_arch = "x86_64")))]
+/// Return the estimated relative error of the current calibration result.
+///
+/// Before any call to [`recalibrate`], it returns the precision that the initial
+/// calibration is required to achieve. Afterwards, it returns the absolute value of the
+/// error measured by the latest [`recalibrate`].
+#[inline]
+pub fn calibration_error() -> f64 {
+    tsc::calibration_error()
+}
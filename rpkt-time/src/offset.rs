@@ -0,0 +1,159 @@
+use std::fs::read_to_string;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::Arc;
+
+use crate::instant::Instant;
+use crate::tsc::{self, Error};
+
+// The number of round trips used to measure the offset of a single socket.
+const ROUND_TRIPS: u64 = 1000;
+
+/// The tsc offset of a CPU socket, measured against the socket of the first
+/// available CPU.
+///
+/// The tsc counters of the cores on the same socket are synchronized by the
+/// hardware, but the counters of different sockets may not be, which skews the
+/// latencies computed from timestamps taken on different NUMA nodes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TscOffset {
+    /// The physical package id of the socket.
+    pub socket_id: u32,
+    /// The CPU that is used to measure the offset.
+    pub cpu_id: usize,
+    /// The tsc counter of this socket minus that of the reference socket.
+    pub offset: i64,
+    /// The half round-trip time in cycles of the best measurement, which bounds
+    /// the error of `offset`.
+    pub uncertainty: u64,
+}
+
+impl TscOffset {
+    /// Translate an instant taken on this socket into the timebase of the
+    /// reference socket, so that it can be compared against the instants
+    /// taken on other sockets.
+    #[inline]
+    pub fn compensate(&self, instant: Instant) -> Instant {
+        Instant::from_raw(instant.raw().wrapping_sub(self.offset as u64))
+    }
+}
+
+/// Measure the tsc offset of each CPU socket.
+///
+/// For every socket, a thread pinned to the first CPU of the reference socket
+/// exchanges tsc readings with a thread pinned to a CPU of that socket, and the
+/// offset is estimated from the round trip with the lowest latency. The reference
+/// socket always has an offset of 0.
+///
+/// The measurement takes a few milliseconds, so it should be done during
+/// initialization. The result is sorted by the socket id.
+///
+/// # Examples
+/// ```
+/// let offsets = rpkt_time::detect_tsc_offsets().unwrap();
+/// for offset in offsets.iter() {
+///     println!(
+///         "socket {}: {} cycles (+/- {})",
+///         offset.socket_id, offset.offset, offset.uncertainty
+///     );
+/// }
+/// ```
+pub fn detect_tsc_offsets() -> Result<Vec<TscOffset>, Error> {
+    let cpus = tsc::available_cpus()?;
+    let ref_cpu = *cpus.first().ok_or("no available cpu")?;
+    let ref_socket = socket_id(ref_cpu)?;
+
+    let mut res = vec![TscOffset {
+        socket_id: ref_socket,
+        cpu_id: ref_cpu,
+        offset: 0,
+        uncertainty: 0,
+    }];
+    for cpu in cpus {
+        let socket_id = socket_id(cpu)?;
+        if res.iter().any(|offset| offset.socket_id == socket_id) {
+            continue;
+        }
+        let (offset, uncertainty) = measure_offset(ref_cpu, cpu)?;
+        res.push(TscOffset {
+            socket_id,
+            cpu_id: cpu,
+            offset,
+            uncertainty,
+        });
+    }
+
+    res.sort_by_key(|offset| offset.socket_id);
+    Ok(res)
+}
+
+fn socket_id(cpu: usize) -> Result<u32, Error> {
+    let s = read_to_string(format!(
+        "/sys/devices/system/cpu/cpu{cpu}/topology/physical_package_id"
+    ))?;
+    Ok(s.trim().parse()?)
+}
+
+// Return value:
+// Ok((offset, uncertainty)) of the remote cpu against the reference cpu
+fn measure_offset(ref_cpu: usize, remote_cpu: usize) -> Result<(i64, u64), Error> {
+    // `seq` is odd when the reference thread is waiting for the remote thread,
+    // and even when the remote thread has replied with its tsc in `remote_tsc`.
+    let seq = Arc::new(AtomicU64::new(0));
+    let remote_tsc = Arc::new(AtomicU64::new(0));
+    // Set when one of the threads fails, so that the other one stops spinning.
+    let abort = Arc::new(AtomicBool::new(false));
+
+    let remote = {
+        let seq = seq.clone();
+        let remote_tsc = remote_tsc.clone();
+        let abort = abort.clone();
+        std::thread::spawn(move || -> Result<(), String> {
+            tsc::set_affinity(remote_cpu).map_err(|e| {
+                abort.store(true, Ordering::Relaxed);
+                e.to_string()
+            })?;
+            for i in 0..ROUND_TRIPS {
+                while seq.load(Ordering::Acquire) != 2 * i + 1 {
+                    if abort.load(Ordering::Relaxed) {
+                        return Err("measurement aborted".to_string());
+                    }
+                    std::hint::spin_loop();
+                }
+                remote_tsc.store(tsc::tsc(), Ordering::Relaxed);
+                seq.store(2 * i + 2, Ordering::Release);
+            }
+            Ok(())
+        })
+    };
+
+    let local = std::thread::spawn(move || -> Result<(i64, u64), String> {
+        tsc::set_affinity(ref_cpu).map_err(|e| {
+            abort.store(true, Ordering::Relaxed);
+            e.to_string()
+        })?;
+        let mut best = (0, u64::MAX);
+        for i in 0..ROUND_TRIPS {
+            let t0 = tsc::tsc();
+            seq.store(2 * i + 1, Ordering::Release);
+            while seq.load(Ordering::Acquire) != 2 * i + 2 {
+                if abort.load(Ordering::Relaxed) {
+                    return Err("measurement aborted".to_string());
+                }
+                std::hint::spin_loop();
+            }
+            let t1 = tsc::tsc();
+
+            let half_rtt = (t1 - t0) / 2;
+            if half_rtt < best.1 {
+                let remote = remote_tsc.load(Ordering::Relaxed);
+                best = (remote.wrapping_sub(t0 + half_rtt) as i64, half_rtt);
+            }
+        }
+        Ok(best)
+    });
+
+    let remote_res = remote.join().map_err(|_| "measurement thread panicked")?;
+    let local_res = local.join().map_err(|_| "measurement thread panicked")?;
+    remote_res?;
+    Ok(local_res?)
+}
@@ -0,0 +1,190 @@
+use crate::instant::Instant;
+use crate::tsc;
+
+/// A common interface of the rate limiters.
+///
+/// The unit of the rate is decided by the caller. For instance, the rate can be
+/// measured in packets per second if `n` counts the packets, or bytes per second
+/// if `n` counts the bytes.
+pub trait RateLimiter {
+    /// Try to admit `n` units at instant `now`. Returns `true` and consumes the
+    /// quota if the admission is allowed.
+    fn check_at(&mut self, n: u64, now: Instant) -> bool;
+
+    /// Try to admit `n` units at the current instant.
+    #[inline]
+    fn check(&mut self, n: u64) -> bool {
+        self.check_at(n, Instant::now())
+    }
+
+    /// Busy-wait until `n` units are admitted.
+    ///
+    /// The caller must make sure that `n` units can be admitted at all, otherwise
+    /// this method never returns.
+    #[inline]
+    fn wait(&mut self, n: u64) {
+        while !self.check(n) {
+            std::hint::spin_loop();
+        }
+    }
+}
+
+/// A token bucket rate limiter.
+///
+/// The bucket is refilled with `rate` tokens per second, and can hold up to `burst`
+/// tokens. It allows the caller to send a burst of up to `burst` units after being
+/// idle, while bounding the long-term rate to `rate`.
+///
+/// The tokens are tracked in units of `1 / cycles_per_sec` so that the refilling
+/// only involves integer multiplications.
+///
+/// # Examples
+/// ```
+/// use rpkt_time::{RateLimiter, TokenBucket};
+///
+/// // 1 million packets per second, with a burst size of 32 packets.
+/// let mut tb = TokenBucket::new(1_000_000, 32);
+///
+/// assert!(tb.check(32));
+/// assert!(!tb.check(32));
+/// tb.wait(32);
+/// ```
+pub struct TokenBucket {
+    rate: u64,
+    burst: u64,
+    cycles_per_sec: u64,
+    capacity: u64,
+    credit: u64,
+    // The number of cycles that fills up an empty bucket.
+    fill_cycles: u64,
+    last: u64,
+}
+
+impl TokenBucket {
+    /// Create a new token bucket with a full bucket.
+    ///
+    /// # Panics
+    ///
+    /// This function panics if `rate` or `burst` is zero, or if the capacity of
+    /// the bucket overflows.
+    pub fn new(rate: u64, burst: u64) -> Self {
+        assert!(rate > 0 && burst > 0, "invalid rate limiter parameters");
+        let cycles_per_sec = tsc::cycles_per_sec();
+        let capacity = burst
+            .checked_mul(cycles_per_sec)
+            .expect("token bucket capacity overflow");
+        Self {
+            rate,
+            burst,
+            cycles_per_sec,
+            capacity,
+            credit: capacity,
+            fill_cycles: capacity / rate + 1,
+            last: Instant::now().raw(),
+        }
+    }
+
+    /// Returns the rate in units per second.
+    #[inline]
+    pub fn rate(&self) -> u64 {
+        self.rate
+    }
+
+    /// Returns the burst size in units.
+    #[inline]
+    pub fn burst(&self) -> u64 {
+        self.burst
+    }
+
+    /// Returns the number of available tokens at the last admission.
+    #[inline]
+    pub fn available(&self) -> u64 {
+        self.credit / self.cycles_per_sec
+    }
+}
+
+impl RateLimiter for TokenBucket {
+    #[inline]
+    fn check_at(&mut self, n: u64, now: Instant) -> bool {
+        let elapsed = now.raw().saturating_sub(self.last).min(self.fill_cycles);
+        self.last = self.last.max(now.raw());
+        self.credit = (self.credit + elapsed * self.rate).min(self.capacity);
+
+        match n.checked_mul(self.cycles_per_sec) {
+            Some(cost) if cost <= self.credit => {
+                self.credit -= cost;
+                true
+            }
+            _ => false,
+        }
+    }
+}
+
+/// A leaky bucket rate limiter.
+///
+/// Unlike the [`TokenBucket`], the leaky bucket does not allow bursts. Each
+/// admission of `n` units pushes back the next departure time by `n / rate`
+/// seconds, producing an evenly paced output. This is suitable for pacing the
+/// tx loop of a traffic generator.
+///
+/// # Examples
+/// ```
+/// use rpkt_time::{LeakyBucket, RateLimiter};
+///
+/// // 10 Gbps, measured in bytes.
+/// let mut lb = LeakyBucket::new(10_000_000_000 / 8);
+///
+/// lb.wait(1500);
+/// lb.wait(1500);
+/// ```
+pub struct LeakyBucket {
+    rate: u64,
+    // The departure time and the cost per unit are stored in 32.32 fixed-point
+    // cycles to avoid accumulating rounding errors.
+    cost_per_unit: u128,
+    next: u128,
+}
+
+impl LeakyBucket {
+    /// Create a new leaky bucket that drains `rate` units per second.
+    ///
+    /// # Panics
+    ///
+    /// This function panics if `rate` is zero.
+    pub fn new(rate: u64) -> Self {
+        assert!(rate > 0, "invalid rate limiter parameters");
+        Self {
+            rate,
+            cost_per_unit: (u128::from(tsc::cycles_per_sec()) << 32) / u128::from(rate),
+            next: u128::from(Instant::now().raw()) << 32,
+        }
+    }
+
+    /// Returns the rate in units per second.
+    #[inline]
+    pub fn rate(&self) -> u64 {
+        self.rate
+    }
+
+    /// Returns the earliest instant at which the next admission is allowed.
+    #[inline]
+    pub fn next_departure(&self) -> Instant {
+        Instant::from_raw((self.next >> 32) as u64)
+    }
+}
+
+impl RateLimiter for LeakyBucket {
+    #[inline]
+    fn check_at(&mut self, n: u64, now: Instant) -> bool {
+        let now = u128::from(now.raw()) << 32;
+        if now < self.next {
+            return false;
+        }
+
+        let cost = self.cost_per_unit * u128::from(n);
+        // If the caller is late, allow it to catch up by at most a single admission,
+        // so that the long-term rate is not lowered by the scheduling jitter.
+        self.next = self.next.max(now.saturating_sub(cost)) + cost;
+        true
+    }
+}
@@ -0,0 +1,434 @@
+//! A hierarchical timing wheel driven by raw tsc deadlines.
+//!
+//! The wheel is designed to be owned by a single lcore and polled from its
+//! run-to-completion loop. Inserting and cancelling a timer take O(1) time,
+//! while polling only touches the slots whose tick has elapsed.
+//!
+//! # Examples
+//! ```
+//! use rpkt_time::timer::TimerWheel;
+//! use rpkt_time::{Duration, Instant};
+//!
+//! let now = Instant::now();
+//! let mut wheel = TimerWheel::new(Duration::from_micros(10), now);
+//!
+//! let id = wheel.insert(now + Duration::from_millis(1), "retransmit");
+//! wheel.insert(now + Duration::from_millis(2), "flush");
+//! assert_eq!(wheel.cancel(id), Some("retransmit"));
+//!
+//! // Nothing expires before the deadline.
+//! assert!(wheel.poll(now + Duration::from_micros(1500)).is_none());
+//! let (_, data) = wheel.poll(now + Duration::from_millis(3)).unwrap();
+//! assert_eq!(data, "flush");
+//! ```
+
+use crate::duration::Duration;
+use crate::instant::Instant;
+
+const LEVEL_BITS: u32 = 6;
+const SLOTS: usize = 1 << LEVEL_BITS;
+const SLOT_MASK: u64 = (SLOTS - 1) as u64;
+const LEVELS: usize = 6;
+// The maximum number of ticks that can be represented by the wheel.
+// Timers with a larger deadline are parked in the last level and re-inserted
+// when that level is cascaded.
+const MAX_TICKS: u64 = 1 << (LEVEL_BITS as usize * LEVELS);
+
+const NIL: u32 = u32::MAX;
+// The pseudo level that identifies the list of expired timers.
+const READY: u8 = LEVELS as u8;
+
+/// A handle to a timer stored in the [`TimerWheel`].
+///
+/// The handle becomes stale once the timer expires or is cancelled, and
+/// passing a stale handle to the wheel is a no-op.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct TimerId {
+    index: u32,
+    gen: u32,
+}
+
+struct Entry<T> {
+    data: Option<T>,
+    deadline: u64,
+    when: u64,
+    gen: u32,
+    prev: u32,
+    next: u32,
+    level: u8,
+    slot: u8,
+}
+
+/// A hierarchical timing wheel keyed on raw tsc deadlines.
+///
+/// The wheel has 6 levels with 64 slots each. Every slot of level 0 spans a
+/// single tick, whose length is configured when the wheel is created. Deadlines
+/// are rounded up to the next tick, so a timer never expires early, but it may
+/// expire up to one tick late.
+///
+/// The wheel is not thread-safe. It is supposed to be used by the lcore that
+/// owns it, e.g. for flow expiry, retransmission or periodic stats flushing.
+pub struct TimerWheel<T> {
+    entries: Vec<Entry<T>>,
+    free: u32,
+    heads: [[u32; SLOTS]; LEVELS],
+    ready: u32,
+    tick_cycles: u64,
+    // The next tick to be processed.
+    elapsed: u64,
+    len: usize,
+}
+
+impl<T> TimerWheel<T> {
+    /// Create a new timing wheel whose ticks are `resolution` long, starting
+    /// at instant `now`.
+    ///
+    /// # Panics
+    ///
+    /// This function panics if `resolution` is zero.
+    pub fn new(resolution: Duration, now: Instant) -> Self {
+        assert!(!resolution.is_zero(), "zero timer resolution");
+        let tick_cycles = resolution.raw();
+        Self {
+            entries: Vec::new(),
+            free: NIL,
+            heads: [[NIL; SLOTS]; LEVELS],
+            ready: NIL,
+            tick_cycles,
+            elapsed: now.raw() / tick_cycles,
+            len: 0,
+        }
+    }
+
+    /// Returns the length of a single tick.
+    #[inline]
+    pub fn resolution(&self) -> Duration {
+        Duration::from_raw(self.tick_cycles)
+    }
+
+    /// Returns the number of pending timers, including the expired timers that
+    /// have not been polled.
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    /// Returns true if the wheel contains no timers.
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Insert a timer that expires at `deadline`, carrying `data`.
+    ///
+    /// If the tick of `deadline` has already been processed by [`poll`], the timer
+    /// is expired right away and returned by the next poll, whatever instant is
+    /// passed to it.
+    ///
+    /// [`poll`]: TimerWheel::poll
+    pub fn insert(&mut self, deadline: Instant, data: T) -> TimerId {
+        let when =
+            deadline.raw() / self.tick_cycles + u64::from(deadline.raw() % self.tick_cycles != 0);
+
+        let index = if self.free != NIL {
+            let index = self.free;
+            let entry = &mut self.entries[index as usize];
+            self.free = entry.next;
+            entry.data = Some(data);
+            entry.deadline = deadline.raw();
+            entry.when = when;
+            index
+        } else {
+            assert!(self.entries.len() < NIL as usize, "too many timers");
+            self.entries.push(Entry {
+                data: Some(data),
+                deadline: deadline.raw(),
+                when,
+                gen: 0,
+                prev: NIL,
+                next: NIL,
+                level: 0,
+                slot: 0,
+            });
+            (self.entries.len() - 1) as u32
+        };
+
+        self.len += 1;
+        self.schedule(index);
+        TimerId {
+            index,
+            gen: self.entries[index as usize].gen,
+        }
+    }
+
+    /// Returns the deadline of the timer if it is still pending.
+    pub fn deadline(&self, id: TimerId) -> Option<Instant> {
+        self.entry(id)
+            .map(|entry| Instant::from_raw(entry.deadline))
+    }
+
+    /// Cancel a pending timer, returning its data.
+    ///
+    /// Returns `None` if the timer has expired and been polled, or has already
+    /// been cancelled.
+    pub fn cancel(&mut self, id: TimerId) -> Option<T> {
+        self.entry(id)?;
+        self.unlink(id.index);
+        Some(self.release(id.index))
+    }
+
+    /// Poll an expired timer, returning its handle and data.
+    ///
+    /// All the ticks up to `now` are processed before this method returns `None`,
+    /// so the caller typically polls in a loop until no timer is returned.
+    pub fn poll(&mut self, now: Instant) -> Option<(TimerId, T)> {
+        let now_tick = now.raw() / self.tick_cycles;
+
+        while self.ready == NIL && self.elapsed <= now_tick {
+            // Skip the ticks that do not touch any non-empty slot.
+            let next_tick = self.next_tick();
+            if next_tick > now_tick {
+                self.elapsed = now_tick + 1;
+                break;
+            }
+            self.elapsed = next_tick;
+            self.process_tick();
+        }
+
+        if self.ready == NIL {
+            return None;
+        }
+        let index = self.ready;
+        self.unlink(index);
+        let gen = self.entries[index as usize].gen;
+        Some((TimerId { index, gen }, self.release(index)))
+    }
+
+    fn entry(&self, id: TimerId) -> Option<&Entry<T>> {
+        self.entries
+            .get(id.index as usize)
+            .filter(|entry| entry.gen == id.gen && entry.data.is_some())
+    }
+
+    // Put a linked-out entry into the slot that matches its expiry tick, or into
+    // the ready list if that tick has already been processed.
+    fn schedule(&mut self, index: u32) {
+        let when = self.entries[index as usize].when;
+        if when < self.elapsed {
+            self.push_ready(index);
+            return;
+        }
+        let delta = when - self.elapsed;
+
+        let (level, when) = if delta >= MAX_TICKS {
+            (LEVELS - 1, self.elapsed + MAX_TICKS - 1)
+        } else {
+            let level = (64 - (delta | SLOT_MASK).leading_zeros() - 1) / LEVEL_BITS;
+            (level as usize, when)
+        };
+        let slot = ((when >> (level as u32 * LEVEL_BITS)) & SLOT_MASK) as usize;
+
+        let head = self.heads[level][slot];
+        let entry = &mut self.entries[index as usize];
+        entry.level = level as u8;
+        entry.slot = slot as u8;
+        entry.prev = NIL;
+        entry.next = head;
+        if head != NIL {
+            self.entries[head as usize].prev = index;
+        }
+        self.heads[level][slot] = index;
+    }
+
+    fn unlink(&mut self, index: u32) {
+        let entry = &self.entries[index as usize];
+        let (prev, next) = (entry.prev, entry.next);
+        if prev != NIL {
+            self.entries[prev as usize].next = next;
+        } else if entry.level == READY {
+            self.ready = next;
+        } else {
+            self.heads[entry.level as usize][entry.slot as usize] = next;
+        }
+        if next != NIL {
+            self.entries[next as usize].prev = prev;
+        }
+    }
+
+    fn release(&mut self, index: u32) -> T {
+        let entry = &mut self.entries[index as usize];
+        entry.gen = entry.gen.wrapping_add(1);
+        entry.prev = NIL;
+        entry.next = self.free;
+        self.free = index;
+        self.len -= 1;
+        entry.data.take().unwrap()
+    }
+
+    // Find the earliest tick that processes a non-empty slot, or `u64::MAX` if
+    // the wheel is empty.
+    fn next_tick(&self) -> u64 {
+        let mut res = u64::MAX;
+        for level in 0..LEVELS {
+            let shift = level as u32 * LEVEL_BITS;
+            // The first tick that may process a slot of the current level.
+            let start = (self.elapsed + (1 << shift) - 1) >> shift;
+            if start << shift >= res {
+                break;
+            }
+            if let Some(offset) = (0..SLOTS as u64)
+                .find(|i| self.heads[level][((start + i) & SLOT_MASK) as usize] != NIL)
+            {
+                res = res.min((start + offset) << shift);
+            }
+        }
+        res
+    }
+
+    fn process_tick(&mut self) {
+        let tick = self.elapsed;
+
+        // Cascade the timers of the upper levels whenever the lower levels wrap around.
+        for level in 1..LEVELS {
+            let shift = level as u32 * LEVEL_BITS;
+            if tick & ((1 << shift) - 1) != 0 {
+                break;
+            }
+            let slot = ((tick >> shift) & SLOT_MASK) as usize;
+            let mut index = std::mem::replace(&mut self.heads[level][slot], NIL);
+            while index != NIL {
+                let next = self.entries[index as usize].next;
+                self.schedule(index);
+                index = next;
+            }
+        }
+
+        let slot = (tick & SLOT_MASK) as usize;
+        let mut index = std::mem::replace(&mut self.heads[0][slot], NIL);
+        while index != NIL {
+            let next = self.entries[index as usize].next;
+            self.push_ready(index);
+            index = next;
+        }
+
+        self.elapsed += 1;
+    }
+
+    fn push_ready(&mut self, index: u32) {
+        let entry = &mut self.entries[index as usize];
+        entry.level = READY;
+        entry.prev = NIL;
+        entry.next = self.ready;
+        if self.ready != NIL {
+            self.entries[self.ready as usize].prev = index;
+        }
+        self.ready = index;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const TICK: u64 = 10;
+
+    fn at(tick: u64) -> Instant {
+        Instant::from_raw(tick * TICK)
+    }
+
+    fn new_wheel() -> TimerWheel<u32> {
+        TimerWheel::new(Duration::from_raw(TICK), at(0))
+    }
+
+    #[test]
+    fn cascade_to_level_0() {
+        let mut wheel = new_wheel();
+        let a = wheel.insert(at(100), 1);
+        let b = wheel.insert(at(5000), 2);
+        assert_eq!(wheel.entries[a.index as usize].level, 1);
+        assert_eq!(wheel.entries[b.index as usize].level, 2);
+
+        // Tick 64 cascades `a` from level 1, while `b` is cascaded from level 2
+        // at tick 4096 and from level 1 at tick 4992.
+        assert!(wheel.poll(at(99)).is_none());
+        assert_eq!(wheel.entries[a.index as usize].level, 0);
+        assert_eq!(wheel.poll(at(100)), Some((a, 1)));
+        assert!(wheel.poll(at(4999)).is_none());
+        assert_eq!(wheel.entries[b.index as usize].level, 0);
+        assert_eq!(wheel.poll(at(5000)), Some((b, 2)));
+        assert!(wheel.is_empty());
+
+        // A deadline within a tick is rounded up.
+        let c = wheel.insert(Instant::from_raw(6000 * TICK + 1), 3);
+        assert!(wheel.poll(at(6000)).is_none());
+        assert_eq!(wheel.poll(at(6001)), Some((c, 3)));
+    }
+
+    #[test]
+    fn park_beyond_max_ticks() {
+        let mut wheel = new_wheel();
+        let id = wheel.insert(at(MAX_TICKS + 10), 1);
+        assert_eq!(wheel.entries[id.index as usize].level as usize, LEVELS - 1);
+        assert_eq!(wheel.deadline(id), Some(at(MAX_TICKS + 10)));
+
+        assert!(wheel.poll(at(MAX_TICKS - 1)).is_none());
+        assert!(wheel.poll(at(MAX_TICKS + 9)).is_none());
+        assert_eq!(wheel.poll(at(MAX_TICKS + 10)), Some((id, 1)));
+        assert!(wheel.is_empty());
+    }
+
+    #[test]
+    fn cancel_after_cascade() {
+        let mut wheel = new_wheel();
+        let a = wheel.insert(at(100), 1);
+        let b = wheel.insert(at(100), 2);
+        let c = wheel.insert(at(101), 3);
+
+        assert!(wheel.poll(at(64)).is_none());
+        assert_eq!(wheel.entries[b.index as usize].level, 0);
+        assert_eq!(wheel.cancel(b), Some(2));
+        assert_eq!(wheel.cancel(b), None);
+        assert_eq!(wheel.deadline(b), None);
+        assert_eq!(wheel.len(), 2);
+
+        assert_eq!(wheel.poll(at(101)), Some((a, 1)));
+        assert_eq!(wheel.poll(at(101)), Some((c, 3)));
+        assert!(wheel.poll(at(101)).is_none());
+    }
+
+    #[test]
+    fn reuse_released_slot() {
+        let mut wheel = new_wheel();
+        let a = wheel.insert(at(10), 1);
+        assert_eq!(wheel.cancel(a), Some(1));
+
+        let b = wheel.insert(at(20), 2);
+        assert_eq!(b.index, a.index);
+        assert_ne!(b, a);
+        assert_eq!(wheel.cancel(a), None);
+        assert_eq!(wheel.deadline(b), Some(at(20)));
+
+        assert_eq!(wheel.poll(at(20)), Some((b, 2)));
+        let c = wheel.insert(at(30), 3);
+        assert_eq!(c.index, a.index);
+        assert_eq!(wheel.cancel(b), None);
+        assert_eq!(wheel.poll(at(30)), Some((c, 3)));
+    }
+
+    #[test]
+    fn insert_expired() {
+        let mut wheel = new_wheel();
+        assert!(wheel.poll(at(100)).is_none());
+
+        // Both deadlines belong to processed ticks, so they expire on the next
+        // poll even if it is given an earlier instant.
+        let a = wheel.insert(at(50), 1);
+        let b = wheel.insert(at(100), 2);
+        let c = wheel.insert(at(101), 3);
+        assert_eq!(wheel.entries[a.index as usize].level, READY);
+        assert_eq!(wheel.cancel(a), Some(1));
+        assert_eq!(wheel.poll(at(0)), Some((b, 2)));
+        assert!(wheel.poll(at(100)).is_none());
+        assert_eq!(wheel.poll(at(101)), Some((c, 3)));
+    }
+}
@@ -1,17 +1,21 @@
 use std::cell::UnsafeCell;
 use std::fs::read_to_string;
 use std::mem::{size_of, zeroed, MaybeUninit};
+use std::sync::atomic::{AtomicU64, Ordering};
 use std::sync::{Arc, RwLock};
 use std::time::Instant;
 
 use ctor::ctor;
 use libc::{cpu_set_t, sched_setaffinity, CPU_SET};
 
-type Error = Box<dyn std::error::Error>;
+pub(crate) type Error = Box<dyn std::error::Error>;
 
 struct TimeState {
-    nanos_per_cycle: UnsafeCell<f64>,
-    cycles_per_sec: UnsafeCell<u64>,
+    // The calibration result may be updated by `recalibrate` at runtime, so it is
+    // stored in atomics. `nanos_per_cycle` and `calibration_error` store the bits of f64.
+    nanos_per_cycle: AtomicU64,
+    cycles_per_sec: AtomicU64,
+    calibration_error: AtomicU64,
     ref_time: UnsafeCell<MaybeUninit<Instant>>,
     ref_tsc: UnsafeCell<u64>,
     tsc_stable: UnsafeCell<bool>,
@@ -20,8 +24,9 @@ struct TimeState {
 unsafe impl Sync for TimeState {}
 
 static TS: TimeState = TimeState {
-    nanos_per_cycle: UnsafeCell::new(0.0),
-    cycles_per_sec: UnsafeCell::new(0),
+    nanos_per_cycle: AtomicU64::new(0),
+    cycles_per_sec: AtomicU64::new(0),
+    calibration_error: AtomicU64::new(0),
     ref_time: UnsafeCell::new(MaybeUninit::uninit()),
     ref_tsc: UnsafeCell::new(0),
     tsc_stable: UnsafeCell::new(false),
@@ -30,7 +35,7 @@ static TS: TimeState = TimeState {
 static PRECISION: f64 = 0.00001;
 
 #[inline]
-fn tsc() -> u64 {
+pub(crate) fn tsc() -> u64 {
     #[cfg(target_arch = "x86")]
     use core::arch::x86::_rdtsc;
 
@@ -42,12 +47,12 @@ fn tsc() -> u64 {
 
 #[inline]
 pub(crate) fn nanos_per_cycle() -> f64 {
-    unsafe { *TS.nanos_per_cycle.get() }
+    f64::from_bits(TS.nanos_per_cycle.load(Ordering::Relaxed))
 }
 
 #[inline]
 pub(crate) fn cycles_per_sec() -> u64 {
-    unsafe { *TS.cycles_per_sec.get() }
+    TS.cycles_per_sec.load(Ordering::Relaxed)
 }
 
 #[inline]
@@ -55,10 +60,74 @@ pub(crate) fn tsc_from_ref() -> u64 {
     tsc().wrapping_sub(unsafe { *TS.ref_tsc.get() })
 }
 
+#[inline]
+pub(crate) fn ref_tsc() -> u64 {
+    unsafe { *TS.ref_tsc.get() }
+}
+
 pub(crate) fn tsc_stable() -> bool {
     unsafe { *TS.tsc_stable.get() }
 }
 
+#[inline]
+pub(crate) fn calibration_error() -> f64 {
+    f64::from_bits(TS.calibration_error.load(Ordering::Relaxed))
+}
+
+// Refine the tsc frequency by comparing the tsc counter against CLOCK_MONOTONIC
+// (which backs `std::time::Instant` on Linux) over the whole interval since the
+// reference point was taken.
+//
+// Return value:
+// Some(error): the relative error of the previous calibration result
+// None: tsc is not stable, the interval is too short for a meaningful measurement,
+// or the tsc readings are not monotonic
+pub(crate) fn recalibrate() -> Option<f64> {
+    if !tsc_stable() {
+        return None;
+    }
+    let (ref_time, ref_tsc) = unsafe { ((*TS.ref_time.get()).assume_init(), *TS.ref_tsc.get()) };
+
+    // Take the pair of readings that are most tightly bracketed by two tsc readings
+    // to reduce the noise of reading the monotonic clock.
+    //
+    // The calling thread is not pinned, so it may migrate to a core whose tsc is
+    // skewed between two readings. A pair whose tsc goes backwards is dropped.
+    let mut best = (u64::MAX, 0, ref_time);
+    for _ in 0..16 {
+        let tsc1 = tsc();
+        let t = Instant::now();
+        let tsc2 = tsc();
+        match tsc2.checked_sub(tsc1) {
+            Some(bracket) if bracket < best.0 => best = (bracket, tsc1 + bracket / 2, t),
+            _ => {}
+        }
+    }
+    let (bracket, cur_tsc, cur_time) = best;
+    if bracket == u64::MAX {
+        return None;
+    }
+
+    let elapsed_ns = (cur_time - ref_time).as_nanos() as f64;
+    if elapsed_ns < 10_000_000.0 {
+        return None;
+    }
+    let cycles = cur_tsc.checked_sub(ref_tsc)? as f64;
+
+    let error = (cycles * nanos_per_cycle() - elapsed_ns) / elapsed_ns;
+    let nanos_per_cycle = elapsed_ns / cycles;
+    TS.nanos_per_cycle
+        .store(nanos_per_cycle.to_bits(), Ordering::Relaxed);
+    TS.cycles_per_sec.store(
+        (1_000_000_000f64 / nanos_per_cycle) as u64,
+        Ordering::Relaxed,
+    );
+    TS.calibration_error
+        .store(error.abs().to_bits(), Ordering::Relaxed);
+
+    Some(error)
+}
+
 // Return value:
 // Ok((nanos_per_cycle, cycles_per_sec, ref_time, ref_tsc))
 // None: no stable measurement
@@ -165,8 +234,11 @@ unsafe fn module_init() {
 
     match measured_fraq {
         Some((nanos_per_cycle, cycles_per_sec, ref_time, ref_tsc)) => {
-            *TS.nanos_per_cycle.get() = nanos_per_cycle;
-            *TS.cycles_per_sec.get() = cycles_per_sec;
+            TS.nanos_per_cycle
+                .store(nanos_per_cycle.to_bits(), Ordering::Relaxed);
+            TS.cycles_per_sec.store(cycles_per_sec, Ordering::Relaxed);
+            TS.calibration_error
+                .store(PRECISION.to_bits(), Ordering::Relaxed);
             (*TS.ref_time.get()).write(ref_time);
             (*TS.ref_time.get()).assume_init();
             *TS.ref_tsc.get() = ref_tsc;
@@ -187,13 +259,13 @@ unsafe fn module_init() {
 // The following code is taken from minstant at https://github.com/tikv/minstant
 
 // Retrieve available CPUs from `/sys` filesystem.
-fn available_cpus() -> Result<Vec<usize>, Error> {
+pub(crate) fn available_cpus() -> Result<Vec<usize>, Error> {
     let s = read_to_string("/sys/devices/system/cpu/online")?;
     parse_cpu_list_format(&s)
 }
 
 /// A wrapper function of sched_setaffinity(2)
-fn set_affinity(cpuid: usize) -> Result<(), Error> {
+pub(crate) fn set_affinity(cpuid: usize) -> Result<(), Error> {
     let mut set = unsafe { zeroed::<cpu_set_t>() };
 
     unsafe { CPU_SET(cpuid, &mut set) };
@@ -0,0 +1,42 @@
+use std::hint::black_box;
+use std::time::{Duration, Instant};
+
+use crate::dissect::parse_layers;
+use crate::{read_frames, Args, Result};
+
+pub fn run(args: &Args) -> Result<()> {
+    let frames = match args.positional.first() {
+        Some(path) => read_frames(path)?,
+        None => rpkt::corpus::generate()
+            .into_iter()
+            .filter(|sample| sample.valid)
+            .map(|sample| sample.frame)
+            .collect(),
+    };
+    if frames.is_empty() {
+        return Err("no frames to parse".to_string());
+    }
+    let secs = args.option("secs")?.unwrap_or(1);
+
+    let duration = Duration::from_secs(secs);
+    let (mut nb_frames, mut nb_bytes, mut nb_layers) = (0u64, 0u64, 0u64);
+    let start = Instant::now();
+    while start.elapsed() < duration {
+        for frame in frames.iter() {
+            nb_layers += black_box(parse_layers(black_box(frame))) as u64;
+            nb_bytes += frame.len() as u64;
+        }
+        nb_frames += frames.len() as u64;
+    }
+    let elapsed = start.elapsed().as_secs_f64();
+
+    println!(
+        "parsed {} frames in {:.3}s: {:.2} Mpps, {:.2} Gbps, {:.2} layers per frame",
+        nb_frames,
+        elapsed,
+        nb_frames as f64 / elapsed / 1e6,
+        nb_bytes as f64 * 8.0 / elapsed / 1e9,
+        nb_layers as f64 / nb_frames as f64
+    );
+    Ok(())
+}
@@ -0,0 +1,216 @@
+use std::fmt::{Display, Write};
+
+use rpkt::ether::{EtherHeader, EtherType, VlanStack};
+use rpkt::icmpv4::Icmpv4Packet;
+use rpkt::ipv4::{IpProtocol, Ipv4Packet};
+use rpkt::ipv6::Ipv6Packet;
+use rpkt::tcp::TcpPacket;
+use rpkt::udp::UdpPacket;
+use rpkt::Cursor;
+use rpkt_io::RxQueueLike;
+
+use crate::{open_pcap, Args, Result};
+
+pub fn run(args: &Args) -> Result<()> {
+    let path = args.positional(0, "pcap")?;
+    let hex = args.flag("hex");
+
+    let mut rxq = open_pcap(path)?;
+    let mut index = 0;
+    loop {
+        let nb_rx = rxq
+            .rx(&mut |frame| {
+                println!("{:>6} {}", index, summary(frame));
+                if hex {
+                    print!("{}", rpkt::fmt::hexdump(frame));
+                }
+                index += 1;
+            })
+            .map_err(|err| format!("{}: {}", path, err))?;
+        if nb_rx == 0 {
+            return Ok(());
+        }
+    }
+}
+
+/// Returns a line describing the layers of the Ethernet frame, e.g.
+/// `02:00:00:00:00:01 > 02:00:00:00:00:02, vlan 10, IPv4 192.0.2.1.1024 >
+/// 192.0.2.2.53: UDP len 20, len 74`.
+pub fn summary(frame: &[u8]) -> String {
+    let vlans = match VlanStack::parse(frame) {
+        Some(vlans) => vlans,
+        None => return format!("truncated ethernet frame, len {}", frame.len()),
+    };
+
+    let header = EtherHeader::new_unchecked(frame);
+    let mut out = format!("{} > {}", header.source_mac(), header.dest_mac());
+    for tag in vlans.tags() {
+        write!(out, ", vlan {}", tag.vid).unwrap();
+    }
+    out.push_str(", ");
+
+    let payload = Cursor::new(&frame[vlans.header_len()..]);
+    match vlans.ethertype() {
+        EtherType::IPV4 => match Ipv4Packet::parse(payload) {
+            // The transport header is only in the first fragment.
+            Ok(pkt) if pkt.frag_offset() == 0 => {
+                let (src, dst, protocol) = (pkt.source_ip(), pkt.dest_ip(), pkt.protocol());
+                transport(&mut out, "IPv4", src, dst, protocol, pkt.payload());
+            }
+            Ok(pkt) => write!(
+                out,
+                "IPv4 {} > {}: {} fragment",
+                pkt.source_ip(),
+                pkt.dest_ip(),
+                pkt.protocol()
+            )
+            .unwrap(),
+            Err(_) => out.push_str("malformed IPv4"),
+        },
+        EtherType::IPV6 => match Ipv6Packet::parse(payload) {
+            Ok(pkt) => {
+                let (src, dst, protocol) = (pkt.source_ip(), pkt.dest_ip(), pkt.next_header());
+                transport(&mut out, "IPv6", src, dst, protocol, pkt.payload());
+            }
+            Err(_) => out.push_str("malformed IPv6"),
+        },
+        ethertype => write!(out, "{}", ethertype).unwrap(),
+    }
+
+    write!(out, ", len {}", frame.len()).unwrap();
+    out
+}
+
+fn transport<A: Display>(
+    out: &mut String,
+    ip: &str,
+    src: A,
+    dst: A,
+    protocol: IpProtocol,
+    payload: Cursor,
+) {
+    match protocol {
+        IpProtocol::TCP => match TcpPacket::parse(payload) {
+            Ok(pkt) => {
+                let flags: String = [
+                    (pkt.syn(), 'S'),
+                    (pkt.fin(), 'F'),
+                    (pkt.rst(), 'R'),
+                    (pkt.psh(), 'P'),
+                    (pkt.ack(), '.'),
+                ]
+                .iter()
+                .filter(|(set, _)| *set)
+                .map(|(_, flag)| *flag)
+                .collect();
+                write!(
+                    out,
+                    "{} {}.{} > {}.{}: TCP [{}] seq {} ack {}",
+                    ip,
+                    src,
+                    pkt.src_port(),
+                    dst,
+                    pkt.dst_port(),
+                    flags,
+                    pkt.seq_number(),
+                    pkt.ack_number()
+                )
+                .unwrap()
+            }
+            Err(_) => write!(out, "{} {} > {}: malformed TCP", ip, src, dst).unwrap(),
+        },
+        IpProtocol::UDP => match UdpPacket::parse(payload) {
+            Ok(pkt) => write!(
+                out,
+                "{} {}.{} > {}.{}: UDP len {}",
+                ip,
+                src,
+                pkt.source_port(),
+                dst,
+                pkt.dest_port(),
+                pkt.packet_len()
+            )
+            .unwrap(),
+            Err(_) => write!(out, "{} {} > {}: malformed UDP", ip, src, dst).unwrap(),
+        },
+        IpProtocol::ICMP => match Icmpv4Packet::parse(payload) {
+            Ok(pkt) => write!(
+                out,
+                "{} {} > {}: ICMP type {} code {}",
+                ip,
+                src,
+                dst,
+                u8::from(pkt.icmp_type()),
+                pkt.code()
+            )
+            .unwrap(),
+            Err(_) => write!(out, "{} {} > {}: malformed ICMP", ip, src, dst).unwrap(),
+        },
+        _ => write!(out, "{} {} > {}: {}", ip, src, dst, protocol).unwrap(),
+    }
+}
+
+/// Parse the layers of the Ethernet frame up to the transport header, returns
+/// the number of parsed layers.
+///
+/// It parses the same layers as [`summary`] without formatting them.
+pub fn parse_layers(frame: &[u8]) -> usize {
+    let vlans = match VlanStack::parse(frame) {
+        Some(vlans) => vlans,
+        None => return 0,
+    };
+    let payload = Cursor::new(&frame[vlans.header_len()..]);
+    let (protocol, payload) = match vlans.ethertype() {
+        EtherType::IPV4 => match Ipv4Packet::parse(payload) {
+            Ok(pkt) if pkt.frag_offset() == 0 => (pkt.protocol(), pkt.payload()),
+            Ok(_) => return 2,
+            Err(_) => return 1,
+        },
+        EtherType::IPV6 => match Ipv6Packet::parse(payload) {
+            Ok(pkt) => (pkt.next_header(), pkt.payload()),
+            Err(_) => return 1,
+        },
+        _ => return 1,
+    };
+    let parsed = match protocol {
+        IpProtocol::TCP => TcpPacket::parse(payload).is_ok(),
+        IpProtocol::UDP => UdpPacket::parse(payload).is_ok(),
+        IpProtocol::ICMP => Icmpv4Packet::parse(payload).is_ok(),
+        _ => false,
+    };
+    2 + usize::from(parsed)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::gen;
+
+    #[test]
+    fn summarize_frames() {
+        let frame = gen::udp_frame(46, 3);
+        assert_eq!(
+            summary(&frame),
+            "02:00:00:00:00:01 > 02:00:00:00:00:02, IPv4 192.0.2.1.1027 > 192.0.2.2.9: UDP len 26, len 60"
+        );
+        assert_eq!(parse_layers(&frame), 3);
+
+        assert_eq!(summary(&frame[..10]), "truncated ethernet frame, len 10");
+        assert_eq!(parse_layers(&frame[..10]), 0);
+        assert!(summary(&frame[..30]).contains("malformed IPv4"));
+        assert_eq!(parse_layers(&frame[..30]), 1);
+
+        for sample in rpkt::corpus::generate() {
+            let line = summary(&sample.frame);
+            assert!(
+                line.ends_with(&format!("len {}", sample.frame.len())),
+                "{}",
+                line
+            );
+            if sample.valid && sample.protocol == "tcp" {
+                assert!(line.contains(": TCP ["), "{}", line);
+                assert_eq!(parse_layers(&sample.frame), 3);
+            }
+        }
+    }
+}
@@ -0,0 +1,174 @@
+use std::fs::File;
+use std::io::BufWriter;
+use std::str::FromStr;
+
+use rpkt::ether::{EtherPacket, EtherType, MacAddr, ETHER_HEADER_LEN, ETHER_HEADER_TEMPLATE};
+use rpkt::fill::Pattern;
+use rpkt::ipv4::{IpProtocol, Ipv4Addr, Ipv4Packet, IPV4_HEADER_LEN, IPV4_HEADER_TEMPLATE};
+use rpkt::pcap::PcapWriter;
+use rpkt::udp::{UdpPacket, UDP_HEADER_LEN, UDP_HEADER_TEMPLATE};
+use rpkt::{Buf, CursorMut};
+
+use crate::{Args, Result};
+
+const SRC_MAC: MacAddr = MacAddr([0x02, 0x00, 0x00, 0x00, 0x00, 0x01]);
+const DST_MAC: MacAddr = MacAddr([0x02, 0x00, 0x00, 0x00, 0x00, 0x02]);
+const SRC_IPV4: Ipv4Addr = Ipv4Addr([192, 0, 2, 1]);
+const DST_IPV4: Ipv4Addr = Ipv4Addr([192, 0, 2, 2]);
+
+// The discard port.
+const DST_PORT: u16 = 9;
+
+// The number of source ports the frames are spread over, so that they are
+// spread over the queues by RSS.
+const NB_FLOWS: usize = 256;
+
+// The shortest Ethernet frame without the FCS.
+const MIN_FRAME_LEN: usize = 60;
+
+// The longest frame of the `udp` profile, a jumbo frame without the FCS.
+const MAX_FRAME_LEN: usize = 9014;
+
+// The IP packet lengths of the simple IMIX and their weights.
+const IMIX: [(usize, usize); 3] = [(40, 7), (576, 4), (1500, 1)];
+
+/// The frames written by `gen`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Profile {
+    /// The samples of `rpkt::corpus`.
+    Corpus,
+    /// UDP frames of the given length, without the FCS.
+    Udp(usize),
+    /// UDP frames of the simple IMIX, with the IP packets of 40, 576 and
+    /// 1500 bytes in the ratio of 7:4:1.
+    Imix,
+}
+
+impl Profile {
+    /// The number of frames written when `--count` is not given.
+    const DEFAULT_COUNT: usize = 1024;
+
+    /// Returns `count` frames of the profile, the corpus is truncated to
+    /// `count` frames when it is given.
+    pub fn frames(&self, count: Option<usize>) -> Vec<Vec<u8>> {
+        match *self {
+            Profile::Corpus => {
+                let frames = rpkt::corpus::generate().into_iter().map(|s| s.frame);
+                frames.take(count.unwrap_or(usize::MAX)).collect()
+            }
+            Profile::Udp(frame_len) => (0..count.unwrap_or(Self::DEFAULT_COUNT))
+                .map(|i| udp_frame(frame_len - ETHER_HEADER_LEN, i))
+                .collect(),
+            Profile::Imix => {
+                let cycle: Vec<usize> = IMIX
+                    .iter()
+                    .flat_map(|&(ip_len, weight)| std::iter::repeat(ip_len).take(weight))
+                    .collect();
+                (0..count.unwrap_or(Self::DEFAULT_COUNT))
+                    .map(|i| udp_frame(cycle[i % cycle.len()], i))
+                    .collect()
+            }
+        }
+    }
+}
+
+impl FromStr for Profile {
+    type Err = String;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        match s.split_once(':') {
+            None if s == "corpus" => Ok(Profile::Corpus),
+            None if s == "imix" => Ok(Profile::Imix),
+            None if s == "udp" => Ok(Profile::Udp(MIN_FRAME_LEN)),
+            Some(("udp", len)) => match len.parse() {
+                Ok(len) if (MIN_FRAME_LEN..=MAX_FRAME_LEN).contains(&len) => Ok(Profile::Udp(len)),
+                _ => Err(format!(
+                    "the frame length of udp must be in [{}, {}], not {:?}",
+                    MIN_FRAME_LEN, MAX_FRAME_LEN, len
+                )),
+            },
+            _ => Err(format!("unknown profile {:?}", s)),
+        }
+    }
+}
+
+/// Build a UDP frame carrying an IPv4 packet of `ip_len` bytes, from the
+/// source port of `flow`.
+///
+/// The UDP payload is filled with the PRBS31 pattern seeded with `flow`, and
+/// the frame is padded to the minimum Ethernet frame length.
+pub fn udp_frame(ip_len: usize, flow: usize) -> Vec<u8> {
+    assert!(ip_len >= IPV4_HEADER_LEN + UDP_HEADER_LEN);
+    let frame_len = ETHER_HEADER_LEN + ip_len;
+    let mut frame = vec![0; frame_len.max(MIN_FRAME_LEN)];
+    let header_len = ETHER_HEADER_LEN + IPV4_HEADER_LEN + UDP_HEADER_LEN;
+    Pattern::Prbs31.fill(&mut frame[header_len..frame_len], flow as u64);
+
+    let mut buf = CursorMut::new(&mut frame[..frame_len]);
+    buf.advance(header_len);
+
+    let mut udppkt = UdpPacket::prepend_header(buf, &UDP_HEADER_TEMPLATE);
+    udppkt.set_source_port(1024 + (flow % NB_FLOWS) as u16);
+    udppkt.set_dest_port(DST_PORT);
+    udppkt.adjust_ipv4_checksum(SRC_IPV4, DST_IPV4);
+
+    let mut ippkt = Ipv4Packet::prepend_header(udppkt.release(), &IPV4_HEADER_TEMPLATE);
+    ippkt.set_protocol(IpProtocol::UDP);
+    ippkt.set_source_ip(SRC_IPV4);
+    ippkt.set_dest_ip(DST_IPV4);
+    ippkt.adjust_checksum();
+
+    let mut ethpkt = EtherPacket::prepend_header(ippkt.release(), &ETHER_HEADER_TEMPLATE);
+    ethpkt.set_source_mac(SRC_MAC);
+    ethpkt.set_dest_mac(DST_MAC);
+    ethpkt.set_ethertype(EtherType::IPV4);
+
+    frame
+}
+
+pub fn run(args: &Args) -> Result<()> {
+    let profile: Profile = args.positional(0, "profile")?.parse()?;
+    let path = args.positional(1, "pcap")?;
+    let count = args.option("count")?;
+
+    let frames = profile.frames(count);
+    let io_err = |err: std::io::Error| format!("{}: {}", path, err);
+    let file = File::create(path).map_err(io_err)?;
+    let mut writer = PcapWriter::new(BufWriter::new(file)).map_err(io_err)?;
+    for (i, frame) in frames.iter().enumerate() {
+        writer.write_frame(i as u64, frame).map_err(io_err)?;
+    }
+    writer.flush().map_err(io_err)?;
+
+    println!("wrote {} frames into {}", frames.len(), path);
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn profiles() {
+        assert_eq!("corpus".parse(), Ok(Profile::Corpus));
+        assert_eq!("udp".parse(), Ok(Profile::Udp(60)));
+        assert_eq!("udp:1514".parse(), Ok(Profile::Udp(1514)));
+        assert_eq!("imix".parse(), Ok(Profile::Imix));
+        for s in ["udp:59", "udp:9015", "udp:x", "tcp", "imix:1"] {
+            assert!(s.parse::<Profile>().is_err(), "{}", s);
+        }
+
+        let frames = Profile::Udp(1514).frames(Some(3));
+        assert_eq!(frames.len(), 3);
+        assert!(frames.iter().all(|frame| frame.len() == 1514));
+        assert_eq!(Pattern::Prbs31.check(&frames[2][42..], 2), None);
+        assert_eq!(Profile::Corpus.frames(Some(2)).len(), 2);
+
+        let lens: Vec<usize> = Profile::Imix.frames(None).iter().map(|f| f.len()).collect();
+        assert_eq!(lens.len(), Profile::DEFAULT_COUNT);
+        assert_eq!(
+            &lens[..12],
+            &[60, 60, 60, 60, 60, 60, 60, 590, 590, 590, 590, 1514]
+        );
+    }
+}
@@ -0,0 +1,218 @@
+//! `rpkt-tool`, a command line tool that exercises rpkt end to end and checks
+//! the environment of an application.
+//!
+//! ```text
+//! rpkt-tool dissect <pcap> [--hex]
+//! rpkt-tool gen <profile> <pcap> [--count <n>]
+//! rpkt-tool replay <pcap> <backend> [--loops <n>] [--dpdk-config <file>]
+//! rpkt-tool ports [--dpdk-config <file>]
+//! rpkt-tool bench [<pcap>] [--secs <n>]
+//! ```
+//!
+//! The backends of `replay` are the ones of `rpkt_io::BackendConf`. The DPDK
+//! ports, in `replay` and in `ports`, require the `dpdk` feature. The EAL
+//! arguments, mempools and ports are then bootstrapped from the TOML file of
+//! `--dpdk-config`, see `rpkt_dpdk::config`, or from the default EAL
+//! arguments without it.
+
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::BufReader;
+use std::process::ExitCode;
+use std::str::FromStr;
+
+use rpkt_io::{PcapRxQueue, RxQueueLike};
+
+mod bench;
+mod dissect;
+mod gen;
+mod ports;
+mod replay;
+
+const USAGE: &str = "\
+usage: rpkt-tool <command> [<args>]
+
+commands:
+  dissect <pcap> [--hex]
+      print a summary line of every frame of a pcap file, and its hexdump
+      with --hex
+  gen <profile> <pcap> [--count <n>]
+      write the frames of a profile into a pcap file, the profiles are
+      corpus, udp[:<frame_len>] and imix
+  replay <pcap> <backend> [--loops <n>] [--dpdk-config <file>]
+      send the frames of a pcap file to the tx queue 0 of a port, the
+      backends are dpdk:<port_id>[,<mempool>], af_packet:<ifname> and
+      pcap:<rx_path>[,<tx_path>]
+  ports [--dpdk-config <file>]
+      list the DPDK ports
+  bench [<pcap>] [--secs <n>]
+      measure the parsing rate of the frames of a pcap file, or of the
+      valid frames of the corpus";
+
+type Result<T> = std::result::Result<T, String>;
+
+/// The arguments of a command, the positional ones in order and the options
+/// given as `--<name> <value>`, or as `--<name>` for the flags.
+pub(crate) struct Args {
+    positional: Vec<String>,
+    options: HashMap<String, Option<String>>,
+}
+
+impl Args {
+    /// Parse `args`, the options in `valued` take a value and the others are
+    /// flags.
+    fn parse<I: IntoIterator<Item = String>>(args: I, valued: &[&str]) -> Result<Self> {
+        let mut parsed = Self {
+            positional: Vec::new(),
+            options: HashMap::new(),
+        };
+        let mut args = args.into_iter();
+        while let Some(arg) = args.next() {
+            match arg.strip_prefix("--") {
+                Some(name) if valued.contains(&name) => {
+                    let value = args
+                        .next()
+                        .ok_or_else(|| format!("missing the value of --{}", name))?;
+                    parsed.options.insert(name.to_string(), Some(value));
+                }
+                Some(name) => {
+                    parsed.options.insert(name.to_string(), None);
+                }
+                None => parsed.positional.push(arg),
+            }
+        }
+        Ok(parsed)
+    }
+
+    /// Returns the positional argument at `idx`, described by `name` in the
+    /// error.
+    fn positional(&self, idx: usize, name: &str) -> Result<&str> {
+        self.positional
+            .get(idx)
+            .map(|arg| arg.as_str())
+            .ok_or_else(|| format!("missing the {} argument", name))
+    }
+
+    /// Returns the value of the option `name`, if it is given.
+    fn option<T: FromStr>(&self, name: &str) -> Result<Option<T>> {
+        match self.options.get(name) {
+            Some(Some(value)) => value
+                .parse()
+                .map(Some)
+                .map_err(|_| format!("invalid value {:?} of --{}", value, name)),
+            _ => Ok(None),
+        }
+    }
+
+    /// Returns whether the flag `name` is given.
+    fn flag(&self, name: &str) -> bool {
+        self.options.contains_key(name)
+    }
+
+    // Reject the arguments that the command does not take.
+    fn check(&self, nb_positional: usize, options: &[&str]) -> Result<()> {
+        if let Some(arg) = self.positional.get(nb_positional) {
+            return Err(format!("unexpected argument {:?}", arg));
+        }
+        match self
+            .options
+            .keys()
+            .find(|name| !options.contains(&name.as_str()))
+        {
+            Some(name) => Err(format!("unknown option --{}", name)),
+            None => Ok(()),
+        }
+    }
+}
+
+/// Open the pcap file at `path`.
+pub(crate) fn open_pcap(path: &str) -> Result<PcapRxQueue<BufReader<File>>> {
+    let file = File::open(path).map_err(|err| format!("{}: {}", path, err))?;
+    PcapRxQueue::new(BufReader::new(file)).map_err(|err| format!("{}: {}", path, err))
+}
+
+/// Read all the frames of the pcap file at `path`.
+pub(crate) fn read_frames(path: &str) -> Result<Vec<Vec<u8>>> {
+    let mut rxq = open_pcap(path)?;
+    let mut frames = Vec::new();
+    while rxq
+        .rx(&mut |frame| frames.push(frame.to_vec()))
+        .map_err(|err| format!("{}: {}", path, err))?
+        > 0
+    {}
+    Ok(frames)
+}
+
+fn run() -> Result<()> {
+    let mut args = std::env::args().skip(1);
+    let command = args.next().ok_or_else(|| USAGE.to_string())?;
+    match command.as_str() {
+        "dissect" => {
+            let args = Args::parse(args, &[])?;
+            args.check(1, &["hex"])?;
+            dissect::run(&args)
+        }
+        "gen" => {
+            let args = Args::parse(args, &["count"])?;
+            args.check(2, &["count"])?;
+            gen::run(&args)
+        }
+        "replay" => {
+            let args = Args::parse(args, &["loops", "dpdk-config"])?;
+            args.check(2, &["loops", "dpdk-config"])?;
+            replay::run(&args)
+        }
+        "ports" => {
+            let args = Args::parse(args, &["dpdk-config"])?;
+            args.check(0, &["dpdk-config"])?;
+            ports::run(&args)
+        }
+        "bench" => {
+            let args = Args::parse(args, &["secs"])?;
+            args.check(1, &["secs"])?;
+            bench::run(&args)
+        }
+        "help" | "-h" | "--help" => {
+            println!("{}", USAGE);
+            Ok(())
+        }
+        _ => Err(format!("unknown command {:?}\n\n{}", command, USAGE)),
+    }
+}
+
+fn main() -> ExitCode {
+    match run() {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(err) => {
+            eprintln!("{}", err);
+            ExitCode::FAILURE
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn args(s: &str, valued: &[&str]) -> Result<Args> {
+        Args::parse(s.split_whitespace().map(String::from), valued)
+    }
+
+    #[test]
+    fn parse_args() {
+        let parsed = args("in.pcap --loops 3 dpdk:0 --hex", &["loops"]).unwrap();
+        assert_eq!(parsed.positional(0, "pcap").unwrap(), "in.pcap");
+        assert_eq!(parsed.positional(1, "backend").unwrap(), "dpdk:0");
+        assert!(parsed.positional(2, "other").is_err());
+        assert_eq!(parsed.option::<u32>("loops").unwrap(), Some(3));
+        assert_eq!(parsed.option::<u32>("count").unwrap(), None);
+        assert!(parsed.flag("hex"));
+        assert!(parsed.check(2, &["loops", "hex"]).is_ok());
+        assert!(parsed.check(1, &["loops", "hex"]).is_err());
+        assert!(parsed.check(2, &["loops"]).is_err());
+
+        assert!(args("--loops", &["loops"]).is_err());
+        let parsed = args("--loops x", &["loops"]).unwrap();
+        assert!(parsed.option::<u32>("loops").is_err());
+    }
+}
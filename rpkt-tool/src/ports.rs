@@ -0,0 +1,76 @@
+use crate::{Args, Result};
+
+/// Initialize the DPDK service, from the file of `--dpdk-config` if it is
+/// given, and print the effective configuration.
+#[cfg(feature = "dpdk")]
+pub fn init_dpdk(args: &Args) -> Result<()> {
+    use rpkt_dpdk::{DpdkOption, DpdkService};
+
+    match args.option::<String>("dpdk-config")? {
+        Some(path) => {
+            let config = DpdkService::from_config(&path).map_err(|err| err.to_string())?;
+            eprintln!("# the effective configuration of {}\n{}", path, config);
+            Ok(())
+        }
+        None => DpdkOption::new()
+            .init()
+            .map_err(|err| format!("fail to init dpdk: {}", err)),
+    }
+}
+
+#[cfg(not(feature = "dpdk"))]
+pub fn init_dpdk(_args: &Args) -> Result<()> {
+    Err("the dpdk ports require the `dpdk` feature".to_string())
+}
+
+#[cfg(feature = "dpdk")]
+pub fn run(args: &Args) -> Result<()> {
+    use rpkt::ether::MacAddr;
+    use rpkt_dpdk::service;
+
+    init_dpdk(args)?;
+    let dpdk_err = |err: rpkt_dpdk::error::Error| err.to_string();
+
+    let nb_ports = service().port_num().map_err(dpdk_err)?;
+    println!("{} ports", nb_ports);
+    for port_id in 0..nb_ports {
+        let info = service().port_info(port_id).map_err(dpdk_err)?;
+        println!(
+            "port {}: driver {}, mac {}, socket {}, started {}",
+            info.port_id,
+            info.driver_name,
+            MacAddr(info.eth_addr),
+            info.socket_id,
+            info.started
+        );
+        println!(
+            "  queues rx {} tx {}, mtu [{}, {}], speeds {}",
+            info.max_rx_queues(),
+            info.max_tx_queues(),
+            info.min_mtu(),
+            info.max_mtu(),
+            info.speed_capa()
+        );
+        let (rx, tx) = (info.rx_offload_capa(), info.tx_offload_capa());
+        println!(
+            "  rx offloads ipv4_cksum {} udp_cksum {} tcp_cksum {} rss_hash {}",
+            rx.ipv4_cksum(),
+            rx.udp_cksum(),
+            rx.tcp_cksum(),
+            rx.rss_hash()
+        );
+        println!(
+            "  tx offloads ipv4_cksum {} udp_cksum {} tcp_cksum {}",
+            tx.ipv4_cksum(),
+            tx.udp_cksum(),
+            tx.tcp_cksum()
+        );
+    }
+
+    service().service_close().map_err(dpdk_err)
+}
+
+#[cfg(not(feature = "dpdk"))]
+pub fn run(args: &Args) -> Result<()> {
+    init_dpdk(args)
+}
@@ -0,0 +1,50 @@
+use std::time::Instant;
+
+use rpkt_io::{BackendConf, BURST_SIZE};
+
+use crate::{read_frames, Args, Result};
+
+pub fn run(args: &Args) -> Result<()> {
+    let path = args.positional(0, "pcap")?;
+    let backend: BackendConf = args.positional(1, "backend")?.parse()?;
+    let loops: u64 = args.option("loops")?.unwrap_or(1);
+
+    if let BackendConf::Dpdk { .. } = backend {
+        crate::ports::init_dpdk(args)?;
+    }
+
+    let frames = read_frames(path)?;
+    let refs: Vec<&[u8]> = frames.iter().map(|frame| &frame[..]).collect();
+    let bytes: usize = frames.iter().map(|frame| frame.len()).sum();
+
+    let mut port = backend
+        .open()
+        .map_err(|err| format!("{}: {}", backend, err))?;
+    let mut txq = port
+        .tx_queue(0)
+        .map_err(|err| format!("{}: {}", backend, err))?;
+
+    let start = Instant::now();
+    for _ in 0..loops {
+        for burst in refs.chunks(BURST_SIZE) {
+            // Retry until the queue takes the whole burst.
+            let mut sent = 0;
+            while sent < burst.len() {
+                sent += txq
+                    .tx(&burst[sent..])
+                    .map_err(|err| format!("{}: {}", backend, err))?;
+            }
+        }
+    }
+    txq.flush().map_err(|err| format!("{}: {}", backend, err))?;
+    let elapsed = start.elapsed().as_secs_f64();
+
+    println!(
+        "sent {} frames, {} bytes to {} in {:.3}s",
+        frames.len() as u64 * loops,
+        bytes as u64 * loops,
+        backend,
+        elapsed
+    );
+    Ok(())
+}
@@ -0,0 +1,640 @@
+//! Access control lists for building stateless firewalls.
+//!
+//! An [`Acl`] is a set of [`AclRule`]s, each matching a source prefix, a
+//! destination prefix, an optional IP protocol and ranges of source and
+//! destination ports. A packet is classified by its [`FiveTuple`], and gets
+//! the [`AclAction`] of the matching rule with the highest priority, or the
+//! default action of the list if no rule matches.
+//!
+//! The rules are grouped by the lengths of their prefixes and whether they
+//! match a protocol (tuple space search). Each group is a hash table keyed by
+//! the masked addresses and the protocol, so a lookup costs one hash probe per
+//! group instead of a scan over all the rules. The groups are visited in the
+//! order of their highest priority, and the search stops once no remaining
+//! group can beat the best match.
+//!
+//! # Examples
+//! ```
+//! use std::net::Ipv4Addr;
+//!
+//! use rpkt::acl::{Acl, AclAction, AclRule, IpPrefix};
+//! use rpkt::flow::FiveTuple;
+//! use rpkt::ipv4::IpProtocol;
+//!
+//! let mut acl = Acl::new(AclAction::Drop);
+//! // Allow DNS to the resolver, and reject the rest of the traffic to it.
+//! let resolver = IpPrefix::from_v4(Ipv4Addr::new(10, 0, 0, 53), 32);
+//! acl.insert(
+//!     AclRule::new(10, AclAction::Accept)
+//!         .with_dst(resolver)
+//!         .with_protocol(IpProtocol::UDP)
+//!         .with_dst_ports(53..=53),
+//! );
+//! acl.insert(AclRule::new(1, AclAction::Reject).with_dst(resolver));
+//!
+//! let mut packet = [0; 28];
+//! packet[0] = 0x45;
+//! packet[9] = 17;
+//! packet[12..16].copy_from_slice(&[192, 168, 0, 1]);
+//! packet[16..20].copy_from_slice(&[10, 0, 0, 53]);
+//! packet[20..24].copy_from_slice(&[0x30, 0x39, 0, 53]);
+//! let tuple = FiveTuple::from_ip(&packet).unwrap();
+//! assert_eq!(acl.verdict(&tuple), AclAction::Accept);
+//!
+//! packet[23] = 54;
+//! let tuple = FiveTuple::from_ip(&packet).unwrap();
+//! assert_eq!(acl.verdict(&tuple), AclAction::Reject);
+//!
+//! packet[19] = 54;
+//! let tuple = FiveTuple::from_ip(&packet).unwrap();
+//! assert_eq!(acl.verdict(&tuple), AclAction::Drop);
+//! ```
+
+use std::cmp::Reverse;
+use std::collections::HashMap;
+use std::fmt;
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr};
+use std::ops::RangeInclusive;
+
+use crate::flow::FiveTuple;
+use crate::ipv4::IpProtocol;
+
+/// The length of the IPv4-mapped IPv6 prefix, in bits.
+const IPV4_MAPPED_LEN: u8 = 96;
+
+/// The action applied to the packets matching a rule.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum AclAction {
+    /// Let the packet through.
+    Accept,
+    /// Silently drop the packet.
+    Drop,
+    /// Drop the packet and notify the sender, e.g. with a TCP reset or an ICMP
+    /// error. Sending the notification is left to the caller.
+    Reject,
+}
+
+/// An IPv4 or IPv6 prefix.
+///
+/// IPv4 prefixes are stored as IPv4-mapped IPv6 prefixes, like the addresses of
+/// [`FiveTuple`], so an IPv4 prefix never matches an IPv6 address. The
+/// [`IpPrefix::ANY`] prefix matches the addresses of both families.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct IpPrefix {
+    addr: u128,
+    len: u8,
+}
+
+impl IpPrefix {
+    /// The prefix that matches any IPv4 or IPv6 address.
+    pub const ANY: IpPrefix = IpPrefix { addr: 0, len: 0 };
+
+    /// Create a prefix from an address and a prefix length.
+    ///
+    /// # Panics
+    ///
+    /// This function panics if `prefix_len` exceeds the number of bits of the
+    /// address.
+    pub fn new(addr: IpAddr, prefix_len: u8) -> Self {
+        match addr {
+            IpAddr::V4(addr) => Self::from_v4(addr, prefix_len),
+            IpAddr::V6(addr) => Self::from_v6(addr, prefix_len),
+        }
+    }
+
+    /// Create an IPv4 prefix, the bits of `addr` beyond `prefix_len` are ignored.
+    ///
+    /// # Panics
+    ///
+    /// This function panics if `prefix_len` exceeds 32.
+    pub fn from_v4(addr: Ipv4Addr, prefix_len: u8) -> Self {
+        assert!(prefix_len <= 32, "invalid prefix length: {}", prefix_len);
+        let len = IPV4_MAPPED_LEN + prefix_len;
+        Self {
+            addr: u128::from(addr.to_ipv6_mapped()) & mask(len),
+            len,
+        }
+    }
+
+    /// Create an IPv6 prefix, the bits of `addr` beyond `prefix_len` are ignored.
+    ///
+    /// # Panics
+    ///
+    /// This function panics if `prefix_len` exceeds 128.
+    pub fn from_v6(addr: Ipv6Addr, prefix_len: u8) -> Self {
+        assert!(prefix_len <= 128, "invalid prefix length: {}", prefix_len);
+        Self {
+            addr: u128::from(addr) & mask(prefix_len),
+            len: prefix_len,
+        }
+    }
+
+    /// Returns whether the prefix is an IPv4 prefix.
+    #[inline]
+    pub fn is_ipv4(&self) -> bool {
+        self.len >= IPV4_MAPPED_LEN && self.addr >> 32 == 0xffff
+    }
+
+    /// Returns the address of the prefix, with the bits beyond the prefix length
+    /// cleared.
+    pub fn addr(&self) -> IpAddr {
+        if self.is_ipv4() {
+            IpAddr::V4(Ipv4Addr::from(self.addr as u32))
+        } else {
+            IpAddr::V6(Ipv6Addr::from(self.addr))
+        }
+    }
+
+    /// Returns the prefix length, counted in the bits of the address family.
+    pub fn prefix_len(&self) -> u8 {
+        if self.is_ipv4() {
+            self.len - IPV4_MAPPED_LEN
+        } else {
+            self.len
+        }
+    }
+
+    /// Returns whether the prefix contains `addr`, an IPv6 or IPv4-mapped
+    /// address as stored in [`FiveTuple`].
+    #[inline]
+    pub fn contains(&self, addr: &[u8; 16]) -> bool {
+        u128::from_be_bytes(*addr) & mask(self.len) == self.addr
+    }
+}
+
+impl fmt::Display for IpPrefix {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}/{}", self.addr(), self.prefix_len())
+    }
+}
+
+/// A rule of an [`Acl`].
+///
+/// A rule created by [`AclRule::new`] matches every packet, and is narrowed down
+/// by the `with_*` methods.
+///
+/// The ports of a [`FiveTuple`] are zero when the protocol has no ports or when
+/// the packet is a fragment, so such packets only match the port ranges that
+/// contain zero.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AclRule {
+    /// The priority of the rule. When several rules match a packet, the one with
+    /// the highest priority wins, and among equal priorities the one inserted
+    /// first wins.
+    pub priority: u32,
+    /// The source prefix.
+    pub src: IpPrefix,
+    /// The destination prefix.
+    pub dst: IpPrefix,
+    /// The IP protocol, or `None` to match any protocol.
+    pub protocol: Option<IpProtocol>,
+    /// The range of source ports.
+    pub src_ports: RangeInclusive<u16>,
+    /// The range of destination ports.
+    pub dst_ports: RangeInclusive<u16>,
+    /// The action applied to the matching packets.
+    pub action: AclAction,
+}
+
+impl AclRule {
+    /// Create a rule that matches every packet.
+    pub fn new(priority: u32, action: AclAction) -> Self {
+        Self {
+            priority,
+            src: IpPrefix::ANY,
+            dst: IpPrefix::ANY,
+            protocol: None,
+            src_ports: 0..=u16::MAX,
+            dst_ports: 0..=u16::MAX,
+            action,
+        }
+    }
+
+    /// Match the packets whose source address is in `prefix`.
+    pub fn with_src(mut self, prefix: IpPrefix) -> Self {
+        self.src = prefix;
+        self
+    }
+
+    /// Match the packets whose destination address is in `prefix`.
+    pub fn with_dst(mut self, prefix: IpPrefix) -> Self {
+        self.dst = prefix;
+        self
+    }
+
+    /// Match the packets of `protocol`.
+    pub fn with_protocol(mut self, protocol: IpProtocol) -> Self {
+        self.protocol = Some(protocol);
+        self
+    }
+
+    /// Match the packets whose source port is in `ports`.
+    pub fn with_src_ports(mut self, ports: RangeInclusive<u16>) -> Self {
+        self.src_ports = ports;
+        self
+    }
+
+    /// Match the packets whose destination port is in `ports`.
+    pub fn with_dst_ports(mut self, ports: RangeInclusive<u16>) -> Self {
+        self.dst_ports = ports;
+        self
+    }
+
+    /// Returns whether the rule matches `tuple`.
+    pub fn matches(&self, tuple: &FiveTuple) -> bool {
+        self.src.contains(&tuple.src_addr)
+            && self.dst.contains(&tuple.dst_addr)
+            && (self.protocol.is_none() || self.protocol == Some(tuple.protocol.into()))
+            && self.ports_match(tuple)
+    }
+
+    #[inline]
+    fn ports_match(&self, tuple: &FiveTuple) -> bool {
+        self.src_ports.contains(&tuple.src_port) && self.dst_ports.contains(&tuple.dst_port)
+    }
+
+    // The order in which the matching rules win, the smallest first.
+    #[inline]
+    fn rank(&self, id: usize) -> (Reverse<u32>, usize) {
+        (Reverse(self.priority), id)
+    }
+}
+
+// The masked addresses and the protocol of a rule or a packet.
+type TupleKey = (u128, u128, u8);
+
+// The rules sharing the same prefix lengths and protocol wildcard.
+struct TupleTable {
+    src_len: u8,
+    dst_len: u8,
+    exact_protocol: bool,
+    // The rules of each key, in the order of their ranks.
+    buckets: HashMap<TupleKey, Vec<usize>>,
+    max_priority: u32,
+}
+
+impl TupleTable {
+    fn key(&self, src: u128, dst: u128, protocol: u8) -> TupleKey {
+        (
+            src & mask(self.src_len),
+            dst & mask(self.dst_len),
+            if self.exact_protocol { protocol } else { 0 },
+        )
+    }
+}
+
+/// An access control list that classifies packets by their 5-tuples.
+///
+/// See the [module documentation](self) for how the rules are matched.
+pub struct Acl {
+    rules: Vec<Option<AclRule>>,
+    len: usize,
+    // Sorted by the highest priority of the tables.
+    tables: Vec<TupleTable>,
+    default_action: AclAction,
+}
+
+impl Acl {
+    /// Create an empty list, which applies `default_action` to every packet.
+    pub fn new(default_action: AclAction) -> Self {
+        Self {
+            rules: Vec::new(),
+            len: 0,
+            tables: Vec::new(),
+            default_action,
+        }
+    }
+
+    /// Returns the action applied to the packets that match no rule.
+    #[inline]
+    pub fn default_action(&self) -> AclAction {
+        self.default_action
+    }
+
+    /// Set the action applied to the packets that match no rule.
+    #[inline]
+    pub fn set_default_action(&mut self, action: AclAction) {
+        self.default_action = action;
+    }
+
+    /// Returns the number of rules.
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    /// Returns whether the list has no rules.
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Returns the rule identified by `id`.
+    #[inline]
+    pub fn get(&self, id: usize) -> Option<&AclRule> {
+        self.rules.get(id).and_then(|rule| rule.as_ref())
+    }
+
+    /// Iterate over the identifiers and the rules, in the order of insertion.
+    pub fn iter(&self) -> impl Iterator<Item = (usize, &AclRule)> {
+        self.rules
+            .iter()
+            .enumerate()
+            .filter_map(|(id, rule)| rule.as_ref().map(|rule| (id, rule)))
+    }
+
+    /// Add a rule, and returns the identifier of the rule.
+    ///
+    /// The identifiers are never reused, so they also record the order of
+    /// insertion.
+    pub fn insert(&mut self, rule: AclRule) -> usize {
+        let id = self.rules.len();
+        let (src_len, dst_len) = (rule.src.len, rule.dst.len);
+        let exact_protocol = rule.protocol.is_some();
+
+        let pos = self.tables.iter().position(|table| {
+            (table.src_len, table.dst_len, table.exact_protocol)
+                == (src_len, dst_len, exact_protocol)
+        });
+        let table = match pos {
+            Some(pos) => &mut self.tables[pos],
+            None => {
+                self.tables.push(TupleTable {
+                    src_len,
+                    dst_len,
+                    exact_protocol,
+                    buckets: HashMap::new(),
+                    max_priority: 0,
+                });
+                self.tables.last_mut().unwrap()
+            }
+        };
+
+        let key = table.key(
+            rule.src.addr,
+            rule.dst.addr,
+            rule.protocol.map_or(0, u8::from),
+        );
+        let rank = rule.rank(id);
+        let bucket = table.buckets.entry(key).or_default();
+        let rules = &self.rules;
+        let idx =
+            bucket.partition_point(|other| rules[*other].as_ref().unwrap().rank(*other) < rank);
+        bucket.insert(idx, id);
+        table.max_priority = table.max_priority.max(rule.priority);
+
+        self.rules.push(Some(rule));
+        self.len += 1;
+        self.sort_tables();
+        id
+    }
+
+    /// Remove the rule identified by `id`, and returns it.
+    pub fn remove(&mut self, id: usize) -> Option<AclRule> {
+        let rule = self.rules.get_mut(id)?.take()?;
+        self.len -= 1;
+
+        let pos = self
+            .tables
+            .iter()
+            .position(|table| {
+                (table.src_len, table.dst_len, table.exact_protocol)
+                    == (rule.src.len, rule.dst.len, rule.protocol.is_some())
+            })
+            .unwrap();
+        let table = &mut self.tables[pos];
+        let key = table.key(
+            rule.src.addr,
+            rule.dst.addr,
+            rule.protocol.map_or(0, u8::from),
+        );
+        let bucket = table.buckets.get_mut(&key).unwrap();
+        bucket.retain(|other| *other != id);
+        if bucket.is_empty() {
+            table.buckets.remove(&key);
+        }
+
+        if table.buckets.is_empty() {
+            self.tables.remove(pos);
+        } else {
+            let rules = &self.rules;
+            table.max_priority = table
+                .buckets
+                .values()
+                .flatten()
+                .map(|other| rules[*other].as_ref().unwrap().priority)
+                .max()
+                .unwrap();
+            self.sort_tables();
+        }
+        Some(rule)
+    }
+
+    /// Remove all the rules.
+    pub fn clear(&mut self) {
+        self.rules.clear();
+        self.tables.clear();
+        self.len = 0;
+    }
+
+    /// Returns the identifier of the rule that matches `tuple` with the highest
+    /// priority, or `None` if no rule matches.
+    pub fn classify(&self, tuple: &FiveTuple) -> Option<usize> {
+        let src = u128::from_be_bytes(tuple.src_addr);
+        let dst = u128::from_be_bytes(tuple.dst_addr);
+
+        let mut best: Option<(Reverse<u32>, usize)> = None;
+        for table in self.tables.iter() {
+            // A table whose rules all have lower priorities can not win, and
+            // neither can the following tables.
+            if let Some((Reverse(priority), _)) = best {
+                if table.max_priority < priority {
+                    break;
+                }
+            }
+            let bucket = match table.buckets.get(&table.key(src, dst, tuple.protocol)) {
+                Some(bucket) => bucket,
+                None => continue,
+            };
+            for id in bucket.iter().copied() {
+                let rule = self.rules[id].as_ref().unwrap();
+                let rank = rule.rank(id);
+                if best.is_some_and(|best| rank >= best) {
+                    break;
+                }
+                if rule.ports_match(tuple) {
+                    best = Some(rank);
+                    break;
+                }
+            }
+        }
+        best.map(|(_, id)| id)
+    }
+
+    /// Returns the action applied to the packet of `tuple`.
+    pub fn verdict(&self, tuple: &FiveTuple) -> AclAction {
+        match self.classify(tuple) {
+            Some(id) => self.rules[id].as_ref().unwrap().action,
+            None => self.default_action,
+        }
+    }
+
+    fn sort_tables(&mut self) {
+        self.tables.sort_by_key(|table| Reverse(table.max_priority));
+    }
+}
+
+// The mask of the first `len` bits of a 128-bit address.
+#[inline]
+fn mask(len: u8) -> u128 {
+    u128::MAX.checked_shl(128 - u32::from(len)).unwrap_or(0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fill::Filler;
+
+    fn tuple(src: IpAddr, dst: IpAddr, protocol: u8, src_port: u16, dst_port: u16) -> FiveTuple {
+        let octets = |addr: IpAddr| match addr {
+            IpAddr::V4(addr) => addr.to_ipv6_mapped().octets(),
+            IpAddr::V6(addr) => addr.octets(),
+        };
+        FiveTuple {
+            src_addr: octets(src),
+            dst_addr: octets(dst),
+            src_port,
+            dst_port,
+            protocol,
+        }
+    }
+
+    #[test]
+    fn prefixes() {
+        let prefix = IpPrefix::from_v4(Ipv4Addr::new(192, 168, 1, 77), 24);
+        assert!(prefix.is_ipv4());
+        assert_eq!(prefix.to_string(), "192.168.1.0/24");
+        assert!(prefix.contains(&Ipv4Addr::new(192, 168, 1, 1).to_ipv6_mapped().octets()));
+        assert!(!prefix.contains(&Ipv4Addr::new(192, 168, 2, 1).to_ipv6_mapped().octets()));
+
+        let prefix = IpPrefix::from_v6("2001:db8::1".parse().unwrap(), 32);
+        assert!(!prefix.is_ipv4());
+        assert_eq!(prefix.to_string(), "2001:db8::/32");
+
+        // An IPv4 wildcard does not match IPv6 addresses, unlike ANY.
+        let any_v4 = IpPrefix::from_v4(Ipv4Addr::UNSPECIFIED, 0);
+        let addr = "2001:db8::1".parse::<Ipv6Addr>().unwrap().octets();
+        assert!(!any_v4.contains(&addr));
+        assert!(IpPrefix::ANY.contains(&addr));
+        assert_eq!(any_v4.to_string(), "0.0.0.0/0");
+    }
+
+    #[test]
+    fn priorities() {
+        let server = IpAddr::V4(Ipv4Addr::new(10, 0, 0, 1));
+        let client = IpAddr::V4(Ipv4Addr::new(192, 168, 0, 1));
+        let tcp = u8::from(IpProtocol::TCP);
+
+        let mut acl = Acl::new(AclAction::Accept);
+        let deny_all =
+            acl.insert(AclRule::new(1, AclAction::Drop).with_dst(IpPrefix::new(server, 32)));
+        let allow_web = acl.insert(
+            AclRule::new(10, AclAction::Accept)
+                .with_dst(IpPrefix::new(server, 24))
+                .with_protocol(IpProtocol::TCP)
+                .with_dst_ports(80..=443),
+        );
+        // Inserted later with the same priority, so it never wins.
+        let shadowed = acl.insert(
+            AclRule::new(10, AclAction::Reject)
+                .with_dst(IpPrefix::new(server, 32))
+                .with_dst_ports(443..=443),
+        );
+        assert_eq!(acl.len(), 3);
+
+        assert_eq!(
+            acl.classify(&tuple(client, server, tcp, 40000, 443)),
+            Some(allow_web)
+        );
+        assert_eq!(
+            acl.classify(&tuple(client, server, tcp, 40000, 22)),
+            Some(deny_all)
+        );
+        assert_eq!(
+            acl.verdict(&tuple(server, client, tcp, 443, 40000)),
+            AclAction::Accept
+        );
+
+        assert_eq!(acl.remove(allow_web).unwrap().priority, 10);
+        assert_eq!(acl.remove(allow_web), None);
+        assert_eq!(
+            acl.classify(&tuple(client, server, tcp, 40000, 443)),
+            Some(shadowed)
+        );
+        assert_eq!(
+            acl.verdict(&tuple(client, server, tcp, 40000, 80)),
+            AclAction::Drop
+        );
+
+        acl.clear();
+        assert!(acl.is_empty());
+        assert_eq!(acl.classify(&tuple(client, server, tcp, 40000, 80)), None);
+    }
+
+    #[test]
+    fn same_as_linear_scan() {
+        let mut filler = Filler::new(0x2545f4914f6cdd1d);
+        let mut rnd = move || filler.next_u64();
+        // Draw the addresses from a small pool, so that the prefixes overlap.
+        let pool = [
+            IpAddr::V4(Ipv4Addr::new(10, 0, 0, 1)),
+            IpAddr::V4(Ipv4Addr::new(10, 0, 1, 1)),
+            IpAddr::V4(Ipv4Addr::new(10, 1, 0, 1)),
+            IpAddr::V6("2001:db8::1".parse().unwrap()),
+            IpAddr::V6("2001:db8:1::1".parse().unwrap()),
+        ];
+        let prefix = |rnd: &mut dyn FnMut() -> u64| {
+            let addr = pool[rnd() as usize % pool.len()];
+            match (rnd() % 5, addr) {
+                (0, _) => IpPrefix::ANY,
+                (_, IpAddr::V4(_)) => IpPrefix::new(addr, [0, 8, 16, 24, 32][rnd() as usize % 5]),
+                (_, IpAddr::V6(_)) => IpPrefix::new(addr, [0, 32, 48, 128][rnd() as usize % 4]),
+            }
+        };
+
+        let mut acl = Acl::new(AclAction::Drop);
+        for _ in 0..200 {
+            let mut rule = AclRule::new(rnd() as u32 % 8, AclAction::Accept)
+                .with_src(prefix(&mut rnd))
+                .with_dst(prefix(&mut rnd));
+            if rnd() % 2 == 0 {
+                rule = rule.with_protocol([IpProtocol::TCP, IpProtocol::UDP][rnd() as usize % 2]);
+            }
+            if rnd() % 2 == 0 {
+                let start = rnd() as u16 % 4;
+                rule = rule.with_dst_ports(start..=start + rnd() as u16 % 4);
+            }
+            acl.insert(rule);
+        }
+        for id in (0..200).step_by(3) {
+            acl.remove(id);
+        }
+
+        let linear = |acl: &Acl, tuple: &FiveTuple| {
+            acl.iter()
+                .filter(|(_, rule)| rule.matches(tuple))
+                .min_by_key(|(id, rule)| rule.rank(*id))
+                .map(|(id, _)| id)
+        };
+        for _ in 0..2000 {
+            let tuple = tuple(
+                pool[rnd() as usize % pool.len()],
+                pool[rnd() as usize % pool.len()],
+                [6, 17][rnd() as usize % 2],
+                rnd() as u16 % 8,
+                rnd() as u16 % 8,
+            );
+            assert_eq!(acl.classify(&tuple), linear(&acl, &tuple));
+        }
+    }
+}
@@ -0,0 +1,213 @@
+//! Gratuitous announcements of address ownership.
+//!
+//! When an address moves to another host, e.g. on a failover, the new owner
+//! announces it so that the neighbors update their caches right away instead
+//! of waiting for the stale entries to time out. IPv4 uses a gratuitous ARP
+//! (RFC 5227), and IPv6 uses an unsolicited Neighbor Advertisement with the
+//! override flag set (RFC 4861, section 7.2.6).
+//!
+//! Both builders fill the whole frame, from the Ethernet header down, into an
+//! empty buffer with enough headroom.
+//!
+//! # Examples
+//! ```
+//! use rpkt::announce::{prepend_gratuitous_arp, GRATUITOUS_ARP_LEN};
+//! use rpkt::ether::{EtherType, MacAddr};
+//! use rpkt::ipv4::Ipv4Addr;
+//! use rpkt::{Buf, CursorMut};
+//!
+//! let mut frame = [0; GRATUITOUS_ARP_LEN];
+//! let mut buf = CursorMut::new(&mut frame[..]);
+//! buf.advance(GRATUITOUS_ARP_LEN);
+//!
+//! let mac = MacAddr([0x02, 0, 0, 0, 0, 0x01]);
+//! let ethpkt = prepend_gratuitous_arp(buf, mac, Ipv4Addr::new(192, 168, 1, 1));
+//! assert_eq!(ethpkt.dest_mac(), MacAddr::BROADCAST);
+//! assert_eq!(ethpkt.ethertype(), EtherType::ARP);
+//! assert_eq!(ethpkt.buf().remaining(), GRATUITOUS_ARP_LEN);
+//! ```
+
+use crate::arp::{ArpPacket, Operation, ARP_HEADER_LEN, ARP_HEADER_TEMPLATE};
+use crate::ether::{
+    EtherPacket, EtherType, MacAddr, ETHER_HEADER_LEN, ETHER_HEADER_TEMPLATE, ETHER_MIN_FRAME_LEN,
+};
+use crate::icmpv6::ndp::NdpOptionWriter;
+use crate::icmpv6::Icmpv6Packet;
+use crate::ipv4::{IpProtocol, Ipv4Addr};
+use crate::ipv6::{Ipv6Addr, Ipv6Header, Ipv6Packet, IPV6_HEADER_LEN};
+use crate::PktMut;
+
+/// The length of a gratuitous ARP frame, which is padded to the minimum frame
+/// length.
+pub const GRATUITOUS_ARP_LEN: usize = ETHER_MIN_FRAME_LEN;
+
+// A neighbor advertisement carrying a target link-layer address option.
+const NEIGHBOR_ADV_LEN: usize = 32;
+
+/// The length of an unsolicited neighbor advertisement frame.
+pub const UNSOLICITED_NA_LEN: usize = ETHER_HEADER_LEN + IPV6_HEADER_LEN + NEIGHBOR_ADV_LEN;
+
+// NDP messages must be sent with the maximum hop limit.
+const NDP_HOP_LIMIT: u8 = 255;
+
+// The multicast MAC address of the all-nodes address ff02::1.
+const ALL_NODES_MAC: MacAddr = MacAddr([0x33, 0x33, 0, 0, 0, 0x01]);
+
+/// Prepend a gratuitous ARP that announces `ip` at `mac`.
+///
+/// The announcement is a broadcast ARP request whose sender and target
+/// protocol addresses are both `ip`, as recommended by RFC 5227. The frame is
+/// padded to [`GRATUITOUS_ARP_LEN`].
+///
+/// The `buf` must be empty and have at least [`GRATUITOUS_ARP_LEN`] bytes of
+/// headroom.
+pub fn prepend_gratuitous_arp<T: PktMut>(mut buf: T, mac: MacAddr, ip: Ipv4Addr) -> EtherPacket<T> {
+    assert!(buf.remaining() == 0 && buf.chunk_headroom() >= GRATUITOUS_ARP_LEN);
+
+    let pad_len = GRATUITOUS_ARP_LEN - ETHER_HEADER_LEN - ARP_HEADER_LEN;
+    buf.move_back(pad_len);
+    buf.chunk_mut()[..pad_len].fill(0);
+
+    let mut arppkt = ArpPacket::prepend_header(buf, &ARP_HEADER_TEMPLATE);
+    arppkt.set_operation(Operation::REQUEST);
+    arppkt.set_sender_hardware_addr(mac.as_bytes());
+    arppkt.set_sender_protocol_addr(&ip.0);
+    arppkt.set_target_hardware_addr(&[0; 6]);
+    arppkt.set_target_protocol_addr(&ip.0);
+
+    let mut ethpkt = EtherPacket::prepend_header(arppkt.release(), &ETHER_HEADER_TEMPLATE);
+    ethpkt.set_dest_mac(MacAddr::BROADCAST);
+    ethpkt.set_source_mac(mac);
+    ethpkt.set_ethertype(EtherType::ARP);
+    ethpkt
+}
+
+/// Prepend an unsolicited neighbor advertisement that announces `ip` at `mac`.
+///
+/// The advertisement is sent from `ip` to the all-nodes address, with the
+/// override flag set, the solicited flag cleared, and a target link-layer
+/// address option carrying `mac`. The router flag is set to `router`.
+///
+/// The `buf` must be empty and have at least [`UNSOLICITED_NA_LEN`] bytes of
+/// headroom.
+pub fn prepend_unsolicited_na<T: PktMut>(
+    mut buf: T,
+    mac: MacAddr,
+    ip: Ipv6Addr,
+    router: bool,
+) -> EtherPacket<T> {
+    assert!(buf.remaining() == 0 && buf.chunk_headroom() >= UNSOLICITED_NA_LEN);
+    let dst = Ipv6Addr::LINK_LOCAL_ALL_NODES;
+
+    let mut msg = Icmpv6Packet::prepend_msg_ndp_neighbor_adv(&mut buf, NEIGHBOR_ADV_LEN);
+    msg.set_r_flag(router);
+    msg.set_s_flag(false);
+    msg.set_o_flag(true);
+    msg.set_target_addr(&ip.0);
+    NdpOptionWriter::from_option_bytes_mut(msg.option_bytes_mut())
+        .dst_link_addr()
+        .set_link_addr(mac.as_bytes());
+
+    let mut icmppkt = Icmpv6Packet::parse_unchecked(buf);
+    icmppkt.adjust_checksum(ip, dst);
+
+    let mut header = Ipv6Header::new_unchecked([0; IPV6_HEADER_LEN]);
+    header.adjust_version();
+    header.set_next_header(IpProtocol::ICMPV6);
+    header.set_hop_limit(NDP_HOP_LIMIT);
+    header.set_source_ip(&ip);
+    header.set_dest_ip(&dst);
+    let ippkt = Ipv6Packet::prepend_header(icmppkt.release(), &header);
+
+    let mut ethpkt = EtherPacket::prepend_header(ippkt.release(), &ETHER_HEADER_TEMPLATE);
+    ethpkt.set_dest_mac(ALL_NODES_MAC);
+    ethpkt.set_source_mac(mac);
+    ethpkt.set_ethertype(EtherType::IPV6);
+    ethpkt
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::icmpv6::ndp::{NdpOption, NdpOptionIter};
+    use crate::icmpv6::{Icmpv6Msg, Icmpv6MsgType};
+    use crate::{Buf, Cursor, CursorMut};
+
+    const MAC: MacAddr = MacAddr([0x02, 0, 0, 0, 0, 0x01]);
+
+    #[test]
+    fn gratuitous_arp() {
+        let mut frame = [0xff; 100];
+        let mut buf = CursorMut::new(&mut frame[..GRATUITOUS_ARP_LEN + 8]);
+        buf.advance(GRATUITOUS_ARP_LEN + 8);
+        let ethpkt = prepend_gratuitous_arp(buf, MAC, Ipv4Addr::new(10, 0, 0, 1));
+        assert_eq!(ethpkt.buf().remaining(), GRATUITOUS_ARP_LEN);
+        assert!(frame[..8].iter().all(|b| *b == 0xff));
+
+        let frame = &frame[8..GRATUITOUS_ARP_LEN + 8];
+        assert!(frame[ETHER_HEADER_LEN + ARP_HEADER_LEN..]
+            .iter()
+            .all(|b| *b == 0));
+        let ethpkt = EtherPacket::parse(Cursor::new(frame)).unwrap();
+        assert_eq!(ethpkt.dest_mac(), MacAddr::BROADCAST);
+        assert_eq!(ethpkt.source_mac(), MAC);
+        assert_eq!(ethpkt.ethertype(), EtherType::ARP);
+
+        let arppkt = ArpPacket::parse(ethpkt.payload()).unwrap();
+        assert_eq!(arppkt.operation(), Operation::REQUEST);
+        assert_eq!(arppkt.sender_hardware_addr(), MAC.as_bytes());
+        assert_eq!(arppkt.sender_protocol_addr(), &[10, 0, 0, 1]);
+        assert_eq!(arppkt.target_hardware_addr(), &[0; 6]);
+        assert_eq!(arppkt.target_protocol_addr(), &[10, 0, 0, 1]);
+    }
+
+    #[test]
+    fn unsolicited_na() {
+        let ip = Ipv6Addr::new(0x2001, 0xdb8, 0, 0, 0, 0, 0, 1);
+        let mut frame = [0xff; UNSOLICITED_NA_LEN];
+        let mut buf = CursorMut::new(&mut frame[..]);
+        buf.advance(UNSOLICITED_NA_LEN);
+        prepend_unsolicited_na(buf, MAC, ip, false);
+
+        let ethpkt = EtherPacket::parse(Cursor::new(&frame[..])).unwrap();
+        assert_eq!(ethpkt.dest_mac(), ALL_NODES_MAC);
+        assert_eq!(ethpkt.source_mac(), MAC);
+        assert_eq!(ethpkt.ethertype(), EtherType::IPV6);
+
+        let ippkt = Ipv6Packet::parse(ethpkt.payload()).unwrap();
+        assert_eq!(ippkt.next_header(), IpProtocol::ICMPV6);
+        assert_eq!(ippkt.hop_limit(), 255);
+        assert_eq!(ippkt.source_ip(), ip);
+        assert_eq!(ippkt.dest_ip(), Ipv6Addr::LINK_LOCAL_ALL_NODES);
+        assert_eq!(usize::from(ippkt.payload_len()), NEIGHBOR_ADV_LEN);
+
+        let icmppkt = Icmpv6Packet::parse(ippkt.payload()).unwrap();
+        assert_eq!(icmppkt.msg_type(), Icmpv6MsgType::NDP_NEIGHBOR_ADV);
+        assert!(icmppkt.verify_checksum(ip, Ipv6Addr::LINK_LOCAL_ALL_NODES));
+        let msg = match icmppkt.msg() {
+            Icmpv6Msg::NdpNeighborAdv(msg) => msg,
+            _ => panic!("not a neighbor advertisement"),
+        };
+        assert!(!msg.r_flag() && !msg.s_flag() && msg.o_flag());
+        assert!(msg.check_reserved());
+        assert_eq!(msg.target_addr(), &ip.0);
+
+        let mut options = NdpOptionIter::from_option_bytes(msg.option_bytes());
+        match options.next() {
+            Some(NdpOption::DstLinkAddr(opt)) => assert_eq!(opt.link_addr(), MAC.as_bytes()),
+            _ => panic!("no target link-layer address option"),
+        }
+        assert!(options.next().is_none());
+    }
+
+    #[test]
+    fn router_flag() {
+        let mut frame = [0; UNSOLICITED_NA_LEN];
+        let mut buf = CursorMut::new(&mut frame[..]);
+        buf.advance(UNSOLICITED_NA_LEN);
+        prepend_unsolicited_na(buf, MAC, Ipv6Addr::LOOPBACK, true);
+
+        let offset = ETHER_HEADER_LEN + IPV6_HEADER_LEN;
+        assert_eq!(frame[offset + 4], 0xa0);
+    }
+}
@@ -33,11 +33,13 @@ packet_base! {
 impl<T: Buf> ArpPacket<T> {
     #[inline]
     pub fn parse(buf: T) -> Result<ArpPacket<T>, T> {
-        if buf.chunk().len() >= ARP_HEADER_LEN {
-            return Ok(ArpPacket { buf });
-        } else {
-            Err(buf)
-        }
+        traced_parse!("arp", buf, |_: &Self| ARP_HEADER_LEN, {
+            if buf.chunk().len() >= ARP_HEADER_LEN {
+                return Ok(ArpPacket { buf });
+            } else {
+                Err(buf)
+            }
+        })
     }
 
     #[inline]
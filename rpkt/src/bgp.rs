@@ -0,0 +1,767 @@
+//! Decoders of the BGP path attributes that carry traffic filters.
+//!
+//! DDoS mitigation relies on BGP to distribute the filters: a Flowspec NLRI
+//! (RFC 8955) describes the packets of an attack by their prefixes, ports and
+//! other fields, and the extended communities (RFC 4360) attached to it tell
+//! what to do with them, e.g. to drop them or to limit their rate. Large
+//! communities (RFC 8092) are commonly used to tag blackholed routes.
+//!
+//! [`PathAttrIter`] iterates over the path attributes of an UPDATE message,
+//! [`FlowspecNlriIter`] over the IPv4 Flowspec rules of an MP_REACH_NLRI or
+//! MP_UNREACH_NLRI attribute, and [`ExtCommunity`] and [`LargeCommunity`]
+//! decode the communities.
+//!
+//! # Examples
+//! ```
+//! use std::net::Ipv4Addr;
+//!
+//! use rpkt::acl::IpPrefix;
+//! use rpkt::bgp::{FlowspecComponent, FlowspecNlriIter, FlowspecType};
+//!
+//! // Traffic to 10.0.0.0/24 with destination port 53.
+//! let nlri = [0x08, 0x01, 0x18, 10, 0, 0, 0x05, 0x81, 53];
+//! let rule = FlowspecNlriIter::new(&nlri).next().unwrap();
+//! let mut components = rule.components();
+//! assert_eq!(
+//!     components.next(),
+//!     Some(FlowspecComponent::DstPrefix(IpPrefix::from_v4(Ipv4Addr::new(10, 0, 0, 0), 24)))
+//! );
+//! match components.next() {
+//!     Some(FlowspecComponent::Ops(FlowspecType::DST_PORT, ops)) => {
+//!         assert!(ops.matches(53) && !ops.matches(80));
+//!     }
+//!     _ => panic!("not a destination port component"),
+//! }
+//! assert!(components.next().is_none() && components.is_valid());
+//! ```
+
+use std::fmt;
+use std::net::Ipv4Addr;
+
+use byteorder::{ByteOrder, NetworkEndian};
+
+use crate::acl::IpPrefix;
+
+/// The TCP port of BGP.
+pub const BGP_PORT: u16 = 179;
+
+/// The subsequent address family of the Flowspec NLRI (RFC 8955).
+pub const SAFI_FLOWSPEC: u8 = 133;
+
+/// The subsequent address family of the Flowspec NLRI of a VPN.
+pub const SAFI_FLOWSPEC_VPN: u8 = 134;
+
+// The flags of a path attribute.
+const ATTR_FLAG_EXTENDED_LEN: u8 = 0x10;
+
+// A Flowspec NLRI length of 240 or more is encoded in 2 bytes.
+const NLRI_LEN_2BYTES: u8 = 0xf0;
+
+// The bits of a Flowspec operator byte.
+const OP_END_OF_LIST: u8 = 0x80;
+const OP_AND: u8 = 0x40;
+const OP_LEN_MASK: u8 = 0x30;
+const OP_LT: u8 = 0x04;
+const OP_GT: u8 = 0x02;
+const OP_EQ: u8 = 0x01;
+const OP_NOT: u8 = 0x02;
+const OP_MATCH: u8 = 0x01;
+
+enum_sim! {
+    /// The type code of a path attribute.
+    pub struct PathAttrType (u8) {
+        ORIGIN = 1,
+        AS_PATH = 2,
+        NEXT_HOP = 3,
+        COMMUNITIES = 8,
+        MP_REACH_NLRI = 14,
+        MP_UNREACH_NLRI = 15,
+        EXTENDED_COMMUNITIES = 16,
+        LARGE_COMMUNITY = 32,
+    }
+}
+
+/// A path attribute yielded by [`PathAttrIter`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PathAttr<'a> {
+    pub flags: u8,
+    pub attr_type: PathAttrType,
+    pub value: &'a [u8],
+}
+
+impl<'a> PathAttr<'a> {
+    /// Returns the address family, the subsequent address family and the
+    /// NLRI of an MP_REACH_NLRI or MP_UNREACH_NLRI attribute (RFC 4760).
+    ///
+    /// The next hop of an MP_REACH_NLRI attribute is skipped.
+    pub fn mp_nlri(&self) -> Option<(u16, u8, &'a [u8])> {
+        let value = self.value;
+        let nlri_start = match self.attr_type {
+            PathAttrType::MP_REACH_NLRI => {
+                // The next hop is followed by a reserved byte.
+                let nh_len = usize::from(*value.get(3)?);
+                4 + nh_len + 1
+            }
+            PathAttrType::MP_UNREACH_NLRI => 3,
+            _ => return None,
+        };
+        let nlri = value.get(nlri_start..)?;
+        Some((NetworkEndian::read_u16(&value[0..2]), value[2], nlri))
+    }
+}
+
+/// Iterates over the path attributes of an UPDATE message.
+///
+/// The iteration stops at the first truncated attribute, in which case
+/// [`PathAttrIter::is_valid`] returns `false`.
+#[derive(Debug, Clone)]
+pub struct PathAttrIter<'a> {
+    buf: &'a [u8],
+    valid: bool,
+}
+
+impl<'a> PathAttrIter<'a> {
+    /// Create an iterator over the path attributes in `buf`, without the
+    /// length field that precedes them.
+    #[inline]
+    pub fn new(buf: &'a [u8]) -> Self {
+        Self { buf, valid: true }
+    }
+
+    /// Returns whether no truncated attribute has been found.
+    #[inline]
+    pub fn is_valid(&self) -> bool {
+        self.valid
+    }
+}
+
+impl<'a> Iterator for PathAttrIter<'a> {
+    type Item = PathAttr<'a>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if !self.valid || self.buf.is_empty() {
+            return None;
+        }
+
+        match read_path_attr(self.buf) {
+            Some((attr, len)) => {
+                self.buf = &self.buf[len..];
+                Some(attr)
+            }
+            None => {
+                self.valid = false;
+                None
+            }
+        }
+    }
+}
+
+// Returns the attribute at the start of `buf` and its length.
+fn read_path_attr(buf: &[u8]) -> Option<(PathAttr<'_>, usize)> {
+    let flags = *buf.first()?;
+    let attr_type = PathAttrType::from(*buf.get(1)?);
+    let (len, header_len) = if flags & ATTR_FLAG_EXTENDED_LEN != 0 {
+        (NetworkEndian::read_u16(buf.get(2..4)?), 4)
+    } else {
+        (u16::from(*buf.get(2)?), 3)
+    };
+    let value = buf.get(header_len..header_len + usize::from(len))?;
+    let attr = PathAttr {
+        flags,
+        attr_type,
+        value,
+    };
+    Some((attr, header_len + value.len()))
+}
+
+enum_sim! {
+    /// The type of a Flowspec component.
+    pub struct FlowspecType (u8) {
+        DST_PREFIX = 1,
+        SRC_PREFIX = 2,
+        IP_PROTOCOL = 3,
+        /// Either the source or the destination port.
+        PORT = 4,
+        DST_PORT = 5,
+        SRC_PORT = 6,
+        ICMP_TYPE = 7,
+        ICMP_CODE = 8,
+        TCP_FLAGS = 9,
+        PACKET_LEN = 10,
+        DSCP = 11,
+        FRAGMENT = 12,
+    }
+}
+
+impl FlowspecType {
+    /// Returns whether the operators of the component are bitmask operators
+    /// instead of numeric operators.
+    #[inline]
+    pub fn is_bitmask(&self) -> bool {
+        *self == FlowspecType::TCP_FLAGS || *self == FlowspecType::FRAGMENT
+    }
+}
+
+/// Iterates over the rules of the NLRI of a Flowspec MP_REACH_NLRI or
+/// MP_UNREACH_NLRI attribute.
+///
+/// The iteration stops at the first truncated rule, in which case
+/// [`FlowspecNlriIter::is_valid`] returns `false`.
+#[derive(Debug, Clone)]
+pub struct FlowspecNlriIter<'a> {
+    buf: &'a [u8],
+    valid: bool,
+}
+
+impl<'a> FlowspecNlriIter<'a> {
+    #[inline]
+    pub fn new(buf: &'a [u8]) -> Self {
+        Self { buf, valid: true }
+    }
+
+    /// Returns whether no truncated rule has been found.
+    #[inline]
+    pub fn is_valid(&self) -> bool {
+        self.valid
+    }
+}
+
+impl<'a> Iterator for FlowspecNlriIter<'a> {
+    type Item = FlowspecRule<'a>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if !self.valid || self.buf.is_empty() {
+            return None;
+        }
+
+        let first = self.buf[0];
+        let (len, header_len) = if first >= NLRI_LEN_2BYTES {
+            match self.buf.get(1) {
+                Some(&second) => ((usize::from(first & 0x0f) << 8) | usize::from(second), 2),
+                // The missing byte fails the range check below.
+                None => (0, 2),
+            }
+        } else {
+            (usize::from(first), 1)
+        };
+        match self.buf.get(header_len..header_len + len) {
+            Some(components) => {
+                self.buf = &self.buf[header_len + len..];
+                Some(FlowspecRule { buf: components })
+            }
+            None => {
+                self.valid = false;
+                None
+            }
+        }
+    }
+}
+
+/// An IPv4 Flowspec rule, which matches the packets matching all of its
+/// components.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FlowspecRule<'a> {
+    buf: &'a [u8],
+}
+
+impl<'a> FlowspecRule<'a> {
+    /// Returns the encoded components, without the length of the NLRI.
+    #[inline]
+    pub fn as_bytes(&self) -> &'a [u8] {
+        self.buf
+    }
+
+    #[inline]
+    pub fn components(&self) -> FlowspecIter<'a> {
+        FlowspecIter {
+            buf: self.buf,
+            valid: true,
+            last_type: 0,
+        }
+    }
+
+    /// Returns whether all the components are well-formed and in the
+    /// ascending order of their types.
+    pub fn check_components(&self) -> bool {
+        let mut iter = self.components();
+        (&mut iter).for_each(drop);
+        iter.valid
+    }
+
+    /// Returns the first component of `comp_type`.
+    pub fn component(&self, comp_type: FlowspecType) -> Option<FlowspecComponent<'a>> {
+        self.components().find(|comp| comp.comp_type() == comp_type)
+    }
+}
+
+/// A component of a [`FlowspecRule`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FlowspecComponent<'a> {
+    DstPrefix(IpPrefix),
+    SrcPrefix(IpPrefix),
+    /// A component made of a list of operators, matching the field of its
+    /// type.
+    Ops(FlowspecType, FlowspecOps<'a>),
+}
+
+impl<'a> FlowspecComponent<'a> {
+    pub fn comp_type(&self) -> FlowspecType {
+        match self {
+            FlowspecComponent::DstPrefix(_) => FlowspecType::DST_PREFIX,
+            FlowspecComponent::SrcPrefix(_) => FlowspecType::SRC_PREFIX,
+            FlowspecComponent::Ops(comp_type, _) => *comp_type,
+        }
+    }
+}
+
+/// Iterates over the components of a [`FlowspecRule`].
+///
+/// The iteration stops at the first malformed component, at a component of
+/// an unknown type, or at a component that is not in the ascending order of
+/// the types, in which case [`FlowspecIter::is_valid`] returns `false`.
+#[derive(Debug, Clone)]
+pub struct FlowspecIter<'a> {
+    buf: &'a [u8],
+    valid: bool,
+    last_type: u8,
+}
+
+impl<'a> FlowspecIter<'a> {
+    /// Returns whether no malformed component has been found.
+    #[inline]
+    pub fn is_valid(&self) -> bool {
+        self.valid
+    }
+
+    fn read_component(&self) -> Option<(FlowspecComponent<'a>, usize)> {
+        let comp_type = FlowspecType::from(self.buf[0]);
+        if u8::from(comp_type) <= self.last_type {
+            return None;
+        }
+        match comp_type {
+            FlowspecType::DST_PREFIX | FlowspecType::SRC_PREFIX => {
+                let prefix_len = *self.buf.get(1)?;
+                if prefix_len > 32 {
+                    return None;
+                }
+                let addr_len = (usize::from(prefix_len) + 7) / 8;
+                let mut addr = [0; 4];
+                addr[..addr_len].copy_from_slice(self.buf.get(2..2 + addr_len)?);
+                let prefix = IpPrefix::from_v4(Ipv4Addr::from(addr), prefix_len);
+                let comp = if comp_type == FlowspecType::DST_PREFIX {
+                    FlowspecComponent::DstPrefix(prefix)
+                } else {
+                    FlowspecComponent::SrcPrefix(prefix)
+                };
+                Some((comp, 2 + addr_len))
+            }
+            _ if (FlowspecType::IP_PROTOCOL..=FlowspecType::FRAGMENT).contains(&comp_type) => {
+                // Walk the operators up to the one with the end-of-list bit.
+                let mut len = 1;
+                loop {
+                    let op = *self.buf.get(len)?;
+                    len += 1 + op_value_len(op);
+                    if op & OP_END_OF_LIST != 0 {
+                        break;
+                    }
+                }
+                let ops = FlowspecOps {
+                    buf: self.buf.get(1..len)?,
+                    bitmask: comp_type.is_bitmask(),
+                };
+                Some((FlowspecComponent::Ops(comp_type, ops), len))
+            }
+            _ => None,
+        }
+    }
+}
+
+impl<'a> Iterator for FlowspecIter<'a> {
+    type Item = FlowspecComponent<'a>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if !self.valid || self.buf.is_empty() {
+            return None;
+        }
+
+        match self.read_component() {
+            Some((comp, len)) => {
+                self.last_type = self.buf[0];
+                self.buf = &self.buf[len..];
+                Some(comp)
+            }
+            None => {
+                self.valid = false;
+                None
+            }
+        }
+    }
+}
+
+fn op_value_len(op: u8) -> usize {
+    1 << ((op & OP_LEN_MASK) >> 4)
+}
+
+/// The list of operators of a component, which is iterated as
+/// [`FlowspecOp`]s.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FlowspecOps<'a> {
+    buf: &'a [u8],
+    bitmask: bool,
+}
+
+impl<'a> FlowspecOps<'a> {
+    /// Returns whether a field of `value` matches the list.
+    ///
+    /// The operators with the AND bit are bound to the previous ones, and the
+    /// resulting groups are ORed, i.e. `a || b && c` matches if `a` matches or
+    /// both `b` and `c` match.
+    pub fn matches(&self, value: u64) -> bool {
+        let mut any = false;
+        let mut group = true;
+        for (i, op) in self.into_iter().enumerate() {
+            if i > 0 && !op.and {
+                any |= group;
+                group = true;
+            }
+            group &= op.matches(value);
+        }
+        any || group
+    }
+}
+
+impl<'a> IntoIterator for FlowspecOps<'a> {
+    type Item = FlowspecOp;
+    type IntoIter = FlowspecOpIter<'a>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        FlowspecOpIter {
+            buf: self.buf,
+            bitmask: self.bitmask,
+        }
+    }
+}
+
+impl<'a> IntoIterator for &FlowspecOps<'a> {
+    type Item = FlowspecOp;
+    type IntoIter = FlowspecOpIter<'a>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        (*self).into_iter()
+    }
+}
+
+/// Iterates over the operators of a [`FlowspecOps`].
+#[derive(Debug, Clone)]
+pub struct FlowspecOpIter<'a> {
+    buf: &'a [u8],
+    bitmask: bool,
+}
+
+impl<'a> Iterator for FlowspecOpIter<'a> {
+    type Item = FlowspecOp;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        // The list is checked by `FlowspecIter`.
+        let op = *self.buf.first()?;
+        let len = op_value_len(op);
+        let value = self.buf[1..1 + len]
+            .iter()
+            .fold(0, |acc, &b| (acc << 8) | u64::from(b));
+        self.buf = &self.buf[1 + len..];
+        Some(FlowspecOp {
+            and: op & OP_AND != 0,
+            bitmask: self.bitmask,
+            flags: op & 0x07,
+            value,
+        })
+    }
+}
+
+/// An operator of a component, comparing a field with its value.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FlowspecOp {
+    /// Whether the operator is ANDed with the previous one, instead of ORed.
+    pub and: bool,
+    /// Whether this is a bitmask operator instead of a numeric operator.
+    pub bitmask: bool,
+    /// The lowest 3 bits of the operator byte, the `lt`, `gt` and `eq` bits of
+    /// a numeric operator, or the `not` and `m` bits of a bitmask operator.
+    pub flags: u8,
+    pub value: u64,
+}
+
+impl FlowspecOp {
+    /// Returns whether a field of `value` matches the operator.
+    pub fn matches(&self, value: u64) -> bool {
+        if self.bitmask {
+            let matched = if self.flags & OP_MATCH != 0 {
+                value & self.value == self.value
+            } else {
+                value & self.value != 0
+            };
+            matched != (self.flags & OP_NOT != 0)
+        } else {
+            // All the bits cleared is false, and all of them set is true.
+            (self.flags & OP_LT != 0 && value < self.value)
+                || (self.flags & OP_GT != 0 && value > self.value)
+                || (self.flags & OP_EQ != 0 && value == self.value)
+                || self.flags & 0x07 == 0x07
+        }
+    }
+}
+
+/// An extended community (RFC 4360).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct ExtCommunity(pub [u8; 8]);
+
+impl ExtCommunity {
+    /// Returns the high-order type byte.
+    #[inline]
+    pub fn type_high(&self) -> u8 {
+        self.0[0]
+    }
+
+    #[inline]
+    pub fn subtype(&self) -> u8 {
+        self.0[1]
+    }
+
+    /// Returns whether the community may be advertised to other ASes.
+    #[inline]
+    pub fn is_transitive(&self) -> bool {
+        self.0[0] & 0x40 == 0
+    }
+
+    /// Decode the community as a Flowspec action (RFC 8955, section 7).
+    pub fn flowspec_action(&self) -> Option<FlowspecAction> {
+        let data = &self.0;
+        match (data[0], data[1]) {
+            (0x80, 0x06) => Some(FlowspecAction::TrafficRate {
+                asn: NetworkEndian::read_u16(&data[2..4]),
+                rate: NetworkEndian::read_f32(&data[4..8]),
+            }),
+            (0x80, 0x07) => Some(FlowspecAction::TrafficAction {
+                sample: data[7] & 0x02 != 0,
+                terminal: data[7] & 0x01 != 0,
+            }),
+            (0x80, 0x08) => Some(FlowspecAction::Redirect {
+                asn: NetworkEndian::read_u16(&data[2..4]),
+                value: NetworkEndian::read_u32(&data[4..8]),
+            }),
+            (0x80, 0x09) => Some(FlowspecAction::TrafficMarking {
+                dscp: data[7] & 0x3f,
+            }),
+            _ => None,
+        }
+    }
+}
+
+/// The action of a Flowspec rule, carried by an extended community.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum FlowspecAction {
+    /// Limit the traffic to `rate` bytes per second, 0 drops all of it.
+    TrafficRate { asn: u16, rate: f32 },
+    /// Sample the traffic, and stop evaluating the rules behind this one
+    /// unless `terminal` is cleared.
+    TrafficAction { sample: bool, terminal: bool },
+    /// Redirect the traffic to the VRF with the route target `asn:value`.
+    Redirect { asn: u16, value: u32 },
+    /// Rewrite the DSCP of the traffic.
+    TrafficMarking { dscp: u8 },
+}
+
+impl FlowspecAction {
+    /// Returns whether the action drops all the traffic.
+    pub fn is_discard(&self) -> bool {
+        matches!(self, FlowspecAction::TrafficRate { rate, .. } if *rate == 0.0)
+    }
+}
+
+/// Iterate over the communities of an EXTENDED_COMMUNITIES attribute.
+///
+/// A trailing partial community is ignored.
+pub fn ext_communities(value: &[u8]) -> impl Iterator<Item = ExtCommunity> + '_ {
+    value.chunks_exact(8).map(|data| {
+        let mut community = [0; 8];
+        community.copy_from_slice(data);
+        ExtCommunity(community)
+    })
+}
+
+/// A large community (RFC 8092).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct LargeCommunity {
+    /// The AS that defines the community.
+    pub global_admin: u32,
+    pub local_data1: u32,
+    pub local_data2: u32,
+}
+
+impl LargeCommunity {
+    /// Returns the community as 12 bytes.
+    pub fn to_bytes(&self) -> [u8; 12] {
+        let mut data = [0; 12];
+        NetworkEndian::write_u32(&mut data[0..4], self.global_admin);
+        NetworkEndian::write_u32(&mut data[4..8], self.local_data1);
+        NetworkEndian::write_u32(&mut data[8..12], self.local_data2);
+        data
+    }
+}
+
+impl fmt::Display for LargeCommunity {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{}:{}:{}",
+            self.global_admin, self.local_data1, self.local_data2
+        )
+    }
+}
+
+/// Iterate over the communities of a LARGE_COMMUNITY attribute.
+///
+/// A trailing partial community is ignored.
+pub fn large_communities(value: &[u8]) -> impl Iterator<Item = LargeCommunity> + '_ {
+    value.chunks_exact(12).map(|data| LargeCommunity {
+        global_admin: NetworkEndian::read_u32(&data[0..4]),
+        local_data1: NetworkEndian::read_u32(&data[4..8]),
+        local_data2: NetworkEndian::read_u32(&data[8..12]),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn flowspec_nlri() {
+        // Traffic from 192.0.2.0/25 with IP protocol 17, source port 123 or
+        // a port in [1024, 2047], and fragments.
+        let rule = [
+            0x02, 0x19, 192, 0, 2, 0x00, // source prefix
+            0x03, 0x81, 17, // ip protocol
+            0x06, 0x01, 123, 0x13, 0x04, 0x00, 0xd5, 0x07, 0xff, // source port
+            0x0c, 0x80, 0x01, // fragment, not a match-all
+        ];
+        let mut nlri = vec![rule.len() as u8];
+        nlri.extend_from_slice(&rule);
+        // A second rule with a 2-byte length.
+        nlri.extend_from_slice(&[0xf0, 0x03, 0x03, 0x81, 6]);
+
+        let mut iter = FlowspecNlriIter::new(&nlri);
+        let rule = iter.next().unwrap();
+        assert!(rule.check_components());
+        assert_eq!(
+            rule.component(FlowspecType::SRC_PREFIX),
+            Some(FlowspecComponent::SrcPrefix(IpPrefix::from_v4(
+                Ipv4Addr::new(192, 0, 2, 0),
+                25
+            )))
+        );
+        assert_eq!(rule.component(FlowspecType::DST_PREFIX), None);
+
+        let ports = match rule.component(FlowspecType::SRC_PORT) {
+            Some(FlowspecComponent::Ops(_, ops)) => ops,
+            _ => panic!("no source port component"),
+        };
+        let ops: Vec<_> = ports.into_iter().map(|op| (op.and, op.value)).collect();
+        assert_eq!(ops, [(false, 123), (false, 1024), (true, 2047)]);
+        assert!(ports.matches(123) && ports.matches(1024) && ports.matches(2047));
+        assert!(!ports.matches(124) && !ports.matches(2048));
+
+        match rule.component(FlowspecType::FRAGMENT) {
+            Some(FlowspecComponent::Ops(_, ops)) => {
+                assert!(ops.matches(0x01) && !ops.matches(0x02));
+            }
+            _ => panic!("no fragment component"),
+        }
+
+        let rule = iter.next().unwrap();
+        assert_eq!(rule.as_bytes(), &[0x03, 0x81, 6]);
+        assert!(iter.next().is_none() && iter.is_valid());
+
+        // A rule running past the NLRI.
+        let mut iter = FlowspecNlriIter::new(&nlri[..10]);
+        assert!(iter.next().is_none() && !iter.is_valid());
+    }
+
+    #[test]
+    fn flowspec_invalid() {
+        // Types out of order, an unknown type, a prefix over 32 bits and an
+        // operator list without the end-of-list bit.
+        let bad: [&[u8]; 4] = [
+            &[0x05, 0x81, 53, 0x03, 0x81, 17],
+            &[0x0d, 0x81, 0],
+            &[0x01, 0x21, 0, 0, 0, 0, 0],
+            &[0x03, 0x01, 17, 0x01, 6],
+        ];
+        for components in bad {
+            let mut nlri = vec![components.len() as u8];
+            nlri.extend_from_slice(components);
+            let rule = FlowspecNlriIter::new(&nlri).next().unwrap();
+            assert!(!rule.check_components());
+        }
+
+        // Bitmask operators on the TCP flags: SYN set and ACK not set.
+        let nlri = [0x05, 0x09, 0x01, 0x02, 0xc3, 0x10];
+        let rule = FlowspecNlriIter::new(&nlri).next().unwrap();
+        match rule.components().next() {
+            Some(FlowspecComponent::Ops(FlowspecType::TCP_FLAGS, ops)) => {
+                assert!(ops.matches(0x02));
+                assert!(!ops.matches(0x12));
+            }
+            _ => panic!("no tcp flags component"),
+        }
+    }
+
+    #[test]
+    fn path_attrs_and_communities() {
+        let mut attrs = vec![0x40, 0x01, 0x01, 0x00];
+        // MP_REACH_NLRI of IPv4 Flowspec, without a next hop.
+        attrs.extend_from_slice(&[0x90, 0x0e, 0x00, 0x09, 0x00, 0x01, 133, 0x00, 0x00]);
+        attrs.extend_from_slice(&[0x03, 0x03, 0x81, 17]);
+        // A discard action and a redirect.
+        attrs.extend_from_slice(&[0xc0, 0x10, 0x10]);
+        attrs.extend_from_slice(&[0x80, 0x06, 0xfd, 0xe8, 0, 0, 0, 0]);
+        attrs.extend_from_slice(&[0x80, 0x08, 0xfd, 0xe8, 0, 0, 0, 100]);
+        attrs.extend_from_slice(&[
+            0xc0, 0x20, 0x0c, 0, 0, 0xfd, 0xe8, 0, 0, 0x02, 0x9a, 0, 0, 0, 0,
+        ]);
+
+        let mut iter = PathAttrIter::new(&attrs);
+        assert_eq!(
+            iter.next().map(|attr| attr.attr_type),
+            Some(PathAttrType::ORIGIN)
+        );
+
+        let mp_reach = iter.next().unwrap();
+        let (afi, safi, nlri) = mp_reach.mp_nlri().unwrap();
+        assert_eq!((afi, safi), (1, SAFI_FLOWSPEC));
+        assert_eq!(FlowspecNlriIter::new(nlri).count(), 1);
+
+        let ext = iter.next().unwrap();
+        assert_eq!(ext.attr_type, PathAttrType::EXTENDED_COMMUNITIES);
+        let actions: Vec<_> = ext_communities(ext.value)
+            .map(|community| community.flowspec_action().unwrap())
+            .collect();
+        assert!(actions[0].is_discard());
+        assert_eq!(
+            actions[1],
+            FlowspecAction::Redirect {
+                asn: 65000,
+                value: 100
+            }
+        );
+
+        let large = iter.next().unwrap();
+        let community = large_communities(large.value).next().unwrap();
+        assert_eq!(community.to_string(), "65000:666:0");
+        assert_eq!(&community.to_bytes()[..], large.value);
+        assert!(iter.next().is_none() && iter.is_valid());
+
+        // A truncated attribute.
+        let mut iter = PathAttrIter::new(&attrs[..6]);
+        assert!(iter.nth(1).is_none() && !iter.is_valid());
+    }
+}
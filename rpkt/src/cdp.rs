@@ -0,0 +1,213 @@
+//! A parser of the Cisco Discovery Protocol.
+//!
+//! CDP serves the same purpose as LLDP on Cisco devices. A CDP frame is an
+//! IEEE 802.3 frame with an LLC/SNAP header, followed by a 4-byte CDP header
+//! and a list of TLVs with 2-byte types and 2-byte lengths that count the
+//! header of the TLV.
+//!
+//! # Examples
+//! ```
+//! use rpkt::cdp::{CdpPdu, CDP_SNAP_HEADER};
+//!
+//! let mut payload = CDP_SNAP_HEADER.to_vec();
+//! payload.extend_from_slice(&[0x02, 0xb4, 0x00, 0x00]);
+//! payload.extend_from_slice(&[0x00, 0x01, 0x00, 0x06, b's', b'w']);
+//! payload.extend_from_slice(&[0x00, 0x03, 0x00, 0x08, b'G', b'i', b'0', b'1']);
+//!
+//! let pdu = CdpPdu::parse(&payload).unwrap();
+//! assert_eq!(pdu.ttl(), 180);
+//! assert_eq!(pdu.device_id(), Some(&b"sw"[..]));
+//! assert_eq!(pdu.port_id(), Some(&b"Gi01"[..]));
+//! ```
+
+use byteorder::{ByteOrder, NetworkEndian};
+
+use crate::ether::MacAddr;
+use crate::tlv::{TlvFormat, TlvIter};
+
+/// The destination address of the CDP frames.
+pub const CDP_MULTICAST: MacAddr = MacAddr([0x01, 0x00, 0x0c, 0xcc, 0xcc, 0xcc]);
+
+/// The LLC/SNAP header in front of a CDP frame, with the Cisco OUI and the
+/// CDP protocol ID.
+pub const CDP_SNAP_HEADER: [u8; 8] = [0xaa, 0xaa, 0x03, 0x00, 0x00, 0x0c, 0x20, 0x00];
+
+/// The length of the CDP header.
+pub const CDP_HEADER_LEN: usize = 4;
+
+/// The layout of the CDP TLVs.
+pub const CDP_TLV_FORMAT: TlvFormat = TlvFormat {
+    type_width: 2,
+    len_width: 2,
+    len_includes_header: true,
+    len_unit: 1,
+    align: 1,
+    pad_type: None,
+    end_type: None,
+};
+
+enum_sim! {
+    /// The type of a CDP TLV.
+    pub struct CdpTlvType (u16) {
+        DEVICE_ID = 0x0001,
+        ADDRESSES = 0x0002,
+        PORT_ID = 0x0003,
+        CAPABILITIES = 0x0004,
+        SOFTWARE_VERSION = 0x0005,
+        PLATFORM = 0x0006,
+        NATIVE_VLAN = 0x000a,
+        DUPLEX = 0x000b,
+        MGMT_ADDRESSES = 0x0016,
+    }
+}
+
+/// A CDP frame, starting from the LLC/SNAP header.
+#[derive(Debug, Clone, Copy)]
+pub struct CdpPdu<'a> {
+    // The CDP header and the TLVs.
+    buf: &'a [u8],
+}
+
+impl<'a> CdpPdu<'a> {
+    /// Parse the payload of an 802.3 frame, without the padding of the frame.
+    ///
+    /// Returns `None` if the payload is not a CDP frame of version 1 or 2, or
+    /// if a TLV is malformed.
+    pub fn parse(buf: &'a [u8]) -> Option<Self> {
+        if buf.get(..CDP_SNAP_HEADER.len())? != CDP_SNAP_HEADER {
+            return None;
+        }
+        let buf = &buf[CDP_SNAP_HEADER.len()..];
+        if buf.len() < CDP_HEADER_LEN
+            || !matches!(buf[0], 1 | 2)
+            || !TlvIter::check_bytes(&buf[CDP_HEADER_LEN..], CDP_TLV_FORMAT)
+        {
+            return None;
+        }
+        Some(Self { buf })
+    }
+
+    #[inline]
+    pub fn version(&self) -> u8 {
+        self.buf[0]
+    }
+
+    /// Returns how long the information is valid in seconds.
+    #[inline]
+    pub fn ttl(&self) -> u8 {
+        self.buf[1]
+    }
+
+    #[inline]
+    pub fn checksum(&self) -> u16 {
+        NetworkEndian::read_u16(&self.buf[2..4])
+    }
+
+    #[inline]
+    pub fn tlvs(&self) -> TlvIter<'a> {
+        TlvIter::new(&self.buf[CDP_HEADER_LEN..], CDP_TLV_FORMAT)
+    }
+
+    /// Returns the value of the first TLV of `tlv_type`.
+    pub fn tlv(&self, tlv_type: CdpTlvType) -> Option<&'a [u8]> {
+        let tlv_type = u32::from(u16::from(tlv_type));
+        self.tlvs()
+            .find(|tlv| tlv.tlv_type == tlv_type)
+            .map(|tlv| tlv.value)
+    }
+
+    /// Returns the name of the device, the equivalent of the chassis ID of
+    /// LLDP.
+    #[inline]
+    pub fn device_id(&self) -> Option<&'a [u8]> {
+        self.tlv(CdpTlvType::DEVICE_ID)
+    }
+
+    /// Returns the name of the port sending the frame.
+    #[inline]
+    pub fn port_id(&self) -> Option<&'a [u8]> {
+        self.tlv(CdpTlvType::PORT_ID)
+    }
+
+    #[inline]
+    pub fn platform(&self) -> Option<&'a [u8]> {
+        self.tlv(CdpTlvType::PLATFORM)
+    }
+
+    #[inline]
+    pub fn software_version(&self) -> Option<&'a [u8]> {
+        self.tlv(CdpTlvType::SOFTWARE_VERSION)
+    }
+
+    /// Returns the capability bits, e.g. 0x01 for a router and 0x08 for a
+    /// switch.
+    pub fn capabilities(&self) -> Option<u32> {
+        let value = self.tlv(CdpTlvType::CAPABILITIES)?;
+        (value.len() >= 4).then(|| NetworkEndian::read_u32(value))
+    }
+
+    pub fn native_vlan(&self) -> Option<u16> {
+        let value = self.tlv(CdpTlvType::NATIVE_VLAN)?;
+        (value.len() >= 2).then(|| NetworkEndian::read_u16(value))
+    }
+
+    /// Returns the first IPv4 address of the addresses TLV.
+    pub fn ipv4_addr(&self) -> Option<[u8; 4]> {
+        let value = self.tlv(CdpTlvType::ADDRESSES)?;
+        let count = NetworkEndian::read_u32(value.get(0..4)?);
+        let mut rest = &value[4..];
+        for _ in 0..count {
+            // The protocol type, the length of the protocol and the protocol,
+            // followed by the length of the address and the address.
+            let proto_len = usize::from(*rest.get(1)?);
+            let proto = rest.get(2..2 + proto_len)?;
+            let addr_start = 2 + proto_len + 2;
+            let addr_len = usize::from(NetworkEndian::read_u16(
+                rest.get(addr_start - 2..addr_start)?,
+            ));
+            let addr = rest.get(addr_start..addr_start + addr_len)?;
+            // NLPID 0xcc is IPv4.
+            if rest[0] == 1 && proto == [0xcc] && addr.len() == 4 {
+                let mut bytes = [0; 4];
+                bytes.copy_from_slice(addr);
+                return Some(bytes);
+            }
+            rest = &rest[addr_start + addr_len..];
+        }
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn cdp_pdu() {
+        let mut payload = CDP_SNAP_HEADER.to_vec();
+        payload.extend_from_slice(&[0x02, 0xb4, 0x12, 0x34]);
+        payload.extend_from_slice(&[0x00, 0x01, 0x00, 0x07, b'r', b't', b'1']);
+        // An IPv4 address of 10.0.0.1.
+        payload.extend_from_slice(&[0x00, 0x02, 0x00, 0x11, 0x00, 0x00, 0x00, 0x01]);
+        payload.extend_from_slice(&[0x01, 0x01, 0xcc, 0x00, 0x04, 10, 0, 0, 1]);
+        payload.extend_from_slice(&[0x00, 0x04, 0x00, 0x08, 0x00, 0x00, 0x00, 0x01]);
+        payload.extend_from_slice(&[0x00, 0x0a, 0x00, 0x06, 0x00, 0x64]);
+
+        let pdu = CdpPdu::parse(&payload).unwrap();
+        assert_eq!((pdu.version(), pdu.ttl(), pdu.checksum()), (2, 180, 0x1234));
+        assert_eq!(pdu.device_id(), Some(&b"rt1"[..]));
+        assert_eq!(pdu.port_id(), None);
+        assert_eq!(pdu.ipv4_addr(), Some([10, 0, 0, 1]));
+        assert_eq!(pdu.capabilities(), Some(0x01));
+        assert_eq!(pdu.native_vlan(), Some(100));
+
+        // A truncated TLV, a wrong version and another SNAP protocol.
+        assert!(CdpPdu::parse(&payload[..payload.len() - 1]).is_none());
+        let mut bytes = payload.clone();
+        bytes[8] = 3;
+        assert!(CdpPdu::parse(&bytes).is_none());
+        bytes[8] = 2;
+        bytes[7] = 0x04;
+        assert!(CdpPdu::parse(&bytes).is_none());
+    }
+}
@@ -54,60 +54,185 @@ pub(crate) fn combine(checksums: &[u16]) -> u16 {
     propagate_carries(accum)
 }
 
-fn from_slice_with_tail_byte(
-    mut data: &[u8],
-    accum: &mut u32,
+/// An incremental RFC 1071 checksum accumulator.
+///
+/// The data can be fed segment by segment, e.g. from the chained segments of a
+/// multi-segment `Mbuf`. A segment may end at an odd offset, in which case the
+/// trailing byte is paired with the first byte of the next segment.
+///
+/// # Examples
+/// ```
+/// use rpkt::ChecksumAccumulator;
+///
+/// let mut accum = ChecksumAccumulator::new();
+/// accum.update(&[0x45, 0x00, 0x00]);
+/// accum.update(&[0x1c]);
+/// assert_eq!(accum.finish(), 0x451c);
+/// ```
+#[derive(Debug, Clone, Copy, Default)]
+pub struct ChecksumAccumulator {
+    accum: u64,
     tail_byte: Option<u8>,
-) -> Option<u8> {
-    *accum += tail_byte
-        .map(|byte| {
-            let byte_array = [byte, data[0]];
+}
+
+impl ChecksumAccumulator {
+    /// Create an empty accumulator.
+    #[inline]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Feed a segment of data into the accumulator.
+    pub fn update(&mut self, mut data: &[u8]) {
+        if data.is_empty() {
+            return;
+        }
+
+        if let Some(byte) = self.tail_byte.take() {
+            self.accum += u64::from(NetworkEndian::read_u16(&[byte, data[0]][..]));
             data = &data[1..];
-            NetworkEndian::read_u16(&byte_array[..]) as u32
-        })
-        .unwrap_or(0);
+        }
 
-    // For each 32-byte chunk...
-    const CHUNK_SIZE: usize = 32;
-    while data.len() >= CHUNK_SIZE {
-        let mut d = &data[..CHUNK_SIZE];
-        // ... take by 2 bytes and sum them.
-        while d.len() >= 2 {
-            *accum += NetworkEndian::read_u16(d) as u32;
-            d = &d[2..];
+        // For each 32-byte chunk...
+        const CHUNK_SIZE: usize = 32;
+        while data.len() >= CHUNK_SIZE {
+            let mut d = &data[..CHUNK_SIZE];
+            // ... take by 2 bytes and sum them.
+            while d.len() >= 2 {
+                self.accum += u64::from(NetworkEndian::read_u16(d));
+                d = &d[2..];
+            }
+
+            data = &data[CHUNK_SIZE..];
         }
 
-        data = &data[CHUNK_SIZE..];
+        // Sum the rest that does not fit the last 32-byte chunk,
+        // taking by 2 bytes.
+        while data.len() >= 2 {
+            self.accum += u64::from(NetworkEndian::read_u16(data));
+            data = &data[2..];
+        }
+
+        self.tail_byte = data.first().copied();
     }
 
-    // Sum the rest that does not fit the last 32-byte chunk,
-    // taking by 2 bytes.
-    while data.len() >= 2 {
-        *accum += NetworkEndian::read_u16(data) as u32;
-        data = &data[2..];
+    /// Feed the first `len` bytes of `buf` into the accumulator, walking through
+    /// all the chunks of the buffer.
+    ///
+    /// If `buf` contains less than `len` bytes, all the remaining bytes are used.
+    pub fn update_buf<T: Buf>(&mut self, buf: T, len: usize) {
+        let mut buf = buf.take(len);
+        while buf.has_remaining() {
+            let chunk = buf.chunk();
+            let chunk_len = chunk.len();
+            self.update(chunk);
+            buf.advance(chunk_len);
+        }
+    }
+
+    /// Add a checksum computed elsewhere, e.g. the checksum of a pseudo header.
+    ///
+    /// # Panics
+    ///
+    /// This function panics if an odd number of bytes has been fed into the
+    /// accumulator, since the checksum must start at an even offset.
+    #[inline]
+    pub fn add_checksum(&mut self, checksum: u16) {
+        assert!(self.tail_byte.is_none(), "checksum added at an odd offset");
+        self.accum += u64::from(checksum);
     }
 
-    data.first().map(|byte| *byte)
+    /// Returns the accumulated checksum, without the final complement.
+    pub fn finish(&self) -> u16 {
+        let mut accum = self.accum + self.tail_byte.map_or(0, |byte| u64::from(byte) << 8);
+        while accum >> 16 != 0 {
+            accum = (accum >> 16) + (accum & 0xffff);
+        }
+        accum as u16
+    }
 }
 
 // This function calculates checksum from a multi-segment memory buffer.
 pub(crate) fn from_buf<T: Buf>(buf: T, len: usize) -> u16 {
-    let mut buf = buf.take(len);
-    let mut tail_byte = None;
-    let mut accum = 0;
+    let mut accum = ChecksumAccumulator::new();
+    accum.update_buf(buf, len);
+    accum.finish()
+}
 
-    while buf.has_remaining() {
-        let chunk = buf.chunk();
-        let chunk_len = chunk.len();
+#[cfg(test)]
+mod tests {
+    use super::*;
 
-        tail_byte = from_slice_with_tail_byte(chunk, &mut accum, tail_byte);
+    // A buffer made up of several chunks, resembling a multi-segment mbuf.
+    struct Chunks<'a> {
+        chunks: Vec<&'a [u8]>,
+    }
 
-        buf.advance(chunk_len);
+    impl<'a> Buf for Chunks<'a> {
+        fn remaining(&self) -> usize {
+            self.chunks.iter().map(|chunk| chunk.len()).sum()
+        }
+
+        fn chunk(&self) -> &[u8] {
+            self.chunks.first().copied().unwrap_or(&[])
+        }
+
+        fn advance(&mut self, mut cnt: usize) {
+            // Drop the empty chunks, so that a non-empty chunk is always exposed.
+            self.chunks.retain(|chunk| !chunk.is_empty());
+            while cnt > 0 {
+                let first = self.chunks[0];
+                if cnt < first.len() {
+                    self.chunks[0] = &first[cnt..];
+                    break;
+                }
+                cnt -= first.len();
+                self.chunks.remove(0);
+            }
+        }
     }
 
-    tail_byte.map(|byte| {
-        accum += (byte as u32) << 8;
-    });
+    #[test]
+    fn accumulator_multi_chunk() {
+        let data: Vec<u8> = (0..1001).map(|i| (i * 7 + 3) as u8).collect();
+        let expected = from_slice(&data);
+
+        for split in [
+            vec![1, 1000],
+            vec![3, 500, 498],
+            vec![0, 64, 0, 937],
+            vec![1001],
+        ] {
+            let mut chunks = Vec::new();
+            let mut rest = &data[..];
+            for len in split {
+                chunks.push(&rest[..len]);
+                rest = &rest[len..];
+            }
+
+            let mut accum = ChecksumAccumulator::new();
+            for chunk in chunks.iter() {
+                accum.update(chunk);
+            }
+            assert_eq!(accum.finish(), expected);
+
+            let mut buf = Chunks { chunks };
+            buf.advance(0);
+            assert_eq!(from_buf(buf, data.len()), expected);
+        }
+    }
 
-    propagate_carries(accum)
+    #[test]
+    fn accumulator_partial_len() {
+        let data: Vec<u8> = (0..200).map(|i| (i * 13 + 1) as u8).collect();
+        let buf = Chunks {
+            chunks: vec![&data[..77], &data[77..150], &data[150..]],
+        };
+        assert_eq!(from_buf(buf, 151), from_slice(&data[..151]));
+
+        let mut accum = ChecksumAccumulator::new();
+        accum.add_checksum(from_slice(&data[..100]));
+        accum.update(&data[100..]);
+        assert_eq!(accum.finish(), from_slice(&data));
+    }
 }
@@ -0,0 +1,663 @@
+//! A deterministic corpus of valid and boundary-case packets.
+//!
+//! [`generate`] crafts a labeled set of Ethernet frames for every supported
+//! protocol: the minimum and maximum header lengths, the optional parts of the
+//! headers, and frames that are malformed at exactly one layer. All the layers
+//! in front of the protocol under test are well-formed, so a malformed sample
+//! is expected to be rejected by the parser of that protocol only.
+//!
+//! The corpus is identical across runs, and can be written to pcap files with
+//! [`write_pcap`] to validate packet processing pipelines.
+//!
+//! # Examples
+//! ```
+//! use rpkt::corpus;
+//!
+//! let samples = corpus::generate();
+//! let tcp = samples.iter().filter(|s| s.protocol == "tcp");
+//! assert!(tcp.clone().any(|s| s.valid));
+//! assert!(tcp.clone().any(|s| !s.valid));
+//! ```
+
+use std::io::{self, Write};
+
+use crate::arp::{Operation, ARP_HEADER_LEN, ARP_HEADER_TEMPLATE};
+use crate::ether::{EtherType, MacAddr, ETHER_HEADER_LEN, ETHER_HEADER_TEMPLATE};
+use crate::icmpv4::{IcmpType, ICMPV4_HEADER_LEN, ICMPV4_HEADER_TEMPLATE};
+use crate::ipv4::{
+    Ecn, IpProtocol, Ipv4Addr, Ipv4Header, Ipv4OptionWriter, IPV4_HEADER_LEN, IPV4_HEADER_TEMPLATE,
+};
+use crate::ipv6::{Ipv6Addr, Ipv6Header, IPV6_HEADER_LEN};
+use crate::pcap::PcapWriter;
+use crate::tcp::{TcpHeader, TcpOptionWriter, TCP_HEADER_LEN, TCP_HEADER_TEMPLATE};
+use crate::udp::{UdpHeader, UDP_HEADER_LEN, UDP_HEADER_TEMPLATE};
+use crate::ChecksumAccumulator;
+
+const SRC_MAC: MacAddr = MacAddr([0x02, 0x00, 0x00, 0x00, 0x00, 0x01]);
+const DST_MAC: MacAddr = MacAddr([0x02, 0x00, 0x00, 0x00, 0x00, 0x02]);
+const SRC_IPV4: Ipv4Addr = Ipv4Addr([192, 0, 2, 1]);
+const DST_IPV4: Ipv4Addr = Ipv4Addr([192, 0, 2, 2]);
+const SRC_IPV6: Ipv6Addr = Ipv6Addr([0x20, 0x01, 0x0d, 0xb8, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 1]);
+const DST_IPV6: Ipv6Addr = Ipv6Addr([0x20, 0x01, 0x0d, 0xb8, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 2]);
+
+/// The local experimental ethertype, for frames without a known payload.
+const ETHERTYPE_EXPERIMENTAL: u16 = 0x88b5;
+
+/// The experimental IP protocol number, for packets without a known payload.
+const IP_PROTOCOL_EXPERIMENTAL: u8 = 253;
+
+/// A labeled packet of the corpus.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Sample {
+    /// The protocol under test, e.g. `"ipv4"`.
+    pub protocol: &'static str,
+    /// The case of the protocol, e.g. `"max-options"`.
+    pub case: &'static str,
+    /// Whether the parser of `protocol` is expected to accept the packet.
+    pub valid: bool,
+    /// The Ethernet frame.
+    pub frame: Vec<u8>,
+}
+
+impl Sample {
+    fn new(protocol: &'static str, case: &'static str, valid: bool, frame: Vec<u8>) -> Self {
+        Self {
+            protocol,
+            case,
+            valid,
+            frame,
+        }
+    }
+
+    /// Returns the label of the sample, e.g. `"ipv4/max-options/valid"`.
+    pub fn label(&self) -> String {
+        let validity = if self.valid { "valid" } else { "invalid" };
+        format!("{}/{}/{}", self.protocol, self.case, validity)
+    }
+}
+
+/// Generate the corpus.
+pub fn generate() -> Vec<Sample> {
+    let mut samples = Vec::new();
+    ether_samples(&mut samples);
+    arp_samples(&mut samples);
+    ipv4_samples(&mut samples);
+    ipv6_samples(&mut samples);
+    udp_samples(&mut samples);
+    tcp_samples(&mut samples);
+    icmpv4_samples(&mut samples);
+    samples
+}
+
+/// Write the samples into a pcap file, and the label of every sample, one per
+/// line in the order of the frames, into `labels`.
+///
+/// The n-th frame is stamped with n seconds, so the timestamp also identifies
+/// the sample.
+pub fn write_pcap<W: Write, L: Write>(
+    samples: &[Sample],
+    pcap: W,
+    mut labels: L,
+) -> io::Result<()> {
+    let mut writer = PcapWriter::new(pcap)?;
+    for (i, sample) in samples.iter().enumerate() {
+        writer.write_frame(i as u64 * 1_000_000, &sample.frame)?;
+        writeln!(labels, "{}", sample.label())?;
+    }
+    writer.flush()?;
+    labels.flush()
+}
+
+fn ether_samples(samples: &mut Vec<Sample>) {
+    let payload = [0; 46];
+    let frame = ether(EtherType::from(ETHERTYPE_EXPERIMENTAL), &payload);
+    samples.push(Sample::new("ether", "min-frame", true, frame.clone()));
+    samples.push(Sample::new(
+        "ether",
+        "truncated-header",
+        false,
+        frame[..ETHER_HEADER_LEN - 1].to_vec(),
+    ));
+}
+
+fn arp_samples(samples: &mut Vec<Sample>) {
+    for (case, op) in [("request", Operation::REQUEST), ("reply", Operation::REPLY)] {
+        let mut header = ARP_HEADER_TEMPLATE;
+        header.set_operation(op);
+        header.set_sender_hardware_addr(SRC_MAC.as_bytes());
+        header.set_sender_protocol_addr(SRC_IPV4.as_bytes());
+        if op == Operation::REPLY {
+            header.set_target_hardware_addr(DST_MAC.as_bytes());
+        }
+        header.set_target_protocol_addr(DST_IPV4.as_bytes());
+        samples.push(Sample::new(
+            "arp",
+            case,
+            true,
+            ether(EtherType::ARP, header.as_bytes()),
+        ));
+    }
+
+    let frame = ether(
+        EtherType::ARP,
+        &ARP_HEADER_TEMPLATE.as_bytes()[..ARP_HEADER_LEN - 1],
+    );
+    samples.push(Sample::new("arp", "truncated", false, frame));
+}
+
+fn ipv4_samples(samples: &mut Vec<Sample>) {
+    let protocol = IpProtocol::from(IP_PROTOCOL_EXPERIMENTAL);
+    samples.push(Sample::new(
+        "ipv4",
+        "min-header",
+        true,
+        ipv4(protocol, &[], &[], |_| {}),
+    ));
+
+    let options = ipv4_options(|writer| {
+        for _ in 0..40 {
+            writer.nop();
+        }
+    });
+    samples.push(Sample::new(
+        "ipv4",
+        "max-options",
+        true,
+        ipv4(protocol, &options, &[], |_| {}),
+    ));
+
+    samples.push(Sample::new(
+        "ipv4",
+        "first-fragment",
+        true,
+        ipv4(protocol, &[], &[0; 8], |ip| {
+            ip.set_dont_frag(false);
+            ip.set_more_frags(true);
+        }),
+    ));
+    samples.push(Sample::new(
+        "ipv4",
+        "last-fragment",
+        true,
+        ipv4(protocol, &[], &[0; 8], |ip| {
+            ip.set_dont_frag(false);
+            ip.set_frag_offset(0xfff8);
+        }),
+    ));
+    samples.push(Sample::new(
+        "ipv4",
+        "boundary-fields",
+        true,
+        ipv4(protocol, &[], &[], |ip| {
+            ip.set_dscp(0x3f);
+            ip.set_ecn_typed(Ecn::Ce);
+            ip.set_ident(0xffff);
+            ip.set_time_to_live(0);
+        }),
+    ));
+
+    // The header length setters reject such values, so patch the raw bytes.
+    let mut frame = ipv4(protocol, &[], &[], |_| {});
+    frame[ETHER_HEADER_LEN] = 0x44;
+    samples.push(Sample::new("ipv4", "header-len-below-min", false, frame));
+
+    let mut frame = ipv4(protocol, &[], &[0; 4], |_| {});
+    ipv4_header(&mut frame).set_header_len(28);
+    samples.push(Sample::new(
+        "ipv4",
+        "header-len-exceeds-packet-len",
+        false,
+        frame,
+    ));
+
+    let mut frame = ipv4(protocol, &[], &[], |_| {});
+    ipv4_header(&mut frame).set_packet_len(100);
+    samples.push(Sample::new(
+        "ipv4",
+        "packet-len-exceeds-frame",
+        false,
+        frame,
+    ));
+
+    let mut frame = ipv4(protocol, &[], &[], |_| {});
+    frame.pop();
+    samples.push(Sample::new("ipv4", "truncated-header", false, frame));
+}
+
+fn ipv6_samples(samples: &mut Vec<Sample>) {
+    samples.push(Sample::new(
+        "ipv6",
+        "no-next-header",
+        true,
+        ipv6(IpProtocol::IPV6_NO_NXT, &[], |_| {}),
+    ));
+    samples.push(Sample::new(
+        "ipv6",
+        "boundary-fields",
+        true,
+        ipv6(IpProtocol::IPV6_NO_NXT, &[], |ip| {
+            ip.set_traffic_class(0xff);
+            ip.set_flow_label(0xfffff);
+            ip.set_hop_limit(0);
+        }),
+    ));
+
+    let mut frame = ipv6(IpProtocol::IPV6_NO_NXT, &[], |_| {});
+    ipv6_header(&mut frame).set_payload_len(8);
+    samples.push(Sample::new(
+        "ipv6",
+        "payload-len-exceeds-frame",
+        false,
+        frame,
+    ));
+
+    let mut frame = ipv6(IpProtocol::IPV6_NO_NXT, &[], |_| {});
+    frame.pop();
+    samples.push(Sample::new("ipv6", "truncated-header", false, frame));
+}
+
+fn udp_samples(samples: &mut Vec<Sample>) {
+    samples.push(Sample::new(
+        "udp",
+        "empty-payload",
+        true,
+        ipv4(IpProtocol::UDP, &[], &udp(&[]), |_| {}),
+    ));
+    samples.push(Sample::new(
+        "udp",
+        "mtu-sized",
+        true,
+        ipv4(
+            IpProtocol::UDP,
+            &[],
+            &udp(&[0xa5; 1500 - IPV4_HEADER_LEN - UDP_HEADER_LEN]),
+            |_| {},
+        ),
+    ));
+    samples.push(Sample::new(
+        "udp",
+        "over-ipv6",
+        true,
+        ipv6(IpProtocol::UDP, &udp(&[0xa5; 16]), |_| {}),
+    ));
+
+    let mut segment = udp(&[]);
+    UdpHeader::new_unchecked(&mut segment[..]).set_packet_len(7);
+    samples.push(Sample::new(
+        "udp",
+        "len-below-min",
+        false,
+        ipv4(IpProtocol::UDP, &[], &segment, |_| {}),
+    ));
+
+    let mut segment = udp(&[]);
+    UdpHeader::new_unchecked(&mut segment[..]).set_packet_len(100);
+    samples.push(Sample::new(
+        "udp",
+        "len-exceeds-packet",
+        false,
+        ipv4(IpProtocol::UDP, &[], &segment, |_| {}),
+    ));
+
+    let segment = udp(&[]);
+    samples.push(Sample::new(
+        "udp",
+        "truncated-header",
+        false,
+        ipv4(IpProtocol::UDP, &[], &segment[..UDP_HEADER_LEN - 1], |_| {}),
+    ));
+}
+
+fn tcp_samples(samples: &mut Vec<Sample>) {
+    samples.push(Sample::new(
+        "tcp",
+        "syn-min-header",
+        true,
+        ipv4(
+            IpProtocol::TCP,
+            &[],
+            &tcp(&[], |tcp| tcp.set_syn(true)),
+            |_| {},
+        ),
+    ));
+
+    let options = tcp_options(|writer| {
+        writer.mss(1460);
+        writer.sack_perm();
+        writer.ts(1, 0);
+        writer.wsopt(14);
+        while writer.written_bytes() < 40 {
+            writer.nop();
+        }
+    });
+    samples.push(Sample::new(
+        "tcp",
+        "max-options",
+        true,
+        ipv4(
+            IpProtocol::TCP,
+            &[],
+            &tcp(&options, |tcp| tcp.set_syn(true)),
+            |_| {},
+        ),
+    ));
+
+    let segment = tcp(&[], |tcp| {
+        tcp.set_fin(true);
+        tcp.set_syn(true);
+        tcp.set_rst(true);
+        tcp.set_psh(true);
+        tcp.set_ack(true);
+        tcp.set_urg(true);
+        tcp.set_ece(true);
+        tcp.set_cwr(true);
+        tcp.set_ns(true);
+        tcp.set_window_size(0xffff);
+        tcp.set_urgent_ptr(0xffff);
+    });
+    samples.push(Sample::new(
+        "tcp",
+        "all-flags",
+        true,
+        ipv4(IpProtocol::TCP, &[], &segment, |_| {}),
+    ));
+    samples.push(Sample::new(
+        "tcp",
+        "over-ipv6",
+        true,
+        ipv6(IpProtocol::TCP, &tcp(&[], |tcp| tcp.set_syn(true)), |_| {}),
+    ));
+
+    let mut segment = tcp(&[], |tcp| tcp.set_syn(true));
+    segment[12] = 0x40;
+    samples.push(Sample::new(
+        "tcp",
+        "header-len-below-min",
+        false,
+        ipv4(IpProtocol::TCP, &[], &segment, |_| {}),
+    ));
+
+    let mut segment = tcp(&[], |tcp| tcp.set_syn(true));
+    TcpHeader::new_unchecked(&mut segment[..]).set_header_len(60);
+    samples.push(Sample::new(
+        "tcp",
+        "header-len-exceeds-segment",
+        false,
+        ipv4(IpProtocol::TCP, &[], &segment, |_| {}),
+    ));
+}
+
+fn icmpv4_samples(samples: &mut Vec<Sample>) {
+    for (case, icmp_type) in [
+        ("echo-request", IcmpType::ECHO_REQUEST),
+        ("echo-reply", IcmpType::ECHO_REPLY),
+    ] {
+        let mut header = ICMPV4_HEADER_TEMPLATE;
+        header.set_icmp_type(icmp_type);
+        header.set_ident(1);
+        header.set_seq_num(1);
+        let mut message = header.as_bytes().to_vec();
+        message.extend_from_slice(&[0xa5; 32]);
+        samples.push(Sample::new(
+            "icmpv4",
+            case,
+            true,
+            ipv4(IpProtocol::ICMP, &[], &message, |_| {}),
+        ));
+    }
+
+    let message = ICMPV4_HEADER_TEMPLATE.as_bytes();
+    samples.push(Sample::new(
+        "icmpv4",
+        "truncated",
+        false,
+        ipv4(
+            IpProtocol::ICMP,
+            &[],
+            &message[..ICMPV4_HEADER_LEN - 1],
+            |_| {},
+        ),
+    ));
+}
+
+fn ether(ethertype: EtherType, payload: &[u8]) -> Vec<u8> {
+    let mut header = ETHER_HEADER_TEMPLATE;
+    header.set_dest_mac(DST_MAC);
+    header.set_source_mac(SRC_MAC);
+    header.set_ethertype(ethertype);
+
+    let mut frame = header.as_bytes().to_vec();
+    frame.extend_from_slice(payload);
+    frame
+}
+
+fn ipv4_header(frame: &mut [u8]) -> Ipv4Header<&mut [u8]> {
+    Ipv4Header::new_unchecked(&mut frame[ETHER_HEADER_LEN..])
+}
+
+fn ipv6_header(frame: &mut [u8]) -> Ipv6Header<&mut [u8]> {
+    Ipv6Header::new_unchecked(&mut frame[ETHER_HEADER_LEN..])
+}
+
+fn ipv4_options<F: FnOnce(&mut Ipv4OptionWriter<'_>)>(f: F) -> Vec<u8> {
+    let mut options = [0; 40];
+    let mut writer = Ipv4OptionWriter::from_option_bytes_mut(&mut options[..]);
+    f(&mut writer);
+    let len = writer.pad();
+    options[..len].to_vec()
+}
+
+fn tcp_options<F: FnOnce(&mut TcpOptionWriter<'_>)>(f: F) -> Vec<u8> {
+    let mut options = [0; 40];
+    let mut writer = TcpOptionWriter::from_option_bytes_mut(&mut options[..]);
+    f(&mut writer);
+    let len = writer.pad();
+    options[..len].to_vec()
+}
+
+// Build an IPv4 frame with valid checksums, then let `f` adjust the header.
+fn ipv4<F>(protocol: IpProtocol, options: &[u8], payload: &[u8], f: F) -> Vec<u8>
+where
+    F: FnOnce(&mut Ipv4Header<&mut [u8]>),
+{
+    let header_len = IPV4_HEADER_LEN + options.len();
+    let mut header = IPV4_HEADER_TEMPLATE;
+    header.set_header_len(header_len as u8);
+    header.set_packet_len((header_len + payload.len()) as u16);
+    header.set_protocol(protocol);
+    header.set_source_ip(SRC_IPV4);
+    header.set_dest_ip(DST_IPV4);
+
+    let mut packet = header.as_bytes().to_vec();
+    packet.extend_from_slice(options);
+    packet.extend_from_slice(payload);
+
+    let mut pseudo = ChecksumAccumulator::new();
+    pseudo.update(SRC_IPV4.as_bytes());
+    pseudo.update(DST_IPV4.as_bytes());
+    pseudo.update(&[0, protocol.into()]);
+    pseudo.update(&(payload.len() as u16).to_be_bytes());
+    fill_l4_checksum(protocol, &mut packet[header_len..], pseudo);
+
+    let mut frame = ether(EtherType::IPV4, &packet);
+    let mut header = ipv4_header(&mut frame);
+    f(&mut header);
+    let mut accum = ChecksumAccumulator::new();
+    accum.update(&header.as_bytes()[..10]);
+    accum.update(&frame[ETHER_HEADER_LEN + 12..ETHER_HEADER_LEN + header_len]);
+    ipv4_header(&mut frame).set_checksum(!accum.finish());
+    frame
+}
+
+// Build an IPv6 frame with a valid upper-layer checksum, then let `f` adjust
+// the header.
+fn ipv6<F>(next_header: IpProtocol, payload: &[u8], f: F) -> Vec<u8>
+where
+    F: FnOnce(&mut Ipv6Header<&mut [u8]>),
+{
+    let mut packet = vec![0; IPV6_HEADER_LEN];
+    let mut header = Ipv6Header::new_unchecked(&mut packet[..]);
+    header.adjust_version();
+    header.set_payload_len(payload.len() as u16);
+    header.set_next_header(next_header);
+    header.set_hop_limit(64);
+    header.set_source_ip(&SRC_IPV6);
+    header.set_dest_ip(&DST_IPV6);
+    packet.extend_from_slice(payload);
+
+    let mut pseudo = ChecksumAccumulator::new();
+    pseudo.update(SRC_IPV6.as_bytes());
+    pseudo.update(DST_IPV6.as_bytes());
+    pseudo.update(&(payload.len() as u32).to_be_bytes());
+    pseudo.update(&[0, 0, 0, next_header.into()]);
+    fill_l4_checksum(next_header, &mut packet[IPV6_HEADER_LEN..], pseudo);
+
+    let mut frame = ether(EtherType::IPV6, &packet);
+    f(&mut ipv6_header(&mut frame));
+    frame
+}
+
+// Fill the checksum of a well-formed UDP, TCP or ICMPv4 message.
+fn fill_l4_checksum(protocol: IpProtocol, message: &mut [u8], pseudo: ChecksumAccumulator) {
+    let (offset, mut accum) = match protocol {
+        IpProtocol::UDP if message.len() >= UDP_HEADER_LEN => (6, pseudo),
+        IpProtocol::TCP if message.len() >= TCP_HEADER_LEN => (16, pseudo),
+        IpProtocol::ICMP if message.len() >= ICMPV4_HEADER_LEN => (2, ChecksumAccumulator::new()),
+        _ => return,
+    };
+    message[offset..offset + 2].copy_from_slice(&[0, 0]);
+    accum.update(message);
+    let checksum = !accum.finish();
+    // An all-zero UDP checksum means no checksum.
+    let checksum = if protocol == IpProtocol::UDP && checksum == 0 {
+        !0
+    } else {
+        checksum
+    };
+    message[offset..offset + 2].copy_from_slice(&checksum.to_be_bytes());
+}
+
+fn udp(payload: &[u8]) -> Vec<u8> {
+    let mut header = UDP_HEADER_TEMPLATE;
+    header.set_source_port(49152);
+    header.set_dest_port(9);
+    header.set_packet_len((UDP_HEADER_LEN + payload.len()) as u16);
+
+    let mut segment = header.as_bytes().to_vec();
+    segment.extend_from_slice(payload);
+    segment
+}
+
+fn tcp<F: FnOnce(&mut TcpHeader<[u8; TCP_HEADER_LEN]>)>(options: &[u8], f: F) -> Vec<u8> {
+    let mut header = TCP_HEADER_TEMPLATE;
+    header.set_src_port(49152);
+    header.set_dst_port(9);
+    header.set_seq_number(1);
+    header.set_header_len((TCP_HEADER_LEN + options.len()) as u8);
+    f(&mut header);
+
+    let mut segment = header.as_bytes().to_vec();
+    segment.extend_from_slice(options);
+    segment
+}
+
+#[cfg(test)]
+mod tests {
+    use bytes::Buf;
+
+    use super::*;
+    use crate::arp::ArpPacket;
+    use crate::ether::EtherPacket;
+    use crate::icmpv4::Icmpv4Packet;
+    use crate::ipv4::Ipv4Packet;
+    use crate::ipv6::Ipv6Packet;
+    use crate::tcp::TcpPacket;
+    use crate::udp::UdpPacket;
+    use crate::Cursor;
+
+    // Parse the frame down to the protocol under test, returning whether the
+    // parser of that protocol accepts it, or `None` if an outer layer fails.
+    fn accepted(sample: &Sample) -> Option<bool> {
+        let ethpkt = EtherPacket::parse(Cursor::new(&sample.frame[..]));
+        if sample.protocol == "ether" {
+            return Some(ethpkt.is_ok());
+        }
+        let ethpkt = ethpkt.ok()?;
+        if sample.protocol == "arp" {
+            return Some(ArpPacket::parse(ethpkt.payload()).is_ok());
+        }
+
+        let ethertype = ethpkt.ethertype();
+        let payload = if ethertype == EtherType::IPV4 {
+            let ippkt = Ipv4Packet::parse(ethpkt.payload());
+            if sample.protocol == "ipv4" {
+                return Some(ippkt.is_ok());
+            }
+            let ippkt = ippkt.ok()?;
+            assert!(ippkt.verify_checksum());
+            ippkt.payload()
+        } else {
+            let ippkt = Ipv6Packet::parse(ethpkt.payload());
+            if sample.protocol == "ipv6" {
+                return Some(ippkt.is_ok());
+            }
+            ippkt.ok()?.payload()
+        };
+
+        Some(match sample.protocol {
+            "udp" => UdpPacket::parse(payload).is_ok(),
+            "tcp" => TcpPacket::parse(payload).is_ok(),
+            "icmpv4" => Icmpv4Packet::parse(payload).is_ok(),
+            _ => panic!("unknown protocol {}", sample.protocol),
+        })
+    }
+
+    #[test]
+    fn corpus_parse() {
+        let samples = generate();
+        assert_eq!(samples, generate());
+        for sample in samples.iter() {
+            assert_eq!(accepted(sample), Some(sample.valid), "{}", sample.label());
+        }
+    }
+
+    #[test]
+    fn corpus_checksums() {
+        for sample in generate().iter().filter(|sample| sample.valid) {
+            let ethpkt = EtherPacket::parse(Cursor::new(&sample.frame[..])).unwrap();
+            if ethpkt.ethertype() != EtherType::IPV4 {
+                continue;
+            }
+            let ippkt = Ipv4Packet::parse(ethpkt.payload()).unwrap();
+            let (src, dst, protocol) = (ippkt.source_ip(), ippkt.dest_ip(), ippkt.protocol());
+            let payload = ippkt.payload();
+            let segment = payload.chunk();
+
+            let mut accum = ChecksumAccumulator::new();
+            if protocol == IpProtocol::UDP || protocol == IpProtocol::TCP {
+                accum.update(src.as_bytes());
+                accum.update(dst.as_bytes());
+                accum.update(&[0, protocol.into()]);
+                accum.update(&(segment.len() as u16).to_be_bytes());
+            } else if protocol != IpProtocol::ICMP {
+                continue;
+            }
+            accum.update(segment);
+            assert_eq!(accum.finish(), 0xffff, "{}", sample.label());
+        }
+    }
+
+    #[test]
+    fn corpus_pcap() {
+        let samples = generate();
+        let mut pcap = Vec::new();
+        let mut labels = Vec::new();
+        write_pcap(&samples, &mut pcap, &mut labels).unwrap();
+
+        let frames_len: usize = samples.iter().map(|s| 16 + s.frame.len()).sum();
+        assert_eq!(pcap.len(), 24 + frames_len);
+        let labels = String::from_utf8(labels).unwrap();
+        assert_eq!(labels.lines().count(), samples.len());
+        assert_eq!(labels.lines().next(), Some("ether/min-frame/valid"));
+    }
+}
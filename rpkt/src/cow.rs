@@ -0,0 +1,229 @@
+//! Copy-on-write header editing of shared packets.
+//!
+//! A middlebox that rewrites a few header fields, e.g. a NAT, should not copy
+//! the whole packet when the buffer it receives is shared or read-only.
+//! [`CowPkt`] borrows such a packet, and copies the header region into a small
+//! owned buffer on the first mutation. The payload stays borrowed, and the
+//! edited packet is read as two chunks: the owned header region, followed by
+//! the rest of the borrowed packet, e.g. for a scatter-gather transmit.
+//!
+//! # Examples
+//! ```
+//! use rpkt::cow::CowPkt;
+//! use rpkt::ether::ETHER_HEADER_LEN;
+//! use rpkt::ipv4::*;
+//!
+//! let mut frame = vec![0; 9000];
+//! frame[ETHER_HEADER_LEN..][..IPV4_HEADER_LEN].copy_from_slice(IPV4_HEADER_TEMPLATE.as_bytes());
+//!
+//! let mut pkt = CowPkt::new(&frame[..]);
+//! let header = pkt.header_mut(ETHER_HEADER_LEN + IPV4_HEADER_LEN);
+//! let mut ipv4 = Ipv4Header::new(&mut header[ETHER_HEADER_LEN..]).unwrap();
+//! ipv4.set_time_to_live(63);
+//!
+//! let [header, payload] = pkt.chunks();
+//! assert_eq!(header.len(), 34);
+//! assert_eq!(payload.len(), 9000 - 34);
+//! assert_eq!(Ipv4Header::new(&header[ETHER_HEADER_LEN..]).unwrap().time_to_live(), 63);
+//! ```
+
+use std::io;
+
+/// The maximum length of the owned header region.
+pub const COW_HEADER_CAPACITY: usize = 256;
+
+/// A borrowed packet whose header region is copied on the first mutation.
+#[derive(Clone)]
+pub struct CowPkt<'a> {
+    data: &'a [u8],
+    // The owned copy of `data[..owned_len]`.
+    header: [u8; COW_HEADER_CAPACITY],
+    owned_len: usize,
+}
+
+impl<'a> CowPkt<'a> {
+    /// Borrow the packet `data`.
+    pub fn new(data: &'a [u8]) -> Self {
+        Self {
+            data,
+            header: [0; COW_HEADER_CAPACITY],
+            owned_len: 0,
+        }
+    }
+
+    /// Returns the length of the packet.
+    pub fn len(&self) -> usize {
+        self.data.len()
+    }
+
+    /// Returns whether the packet is empty.
+    pub fn is_empty(&self) -> bool {
+        self.data.is_empty()
+    }
+
+    /// Returns the length of the owned header region, which is 0 until the
+    /// first mutation.
+    pub fn owned_len(&self) -> usize {
+        self.owned_len
+    }
+
+    /// Returns the borrowed packet, without the mutations.
+    pub fn original(&self) -> &'a [u8] {
+        self.data
+    }
+
+    /// Returns the first `len` bytes of the packet, with the mutations.
+    ///
+    /// # Panics
+    ///
+    /// This function panics if `len` exceeds the length of the packet, or if
+    /// the header region is owned but shorter than `len`, as the bytes are not
+    /// contiguous then.
+    pub fn header(&self, len: usize) -> &[u8] {
+        assert!(len <= self.data.len());
+        if self.owned_len == 0 {
+            &self.data[..len]
+        } else {
+            assert!(
+                len <= self.owned_len,
+                "{} bytes are read, but only {} bytes are owned",
+                len,
+                self.owned_len
+            );
+            &self.header[..len]
+        }
+    }
+
+    /// Returns the first `len` bytes of the packet for mutation, copying the
+    /// bytes that are not owned yet into the owned header region.
+    ///
+    /// # Panics
+    ///
+    /// This function panics if `len` exceeds the length of the packet, or
+    /// [`COW_HEADER_CAPACITY`].
+    pub fn header_mut(&mut self, len: usize) -> &mut [u8] {
+        assert!(len <= self.data.len() && len <= COW_HEADER_CAPACITY);
+        if len > self.owned_len {
+            self.header[self.owned_len..len].copy_from_slice(&self.data[self.owned_len..len]);
+            self.owned_len = len;
+        }
+        &mut self.header[..len]
+    }
+
+    /// Drop the mutations, borrowing the original packet again.
+    pub fn reset(&mut self) {
+        self.owned_len = 0;
+    }
+
+    /// Returns the packet as the owned header region, followed by the rest of
+    /// the borrowed packet.
+    pub fn chunks(&self) -> [&[u8]; 2] {
+        [&self.header[..self.owned_len], &self.data[self.owned_len..]]
+    }
+
+    /// Copy the packet, with the mutations, into a vector.
+    pub fn to_vec(&self) -> Vec<u8> {
+        let mut bytes = Vec::with_capacity(self.len());
+        for chunk in self.chunks() {
+            bytes.extend_from_slice(chunk);
+        }
+        bytes
+    }
+
+    /// Write the packet, with the mutations, into `writer`.
+    pub fn write_to<W: io::Write>(&self, mut writer: W) -> io::Result<()> {
+        for chunk in self.chunks() {
+            writer.write_all(chunk)?;
+        }
+        Ok(())
+    }
+}
+
+impl std::fmt::Debug for CowPkt<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("CowPkt")
+            .field("len", &self.len())
+            .field("owned_len", &self.owned_len)
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ether::ETHER_HEADER_LEN;
+    use crate::ipv4::{Ipv4Addr, Ipv4Header, IPV4_HEADER_LEN, IPV4_HEADER_TEMPLATE};
+    use crate::tcp::{TcpHeader, TCP_HEADER_LEN, TCP_HEADER_TEMPLATE};
+
+    const IP_OFFSET: usize = ETHER_HEADER_LEN;
+    const TCP_OFFSET: usize = ETHER_HEADER_LEN + IPV4_HEADER_LEN;
+
+    fn frame() -> Vec<u8> {
+        let mut frame: Vec<u8> = (0..2000).map(|i| i as u8).collect();
+        let mut ip = IPV4_HEADER_TEMPLATE;
+        ip.set_source_ip(Ipv4Addr::new(10, 0, 0, 1));
+        frame[IP_OFFSET..TCP_OFFSET].copy_from_slice(ip.as_bytes());
+        frame[TCP_OFFSET..][..TCP_HEADER_LEN].copy_from_slice(TCP_HEADER_TEMPLATE.as_bytes());
+        frame
+    }
+
+    #[test]
+    fn rewrite_headers() {
+        let frame = frame();
+        let mut pkt = CowPkt::new(&frame[..]);
+        assert_eq!(pkt.owned_len(), 0);
+        assert_eq!(pkt.header(TCP_OFFSET), &frame[..TCP_OFFSET]);
+        assert_eq!(pkt.chunks(), [&[][..], &frame[..]]);
+
+        // Rewrite the source address, then the source port, which grows the
+        // owned region without losing the first rewrite.
+        let header = pkt.header_mut(TCP_OFFSET);
+        Ipv4Header::new(&mut header[IP_OFFSET..])
+            .unwrap()
+            .set_source_ip(Ipv4Addr::new(192, 0, 2, 1));
+        let header = pkt.header_mut(TCP_OFFSET + TCP_HEADER_LEN);
+        TcpHeader::new(&mut header[TCP_OFFSET..])
+            .unwrap()
+            .set_src_port(40000);
+        assert_eq!(pkt.owned_len(), TCP_OFFSET + TCP_HEADER_LEN);
+
+        let bytes = pkt.to_vec();
+        assert_eq!(bytes.len(), frame.len());
+        let ip = Ipv4Header::new(&bytes[IP_OFFSET..]).unwrap();
+        assert_eq!(ip.source_ip(), Ipv4Addr::new(192, 0, 2, 1));
+        let tcp = TcpHeader::new(&bytes[TCP_OFFSET..]).unwrap();
+        assert_eq!(tcp.src_port(), 40000);
+        assert_eq!(
+            &bytes[TCP_OFFSET + TCP_HEADER_LEN..],
+            &frame[TCP_OFFSET + TCP_HEADER_LEN..]
+        );
+
+        // The borrowed packet is untouched.
+        let ip = Ipv4Header::new(&pkt.original()[IP_OFFSET..]).unwrap();
+        assert_eq!(ip.source_ip(), Ipv4Addr::new(10, 0, 0, 1));
+
+        let mut written = Vec::new();
+        pkt.write_to(&mut written).unwrap();
+        assert_eq!(written, bytes);
+
+        pkt.reset();
+        assert_eq!(pkt.to_vec(), frame);
+    }
+
+    #[test]
+    #[should_panic(expected = "only 14 bytes are owned")]
+    fn read_past_owned_region() {
+        let frame = frame();
+        let mut pkt = CowPkt::new(&frame[..]);
+        pkt.header_mut(ETHER_HEADER_LEN);
+        pkt.header(TCP_OFFSET);
+    }
+
+    #[test]
+    #[should_panic]
+    fn owned_region_overflow() {
+        let frame = frame();
+        let mut pkt = CowPkt::new(&frame[..]);
+        pkt.header_mut(COW_HEADER_CAPACITY + 1);
+    }
+}
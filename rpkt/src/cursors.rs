@@ -31,6 +31,70 @@ impl<'a> Cursor<'a> {
     pub fn cursor(&self) -> usize {
         unsafe { self.chunk.as_ptr().offset_from(self.start_addr) as usize }
     }
+
+    /// Returns the remaining bytes of the cursor, with the lifetime of the
+    /// underlying buffer.
+    #[inline]
+    pub fn remaining_slice(&self) -> &'a [u8] {
+        self.chunk
+    }
+
+    /// Copy the first `N` bytes of the remaining bytes without advancing the
+    /// cursor. Returns `None` if less than `N` bytes remain.
+    #[inline]
+    pub fn peek<const N: usize>(&self) -> Option<[u8; N]> {
+        self.chunk.get(..N).map(|data| data.try_into().unwrap())
+    }
+
+    /// Split the remaining bytes at position `n`.
+    ///
+    /// The first cursor holds the first `n` remaining bytes, while the second
+    /// one holds the rest. Both cursors share the start of the underlying
+    /// buffer, so their `cursor` positions are the same as the original cursor.
+    ///
+    /// # Panics
+    ///
+    /// This function panics if `n` is larger than the number of remaining bytes.
+    #[inline]
+    pub fn split_at(self, n: usize) -> (Cursor<'a>, Cursor<'a>) {
+        let (first, second) = self.chunk.split_at(n);
+        (
+            Cursor {
+                chunk: first,
+                start_addr: self.start_addr,
+            },
+            Cursor {
+                chunk: second,
+                start_addr: self.start_addr,
+            },
+        )
+    }
+
+    /// Advance the cursor to the next position that is a multiple of `n`,
+    /// e.g. to skip the padding after a TLV.
+    ///
+    /// Returns the number of skipped bytes, or `None` without advancing the
+    /// cursor if not enough bytes remain.
+    ///
+    /// # Panics
+    ///
+    /// This function panics if `n` is 0.
+    #[inline]
+    pub fn align_to(&mut self, n: usize) -> Option<usize> {
+        let pad = align_pad(self.cursor(), n);
+        if pad > self.chunk.len() {
+            return None;
+        }
+        self.advance(pad);
+        Some(pad)
+    }
+}
+
+// The number of bytes to skip for `pos` to be a multiple of `n`.
+#[inline]
+fn align_pad(pos: usize, n: usize) -> usize {
+    assert!(n > 0, "zero alignment");
+    (n - pos % n) % n
 }
 
 // custom implementation for &[u8]
@@ -98,6 +162,60 @@ impl<'a> CursorMut<'a> {
     pub fn cursor(&self) -> usize {
         unsafe { self.chunk.as_ptr().offset_from(self.start_addr) as usize }
     }
+
+    /// Returns the remaining bytes of the cursor.
+    #[inline]
+    pub fn remaining_slice(&self) -> &[u8] {
+        self.chunk
+    }
+
+    /// Copy the first `N` bytes of the remaining bytes without advancing the
+    /// cursor. Returns `None` if less than `N` bytes remain.
+    #[inline]
+    pub fn peek<const N: usize>(&self) -> Option<[u8; N]> {
+        self.chunk.get(..N).map(|data| data.try_into().unwrap())
+    }
+
+    /// Split the remaining bytes at position `n`.
+    ///
+    /// The first cursor holds the first `n` remaining bytes and keeps the
+    /// headroom of the original cursor. The second one holds the rest, and
+    /// starts a new buffer with no headroom, so that the two cursors never
+    /// overlap.
+    ///
+    /// # Panics
+    ///
+    /// This function panics if `n` is larger than the number of remaining bytes.
+    #[inline]
+    pub fn split_at(self, n: usize) -> (CursorMut<'a>, CursorMut<'a>) {
+        let (first, second) = self.chunk.split_at_mut(n);
+        (
+            CursorMut {
+                chunk: first,
+                start_addr: self.start_addr,
+            },
+            CursorMut::new(second),
+        )
+    }
+
+    /// Advance the cursor to the next position that is a multiple of `n`,
+    /// e.g. to skip the padding after a TLV.
+    ///
+    /// Returns the number of skipped bytes, or `None` without advancing the
+    /// cursor if not enough bytes remain.
+    ///
+    /// # Panics
+    ///
+    /// This function panics if `n` is 0.
+    #[inline]
+    pub fn align_to(&mut self, n: usize) -> Option<usize> {
+        let pad = align_pad(self.cursor(), n);
+        if pad > self.chunk.len() {
+            return None;
+        }
+        self.advance(pad);
+        Some(pad)
+    }
 }
 
 impl<'a> Buf for CursorMut<'a> {
@@ -224,4 +342,53 @@ mod tests {
             assert_eq!(cursor.chunk_mut(), &mut c[n..(1000 - c_pos)]);
         }
     }
+
+    #[test]
+    fn test_cursor_helpers() {
+        let b: Vec<u8> = (0..20).collect();
+        let mut cursor = Cursor::new(&b[..]);
+        cursor.advance(3);
+
+        assert_eq!(cursor.peek::<2>(), Some([3, 4]));
+        assert_eq!(cursor.peek::<18>(), None);
+        assert_eq!(cursor.remaining_slice(), &b[3..]);
+
+        assert_eq!(cursor.align_to(4), Some(1));
+        assert_eq!(cursor.cursor(), 4);
+        assert_eq!(cursor.align_to(4), Some(0));
+
+        let (mut first, mut second) = cursor.split_at(6);
+        assert_eq!(first.chunk(), &b[4..10]);
+        assert_eq!(second.chunk(), &b[10..]);
+        assert_eq!(second.cursor(), 10);
+        assert_eq!(first.align_to(16), None);
+        assert_eq!(first.cursor(), 4);
+        second.move_back(10);
+        assert_eq!(second.chunk(), &b[..]);
+    }
+
+    #[test]
+    fn test_cursor_mut_helpers() {
+        let mut b: Vec<u8> = (0..20).collect();
+        let c = b.clone();
+        let mut cursor = CursorMut::new(&mut b[..]);
+        cursor.advance(3);
+
+        assert_eq!(cursor.peek::<2>(), Some([3, 4]));
+        assert_eq!(cursor.peek::<18>(), None);
+        assert_eq!(cursor.remaining_slice(), &c[3..]);
+        assert_eq!(cursor.align_to(8), Some(5));
+
+        let (mut first, mut second) = cursor.split_at(6);
+        assert_eq!(first.chunk(), &c[8..14]);
+        assert_eq!(first.chunk_headroom(), 8);
+        assert_eq!(second.chunk(), &c[14..]);
+        assert_eq!(second.chunk_headroom(), 0);
+
+        first.chunk_mut().fill(0xff);
+        second.advance(1);
+        assert_eq!(second.align_to(4), Some(3));
+        assert_eq!(second.chunk(), &c[18..]);
+        assert_eq!(&b[8..14], &[0xff; 6]);
+    }
 }
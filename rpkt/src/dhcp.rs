@@ -0,0 +1,324 @@
+//! Relay agent helpers for DHCPv4 messages.
+//!
+//! A DHCP relay agent (RFC 2131, section 4.1) forwards the broadcast messages
+//! of the clients to a server in another subnet. On the way to the server, it
+//! writes its own address into the `giaddr` field, and appends a Relay Agent
+//! Information option (option 82, RFC 3046) that identifies the circuit and the
+//! remote host. On the way back, it strips the option before delivering the
+//! reply to the client.
+//!
+//! The helpers work on the raw bytes of a DHCP message, i.e. the UDP payload,
+//! and follow the `(buf, len)` convention of [`crate::ether::pad_frame`]:
+//! `buf` holds the message in its first `len` bytes, and the spare bytes
+//! behind it are used when the message grows.
+//!
+//! # Examples
+//! ```
+//! use rpkt::dhcp::*;
+//! use rpkt::ipv4::Ipv4Addr;
+//!
+//! // A DHCPDISCOVER with the message type option.
+//! let mut buf = [0; 300];
+//! buf[0] = 1;
+//! buf[DHCP_FIXED_LEN..DHCP_OPTIONS_OFFSET].copy_from_slice(&DHCP_MAGIC_COOKIE);
+//! buf[DHCP_OPTIONS_OFFSET..DHCP_OPTIONS_OFFSET + 4].copy_from_slice(&[53, 1, 1, 255]);
+//! let len = DHCP_OPTIONS_OFFSET + 4;
+//!
+//! set_giaddr(&mut buf, Ipv4Addr::new(10, 0, 0, 1));
+//! let sub_options = [(CIRCUIT_ID, &b"eth0"[..]), (REMOTE_ID, &b"cpe-1"[..])];
+//! let relayed = insert_relay_agent_info(&mut buf, len, &sub_options).unwrap();
+//! assert_eq!(relayed, len + 2 + 6 + 7);
+//! assert!(relay_agent_info(&buf[..relayed]).is_some());
+//!
+//! assert_eq!(strip_relay_agent_info(&mut buf, relayed), Some(len));
+//! ```
+
+use crate::ipv4::Ipv4Addr;
+use crate::tlv::{TlvFormat, TlvIter, TlvWriter};
+
+/// The length of the fixed part of a DHCP message, before the magic cookie.
+pub const DHCP_FIXED_LEN: usize = 236;
+
+/// The magic cookie that starts the options (RFC 2131, section 3).
+pub const DHCP_MAGIC_COOKIE: [u8; 4] = [99, 130, 83, 99];
+
+/// The offset of the first option of a DHCP message.
+pub const DHCP_OPTIONS_OFFSET: usize = DHCP_FIXED_LEN + 4;
+
+/// The type of the Relay Agent Information option.
+pub const RELAY_AGENT_INFO: u8 = 82;
+
+/// The sub-option type of the Agent Circuit ID.
+pub const CIRCUIT_ID: u8 = 1;
+
+/// The sub-option type of the Agent Remote ID.
+pub const REMOTE_ID: u8 = 2;
+
+/// The layout of the sub-options of the Relay Agent Information option, which
+/// have neither padding nor end records.
+pub const RELAY_SUB_OPTIONS: TlvFormat = TlvFormat {
+    type_width: 1,
+    len_width: 1,
+    len_includes_header: false,
+    len_unit: 1,
+    align: 1,
+    pad_type: None,
+    end_type: None,
+};
+
+// The type of the option that ends the options.
+const END: u32 = 255;
+
+/// Returns the hop count of the message.
+///
+/// # Panics
+///
+/// This function panics if `msg` is shorter than [`DHCP_FIXED_LEN`].
+#[inline]
+pub fn hops(msg: &[u8]) -> u8 {
+    msg[..DHCP_FIXED_LEN][3]
+}
+
+/// Set the hop count of the message.
+///
+/// # Panics
+///
+/// This function panics if `msg` is shorter than [`DHCP_FIXED_LEN`].
+#[inline]
+pub fn set_hops(msg: &mut [u8], value: u8) {
+    msg[..DHCP_FIXED_LEN][3] = value;
+}
+
+/// Returns the relay agent address of the message.
+///
+/// # Panics
+///
+/// This function panics if `msg` is shorter than [`DHCP_FIXED_LEN`].
+#[inline]
+pub fn giaddr(msg: &[u8]) -> Ipv4Addr {
+    Ipv4Addr::from_bytes(&msg[..DHCP_FIXED_LEN][24..28])
+}
+
+/// Set the relay agent address of the message.
+///
+/// # Panics
+///
+/// This function panics if `msg` is shorter than [`DHCP_FIXED_LEN`].
+#[inline]
+pub fn set_giaddr(msg: &mut [u8], addr: Ipv4Addr) {
+    msg[..DHCP_FIXED_LEN][24..28].copy_from_slice(&addr.0);
+}
+
+/// Returns the value of the Relay Agent Information option of `msg`, which
+/// can be iterated with [`TlvIter`] and [`RELAY_SUB_OPTIONS`].
+///
+/// Returns `None` if the message is malformed, or if it has no such option.
+pub fn relay_agent_info(msg: &[u8]) -> Option<&[u8]> {
+    let options = options(msg)?;
+    let mut iter = TlvIter::new(options, TlvFormat::DHCP);
+    let value = iter
+        .by_ref()
+        .find(|tlv| tlv.tlv_type == u32::from(RELAY_AGENT_INFO))
+        .map(|tlv| tlv.value);
+    value.filter(|_| iter.is_valid())
+}
+
+/// Append a Relay Agent Information option with `sub_options` to the message
+/// of `len` bytes at the start of `buf`, and return the new length.
+///
+/// The option is inserted in front of the end option, as RFC 3046 requires it
+/// to be the last one. An end option is added if the message has none.
+///
+/// Returns `None` and leaves the message untouched if the message is
+/// malformed, if it already has a Relay Agent Information option, if the
+/// sub-options exceed the 255-byte limit of the option, or if `buf` is too
+/// short for the new message.
+pub fn insert_relay_agent_info(
+    buf: &mut [u8],
+    len: usize,
+    sub_options: &[(u8, &[u8])],
+) -> Option<usize> {
+    let (end, has_end) = {
+        let msg = buf.get(..len)?;
+        let options = options(msg)?;
+        let mut iter = TlvIter::new(options, TlvFormat::DHCP);
+        let mut end = None;
+        for tlv in &mut iter {
+            if tlv.tlv_type == u32::from(RELAY_AGENT_INFO) {
+                return None;
+            }
+            if tlv.tlv_type == END {
+                end = Some(offset_of(msg, tlv.bytes));
+            }
+        }
+        if !iter.is_valid() {
+            return None;
+        }
+        match end {
+            Some(end) => (end, true),
+            None => (len, false),
+        }
+    };
+
+    let value_len: usize = sub_options.iter().map(|(_, value)| 2 + value.len()).sum();
+    if value_len > 255 {
+        return None;
+    }
+    let opt_len = 2 + value_len;
+    let new_len = len + opt_len + usize::from(!has_end);
+    if buf.len() < new_len {
+        return None;
+    }
+
+    buf.copy_within(end..len, end + opt_len);
+    buf[end] = RELAY_AGENT_INFO;
+    buf[end + 1] = value_len as u8;
+    let mut writer = TlvWriter::new(&mut buf[end + 2..end + opt_len], RELAY_SUB_OPTIONS);
+    for (sub_type, value) in sub_options {
+        writer.write(u32::from(*sub_type), value)?;
+    }
+    if !has_end {
+        buf[new_len - 1] = END as u8;
+    }
+    Some(new_len)
+}
+
+/// Remove the Relay Agent Information option from the message of `len` bytes
+/// at the start of `buf`, and return the new length.
+///
+/// Returns `None` and leaves the message untouched if the message is
+/// malformed, or if it has no such option.
+pub fn strip_relay_agent_info(buf: &mut [u8], len: usize) -> Option<usize> {
+    let (start, opt_len) = {
+        let msg = buf.get(..len)?;
+        let options = options(msg)?;
+        let mut iter = TlvIter::new(options, TlvFormat::DHCP);
+        let tlv = iter
+            .by_ref()
+            .find(|tlv| tlv.tlv_type == u32::from(RELAY_AGENT_INFO))?;
+        if !iter.is_valid() {
+            return None;
+        }
+        (offset_of(msg, tlv.bytes), tlv.bytes.len())
+    };
+    buf.copy_within(start + opt_len..len, start);
+    Some(len - opt_len)
+}
+
+// Returns the options of `msg`, after the magic cookie.
+fn options(msg: &[u8]) -> Option<&[u8]> {
+    if msg.len() < DHCP_OPTIONS_OFFSET
+        || msg[DHCP_FIXED_LEN..DHCP_OPTIONS_OFFSET] != DHCP_MAGIC_COOKIE
+    {
+        return None;
+    }
+    Some(&msg[DHCP_OPTIONS_OFFSET..])
+}
+
+// Returns the offset of `part` in `msg`, `part` must be a subslice of `msg`.
+fn offset_of(msg: &[u8], part: &[u8]) -> usize {
+    part.as_ptr() as usize - msg.as_ptr() as usize
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // A DHCPDISCOVER from 00:0b:86:64:8b:a0, with the message type and the
+    // parameter request list options, the end option and 3 bytes of padding.
+    fn discover() -> ([u8; 512], usize) {
+        let mut buf = [0xee; 512];
+        buf[..DHCP_FIXED_LEN].fill(0);
+        buf[..4].copy_from_slice(&[1, 1, 6, 0]);
+        buf[4..8].copy_from_slice(&[0x39, 0x03, 0xf3, 0x26]);
+        buf[28..34].copy_from_slice(&[0x00, 0x0b, 0x86, 0x64, 0x8b, 0xa0]);
+        buf[DHCP_FIXED_LEN..DHCP_OPTIONS_OFFSET].copy_from_slice(&DHCP_MAGIC_COOKIE);
+        let options = [53, 1, 1, 55, 3, 1, 3, 6, 255, 0, 0, 0];
+        buf[DHCP_OPTIONS_OFFSET..DHCP_OPTIONS_OFFSET + options.len()].copy_from_slice(&options);
+        (buf, DHCP_OPTIONS_OFFSET + options.len())
+    }
+
+    #[test]
+    fn relay_roundtrip() {
+        let (orig, len) = discover();
+        let mut buf = orig;
+        assert_eq!(giaddr(&buf), Ipv4Addr::new(0, 0, 0, 0));
+        set_giaddr(&mut buf, Ipv4Addr::new(192, 0, 2, 1));
+        let hop_count = hops(&buf);
+        set_hops(&mut buf, hop_count + 1);
+        assert_eq!(giaddr(&buf), Ipv4Addr::new(192, 0, 2, 1));
+        assert_eq!(buf[3], 1);
+
+        let sub_options = [
+            (CIRCUIT_ID, &[0, 4, 0, 1, 0, 5][..]),
+            (REMOTE_ID, &b"ab"[..]),
+        ];
+        let new_len = insert_relay_agent_info(&mut buf, len, &sub_options).unwrap();
+        assert_eq!(new_len, len + 14);
+
+        // The option is inserted in front of the end option and the padding.
+        let end = DHCP_OPTIONS_OFFSET + 8;
+        assert_eq!(
+            &buf[end..new_len],
+            &[82, 12, 1, 6, 0, 4, 0, 1, 0, 5, 2, 2, b'a', b'b', 255, 0, 0, 0]
+        );
+        assert_eq!(buf[new_len], 0xee);
+
+        let info = relay_agent_info(&buf[..new_len]).unwrap();
+        let mut iter = TlvIter::new(info, RELAY_SUB_OPTIONS);
+        let circuit = iter.next().unwrap();
+        assert_eq!(circuit.tlv_type, u32::from(CIRCUIT_ID));
+        assert_eq!(circuit.value, &[0, 4, 0, 1, 0, 5]);
+        assert_eq!(iter.next().unwrap().value, b"ab");
+        assert!(iter.next().is_none() && iter.is_valid());
+
+        // The option can only be inserted once.
+        assert_eq!(
+            insert_relay_agent_info(&mut buf, new_len, &sub_options),
+            None
+        );
+
+        assert_eq!(strip_relay_agent_info(&mut buf, new_len), Some(len));
+        assert_eq!(&buf[DHCP_FIXED_LEN..len], &orig[DHCP_FIXED_LEN..len]);
+        assert_eq!(relay_agent_info(&buf[..len]), None);
+        assert_eq!(strip_relay_agent_info(&mut buf, len), None);
+    }
+
+    #[test]
+    fn insert_without_end() {
+        let (mut buf, _) = discover();
+        let len = DHCP_OPTIONS_OFFSET + 3;
+        let new_len = insert_relay_agent_info(&mut buf, len, &[(REMOTE_ID, &[7][..])]).unwrap();
+        assert_eq!(new_len, len + 6);
+        assert_eq!(&buf[len..new_len], &[82, 3, 2, 1, 7, 255]);
+        assert!(relay_agent_info(&buf[..new_len]).is_some());
+    }
+
+    #[test]
+    fn insert_failures() {
+        let (orig, len) = discover();
+
+        // The buffer is too short.
+        let mut buf = orig;
+        assert_eq!(
+            insert_relay_agent_info(&mut buf[..len + 3], len, &[(1, &[0; 4][..])]),
+            None
+        );
+        assert_eq!(buf, orig);
+
+        // The sub-options are too long.
+        let value = [0; 254];
+        assert_eq!(
+            insert_relay_agent_info(&mut buf, len, &[(1, &value[..])]),
+            None
+        );
+
+        // A malformed option, or a missing magic cookie.
+        buf[DHCP_OPTIONS_OFFSET + 1] = 100;
+        assert_eq!(insert_relay_agent_info(&mut buf, len, &[]), None);
+        let mut buf = orig;
+        buf[DHCP_FIXED_LEN] = 0;
+        assert_eq!(insert_relay_agent_info(&mut buf, len, &[]), None);
+        assert_eq!(relay_agent_info(&buf[..len]), None);
+    }
+}
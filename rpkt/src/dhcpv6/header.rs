@@ -0,0 +1,163 @@
+use byteorder::{ByteOrder, NetworkEndian};
+
+use crate::ipv6::Ipv6Addr;
+
+use super::MsgType;
+use super::{hop_count, link_addr, msg_type, peer_addr, transaction_id};
+use super::{hop_count_mut, link_addr_mut, msg_type_mut, peer_addr_mut, transaction_id_mut};
+
+/// The length of the header of the messages between clients and servers.
+pub const DHCPV6_HEADER_LEN: usize = 4;
+
+pub const DHCPV6_HEADER_TEMPLATE: Dhcpv6Header<[u8; DHCPV6_HEADER_LEN]> = Dhcpv6Header {
+    buf: [0x01, 0x00, 0x00, 0x00],
+};
+
+/// The length of the header of the messages between relay agents and servers.
+pub const DHCPV6_RELAY_HEADER_LEN: usize = 34;
+
+pub const DHCPV6_RELAY_HEADER_TEMPLATE: Dhcpv6RelayHeader<[u8; DHCPV6_RELAY_HEADER_LEN]> =
+    Dhcpv6RelayHeader {
+        buf: [
+            0x0c, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+            0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+            0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+        ],
+    };
+
+#[derive(Clone, Copy, Debug)]
+pub struct Dhcpv6Header<T> {
+    buf: T,
+}
+
+impl<T: AsRef<[u8]>> Dhcpv6Header<T> {
+    #[inline]
+    pub fn new(buf: T) -> Result<Self, T> {
+        if buf.as_ref().len() >= DHCPV6_HEADER_LEN {
+            Ok(Self { buf })
+        } else {
+            Err(buf)
+        }
+    }
+
+    #[inline]
+    pub fn new_unchecked(buf: T) -> Self {
+        Self { buf }
+    }
+
+    #[inline]
+    pub fn as_bytes(&self) -> &[u8] {
+        &self.buf.as_ref()[0..DHCPV6_HEADER_LEN]
+    }
+
+    #[inline]
+    pub fn to_owned(&self) -> Dhcpv6Header<[u8; DHCPV6_HEADER_LEN]> {
+        let mut buf = [0; DHCPV6_HEADER_LEN];
+        buf.copy_from_slice(self.as_bytes());
+        Dhcpv6Header { buf }
+    }
+
+    #[inline]
+    pub fn msg_type(&self) -> MsgType {
+        (*msg_type(self.buf.as_ref())).into()
+    }
+
+    #[inline]
+    pub fn transaction_id(&self) -> u32 {
+        let data = transaction_id(self.buf.as_ref());
+        NetworkEndian::read_u24(data)
+    }
+}
+
+impl<T: AsMut<[u8]>> Dhcpv6Header<T> {
+    #[inline]
+    pub fn set_msg_type(&mut self, value: MsgType) {
+        *msg_type_mut(self.buf.as_mut()) = value.into();
+    }
+
+    #[inline]
+    pub fn set_transaction_id(&mut self, value: u32) {
+        assert!(value <= 0xffffff);
+        let data = transaction_id_mut(self.buf.as_mut());
+        NetworkEndian::write_u24(data, value)
+    }
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct Dhcpv6RelayHeader<T> {
+    buf: T,
+}
+
+impl<T: AsRef<[u8]>> Dhcpv6RelayHeader<T> {
+    #[inline]
+    pub fn new(buf: T) -> Result<Self, T> {
+        if buf.as_ref().len() >= DHCPV6_RELAY_HEADER_LEN {
+            Ok(Self { buf })
+        } else {
+            Err(buf)
+        }
+    }
+
+    #[inline]
+    pub fn new_unchecked(buf: T) -> Self {
+        Self { buf }
+    }
+
+    #[inline]
+    pub fn as_bytes(&self) -> &[u8] {
+        &self.buf.as_ref()[0..DHCPV6_RELAY_HEADER_LEN]
+    }
+
+    #[inline]
+    pub fn to_owned(&self) -> Dhcpv6RelayHeader<[u8; DHCPV6_RELAY_HEADER_LEN]> {
+        let mut buf = [0; DHCPV6_RELAY_HEADER_LEN];
+        buf.copy_from_slice(self.as_bytes());
+        Dhcpv6RelayHeader { buf }
+    }
+
+    #[inline]
+    pub fn msg_type(&self) -> MsgType {
+        (*msg_type(self.buf.as_ref())).into()
+    }
+
+    #[inline]
+    pub fn hop_count(&self) -> u8 {
+        *hop_count(self.buf.as_ref())
+    }
+
+    #[inline]
+    pub fn link_addr(&self) -> Ipv6Addr {
+        let data = link_addr(self.buf.as_ref());
+        Ipv6Addr::from_bytes(data)
+    }
+
+    #[inline]
+    pub fn peer_addr(&self) -> Ipv6Addr {
+        let data = peer_addr(self.buf.as_ref());
+        Ipv6Addr::from_bytes(data)
+    }
+}
+
+impl<T: AsMut<[u8]>> Dhcpv6RelayHeader<T> {
+    #[inline]
+    pub fn set_msg_type(&mut self, value: MsgType) {
+        *msg_type_mut(self.buf.as_mut()) = value.into();
+    }
+
+    #[inline]
+    pub fn set_hop_count(&mut self, value: u8) {
+        *hop_count_mut(self.buf.as_mut()) = value;
+    }
+
+    #[inline]
+    pub fn set_link_addr(&mut self, value: &Ipv6Addr) {
+        let data = link_addr_mut(self.buf.as_mut());
+        data.copy_from_slice(value.as_bytes());
+    }
+
+    #[inline]
+    pub fn set_peer_addr(&mut self, value: &Ipv6Addr) {
+        let data = peer_addr_mut(self.buf.as_mut());
+        data.copy_from_slice(value.as_bytes());
+    }
+}
@@ -0,0 +1,68 @@
+enum_sim! {
+    /// The message type of DHCPv6 (RFC 8415).
+    ///
+    /// See https://www.iana.org/assignments/dhcpv6-parameters/dhcpv6-parameters.xhtml
+    pub struct MsgType (u8) {
+        SOLICIT = 1,
+        ADVERTISE = 2,
+        REQUEST = 3,
+        CONFIRM = 4,
+        RENEW = 5,
+        REBIND = 6,
+        REPLY = 7,
+        RELEASE = 8,
+        DECLINE = 9,
+        RECONFIGURE = 10,
+        INFORMATION_REQUEST = 11,
+        RELAY_FORW = 12,
+        RELAY_REPL = 13,
+    }
+}
+
+impl MsgType {
+    /// Returns whether the message is exchanged between relay agents and
+    /// servers, which uses the relay message format.
+    #[inline]
+    pub fn is_relay(&self) -> bool {
+        *self == MsgType::RELAY_FORW || *self == MsgType::RELAY_REPL
+    }
+}
+
+enum_sim! {
+    /// The status code carried by the status code option.
+    pub struct StatusCode (u16) {
+        SUCCESS = 0,
+        UNSPEC_FAIL = 1,
+        NO_ADDRS_AVAIL = 2,
+        NO_BINDING = 3,
+        NOT_ON_LINK = 4,
+        USE_MULTICAST = 5,
+        NO_PREFIX_AVAIL = 6,
+    }
+}
+
+header_field_range_accessors! {
+    (transaction_id, transaction_id_mut, 1..4),
+    (link_addr, link_addr_mut, 2..18),
+    (peer_addr, peer_addr_mut, 18..34),
+}
+
+header_field_val_accessors! {
+    (msg_type, msg_type_mut, 0),
+    (hop_count, hop_count_mut, 1),
+}
+
+mod header;
+pub use header::{
+    Dhcpv6Header, Dhcpv6RelayHeader, DHCPV6_HEADER_LEN, DHCPV6_HEADER_TEMPLATE,
+    DHCPV6_RELAY_HEADER_LEN, DHCPV6_RELAY_HEADER_TEMPLATE,
+};
+
+mod packet;
+pub use packet::{Dhcpv6Packet, Dhcpv6RelayPacket};
+
+mod option;
+pub use option::{
+    Dhcpv6Option, Dhcpv6OptionDuid, Dhcpv6OptionIaAddr, Dhcpv6OptionIaNa, Dhcpv6OptionIter,
+    Dhcpv6OptionIterMut, Dhcpv6OptionMut, Dhcpv6OptionStatusCode, Dhcpv6OptionWriter,
+};
@@ -0,0 +1,390 @@
+use byteorder::{ByteOrder, NetworkEndian};
+
+use crate::ipv6::Ipv6Addr;
+
+use super::StatusCode;
+
+const CLIENT_ID: u16 = 1;
+const SERVER_ID: u16 = 2;
+const IA_NA: u16 = 3;
+const IAADDR: u16 = 5;
+const RELAY_MSG: u16 = 9;
+const STATUS_CODE: u16 = 13;
+
+// The option code and the option length.
+const OPTION_HEADER_LEN: usize = 4;
+
+// The lengths of the fixed parts of the option data.
+const IA_NA_FIXED_LEN: usize = 12;
+const IAADDR_FIXED_LEN: usize = 24;
+const STATUS_CODE_FIXED_LEN: usize = 2;
+const DUID_TYPE_LEN: usize = 2;
+
+pub enum Dhcpv6Option<'a> {
+    ClientId(Dhcpv6OptionDuid<&'a [u8]>),
+    ServerId(Dhcpv6OptionDuid<&'a [u8]>),
+    IaNa(Dhcpv6OptionIaNa<&'a [u8]>),
+    IaAddr(Dhcpv6OptionIaAddr<&'a [u8]>),
+    StatusCode(Dhcpv6OptionStatusCode<&'a [u8]>),
+    /// The message relayed by a relay agent or a server.
+    RelayMsg(&'a [u8]),
+    /// The option code and data of an option without a dedicated container.
+    Other(u16, &'a [u8]),
+}
+
+pub enum Dhcpv6OptionMut<'a> {
+    ClientId(Dhcpv6OptionDuid<&'a mut [u8]>),
+    ServerId(Dhcpv6OptionDuid<&'a mut [u8]>),
+    IaNa(Dhcpv6OptionIaNa<&'a mut [u8]>),
+    IaAddr(Dhcpv6OptionIaAddr<&'a mut [u8]>),
+    StatusCode(Dhcpv6OptionStatusCode<&'a mut [u8]>),
+    RelayMsg(&'a mut [u8]),
+    Other(u16, &'a mut [u8]),
+}
+
+/// The client identifier or the server identifier option, carrying a DUID.
+pub struct Dhcpv6OptionDuid<T> {
+    buf: T,
+}
+
+impl<T: AsRef<[u8]>> Dhcpv6OptionDuid<T> {
+    #[inline]
+    pub fn duid_type(&self) -> u16 {
+        NetworkEndian::read_u16(&self.buf.as_ref()[4..6])
+    }
+
+    /// Returns the whole DUID, starting from the DUID type.
+    #[inline]
+    pub fn duid(&self) -> &[u8] {
+        &self.buf.as_ref()[4..]
+    }
+}
+
+impl<T: AsMut<[u8]>> Dhcpv6OptionDuid<T> {
+    #[inline]
+    pub fn set_duid(&mut self, duid: &[u8]) {
+        self.buf.as_mut()[4..].copy_from_slice(duid);
+    }
+}
+
+/// The identity association for non-temporary addresses option.
+pub struct Dhcpv6OptionIaNa<T> {
+    buf: T,
+}
+
+impl<T: AsRef<[u8]>> Dhcpv6OptionIaNa<T> {
+    #[inline]
+    pub fn iaid(&self) -> u32 {
+        NetworkEndian::read_u32(&self.buf.as_ref()[4..8])
+    }
+
+    #[inline]
+    pub fn t1(&self) -> u32 {
+        NetworkEndian::read_u32(&self.buf.as_ref()[8..12])
+    }
+
+    #[inline]
+    pub fn t2(&self) -> u32 {
+        NetworkEndian::read_u32(&self.buf.as_ref()[12..16])
+    }
+
+    /// Returns the options encapsulated in this option.
+    #[inline]
+    pub fn option_bytes(&self) -> &[u8] {
+        &self.buf.as_ref()[16..]
+    }
+}
+
+impl<T: AsMut<[u8]>> Dhcpv6OptionIaNa<T> {
+    #[inline]
+    pub fn set_iaid(&mut self, value: u32) {
+        NetworkEndian::write_u32(&mut self.buf.as_mut()[4..8], value);
+    }
+
+    #[inline]
+    pub fn set_t1(&mut self, value: u32) {
+        NetworkEndian::write_u32(&mut self.buf.as_mut()[8..12], value);
+    }
+
+    #[inline]
+    pub fn set_t2(&mut self, value: u32) {
+        NetworkEndian::write_u32(&mut self.buf.as_mut()[12..16], value);
+    }
+
+    #[inline]
+    pub fn option_bytes_mut(&mut self) -> &mut [u8] {
+        &mut self.buf.as_mut()[16..]
+    }
+}
+
+/// The IA address option.
+pub struct Dhcpv6OptionIaAddr<T> {
+    buf: T,
+}
+
+impl<T: AsRef<[u8]>> Dhcpv6OptionIaAddr<T> {
+    #[inline]
+    pub fn addr(&self) -> Ipv6Addr {
+        Ipv6Addr::from_bytes(&self.buf.as_ref()[4..20])
+    }
+
+    #[inline]
+    pub fn preferred_lifetime(&self) -> u32 {
+        NetworkEndian::read_u32(&self.buf.as_ref()[20..24])
+    }
+
+    #[inline]
+    pub fn valid_lifetime(&self) -> u32 {
+        NetworkEndian::read_u32(&self.buf.as_ref()[24..28])
+    }
+
+    /// Returns the options encapsulated in this option.
+    #[inline]
+    pub fn option_bytes(&self) -> &[u8] {
+        &self.buf.as_ref()[28..]
+    }
+}
+
+impl<T: AsMut<[u8]>> Dhcpv6OptionIaAddr<T> {
+    #[inline]
+    pub fn set_addr(&mut self, value: &Ipv6Addr) {
+        self.buf.as_mut()[4..20].copy_from_slice(value.as_bytes());
+    }
+
+    #[inline]
+    pub fn set_preferred_lifetime(&mut self, value: u32) {
+        NetworkEndian::write_u32(&mut self.buf.as_mut()[20..24], value);
+    }
+
+    #[inline]
+    pub fn set_valid_lifetime(&mut self, value: u32) {
+        NetworkEndian::write_u32(&mut self.buf.as_mut()[24..28], value);
+    }
+
+    #[inline]
+    pub fn option_bytes_mut(&mut self) -> &mut [u8] {
+        &mut self.buf.as_mut()[28..]
+    }
+}
+
+pub struct Dhcpv6OptionStatusCode<T> {
+    buf: T,
+}
+
+impl<T: AsRef<[u8]>> Dhcpv6OptionStatusCode<T> {
+    #[inline]
+    pub fn status_code(&self) -> StatusCode {
+        NetworkEndian::read_u16(&self.buf.as_ref()[4..6]).into()
+    }
+
+    /// Returns the UTF-8 encoded status message.
+    #[inline]
+    pub fn status_msg(&self) -> &[u8] {
+        &self.buf.as_ref()[6..]
+    }
+}
+
+impl<T: AsMut<[u8]>> Dhcpv6OptionStatusCode<T> {
+    #[inline]
+    pub fn set_status_code(&mut self, value: StatusCode) {
+        NetworkEndian::write_u16(&mut self.buf.as_mut()[4..6], value.into());
+    }
+
+    #[inline]
+    pub fn set_status_msg(&mut self, msg: &[u8]) {
+        self.buf.as_mut()[6..].copy_from_slice(msg);
+    }
+}
+
+// Returns the code and the total length of the first option of `buf`, if the
+// option is complete and at least as long as the fixed part of its data.
+#[inline]
+fn option_code_len(buf: &[u8]) -> Option<(u16, usize)> {
+    if buf.len() < OPTION_HEADER_LEN {
+        return None;
+    }
+
+    let code = NetworkEndian::read_u16(&buf[0..2]);
+    let data_len = usize::from(NetworkEndian::read_u16(&buf[2..4]));
+    let min_len = match code {
+        CLIENT_ID | SERVER_ID => DUID_TYPE_LEN,
+        IA_NA => IA_NA_FIXED_LEN,
+        IAADDR => IAADDR_FIXED_LEN,
+        STATUS_CODE => STATUS_CODE_FIXED_LEN,
+        _ => 0,
+    };
+    let opt_len = OPTION_HEADER_LEN + data_len;
+    if data_len < min_len || buf.len() < opt_len {
+        None
+    } else {
+        Some((code, opt_len))
+    }
+}
+
+pub struct Dhcpv6OptionIter<'a> {
+    buf: &'a [u8],
+    valid: bool,
+}
+
+impl<'a> Dhcpv6OptionIter<'a> {
+    #[inline]
+    pub fn from_option_bytes(buf: &'a [u8]) -> Dhcpv6OptionIter<'a> {
+        Self { buf, valid: true }
+    }
+
+    #[inline]
+    pub fn check_option_bytes(buf: &'a [u8]) -> bool {
+        let mut reader = Self::from_option_bytes(buf);
+        reader.by_ref().for_each(drop);
+        reader.valid
+    }
+}
+
+impl<'a> Iterator for Dhcpv6OptionIter<'a> {
+    type Item = Dhcpv6Option<'a>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if !self.valid || self.buf.is_empty() {
+            return None;
+        }
+
+        let Some((code, opt_len)) = option_code_len(self.buf) else {
+            self.valid = false;
+            return None;
+        };
+        let (buf, remaining) = self.buf.split_at(opt_len);
+        self.buf = remaining;
+
+        let opt = match code {
+            CLIENT_ID => Dhcpv6Option::ClientId(Dhcpv6OptionDuid { buf }),
+            SERVER_ID => Dhcpv6Option::ServerId(Dhcpv6OptionDuid { buf }),
+            IA_NA => Dhcpv6Option::IaNa(Dhcpv6OptionIaNa { buf }),
+            IAADDR => Dhcpv6Option::IaAddr(Dhcpv6OptionIaAddr { buf }),
+            STATUS_CODE => Dhcpv6Option::StatusCode(Dhcpv6OptionStatusCode { buf }),
+            RELAY_MSG => Dhcpv6Option::RelayMsg(&buf[OPTION_HEADER_LEN..]),
+            _ => Dhcpv6Option::Other(code, &buf[OPTION_HEADER_LEN..]),
+        };
+        Some(opt)
+    }
+}
+
+pub struct Dhcpv6OptionIterMut<'a> {
+    buf: &'a mut [u8],
+    valid: bool,
+}
+
+impl<'a> Dhcpv6OptionIterMut<'a> {
+    #[inline]
+    pub fn from_option_bytes_mut(buf: &'a mut [u8]) -> Dhcpv6OptionIterMut<'a> {
+        Self { buf, valid: true }
+    }
+}
+
+impl<'a> Iterator for Dhcpv6OptionIterMut<'a> {
+    type Item = Dhcpv6OptionMut<'a>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if !self.valid || self.buf.is_empty() {
+            return None;
+        }
+
+        let Some((code, opt_len)) = option_code_len(self.buf) else {
+            self.valid = false;
+            return None;
+        };
+        let (buf, remaining) = std::mem::take(&mut self.buf).split_at_mut(opt_len);
+        self.buf = remaining;
+
+        let opt = match code {
+            CLIENT_ID => Dhcpv6OptionMut::ClientId(Dhcpv6OptionDuid { buf }),
+            SERVER_ID => Dhcpv6OptionMut::ServerId(Dhcpv6OptionDuid { buf }),
+            IA_NA => Dhcpv6OptionMut::IaNa(Dhcpv6OptionIaNa { buf }),
+            IAADDR => Dhcpv6OptionMut::IaAddr(Dhcpv6OptionIaAddr { buf }),
+            STATUS_CODE => Dhcpv6OptionMut::StatusCode(Dhcpv6OptionStatusCode { buf }),
+            RELAY_MSG => Dhcpv6OptionMut::RelayMsg(&mut buf[OPTION_HEADER_LEN..]),
+            _ => Dhcpv6OptionMut::Other(code, &mut buf[OPTION_HEADER_LEN..]),
+        };
+        Some(opt)
+    }
+}
+
+pub struct Dhcpv6OptionWriter<'a> {
+    buf: &'a mut [u8],
+    written: usize,
+}
+
+impl<'a> Dhcpv6OptionWriter<'a> {
+    pub fn client_id(&mut self, duid_len: usize) -> Dhcpv6OptionDuid<&'a mut [u8]> {
+        assert!(duid_len >= DUID_TYPE_LEN);
+        let buf = self.option(CLIENT_ID, duid_len);
+        Dhcpv6OptionDuid { buf }
+    }
+
+    pub fn server_id(&mut self, duid_len: usize) -> Dhcpv6OptionDuid<&'a mut [u8]> {
+        assert!(duid_len >= DUID_TYPE_LEN);
+        let buf = self.option(SERVER_ID, duid_len);
+        Dhcpv6OptionDuid { buf }
+    }
+
+    /// Write an IA_NA option with room for `options_len` bytes of
+    /// encapsulated options.
+    pub fn ia_na(&mut self, options_len: usize) -> Dhcpv6OptionIaNa<&'a mut [u8]> {
+        let buf = self.option(IA_NA, IA_NA_FIXED_LEN + options_len);
+        Dhcpv6OptionIaNa { buf }
+    }
+
+    /// Write an IA address option with room for `options_len` bytes of
+    /// encapsulated options.
+    pub fn ia_addr(&mut self, options_len: usize) -> Dhcpv6OptionIaAddr<&'a mut [u8]> {
+        let buf = self.option(IAADDR, IAADDR_FIXED_LEN + options_len);
+        Dhcpv6OptionIaAddr { buf }
+    }
+
+    pub fn status_code(&mut self, msg_len: usize) -> Dhcpv6OptionStatusCode<&'a mut [u8]> {
+        let buf = self.option(STATUS_CODE, STATUS_CODE_FIXED_LEN + msg_len);
+        Dhcpv6OptionStatusCode { buf }
+    }
+
+    /// Write a relay message option and return the room for the relayed
+    /// message of `msg_len` bytes.
+    pub fn relay_msg(&mut self, msg_len: usize) -> &'a mut [u8] {
+        &mut self.option(RELAY_MSG, msg_len)[OPTION_HEADER_LEN..]
+    }
+
+    /// Write an option of `code` carrying `data`.
+    pub fn other(&mut self, code: u16, data: &[u8]) {
+        self.option(code, data.len())[OPTION_HEADER_LEN..].copy_from_slice(data);
+    }
+
+    #[inline]
+    pub fn from_option_bytes_mut(buf: &'a mut [u8]) -> Self {
+        Self { buf, written: 0 }
+    }
+
+    #[inline]
+    pub fn remaining_bytes(&self) -> usize {
+        self.buf.len()
+    }
+
+    /// Returns the number of bytes written so far.
+    #[inline]
+    pub fn written_bytes(&self) -> usize {
+        self.written
+    }
+
+    // Write the header of an option with `data_len` bytes of zeroed data.
+    #[inline]
+    fn option(&mut self, code: u16, data_len: usize) -> &'a mut [u8] {
+        assert!(data_len <= usize::from(u16::MAX));
+
+        let (buf, remaining) =
+            std::mem::take(&mut self.buf).split_at_mut(OPTION_HEADER_LEN + data_len);
+        self.buf = remaining;
+        self.written += buf.len();
+
+        NetworkEndian::write_u16(&mut buf[0..2], code);
+        NetworkEndian::write_u16(&mut buf[2..4], data_len as u16);
+        buf[OPTION_HEADER_LEN..].fill(0);
+        buf
+    }
+}
@@ -0,0 +1,287 @@
+use bytes::Buf;
+
+use crate::ipv6::Ipv6Addr;
+use crate::PktMut;
+
+use super::header::{Dhcpv6Header, Dhcpv6RelayHeader, DHCPV6_HEADER_LEN, DHCPV6_RELAY_HEADER_LEN};
+use super::MsgType;
+
+packet_base! {
+    /// A DHCPv6 message exchanged between clients and servers.
+    pub struct Dhcpv6Packet: Dhcpv6Header {
+        header_len: DHCPV6_HEADER_LEN,
+        get_methods: [
+            (msg_type, MsgType),
+            (transaction_id, u32),
+        ],
+        set_methods: [
+            (set_transaction_id, value: u32),
+        ],
+        unchecked_set_methods: [
+            (set_msg_type_unchecked, set_msg_type, value: MsgType),
+        ]
+    }
+}
+
+impl<T: Buf> Dhcpv6Packet<T> {
+    #[inline]
+    pub fn parse(buf: T) -> Result<Dhcpv6Packet<T>, T> {
+        traced_parse!("dhcpv6", buf, |_: &Self| DHCPV6_HEADER_LEN, {
+            if buf.chunk().len() < DHCPV6_HEADER_LEN {
+                return Err(buf);
+            }
+
+            let packet = Dhcpv6Packet::parse_unchecked(buf);
+            if packet.msg_type().is_relay() {
+                return Err(packet.release());
+            }
+
+            Ok(packet)
+        })
+    }
+
+    /// Returns the options in the first chunk of the message.
+    #[inline]
+    pub fn option_bytes(&self) -> &[u8] {
+        &self.buf.chunk()[DHCPV6_HEADER_LEN..]
+    }
+}
+
+impl<T: PktMut> Dhcpv6Packet<T> {
+    #[inline]
+    pub fn option_bytes_mut(&mut self) -> &mut [u8] {
+        &mut self.buf.chunk_mut()[DHCPV6_HEADER_LEN..]
+    }
+
+    /// Prepend the message header to the options in `buf`.
+    ///
+    /// # Panics
+    ///
+    /// This function panics if `header` is a relay message type.
+    #[inline]
+    pub fn prepend_header<HT: AsRef<[u8]>>(
+        mut buf: T,
+        header: &Dhcpv6Header<HT>,
+    ) -> Dhcpv6Packet<T> {
+        assert!(!header.msg_type().is_relay() && buf.chunk_headroom() >= DHCPV6_HEADER_LEN);
+        buf.move_back(DHCPV6_HEADER_LEN);
+
+        let data = &mut buf.chunk_mut()[0..DHCPV6_HEADER_LEN];
+        data.copy_from_slice(header.as_bytes());
+
+        Dhcpv6Packet::parse_unchecked(buf)
+    }
+}
+
+packet_base! {
+    /// A DHCPv6 message exchanged between relay agents and servers.
+    pub struct Dhcpv6RelayPacket: Dhcpv6RelayHeader {
+        header_len: DHCPV6_RELAY_HEADER_LEN,
+        get_methods: [
+            (msg_type, MsgType),
+            (hop_count, u8),
+            (link_addr, Ipv6Addr),
+            (peer_addr, Ipv6Addr),
+        ],
+        set_methods: [
+            (set_hop_count, value: u8),
+            (set_link_addr, value: &Ipv6Addr),
+            (set_peer_addr, value: &Ipv6Addr),
+        ],
+        unchecked_set_methods: [
+            (set_msg_type_unchecked, set_msg_type, value: MsgType),
+        ]
+    }
+}
+
+impl<T: Buf> Dhcpv6RelayPacket<T> {
+    #[inline]
+    pub fn parse(buf: T) -> Result<Dhcpv6RelayPacket<T>, T> {
+        traced_parse!("dhcpv6_relay", buf, |_: &Self| DHCPV6_RELAY_HEADER_LEN, {
+            if buf.chunk().len() < DHCPV6_RELAY_HEADER_LEN {
+                return Err(buf);
+            }
+
+            let packet = Dhcpv6RelayPacket::parse_unchecked(buf);
+            if !packet.msg_type().is_relay() {
+                return Err(packet.release());
+            }
+
+            Ok(packet)
+        })
+    }
+
+    /// Returns the options in the first chunk of the message, which include
+    /// the relayed message.
+    #[inline]
+    pub fn option_bytes(&self) -> &[u8] {
+        &self.buf.chunk()[DHCPV6_RELAY_HEADER_LEN..]
+    }
+}
+
+impl<T: PktMut> Dhcpv6RelayPacket<T> {
+    #[inline]
+    pub fn option_bytes_mut(&mut self) -> &mut [u8] {
+        &mut self.buf.chunk_mut()[DHCPV6_RELAY_HEADER_LEN..]
+    }
+
+    /// Prepend the relay message header to the options in `buf`.
+    ///
+    /// # Panics
+    ///
+    /// This function panics if `header` is not a relay message type.
+    #[inline]
+    pub fn prepend_header<HT: AsRef<[u8]>>(
+        mut buf: T,
+        header: &Dhcpv6RelayHeader<HT>,
+    ) -> Dhcpv6RelayPacket<T> {
+        assert!(header.msg_type().is_relay() && buf.chunk_headroom() >= DHCPV6_RELAY_HEADER_LEN);
+        buf.move_back(DHCPV6_RELAY_HEADER_LEN);
+
+        let data = &mut buf.chunk_mut()[0..DHCPV6_RELAY_HEADER_LEN];
+        data.copy_from_slice(header.as_bytes());
+
+        Dhcpv6RelayPacket::parse_unchecked(buf)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::dhcpv6::*;
+    use crate::{Cursor, CursorMut};
+
+    // A solicit message with a client identifier, an empty IA_NA and an
+    // elapsed time option.
+    static SOLICIT: [u8; 44] = [
+        0x01, 0x12, 0x34, 0x56, 0x00, 0x01, 0x00, 0x0a, 0x00, 0x03, 0x00, 0x01, 0x02, 0x00, 0x00,
+        0x00, 0x00, 0x01, 0x00, 0x03, 0x00, 0x0c, 0x00, 0x00, 0x00, 0x01, 0x00, 0x00, 0x0e, 0x10,
+        0x00, 0x00, 0x15, 0x18, 0x00, 0x08, 0x00, 0x02, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+    ];
+
+    #[test]
+    fn parse_solicit() {
+        let pkt = Dhcpv6Packet::parse(Cursor::new(&SOLICIT[..40])).unwrap();
+        assert_eq!(pkt.msg_type(), MsgType::SOLICIT);
+        assert_eq!(pkt.transaction_id(), 0x123456);
+
+        let mut options = Dhcpv6OptionIter::from_option_bytes(pkt.option_bytes());
+        match options.next() {
+            Some(Dhcpv6Option::ClientId(opt)) => {
+                assert_eq!(opt.duid_type(), 3);
+                assert_eq!(opt.duid(), &SOLICIT[8..18]);
+            }
+            _ => panic!("no client identifier"),
+        }
+        match options.next() {
+            Some(Dhcpv6Option::IaNa(opt)) => {
+                assert_eq!((opt.iaid(), opt.t1(), opt.t2()), (1, 3600, 5400));
+                assert!(opt.option_bytes().is_empty());
+            }
+            _ => panic!("no ia_na"),
+        }
+        match options.next() {
+            Some(Dhcpv6Option::Other(8, data)) => assert_eq!(data, &[0, 0]),
+            _ => panic!("no elapsed time"),
+        }
+        assert!(options.next().is_none());
+        assert!(Dhcpv6OptionIter::check_option_bytes(pkt.option_bytes()));
+
+        // A truncated option, and a relay message type.
+        assert!(!Dhcpv6OptionIter::check_option_bytes(&SOLICIT[4..42]));
+        assert!(!Dhcpv6OptionIter::check_option_bytes(&[
+            0x00, 0x03, 0x00, 0x04, 0, 0, 0, 0
+        ]));
+        let mut bytes = SOLICIT;
+        bytes[0] = MsgType::RELAY_FORW.into();
+        assert!(Dhcpv6Packet::parse(Cursor::new(&bytes[..])).is_err());
+    }
+
+    #[test]
+    fn build_reply() {
+        let addr = Ipv6Addr::new(0x2001, 0xdb8, 0, 0, 0, 0, 0, 0x100);
+        let mut frame = [0xff; 100];
+        let mut writer = Dhcpv6OptionWriter::from_option_bytes_mut(&mut frame[DHCPV6_HEADER_LEN..]);
+        writer.server_id(4).set_duid(&[0x00, 0x04, 0xab, 0xcd]);
+        let mut ia_na = writer.ia_na(28 + 8);
+        ia_na.set_iaid(1);
+        let mut nested = Dhcpv6OptionWriter::from_option_bytes_mut(ia_na.option_bytes_mut());
+        let mut ia_addr = nested.ia_addr(0);
+        ia_addr.set_addr(&addr);
+        ia_addr.set_valid_lifetime(7200);
+        nested.status_code(2).set_status_msg(b"ok");
+        assert_eq!(nested.remaining_bytes(), 0);
+        let len = DHCPV6_HEADER_LEN + writer.written_bytes();
+        assert_eq!(len, 4 + 8 + 16 + 28 + 8);
+
+        let mut buf = CursorMut::new(&mut frame[..len]);
+        buf.advance(DHCPV6_HEADER_LEN);
+        let mut header = DHCPV6_HEADER_TEMPLATE;
+        header.set_msg_type(MsgType::REPLY);
+        header.set_transaction_id(0xabcdef);
+        Dhcpv6Packet::prepend_header(buf, &header);
+
+        let pkt = Dhcpv6Packet::parse(Cursor::new(&frame[..len])).unwrap();
+        assert_eq!(pkt.msg_type(), MsgType::REPLY);
+        assert_eq!(pkt.transaction_id(), 0xabcdef);
+        let mut options = Dhcpv6OptionIter::from_option_bytes(pkt.option_bytes());
+        assert!(matches!(options.next(), Some(Dhcpv6Option::ServerId(_))));
+        let ia_na = match options.next() {
+            Some(Dhcpv6Option::IaNa(opt)) => opt,
+            _ => panic!("no ia_na"),
+        };
+        assert_eq!((ia_na.iaid(), ia_na.t1()), (1, 0));
+        let mut nested = Dhcpv6OptionIter::from_option_bytes(ia_na.option_bytes());
+        match nested.next() {
+            Some(Dhcpv6Option::IaAddr(opt)) => {
+                assert_eq!(opt.addr(), addr);
+                assert_eq!((opt.preferred_lifetime(), opt.valid_lifetime()), (0, 7200));
+            }
+            _ => panic!("no ia address"),
+        }
+        match nested.next() {
+            Some(Dhcpv6Option::StatusCode(opt)) => {
+                assert_eq!(opt.status_code(), StatusCode::SUCCESS);
+                assert_eq!(opt.status_msg(), b"ok");
+            }
+            _ => panic!("no status code"),
+        }
+        assert!(options.next().is_none() && nested.next().is_none());
+    }
+
+    #[test]
+    fn relay_forward() {
+        let mut frame = [0; 128];
+        let relay_len = DHCPV6_RELAY_HEADER_LEN + 4 + 40;
+        frame[DHCPV6_RELAY_HEADER_LEN..DHCPV6_RELAY_HEADER_LEN + 4]
+            .copy_from_slice(&[0x00, 0x09, 0x00, 0x28]);
+        frame[DHCPV6_RELAY_HEADER_LEN + 4..relay_len].copy_from_slice(&SOLICIT[..40]);
+
+        let mut buf = CursorMut::new(&mut frame[..relay_len]);
+        buf.advance(DHCPV6_RELAY_HEADER_LEN);
+        let mut relay = Dhcpv6RelayPacket::prepend_header(buf, &DHCPV6_RELAY_HEADER_TEMPLATE);
+        relay.set_hop_count(1);
+        relay.set_link_addr(&Ipv6Addr::new(0x2001, 0xdb8, 0, 1, 0, 0, 0, 1));
+        relay.set_peer_addr(&Ipv6Addr::new(0xfe80, 0, 0, 0, 0, 0, 0, 2));
+        match Dhcpv6OptionIterMut::from_option_bytes_mut(relay.option_bytes_mut()).next() {
+            Some(Dhcpv6OptionMut::RelayMsg(msg)) => msg[1] = 0x65,
+            _ => panic!("no relay message"),
+        }
+
+        let relay = Dhcpv6RelayPacket::parse(Cursor::new(&frame[..relay_len])).unwrap();
+        assert_eq!(relay.msg_type(), MsgType::RELAY_FORW);
+        assert_eq!(relay.hop_count(), 1);
+        assert_eq!(
+            relay.peer_addr(),
+            Ipv6Addr::new(0xfe80, 0, 0, 0, 0, 0, 0, 2)
+        );
+        let msg = match Dhcpv6OptionIter::from_option_bytes(relay.option_bytes()).next() {
+            Some(Dhcpv6Option::RelayMsg(msg)) => msg,
+            _ => panic!("no relay message"),
+        };
+        let inner = Dhcpv6Packet::parse(Cursor::new(msg)).unwrap();
+        assert_eq!(inner.transaction_id(), 0x653456);
+
+        assert!(Dhcpv6RelayPacket::parse(Cursor::new(&SOLICIT[..])).is_err());
+    }
+}
@@ -0,0 +1,775 @@
+//! A parser of DNS queries and a builder of DNS responses (RFC 1035).
+//!
+//! The module targets DNS servers and load-testing targets that answer
+//! queries at line rate:
+//!
+//! * [`DnsQuery`] parses a query with a single question, and the EDNS(0) UDP
+//!   payload size advertised by the client (RFC 6891).
+//! * [`DnsResponse`] writes the response into a buffer. The question is copied
+//!   from the query, the owner names of the answers are compressed into
+//!   pointers to it, and the records that do not fit in the size accepted by
+//!   the client are dropped with the TC bit set.
+//!
+//! # Examples
+//! ```
+//! use std::net::Ipv4Addr;
+//!
+//! use rpkt::dns::{encode_name, DnsQuery, DnsRcode, DnsRdata, DnsResponse, DnsType};
+//!
+//! // A query of the A records of "example.com".
+//! let mut query = vec![0x12, 0x34, 0x01, 0x00, 0, 1, 0, 0, 0, 0, 0, 0];
+//! let mut name = [0; 255];
+//! let len = encode_name("example.com", &mut name).unwrap();
+//! query.extend_from_slice(&name[..len]);
+//! query.extend_from_slice(&[0, 1, 0, 1]);
+//!
+//! let query = DnsQuery::parse(&query).unwrap();
+//! assert_eq!(query.qtype(), DnsType::A);
+//!
+//! let mut buf = [0; 512];
+//! let mut response = DnsResponse::new(&mut buf, &query, DnsRcode::NOERROR).unwrap();
+//! response.set_authoritative(true);
+//! assert!(response.add_answer(300, &DnsRdata::A(Ipv4Addr::new(192, 0, 2, 1))));
+//! let len = response.finish();
+//! assert_eq!(&buf[..2], &[0x12, 0x34]);
+//! assert_eq!(len, 12 + 17 + 16);
+//! ```
+
+use std::net::{Ipv4Addr, Ipv6Addr};
+
+/// The length of the DNS header.
+pub const DNS_HEADER_LEN: usize = 12;
+
+/// The maximum length of a DNS message over UDP without EDNS(0).
+pub const DNS_UDP_LEN: usize = 512;
+
+/// The UDP payload size advertised in the OPT record of the responses.
+pub const DNS_EDNS_UDP_LEN: u16 = 1232;
+
+/// The maximum length of a domain name in wire format.
+pub const DNS_NAME_LEN_MAX: usize = 255;
+
+/// The Internet class.
+pub const DNS_CLASS_IN: u16 = 1;
+
+/// The class of a question that matches any class.
+pub const DNS_CLASS_ANY: u16 = 255;
+
+const LABEL_LEN_MAX: usize = 63;
+const POINTER_MASK: u8 = 0xc0;
+// A pointer to the name of the question, right after the header.
+const QNAME_POINTER: [u8; 2] = [0xc0, DNS_HEADER_LEN as u8];
+// The type, class, TTL and rdata length of a resource record.
+const RR_FIXED_LEN: usize = 10;
+// An OPT record: the root name, then the fixed fields without rdata.
+const OPT_RR_LEN: usize = 1 + RR_FIXED_LEN;
+const TXT_STRING_LEN_MAX: usize = 255;
+
+const FLAG_QR: u16 = 0x8000;
+const FLAG_AA: u16 = 0x0400;
+const FLAG_TC: u16 = 0x0200;
+const FLAG_RD: u16 = 0x0100;
+const OPCODE_SHIFT: u16 = 11;
+const OPCODE_MASK: u16 = 0xf;
+const RCODE_MASK: u16 = 0xf;
+
+enum_sim! {
+    /// The type of a resource record.
+    #[derive(Hash)]
+    pub struct DnsType (u16) {
+        A = 1,
+        NS = 2,
+        CNAME = 5,
+        SOA = 6,
+        PTR = 12,
+        MX = 15,
+        TXT = 16,
+        AAAA = 28,
+        OPT = 41,
+        ANY = 255,
+    }
+}
+
+enum_sim! {
+    /// The response code of a DNS message.
+    pub struct DnsRcode (u8) {
+        NOERROR = 0,
+        FORMERR = 1,
+        SERVFAIL = 2,
+        NXDOMAIN = 3,
+        NOTIMP = 4,
+        REFUSED = 5,
+    }
+}
+
+/// A DNS query with a single question.
+#[derive(Debug, Clone, Copy)]
+pub struct DnsQuery<'a> {
+    buf: &'a [u8],
+    qname_end: usize,
+    edns_udp_len: Option<u16>,
+}
+
+impl<'a> DnsQuery<'a> {
+    /// Parse the DNS query at the start of `buf`, e.g. the payload of a UDP
+    /// datagram.
+    ///
+    /// Returns `None` if `buf` is a response, holds more or less than one
+    /// question, or is malformed. The name of the question must not be
+    /// compressed.
+    pub fn parse(buf: &'a [u8]) -> Option<Self> {
+        if buf.len() < DNS_HEADER_LEN || read_u16(buf, 2) & FLAG_QR != 0 || read_u16(buf, 4) != 1 {
+            return None;
+        }
+        let qname_end = parse_name(buf, DNS_HEADER_LEN)?;
+        let mut off = qname_end + 4;
+        if off > buf.len() {
+            return None;
+        }
+
+        let nb_records = usize::from(read_u16(buf, 6)) + usize::from(read_u16(buf, 8));
+        for _ in 0..nb_records {
+            off = skip_record(buf, off)?.0;
+        }
+        let mut edns_udp_len = None;
+        for _ in 0..read_u16(buf, 10) {
+            let (end, rtype, class) = skip_record(buf, off)?;
+            if rtype == DnsType::OPT {
+                edns_udp_len = Some(class);
+            }
+            off = end;
+        }
+
+        Some(Self {
+            buf,
+            qname_end,
+            edns_udp_len,
+        })
+    }
+
+    /// Returns the ID of the query.
+    #[inline]
+    pub fn id(&self) -> u16 {
+        read_u16(self.buf, 0)
+    }
+
+    /// Returns the opcode of the query, 0 for a standard query.
+    #[inline]
+    pub fn opcode(&self) -> u8 {
+        ((read_u16(self.buf, 2) >> OPCODE_SHIFT) & OPCODE_MASK) as u8
+    }
+
+    /// Returns whether the client desires recursion.
+    #[inline]
+    pub fn recursion_desired(&self) -> bool {
+        read_u16(self.buf, 2) & FLAG_RD != 0
+    }
+
+    /// Returns the name of the question in wire format, with the case of the
+    /// query preserved.
+    #[inline]
+    pub fn qname(&self) -> &'a [u8] {
+        &self.buf[DNS_HEADER_LEN..self.qname_end]
+    }
+
+    /// Returns the type of the question.
+    #[inline]
+    pub fn qtype(&self) -> DnsType {
+        read_u16(self.buf, self.qname_end).into()
+    }
+
+    /// Returns the class of the question, 1 for the Internet.
+    #[inline]
+    pub fn qclass(&self) -> u16 {
+        read_u16(self.buf, self.qname_end + 2)
+    }
+
+    /// Returns the UDP payload size of the OPT record, or `None` if the query
+    /// does not use EDNS(0).
+    #[inline]
+    pub fn edns_udp_len(&self) -> Option<u16> {
+        self.edns_udp_len
+    }
+
+    /// Returns the maximum length of a UDP response accepted by the client.
+    #[inline]
+    pub fn max_response_len(&self) -> usize {
+        self.edns_udp_len
+            .map_or(DNS_UDP_LEN, |len| usize::from(len).max(DNS_UDP_LEN))
+    }
+
+    // The question section, which is copied into the response.
+    #[inline]
+    fn question(&self) -> &'a [u8] {
+        &self.buf[DNS_HEADER_LEN..self.qname_end + 4]
+    }
+}
+
+/// The section of a DNS response that a record is added to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum DnsSection {
+    Answer,
+    Authority,
+    Additional,
+}
+
+/// The data of a SOA record.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DnsSoa<'a> {
+    /// The name of the primary name server of the zone.
+    pub mname: &'a str,
+    /// The mailbox of the administrator, with the `@` replaced by a dot.
+    pub rname: &'a str,
+    pub serial: u32,
+    pub refresh: u32,
+    pub retry: u32,
+    pub expire: u32,
+    /// The TTL of the negative responses.
+    pub minimum: u32,
+}
+
+/// The data of a resource record.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DnsRdata<'a> {
+    A(Ipv4Addr),
+    Aaaa(Ipv6Addr),
+    Ns(&'a str),
+    Cname(&'a str),
+    /// The text is split into character-strings of at most 255 bytes.
+    Txt(&'a [u8]),
+    Soa(DnsSoa<'a>),
+}
+
+impl<'a> DnsRdata<'a> {
+    /// Returns the type of the record.
+    pub fn rtype(&self) -> DnsType {
+        match self {
+            DnsRdata::A(_) => DnsType::A,
+            DnsRdata::Aaaa(_) => DnsType::AAAA,
+            DnsRdata::Ns(_) => DnsType::NS,
+            DnsRdata::Cname(_) => DnsType::CNAME,
+            DnsRdata::Txt(_) => DnsType::TXT,
+            DnsRdata::Soa(_) => DnsType::SOA,
+        }
+    }
+
+    // Write the rdata into `buf`, returning the written length.
+    fn write(&self, buf: &mut [u8]) -> Option<usize> {
+        match self {
+            DnsRdata::A(addr) => write_bytes(buf, &addr.octets()),
+            DnsRdata::Aaaa(addr) => write_bytes(buf, &addr.octets()),
+            DnsRdata::Ns(name) | DnsRdata::Cname(name) => Some(encode_name_checked(name, buf)),
+            DnsRdata::Txt(text) => {
+                let mut off = 0;
+                for chunk in text.chunks(TXT_STRING_LEN_MAX) {
+                    *buf.get_mut(off)? = chunk.len() as u8;
+                    off += 1 + write_bytes(&mut buf[off + 1..], chunk)?;
+                }
+                if text.is_empty() {
+                    *buf.first_mut()? = 0;
+                    off = 1;
+                }
+                Some(off)
+            }
+            DnsRdata::Soa(soa) => {
+                let mut off = encode_name_checked(soa.mname, buf);
+                off += encode_name_checked(soa.rname, buf.get_mut(off..)?);
+                for value in [soa.serial, soa.refresh, soa.retry, soa.expire, soa.minimum] {
+                    off += write_bytes(buf.get_mut(off..)?, &value.to_be_bytes())?;
+                }
+                Some(off)
+            }
+        }
+    }
+}
+
+/// A builder of the response to a [`DnsQuery`].
+///
+/// The records must be added section by section, in the order of
+/// [`DnsSection`]. The response is limited to the length accepted by the
+/// client. If an answer or authority record does not fit, the TC bit is set
+/// and no more records are added, so that the client retries over TCP. The
+/// additional records that do not fit are dropped silently.
+///
+/// If the query uses EDNS(0), an OPT record is appended to the response by
+/// [`DnsResponse::finish`].
+pub struct DnsResponse<'a> {
+    buf: &'a mut [u8],
+    len: usize,
+    limit: usize,
+    section: DnsSection,
+    counts: [u16; 3],
+    truncated: bool,
+    edns: bool,
+}
+
+impl<'a> DnsResponse<'a> {
+    /// Start the response to `query` with the response code `rcode`.
+    ///
+    /// Returns `None` if `buf` can not hold the header, the question and the
+    /// OPT record.
+    pub fn new(buf: &'a mut [u8], query: &DnsQuery<'_>, rcode: DnsRcode) -> Option<Self> {
+        let edns = query.edns_udp_len().is_some();
+        let reserved = if edns { OPT_RR_LEN } else { 0 };
+        let question = query.question();
+        let len = DNS_HEADER_LEN + question.len();
+        let limit = buf
+            .len()
+            .min(query.max_response_len())
+            .checked_sub(reserved)?;
+        if len > limit {
+            return None;
+        }
+
+        let mut flags = FLAG_QR | (u16::from(query.opcode()) << OPCODE_SHIFT);
+        if query.recursion_desired() {
+            flags |= FLAG_RD;
+        }
+        flags |= u16::from(u8::from(rcode)) & RCODE_MASK;
+        buf[0..2].copy_from_slice(&query.id().to_be_bytes());
+        buf[2..4].copy_from_slice(&flags.to_be_bytes());
+        buf[4..6].copy_from_slice(&1u16.to_be_bytes());
+        buf[6..DNS_HEADER_LEN].fill(0);
+        buf[DNS_HEADER_LEN..len].copy_from_slice(question);
+
+        Some(Self {
+            buf,
+            len,
+            limit,
+            section: DnsSection::Answer,
+            counts: [0; 3],
+            truncated: false,
+            edns,
+        })
+    }
+
+    /// Set the AA bit, which tells that the server is authoritative for the
+    /// name of the question.
+    pub fn set_authoritative(&mut self, value: bool) {
+        self.set_flag(FLAG_AA, value);
+    }
+
+    /// Set the response code.
+    pub fn set_rcode(&mut self, rcode: DnsRcode) {
+        let flags =
+            (read_u16(self.buf, 2) & !RCODE_MASK) | (u16::from(u8::from(rcode)) & RCODE_MASK);
+        self.buf[2..4].copy_from_slice(&flags.to_be_bytes());
+    }
+
+    /// Returns whether the TC bit is set.
+    #[inline]
+    pub fn is_truncated(&self) -> bool {
+        self.truncated
+    }
+
+    /// Returns the length of the response written so far.
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    /// Returns whether the response holds no record.
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.counts == [0; 3]
+    }
+
+    /// Add an answer owned by the name of the question, returning whether the
+    /// record fits.
+    pub fn add_answer(&mut self, ttl: u32, rdata: &DnsRdata<'_>) -> bool {
+        self.add_record(DnsSection::Answer, None, ttl, rdata)
+    }
+
+    /// Add a record to `section`, returning whether the record fits.
+    ///
+    /// The record is owned by `name`, or by the name of the question if `name`
+    /// is `None`.
+    ///
+    /// # Panics
+    ///
+    /// This function panics if `section` precedes the section of the previous
+    /// record, or if a name of the record is not a valid domain name.
+    pub fn add_record(
+        &mut self,
+        section: DnsSection,
+        name: Option<&str>,
+        ttl: u32,
+        rdata: &DnsRdata<'_>,
+    ) -> bool {
+        assert!(section >= self.section, "records are added out of order");
+        self.section = section;
+        if self.truncated {
+            return false;
+        }
+
+        let len = self.len;
+        match self.write_record(name, ttl, rdata) {
+            Some(record_len) => {
+                self.len += record_len;
+                self.counts[section as usize] += 1;
+                true
+            }
+            None => {
+                // Do not leave a partial record behind.
+                self.len = len;
+                if section != DnsSection::Additional {
+                    self.truncated = true;
+                    self.set_flag(FLAG_TC, true);
+                }
+                false
+            }
+        }
+    }
+
+    /// Finish the response, returning its length.
+    pub fn finish(self) -> usize {
+        for (idx, count) in self.counts.iter().enumerate() {
+            let off = 6 + 2 * idx;
+            self.buf[off..off + 2].copy_from_slice(&count.to_be_bytes());
+        }
+        let mut len = self.len;
+        if self.edns {
+            let ar_count = self.counts[DnsSection::Additional as usize] + 1;
+            self.buf[10..12].copy_from_slice(&ar_count.to_be_bytes());
+            // The root name, the type, the UDP payload size, a zero extended
+            // rcode, version and flags, and no options.
+            let opt = &mut self.buf[len..len + OPT_RR_LEN];
+            opt.fill(0);
+            opt[1..3].copy_from_slice(&u16::from(DnsType::OPT).to_be_bytes());
+            opt[3..5].copy_from_slice(&DNS_EDNS_UDP_LEN.to_be_bytes());
+            len += OPT_RR_LEN;
+        }
+        len
+    }
+
+    fn set_flag(&mut self, flag: u16, value: bool) {
+        let mut flags = read_u16(self.buf, 2);
+        if value {
+            flags |= flag;
+        } else {
+            flags &= !flag;
+        }
+        self.buf[2..4].copy_from_slice(&flags.to_be_bytes());
+    }
+
+    fn write_record(
+        &mut self,
+        name: Option<&str>,
+        ttl: u32,
+        rdata: &DnsRdata<'_>,
+    ) -> Option<usize> {
+        let buf = &mut self.buf[self.len..self.limit];
+        let mut off = match name {
+            Some(name) => encode_name_checked(name, buf),
+            None => write_bytes(buf, &QNAME_POINTER)?,
+        };
+        let fixed = buf.get_mut(off..off + RR_FIXED_LEN)?;
+        fixed[0..2].copy_from_slice(&u16::from(rdata.rtype()).to_be_bytes());
+        fixed[2..4].copy_from_slice(&DNS_CLASS_IN.to_be_bytes());
+        fixed[4..8].copy_from_slice(&ttl.to_be_bytes());
+        off += RR_FIXED_LEN;
+
+        let rdata_len = rdata.write(&mut buf[off..])?;
+        if off + rdata_len > buf.len() {
+            return None;
+        }
+        buf[off - 2..off].copy_from_slice(&(rdata_len as u16).to_be_bytes());
+        Some(off + rdata_len)
+    }
+}
+
+/// Encode the domain name `name`, e.g. "example.com" or "example.com.", in
+/// wire format into `buf`, returning the encoded length.
+///
+/// Returns `None` if `name` is not a valid domain name, or if `buf` is too
+/// small. The empty name and "." are the root.
+pub fn encode_name(name: &str, buf: &mut [u8]) -> Option<usize> {
+    let name = name.strip_suffix('.').unwrap_or(name);
+    let mut off = 0;
+    if !name.is_empty() {
+        for label in name.split('.') {
+            if label.is_empty() || label.len() > LABEL_LEN_MAX {
+                return None;
+            }
+            *buf.get_mut(off)? = label.len() as u8;
+            off += 1 + write_bytes(buf.get_mut(off + 1..)?, label.as_bytes())?;
+        }
+    }
+    *buf.get_mut(off)? = 0;
+    off += 1;
+    if off > DNS_NAME_LEN_MAX {
+        return None;
+    }
+    Some(off)
+}
+
+// Encode a name into a buffer that may be too small. An invalid name panics,
+// while a full buffer returns a length that overflows it, so that the caller
+// fails at the next bounds check.
+fn encode_name_checked(name: &str, buf: &mut [u8]) -> usize {
+    let mut tmp = [0; DNS_NAME_LEN_MAX];
+    let len =
+        encode_name(name, &mut tmp).unwrap_or_else(|| panic!("invalid domain name: {}", name));
+    match buf.get_mut(..len) {
+        Some(dst) => {
+            dst.copy_from_slice(&tmp[..len]);
+            len
+        }
+        None => buf.len() + 1,
+    }
+}
+
+fn write_bytes(buf: &mut [u8], bytes: &[u8]) -> Option<usize> {
+    buf.get_mut(..bytes.len())?.copy_from_slice(bytes);
+    Some(bytes.len())
+}
+
+#[inline]
+fn read_u16(buf: &[u8], off: usize) -> u16 {
+    u16::from_be_bytes([buf[off], buf[off + 1]])
+}
+
+// Parse an uncompressed name starting at `off`, returning the offset after it.
+fn parse_name(buf: &[u8], mut off: usize) -> Option<usize> {
+    let start = off;
+    loop {
+        let len = usize::from(*buf.get(off)?);
+        off += 1;
+        if len == 0 {
+            break;
+        }
+        if len > LABEL_LEN_MAX {
+            return None;
+        }
+        off += len;
+    }
+    (off - start <= DNS_NAME_LEN_MAX && off <= buf.len()).then_some(off)
+}
+
+// Skip a possibly compressed name starting at `off`, returning the offset
+// after it.
+fn skip_name(buf: &[u8], mut off: usize) -> Option<usize> {
+    loop {
+        let len = *buf.get(off)?;
+        if len & POINTER_MASK == POINTER_MASK {
+            buf.get(off + 1)?;
+            return Some(off + 2);
+        }
+        if usize::from(len) > LABEL_LEN_MAX {
+            return None;
+        }
+        off += 1 + usize::from(len);
+        if len == 0 {
+            return Some(off);
+        }
+    }
+}
+
+// Skip a resource record starting at `off`, returning the offset after it, its
+// type and its class.
+fn skip_record(buf: &[u8], off: usize) -> Option<(usize, DnsType, u16)> {
+    let off = skip_name(buf, off)?;
+    if off + RR_FIXED_LEN > buf.len() {
+        return None;
+    }
+    let end = off + RR_FIXED_LEN + usize::from(read_u16(buf, off + 8));
+    if end > buf.len() {
+        return None;
+    }
+    Some((end, read_u16(buf, off).into(), read_u16(buf, off + 2)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn query(name: &str, qtype: DnsType, edns: Option<u16>) -> Vec<u8> {
+        let mut buf = vec![0xab, 0xcd, 0x01, 0x00, 0, 1, 0, 0, 0, 0, 0, 0];
+        let mut wire = [0; DNS_NAME_LEN_MAX];
+        let len = encode_name(name, &mut wire).unwrap();
+        buf.extend_from_slice(&wire[..len]);
+        buf.extend_from_slice(&u16::from(qtype).to_be_bytes());
+        buf.extend_from_slice(&DNS_CLASS_IN.to_be_bytes());
+        if let Some(udp_len) = edns {
+            buf[11] = 1;
+            buf.extend_from_slice(&[0, 0, 41]);
+            buf.extend_from_slice(&udp_len.to_be_bytes());
+            buf.extend_from_slice(&[0, 0, 0, 0, 0, 0]);
+        }
+        buf
+    }
+
+    #[test]
+    fn names() {
+        let mut buf = [0; DNS_NAME_LEN_MAX];
+        assert_eq!(encode_name("", &mut buf), Some(1));
+        assert_eq!(encode_name(".", &mut buf), Some(1));
+        assert_eq!(encode_name("a.bc.", &mut buf), Some(6));
+        assert_eq!(&buf[..6], &[1, b'a', 2, b'b', b'c', 0]);
+        assert_eq!(encode_name("a..b", &mut buf), None);
+        assert_eq!(encode_name(&"a".repeat(64), &mut buf), None);
+        assert_eq!(encode_name("a.b", &mut buf[..4]), None);
+
+        // 127 labels of one byte fill up the 255 bytes.
+        let long = vec!["a"; 127].join(".");
+        assert_eq!(encode_name(&long, &mut buf), Some(255));
+        assert_eq!(encode_name(&format!("b.{}", long), &mut [0; 512]), None);
+    }
+
+    #[test]
+    fn parse_queries() {
+        let buf = query("Example.com", DnsType::AAAA, None);
+        let q = DnsQuery::parse(&buf).unwrap();
+        assert_eq!(q.id(), 0xabcd);
+        assert_eq!(q.opcode(), 0);
+        assert!(q.recursion_desired());
+        assert_eq!(q.qname(), b"\x07Example\x03com\x00");
+        assert_eq!(q.qtype(), DnsType::AAAA);
+        assert_eq!(q.qclass(), DNS_CLASS_IN);
+        assert_eq!(q.edns_udp_len(), None);
+        assert_eq!(q.max_response_len(), DNS_UDP_LEN);
+
+        let buf = query("example.com", DnsType::A, Some(4096));
+        let q = DnsQuery::parse(&buf).unwrap();
+        assert_eq!(q.edns_udp_len(), Some(4096));
+        assert_eq!(q.max_response_len(), 4096);
+        let buf = query("example.com", DnsType::A, Some(100));
+        assert_eq!(
+            DnsQuery::parse(&buf).unwrap().max_response_len(),
+            DNS_UDP_LEN
+        );
+
+        // Truncated queries, responses and multiple questions are rejected.
+        for len in 0..buf.len() {
+            assert!(DnsQuery::parse(&buf[..len]).is_none());
+        }
+        let mut response = buf.clone();
+        response[2] |= 0x80;
+        assert!(DnsQuery::parse(&response).is_none());
+        let mut multiple = buf.clone();
+        multiple[5] = 2;
+        assert!(DnsQuery::parse(&multiple).is_none());
+        // A compressed name in the question is rejected.
+        let mut compressed = buf;
+        compressed[DNS_HEADER_LEN] = 0xc0;
+        assert!(DnsQuery::parse(&compressed).is_none());
+    }
+
+    #[test]
+    fn build_responses() {
+        let buf = query("example.com", DnsType::A, None);
+        let q = DnsQuery::parse(&buf).unwrap();
+        let mut out = [0; 1024];
+        let mut resp = DnsResponse::new(&mut out, &q, DnsRcode::NOERROR).unwrap();
+        resp.set_authoritative(true);
+        assert!(resp.add_answer(60, &DnsRdata::A(Ipv4Addr::new(192, 0, 2, 1))));
+        assert!(resp.add_answer(60, &DnsRdata::A(Ipv4Addr::new(192, 0, 2, 2))));
+        assert!(resp.add_record(
+            DnsSection::Authority,
+            Some("example.com"),
+            3600,
+            &DnsRdata::Ns("ns.example.com")
+        ));
+        assert!(resp.add_record(
+            DnsSection::Additional,
+            Some("ns.example.com"),
+            3600,
+            &DnsRdata::Aaaa(Ipv6Addr::LOCALHOST)
+        ));
+        let len = resp.finish();
+        let msg = &out[..len];
+
+        assert_eq!(
+            &msg[..12],
+            &[0xab, 0xcd, 0x85, 0x00, 0, 1, 0, 2, 0, 1, 0, 1]
+        );
+        assert_eq!(&msg[12..buf.len()], &buf[12..]);
+        let mut off = buf.len();
+        // The answers point to the question.
+        assert_eq!(
+            &msg[off..off + 16],
+            &[0xc0, 12, 0, 1, 0, 1, 0, 0, 0, 60, 0, 4, 192, 0, 2, 1]
+        );
+        off += 32;
+        let ns = b"\x07example\x03com\x00\x00\x02\x00\x01\x00\x00\x0e\x10\x00\x10\x02ns\x07example\x03com\x00";
+        assert_eq!(&msg[off..off + ns.len()], &ns[..]);
+        off += ns.len();
+        assert_eq!(read_u16(msg, off + 16), u16::from(DnsType::AAAA));
+        assert_eq!(len, off + 16 + 10 + 16);
+
+        // NXDOMAIN with a SOA record, EDNS(0) and a TXT answer.
+        let buf = query("nx.example.com", DnsType::TXT, Some(1232));
+        let q = DnsQuery::parse(&buf).unwrap();
+        let mut resp = DnsResponse::new(&mut out, &q, DnsRcode::SERVFAIL).unwrap();
+        resp.set_rcode(DnsRcode::NXDOMAIN);
+        let soa = DnsSoa {
+            mname: "ns.example.com",
+            rname: "admin.example.com",
+            serial: 1,
+            refresh: 2,
+            retry: 3,
+            expire: 4,
+            minimum: 5,
+        };
+        assert!(resp.add_record(
+            DnsSection::Authority,
+            Some("example.com"),
+            5,
+            &DnsRdata::Soa(soa)
+        ));
+        let len = resp.finish();
+        assert_eq!(&out[2..12], &[0x81, 0x03, 0, 1, 0, 0, 0, 1, 0, 1]);
+        assert_eq!(
+            &out[len - OPT_RR_LEN..len],
+            &[0, 0, 41, 0x04, 0xd0, 0, 0, 0, 0, 0, 0]
+        );
+        let soa_len = 13 + RR_FIXED_LEN + 16 + 19 + 20;
+        assert_eq!(len, buf.len() - OPT_RR_LEN + soa_len + OPT_RR_LEN);
+
+        let mut resp = DnsResponse::new(&mut out, &q, DnsRcode::NOERROR).unwrap();
+        let text = [b'x'; 300];
+        assert!(resp.add_answer(0, &DnsRdata::Txt(&text)));
+        assert!(resp.add_answer(0, &DnsRdata::Txt(b"")));
+        let len = resp.finish();
+        let answer = &out[buf.len() - OPT_RR_LEN..];
+        assert_eq!(read_u16(answer, 10), 302);
+        assert_eq!((answer[12], answer[12 + 256]), (255, 45));
+        assert_eq!(len, buf.len() + 12 + 302 + 12 + 1);
+    }
+
+    #[test]
+    fn truncation() {
+        let buf = query("example.com", DnsType::AAAA, None);
+        let q = DnsQuery::parse(&buf).unwrap();
+        let mut out = [0; 1024];
+        let mut resp = DnsResponse::new(&mut out, &q, DnsRcode::NOERROR).unwrap();
+        let mut answers = 0;
+        while resp.add_answer(60, &DnsRdata::Aaaa(Ipv6Addr::LOCALHOST)) {
+            answers += 1;
+        }
+        // Each answer takes 28 bytes of the 512 - 29 bytes after the question.
+        assert_eq!(answers, 17);
+        assert!(resp.is_truncated());
+        assert!(!resp.add_record(DnsSection::Additional, None, 0, &DnsRdata::Txt(b"")));
+        let len = resp.finish();
+        assert_eq!(len, 29 + 17 * 28);
+        assert_eq!(&out[2..4], &[0x83, 0x00]);
+        assert_eq!(read_u16(&out, 6), 17);
+
+        // A dropped additional record does not set the TC bit.
+        let mut small = [0; 64];
+        let mut resp = DnsResponse::new(&mut small, &q, DnsRcode::NOERROR).unwrap();
+        assert!(resp.add_answer(60, &DnsRdata::Aaaa(Ipv6Addr::LOCALHOST)));
+        assert!(!resp.add_record(
+            DnsSection::Additional,
+            None,
+            0,
+            &DnsRdata::Aaaa(Ipv6Addr::LOCALHOST)
+        ));
+        assert!(!resp.is_truncated());
+        assert_eq!(resp.finish(), 29 + 28);
+        assert_eq!(read_u16(&small, 10), 0);
+
+        assert!(DnsResponse::new(&mut small[..28], &q, DnsRcode::NOERROR).is_none());
+    }
+}
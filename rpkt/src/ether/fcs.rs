@@ -0,0 +1,99 @@
+/// The length of the frame check sequence at the end of an Ethernet frame.
+pub const ETHER_FCS_LEN: usize = 4;
+
+// The CRC-32 of a frame followed by its FCS.
+const FCS_RESIDUE: u32 = 0x2144_df1c;
+
+// The table of the reflected CRC-32 polynomial, for one byte at a time.
+const CRC32_TABLE: [u32; 256] = crc32_table();
+
+const fn crc32_table() -> [u32; 256] {
+    let mut table = [0; 256];
+    let mut i = 0;
+    while i < 256 {
+        let mut crc = i as u32;
+        let mut bit = 0;
+        while bit < 8 {
+            crc = if crc & 1 != 0 {
+                (crc >> 1) ^ 0xedb8_8320
+            } else {
+                crc >> 1
+            };
+            bit += 1;
+        }
+        table[i] = crc;
+        i += 1;
+    }
+    table
+}
+
+/// Calculate the FCS of `frame`, the CRC-32 of IEEE 802.3.
+///
+/// The FCS is transmitted in little-endian byte order, see [`write_fcs`].
+pub fn calc_fcs(frame: &[u8]) -> u32 {
+    !frame.iter().fold(!0u32, |crc, byte| {
+        CRC32_TABLE[usize::from((crc as u8) ^ byte)] ^ (crc >> 8)
+    })
+}
+
+/// Write the FCS of the frame of `len` bytes at the start of `buf` behind the
+/// frame, and return the length of the frame with the FCS.
+///
+/// A short frame must be padded before, as the padding is covered by the FCS.
+///
+/// # Panics
+///
+/// This function panics if `buf` is shorter than `len + ETHER_FCS_LEN`.
+pub fn write_fcs(buf: &mut [u8], len: usize) -> usize {
+    let fcs = calc_fcs(&buf[..len]);
+    buf[len..len + ETHER_FCS_LEN].copy_from_slice(&fcs.to_le_bytes());
+    len + ETHER_FCS_LEN
+}
+
+/// Returns whether `frame` ends with a valid FCS.
+pub fn verify_fcs(frame: &[u8]) -> bool {
+    frame.len() >= ETHER_FCS_LEN && calc_fcs(frame) == FCS_RESIDUE
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ether::{EtherPacket, ETHER_HEADER_LEN};
+    use crate::{Buf, Cursor};
+
+    #[test]
+    fn crc32_check_value() {
+        assert_eq!(calc_fcs(b"123456789"), 0xcbf4_3926);
+        assert_eq!(calc_fcs(&[]), 0);
+    }
+
+    #[test]
+    fn frame_fcs() {
+        let mut frame = [0; 64];
+        frame.iter_mut().enumerate().for_each(|(i, b)| *b = i as u8);
+        assert_eq!(calc_fcs(&frame[..60]), 0xb0ec_7fee);
+
+        assert_eq!(write_fcs(&mut frame, 60), 64);
+        assert_eq!(frame[60..], [0xee, 0x7f, 0xec, 0xb0]);
+        assert!(verify_fcs(&frame));
+
+        frame[20] ^= 0x01;
+        assert!(!verify_fcs(&frame));
+        assert!(!verify_fcs(&frame[..3]));
+    }
+
+    #[test]
+    fn strip_fcs() {
+        let mut frame = [0; 64];
+        write_fcs(&mut frame, 60);
+
+        let mut ethpkt = EtherPacket::parse(Cursor::new(&frame[..])).unwrap();
+        assert!(verify_fcs(ethpkt.buf().chunk()));
+        assert!(ethpkt.strip_fcs());
+        assert_eq!(ethpkt.buf().remaining(), 60);
+
+        let mut ethpkt = EtherPacket::parse(Cursor::new(&frame[..ETHER_HEADER_LEN + 2])).unwrap();
+        assert!(!ethpkt.strip_fcs());
+        assert_eq!(ethpkt.buf().remaining(), ETHER_HEADER_LEN + 2);
+    }
+}
@@ -86,3 +86,45 @@ impl<T: AsMut<[u8]>> EtherHeader<T> {
         NetworkEndian::write_u16(data, value.into())
     }
 }
+
+/// Const builders for the header template, so that the presets of the header can
+/// be evaluated at compile time and stored in `const`s or `static`s.
+///
+/// # Examples
+/// ```
+/// use rpkt::ether::*;
+///
+/// static ARP_BCAST: EtherHeader<[u8; ETHER_HEADER_LEN]> = ETHER_HEADER_TEMPLATE
+///     .with_dest_mac(MacAddr::BROADCAST)
+///     .with_ethertype(EtherType::ARP);
+/// assert_eq!(ARP_BCAST.ethertype(), EtherType::ARP);
+/// ```
+impl EtherHeader<[u8; ETHER_HEADER_LEN]> {
+    #[inline]
+    pub const fn with_dest_mac(mut self, value: MacAddr) -> Self {
+        let mut i = 0;
+        while i < 6 {
+            self.buf[i] = value.0[i];
+            i += 1;
+        }
+        self
+    }
+
+    #[inline]
+    pub const fn with_source_mac(mut self, value: MacAddr) -> Self {
+        let mut i = 0;
+        while i < 6 {
+            self.buf[6 + i] = value.0[i];
+            i += 1;
+        }
+        self
+    }
+
+    #[inline]
+    pub const fn with_ethertype(mut self, value: EtherType) -> Self {
+        let bytes = value.0.to_be_bytes();
+        self.buf[12] = bytes[0];
+        self.buf[13] = bytes[1];
+        self
+    }
+}
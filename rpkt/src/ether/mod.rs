@@ -1,5 +1,9 @@
 use std::fmt;
 
+use crate::arp::ArpPacket;
+use crate::ipv4::Ipv4Packet;
+use crate::ipv6::Ipv6Packet;
+
 ///  This is copied directly from smoltcp.
 #[derive(Debug, Hash, PartialEq, Eq, PartialOrd, Ord, Clone, Copy, Default)]
 pub struct MacAddr(pub [u8; 6]);
@@ -66,6 +70,27 @@ enum_sim! {
         ARP =  0x0806,
         IPV4 = 0x0800,
         IPV6 = 0x86DD,
+        VLAN = 0x8100,
+        QINQ = 0x88A8,
+        /// Transparent Ethernet bridging, e.g. an Ethernet frame carried by GRE.
+        TRANS_ETHER_BRIDGING = 0x6558,
+        PPPOE_DISCOVERY = 0x8863,
+        PPPOE_SESSION = 0x8864,
+        /// ERSPAN type I and type II, carried by GRE.
+        ERSPAN_TYPE2 = 0x88BE,
+        /// ERSPAN type III, carried by GRE.
+        ERSPAN_TYPE3 = 0x22EB,
+        /// The tag of a frame in an HSR ring.
+        HSR = 0x892F,
+        /// GOOSE of IEC 61850-8-1.
+        GOOSE = 0x88B8,
+        /// Sampled Values of IEC 61850-9-2.
+        SV = 0x88BA,
+        LLDP = 0x88CC,
+        /// The Slow Protocols of IEEE 802.3, e.g. LACP.
+        SLOW_PROTOCOLS = 0x8809,
+        /// PTP of IEEE 1588 over Ethernet.
+        PTP = 0x88F7,
     }
 }
 
@@ -75,14 +100,48 @@ impl fmt::Display for EtherType {
             EtherType::ARP => write!(f, "ARP"),
             EtherType::IPV4 => write!(f, "IPv4"),
             EtherType::IPV6 => write!(f, "IPv6"),
+            EtherType::VLAN => write!(f, "VLAN"),
+            EtherType::QINQ => write!(f, "QinQ"),
             _ => write!(f, "0x{:04x}", u16::from(*self)),
         }
     }
 }
 
+/// A packet that can be carried in the payload of an Ethernet frame.
+///
+/// It allows the layer chaining code to set the ethertype with
+/// [`EtherPacket::set_ethertype_for`] instead of remembering the constants.
+pub trait EtherPayload {
+    const ETHERTYPE: EtherType;
+}
+
+impl<T> EtherPayload for ArpPacket<T> {
+    const ETHERTYPE: EtherType = EtherType::ARP;
+}
+
+impl<T> EtherPayload for Ipv4Packet<T> {
+    const ETHERTYPE: EtherType = EtherType::IPV4;
+}
+
+impl<T> EtherPayload for Ipv6Packet<T> {
+    const ETHERTYPE: EtherType = EtherType::IPV6;
+}
+
 mod header;
 pub use header::{EtherHeader, ETHER_HEADER_LEN, ETHER_HEADER_TEMPLATE};
 
+mod vlan;
+pub use vlan::{VlanStack, VlanTag, VLAN_STACK_MAX_DEPTH, VLAN_TAG_LEN};
+
+mod prp;
+pub use prp::{HsrTag, PrpTrailer, RedundancyTag, HSR_TAG_LEN, PRP_SUFFIX, PRP_TRAILER_LEN};
+
+mod fcs;
+pub use fcs::{calc_fcs, verify_fcs, write_fcs, ETHER_FCS_LEN};
+
+mod pad;
+pub use pad::{pad_frame, padded_len, unpadded_len, ETHER_MIN_FRAME_LEN};
+
 mod packet;
 pub use self::packet::{
     EtherPacket, ETHER_MAX_JUMBO_PKT_LEN, ETHER_MAX_LEN, ETHER_MIN_LEN, ETHER_MTU, ETHER_OVERHEAD,
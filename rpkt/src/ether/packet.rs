@@ -1,10 +1,11 @@
 use bytes::Buf;
 
-use crate::PktMut;
 use crate::{Cursor, CursorMut};
+use crate::{PktBuf, PktMut};
 
+use super::fcs::ETHER_FCS_LEN;
 use super::header::{EtherHeader, ETHER_HEADER_LEN};
-use super::{EtherType, MacAddr};
+use super::{EtherPayload, EtherType, MacAddr};
 
 /// The default ethernet overhead without VLAN.
 /// It includes the 14-byte ethernet header and the 4-byte crc checksum.
@@ -56,11 +57,13 @@ packet_base! {
 impl<T: Buf> EtherPacket<T> {
     #[inline]
     pub fn parse(buf: T) -> Result<EtherPacket<T>, T> {
-        if buf.chunk().len() >= ETHER_HEADER_LEN {
-            Ok(EtherPacket::parse_unchecked(buf))
-        } else {
-            Err(buf)
-        }
+        traced_parse!("ether", buf, |_: &Self| ETHER_HEADER_LEN, {
+            if buf.chunk().len() >= ETHER_HEADER_LEN {
+                Ok(EtherPacket::parse_unchecked(buf))
+            } else {
+                Err(buf)
+            }
+        })
     }
 
     #[inline]
@@ -71,6 +74,39 @@ impl<T: Buf> EtherPacket<T> {
     }
 }
 
+impl<T: PktBuf> EtherPacket<T> {
+    /// Trim off the padding, or any other trailer, behind the IPv4 or IPv6
+    /// packet of the frame, and return the number of trimmed bytes.
+    ///
+    /// The frame is left untouched if its length is not known from the IP
+    /// header, see [`unpadded_len`](super::unpadded_len).
+    pub fn strip_padding(&mut self) -> usize {
+        let frame_len = match super::unpadded_len(self.buf.chunk()) {
+            Some(frame_len) => frame_len,
+            None => return 0,
+        };
+        let trailer = self.buf.remaining().saturating_sub(frame_len);
+        if trailer > 0 {
+            self.buf.trim_off(trailer);
+        }
+        trailer
+    }
+
+    /// Trim off the FCS at the end of the frame, e.g. of a frame delivered by
+    /// a driver that keeps the CRC. The FCS is not verified, see
+    /// [`verify_fcs`](super::verify_fcs).
+    ///
+    /// Returns `false` if the frame is too short to carry an FCS behind the
+    /// header, leaving it untouched.
+    pub fn strip_fcs(&mut self) -> bool {
+        if self.buf.remaining() < ETHER_HEADER_LEN + ETHER_FCS_LEN {
+            return false;
+        }
+        self.buf.trim_off(ETHER_FCS_LEN);
+        true
+    }
+}
+
 impl<T: PktMut> EtherPacket<T> {
     #[inline]
     pub fn prepend_header<HT: AsRef<[u8]>>(mut buf: T, header: &EtherHeader<HT>) -> EtherPacket<T> {
@@ -82,6 +118,12 @@ impl<T: PktMut> EtherPacket<T> {
 
         EtherPacket { buf }
     }
+
+    /// Set the ethertype to the one that identifies the payload packet type `P`.
+    #[inline]
+    pub fn set_ethertype_for<P: EtherPayload>(&mut self) {
+        self.set_ethertype(P::ETHERTYPE)
+    }
 }
 
 impl<'a> EtherPacket<Cursor<'a>> {
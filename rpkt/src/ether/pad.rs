@@ -0,0 +1,137 @@
+use crate::ipv4::{Ipv4Header, IPV4_HEADER_LEN};
+use crate::ipv6::{Ipv6Header, IPV6_HEADER_LEN};
+
+use super::{EtherType, VlanStack, ETHER_MIN_LEN};
+
+/// The minimum length of an Ethernet frame without the 4-byte FCS, the length
+/// that short frames are padded to.
+pub const ETHER_MIN_FRAME_LEN: usize = ETHER_MIN_LEN - 4;
+
+/// Returns the length of a frame of `len` bytes once padded to
+/// [`ETHER_MIN_FRAME_LEN`].
+#[inline]
+pub const fn padded_len(len: usize) -> usize {
+    if len < ETHER_MIN_FRAME_LEN {
+        ETHER_MIN_FRAME_LEN
+    } else {
+        len
+    }
+}
+
+/// Pad the frame of `len` bytes at the start of `buf` with zeros up to
+/// [`ETHER_MIN_FRAME_LEN`], and return the padded length.
+///
+/// # Panics
+///
+/// This function panics if `buf` is shorter than the padded length.
+pub fn pad_frame(buf: &mut [u8], len: usize) -> usize {
+    let padded = padded_len(len);
+    buf[len..padded].fill(0);
+    padded
+}
+
+/// Returns the length of an Ethernet frame without its padding, i.e. the
+/// length of the Ethernet and VLAN headers plus the total length of the IPv4
+/// or IPv6 packet.
+///
+/// Returns `None` if the frame does not carry an IP packet, if the IP header
+/// is truncated or its length is inconsistent, or if it is an IPv6 packet with
+/// a zero payload length, e.g. a jumbogram, whose length is unknown.
+pub fn unpadded_len(frame: &[u8]) -> Option<usize> {
+    let stack = VlanStack::parse(frame)?;
+    let offset = stack.header_len();
+    let ip_len = match stack.ethertype() {
+        EtherType::IPV4 => {
+            let header = Ipv4Header::new(&frame[offset..]).ok()?;
+            let packet_len = usize::from(header.packet_len());
+            if !header.check_version() || packet_len < IPV4_HEADER_LEN {
+                return None;
+            }
+            packet_len
+        }
+        EtherType::IPV6 => {
+            let header = Ipv6Header::new(&frame[offset..]).ok()?;
+            if !header.check_version() || header.payload_len() == 0 {
+                return None;
+            }
+            IPV6_HEADER_LEN + usize::from(header.payload_len())
+        }
+        _ => return None,
+    };
+    Some(offset + ip_len)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ether::{EtherPacket, ETHER_HEADER_LEN, ETHER_HEADER_TEMPLATE};
+    use crate::ipv4::{Ipv4Packet, IPV4_HEADER_TEMPLATE};
+    use crate::udp::{UdpPacket, UDP_HEADER_LEN, UDP_HEADER_TEMPLATE};
+    use crate::{Buf, Cursor, CursorMut};
+
+    const UDP_FRAME_LEN: usize = ETHER_HEADER_LEN + IPV4_HEADER_LEN + UDP_HEADER_LEN + 2;
+
+    // A UDP datagram with a 2-byte payload, which is shorter than the minimum
+    // frame, followed by 0xee bytes.
+    fn short_udp_frame() -> [u8; 128] {
+        let mut bytes = [0xee; 128];
+        bytes[..UDP_FRAME_LEN].fill(0xaa);
+        let mut buf = CursorMut::new(&mut bytes[..UDP_FRAME_LEN]);
+        buf.advance(UDP_FRAME_LEN - 2);
+        let udppkt = UdpPacket::prepend_header(buf, &UDP_HEADER_TEMPLATE);
+        let ippkt = Ipv4Packet::prepend_header(udppkt.release(), &IPV4_HEADER_TEMPLATE);
+        EtherPacket::prepend_header(ippkt.release(), &ETHER_HEADER_TEMPLATE);
+        bytes
+    }
+
+    #[test]
+    fn pad_short_frame() {
+        assert_eq!(padded_len(0), ETHER_MIN_FRAME_LEN);
+        assert_eq!(padded_len(100), 100);
+
+        let mut frame = short_udp_frame();
+        assert_eq!(pad_frame(&mut frame, UDP_FRAME_LEN), 60);
+        assert!(frame[UDP_FRAME_LEN..60].iter().all(|b| *b == 0));
+        assert_eq!(frame[60], 0xee);
+        assert_eq!(pad_frame(&mut frame, 80), 80);
+    }
+
+    #[test]
+    fn strip_padding() {
+        let mut frame = short_udp_frame();
+        let len = pad_frame(&mut frame, UDP_FRAME_LEN);
+        assert_eq!(unpadded_len(&frame[..len]), Some(UDP_FRAME_LEN));
+
+        let mut ethpkt = EtherPacket::parse(Cursor::new(&frame[..len])).unwrap();
+        assert_eq!(ethpkt.strip_padding(), len - UDP_FRAME_LEN);
+        assert_eq!(ethpkt.buf().remaining(), UDP_FRAME_LEN);
+        assert_eq!(ethpkt.strip_padding(), 0);
+
+        let ippkt = Ipv4Packet::parse(ethpkt.payload()).unwrap();
+        let udppkt = UdpPacket::parse(ippkt.payload()).unwrap();
+        assert_eq!(udppkt.payload().chunk(), &[0xaa, 0xaa]);
+    }
+
+    #[test]
+    fn unknown_length() {
+        let frame = short_udp_frame();
+        // A truncated IP header.
+        assert_eq!(unpadded_len(&frame[..ETHER_HEADER_LEN + 10]), None);
+
+        // Not an IP packet.
+        let mut arp = frame;
+        arp[12..14].copy_from_slice(&[0x08, 0x06]);
+        assert_eq!(unpadded_len(&arp), None);
+        let mut ethpkt = EtherPacket::parse(Cursor::new(&arp[..])).unwrap();
+        assert_eq!(ethpkt.strip_padding(), 0);
+        assert_eq!(ethpkt.buf().remaining(), arp.len());
+
+        // An IPv6 jumbogram.
+        let mut ipv6 = [0; 100];
+        ipv6[12..14].copy_from_slice(&[0x86, 0xdd]);
+        ipv6[ETHER_HEADER_LEN] = 0x60;
+        assert_eq!(unpadded_len(&ipv6), None);
+        ipv6[ETHER_HEADER_LEN + 5] = 8;
+        assert_eq!(unpadded_len(&ipv6), Some(ETHER_HEADER_LEN + 48));
+    }
+}
@@ -0,0 +1,346 @@
+use crate::{PktBuf, PktMut};
+
+use super::header::ETHER_HEADER_LEN;
+use super::pad::ETHER_MIN_FRAME_LEN;
+use super::{EtherType, MacAddr, VlanStack};
+
+/// The length of an HSR tag, including its ethertype.
+pub const HSR_TAG_LEN: usize = 6;
+
+/// The length of a PRP redundancy control trailer.
+pub const PRP_TRAILER_LEN: usize = 6;
+
+/// The suffix that ends a PRP redundancy control trailer.
+pub const PRP_SUFFIX: u16 = 0x88fb;
+
+// The offset of the ethertype in an untagged frame.
+const ETHERTYPE_OFFSET: usize = 12;
+
+// The LAN identifiers of a PRP trailer.
+const PRP_LAN_A: u8 = 0xa;
+const PRP_LAN_B: u8 = 0xb;
+
+/// The tag of a frame in an HSR ring (IEC 62439-3, clause 5).
+///
+/// The tag follows the MAC addresses, or the VLAN tag of a tagged frame, and
+/// is followed by the ethertype of the payload.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct HsrTag {
+    /// The path identifier, with the direction in the ring in the lowest bit.
+    pub path_id: u8,
+    /// The size of the frame from the path identifier to the end.
+    pub lsdu_size: u16,
+    pub seq: u16,
+    /// The ethertype of the payload behind the tag.
+    pub ethertype: EtherType,
+}
+
+impl HsrTag {
+    /// Parse the HSR tag of the Ethernet frame in `frame`, and return it with
+    /// its offset.
+    ///
+    /// Returns `None` if the frame is not HSR tagged, or if it is truncated.
+    pub fn parse(frame: &[u8]) -> Option<(Self, usize)> {
+        let stack = VlanStack::parse(frame)?;
+        if stack.ethertype() != EtherType::HSR {
+            return None;
+        }
+        let offset = stack.header_len() - 2;
+        let data = frame.get(offset..offset + HSR_TAG_LEN + 2)?;
+        let path_lsdu = u16::from_be_bytes([data[2], data[3]]);
+        let tag = Self {
+            path_id: (path_lsdu >> 12) as u8,
+            lsdu_size: path_lsdu & 0x0fff,
+            seq: u16::from_be_bytes([data[4], data[5]]),
+            ethertype: EtherType::from(u16::from_be_bytes([data[6], data[7]])),
+        };
+        Some((tag, offset))
+    }
+
+    /// Returns the tag as 6 bytes, without the ethertype of the payload.
+    pub fn to_bytes(&self) -> [u8; HSR_TAG_LEN] {
+        assert!(self.path_id <= 0x0f && self.lsdu_size <= 0x0fff);
+        let path_lsdu = (u16::from(self.path_id) << 12) | self.lsdu_size;
+        let ethertype = u16::from(EtherType::HSR).to_be_bytes();
+        let path_lsdu = path_lsdu.to_be_bytes();
+        let seq = self.seq.to_be_bytes();
+        [
+            ethertype[0],
+            ethertype[1],
+            path_lsdu[0],
+            path_lsdu[1],
+            seq[0],
+            seq[1],
+        ]
+    }
+
+    /// Insert an HSR tag with `path_id` and `seq` into the untagged frame
+    /// starting at `buf`, the LSDU size is set to cover the rest of the frame.
+    ///
+    /// # Panics
+    /// The function panics if `buf` has less than 6 bytes of headroom, or if
+    /// the frame is shorter than an Ethernet header.
+    pub fn push<T: PktMut>(buf: &mut T, path_id: u8, seq: u16) -> HsrTag {
+        assert!(buf.chunk_headroom() >= HSR_TAG_LEN);
+        assert!(buf.chunk().len() >= ETHER_HEADER_LEN);
+        let lsdu_size = buf.remaining() + HSR_TAG_LEN - ETHER_HEADER_LEN;
+        assert!(lsdu_size <= 0x0fff);
+        buf.move_back(HSR_TAG_LEN);
+
+        let data = buf.chunk_mut();
+        data.copy_within(HSR_TAG_LEN..HSR_TAG_LEN + ETHERTYPE_OFFSET, 0);
+        let tag = HsrTag {
+            path_id,
+            lsdu_size: lsdu_size as u16,
+            seq,
+            ethertype: EtherType::from(u16::from_be_bytes([
+                data[ETHERTYPE_OFFSET + HSR_TAG_LEN],
+                data[ETHERTYPE_OFFSET + HSR_TAG_LEN + 1],
+            ])),
+        };
+        data[ETHERTYPE_OFFSET..ETHERTYPE_OFFSET + HSR_TAG_LEN].copy_from_slice(&tag.to_bytes());
+        tag
+    }
+
+    /// Remove the HSR tag of the frame starting at `buf`, which must not be
+    /// VLAN tagged.
+    ///
+    /// Returns `None` and leaves the frame untouched if it is not HSR tagged.
+    pub fn pop<T: PktMut>(buf: &mut T) -> Option<HsrTag> {
+        let (tag, offset) = Self::parse(buf.chunk())?;
+        if offset != ETHERTYPE_OFFSET {
+            return None;
+        }
+
+        let data = buf.chunk_mut();
+        data.copy_within(0..ETHERTYPE_OFFSET, HSR_TAG_LEN);
+        buf.advance(HSR_TAG_LEN);
+        Some(tag)
+    }
+}
+
+/// The redundancy control trailer of a frame in a PRP network (IEC 62439-3,
+/// clause 4).
+///
+/// The trailer ends the frame, or precedes the padding of a short frame.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PrpTrailer {
+    pub seq: u16,
+    /// The LAN identifier, 0xa for LAN A and 0xb for LAN B.
+    pub lan_id: u8,
+    /// The size of the frame from the payload behind the Ethernet header to
+    /// the end of the trailer.
+    pub lsdu_size: u16,
+}
+
+impl PrpTrailer {
+    /// Create the trailer of a frame sent on LAN A or LAN B.
+    pub fn new(seq: u16, lan_b: bool) -> Self {
+        Self {
+            seq,
+            lan_id: if lan_b { PRP_LAN_B } else { PRP_LAN_A },
+            lsdu_size: 0,
+        }
+    }
+
+    /// Find the trailer of the Ethernet frame in `frame`, and return it with
+    /// its offset.
+    ///
+    /// The trailer is looked up at the end of the frame, and before the
+    /// padding of a frame that is not longer than [`ETHER_MIN_FRAME_LEN`].
+    /// A trailer is only recognized if its LSDU size matches its offset.
+    pub fn parse(frame: &[u8]) -> Option<(Self, usize)> {
+        let payload_start = VlanStack::parse(frame)?.header_len();
+        let last = frame.len().checked_sub(PRP_TRAILER_LEN)?;
+        let first = if frame.len() <= ETHER_MIN_FRAME_LEN {
+            payload_start
+        } else {
+            last
+        };
+
+        (first..=last).rev().find_map(|offset| {
+            let data = &frame[offset..offset + PRP_TRAILER_LEN];
+            if u16::from_be_bytes([data[4], data[5]]) != PRP_SUFFIX {
+                return None;
+            }
+            let lan_lsdu = u16::from_be_bytes([data[2], data[3]]);
+            let trailer = Self {
+                seq: u16::from_be_bytes([data[0], data[1]]),
+                lan_id: (lan_lsdu >> 12) as u8,
+                lsdu_size: lan_lsdu & 0x0fff,
+            };
+            let lsdu_size = offset + PRP_TRAILER_LEN - payload_start;
+            ((trailer.lan_id == PRP_LAN_A || trailer.lan_id == PRP_LAN_B)
+                && usize::from(trailer.lsdu_size) == lsdu_size)
+                .then_some((trailer, offset))
+        })
+    }
+
+    /// Returns whether the frame was sent on LAN B.
+    pub fn is_lan_b(&self) -> bool {
+        self.lan_id == PRP_LAN_B
+    }
+
+    /// Returns the trailer as 6 bytes.
+    pub fn to_bytes(&self) -> [u8; PRP_TRAILER_LEN] {
+        assert!(self.lan_id <= 0x0f && self.lsdu_size <= 0x0fff);
+        let lan_lsdu = (u16::from(self.lan_id) << 12) | self.lsdu_size;
+        let seq = self.seq.to_be_bytes();
+        let lan_lsdu = lan_lsdu.to_be_bytes();
+        let suffix = PRP_SUFFIX.to_be_bytes();
+        [
+            seq[0],
+            seq[1],
+            lan_lsdu[0],
+            lan_lsdu[1],
+            suffix[0],
+            suffix[1],
+        ]
+    }
+
+    /// Append the trailer to the frame of `len` bytes at the start of `buf`,
+    /// with the LSDU size set accordingly, and return the length of the frame
+    /// with the trailer.
+    ///
+    /// The frame must not be padded, it may be padded after the trailer.
+    ///
+    /// # Panics
+    /// The function panics if `buf` is too short for the trailer, or if the
+    /// frame is shorter than an Ethernet header.
+    pub fn append(&self, buf: &mut [u8], len: usize) -> usize {
+        let payload_start = VlanStack::parse(&buf[..len])
+            .expect("frame shorter than an ethernet header")
+            .header_len();
+        let trailer = Self {
+            lsdu_size: (len + PRP_TRAILER_LEN - payload_start) as u16,
+            ..*self
+        };
+        buf[len..len + PRP_TRAILER_LEN].copy_from_slice(&trailer.to_bytes());
+        len + PRP_TRAILER_LEN
+    }
+
+    /// Remove the trailer of the frame in `buf`, together with the padding
+    /// behind it.
+    ///
+    /// Returns `None` and leaves the frame untouched if there is no trailer.
+    pub fn strip<T: PktBuf>(buf: &mut T) -> Option<PrpTrailer> {
+        let (trailer, offset) = Self::parse(buf.chunk())?;
+        buf.trim_off(buf.remaining() - offset);
+        Some(trailer)
+    }
+}
+
+/// The HSR tag or the PRP trailer of a frame.
+///
+/// A node discards the duplicates of a frame by its source MAC address and
+/// the sequence number of its tag or trailer.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RedundancyTag {
+    Hsr(HsrTag),
+    Prp(PrpTrailer),
+}
+
+impl RedundancyTag {
+    /// Parse the HSR tag, or else the PRP trailer, of the Ethernet frame in
+    /// `frame`.
+    pub fn parse(frame: &[u8]) -> Option<Self> {
+        HsrTag::parse(frame)
+            .map(|(tag, _)| RedundancyTag::Hsr(tag))
+            .or_else(|| PrpTrailer::parse(frame).map(|(trailer, _)| RedundancyTag::Prp(trailer)))
+    }
+
+    pub fn seq(&self) -> u16 {
+        match self {
+            RedundancyTag::Hsr(tag) => tag.seq,
+            RedundancyTag::Prp(trailer) => trailer.seq,
+        }
+    }
+
+    /// Returns the key that identifies the duplicates of `frame`, its source
+    /// MAC address and its sequence number.
+    pub fn dup_key(frame: &[u8]) -> Option<(MacAddr, u16)> {
+        let tag = Self::parse(frame)?;
+        Some((MacAddr::from_bytes(&frame[6..12]), tag.seq()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Buf, Cursor, CursorMut};
+
+    fn frame(ethertype: u16, payload_len: usize) -> Vec<u8> {
+        let mut frame = vec![0xff; 6];
+        frame.extend_from_slice(&[0x02, 0, 0, 0, 0, 0x01]);
+        frame.extend_from_slice(&ethertype.to_be_bytes());
+        frame.extend((0..payload_len).map(|i| i as u8));
+        frame
+    }
+
+    #[test]
+    fn hsr_tag() {
+        let orig = frame(0x0800, 50);
+        let mut bytes = vec![0; HSR_TAG_LEN];
+        bytes.extend_from_slice(&orig);
+        let mut buf = CursorMut::new(&mut bytes[..]);
+        buf.advance(HSR_TAG_LEN);
+
+        let tag = HsrTag::push(&mut buf, 1, 0x1234);
+        assert_eq!(tag.lsdu_size, 56);
+        assert_eq!(tag.ethertype, EtherType::IPV4);
+        assert_eq!(
+            &buf.chunk()[12..20],
+            &[0x89, 0x2f, 0x10, 0x38, 0x12, 0x34, 0x08, 0x00]
+        );
+        assert_eq!(HsrTag::parse(buf.chunk()), Some((tag, 12)));
+        assert_eq!(
+            RedundancyTag::dup_key(buf.chunk()),
+            Some((MacAddr([0x02, 0, 0, 0, 0, 0x01]), 0x1234))
+        );
+
+        assert_eq!(HsrTag::pop(&mut buf), Some(tag));
+        assert_eq!(buf.chunk(), &orig[..]);
+        assert_eq!(HsrTag::pop(&mut buf), None);
+
+        // Behind a VLAN tag.
+        let mut tagged = frame(0x8100, 0);
+        tagged.extend_from_slice(&[0x00, 0x64, 0x89, 0x2f, 0x00, 0x08, 0x00, 0x07, 0x88, 0xb8]);
+        let (tag, offset) = HsrTag::parse(&tagged).unwrap();
+        assert_eq!(
+            (tag.seq, tag.ethertype, offset),
+            (7, EtherType::from(0x88b8), 16)
+        );
+        assert!(HsrTag::parse(&tagged[..22]).is_none());
+    }
+
+    #[test]
+    fn prp_trailer() {
+        // A long frame, the trailer is at the end.
+        let mut bytes = frame(0x0800, 60);
+        let len = bytes.len();
+        bytes.resize(len + PRP_TRAILER_LEN, 0);
+        let len = PrpTrailer::new(0xabcd, true).append(&mut bytes, len);
+        assert_eq!(&bytes[len - 6..], &[0xab, 0xcd, 0xb0, 0x42, 0x88, 0xfb]);
+        let (trailer, offset) = PrpTrailer::parse(&bytes).unwrap();
+        assert!(trailer.is_lan_b());
+        assert_eq!((trailer.seq, trailer.lsdu_size, offset), (0xabcd, 66, 74));
+
+        // A short frame padded behind the trailer.
+        let mut bytes = frame(0x0806, 28);
+        let len = bytes.len();
+        bytes.resize(ETHER_MIN_FRAME_LEN, 0);
+        let len = PrpTrailer::new(5, false).append(&mut bytes, len);
+        assert_eq!(len, 48);
+        assert_eq!(RedundancyTag::parse(&bytes).map(|tag| tag.seq()), Some(5));
+        let mut buf = Cursor::new(&bytes[..]);
+        let trailer = PrpTrailer::strip(&mut buf).unwrap();
+        assert!(!trailer.is_lan_b());
+        assert_eq!(buf.remaining(), 42);
+
+        // The suffix without a matching LSDU size, and no trailer at all.
+        let mut bytes = frame(0x0800, 70);
+        bytes[78..].copy_from_slice(&[0, 1, 0xa0, 0x10, 0x88, 0xfb]);
+        assert!(PrpTrailer::parse(&bytes).is_none());
+        assert!(RedundancyTag::parse(&frame(0x0800, 40)).is_none());
+    }
+}
@@ -0,0 +1,297 @@
+use bytes::Buf;
+
+use crate::PktMut;
+
+use super::header::ETHER_HEADER_LEN;
+use super::EtherType;
+
+/// The length of an 802.1Q tag.
+pub const VLAN_TAG_LEN: usize = 4;
+
+/// The maximum number of tags held by a [`VlanStack`].
+pub const VLAN_STACK_MAX_DEPTH: usize = 8;
+
+// The offset of the ethertype, or the TPID of the outer tag, in a frame.
+const TPID_OFFSET: usize = 12;
+
+fn is_tpid(ethertype: EtherType) -> bool {
+    ethertype == EtherType::VLAN || ethertype == EtherType::QINQ
+}
+
+/// An 802.1Q or 802.1ad tag.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct VlanTag {
+    /// The tag protocol identifier, `EtherType::VLAN` for a customer tag and
+    /// `EtherType::QINQ` for a service tag.
+    pub tpid: EtherType,
+    /// The priority code point.
+    pub pcp: u8,
+    /// The drop eligible indicator.
+    pub dei: bool,
+    /// The VLAN identifier.
+    pub vid: u16,
+}
+
+impl VlanTag {
+    /// Create an 802.1Q customer tag.
+    pub fn customer(vid: u16) -> Self {
+        Self::with_tpid(EtherType::VLAN, vid)
+    }
+
+    /// Create an 802.1ad service tag.
+    pub fn service(vid: u16) -> Self {
+        Self::with_tpid(EtherType::QINQ, vid)
+    }
+
+    fn with_tpid(tpid: EtherType, vid: u16) -> Self {
+        assert!(vid <= 0xfff);
+        Self {
+            tpid,
+            pcp: 0,
+            dei: false,
+            vid,
+        }
+    }
+
+    /// Read a tag from the first 4 bytes of `data`.
+    ///
+    /// # Panics
+    /// The function panics if `data` is shorter than 4 bytes.
+    pub fn from_bytes(data: &[u8]) -> Self {
+        let tci = u16::from_be_bytes([data[2], data[3]]);
+        Self {
+            tpid: EtherType::from(u16::from_be_bytes([data[0], data[1]])),
+            pcp: (tci >> 13) as u8,
+            dei: tci & 0x1000 != 0,
+            vid: tci & 0xfff,
+        }
+    }
+
+    /// Returns the tag as 4 bytes.
+    pub fn to_bytes(&self) -> [u8; VLAN_TAG_LEN] {
+        assert!(self.pcp <= 0x07 && self.vid <= 0xfff);
+        let tci = (u16::from(self.pcp) << 13) | (u16::from(self.dei) << 12) | self.vid;
+        let tpid = u16::from(self.tpid).to_be_bytes();
+        let tci = tci.to_be_bytes();
+        [tpid[0], tpid[1], tci[0], tci[1]]
+    }
+}
+
+/// The sequence of VLAN tags in front of the payload of an Ethernet frame.
+///
+/// Tags with either the 0x8100 or the 0x88a8 TPID are accepted in any order,
+/// up to [`VLAN_STACK_MAX_DEPTH`] tags.
+///
+/// # Examples
+/// ```
+/// use rpkt::ether::*;
+/// use rpkt::CursorMut;
+/// use rpkt::Buf;
+///
+/// let mut bytes = [0; 64];
+/// let mut buf = CursorMut::new(&mut bytes[..]);
+/// buf.advance(ETHER_HEADER_LEN + 2 * VLAN_TAG_LEN);
+/// let mut ethpkt = EtherPacket::prepend_header(buf, &ETHER_HEADER_TEMPLATE);
+/// ethpkt.set_ethertype(EtherType::IPV4);
+///
+/// let mut buf = ethpkt.release();
+/// VlanStack::push(&mut buf, VlanTag::customer(100));
+/// VlanStack::push(&mut buf, VlanTag::service(10));
+///
+/// let stack = VlanStack::parse(buf.chunk()).unwrap();
+/// assert_eq!(stack.outer_vid(), Some(10));
+/// assert_eq!(stack.inner_vid(), Some(100));
+/// assert_eq!(stack.ethertype(), EtherType::IPV4);
+///
+/// assert_eq!(VlanStack::pop(&mut buf), Some(VlanTag::service(10)));
+/// assert_eq!(VlanStack::parse(buf.chunk()).unwrap().outer_vid(), Some(100));
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct VlanStack {
+    tags: [VlanTag; VLAN_STACK_MAX_DEPTH],
+    depth: usize,
+    ethertype: EtherType,
+}
+
+impl VlanStack {
+    /// Parse the tags of the Ethernet frame in `frame`.
+    ///
+    /// Returns `None` if the frame is truncated in the middle of the tags, or
+    /// if it carries more than [`VLAN_STACK_MAX_DEPTH`] tags.
+    pub fn parse(frame: &[u8]) -> Option<Self> {
+        if frame.len() < ETHER_HEADER_LEN {
+            return None;
+        }
+        let mut stack = Self {
+            tags: [VlanTag::customer(0); VLAN_STACK_MAX_DEPTH],
+            depth: 0,
+            ethertype: EtherType::from(0),
+        };
+        let mut offset = TPID_OFFSET;
+        loop {
+            let ethertype = EtherType::from(u16::from_be_bytes([frame[offset], frame[offset + 1]]));
+            if !is_tpid(ethertype) {
+                stack.ethertype = ethertype;
+                return Some(stack);
+            }
+            if stack.depth == VLAN_STACK_MAX_DEPTH || frame.len() < offset + VLAN_TAG_LEN + 2 {
+                return None;
+            }
+            stack.tags[stack.depth] = VlanTag::from_bytes(&frame[offset..]);
+            stack.depth += 1;
+            offset += VLAN_TAG_LEN;
+        }
+    }
+
+    /// Returns the tags, from the outermost to the innermost.
+    pub fn tags(&self) -> &[VlanTag] {
+        &self.tags[..self.depth]
+    }
+
+    /// Returns the outermost tag.
+    pub fn outer(&self) -> Option<&VlanTag> {
+        self.tags().first()
+    }
+
+    /// Returns the innermost tag.
+    pub fn inner(&self) -> Option<&VlanTag> {
+        self.tags().last()
+    }
+
+    /// Returns the VID of the outermost tag.
+    pub fn outer_vid(&self) -> Option<u16> {
+        self.outer().map(|tag| tag.vid)
+    }
+
+    /// Returns the VID of the innermost tag.
+    pub fn inner_vid(&self) -> Option<u16> {
+        self.inner().map(|tag| tag.vid)
+    }
+
+    /// Returns the ethertype of the payload behind the tags.
+    pub fn ethertype(&self) -> EtherType {
+        self.ethertype
+    }
+
+    /// Returns the length of the Ethernet header including the tags.
+    pub fn header_len(&self) -> usize {
+        ETHER_HEADER_LEN + self.depth * VLAN_TAG_LEN
+    }
+
+    /// Push `tag` as the new outermost tag of the frame starting at `buf`.
+    ///
+    /// The MAC addresses are moved into the headroom, and the TPID of `tag`
+    /// takes the place of the ethertype, which now follows the tag.
+    ///
+    /// # Panics
+    /// The function panics if `buf` has less than 4 bytes of headroom, or if
+    /// the frame is shorter than an Ethernet header.
+    pub fn push<T: PktMut>(buf: &mut T, tag: VlanTag) {
+        assert!(buf.chunk_headroom() >= VLAN_TAG_LEN);
+        assert!(buf.chunk().len() >= ETHER_HEADER_LEN);
+        buf.move_back(VLAN_TAG_LEN);
+
+        let data = buf.chunk_mut();
+        data.copy_within(VLAN_TAG_LEN..VLAN_TAG_LEN + TPID_OFFSET, 0);
+        data[TPID_OFFSET..TPID_OFFSET + VLAN_TAG_LEN].copy_from_slice(&tag.to_bytes());
+    }
+
+    /// Pop the outermost tag of the frame starting at `buf`.
+    ///
+    /// The MAC addresses are moved over the tag, so the TPID of the next tag,
+    /// or the ethertype of the payload, becomes the ethertype of the frame.
+    /// Returns `None` and leaves the frame untouched if it is not tagged.
+    pub fn pop<T: PktMut>(buf: &mut T) -> Option<VlanTag> {
+        let data = buf.chunk_mut();
+        if data.len() < ETHER_HEADER_LEN + VLAN_TAG_LEN {
+            return None;
+        }
+        let tag = VlanTag::from_bytes(&data[TPID_OFFSET..]);
+        if !is_tpid(tag.tpid) {
+            return None;
+        }
+
+        data.copy_within(0..TPID_OFFSET, VLAN_TAG_LEN);
+        buf.advance(VLAN_TAG_LEN);
+        Some(tag)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::CursorMut;
+
+    static QINQ_FRAME: [u8; 26] = [
+        0x01, 0x02, 0x03, 0x04, 0x05, 0x06, 0x11, 0x12, 0x13, 0x14, 0x15, 0x16, 0x88, 0xa8, 0xa0,
+        0x0a, 0x81, 0x00, 0x30, 0x64, 0x08, 0x00, 0x45, 0x00, 0x00, 0x14,
+    ];
+
+    #[test]
+    fn vlan_parse() {
+        let stack = VlanStack::parse(&QINQ_FRAME[..]).unwrap();
+        assert_eq!(stack.tags().len(), 2);
+        assert_eq!(
+            stack.outer(),
+            Some(&VlanTag {
+                tpid: EtherType::QINQ,
+                pcp: 5,
+                dei: false,
+                vid: 10
+            })
+        );
+        assert_eq!(
+            stack.inner(),
+            Some(&VlanTag {
+                tpid: EtherType::VLAN,
+                pcp: 1,
+                dei: true,
+                vid: 100
+            })
+        );
+        assert_eq!(stack.ethertype(), EtherType::IPV4);
+        assert_eq!(stack.header_len(), 22);
+
+        let stack = VlanStack::parse(&QINQ_FRAME[4 * 2..]).unwrap();
+        assert_eq!(stack.tags(), &[]);
+        assert_eq!(stack.outer_vid(), None);
+
+        // Truncated in the middle of the tags.
+        assert_eq!(VlanStack::parse(&QINQ_FRAME[..19]), None);
+        assert_eq!(VlanStack::parse(&QINQ_FRAME[..13]), None);
+
+        // Too many tags.
+        let mut bytes = [0; 64];
+        for tag in bytes[12..].chunks_mut(4) {
+            tag.copy_from_slice(&[0x81, 0x00, 0x00, 0x01]);
+        }
+        assert_eq!(VlanStack::parse(&bytes[..]), None);
+    }
+
+    #[test]
+    fn vlan_push_pop() {
+        let mut untagged = [0; 18];
+        untagged[..12].copy_from_slice(&QINQ_FRAME[..12]);
+        untagged[12..14].copy_from_slice(&[0x08, 0x00]);
+        untagged[14..].copy_from_slice(&QINQ_FRAME[22..]);
+
+        let mut bytes = [0; 30];
+        bytes[12..].copy_from_slice(&untagged[..]);
+
+        let mut buf = CursorMut::new(&mut bytes[..]);
+        buf.advance(12);
+        let mut tag = VlanTag::customer(100);
+        tag.pcp = 1;
+        tag.dei = true;
+        VlanStack::push(&mut buf, tag);
+        let mut tag = VlanTag::service(10);
+        tag.pcp = 5;
+        VlanStack::push(&mut buf, tag);
+        assert_eq!(buf.chunk(), &QINQ_FRAME[..]);
+
+        assert_eq!(VlanStack::pop(&mut buf), Some(tag));
+        assert_eq!(VlanStack::pop(&mut buf).map(|tag| tag.vid), Some(100));
+        assert_eq!(VlanStack::pop(&mut buf), None);
+        assert_eq!(buf.chunk(), &untagged[..]);
+    }
+}
@@ -0,0 +1,298 @@
+//! Deterministic pseudo-random fillers for the fields of test traffic.
+//!
+//! A [`Filler`] is seeded once and then draws valid field values, such as
+//! locally administered MAC addresses, addresses within a prefix, ephemeral
+//! ports and TCP initial sequence numbers. The same seed always produces the
+//! same values, so the traffic of a failing test can be regenerated from the
+//! seed alone.
+//!
+//! The payloads are filled with a [`Pattern`], either incrementing bytes or a
+//! PRBS sequence of ITU-T O.150, which a receiver can regenerate to check the
+//! payload.
+//!
+//! # Examples
+//! ```
+//! use std::net::{IpAddr, Ipv4Addr};
+//!
+//! use rpkt::acl::IpPrefix;
+//! use rpkt::fill::{Filler, Pattern};
+//!
+//! let mut filler = Filler::new(7);
+//! let prefix = IpPrefix::from_v4(Ipv4Addr::new(10, 0, 0, 0), 8);
+//! match filler.ip_in(&prefix) {
+//!     IpAddr::V4(addr) => assert_eq!(addr.octets()[0], 10),
+//!     IpAddr::V6(_) => unreachable!(),
+//! }
+//! assert!(filler.ephemeral_port() >= 49152);
+//!
+//! let mut payload = [0u8; 32];
+//! Pattern::Prbs31.fill(&mut payload, 1);
+//! assert!(Pattern::Prbs31.check(&payload, 1).is_none());
+//! ```
+
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr};
+
+use crate::acl::IpPrefix;
+use crate::ether::MacAddr;
+
+/// The first port of the dynamic, or ephemeral, port range of RFC 6335.
+pub const EPHEMERAL_PORT_START: u16 = 49152;
+
+/// A seeded generator of field values, based on xorshift64*.
+#[derive(Debug, Clone)]
+pub struct Filler {
+    state: u64,
+}
+
+impl Filler {
+    /// Create a filler from `seed`. The same seed always produces the same
+    /// sequence of values.
+    pub fn new(seed: u64) -> Self {
+        // The xorshift state must not be zero.
+        let state = seed ^ 0x9e37_79b9_7f4a_7c15;
+        Self {
+            state: if state == 0 { 1 } else { state },
+        }
+    }
+
+    /// Returns the next pseudo-random number.
+    pub fn next_u64(&mut self) -> u64 {
+        // xorshift64*
+        self.state ^= self.state >> 12;
+        self.state ^= self.state << 25;
+        self.state ^= self.state >> 27;
+        self.state.wrapping_mul(0x2545_f491_4f6c_dd1d)
+    }
+
+    /// Returns the next pseudo-random number, in `[0, n)`.
+    ///
+    /// # Panics
+    ///
+    /// This function panics if `n` is 0.
+    pub fn below(&mut self, n: u64) -> u64 {
+        self.next_u64() % n
+    }
+
+    /// Fill `buf` with pseudo-random bytes.
+    pub fn fill_bytes(&mut self, buf: &mut [u8]) {
+        for chunk in buf.chunks_mut(8) {
+            let bytes = self.next_u64().to_be_bytes();
+            chunk.copy_from_slice(&bytes[..chunk.len()]);
+        }
+    }
+
+    /// Returns a locally administered unicast MAC address.
+    pub fn mac(&mut self) -> MacAddr {
+        let mut addr = [0; 6];
+        self.fill_bytes(&mut addr);
+        // Set the local bit and clear the group bit.
+        addr[0] = (addr[0] | 0x02) & !0x01;
+        MacAddr(addr)
+    }
+
+    /// Returns an address within `prefix`, of the family of the prefix.
+    ///
+    /// [`IpPrefix::ANY`] yields IPv6 addresses.
+    pub fn ip_in(&mut self, prefix: &IpPrefix) -> IpAddr {
+        let host_mask = |len: u8, bits: u8| match u32::from(bits - len) {
+            0 => 0,
+            host_len => u128::MAX >> (128 - host_len),
+        };
+        let random = u128::from(self.next_u64()) << 64 | u128::from(self.next_u64());
+        match prefix.addr() {
+            IpAddr::V4(addr) if prefix.is_ipv4() => {
+                let mask = host_mask(prefix.prefix_len(), 32) as u32;
+                IpAddr::V4(Ipv4Addr::from(u32::from(addr) | (random as u32 & mask)))
+            }
+            addr => {
+                let addr = match addr {
+                    IpAddr::V4(addr) => addr.to_ipv6_mapped(),
+                    IpAddr::V6(addr) => addr,
+                };
+                let mask = host_mask(prefix.prefix_len(), 128);
+                IpAddr::V6(Ipv6Addr::from(u128::from(addr) | (random & mask)))
+            }
+        }
+    }
+
+    /// Returns a port of the ephemeral port range, from
+    /// [`EPHEMERAL_PORT_START`] to 65535.
+    pub fn ephemeral_port(&mut self) -> u16 {
+        EPHEMERAL_PORT_START + self.below(u64::from(u16::MAX - EPHEMERAL_PORT_START) + 1) as u16
+    }
+
+    /// Returns an initial sequence number of a TCP connection.
+    pub fn tcp_isn(&mut self) -> u32 {
+        (self.next_u64() >> 32) as u32
+    }
+}
+
+/// A payload pattern that the receiver can regenerate from its seed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Pattern {
+    /// Bytes incrementing by 1 from the low byte of the seed, wrapping at 255.
+    Incrementing,
+    /// The PRBS of the polynomial x^7 + x^6 + 1.
+    Prbs7,
+    /// The PRBS of the polynomial x^15 + x^14 + 1.
+    Prbs15,
+    /// The PRBS of the polynomial x^23 + x^18 + 1.
+    Prbs23,
+    /// The PRBS of the polynomial x^31 + x^28 + 1.
+    Prbs31,
+}
+
+impl Pattern {
+    /// Fill `buf` with the pattern, starting from `seed`.
+    pub fn fill(&self, buf: &mut [u8], seed: u64) {
+        match self.prbs(seed) {
+            Some(mut prbs) => buf.iter_mut().for_each(|b| *b = prbs.next_byte()),
+            None => {
+                for (i, b) in buf.iter_mut().enumerate() {
+                    *b = (seed as u8).wrapping_add(i as u8);
+                }
+            }
+        }
+    }
+
+    /// Check that `buf` holds the pattern started from `seed`, returns the
+    /// offset of the first differing byte.
+    pub fn check(&self, buf: &[u8], seed: u64) -> Option<usize> {
+        match self.prbs(seed) {
+            Some(mut prbs) => buf.iter().position(|&b| b != prbs.next_byte()),
+            None => buf
+                .iter()
+                .enumerate()
+                .position(|(i, &b)| b != (seed as u8).wrapping_add(i as u8)),
+        }
+    }
+
+    fn prbs(&self, seed: u64) -> Option<Prbs> {
+        let (order, tap) = match self {
+            Pattern::Incrementing => return None,
+            Pattern::Prbs7 => (7, 6),
+            Pattern::Prbs15 => (15, 14),
+            Pattern::Prbs23 => (23, 18),
+            Pattern::Prbs31 => (31, 28),
+        };
+        Some(Prbs::new(order, tap, seed))
+    }
+}
+
+/// A pseudo-random binary sequence generated by a Fibonacci LFSR with two
+/// taps, as specified by ITU-T O.150.
+#[derive(Debug, Clone)]
+pub struct Prbs {
+    state: u32,
+    order: u32,
+    tap: u32,
+}
+
+impl Prbs {
+    /// Create the generator of the polynomial x^`order` + x^`tap` + 1, whose
+    /// register is loaded with the low `order` bits of `seed`, or with all
+    /// ones if they are all zeros.
+    ///
+    /// # Panics
+    ///
+    /// This function panics if `order` is not in `[2, 31]` or `tap` is not in
+    /// `[1, order)`.
+    pub fn new(order: u32, tap: u32, seed: u64) -> Self {
+        assert!((2..=31).contains(&order) && (1..order).contains(&tap));
+        let mask = (1u32 << order) - 1;
+        let state = seed as u32 & mask;
+        Self {
+            state: if state == 0 { mask } else { state },
+            order,
+            tap,
+        }
+    }
+
+    /// Returns the next bit of the sequence.
+    #[inline]
+    pub fn next_bit(&mut self) -> u8 {
+        let bit = ((self.state >> (self.order - 1)) ^ (self.state >> (self.tap - 1))) & 1;
+        self.state = ((self.state << 1) | bit) & ((1 << self.order) - 1);
+        bit as u8
+    }
+
+    /// Returns the next 8 bits of the sequence, the first bit as the most
+    /// significant one.
+    #[inline]
+    pub fn next_byte(&mut self) -> u8 {
+        (0..8).fold(0, |byte, _| (byte << 1) | self.next_bit())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn seeded_fields() {
+        let (mut a, mut b) = (Filler::new(42), Filler::new(42));
+        for _ in 0..100 {
+            assert_eq!(a.next_u64(), b.next_u64());
+        }
+        assert_ne!(Filler::new(1).next_u64(), Filler::new(2).next_u64());
+
+        let mut filler = Filler::new(0);
+        let v4 = IpPrefix::from_v4(Ipv4Addr::new(192, 0, 2, 0), 24);
+        let v6 = IpPrefix::from_v6("2001:db8::".parse().unwrap(), 32);
+        for _ in 0..1000 {
+            let mac = filler.mac();
+            assert_eq!(mac.0[0] & 0x03, 0x02);
+
+            match filler.ip_in(&v4) {
+                IpAddr::V4(addr) => assert_eq!(&addr.octets()[..3], &[192, 0, 2]),
+                addr => panic!("{}", addr),
+            }
+            match filler.ip_in(&v6) {
+                IpAddr::V6(addr) => assert_eq!(&addr.segments()[..2], &[0x2001, 0xdb8]),
+                addr => panic!("{}", addr),
+            }
+            assert!(filler.ephemeral_port() >= EPHEMERAL_PORT_START);
+        }
+
+        let host = IpPrefix::from_v4(Ipv4Addr::new(192, 0, 2, 1), 32);
+        assert_eq!(filler.ip_in(&host), IpAddr::V4(Ipv4Addr::new(192, 0, 2, 1)));
+        assert!(filler.ip_in(&IpPrefix::ANY).is_ipv6());
+
+        let mut buf = [0u8; 13];
+        filler.fill_bytes(&mut buf);
+        assert!(buf.iter().any(|&b| b != 0));
+    }
+
+    #[test]
+    fn prbs_period() {
+        for (order, tap) in [(7, 6), (15, 14)] {
+            let mut prbs = Prbs::new(order, tap, 1);
+            let start = prbs.state;
+            let period = (1..).find(|_| {
+                prbs.next_bit();
+                prbs.state == start
+            });
+            assert_eq!(period, Some((1 << order) - 1));
+        }
+    }
+
+    #[test]
+    fn patterns() {
+        let mut buf = [0u8; 300];
+        Pattern::Incrementing.fill(&mut buf, 0xfe);
+        assert_eq!(&buf[..3], &[0xfe, 0xff, 0x00]);
+
+        for pattern in [
+            Pattern::Incrementing,
+            Pattern::Prbs7,
+            Pattern::Prbs15,
+            Pattern::Prbs23,
+            Pattern::Prbs31,
+        ] {
+            pattern.fill(&mut buf, 9);
+            assert_eq!(pattern.check(&buf, 9), None);
+            buf[100] ^= 0x10;
+            assert_eq!(pattern.check(&buf, 9), Some(100));
+        }
+    }
+}
@@ -0,0 +1,426 @@
+//! Flow keys for distributing packets across cores in software.
+//!
+//! [`FlowKey`] identifies the flow of a packet so that a software load
+//! balancer can pin it to a core with [`FlowKey::core_index`]. The keys are
+//! symmetric: both directions of a connection produce the same key. Tunneled
+//! traffic is looked into, so that the flows carried by a tunnel are spread
+//! over the cores instead of all following the outer header:
+//!
+//! * GTP-U packets are keyed by the outer addresses and the inner 5-tuple.
+//!   The TEIDs are left out, as each direction of a bearer uses its own.
+//! * QUIC packets sent to [`FlowKeyConfig::quic_port`] are keyed by the
+//!   destination connection ID, which is chosen by the server and survives
+//!   the migration of the client to another address.
+//!
+//! # Examples
+//! ```
+//! use rpkt::flow::{FlowKey, FlowKeyConfig};
+//!
+//! let conf = FlowKeyConfig::default();
+//! let mut packet = [0; 28];
+//! packet[0] = 0x45;
+//! packet[9] = 17;
+//! packet[12..16].copy_from_slice(&[10, 0, 0, 1]);
+//! packet[16..20].copy_from_slice(&[10, 0, 0, 2]);
+//! packet[20..24].copy_from_slice(&[0x30, 0x39, 0, 53]);
+//! let request = FlowKey::from_ip(&packet, &conf).unwrap();
+//!
+//! packet[12..16].copy_from_slice(&[10, 0, 0, 2]);
+//! packet[16..20].copy_from_slice(&[10, 0, 0, 1]);
+//! packet[20..24].copy_from_slice(&[0, 53, 0x30, 0x39]);
+//! let response = FlowKey::from_ip(&packet, &conf).unwrap();
+//!
+//! assert_eq!(request, response);
+//! assert_eq!(request.core_index(4), response.core_index(4));
+//! ```
+
+use crate::ether::{EtherType, VlanStack};
+use crate::ipv4::{IpProtocol, Ipv4Header, IPV4_HEADER_LEN};
+use crate::ipv6::{Ipv6Header, IPV6_HEADER_LEN};
+use crate::quic;
+
+pub use crate::quic::QUIC_CID_LEN_MAX;
+
+const GTPU_HEADER_LEN: usize = 8;
+const GTPU_MSG_TYPE_TPDU: u8 = 255;
+
+/// Controls how [`FlowKey`] looks into tunneled traffic.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FlowKeyConfig {
+    /// The UDP port of GTP-U, or `None` to key GTP-U by the outer header.
+    pub gtpu_port: Option<u16>,
+    /// The UDP port of the QUIC server, or `None` to key QUIC by the 5-tuple.
+    pub quic_port: Option<u16>,
+    /// The length of the connection IDs issued by the QUIC server, needed to
+    /// parse the short header packets.
+    pub quic_cid_len: usize,
+}
+
+impl Default for FlowKeyConfig {
+    fn default() -> Self {
+        Self {
+            gtpu_port: Some(2152),
+            quic_port: Some(443),
+            quic_cid_len: 8,
+        }
+    }
+}
+
+/// The addresses, ports and protocol of a packet.
+///
+/// IPv4 addresses are stored as IPv4-mapped IPv6 addresses. The ports are
+/// zero if the protocol has no ports, or if the packet is a fragment.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub struct FiveTuple {
+    pub src_addr: [u8; 16],
+    pub dst_addr: [u8; 16],
+    pub src_port: u16,
+    pub dst_port: u16,
+    pub protocol: u8,
+}
+
+impl FiveTuple {
+    /// Parse the 5-tuple of the IPv4 or IPv6 packet starting at `buf`.
+    pub fn from_ip(buf: &[u8]) -> Option<Self> {
+        parse_ip(buf).map(|(tuple, _)| tuple)
+    }
+
+    /// Returns the 5-tuple with the endpoints ordered, so that both
+    /// directions of a flow return the same value.
+    pub fn symmetric(&self) -> Self {
+        if (self.src_addr, self.src_port) <= (self.dst_addr, self.dst_port) {
+            *self
+        } else {
+            Self {
+                src_addr: self.dst_addr,
+                dst_addr: self.src_addr,
+                src_port: self.dst_port,
+                dst_port: self.src_port,
+                protocol: self.protocol,
+            }
+        }
+    }
+
+    fn hash_into(&self, hash: &mut u32) {
+        fnv1a(hash, &self.src_addr);
+        fnv1a(hash, &self.dst_addr);
+        fnv1a(hash, &self.src_port.to_be_bytes());
+        fnv1a(hash, &self.dst_port.to_be_bytes());
+        fnv1a(hash, &[self.protocol]);
+    }
+}
+
+/// The symmetric flow key of a packet.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum FlowKey {
+    /// A plain IP packet, or a tunnel that is not looked into.
+    FiveTuple(FiveTuple),
+    /// A GTP-U packet carrying a T-PDU. `outer` only holds the addresses.
+    GtpU { outer: FiveTuple, inner: FiveTuple },
+    /// A QUIC packet sent to the server.
+    Quic {
+        cid: [u8; QUIC_CID_LEN_MAX],
+        cid_len: u8,
+    },
+}
+
+impl FlowKey {
+    /// Compute the key of the Ethernet frame starting at `frame`, skipping
+    /// the VLAN tags.
+    pub fn from_ether(frame: &[u8], conf: &FlowKeyConfig) -> Option<Self> {
+        let stack = VlanStack::parse(frame)?;
+        match stack.ethertype() {
+            EtherType::IPV4 | EtherType::IPV6 => Self::from_ip(&frame[stack.header_len()..], conf),
+            _ => None,
+        }
+    }
+
+    /// Compute the key of the IPv4 or IPv6 packet starting at `buf`.
+    ///
+    /// Returns `None` if the IP header is malformed. If a tunnel header is
+    /// malformed, the packet is keyed by its outer 5-tuple.
+    pub fn from_ip(buf: &[u8], conf: &FlowKeyConfig) -> Option<Self> {
+        let (outer, payload) = parse_ip(buf)?;
+        if outer.protocol == u8::from(IpProtocol::UDP) {
+            if let Some(payload) = payload.get(8..) {
+                if conf.gtpu_port.is_some()
+                    && (conf.gtpu_port == Some(outer.src_port)
+                        || conf.gtpu_port == Some(outer.dst_port))
+                {
+                    if let Some(inner) = gtpu_tpdu(payload).and_then(FiveTuple::from_ip) {
+                        return Some(FlowKey::GtpU {
+                            outer: FiveTuple {
+                                src_port: 0,
+                                dst_port: 0,
+                                ..outer
+                            }
+                            .symmetric(),
+                            inner: inner.symmetric(),
+                        });
+                    }
+                } else if conf.quic_port.is_some() && conf.quic_port == Some(outer.dst_port) {
+                    if let Some(cid) = quic::dest_cid(payload, conf.quic_cid_len) {
+                        let mut key = [0; QUIC_CID_LEN_MAX];
+                        key[..cid.len()].copy_from_slice(cid);
+                        return Some(FlowKey::Quic {
+                            cid: key,
+                            cid_len: cid.len() as u8,
+                        });
+                    }
+                }
+            }
+        }
+        Some(FlowKey::FiveTuple(outer.symmetric()))
+    }
+
+    /// Returns a 32-bit hash of the key, which is stable across runs and
+    /// platforms.
+    pub fn hash32(&self) -> u32 {
+        let mut hash = 0x811c9dc5;
+        match self {
+            FlowKey::FiveTuple(tuple) => tuple.hash_into(&mut hash),
+            FlowKey::GtpU { outer, inner } => {
+                outer.hash_into(&mut hash);
+                inner.hash_into(&mut hash);
+            }
+            FlowKey::Quic { cid, cid_len } => fnv1a(&mut hash, &cid[..usize::from(*cid_len)]),
+        }
+        hash
+    }
+
+    /// Returns the index of the core among `cores` cores that the flow is
+    /// pinned to.
+    ///
+    /// # Panics
+    /// This function panics if `cores` is 0.
+    pub fn core_index(&self, cores: usize) -> usize {
+        assert!(cores > 0);
+        self.hash32() as usize % cores
+    }
+}
+
+fn fnv1a(hash: &mut u32, data: &[u8]) {
+    for b in data {
+        *hash ^= u32::from(*b);
+        *hash = hash.wrapping_mul(0x01000193);
+    }
+}
+
+// Parse the IP header, and return the 5-tuple and the transport layer bytes.
+fn parse_ip(buf: &[u8]) -> Option<(FiveTuple, &[u8])> {
+    let mut tuple = FiveTuple::default();
+    let (protocol, payload, is_frag) = match buf.first()? >> 4 {
+        4 => {
+            let header = Ipv4Header::new(buf).ok()?;
+            let header_len = usize::from(header.header_len());
+            if header_len < IPV4_HEADER_LEN || header_len > buf.len() {
+                return None;
+            }
+            tuple.src_addr[10..12].copy_from_slice(&[0xff, 0xff]);
+            tuple.src_addr[12..].copy_from_slice(&header.source_ip().0);
+            tuple.dst_addr[10..12].copy_from_slice(&[0xff, 0xff]);
+            tuple.dst_addr[12..].copy_from_slice(&header.dest_ip().0);
+            let is_frag = header.more_frags() || header.frag_offset() != 0;
+            (header.protocol(), &buf[header_len..], is_frag)
+        }
+        6 => {
+            let header = Ipv6Header::new(buf).ok()?;
+            tuple.src_addr = header.source_ip().0;
+            tuple.dst_addr = header.dest_ip().0;
+            skip_ipv6_extensions(header.next_header(), &buf[IPV6_HEADER_LEN..])
+        }
+        _ => return None,
+    };
+
+    tuple.protocol = protocol.into();
+    if !is_frag && matches!(protocol, IpProtocol::TCP | IpProtocol::UDP) && payload.len() >= 4 {
+        tuple.src_port = u16::from_be_bytes([payload[0], payload[1]]);
+        tuple.dst_port = u16::from_be_bytes([payload[2], payload[3]]);
+    }
+    Some((tuple, payload))
+}
+
+// Skip the hop-by-hop, routing, destination options and fragment headers.
+pub(crate) fn skip_ipv6_extensions(
+    mut next_header: IpProtocol,
+    mut buf: &[u8],
+) -> (IpProtocol, &[u8], bool) {
+    let mut is_frag = false;
+    loop {
+        let len = match next_header {
+            IpProtocol::HOPOPT | IpProtocol::IPV6_ROUTE | IpProtocol::IPV6_OPTS
+                if buf.len() >= 2 =>
+            {
+                (usize::from(buf[1]) + 1) * 8
+            }
+            IpProtocol::IPV6_FRAG if buf.len() >= 8 => {
+                is_frag = true;
+                8
+            }
+            _ => return (next_header, buf, is_frag),
+        };
+        if len > buf.len() {
+            return (next_header, buf, is_frag);
+        }
+        next_header = IpProtocol::from(buf[0]);
+        buf = &buf[len..];
+    }
+}
+
+// Returns the T-PDU carried by a GTP-U message.
+pub(crate) fn gtpu_tpdu(buf: &[u8]) -> Option<&[u8]> {
+    if buf.len() < GTPU_HEADER_LEN || buf[0] >> 4 != 0x3 || buf[1] != GTPU_MSG_TYPE_TPDU {
+        return None;
+    }
+    if buf[0] & 0x07 == 0 {
+        return Some(&buf[GTPU_HEADER_LEN..]);
+    }
+
+    // The sequence number, the N-PDU number and the next extension header
+    // type are present if any of the flags is set.
+    let mut offset = GTPU_HEADER_LEN + 4;
+    let mut next_ext = *buf.get(offset - 1)?;
+    if buf[0] & 0x04 != 0 {
+        while next_ext != 0 {
+            let len = usize::from(*buf.get(offset)?) * 4;
+            if len == 0 {
+                return None;
+            }
+            offset += len;
+            next_ext = *buf.get(offset - 1)?;
+        }
+    }
+    buf.get(offset..)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ipv4_udp(src: [u8; 4], dst: [u8; 4], sport: u16, dport: u16, payload: &[u8]) -> Vec<u8> {
+        let mut packet = vec![0; 28];
+        packet[0] = 0x45;
+        packet[2..4].copy_from_slice(&((28 + payload.len()) as u16).to_be_bytes());
+        packet[9] = 17;
+        packet[12..16].copy_from_slice(&src);
+        packet[16..20].copy_from_slice(&dst);
+        packet[20..22].copy_from_slice(&sport.to_be_bytes());
+        packet[22..24].copy_from_slice(&dport.to_be_bytes());
+        packet.extend_from_slice(payload);
+        packet
+    }
+
+    fn gtpu(teid: u32, inner: &[u8], ext: bool) -> Vec<u8> {
+        let mut msg = vec![if ext { 0x34 } else { 0x30 }, 0xff, 0, 0];
+        msg.extend_from_slice(&teid.to_be_bytes());
+        if ext {
+            // Sequence number, N-PDU number, a PDU session container.
+            msg.extend_from_slice(&[0, 0, 0, 0x85, 1, 0x10, 0x09, 0]);
+        }
+        msg.extend_from_slice(inner);
+        msg
+    }
+
+    #[test]
+    fn flow_key_gtpu() {
+        let conf = FlowKeyConfig::default();
+        let enb = [192, 168, 0, 1];
+        let upf = [192, 168, 0, 2];
+        let ue = [10, 45, 0, 7];
+        let server = [8, 8, 8, 8];
+
+        let uplink = ipv4_udp(ue, server, 40000, 53, &[]);
+        let downlink = ipv4_udp(server, ue, 53, 40000, &[]);
+        let up = ipv4_udp(enb, upf, 2152, 2152, &gtpu(0x1000, &uplink, true));
+        let down = ipv4_udp(upf, enb, 2152, 2152, &gtpu(0x2000, &downlink, false));
+
+        let up = FlowKey::from_ip(&up, &conf).unwrap();
+        let down = FlowKey::from_ip(&down, &conf).unwrap();
+        assert_eq!(up, down);
+        match up {
+            FlowKey::GtpU { inner, .. } => {
+                assert_eq!(inner, FiveTuple::from_ip(&downlink).unwrap().symmetric())
+            }
+            _ => panic!(),
+        }
+
+        // Another flow of the same bearer.
+        let other = ipv4_udp(ue, server, 40001, 53, &[]);
+        let other = ipv4_udp(enb, upf, 2152, 2152, &gtpu(0x1000, &other, false));
+        assert_ne!(FlowKey::from_ip(&other, &conf).unwrap(), up);
+
+        // Not looked into.
+        let conf = FlowKeyConfig {
+            gtpu_port: None,
+            ..conf
+        };
+        let other = FlowKey::from_ip(&other, &conf).unwrap();
+        assert!(matches!(other, FlowKey::FiveTuple(_)));
+    }
+
+    #[test]
+    fn flow_key_quic() {
+        let conf = FlowKeyConfig::default();
+        let cid = [1, 2, 3, 4, 5, 6, 7, 8];
+
+        let mut initial = vec![0xc0, 0, 0, 0, 1, 8];
+        initial.extend_from_slice(&cid);
+        initial.extend_from_slice(&[0; 16]);
+        let mut short = vec![0x40];
+        short.extend_from_slice(&cid);
+        short.extend_from_slice(&[0; 16]);
+
+        let initial = ipv4_udp([10, 0, 0, 1], [10, 0, 0, 2], 50000, 443, &initial);
+        // The client has migrated to another address.
+        let short = ipv4_udp([10, 0, 0, 9], [10, 0, 0, 2], 50123, 443, &short);
+        let key = FlowKey::from_ip(&initial, &conf).unwrap();
+        assert_eq!(key, FlowKey::from_ip(&short, &conf).unwrap());
+        assert_eq!(
+            key.core_index(7),
+            FlowKey::from_ip(&short, &conf).unwrap().core_index(7)
+        );
+
+        // Packets sent by the server, and without the fixed bit.
+        let reply = ipv4_udp([10, 0, 0, 2], [10, 0, 0, 1], 443, 50000, &[0x40; 9]);
+        assert!(matches!(
+            FlowKey::from_ip(&reply, &conf),
+            Some(FlowKey::FiveTuple(_))
+        ));
+        let bad = ipv4_udp([10, 0, 0, 1], [10, 0, 0, 2], 50000, 443, &[0x00; 9]);
+        assert!(matches!(
+            FlowKey::from_ip(&bad, &conf),
+            Some(FlowKey::FiveTuple(_))
+        ));
+    }
+
+    #[test]
+    fn flow_key_ipv6() {
+        let conf = FlowKeyConfig::default();
+        let mut packet = vec![0; IPV6_HEADER_LEN + 8 + 20];
+        packet[0] = 0x60;
+        packet[6] = 0;
+        packet[8..24]
+            .copy_from_slice(&[0x20, 0x01, 0x0d, 0xb8, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 1]);
+        packet[24..40]
+            .copy_from_slice(&[0x20, 0x01, 0x0d, 0xb8, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 2]);
+        // A hop-by-hop header followed by TCP.
+        packet[40] = 6;
+        packet[48..50].copy_from_slice(&1234u16.to_be_bytes());
+        packet[50..52].copy_from_slice(&80u16.to_be_bytes());
+
+        let tuple = FiveTuple::from_ip(&packet).unwrap();
+        assert_eq!(tuple.protocol, 6);
+        assert_eq!((tuple.src_port, tuple.dst_port), (1234, 80));
+
+        let mut frame = vec![0; 14];
+        frame[12..14].copy_from_slice(&[0x86, 0xdd]);
+        frame.extend_from_slice(&packet);
+        assert_eq!(
+            FlowKey::from_ether(&frame, &conf),
+            Some(FlowKey::FiveTuple(tuple.symmetric()))
+        );
+
+        assert_eq!(FiveTuple::from_ip(&packet[..20]), None);
+        assert_eq!(FiveTuple::from_ip(&[0x10; 40]), None);
+    }
+}
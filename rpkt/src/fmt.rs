@@ -0,0 +1,142 @@
+//! Human-readable dumps of packet buffers.
+
+use std::fmt::Write;
+
+const ROW_LEN: usize = 16;
+
+/// Format `buf` as rows of 16 bytes, each holding the offset, the hex bytes
+/// and the printable ASCII characters.
+///
+/// # Examples
+/// ```
+/// let dump = rpkt::fmt::hexdump(b"rpkt\x00\x01");
+/// assert_eq!(
+///     dump,
+///     "0000  72 70 6b 74 00 01                                |rpkt..|\n"
+/// );
+/// ```
+pub fn hexdump(buf: &[u8]) -> String {
+    let mut out = String::new();
+    for (row, chunk) in buf.chunks(ROW_LEN).enumerate() {
+        write_row(&mut out, "", row * ROW_LEN, chunk);
+        out.push(' ');
+        out.push('|');
+        for &b in chunk {
+            out.push(printable(b));
+        }
+        out.push_str("|\n");
+    }
+    out
+}
+
+/// Format the rows where `a` and `b` differ.
+///
+/// Each differing row is printed for `a` with a `-` prefix and for `b` with a
+/// `+` prefix, followed by a line marking the differing bytes with `^^`.
+/// Bytes that exist in only one of the buffers count as different. Returns an
+/// empty string if the buffers are equal.
+///
+/// # Examples
+/// ```
+/// let diff = rpkt::fmt::hexdiff(&[0x45, 0x00, 0x01], &[0x45, 0x10]);
+/// let lines: Vec<_> = diff.lines().collect();
+/// assert_eq!(lines[0], "- 0000  45 00 01");
+/// assert_eq!(lines[1], "+ 0000  45 10");
+/// assert_eq!(lines[2], "           ^^ ^^");
+/// ```
+pub fn hexdiff(a: &[u8], b: &[u8]) -> String {
+    let mut out = String::new();
+    let len = a.len().max(b.len());
+    let mut offset = 0;
+    while offset < len {
+        let end = (offset + ROW_LEN).min(len);
+        let row_a = &a[offset.min(a.len())..end.min(a.len())];
+        let row_b = &b[offset.min(b.len())..end.min(b.len())];
+        if row_a != row_b {
+            write_row(&mut out, "- ", offset, row_a);
+            out.push('\n');
+            write_row(&mut out, "+ ", offset, row_b);
+            out.push('\n');
+
+            let mut marks = String::new();
+            for i in 0..end - offset {
+                if i == ROW_LEN / 2 {
+                    marks.push(' ');
+                }
+                let diff = row_a.get(i) != row_b.get(i);
+                marks.push_str(if diff { " ^^" } else { "   " });
+            }
+            let _ = writeln!(out, "{:7}{}", "", marks.trim_end());
+        }
+        offset = end;
+    }
+    out
+}
+
+// Write the prefix, the offset and the hex bytes of a row, padding the hex
+// bytes to a full row if the row is followed by the ASCII column.
+fn write_row(out: &mut String, prefix: &str, offset: usize, chunk: &[u8]) {
+    let _ = write!(out, "{}{:04x} ", prefix, offset);
+    for (i, b) in chunk.iter().enumerate() {
+        if i == ROW_LEN / 2 {
+            out.push(' ');
+        }
+        let _ = write!(out, " {:02x}", b);
+    }
+    if prefix.is_empty() {
+        for i in chunk.len()..ROW_LEN {
+            if i == ROW_LEN / 2 {
+                out.push(' ');
+            }
+            out.push_str("   ");
+        }
+    }
+}
+
+fn printable(b: u8) -> char {
+    if b.is_ascii_graphic() || b == b' ' {
+        b as char
+    } else {
+        '.'
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn hexdump_rows() {
+        let buf: Vec<u8> = (0x30..0x30 + 18).collect();
+        assert_eq!(
+            hexdump(&buf),
+            "0000  30 31 32 33 34 35 36 37  38 39 3a 3b 3c 3d 3e 3f |0123456789:;<=>?|\n\
+             0010  40 41                                            |@A|\n"
+        );
+        assert_eq!(hexdump(&[]), "");
+    }
+
+    #[test]
+    fn hexdiff_rows() {
+        let a = [0u8; 40];
+        assert_eq!(hexdiff(&a, &a), "");
+
+        let mut b = a;
+        b[17] = 0xff;
+        assert_eq!(
+            hexdiff(&a, &b),
+            "- 0010  00 00 00 00 00 00 00 00  00 00 00 00 00 00 00 00\n\
+             + 0010  00 ff 00 00 00 00 00 00  00 00 00 00 00 00 00 00\n\
+             \x20          ^^\n"
+        );
+
+        // The missing bytes of a shorter buffer differ.
+        let diff = hexdiff(&a[..33], &a);
+        assert_eq!(
+            diff,
+            "- 0020  00\n\
+             + 0020  00 00 00 00 00 00 00 00\n\
+             \x20          ^^ ^^ ^^ ^^ ^^ ^^ ^^\n"
+        );
+    }
+}
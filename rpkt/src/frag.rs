@@ -0,0 +1,504 @@
+//! Split oversized IP packets into fragments.
+//!
+//! [`Ipv4Fragmenter`] and [`Ipv6Fragmenter`] borrow an IP packet and write one
+//! fragment at a time into caller-provided buffers, so the fragments can be
+//! built directly in the buffers that are sent, e.g. `Mbuf`s. The caller asks
+//! for the length of the next fragment with `next_len`, and passes a buffer
+//! whose chunk holds at least that many bytes to `next_fragment`. The excess
+//! bytes are trimmed off the end of the buffer.
+//!
+//! # Examples
+//! ```
+//! use rpkt::frag::Ipv4Fragmenter;
+//! use rpkt::ipv4::*;
+//! use rpkt::CursorMut;
+//!
+//! let mut packet = [0; 1000];
+//! let mut header = IPV4_HEADER_TEMPLATE;
+//! header.set_dont_frag(false);
+//! header.set_packet_len(1000);
+//! packet[..IPV4_HEADER_LEN].copy_from_slice(header.as_bytes());
+//!
+//! let mut fragmenter = Ipv4Fragmenter::new(&packet[..], 576).unwrap();
+//! let mut lens = Vec::new();
+//! while let Some(len) = fragmenter.next_len() {
+//!     let mut buf = [0; 576];
+//!     let frag = fragmenter.next_fragment(CursorMut::new(&mut buf[..])).unwrap();
+//!     assert_eq!(usize::from(frag.packet_len()), len);
+//!     assert!(frag.verify_checksum());
+//!     lens.push(len);
+//! }
+//! assert_eq!(lens, [572, 448]);
+//! ```
+
+use byteorder::{ByteOrder, NetworkEndian};
+
+use crate::ipv4::{IpProtocol, Ipv4Header, Ipv4Packet, IPV4_HEADER_LEN, IPV4_HEADER_LEN_MAX};
+use crate::ipv6::extentions::{FragHeader, FRAG_HEADER_LEN};
+use crate::ipv6::{Ipv6Header, Ipv6Packet, IPV6_HEADER_LEN};
+use crate::PktMut;
+
+/// Splits an IPv4 packet into fragments.
+///
+/// The first fragment carries all the options of the packet, and the other
+/// fragments only carry the options with the copied flag (RFC 791). A packet
+/// that is already a fragment can be split further, the offsets and the
+/// more-fragments flag of the last piece are derived from the original ones.
+#[derive(Debug)]
+pub struct Ipv4Fragmenter<'a> {
+    header: &'a [u8],
+    // The copied options of the non-first fragments, padded to 4 bytes.
+    copied_options: [u8; IPV4_HEADER_LEN_MAX - IPV4_HEADER_LEN],
+    copied_len: usize,
+    payload: &'a [u8],
+    mtu: usize,
+    offset: usize,
+    done: bool,
+}
+
+impl<'a> Ipv4Fragmenter<'a> {
+    /// Create a fragmenter of the IPv4 `packet` for a link of `mtu` bytes.
+    ///
+    /// Returns `None` if `packet` is not a well-formed IPv4 packet, if the
+    /// packet needs fragmentation while the don't-fragment flag is set, or if
+    /// `mtu` can not hold 8 bytes of payload behind the header.
+    pub fn new(packet: &'a [u8], mtu: usize) -> Option<Self> {
+        let header = Ipv4Header::new(packet).ok()?;
+        let header_len = usize::from(header.header_len());
+        let packet_len = usize::from(header.packet_len());
+        if header_len < IPV4_HEADER_LEN || packet_len < header_len || packet_len > packet.len() {
+            return None;
+        }
+        if header.dont_frag() && packet_len > mtu {
+            return None;
+        }
+        if mtu < header_len + 8 {
+            return None;
+        }
+        if usize::from(header.frag_offset()) + packet_len - header_len > 0xffff {
+            return None;
+        }
+
+        let mut copied_options = [0; IPV4_HEADER_LEN_MAX - IPV4_HEADER_LEN];
+        let mut copied_len = 0;
+        let mut options = &packet[IPV4_HEADER_LEN..header_len];
+        while let Some(&kind) = options.first() {
+            let len = match kind {
+                // End of option list.
+                0 => break,
+                // No operation.
+                1 => 1,
+                _ => match options.get(1) {
+                    Some(&len) if (2..=options.len()).contains(&usize::from(len)) => {
+                        usize::from(len)
+                    }
+                    _ => return None,
+                },
+            };
+            if kind & 0x80 != 0 {
+                copied_options[copied_len..copied_len + len].copy_from_slice(&options[..len]);
+                copied_len += len;
+            }
+            options = &options[len..];
+        }
+        // Pad with end of option list.
+        copied_len = (copied_len + 3) & !3;
+
+        Some(Self {
+            header: &packet[..header_len],
+            copied_options,
+            copied_len,
+            payload: &packet[header_len..packet_len],
+            mtu,
+            offset: 0,
+            done: false,
+        })
+    }
+
+    fn header_len(&self) -> usize {
+        if self.offset == 0 {
+            self.header.len()
+        } else {
+            IPV4_HEADER_LEN + self.copied_len
+        }
+    }
+
+    // The payload length of the next fragment.
+    fn next_payload_len(&self) -> usize {
+        let remaining = self.payload.len() - self.offset;
+        let max = (self.mtu - self.header_len()) & !7;
+        remaining.min(max)
+    }
+
+    /// Returns whether all the fragments are written.
+    pub fn is_done(&self) -> bool {
+        self.done
+    }
+
+    /// Returns the length of the next fragment, or `None` if all the fragments
+    /// are written.
+    pub fn next_len(&self) -> Option<usize> {
+        if self.done {
+            None
+        } else {
+            Some(self.header_len() + self.next_payload_len())
+        }
+    }
+
+    /// Write the next fragment into `buf`.
+    ///
+    /// Returns `buf` back if all the fragments are written.
+    ///
+    /// # Panics
+    /// The function panics if the chunk of `buf` is shorter than
+    /// [`Ipv4Fragmenter::next_len`].
+    pub fn next_fragment<T: PktMut>(&mut self, mut buf: T) -> Result<Ipv4Packet<T>, T> {
+        let frag_len = match self.next_len() {
+            Some(len) => len,
+            None => return Err(buf),
+        };
+        let header_len = self.header_len();
+        let payload_len = self.next_payload_len();
+        let end = self.offset + payload_len;
+
+        let data = buf.chunk_mut();
+        assert!(data.len() >= frag_len);
+        data[..IPV4_HEADER_LEN].copy_from_slice(&self.header[..IPV4_HEADER_LEN]);
+        if self.offset == 0 {
+            data[IPV4_HEADER_LEN..header_len].copy_from_slice(&self.header[IPV4_HEADER_LEN..]);
+        } else {
+            data[IPV4_HEADER_LEN..header_len]
+                .copy_from_slice(&self.copied_options[..self.copied_len]);
+        }
+        data[header_len..frag_len].copy_from_slice(&self.payload[self.offset..end]);
+
+        let orig = Ipv4Header::new_unchecked(self.header);
+        let mut header = Ipv4Header::new_unchecked(&mut data[..header_len]);
+        header.set_header_len(header_len as u8);
+        header.set_packet_len(frag_len as u16);
+        header.set_frag_offset(orig.frag_offset() + self.offset as u16);
+        header.set_more_frags(end < self.payload.len() || orig.more_frags());
+        header.set_checksum(0);
+        let checksum = !crate::checksum_utils::from_slice(&data[..header_len]);
+        Ipv4Header::new_unchecked(&mut data[..header_len]).set_checksum(checksum);
+
+        let excess = buf.chunk().len() - frag_len;
+        buf.trim_off(excess);
+        self.offset = end;
+        self.done = end == self.payload.len();
+        Ok(Ipv4Packet::parse_unchecked(buf))
+    }
+}
+
+/// Generates the identification of IPv6 fragments.
+///
+/// The identifications are pseudo-random, so that they are hard to predict
+/// by off-path attackers (RFC 7739), and distinct for 2^32 consecutive calls.
+#[derive(Debug, Clone)]
+pub struct FragIdentGen {
+    counter: u32,
+    key: u32,
+}
+
+impl FragIdentGen {
+    /// Create a generator from a random `seed`.
+    pub fn new(seed: u64) -> Self {
+        Self {
+            counter: seed as u32,
+            key: (seed >> 32) as u32 | 1,
+        }
+    }
+
+    /// Returns the next identification.
+    pub fn next_ident(&mut self) -> u32 {
+        self.counter = self.counter.wrapping_add(1);
+        // A bijective mix of the counter, so no identification repeats until
+        // the counter wraps around.
+        let mut x = self.counter.wrapping_mul(self.key);
+        x ^= x >> 16;
+        x = x.wrapping_mul(0x7feb_352d);
+        x ^= x >> 15;
+        x = x.wrapping_mul(0x846c_a68b);
+        x ^ (x >> 16)
+    }
+}
+
+/// Splits an IPv6 packet into fragments.
+///
+/// The unfragmentable part of the packet, i.e. the IPv6 header, and the
+/// hop-by-hop options, destination options and routing headers up to the last
+/// routing header, is repeated in every fragment, followed by a fragment
+/// header (RFC 8200).
+#[derive(Debug)]
+pub struct Ipv6Fragmenter<'a> {
+    unfragmentable: &'a [u8],
+    // The offset of the next header field to patch with `IPV6_FRAG`.
+    next_header_offset: usize,
+    next_header: IpProtocol,
+    ident: u32,
+    payload: &'a [u8],
+    mtu: usize,
+    offset: usize,
+    done: bool,
+}
+
+impl<'a> Ipv6Fragmenter<'a> {
+    /// Create a fragmenter of the IPv6 `packet` for a link of `mtu` bytes,
+    /// with the fragment identification `ident`.
+    ///
+    /// Returns `None` if `packet` is not a well-formed IPv6 packet, or if
+    /// `mtu` can not hold 8 bytes of payload behind the unfragmentable part
+    /// and the fragment header.
+    pub fn new(packet: &'a [u8], mtu: usize, ident: u32) -> Option<Self> {
+        let header = Ipv6Header::new(packet).ok()?;
+        let packet_len = IPV6_HEADER_LEN + usize::from(header.payload_len());
+        if packet_len > packet.len() {
+            return None;
+        }
+
+        let mut next_header = header.next_header();
+        let mut next_header_offset = 6;
+        let mut offset = IPV6_HEADER_LEN;
+        // The end of the unfragmentable part found so far.
+        let mut unfrag = (offset, next_header_offset, next_header);
+        while matches!(
+            next_header,
+            IpProtocol::HOPOPT | IpProtocol::IPV6_OPTS | IpProtocol::IPV6_ROUTE
+        ) {
+            let ext = packet.get(offset..offset + 2)?;
+            let ext_len = (usize::from(ext[1]) + 1) * 8;
+            if offset + ext_len > packet_len {
+                return None;
+            }
+            let is_route = next_header == IpProtocol::IPV6_ROUTE;
+            let is_hbh = next_header == IpProtocol::HOPOPT;
+            next_header_offset = offset;
+            next_header = IpProtocol::from(packet[offset]);
+            offset += ext_len;
+            // Destination options only belong to the unfragmentable part if a
+            // routing header follows.
+            if is_route || is_hbh {
+                unfrag = (offset, next_header_offset, next_header);
+            }
+        }
+        let (unfrag_len, next_header_offset, next_header) = unfrag;
+        if mtu < unfrag_len + FRAG_HEADER_LEN + 8 {
+            return None;
+        }
+
+        Some(Self {
+            unfragmentable: &packet[..unfrag_len],
+            next_header_offset,
+            next_header,
+            ident,
+            payload: &packet[unfrag_len..packet_len],
+            mtu,
+            offset: 0,
+            done: false,
+        })
+    }
+
+    fn next_payload_len(&self) -> usize {
+        let remaining = self.payload.len() - self.offset;
+        let max = (self.mtu - self.unfragmentable.len() - FRAG_HEADER_LEN) & !7;
+        remaining.min(max)
+    }
+
+    /// Returns whether all the fragments are written.
+    pub fn is_done(&self) -> bool {
+        self.done
+    }
+
+    /// Returns the length of the next fragment, or `None` if all the fragments
+    /// are written.
+    pub fn next_len(&self) -> Option<usize> {
+        if self.done {
+            None
+        } else {
+            Some(self.unfragmentable.len() + FRAG_HEADER_LEN + self.next_payload_len())
+        }
+    }
+
+    /// Write the next fragment into `buf`.
+    ///
+    /// Returns `buf` back if all the fragments are written.
+    ///
+    /// # Panics
+    /// The function panics if the chunk of `buf` is shorter than
+    /// [`Ipv6Fragmenter::next_len`].
+    pub fn next_fragment<T: PktMut>(&mut self, mut buf: T) -> Result<Ipv6Packet<T>, T> {
+        let frag_len = match self.next_len() {
+            Some(len) => len,
+            None => return Err(buf),
+        };
+        let unfrag_len = self.unfragmentable.len();
+        let payload_len = self.next_payload_len();
+        let end = self.offset + payload_len;
+
+        let data = buf.chunk_mut();
+        assert!(data.len() >= frag_len);
+        data[..unfrag_len].copy_from_slice(self.unfragmentable);
+        data[self.next_header_offset] = IpProtocol::IPV6_FRAG.into();
+        NetworkEndian::write_u16(&mut data[4..6], (frag_len - IPV6_HEADER_LEN) as u16);
+
+        let mut frag = FragHeader::new_unchecked(&mut data[unfrag_len..]);
+        frag.set_next_header(self.next_header);
+        frag.adjust_reserved();
+        frag.set_frag_off((self.offset / 8) as u16);
+        frag.set_m_flag(end < self.payload.len());
+        frag.set_ident(self.ident);
+        data[unfrag_len + FRAG_HEADER_LEN..frag_len]
+            .copy_from_slice(&self.payload[self.offset..end]);
+
+        let excess = buf.chunk().len() - frag_len;
+        buf.trim_off(excess);
+        self.offset = end;
+        self.done = end == self.payload.len();
+        Ok(Ipv6Packet::parse_unchecked(buf))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bytes::Buf;
+
+    use super::*;
+    use crate::ipv4::IPV4_HEADER_TEMPLATE;
+    use crate::ipv6::extentions::FragPacket;
+    use crate::CursorMut;
+
+    fn ipv4_packet(options: &[u8], payload_len: usize) -> Vec<u8> {
+        let header_len = IPV4_HEADER_LEN + options.len();
+        let mut header = IPV4_HEADER_TEMPLATE;
+        header.set_dont_frag(false);
+        header.set_header_len(header_len as u8);
+        header.set_packet_len((header_len + payload_len) as u16);
+        header.set_ident(0x1234);
+
+        let mut packet = header.as_bytes().to_vec();
+        packet.extend_from_slice(options);
+        packet.extend((0..payload_len).map(|i| i as u8));
+        packet
+    }
+
+    #[test]
+    fn ipv4_fragments() {
+        // A copied security option and a non-copied record route option.
+        let options = [0x82, 0x04, 0xaa, 0xbb, 0x07, 0x07, 0x04, 0, 0, 0, 0, 0];
+        let packet = ipv4_packet(&options, 3000);
+        let mut fragmenter = Ipv4Fragmenter::new(&packet, 1500).unwrap();
+
+        let mut payload = Vec::new();
+        let mut offsets = Vec::new();
+        while let Some(len) = fragmenter.next_len() {
+            let mut bytes = [0xff; 1600];
+            let mut buf = CursorMut::new(&mut bytes[..]);
+            buf.advance(20);
+            let frag = fragmenter.next_fragment(buf).unwrap();
+            assert_eq!(frag.buf().remaining(), len);
+            assert!(len <= 1500);
+            assert!(frag.verify_checksum());
+            assert_eq!(frag.ident(), 0x1234);
+
+            let more_frags = frag.more_frags();
+            offsets.push((frag.frag_offset(), frag.header_len(), more_frags));
+            if frag.frag_offset() > 0 {
+                assert_eq!(frag.option_bytes(), &[0x82, 0x04, 0xaa, 0xbb]);
+            }
+            payload.extend_from_slice(frag.payload().chunk());
+        }
+        assert_eq!(
+            offsets,
+            [(0, 32, true), (1464, 24, true), (2936, 24, false)]
+        );
+        assert_eq!(&payload[..], &packet[32..]);
+
+        let mut bytes = [0; 64];
+        assert!(fragmenter
+            .next_fragment(CursorMut::new(&mut bytes[..]))
+            .is_err());
+    }
+
+    #[test]
+    fn ipv4_fragments_boundary() {
+        // A packet that fits in the mtu is written as a single fragment.
+        let packet = ipv4_packet(&[], 0);
+        let mut fragmenter = Ipv4Fragmenter::new(&packet, 1500).unwrap();
+        assert_eq!(fragmenter.next_len(), Some(20));
+        let mut bytes = [0; 64];
+        let frag = fragmenter
+            .next_fragment(CursorMut::new(&mut bytes[..]))
+            .unwrap();
+        assert!(!frag.more_frags());
+        assert_eq!(fragmenter.next_len(), None);
+
+        // A fragment is split further.
+        let mut packet = ipv4_packet(&[], 16);
+        let mut header = Ipv4Header::new_unchecked(&mut packet[..]);
+        header.set_more_frags(true);
+        header.set_frag_offset(800);
+        let mut fragmenter = Ipv4Fragmenter::new(&packet, 28).unwrap();
+        let mut frags = Vec::new();
+        while fragmenter.next_len().is_some() {
+            let mut bytes = [0; 64];
+            let frag = fragmenter
+                .next_fragment(CursorMut::new(&mut bytes[..]))
+                .unwrap();
+            frags.push((frag.frag_offset(), frag.more_frags()));
+        }
+        assert_eq!(frags, [(800, true), (808, true)]);
+
+        // The don't-fragment flag is respected, and the mtu must hold 8 bytes.
+        let mut packet = ipv4_packet(&[], 100);
+        assert!(Ipv4Fragmenter::new(&packet, 27).is_none());
+        Ipv4Header::new_unchecked(&mut packet[..]).set_dont_frag(true);
+        assert!(Ipv4Fragmenter::new(&packet, 100).is_none());
+        assert!(Ipv4Fragmenter::new(&packet, 120).is_some());
+    }
+
+    #[test]
+    fn ipv6_fragments() {
+        // An IPv6 header, a hop-by-hop header and a UDP payload.
+        let mut packet = vec![0; IPV6_HEADER_LEN + 8 + 2000];
+        let mut header = Ipv6Header::new_unchecked(&mut packet[..]);
+        header.adjust_version();
+        header.set_payload_len(8 + 2000);
+        header.set_next_header(IpProtocol::HOPOPT);
+        packet[IPV6_HEADER_LEN] = IpProtocol::UDP.into();
+        for (i, b) in packet[IPV6_HEADER_LEN + 8..].iter_mut().enumerate() {
+            *b = i as u8;
+        }
+
+        let mut idents = FragIdentGen::new(7);
+        let ident = idents.next_ident();
+        assert_ne!(ident, idents.next_ident());
+
+        let mut fragmenter = Ipv6Fragmenter::new(&packet, 1280, ident).unwrap();
+        let mut payload = Vec::new();
+        let mut frags = Vec::new();
+        while let Some(len) = fragmenter.next_len() {
+            let mut bytes = [0; 1400];
+            let pkt = fragmenter
+                .next_fragment(CursorMut::new(&mut bytes[..]))
+                .unwrap();
+            assert!(len <= 1280);
+            assert_eq!(usize::from(pkt.payload_len()) + IPV6_HEADER_LEN, len);
+            assert_eq!(pkt.next_header(), IpProtocol::HOPOPT);
+
+            let mut buf = pkt.release();
+            assert_eq!(
+                buf.chunk()[IPV6_HEADER_LEN],
+                u8::from(IpProtocol::IPV6_FRAG)
+            );
+            buf.advance(IPV6_HEADER_LEN + 8);
+            let frag = FragPacket::parse(buf).unwrap();
+            assert_eq!(frag.next_header(), IpProtocol::UDP);
+            assert_eq!(frag.ident(), ident);
+            frags.push((usize::from(frag.frag_off()) * 8, frag.m_flag()));
+            payload.extend_from_slice(frag.payload().chunk());
+        }
+        assert_eq!(frags, [(0, true), (1224, false)]);
+        assert_eq!(&payload[..], &packet[IPV6_HEADER_LEN + 8..]);
+        assert!(fragmenter.is_done());
+    }
+}
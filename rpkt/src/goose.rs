@@ -0,0 +1,403 @@
+//! GOOSE, the Generic Object Oriented Substation Event of IEC 61850-8-1.
+//!
+//! A GOOSE message is carried directly by an Ethernet frame, usually with a
+//! priority tag. It starts with an 8-byte header holding the application
+//! identifier and the length, followed by the `goosePdu` encoded with the
+//! Basic Encoding Rules. [`GoosePacket::pdu`] iterates over the fields of the
+//! PDU with a [`BerIter`].
+//!
+//! The Sampled Values of [`crate::sv`] share the same header.
+//!
+//! # Examples
+//! ```
+//! use rpkt::goose::{GooseField, GoosePacket};
+//! use rpkt::Cursor;
+//!
+//! let bytes = [
+//!     0x00, 0x01, 0x00, 0x12, 0x00, 0x00, 0x00, 0x00, // header
+//!     0x61, 0x08, // goosePdu
+//!     0x85, 0x01, 0x03, // stNum
+//!     0x86, 0x03, 0x01, 0x00, 0x00, // sqNum
+//! ];
+//! let pkt = GoosePacket::parse(Cursor::new(&bytes[..])).unwrap();
+//! assert_eq!(pkt.appid(), 1);
+//! assert_eq!(pkt.st_num(), Some(3));
+//! assert_eq!(pkt.sq_num(), Some(0x10000));
+//! ```
+
+use byteorder::{ByteOrder, NetworkEndian};
+use bytes::Buf;
+
+use crate::ether::{EtherPayload, EtherType};
+use crate::tlv::{BerIter, BerTlv};
+use crate::PktBuf;
+
+/// The length of the header of a GOOSE or SV message.
+pub const GOOSE_HEADER_LEN: usize = 8;
+
+pub const GOOSE_HEADER_TEMPLATE: GooseHeader<[u8; GOOSE_HEADER_LEN]> = GooseHeader {
+    buf: [0x00, 0x00, 0x00, 0x08, 0x00, 0x00, 0x00, 0x00],
+};
+
+/// The tag of the `goosePdu`, `[APPLICATION 1]` constructed.
+pub const GOOSE_PDU_TAG: u32 = 0x61;
+
+// The simulation bit of the first reserved field, added by edition 2.
+const SIMULATION_FLAG: u16 = 0x8000;
+
+enum_sim! {
+    /// The tag of a field of the `goosePdu`.
+    pub struct GooseField (u32) {
+        GOCB_REF = 0x80,
+        TIME_ALLOWED_TO_LIVE = 0x81,
+        DAT_SET = 0x82,
+        GO_ID = 0x83,
+        /// The time of the last state change, as an 8-byte `UtcTime`.
+        T = 0x84,
+        ST_NUM = 0x85,
+        SQ_NUM = 0x86,
+        SIMULATION = 0x87,
+        CONF_REV = 0x88,
+        NDS_COM = 0x89,
+        NUM_DAT_SET_ENTRIES = 0x8a,
+        /// The values of the data set, a constructed record.
+        ALL_DATA = 0xab,
+    }
+}
+
+header_field_range_accessors! {
+    (appid, appid_mut, 0..2),
+    (length, length_mut, 2..4),
+    (reserved1, reserved1_mut, 4..6),
+    (reserved2, reserved2_mut, 6..8),
+}
+
+/// The header of a GOOSE or SV message.
+#[derive(Clone, Copy, Debug)]
+pub struct GooseHeader<T> {
+    buf: T,
+}
+
+impl<T: AsRef<[u8]>> GooseHeader<T> {
+    #[inline]
+    pub fn new(buf: T) -> Result<Self, T> {
+        if buf.as_ref().len() >= GOOSE_HEADER_LEN {
+            Ok(Self { buf })
+        } else {
+            Err(buf)
+        }
+    }
+
+    #[inline]
+    pub fn new_unchecked(buf: T) -> Self {
+        Self { buf }
+    }
+
+    #[inline]
+    pub fn as_bytes(&self) -> &[u8] {
+        &self.buf.as_ref()[0..GOOSE_HEADER_LEN]
+    }
+
+    #[inline]
+    pub fn to_owned(&self) -> GooseHeader<[u8; GOOSE_HEADER_LEN]> {
+        let mut buf = [0; GOOSE_HEADER_LEN];
+        buf.copy_from_slice(self.as_bytes());
+        GooseHeader { buf }
+    }
+
+    #[inline]
+    pub fn appid(&self) -> u16 {
+        let data = appid(self.buf.as_ref());
+        NetworkEndian::read_u16(data)
+    }
+
+    /// Returns the length field, which counts the message from the APPID to
+    /// the end of the PDU.
+    #[inline]
+    pub fn packet_len(&self) -> u16 {
+        let data = length(self.buf.as_ref());
+        NetworkEndian::read_u16(data)
+    }
+
+    #[inline]
+    pub fn reserved1(&self) -> u16 {
+        let data = reserved1(self.buf.as_ref());
+        NetworkEndian::read_u16(data)
+    }
+
+    #[inline]
+    pub fn reserved2(&self) -> u16 {
+        let data = reserved2(self.buf.as_ref());
+        NetworkEndian::read_u16(data)
+    }
+
+    /// Returns whether the message is sent by a test device, as flagged in
+    /// the first reserved field.
+    #[inline]
+    pub fn simulation(&self) -> bool {
+        self.reserved1() & SIMULATION_FLAG != 0
+    }
+}
+
+impl<T: AsMut<[u8]>> GooseHeader<T> {
+    #[inline]
+    pub fn set_appid(&mut self, value: u16) {
+        let data = appid_mut(self.buf.as_mut());
+        NetworkEndian::write_u16(data, value)
+    }
+
+    #[inline]
+    pub fn set_packet_len(&mut self, value: u16) {
+        let data = length_mut(self.buf.as_mut());
+        NetworkEndian::write_u16(data, value)
+    }
+
+    #[inline]
+    pub fn set_reserved1(&mut self, value: u16) {
+        let data = reserved1_mut(self.buf.as_mut());
+        NetworkEndian::write_u16(data, value)
+    }
+
+    #[inline]
+    pub fn set_reserved2(&mut self, value: u16) {
+        let data = reserved2_mut(self.buf.as_mut());
+        NetworkEndian::write_u16(data, value)
+    }
+}
+
+packet_base! {
+    /// A GOOSE message.
+    pub struct GoosePacket: GooseHeader {
+        header_len: GOOSE_HEADER_LEN,
+        get_methods: [
+            (appid, u16),
+            (packet_len, u16),
+            (reserved1, u16),
+            (reserved2, u16),
+            (simulation, bool),
+        ],
+        set_methods: [
+            (set_appid, value: u16),
+            (set_reserved1, value: u16),
+            (set_reserved2, value: u16),
+        ],
+        unchecked_set_methods: [
+            (set_packet_len_unchecked, set_packet_len, value: u16),
+        ]
+    }
+}
+
+impl<T: Buf> GoosePacket<T> {
+    /// Parse a GOOSE message, whose length field must cover the header and
+    /// fit in `buf`.
+    #[inline]
+    pub fn parse(buf: T) -> Result<GoosePacket<T>, T> {
+        traced_parse!("goose", buf, |_: &Self| GOOSE_HEADER_LEN, {
+            if buf.chunk().len() < GOOSE_HEADER_LEN {
+                return Err(buf);
+            }
+
+            let packet = GoosePacket::parse_unchecked(buf);
+            let packet_len = usize::from(packet.packet_len());
+            if packet_len >= GOOSE_HEADER_LEN && packet_len <= packet.buf.remaining() {
+                Ok(packet)
+            } else {
+                Err(packet.release())
+            }
+        })
+    }
+
+    /// Returns the encoded PDU in the chunk of the buffer, without the
+    /// padding of the Ethernet frame.
+    #[inline]
+    pub fn pdu_bytes(&self) -> &[u8] {
+        apdu_bytes(self.buf.chunk(), self.packet_len())
+    }
+
+    /// Iterate over the fields of the `goosePdu`.
+    ///
+    /// Returns `None` if the PDU does not start with a well-formed record
+    /// tagged with [`GOOSE_PDU_TAG`].
+    #[inline]
+    pub fn pdu(&self) -> Option<BerIter<'_>> {
+        pdu_fields(self.pdu_bytes(), GOOSE_PDU_TAG)
+    }
+
+    /// Returns the first field of the PDU with `tag`.
+    #[inline]
+    pub fn field(&self, tag: GooseField) -> Option<BerTlv<'_>> {
+        self.pdu()?.find_tag(tag.into())
+    }
+
+    #[inline]
+    pub fn gocb_ref(&self) -> Option<&[u8]> {
+        self.field(GooseField::GOCB_REF).map(|tlv| tlv.value)
+    }
+
+    #[inline]
+    pub fn go_id(&self) -> Option<&[u8]> {
+        self.field(GooseField::GO_ID).map(|tlv| tlv.value)
+    }
+
+    /// Returns the state number, which is increased on every change of the
+    /// data set.
+    #[inline]
+    pub fn st_num(&self) -> Option<u64> {
+        self.field(GooseField::ST_NUM)?.as_uint()
+    }
+
+    /// Returns the sequence number, which is increased on every
+    /// retransmission of the same state.
+    #[inline]
+    pub fn sq_num(&self) -> Option<u64> {
+        self.field(GooseField::SQ_NUM)?.as_uint()
+    }
+}
+
+impl<T: PktBuf> GoosePacket<T> {
+    /// Returns the encoded PDU, without the padding of the Ethernet frame.
+    #[inline]
+    pub fn payload(self) -> T {
+        let packet_len = usize::from(self.packet_len());
+        assert!(packet_len <= self.buf.remaining());
+        let trim_size = self.buf.remaining() - packet_len;
+
+        let mut buf = self.release();
+        if trim_size > 0 {
+            buf.trim_off(trim_size);
+        }
+
+        buf.advance(GOOSE_HEADER_LEN);
+
+        buf
+    }
+}
+
+impl<T: crate::PktMut> GoosePacket<T> {
+    /// Prepend the header, the length field is set to cover the whole PDU
+    /// in `buf`.
+    #[inline]
+    pub fn prepend_header<HT: AsRef<[u8]>>(mut buf: T, header: &GooseHeader<HT>) -> GoosePacket<T> {
+        let packet_len = prepend_apdu_header(&mut buf, header);
+        let mut pkt = GoosePacket::parse_unchecked(buf);
+        pkt.set_packet_len_unchecked(packet_len);
+        pkt
+    }
+}
+
+impl<T> EtherPayload for GoosePacket<T> {
+    const ETHERTYPE: EtherType = EtherType::GOOSE;
+}
+
+// Returns the PDU behind the header in `chunk`, which may be cut short by the
+// end of the chunk.
+pub(crate) fn apdu_bytes(chunk: &[u8], packet_len: u16) -> &[u8] {
+    let end = usize::from(packet_len).min(chunk.len());
+    &chunk[GOOSE_HEADER_LEN..end]
+}
+
+// Returns the iterator over the contents of the leading `tag` record of `pdu`.
+pub(crate) fn pdu_fields(pdu: &[u8], tag: u32) -> Option<BerIter<'_>> {
+    let pdu = BerIter::new(pdu).next()?;
+    (pdu.tag == tag).then(|| pdu.children())
+}
+
+// Prepend the header to `buf` and return the length of the message.
+pub(crate) fn prepend_apdu_header<T: crate::PktMut, HT: AsRef<[u8]>>(
+    buf: &mut T,
+    header: &GooseHeader<HT>,
+) -> u16 {
+    assert!(buf.chunk_headroom() >= GOOSE_HEADER_LEN);
+    let packet_len = buf.remaining() + GOOSE_HEADER_LEN;
+    assert!(
+        packet_len <= usize::from(u16::MAX),
+        "message of {} bytes exceeds the 65535-byte limit",
+        packet_len
+    );
+    buf.move_back(GOOSE_HEADER_LEN);
+
+    let data = &mut buf.chunk_mut()[0..GOOSE_HEADER_LEN];
+    data.copy_from_slice(header.as_bytes());
+    packet_len as u16
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ether::*;
+    use crate::{Cursor, CursorMut};
+
+    // A goosePdu with the reference, the time allowed to live, the numbers and
+    // the data set values.
+    fn goose_pdu() -> Vec<u8> {
+        let mut fields = vec![0x80, 0x08];
+        fields.extend_from_slice(b"IED1/LLN");
+        fields.extend_from_slice(&[0x81, 0x02, 0x07, 0xd0]);
+        fields.extend_from_slice(&[0x83, 0x04]);
+        fields.extend_from_slice(b"GO_1");
+        fields.extend_from_slice(&[0x85, 0x01, 0x02, 0x86, 0x02, 0x00, 0x80]);
+        fields.extend_from_slice(&[0xab, 0x03, 0x83, 0x01, 0xff]);
+        let mut pdu = vec![0x61, fields.len() as u8];
+        pdu.extend_from_slice(&fields);
+        pdu
+    }
+
+    #[test]
+    fn goose_roundtrip() {
+        let pdu = goose_pdu();
+        let frame_len = ETHER_HEADER_LEN + GOOSE_HEADER_LEN + pdu.len();
+        let mut frame = [0; 64];
+        frame[frame_len - pdu.len()..frame_len].copy_from_slice(&pdu);
+
+        let mut buf = CursorMut::new(&mut frame[..]);
+        buf.advance(frame_len - pdu.len());
+        buf.trim_off(64 - frame_len);
+        let mut pkt = GoosePacket::prepend_header(buf, &GOOSE_HEADER_TEMPLATE);
+        pkt.set_appid(0x3fff);
+        pkt.set_reserved1(0x8000);
+        let mut ethpkt = EtherPacket::prepend_header(pkt.release(), &ETHER_HEADER_TEMPLATE);
+        ethpkt.set_ethertype_for::<GoosePacket<CursorMut>>();
+
+        // The frame is parsed with the padding behind the message.
+        let ethpkt = EtherPacket::parse(Cursor::new(&frame[..])).unwrap();
+        assert_eq!(ethpkt.ethertype(), EtherType::GOOSE);
+        let pkt = GoosePacket::parse(ethpkt.payload()).unwrap();
+        assert_eq!(pkt.appid(), 0x3fff);
+        assert_eq!(usize::from(pkt.packet_len()), GOOSE_HEADER_LEN + pdu.len());
+        assert!(pkt.simulation());
+        assert_eq!(pkt.pdu_bytes(), &pdu[..]);
+
+        assert_eq!(pkt.gocb_ref(), Some(&b"IED1/LLN"[..]));
+        assert_eq!(pkt.go_id(), Some(&b"GO_1"[..]));
+        assert_eq!(pkt.st_num(), Some(2));
+        assert_eq!(pkt.sq_num(), Some(0x80));
+        let all_data = pkt.field(GooseField::ALL_DATA).unwrap();
+        assert!(all_data.is_constructed());
+        assert_eq!(all_data.children().count(), 1);
+        let tags: Vec<_> = pkt.pdu().unwrap().map(|tlv| tlv.tag).collect();
+        assert_eq!(tags, [0x80, 0x81, 0x83, 0x85, 0x86, 0xab]);
+
+        assert_eq!(pkt.payload().chunk(), &pdu[..]);
+    }
+
+    #[test]
+    fn goose_invalid() {
+        let pdu = goose_pdu();
+        let mut bytes = vec![0x00, 0x01, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00];
+        bytes.extend_from_slice(&pdu);
+
+        // A length below the header, or past the buffer.
+        bytes[3] = 7;
+        assert!(GoosePacket::parse(Cursor::new(&bytes[..])).is_err());
+        bytes[3] = (bytes.len() + 1) as u8;
+        assert!(GoosePacket::parse(Cursor::new(&bytes[..])).is_err());
+        assert!(GoosePacket::parse(Cursor::new(&bytes[..7])).is_err());
+
+        // A PDU with another tag.
+        bytes[3] = bytes.len() as u8;
+        bytes[GOOSE_HEADER_LEN] = 0x60;
+        let pkt = GoosePacket::parse(Cursor::new(&bytes[..])).unwrap();
+        assert!(pkt.pdu().is_none());
+        assert_eq!(pkt.st_num(), None);
+    }
+}
@@ -0,0 +1,234 @@
+use byteorder::{ByteOrder, NetworkEndian};
+
+use crate::ether::EtherType;
+
+use super::{flags, flow_id, protocol_type, version, vsid};
+use super::{flags_mut, flow_id_mut, protocol_type_mut, version_mut, vsid_mut};
+
+/// The length of the fixed part of the GRE header (RFC 2784).
+pub const GRE_HEADER_LEN: usize = 4;
+
+/// The length of a GRE header carrying the checksum, the key and the sequence
+/// number fields (RFC 2890).
+pub const GRE_HEADER_LEN_MAX: usize = 16;
+
+pub const GRE_HEADER_TEMPLATE: GreHeader<[u8; GRE_HEADER_LEN]> = GreHeader {
+    buf: [0x00, 0x00, 0x08, 0x00],
+};
+
+/// The length of the NVGRE header (RFC 7637), a GRE header with the key field.
+pub const NVGRE_HEADER_LEN: usize = 8;
+
+/// The largest virtual subnet ID of NVGRE, 0xffffff is reserved.
+pub const NVGRE_VSID_MAX: u32 = 0xfffffe;
+
+pub const NVGRE_HEADER_TEMPLATE: NvgreHeader<[u8; NVGRE_HEADER_LEN]> = NvgreHeader {
+    buf: [0x20, 0x00, 0x65, 0x58, 0x00, 0x00, 0x00, 0x00],
+};
+
+const CHECKSUM_PRESENT: u8 = 0x80;
+const ROUTING_PRESENT: u8 = 0x40;
+const KEY_PRESENT: u8 = 0x20;
+const SEQ_PRESENT: u8 = 0x10;
+
+#[derive(Clone, Copy, Debug)]
+pub struct GreHeader<T> {
+    buf: T,
+}
+
+impl<T: AsRef<[u8]>> GreHeader<T> {
+    #[inline]
+    pub fn new(buf: T) -> Result<Self, T> {
+        if buf.as_ref().len() >= GRE_HEADER_LEN {
+            Ok(Self { buf })
+        } else {
+            Err(buf)
+        }
+    }
+
+    #[inline]
+    pub fn new_unchecked(buf: T) -> Self {
+        Self { buf }
+    }
+
+    #[inline]
+    pub fn as_bytes(&self) -> &[u8] {
+        &self.buf.as_ref()[0..GRE_HEADER_LEN]
+    }
+
+    #[inline]
+    pub fn to_owned(&self) -> GreHeader<[u8; GRE_HEADER_LEN]> {
+        let mut buf = [0; GRE_HEADER_LEN];
+        buf.copy_from_slice(self.as_bytes());
+        GreHeader { buf }
+    }
+
+    #[inline]
+    pub fn checksum_present(&self) -> bool {
+        *flags(self.buf.as_ref()) & CHECKSUM_PRESENT != 0
+    }
+
+    /// Returns the routing present bit of RFC 1701, which must be 0.
+    #[inline]
+    pub fn routing_present(&self) -> bool {
+        *flags(self.buf.as_ref()) & ROUTING_PRESENT != 0
+    }
+
+    #[inline]
+    pub fn key_present(&self) -> bool {
+        *flags(self.buf.as_ref()) & KEY_PRESENT != 0
+    }
+
+    #[inline]
+    pub fn seq_present(&self) -> bool {
+        *flags(self.buf.as_ref()) & SEQ_PRESENT != 0
+    }
+
+    #[inline]
+    pub fn version(&self) -> u8 {
+        *version(self.buf.as_ref()) & 0x07
+    }
+
+    #[inline]
+    pub fn protocol_type(&self) -> EtherType {
+        let data = protocol_type(self.buf.as_ref());
+        NetworkEndian::read_u16(data).into()
+    }
+
+    /// Returns the length of the header with the optional fields indicated by
+    /// the present bits.
+    #[inline]
+    pub fn header_len(&self) -> usize {
+        let fields = [
+            self.checksum_present(),
+            self.key_present(),
+            self.seq_present(),
+        ];
+        GRE_HEADER_LEN + 4 * fields.iter().filter(|present| **present).count()
+    }
+}
+
+impl<T: AsMut<[u8]>> GreHeader<T> {
+    #[inline]
+    pub fn set_checksum_present(&mut self, value: bool) {
+        set_flag(flags_mut(self.buf.as_mut()), CHECKSUM_PRESENT, value)
+    }
+
+    #[inline]
+    pub fn set_key_present(&mut self, value: bool) {
+        set_flag(flags_mut(self.buf.as_mut()), KEY_PRESENT, value)
+    }
+
+    #[inline]
+    pub fn set_seq_present(&mut self, value: bool) {
+        set_flag(flags_mut(self.buf.as_mut()), SEQ_PRESENT, value)
+    }
+
+    #[inline]
+    pub fn set_version(&mut self, value: u8) {
+        assert!(value <= 0x07);
+        let data = version_mut(self.buf.as_mut());
+        *data = (*data & 0xf8) | value;
+    }
+
+    #[inline]
+    pub fn set_protocol_type(&mut self, value: EtherType) {
+        let data = protocol_type_mut(self.buf.as_mut());
+        NetworkEndian::write_u16(data, value.into())
+    }
+}
+
+/// The header of NVGRE, which carries Ethernet frames over GRE.
+///
+/// Only the key present bit is set, and the key field is split into the
+/// 24-bit virtual subnet ID (VSID) and the 8-bit flow ID.
+#[derive(Clone, Copy, Debug)]
+pub struct NvgreHeader<T> {
+    buf: T,
+}
+
+impl<T: AsRef<[u8]>> NvgreHeader<T> {
+    #[inline]
+    pub fn new(buf: T) -> Result<Self, T> {
+        if buf.as_ref().len() >= NVGRE_HEADER_LEN {
+            Ok(Self { buf })
+        } else {
+            Err(buf)
+        }
+    }
+
+    #[inline]
+    pub fn new_unchecked(buf: T) -> Self {
+        Self { buf }
+    }
+
+    #[inline]
+    pub fn as_bytes(&self) -> &[u8] {
+        &self.buf.as_ref()[0..NVGRE_HEADER_LEN]
+    }
+
+    #[inline]
+    pub fn to_owned(&self) -> NvgreHeader<[u8; NVGRE_HEADER_LEN]> {
+        let mut buf = [0; NVGRE_HEADER_LEN];
+        buf.copy_from_slice(self.as_bytes());
+        NvgreHeader { buf }
+    }
+
+    /// Returns whether the flags and the version are those of NVGRE, with the
+    /// key present bit only.
+    #[inline]
+    pub fn check_flags(&self) -> bool {
+        *flags(self.buf.as_ref()) == KEY_PRESENT && *version(self.buf.as_ref()) == 0
+    }
+
+    #[inline]
+    pub fn protocol_type(&self) -> EtherType {
+        let data = protocol_type(self.buf.as_ref());
+        NetworkEndian::read_u16(data).into()
+    }
+
+    #[inline]
+    pub fn vsid(&self) -> u32 {
+        NetworkEndian::read_u24(vsid(self.buf.as_ref()))
+    }
+
+    #[inline]
+    pub fn flow_id(&self) -> u8 {
+        *flow_id(self.buf.as_ref())
+    }
+}
+
+impl<T: AsMut<[u8]>> NvgreHeader<T> {
+    /// Set the flags and the version of NVGRE.
+    #[inline]
+    pub fn adjust_flags(&mut self) {
+        *flags_mut(self.buf.as_mut()) = KEY_PRESENT;
+        *version_mut(self.buf.as_mut()) = 0;
+    }
+
+    #[inline]
+    pub fn set_protocol_type(&mut self, value: EtherType) {
+        let data = protocol_type_mut(self.buf.as_mut());
+        NetworkEndian::write_u16(data, value.into())
+    }
+
+    #[inline]
+    pub fn set_vsid(&mut self, value: u32) {
+        assert!(value <= NVGRE_VSID_MAX);
+        NetworkEndian::write_u24(vsid_mut(self.buf.as_mut()), value)
+    }
+
+    #[inline]
+    pub fn set_flow_id(&mut self, value: u8) {
+        *flow_id_mut(self.buf.as_mut()) = value;
+    }
+}
+
+#[inline]
+fn set_flag(data: &mut u8, flag: u8, value: bool) {
+    if value {
+        *data |= flag;
+    } else {
+        *data &= !flag;
+    }
+}
@@ -0,0 +1,20 @@
+header_field_range_accessors! {
+    (protocol_type, protocol_type_mut, 2..4),
+    (vsid, vsid_mut, 4..7),
+}
+
+header_field_val_accessors! {
+    (flags, flags_mut, 0),
+    (version, version_mut, 1),
+    (flow_id, flow_id_mut, 7),
+}
+
+mod header;
+pub use header::{GreHeader, GRE_HEADER_LEN, GRE_HEADER_LEN_MAX, GRE_HEADER_TEMPLATE};
+pub use header::{NvgreHeader, NVGRE_HEADER_LEN, NVGRE_HEADER_TEMPLATE, NVGRE_VSID_MAX};
+
+mod packet;
+pub use packet::{GrePacket, GRE_KEEPALIVE_LEN};
+
+mod nvgre;
+pub use nvgre::{GreGroup, NvgrePacket};
@@ -0,0 +1,148 @@
+use bytes::Buf;
+
+use crate::ether::EtherType;
+use crate::{PktBuf, PktMut};
+
+use super::header::{NvgreHeader, NVGRE_HEADER_LEN};
+use super::GrePacket;
+
+packet_base! {
+    /// An NVGRE packet (RFC 7637), which carries an Ethernet frame of the
+    /// virtual subnet identified by the VSID.
+    pub struct NvgrePacket: NvgreHeader {
+        header_len: NVGRE_HEADER_LEN,
+        get_methods: [
+            (check_flags, bool),
+            (protocol_type, EtherType),
+            (vsid, u32),
+            (flow_id, u8),
+        ],
+        set_methods: [
+            (adjust_flags),
+            (set_vsid, value: u32),
+            (set_flow_id, value: u8),
+        ],
+        unchecked_set_methods: [
+            (set_protocol_type_unchecked, set_protocol_type, value: EtherType),
+        ]
+    }
+}
+
+impl<T: Buf> NvgrePacket<T> {
+    /// Parse an NVGRE packet, which is a GRE packet with the key present bit
+    /// only, carrying an Ethernet frame.
+    #[inline]
+    pub fn parse(buf: T) -> Result<NvgrePacket<T>, T> {
+        traced_parse!("nvgre", buf, |_: &Self| NVGRE_HEADER_LEN, {
+            if buf.chunk().len() < NVGRE_HEADER_LEN {
+                return Err(buf);
+            }
+
+            let packet = NvgrePacket::parse_unchecked(buf);
+            if packet.check_flags() && packet.protocol_type() == EtherType::TRANS_ETHER_BRIDGING {
+                Ok(packet)
+            } else {
+                Err(packet.release())
+            }
+        })
+    }
+}
+
+impl<T: PktBuf> NvgrePacket<T> {
+    /// Returns the inner Ethernet frame.
+    #[inline]
+    pub fn payload(self) -> T {
+        let mut buf = self.release();
+        buf.advance(NVGRE_HEADER_LEN);
+        buf
+    }
+}
+
+impl<T: PktMut> NvgrePacket<T> {
+    #[inline]
+    pub fn prepend_header<HT: AsRef<[u8]>>(mut buf: T, header: &NvgreHeader<HT>) -> NvgrePacket<T> {
+        assert!(buf.chunk_headroom() >= NVGRE_HEADER_LEN);
+        buf.move_back(NVGRE_HEADER_LEN);
+
+        let data = &mut buf.chunk_mut()[0..NVGRE_HEADER_LEN];
+        data.copy_from_slice(header.as_bytes());
+
+        NvgrePacket::parse_unchecked(buf)
+    }
+}
+
+/// A GRE packet, told apart as NVGRE or plain GRE.
+#[derive(Debug)]
+pub enum GreGroup<T> {
+    Nvgre(NvgrePacket<T>),
+    Gre(GrePacket<T>),
+}
+
+impl<T: Buf> GreGroup<T> {
+    /// Parse the GRE packet in `buf`, as an NVGRE packet if it is one.
+    ///
+    /// A GRE packet that carries Ethernet with other optional fields than the
+    /// key is parsed as a plain GRE packet.
+    #[inline]
+    pub fn parse(buf: T) -> Result<GreGroup<T>, T> {
+        match NvgrePacket::parse(buf) {
+            Ok(packet) => Ok(GreGroup::Nvgre(packet)),
+            Err(buf) => GrePacket::parse(buf).map(GreGroup::Gre),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ether::{EtherPacket, ETHER_HEADER_LEN, ETHER_HEADER_TEMPLATE};
+    use crate::gre::NVGRE_HEADER_TEMPLATE;
+    use crate::{Cursor, CursorMut};
+
+    #[test]
+    fn build_and_parse() {
+        let mut frame = [0; NVGRE_HEADER_LEN + ETHER_HEADER_LEN];
+        let mut buf = CursorMut::new(&mut frame[..]);
+        buf.advance(NVGRE_HEADER_LEN + ETHER_HEADER_LEN);
+        let ethpkt = EtherPacket::prepend_header(buf, &ETHER_HEADER_TEMPLATE);
+        let mut nvgrepkt = NvgrePacket::prepend_header(ethpkt.release(), &NVGRE_HEADER_TEMPLATE);
+        nvgrepkt.set_vsid(0x123456);
+        nvgrepkt.set_flow_id(0x78);
+        assert_eq!(
+            &frame[..NVGRE_HEADER_LEN],
+            &[0x20, 0x00, 0x65, 0x58, 0x12, 0x34, 0x56, 0x78]
+        );
+
+        let nvgrepkt = match GreGroup::parse(Cursor::new(&frame[..])).unwrap() {
+            GreGroup::Nvgre(nvgrepkt) => nvgrepkt,
+            GreGroup::Gre(_) => panic!("not an nvgre packet"),
+        };
+        assert_eq!((nvgrepkt.vsid(), nvgrepkt.flow_id()), (0x123456, 0x78));
+        assert_eq!(nvgrepkt.payload().remaining(), ETHER_HEADER_LEN);
+
+        // The key of the same packet parsed as GRE.
+        let grepkt = GrePacket::parse(Cursor::new(&frame[..])).unwrap();
+        assert_eq!(grepkt.key(), Some(0x12345678));
+    }
+
+    #[test]
+    fn plain_gre() {
+        // A sequence number, another protocol, or no key.
+        for header in [
+            [0x30, 0x00, 0x65, 0x58, 0, 0, 0, 1, 0, 0, 0, 2],
+            [0x20, 0x00, 0x08, 0x00, 0, 0, 0, 1, 0, 0, 0, 0],
+            [0x00, 0x00, 0x65, 0x58, 0, 0, 0, 1, 0, 0, 0, 0],
+        ] {
+            assert!(NvgrePacket::parse(Cursor::new(&header[..])).is_err());
+            assert!(matches!(
+                GreGroup::parse(Cursor::new(&header[..])),
+                Ok(GreGroup::Gre(_))
+            ));
+        }
+
+        // Neither NVGRE nor GRE.
+        let header = [0x20, 0x01, 0x65, 0x58, 0x00, 0x00, 0x00, 0x01];
+        assert!(GreGroup::parse(Cursor::new(&header[..])).is_err());
+        assert!(GreGroup::parse(Cursor::new(&header[..6])).is_err());
+    }
+}
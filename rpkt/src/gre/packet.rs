@@ -0,0 +1,362 @@
+use byteorder::{ByteOrder, NetworkEndian};
+use bytes::Buf;
+
+use crate::checksum_utils;
+use crate::ether::EtherType;
+use crate::ipv4::{IpProtocol, Ipv4Addr, Ipv4Packet, IPV4_HEADER_LEN, IPV4_HEADER_TEMPLATE};
+use crate::{PktBuf, PktMut};
+
+use super::header::{GreHeader, GRE_HEADER_LEN, GRE_HEADER_TEMPLATE};
+
+/// The length of a GRE keepalive, an outer GRE header followed by the IPv4
+/// packet that is sent back by the remote end.
+pub const GRE_KEEPALIVE_LEN: usize = GRE_HEADER_LEN + IPV4_HEADER_LEN + GRE_HEADER_LEN;
+
+packet_base! {
+    pub struct GrePacket: GreHeader {
+        header_len: GRE_HEADER_LEN,
+        get_methods: [
+            (header_len, usize),
+            (checksum_present, bool),
+            (routing_present, bool),
+            (key_present, bool),
+            (seq_present, bool),
+            (version, u8),
+            (protocol_type, EtherType),
+        ],
+        set_methods: [
+            (set_version, value: u8),
+            (set_protocol_type, value: EtherType),
+        ],
+        unchecked_set_methods: [
+            (set_checksum_present_unchecked, set_checksum_present, value: bool),
+            (set_key_present_unchecked, set_key_present, value: bool),
+            (set_seq_present_unchecked, set_seq_present, value: bool),
+        ]
+    }
+}
+
+impl<T: Buf> GrePacket<T> {
+    #[inline]
+    pub fn parse(buf: T) -> Result<GrePacket<T>, T> {
+        traced_parse!("gre", buf, |pkt: &Self| pkt.header_len(), {
+            if buf.chunk().len() < GRE_HEADER_LEN {
+                return Err(buf);
+            }
+
+            let packet = GrePacket::parse_unchecked(buf);
+            if packet.version() == 0
+                && !packet.routing_present()
+                && packet.header_len() <= packet.buf.chunk().len()
+            {
+                Ok(packet)
+            } else {
+                Err(packet.release())
+            }
+        })
+    }
+
+    /// Returns the checksum field, if it is present.
+    #[inline]
+    pub fn checksum(&self) -> Option<u16> {
+        self.checksum_present()
+            .then(|| NetworkEndian::read_u16(&self.buf.chunk()[GRE_HEADER_LEN..]))
+    }
+
+    /// Returns the key field, if it is present.
+    #[inline]
+    pub fn key(&self) -> Option<u32> {
+        self.key_present()
+            .then(|| NetworkEndian::read_u32(&self.buf.chunk()[self.key_offset()..]))
+    }
+
+    /// Returns the sequence number field, if it is present.
+    #[inline]
+    pub fn seq_number(&self) -> Option<u32> {
+        self.seq_present()
+            .then(|| NetworkEndian::read_u32(&self.buf.chunk()[self.seq_offset()..]))
+    }
+
+    #[inline]
+    fn key_offset(&self) -> usize {
+        GRE_HEADER_LEN + 4 * usize::from(self.checksum_present())
+    }
+
+    #[inline]
+    fn seq_offset(&self) -> usize {
+        self.key_offset() + 4 * usize::from(self.key_present())
+    }
+}
+
+impl<T: PktBuf> GrePacket<T> {
+    /// Calculate the checksum over the GRE header and the payload.
+    #[inline]
+    pub fn calc_checksum(&mut self) -> u16 {
+        let total_len = self.buf().remaining();
+
+        let result = checksum_utils::from_buf(&mut self.buf, total_len);
+        self.buf.move_back(total_len);
+        result
+    }
+
+    /// Returns whether the checksum is valid, a packet without the checksum
+    /// field is always valid.
+    #[inline]
+    pub fn verify_checksum(&mut self) -> bool {
+        !self.checksum_present() || self.calc_checksum() == !0
+    }
+
+    #[inline]
+    pub fn payload(self) -> T {
+        let header_len = self.header_len();
+        let mut buf = self.release();
+        buf.advance(header_len);
+        buf
+    }
+}
+
+impl<T: PktMut> GrePacket<T> {
+    /// Set the checksum field.
+    ///
+    /// # Panics
+    ///
+    /// This function panics if the checksum field is not present.
+    #[inline]
+    pub fn set_checksum(&mut self, value: u16) {
+        assert!(self.checksum_present());
+        NetworkEndian::write_u16(&mut self.buf.chunk_mut()[GRE_HEADER_LEN..], value)
+    }
+
+    /// Set the key field.
+    ///
+    /// # Panics
+    ///
+    /// This function panics if the key field is not present.
+    #[inline]
+    pub fn set_key(&mut self, value: u32) {
+        assert!(self.key_present());
+        let offset = self.key_offset();
+        NetworkEndian::write_u32(&mut self.buf.chunk_mut()[offset..], value)
+    }
+
+    /// Set the sequence number field.
+    ///
+    /// # Panics
+    ///
+    /// This function panics if the sequence number field is not present.
+    #[inline]
+    pub fn set_seq_number(&mut self, value: u32) {
+        assert!(self.seq_present());
+        let offset = self.seq_offset();
+        NetworkEndian::write_u32(&mut self.buf.chunk_mut()[offset..], value)
+    }
+
+    #[inline]
+    pub fn adjust_checksum(&mut self) {
+        self.set_checksum(0);
+        let cksum = !self.calc_checksum();
+        self.set_checksum(cksum)
+    }
+
+    /// Add or remove the checksum field, together with the reserved field that
+    /// follows it.
+    ///
+    /// The header grows into the headroom or shrinks from the front, so that
+    /// the payload stays in place. An added field is zeroed, and the checksum
+    /// must be adjusted once the packet is complete.
+    ///
+    /// # Panics
+    ///
+    /// This function panics if there is not enough headroom to add the field.
+    #[inline]
+    pub fn set_checksum_present(&mut self, value: bool) {
+        if value != self.checksum_present() {
+            self.resize_field(GRE_HEADER_LEN, value);
+            self.set_checksum_present_unchecked(value);
+        }
+    }
+
+    /// Add or remove the key field, see [`GrePacket::set_checksum_present`].
+    #[inline]
+    pub fn set_key_present(&mut self, value: bool) {
+        if value != self.key_present() {
+            self.resize_field(self.key_offset(), value);
+            self.set_key_present_unchecked(value);
+        }
+    }
+
+    /// Add or remove the sequence number field, see
+    /// [`GrePacket::set_checksum_present`].
+    #[inline]
+    pub fn set_seq_present(&mut self, value: bool) {
+        if value != self.seq_present() {
+            self.resize_field(self.seq_offset(), value);
+            self.set_seq_present_unchecked(value);
+        }
+    }
+
+    // Insert or remove the 4-byte field at `offset` by shifting the header
+    // bytes before it.
+    fn resize_field(&mut self, offset: usize, insert: bool) {
+        if insert {
+            assert!(self.buf.chunk_headroom() >= 4);
+            self.buf.move_back(4);
+            let data = self.buf.chunk_mut();
+            data.copy_within(4..4 + offset, 0);
+            data[offset..offset + 4].fill(0);
+        } else {
+            self.buf.chunk_mut().copy_within(0..offset, 4);
+            self.buf.advance(4);
+        }
+    }
+
+    /// Prepend a GRE header, the optional fields indicated by the present bits
+    /// of `header` are zeroed.
+    #[inline]
+    pub fn prepend_header<HT: AsRef<[u8]>>(mut buf: T, header: &GreHeader<HT>) -> GrePacket<T> {
+        let header_len = header.header_len();
+        assert!(header_len <= buf.chunk_headroom());
+        buf.move_back(header_len);
+
+        let data = &mut buf.chunk_mut()[0..header_len];
+        data[0..GRE_HEADER_LEN].copy_from_slice(header.as_bytes());
+        data[GRE_HEADER_LEN..].fill(0);
+
+        GrePacket::parse_unchecked(buf)
+    }
+
+    /// Prepend a keepalive of the tunnel from `local` to `remote`.
+    ///
+    /// The keepalive encapsulates an IPv4 packet from `remote` back to `local`,
+    /// which in turn carries an empty GRE packet. The remote end decapsulates
+    /// and forwards the inner packet as usual, so that it returns through the
+    /// tunnel. The caller prepends the outer IPv4 header from `local` to
+    /// `remote`.
+    ///
+    /// The `buf` must be empty and have at least [`GRE_KEEPALIVE_LEN`] bytes
+    /// of headroom.
+    pub fn prepend_keepalive(buf: T, local: Ipv4Addr, remote: Ipv4Addr) -> GrePacket<T> {
+        assert!(buf.remaining() == 0 && buf.chunk_headroom() >= GRE_KEEPALIVE_LEN);
+
+        let mut grepkt = GrePacket::prepend_header(buf, &GRE_HEADER_TEMPLATE);
+        grepkt.set_protocol_type(EtherType::from(0));
+
+        let mut ippkt = Ipv4Packet::prepend_header(grepkt.release(), &IPV4_HEADER_TEMPLATE);
+        ippkt.set_time_to_live(255);
+        ippkt.set_protocol(IpProtocol::GRE);
+        ippkt.set_source_ip(remote);
+        ippkt.set_dest_ip(local);
+        ippkt.adjust_checksum();
+
+        let mut grepkt = GrePacket::prepend_header(ippkt.release(), &GRE_HEADER_TEMPLATE);
+        grepkt.set_protocol_type(EtherType::IPV4);
+        grepkt
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Cursor, CursorMut};
+
+    // A GRE packet with the checksum, key and sequence number fields, and a
+    // 4-byte payload.
+    static GRE_FULL: [u8; 20] = [
+        0xb0, 0x00, 0x08, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x64, 0x00, 0x00, 0x00,
+        0x07, 0xde, 0xad, 0xbe, 0xef,
+    ];
+
+    #[test]
+    fn parse_optional_fields() {
+        let grepkt = GrePacket::parse(Cursor::new(&GRE_FULL[..])).unwrap();
+        assert_eq!(grepkt.header_len(), 16);
+        assert_eq!(grepkt.protocol_type(), EtherType::IPV4);
+        assert_eq!(grepkt.checksum(), Some(0));
+        assert_eq!(grepkt.key(), Some(100));
+        assert_eq!(grepkt.seq_number(), Some(7));
+        assert_eq!(grepkt.payload().chunk(), &[0xde, 0xad, 0xbe, 0xef]);
+
+        let mut bytes = GRE_FULL;
+        bytes[0] = 0x10;
+        let grepkt = GrePacket::parse(Cursor::new(&bytes[..])).unwrap();
+        assert_eq!(grepkt.header_len(), 8);
+        assert_eq!((grepkt.checksum(), grepkt.key()), (None, None));
+        assert_eq!(grepkt.seq_number(), Some(0));
+
+        // A wrong version, the routing bit, or a truncated header.
+        bytes[1] = 0x01;
+        assert!(GrePacket::parse(Cursor::new(&bytes[..])).is_err());
+        assert!(GrePacket::parse(Cursor::new(&[0x40, 0x00, 0x08, 0x00][..])).is_err());
+        assert!(GrePacket::parse(Cursor::new(&GRE_FULL[..12])).is_err());
+    }
+
+    #[test]
+    fn checksum() {
+        let mut bytes = GRE_FULL;
+        let mut grepkt = GrePacket::parse(CursorMut::new(&mut bytes[..])).unwrap();
+        assert!(!grepkt.verify_checksum());
+        grepkt.adjust_checksum();
+        assert!(grepkt.verify_checksum());
+        assert_eq!(grepkt.checksum(), Some(0xa9f6));
+
+        let mut bytes = [0x00, 0x00, 0x08, 0x00, 0x12, 0x34];
+        let mut grepkt = GrePacket::parse(CursorMut::new(&mut bytes[..])).unwrap();
+        assert!(grepkt.verify_checksum());
+    }
+
+    #[test]
+    fn toggle_optional_fields() {
+        let mut frame = [0xff; 40];
+        frame[16..20].copy_from_slice(GRE_HEADER_TEMPLATE.as_bytes());
+        frame[20..24].copy_from_slice(&[0xde, 0xad, 0xbe, 0xef]);
+        let mut buf = CursorMut::new(&mut frame[..24]);
+        buf.advance(16);
+        let mut grepkt = GrePacket::parse(buf).unwrap();
+
+        grepkt.set_seq_present(true);
+        grepkt.set_seq_number(7);
+        grepkt.set_key_present(true);
+        grepkt.set_key(100);
+        grepkt.set_checksum_present(true);
+        grepkt.adjust_checksum();
+        assert_eq!(grepkt.header_len(), 16);
+        assert_eq!(grepkt.key(), Some(100));
+        assert_eq!(grepkt.seq_number(), Some(7));
+        assert!(grepkt.verify_checksum());
+
+        // Removing a field keeps the others and the payload.
+        grepkt.set_key_present(false);
+        grepkt.set_key_present(false);
+        assert_eq!(grepkt.header_len(), 12);
+        assert_eq!(grepkt.key(), None);
+        assert_eq!(grepkt.seq_number(), Some(7));
+        grepkt.set_checksum_present(false);
+        grepkt.set_seq_present(false);
+        assert_eq!(
+            grepkt.buf().chunk(),
+            &[0x00, 0x00, 0x08, 0x00, 0xde, 0xad, 0xbe, 0xef]
+        );
+    }
+
+    #[test]
+    fn keepalive() {
+        let mut frame = [0; GRE_KEEPALIVE_LEN];
+        let mut buf = CursorMut::new(&mut frame[..]);
+        buf.advance(GRE_KEEPALIVE_LEN);
+        let local = Ipv4Addr::new(10, 0, 0, 1);
+        let remote = Ipv4Addr::new(10, 0, 0, 2);
+        let grepkt = GrePacket::prepend_keepalive(buf, local, remote);
+        assert_eq!(grepkt.buf().remaining(), GRE_KEEPALIVE_LEN);
+
+        let grepkt = GrePacket::parse(Cursor::new(&frame[..])).unwrap();
+        assert_eq!(grepkt.protocol_type(), EtherType::IPV4);
+        let ippkt = Ipv4Packet::parse(grepkt.payload()).unwrap();
+        assert!(ippkt.verify_checksum());
+        assert_eq!(ippkt.protocol(), IpProtocol::GRE);
+        assert_eq!((ippkt.source_ip(), ippkt.dest_ip()), (remote, local));
+
+        let inner = GrePacket::parse(ippkt.payload()).unwrap();
+        assert_eq!(inner.protocol_type(), EtherType::from(0));
+        assert_eq!(inner.payload().remaining(), 0);
+    }
+}
@@ -0,0 +1,725 @@
+//! The Hot Standby Router Protocol of Cisco, HSRPv1 (RFC 2281) and HSRPv2.
+//!
+//! The routers of an HSRP group share a virtual IP address and a virtual MAC
+//! address, one of them is active and forwards the packets sent to them. The
+//! routers exchange UDP datagrams on [`HSRP_PORT`], which are multicast to
+//! [`HSRP_V1_IPV4`] for HSRPv1 and to [`HSRP_V2_IPV4`] for HSRPv2.
+//!
+//! An HSRPv1 message is a fixed 20-byte [`HsrpV1Header`]. An HSRPv2 message is
+//! a list of TLVs with 1-byte types and 1-byte lengths, which
+//! [`HsrpV2Message`] iterates: a group state TLV, read and written with
+//! [`HsrpV2GroupState`], for every group of the interface, and optionally an
+//! authentication TLV. [`HsrpMessage::parse`] tells the versions apart.
+//!
+//! # Examples
+//! ```
+//! use rpkt::hsrp::*;
+//! use rpkt::ipv4::Ipv4Addr;
+//!
+//! let mut header = HSRP_V1_HEADER_TEMPLATE;
+//! header.set_state(HsrpV1State::ACTIVE);
+//! header.set_group(10);
+//! header.set_priority(110);
+//! header.set_virtual_ip(Ipv4Addr::new(192, 0, 2, 1));
+//!
+//! match HsrpMessage::parse(header.as_bytes()).unwrap() {
+//!     HsrpMessage::V1(msg) => {
+//!         assert_eq!(msg.state(), HsrpV1State::ACTIVE);
+//!         assert_eq!((msg.group(), msg.priority()), (10, 110));
+//!         assert_eq!(msg.auth_data(), b"cisco\0\0\0");
+//!     }
+//!     HsrpMessage::V2(_) => unreachable!(),
+//! }
+//! ```
+
+use byteorder::{ByteOrder, NetworkEndian};
+
+use crate::ether::MacAddr;
+use crate::ipv4::Ipv4Addr;
+use crate::ipv6::Ipv6Addr;
+use crate::tlv::{TlvFormat, TlvIter};
+
+/// The UDP port of HSRP over IPv4.
+pub const HSRP_PORT: u16 = 1985;
+
+/// The UDP port of HSRP over IPv6, which only exists in HSRPv2.
+pub const HSRP_V6_PORT: u16 = 2029;
+
+/// The IPv4 multicast address of HSRPv1, all the routers.
+pub const HSRP_V1_IPV4: Ipv4Addr = Ipv4Addr::new(224, 0, 0, 2);
+
+/// The IPv4 multicast address of HSRPv2.
+pub const HSRP_V2_IPV4: Ipv4Addr = Ipv4Addr::new(224, 0, 0, 102);
+
+/// The IPv6 multicast address of HSRPv2.
+pub const HSRP_V2_IPV6: Ipv6Addr =
+    Ipv6Addr([0xff, 0x02, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0x66]);
+
+/// The length of an HSRPv1 message.
+pub const HSRP_V1_HEADER_LEN: usize = 20;
+
+/// The length of the authentication data of HSRPv1 and of the text
+/// authentication TLV of HSRPv2.
+pub const HSRP_AUTH_LEN: usize = 8;
+
+/// The authentication data used when none is configured, "cisco" padded with
+/// zeros.
+pub const HSRP_DEFAULT_AUTH: [u8; HSRP_AUTH_LEN] = *b"cisco\0\0\0";
+
+/// An HSRPv1 hello message of the initial state, with the default timers of 3
+/// and 10 seconds, the default priority of 100 and the default
+/// authentication.
+pub const HSRP_V1_HEADER_TEMPLATE: HsrpV1Header<[u8; HSRP_V1_HEADER_LEN]> = HsrpV1Header {
+    buf: [
+        0x00, 0x00, 0x00, 0x03, 0x0a, 0x64, 0x00, 0x00, b'c', b'i', b's', b'c', b'o', 0x00, 0x00,
+        0x00, 0x00, 0x00, 0x00, 0x00,
+    ],
+};
+
+/// The length of the value of a group state TLV.
+pub const HSRP_V2_GROUP_STATE_LEN: usize = 40;
+
+/// The length of the value of an MD5 authentication TLV.
+pub const HSRP_V2_MD5_AUTH_LEN: usize = 28;
+
+/// The layout of the HSRPv2 TLVs.
+pub const HSRP_V2_TLV_FORMAT: TlvFormat = TlvFormat {
+    type_width: 1,
+    len_width: 1,
+    len_includes_header: false,
+    len_unit: 1,
+    align: 1,
+    pad_type: None,
+    end_type: None,
+};
+
+/// Returns the virtual MAC address of an HSRPv1 group, 00:00:0c:07:ac:XX.
+pub fn hsrp_v1_virtual_mac(group: u8) -> MacAddr {
+    MacAddr([0x00, 0x00, 0x0c, 0x07, 0xac, group])
+}
+
+/// Returns the virtual MAC address of an HSRPv2 group over IPv4,
+/// 00:00:0c:9f:fX:XX.
+///
+/// # Panics
+///
+/// This function panics if `group` exceeds 4095.
+pub fn hsrp_v2_virtual_mac(group: u16) -> MacAddr {
+    assert!(group <= 0x0fff);
+    MacAddr([
+        0x00,
+        0x00,
+        0x0c,
+        0x9f,
+        0xf0 | (group >> 8) as u8,
+        group as u8,
+    ])
+}
+
+enum_sim! {
+    /// The operation of an HSRP message.
+    pub struct HsrpOpCode (u8) {
+        HELLO = 0,
+        /// Sent by a router that wants to become the active router.
+        COUP = 1,
+        /// Sent by the active router that no longer wants to be active.
+        RESIGN = 2,
+        /// The interface state message of HSRPv1, which has another format.
+        ADVERTISE = 3,
+    }
+}
+
+enum_sim! {
+    /// The state of the router sending an HSRPv1 message.
+    pub struct HsrpV1State (u8) {
+        INITIAL = 0,
+        LEARN = 1,
+        LISTEN = 2,
+        SPEAK = 4,
+        STANDBY = 8,
+        ACTIVE = 16,
+    }
+}
+
+enum_sim! {
+    /// The state of the router sending an HSRPv2 message, which is numbered
+    /// differently from HSRPv1.
+    pub struct HsrpV2State (u8) {
+        DISABLED = 0,
+        INITIAL = 1,
+        LEARN = 2,
+        LISTEN = 3,
+        SPEAK = 4,
+        STANDBY = 5,
+        ACTIVE = 6,
+    }
+}
+
+enum_sim! {
+    /// The type of an HSRPv2 TLV.
+    pub struct HsrpV2TlvType (u8) {
+        GROUP_STATE = 1,
+        INTERFACE_STATE = 2,
+        TEXT_AUTH = 3,
+        MD5_AUTH = 4,
+    }
+}
+
+header_field_range_accessors! {
+    (v1_auth_data, v1_auth_data_mut, 8..16),
+    (v1_virtual_ip, v1_virtual_ip_mut, 16..20),
+}
+
+header_field_val_accessors! {
+    (v1_version, v1_version_mut, 0),
+    (v1_op_code, v1_op_code_mut, 1),
+    (v1_state, v1_state_mut, 2),
+    (v1_hello_time, v1_hello_time_mut, 3),
+    (v1_hold_time, v1_hold_time_mut, 4),
+    (v1_priority, v1_priority_mut, 5),
+    (v1_group, v1_group_mut, 6),
+}
+
+/// An HSRPv1 message.
+#[derive(Clone, Copy, Debug)]
+pub struct HsrpV1Header<T> {
+    buf: T,
+}
+
+impl<T: AsRef<[u8]>> HsrpV1Header<T> {
+    #[inline]
+    pub fn new(buf: T) -> Result<Self, T> {
+        if buf.as_ref().len() >= HSRP_V1_HEADER_LEN {
+            Ok(Self { buf })
+        } else {
+            Err(buf)
+        }
+    }
+
+    #[inline]
+    pub fn new_unchecked(buf: T) -> Self {
+        Self { buf }
+    }
+
+    #[inline]
+    pub fn as_bytes(&self) -> &[u8] {
+        &self.buf.as_ref()[0..HSRP_V1_HEADER_LEN]
+    }
+
+    #[inline]
+    pub fn to_owned(&self) -> HsrpV1Header<[u8; HSRP_V1_HEADER_LEN]> {
+        let mut buf = [0; HSRP_V1_HEADER_LEN];
+        buf.copy_from_slice(self.as_bytes());
+        HsrpV1Header { buf }
+    }
+
+    /// Returns the version, 0 for HSRPv1.
+    #[inline]
+    pub fn version(&self) -> u8 {
+        *v1_version(self.buf.as_ref())
+    }
+
+    #[inline]
+    pub fn op_code(&self) -> HsrpOpCode {
+        HsrpOpCode::from(*v1_op_code(self.buf.as_ref()))
+    }
+
+    #[inline]
+    pub fn state(&self) -> HsrpV1State {
+        HsrpV1State::from(*v1_state(self.buf.as_ref()))
+    }
+
+    /// Returns the period of the hello messages in seconds.
+    #[inline]
+    pub fn hello_time(&self) -> u8 {
+        *v1_hello_time(self.buf.as_ref())
+    }
+
+    /// Returns how long the hello message is valid in seconds.
+    #[inline]
+    pub fn hold_time(&self) -> u8 {
+        *v1_hold_time(self.buf.as_ref())
+    }
+
+    /// Returns the priority of the router, the router of the highest priority
+    /// becomes active.
+    #[inline]
+    pub fn priority(&self) -> u8 {
+        *v1_priority(self.buf.as_ref())
+    }
+
+    #[inline]
+    pub fn group(&self) -> u8 {
+        *v1_group(self.buf.as_ref())
+    }
+
+    /// Returns the clear text password.
+    #[inline]
+    pub fn auth_data(&self) -> &[u8] {
+        v1_auth_data(self.buf.as_ref())
+    }
+
+    #[inline]
+    pub fn virtual_ip(&self) -> Ipv4Addr {
+        Ipv4Addr::from_bytes(v1_virtual_ip(self.buf.as_ref()))
+    }
+}
+
+impl<T: AsMut<[u8]>> HsrpV1Header<T> {
+    #[inline]
+    pub fn set_version(&mut self, value: u8) {
+        *v1_version_mut(self.buf.as_mut()) = value;
+    }
+
+    #[inline]
+    pub fn set_op_code(&mut self, value: HsrpOpCode) {
+        *v1_op_code_mut(self.buf.as_mut()) = value.into();
+    }
+
+    #[inline]
+    pub fn set_state(&mut self, value: HsrpV1State) {
+        *v1_state_mut(self.buf.as_mut()) = value.into();
+    }
+
+    #[inline]
+    pub fn set_hello_time(&mut self, value: u8) {
+        *v1_hello_time_mut(self.buf.as_mut()) = value;
+    }
+
+    #[inline]
+    pub fn set_hold_time(&mut self, value: u8) {
+        *v1_hold_time_mut(self.buf.as_mut()) = value;
+    }
+
+    #[inline]
+    pub fn set_priority(&mut self, value: u8) {
+        *v1_priority_mut(self.buf.as_mut()) = value;
+    }
+
+    #[inline]
+    pub fn set_group(&mut self, value: u8) {
+        *v1_group_mut(self.buf.as_mut()) = value;
+    }
+
+    #[inline]
+    pub fn set_auth_data(&mut self, value: &[u8; HSRP_AUTH_LEN]) {
+        v1_auth_data_mut(self.buf.as_mut()).copy_from_slice(value);
+    }
+
+    #[inline]
+    pub fn set_virtual_ip(&mut self, value: Ipv4Addr) {
+        v1_virtual_ip_mut(self.buf.as_mut()).copy_from_slice(value.as_bytes());
+    }
+}
+
+header_field_range_accessors! {
+    (v2_group, v2_group_mut, 4..6),
+    (v2_identifier, v2_identifier_mut, 6..12),
+    (v2_priority, v2_priority_mut, 12..16),
+    (v2_hello_time, v2_hello_time_mut, 16..20),
+    (v2_hold_time, v2_hold_time_mut, 20..24),
+    (v2_virtual_ip, v2_virtual_ip_mut, 24..40),
+}
+
+header_field_val_accessors! {
+    (v2_version, v2_version_mut, 0),
+    (v2_op_code, v2_op_code_mut, 1),
+    (v2_state, v2_state_mut, 2),
+    (v2_ip_version, v2_ip_version_mut, 3),
+}
+
+/// The value of an HSRPv2 group state TLV.
+#[derive(Clone, Copy, Debug)]
+pub struct HsrpV2GroupState<T> {
+    buf: T,
+}
+
+impl<T: AsRef<[u8]>> HsrpV2GroupState<T> {
+    #[inline]
+    pub fn new(buf: T) -> Result<Self, T> {
+        if buf.as_ref().len() >= HSRP_V2_GROUP_STATE_LEN {
+            Ok(Self { buf })
+        } else {
+            Err(buf)
+        }
+    }
+
+    #[inline]
+    pub fn new_unchecked(buf: T) -> Self {
+        Self { buf }
+    }
+
+    #[inline]
+    pub fn as_bytes(&self) -> &[u8] {
+        &self.buf.as_ref()[0..HSRP_V2_GROUP_STATE_LEN]
+    }
+
+    /// Returns the version, 2 for HSRPv2.
+    #[inline]
+    pub fn version(&self) -> u8 {
+        *v2_version(self.buf.as_ref())
+    }
+
+    #[inline]
+    pub fn op_code(&self) -> HsrpOpCode {
+        HsrpOpCode::from(*v2_op_code(self.buf.as_ref()))
+    }
+
+    #[inline]
+    pub fn state(&self) -> HsrpV2State {
+        HsrpV2State::from(*v2_state(self.buf.as_ref()))
+    }
+
+    /// Returns the version of the virtual IP address, 4 or 6.
+    #[inline]
+    pub fn ip_version(&self) -> u8 {
+        *v2_ip_version(self.buf.as_ref())
+    }
+
+    #[inline]
+    pub fn group(&self) -> u16 {
+        NetworkEndian::read_u16(v2_group(self.buf.as_ref()))
+    }
+
+    /// Returns the MAC address of the interface of the router.
+    #[inline]
+    pub fn identifier(&self) -> MacAddr {
+        MacAddr::from_bytes(v2_identifier(self.buf.as_ref()))
+    }
+
+    #[inline]
+    pub fn priority(&self) -> u32 {
+        NetworkEndian::read_u32(v2_priority(self.buf.as_ref()))
+    }
+
+    /// Returns the period of the hello messages in milliseconds.
+    #[inline]
+    pub fn hello_time(&self) -> u32 {
+        NetworkEndian::read_u32(v2_hello_time(self.buf.as_ref()))
+    }
+
+    /// Returns how long the hello message is valid in milliseconds.
+    #[inline]
+    pub fn hold_time(&self) -> u32 {
+        NetworkEndian::read_u32(v2_hold_time(self.buf.as_ref()))
+    }
+
+    /// Returns the virtual IPv4 address, if [`ip_version`](Self::ip_version)
+    /// is 4.
+    #[inline]
+    pub fn virtual_ipv4(&self) -> Option<Ipv4Addr> {
+        (self.ip_version() == 4)
+            .then(|| Ipv4Addr::from_bytes(&v2_virtual_ip(self.buf.as_ref())[..4]))
+    }
+
+    /// Returns the virtual IPv6 address, if [`ip_version`](Self::ip_version)
+    /// is 6.
+    #[inline]
+    pub fn virtual_ipv6(&self) -> Option<Ipv6Addr> {
+        (self.ip_version() == 6).then(|| Ipv6Addr::from_bytes(v2_virtual_ip(self.buf.as_ref())))
+    }
+}
+
+impl<T: AsMut<[u8]>> HsrpV2GroupState<T> {
+    #[inline]
+    pub fn set_version(&mut self, value: u8) {
+        *v2_version_mut(self.buf.as_mut()) = value;
+    }
+
+    #[inline]
+    pub fn set_op_code(&mut self, value: HsrpOpCode) {
+        *v2_op_code_mut(self.buf.as_mut()) = value.into();
+    }
+
+    #[inline]
+    pub fn set_state(&mut self, value: HsrpV2State) {
+        *v2_state_mut(self.buf.as_mut()) = value.into();
+    }
+
+    #[inline]
+    pub fn set_group(&mut self, value: u16) {
+        NetworkEndian::write_u16(v2_group_mut(self.buf.as_mut()), value);
+    }
+
+    #[inline]
+    pub fn set_identifier(&mut self, value: MacAddr) {
+        v2_identifier_mut(self.buf.as_mut()).copy_from_slice(value.as_bytes());
+    }
+
+    #[inline]
+    pub fn set_priority(&mut self, value: u32) {
+        NetworkEndian::write_u32(v2_priority_mut(self.buf.as_mut()), value);
+    }
+
+    #[inline]
+    pub fn set_hello_time(&mut self, value: u32) {
+        NetworkEndian::write_u32(v2_hello_time_mut(self.buf.as_mut()), value);
+    }
+
+    #[inline]
+    pub fn set_hold_time(&mut self, value: u32) {
+        NetworkEndian::write_u32(v2_hold_time_mut(self.buf.as_mut()), value);
+    }
+
+    /// Set the virtual IPv4 address and the IP version to 4.
+    #[inline]
+    pub fn set_virtual_ipv4(&mut self, value: Ipv4Addr) {
+        *v2_ip_version_mut(self.buf.as_mut()) = 4;
+        let data = v2_virtual_ip_mut(self.buf.as_mut());
+        data[..4].copy_from_slice(value.as_bytes());
+        data[4..].fill(0);
+    }
+
+    /// Set the virtual IPv6 address and the IP version to 6.
+    #[inline]
+    pub fn set_virtual_ipv6(&mut self, value: Ipv6Addr) {
+        *v2_ip_version_mut(self.buf.as_mut()) = 6;
+        v2_virtual_ip_mut(self.buf.as_mut()).copy_from_slice(&value.0);
+    }
+}
+
+/// The value of an HSRPv2 MD5 authentication TLV.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct HsrpMd5Auth {
+    /// The algorithm of the digest, 1 for keyed MD5.
+    pub algorithm: u8,
+    pub flags: u16,
+    /// The IP address of the sending router.
+    pub ip_addr: Ipv4Addr,
+    pub key_id: u32,
+    pub digest: [u8; 16],
+}
+
+impl HsrpMd5Auth {
+    fn read(data: &[u8]) -> Self {
+        let mut digest = [0; 16];
+        digest.copy_from_slice(&data[12..28]);
+        Self {
+            algorithm: data[0],
+            flags: NetworkEndian::read_u16(&data[2..4]),
+            ip_addr: Ipv4Addr::from_bytes(&data[4..8]),
+            key_id: NetworkEndian::read_u32(&data[8..12]),
+            digest,
+        }
+    }
+
+    /// Write the value of the TLV into `data`.
+    ///
+    /// # Panics
+    ///
+    /// This function panics if `data` is shorter than
+    /// [`HSRP_V2_MD5_AUTH_LEN`].
+    pub fn write(&self, data: &mut [u8]) {
+        data[0] = self.algorithm;
+        data[1] = 0;
+        NetworkEndian::write_u16(&mut data[2..4], self.flags);
+        data[4..8].copy_from_slice(self.ip_addr.as_bytes());
+        NetworkEndian::write_u32(&mut data[8..12], self.key_id);
+        data[12..28].copy_from_slice(&self.digest);
+    }
+}
+
+/// An HSRPv2 message, the payload of a UDP datagram.
+///
+/// The messages are built with a [`TlvWriter`](crate::tlv::TlvWriter) of
+/// [`HSRP_V2_TLV_FORMAT`].
+#[derive(Debug, Clone, Copy)]
+pub struct HsrpV2Message<'a> {
+    buf: &'a [u8],
+}
+
+impl<'a> HsrpV2Message<'a> {
+    /// Parse the payload of a UDP datagram.
+    ///
+    /// Returns `None` if a TLV is malformed, or if the message does not start
+    /// with a group state TLV of version 2.
+    pub fn parse(buf: &'a [u8]) -> Option<Self> {
+        if buf.len() < 2 + HSRP_V2_GROUP_STATE_LEN
+            || buf[0] != u8::from(HsrpV2TlvType::GROUP_STATE)
+            || usize::from(buf[1]) != HSRP_V2_GROUP_STATE_LEN
+            || buf[2] != 2
+            || !TlvIter::check_bytes(buf, HSRP_V2_TLV_FORMAT)
+        {
+            return None;
+        }
+        Some(Self { buf })
+    }
+
+    #[inline]
+    pub fn tlvs(&self) -> TlvIter<'a> {
+        TlvIter::new(self.buf, HSRP_V2_TLV_FORMAT)
+    }
+
+    /// Returns the value of the first TLV of `tlv_type`.
+    pub fn tlv(&self, tlv_type: HsrpV2TlvType) -> Option<&'a [u8]> {
+        let tlv_type = u32::from(u8::from(tlv_type));
+        self.tlvs()
+            .find(|tlv| tlv.tlv_type == tlv_type)
+            .map(|tlv| tlv.value)
+    }
+
+    /// Returns the group state TLVs of the message, skipping the ones that
+    /// are too short.
+    pub fn group_states(&self) -> impl Iterator<Item = HsrpV2GroupState<&'a [u8]>> {
+        let tlv_type = u32::from(u8::from(HsrpV2TlvType::GROUP_STATE));
+        self.tlvs()
+            .filter(move |tlv| tlv.tlv_type == tlv_type)
+            .filter_map(|tlv| HsrpV2GroupState::new(tlv.value).ok())
+    }
+
+    /// Returns the first group state TLV, which every message starts with.
+    #[inline]
+    pub fn group_state(&self) -> HsrpV2GroupState<&'a [u8]> {
+        HsrpV2GroupState::new_unchecked(&self.buf[2..2 + HSRP_V2_GROUP_STATE_LEN])
+    }
+
+    /// Returns the clear text password of the text authentication TLV.
+    pub fn text_auth(&self) -> Option<&'a [u8]> {
+        self.tlv(HsrpV2TlvType::TEXT_AUTH)
+    }
+
+    pub fn md5_auth(&self) -> Option<HsrpMd5Auth> {
+        let value = self.tlv(HsrpV2TlvType::MD5_AUTH)?;
+        (value.len() >= HSRP_V2_MD5_AUTH_LEN).then(|| HsrpMd5Auth::read(value))
+    }
+
+    /// Returns the numbers of the active and of the passive groups of the
+    /// interface state TLV.
+    pub fn interface_state(&self) -> Option<(u16, u16)> {
+        let value = self.tlv(HsrpV2TlvType::INTERFACE_STATE)?;
+        (value.len() >= 4).then(|| {
+            (
+                NetworkEndian::read_u16(&value[0..2]),
+                NetworkEndian::read_u16(&value[2..4]),
+            )
+        })
+    }
+}
+
+/// An HSRP message of either version.
+#[derive(Debug, Clone, Copy)]
+pub enum HsrpMessage<'a> {
+    V1(HsrpV1Header<&'a [u8]>),
+    V2(HsrpV2Message<'a>),
+}
+
+impl<'a> HsrpMessage<'a> {
+    /// Parse the payload of a UDP datagram of [`HSRP_PORT`].
+    ///
+    /// An HSRPv1 message starts with its version of 0, while an HSRPv2
+    /// message starts with the type of a group state TLV of 1.
+    pub fn parse(buf: &'a [u8]) -> Option<Self> {
+        match buf.first()? {
+            0 => HsrpV1Header::new(buf).ok().map(HsrpMessage::V1),
+            _ => HsrpV2Message::parse(buf).map(HsrpMessage::V2),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tlv::TlvWriter;
+
+    // An HSRPv1 hello of the active router of group 1, with a priority of 110
+    // and the virtual IP address of 192.168.0.1.
+    static V1_BYTES: [u8; 20] = [
+        0x00, 0x00, 0x10, 0x03, 0x0a, 0x6e, 0x01, 0x00, 0x63, 0x69, 0x73, 0x63, 0x6f, 0x00, 0x00,
+        0x00, 0xc0, 0xa8, 0x00, 0x01,
+    ];
+
+    #[test]
+    fn hsrp_v1() {
+        let header = HsrpV1Header::new(&V1_BYTES[..]).unwrap();
+        assert_eq!(header.version(), 0);
+        assert_eq!(header.op_code(), HsrpOpCode::HELLO);
+        assert_eq!(header.state(), HsrpV1State::ACTIVE);
+        assert_eq!((header.hello_time(), header.hold_time()), (3, 10));
+        assert_eq!((header.priority(), header.group()), (110, 1));
+        assert_eq!(header.auth_data(), &HSRP_DEFAULT_AUTH);
+        assert_eq!(header.virtual_ip(), Ipv4Addr::new(192, 168, 0, 1));
+        assert!(HsrpV1Header::new(&V1_BYTES[..19]).is_err());
+
+        let mut built = HSRP_V1_HEADER_TEMPLATE;
+        built.set_state(HsrpV1State::ACTIVE);
+        built.set_priority(110);
+        built.set_group(1);
+        built.set_virtual_ip(Ipv4Addr::new(192, 168, 0, 1));
+        assert_eq!(built.as_bytes(), &V1_BYTES[..]);
+
+        built.set_op_code(HsrpOpCode::RESIGN);
+        built.set_hello_time(1);
+        built.set_hold_time(4);
+        built.set_auth_data(b"secret\0\0");
+        assert_eq!(built.op_code(), HsrpOpCode::RESIGN);
+        assert_eq!((built.hello_time(), built.hold_time()), (1, 4));
+        assert_eq!(built.auth_data(), b"secret\0\0");
+
+        assert!(matches!(
+            HsrpMessage::parse(&V1_BYTES),
+            Some(HsrpMessage::V1(_))
+        ));
+        assert_eq!(hsrp_v1_virtual_mac(1).to_string(), "00:00:0c:07:ac:01");
+    }
+
+    #[test]
+    fn hsrp_v2() {
+        let mut state = [0; HSRP_V2_GROUP_STATE_LEN];
+        let mut group = HsrpV2GroupState::new_unchecked(&mut state[..]);
+        group.set_version(2);
+        group.set_op_code(HsrpOpCode::HELLO);
+        group.set_state(HsrpV2State::STANDBY);
+        group.set_group(300);
+        group.set_identifier(MacAddr([0x02, 0, 0, 0, 0, 0x01]));
+        group.set_priority(90);
+        group.set_hello_time(3000);
+        group.set_hold_time(10000);
+        group.set_virtual_ipv4(Ipv4Addr::new(192, 0, 2, 1));
+
+        let auth = HsrpMd5Auth {
+            algorithm: 1,
+            flags: 0,
+            ip_addr: Ipv4Addr::new(192, 0, 2, 2),
+            key_id: 7,
+            digest: [0xaa; 16],
+        };
+        let mut md5 = [0; HSRP_V2_MD5_AUTH_LEN];
+        auth.write(&mut md5);
+
+        let mut bytes = [0; 80];
+        let mut writer = TlvWriter::new(&mut bytes[..], HSRP_V2_TLV_FORMAT);
+        writer.write(1, &state).unwrap();
+        writer.write(4, &md5).unwrap();
+        let len = writer.written_bytes();
+        assert_eq!(len, 2 + 40 + 2 + 28);
+        assert_eq!(
+            &bytes[..8],
+            &[0x01, 0x28, 0x02, 0x00, 0x05, 0x04, 0x01, 0x2c]
+        );
+
+        let msg = match HsrpMessage::parse(&bytes[..len]).unwrap() {
+            HsrpMessage::V2(msg) => msg,
+            HsrpMessage::V1(_) => panic!(),
+        };
+        let group = msg.group_state();
+        assert_eq!((group.version(), group.op_code()), (2, HsrpOpCode::HELLO));
+        assert_eq!(group.state(), HsrpV2State::STANDBY);
+        assert_eq!((group.group(), group.priority()), (300, 90));
+        assert_eq!(group.identifier(), MacAddr([0x02, 0, 0, 0, 0, 0x01]));
+        assert_eq!((group.hello_time(), group.hold_time()), (3000, 10000));
+        assert_eq!(group.virtual_ipv4(), Some(Ipv4Addr::new(192, 0, 2, 1)));
+        assert_eq!(group.virtual_ipv6(), None);
+        assert_eq!(msg.group_states().count(), 1);
+        assert_eq!(msg.md5_auth(), Some(auth));
+        assert_eq!(msg.text_auth(), None);
+        assert_eq!(msg.interface_state(), None);
+        assert_eq!(
+            hsrp_v2_virtual_mac(group.group()).to_string(),
+            "00:00:0c:9f:f1:2c"
+        );
+
+        // A truncated TLV, and a message without a leading group state.
+        assert!(HsrpMessage::parse(&bytes[..len - 1]).is_none());
+        assert!(HsrpV2Message::parse(&bytes[44..len]).is_none());
+    }
+}
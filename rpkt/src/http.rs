@@ -0,0 +1,234 @@
+//! A sniffer of HTTP/1.x requests (RFC 9112).
+//!
+//! [`HttpRequest::sniff`] reads the request line and a few headers from the
+//! first segment of a request, which is enough to log or route the request in
+//! a DPI pipeline. It does not reassemble the TCP stream or decode the body,
+//! and the headers that do not fit in the segment are left out.
+//!
+//! # Examples
+//! ```
+//! use rpkt::http::HttpRequest;
+//! use rpkt::Cursor;
+//!
+//! let segment = b"POST /upload HTTP/1.1\r\nHost: example.com\r\nContent-Length: 5\r\n\r\nhello";
+//! let request = HttpRequest::sniff(Cursor::new(&segment[..])).unwrap();
+//! assert_eq!(request.method(), "POST");
+//! assert_eq!(request.path(), "/upload");
+//! assert_eq!(request.host(), Some("example.com"));
+//! assert_eq!(request.content_length(), Some(5));
+//! assert_eq!(request.body(), b"hello");
+//! ```
+
+use crate::Cursor;
+
+/// The maximum length of a request line or a header line that is sniffed.
+pub const HTTP_LINE_LEN_MAX: usize = 8192;
+
+/// The fields of an HTTP request sniffed from a TCP payload.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct HttpRequest<'a> {
+    method: &'a str,
+    path: &'a str,
+    minor_version: u8,
+    host: Option<&'a str>,
+    content_length: Option<u64>,
+    // The offset of the body, if the headers are complete.
+    body_start: Option<usize>,
+    buf: &'a [u8],
+}
+
+impl<'a> HttpRequest<'a> {
+    /// Sniff the request at the start of the TCP payload `buf`.
+    ///
+    /// Returns `None` if the payload does not start with a complete HTTP/1.0 or
+    /// HTTP/1.1 request line. A malformed header ends the sniffing, as if the
+    /// headers were truncated.
+    pub fn sniff(buf: Cursor<'a>) -> Option<Self> {
+        let buf = buf.chunk_shared_lifetime();
+        let (line, mut offset) = next_line(buf, 0)?;
+        let mut parts = line.split(|b| *b == b' ');
+        let method = parts
+            .next()
+            .filter(|m| !m.is_empty() && m.iter().all(is_tchar))?;
+        let path = parts
+            .next()
+            .filter(|p| !p.is_empty() && p.iter().all(u8::is_ascii_graphic))?;
+        let minor_version = match parts.next()? {
+            b"HTTP/1.0" => 0,
+            b"HTTP/1.1" => 1,
+            _ => return None,
+        };
+        if parts.next().is_some() {
+            return None;
+        }
+
+        let mut request = Self {
+            // Both are ASCII.
+            method: std::str::from_utf8(method).ok()?,
+            path: std::str::from_utf8(path).ok()?,
+            minor_version,
+            host: None,
+            content_length: None,
+            body_start: None,
+            buf,
+        };
+
+        while let Some((line, next)) = next_line(buf, offset) {
+            offset = next;
+            if line.is_empty() {
+                request.body_start = Some(offset);
+                break;
+            }
+            let Some((name, value)) = split_header(line) else {
+                break;
+            };
+            if name.eq_ignore_ascii_case(b"host") {
+                request.host = request.host.or(std::str::from_utf8(value).ok());
+            } else if name.eq_ignore_ascii_case(b"content-length") {
+                request.content_length = request.content_length.or(parse_u64(value));
+            }
+        }
+        Some(request)
+    }
+
+    #[inline]
+    pub fn method(&self) -> &'a str {
+        self.method
+    }
+
+    /// Returns the request target, usually the path and the query.
+    #[inline]
+    pub fn path(&self) -> &'a str {
+        self.path
+    }
+
+    /// Returns the minor version of HTTP/1.x.
+    #[inline]
+    pub fn minor_version(&self) -> u8 {
+        self.minor_version
+    }
+
+    /// Returns the value of the first Host header.
+    #[inline]
+    pub fn host(&self) -> Option<&'a str> {
+        self.host
+    }
+
+    /// Returns the value of the first Content-Length header, if it is a valid
+    /// number.
+    #[inline]
+    pub fn content_length(&self) -> Option<u64> {
+        self.content_length
+    }
+
+    /// Returns whether all the headers are in the payload.
+    #[inline]
+    pub fn headers_complete(&self) -> bool {
+        self.body_start.is_some()
+    }
+
+    /// Returns the part of the body in the payload, which is empty if the
+    /// headers are not complete.
+    #[inline]
+    pub fn body(&self) -> &'a [u8] {
+        self.body_start.map_or(&[], |start| &self.buf[start..])
+    }
+}
+
+// Returns the line starting at `offset` without the line ending, and the
+// offset of the next line. A bare LF ends a line as well.
+fn next_line(buf: &[u8], offset: usize) -> Option<(&[u8], usize)> {
+    let rest = buf.get(offset..)?;
+    let end = rest
+        .iter()
+        .take(HTTP_LINE_LEN_MAX + 2)
+        .position(|b| *b == b'\n')?;
+    let line = &rest[..end];
+    let line = line.strip_suffix(b"\r").unwrap_or(line);
+    Some((line, offset + end + 1))
+}
+
+// Split a header line into its name and its value without the surrounding
+// whitespaces.
+fn split_header(line: &[u8]) -> Option<(&[u8], &[u8])> {
+    let colon = line.iter().position(|b| *b == b':')?;
+    let name = &line[..colon];
+    if name.is_empty() || !name.iter().all(is_tchar) {
+        return None;
+    }
+    let value = &line[colon + 1..];
+    let start = value
+        .iter()
+        .position(|b| *b != b' ' && *b != b'\t')
+        .unwrap_or(value.len());
+    let end = value
+        .iter()
+        .rposition(|b| *b != b' ' && *b != b'\t')
+        .map_or(start, |pos| pos + 1);
+    Some((name, &value[start..end]))
+}
+
+fn parse_u64(value: &[u8]) -> Option<u64> {
+    if value.is_empty() || !value.iter().all(u8::is_ascii_digit) {
+        return None;
+    }
+    std::str::from_utf8(value).ok()?.parse().ok()
+}
+
+// The characters of a token, e.g. a method or a header name.
+fn is_tchar(b: &u8) -> bool {
+    b.is_ascii_alphanumeric() || b"!#$%&'*+-.^_`|~".contains(b)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sniff(segment: &[u8]) -> Option<HttpRequest<'_>> {
+        HttpRequest::sniff(Cursor::new(segment))
+    }
+
+    #[test]
+    fn sniff_requests() {
+        let request = sniff(
+            b"GET /index.html?q=1 HTTP/1.1\r\nUser-Agent: test\r\nhost:  Example.com:8080 \r\n\
+              HOST: other\r\nContent-Length: 0\r\n\r\n",
+        )
+        .unwrap();
+        assert_eq!(request.method(), "GET");
+        assert_eq!(request.path(), "/index.html?q=1");
+        assert_eq!(request.minor_version(), 1);
+        assert_eq!(request.host(), Some("Example.com:8080"));
+        assert_eq!(request.content_length(), Some(0));
+        assert!(request.headers_complete());
+        assert!(request.body().is_empty());
+
+        // Bare LFs, and the headers cut by the end of the segment.
+        let request = sniff(b"PUT /a HTTP/1.0\nContent-Length: 12\nHost: exam").unwrap();
+        assert_eq!(request.minor_version(), 0);
+        assert_eq!(request.content_length(), Some(12));
+        assert_eq!(request.host(), None);
+        assert!(!request.headers_complete());
+
+        // A malformed header ends the sniffing, and an invalid length is
+        // ignored.
+        let request =
+            sniff(b"GET / HTTP/1.1\r\nContent-Length: 1x\r\nbad header\r\nHost: a\r\n\r\n")
+                .unwrap();
+        assert_eq!(request.content_length(), None);
+        assert_eq!(request.host(), None);
+        assert!(!request.headers_complete());
+    }
+
+    #[test]
+    fn not_requests() {
+        // A response, HTTP/2, a TLS record, a truncated request line, and an
+        // extra space.
+        assert!(sniff(b"HTTP/1.1 200 OK\r\n\r\n").is_none());
+        assert!(sniff(b"PRI * HTTP/2.0\r\n\r\nSM\r\n\r\n").is_none());
+        assert!(sniff(&[0x16, 0x03, 0x01, 0x00, 0x05, b'\n']).is_none());
+        assert!(sniff(b"GET / HTTP/1.1").is_none());
+        assert!(sniff(b"GET  / HTTP/1.1\r\n").is_none());
+        assert!(sniff(b"").is_none());
+    }
+}
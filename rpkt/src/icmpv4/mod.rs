@@ -17,4 +17,4 @@ mod header;
 pub use header::{Icmpv4Header, ICMPV4_HEADER_LEN, ICMPV4_HEADER_TEMPLATE};
 
 mod packet;
-pub use self::packet::Icmpv4Packet;
\ No newline at end of file
+pub use self::packet::{Icmpv4Packet, ICMPV4_ERROR_QUOTE_MAX};
\ No newline at end of file
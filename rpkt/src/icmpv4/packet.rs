@@ -4,9 +4,14 @@ use crate::checksum_utils;
 use crate::ipv4::Ipv4Addr;
 use crate::{PktBuf, PktMut};
 
-use super::header::{Icmpv4Header, ICMPV4_HEADER_LEN};
+use super::header::{Icmpv4Header, ICMPV4_HEADER_LEN, ICMPV4_HEADER_TEMPLATE};
 use super::IcmpType;
 
+/// The maximum number of bytes of the offending packet that are quoted in an
+/// ICMPv4 error message, so that the error message does not exceed 576 bytes
+/// (RFC 1812, section 4.3.2.3).
+pub const ICMPV4_ERROR_QUOTE_MAX: usize = 576 - 20 - ICMPV4_HEADER_LEN;
+
 packet_base! {
     pub struct Icmpv4Packet: Icmpv4Header {
         header_len: ICMPV4_HEADER_LEN,
@@ -37,11 +42,13 @@ packet_base! {
 impl<T: Buf> Icmpv4Packet<T> {
     #[inline]
     pub fn parse(buf: T) -> Result<Icmpv4Packet<T>, T> {
-        if buf.chunk().len() >= ICMPV4_HEADER_LEN {
-            return Ok(Icmpv4Packet { buf });
-        } else {
-            Err(buf)
-        }
+        traced_parse!("icmpv4", buf, |_: &Self| ICMPV4_HEADER_LEN, {
+            if buf.chunk().len() >= ICMPV4_HEADER_LEN {
+                return Ok(Icmpv4Packet { buf });
+            } else {
+                Err(buf)
+            }
+        })
     }
 
     #[inline]
@@ -90,6 +97,72 @@ impl<T: PktMut> Icmpv4Packet<T> {
 
         Icmpv4Packet { buf }
     }
+
+    /// Prepend an ICMPv4 error message that quotes the offending IPv4 packet `orig`.
+    ///
+    /// `orig` starts from the IPv4 header of the offending packet. As much of it as
+    /// possible is quoted, bounded by the total length field of its IPv4 header and
+    /// [`ICMPV4_ERROR_QUOTE_MAX`]. The checksum of the error message is computed.
+    ///
+    /// The `buf` must be empty and have enough headroom for the error message.
+    #[inline]
+    pub fn prepend_error(
+        mut buf: T,
+        icmp_type: IcmpType,
+        code: u8,
+        rest_of_header: [u8; 4],
+        orig: &[u8],
+    ) -> Icmpv4Packet<T> {
+        let quote_len = icmpv4_quote_len(orig);
+        assert!(buf.remaining() == 0 && buf.chunk_headroom() >= ICMPV4_HEADER_LEN + quote_len);
+
+        buf.move_back(quote_len);
+        buf.chunk_mut()[..quote_len].copy_from_slice(&orig[..quote_len]);
+
+        let mut pkt = Self::prepend_header(buf, &ICMPV4_HEADER_TEMPLATE);
+        pkt.set_icmp_type(icmp_type);
+        pkt.set_code(code);
+        pkt.set_rest_of_header(&rest_of_header[..]);
+        pkt.adjust_checksum();
+        pkt
+    }
+
+    /// Prepend a Destination Unreachable message that quotes the offending IPv4 packet.
+    ///
+    /// `next_hop_mtu` is only meaningful when `code` is 4 (fragmentation needed), and
+    /// should be set to 0 otherwise.
+    #[inline]
+    pub fn prepend_dst_unreachable(
+        buf: T,
+        code: u8,
+        next_hop_mtu: u16,
+        orig: &[u8],
+    ) -> Icmpv4Packet<T> {
+        let mtu = next_hop_mtu.to_be_bytes();
+        Self::prepend_error(
+            buf,
+            IcmpType::DST_UNREACHABLE,
+            code,
+            [0, 0, mtu[0], mtu[1]],
+            orig,
+        )
+    }
+
+    /// Prepend a Time Exceeded message that quotes the offending IPv4 packet.
+    #[inline]
+    pub fn prepend_time_exceeded(buf: T, code: u8, orig: &[u8]) -> Icmpv4Packet<T> {
+        Self::prepend_error(buf, IcmpType::TIME_EXCEEDED, code, [0; 4], orig)
+    }
+}
+
+// Calculate the number of bytes of the offending packet quoted by the error message.
+fn icmpv4_quote_len(orig: &[u8]) -> usize {
+    let mut len = orig.len().min(ICMPV4_ERROR_QUOTE_MAX);
+    if orig.len() >= 4 && orig[0] >> 4 == 4 {
+        let total_len = usize::from(u16::from_be_bytes([orig[2], orig[3]]));
+        len = len.min(total_len.max(usize::from(orig[0] & 0x0f) * 4));
+    }
+    len
 }
 
 #[cfg(test)]
@@ -179,4 +252,34 @@ mod tests {
 
         assert_eq!(ethpkt.buf().chunk(), &FRAME_BYTES[..]);
     }
+
+    #[test]
+    fn error_build() {
+        let orig = &FRAME_BYTES[ETHER_HEADER_LEN..];
+
+        let mut bytes = [0xff; 200];
+        let mut buf = CursorMut::new(&mut bytes[..]);
+        buf.advance(200);
+
+        let mut icmppkt = Icmpv4Packet::prepend_time_exceeded(buf, 0, orig);
+        assert_eq!(icmppkt.icmp_type(), IcmpType::TIME_EXCEEDED);
+        assert_eq!(icmppkt.code(), 0);
+        assert_eq!(icmppkt.rest_of_header(), [0; 4]);
+        assert!(icmppkt.verify_checksum());
+        assert_eq!(icmppkt.release().chunk()[ICMPV4_HEADER_LEN..], orig[..]);
+
+        // the quote is bounded by the total length of the offending packet
+        let mut long_orig = [0; 200];
+        long_orig[..orig.len()].copy_from_slice(orig);
+        let mut bytes = [0xff; 200];
+        let mut buf = CursorMut::new(&mut bytes[..]);
+        buf.advance(200);
+
+        let mut icmppkt = Icmpv4Packet::prepend_dst_unreachable(buf, 4, 1400, &long_orig[..]);
+        assert_eq!(icmppkt.icmp_type(), IcmpType::DST_UNREACHABLE);
+        assert_eq!(icmppkt.code(), 4);
+        assert_eq!(icmppkt.next_hop_mtu(), 1400);
+        assert!(icmppkt.verify_checksum());
+        assert_eq!(icmppkt.release().chunk()[ICMPV4_HEADER_LEN..], orig[..]);
+    }
 }
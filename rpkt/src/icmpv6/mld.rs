@@ -0,0 +1,208 @@
+use byteorder::{ByteOrder, NetworkEndian};
+
+/// The length of an MLDv1 message, and of the fixed part of an MLDv2 query.
+pub const MLD_MSG_LEN: usize = 24;
+
+/// The length of the fixed part of an MLDv2 query.
+pub const MLDV2_QUERY_LEN: usize = 28;
+
+/// The length of the fixed part of an MLDv2 report.
+pub const MLDV2_REPORT_LEN: usize = 8;
+
+const MLDV2_RECORD_LEN: usize = 20;
+
+/// A multicast listener query, report or done message (RFC 2710), or an
+/// MLDv2 query (RFC 3810).
+pub struct MldMsg<T> {
+    pub(crate) buf: T,
+}
+
+impl<T: AsRef<[u8]>> MldMsg<T> {
+    #[inline]
+    pub fn max_resp_code(&self) -> u16 {
+        let data = &self.buf.as_ref()[4..6];
+        NetworkEndian::read_u16(data)
+    }
+
+    #[inline]
+    pub fn check_reserved(&self) -> bool {
+        self.buf.as_ref()[6..8] == [0, 0]
+    }
+
+    /// Returns the multicast address, which is unspecified in a general query.
+    #[inline]
+    pub fn multicast_addr(&self) -> &[u8] {
+        &self.buf.as_ref()[8..24]
+    }
+
+    /// Returns whether the message is an MLDv2 query, which is told apart by
+    /// its length.
+    #[inline]
+    pub fn is_v2_query(&self) -> bool {
+        self.buf.as_ref().len() >= MLDV2_QUERY_LEN
+    }
+
+    /// Returns the S flag of an MLDv2 query.
+    #[inline]
+    pub fn s_flag(&self) -> bool {
+        (self.buf.as_ref()[24] >> 3) & 1 == 1
+    }
+
+    /// Returns the querier's robustness variable of an MLDv2 query.
+    #[inline]
+    pub fn qrv(&self) -> u8 {
+        self.buf.as_ref()[24] & 0x07
+    }
+
+    /// Returns the querier's query interval code of an MLDv2 query.
+    #[inline]
+    pub fn qqic(&self) -> u8 {
+        self.buf.as_ref()[25]
+    }
+
+    /// Returns the number of sources of an MLDv2 query.
+    #[inline]
+    pub fn nb_sources(&self) -> u16 {
+        let data = &self.buf.as_ref()[26..28];
+        NetworkEndian::read_u16(data)
+    }
+
+    /// Returns the source addresses of an MLDv2 query, truncated to the
+    /// message.
+    #[inline]
+    pub fn source_addrs(&self) -> &[u8] {
+        let data = &self.buf.as_ref()[MLDV2_QUERY_LEN..];
+        let len = (usize::from(self.nb_sources()) * 16).min(data.len() / 16 * 16);
+        &data[..len]
+    }
+}
+
+impl<T: AsMut<[u8]>> MldMsg<T> {
+    #[inline]
+    pub fn set_max_resp_code(&mut self, value: u16) {
+        let data = &mut self.buf.as_mut()[4..6];
+        NetworkEndian::write_u16(data, value);
+    }
+
+    #[inline]
+    pub fn adjust_reserved(&mut self) {
+        self.buf.as_mut()[6..8].fill(0);
+    }
+
+    #[inline]
+    pub fn set_multicast_addr(&mut self, addr: &[u8]) {
+        self.buf.as_mut()[8..24].copy_from_slice(addr);
+    }
+}
+
+/// An MLDv2 report (RFC 3810).
+pub struct Mldv2Report<T> {
+    pub(crate) buf: T,
+}
+
+impl<T: AsRef<[u8]>> Mldv2Report<T> {
+    #[inline]
+    pub fn check_reserved(&self) -> bool {
+        self.buf.as_ref()[4..6] == [0, 0]
+    }
+
+    #[inline]
+    pub fn nb_records(&self) -> u16 {
+        let data = &self.buf.as_ref()[6..8];
+        NetworkEndian::read_u16(data)
+    }
+
+    #[inline]
+    pub fn record_bytes(&self) -> &[u8] {
+        &self.buf.as_ref()[MLDV2_REPORT_LEN..]
+    }
+
+    /// Returns an iterator over the multicast address records, which stops at
+    /// the first truncated record.
+    #[inline]
+    pub fn records(&self) -> Mldv2RecordIter<'_> {
+        Mldv2RecordIter {
+            buf: self.record_bytes(),
+            remaining: self.nb_records(),
+        }
+    }
+}
+
+impl<T: AsMut<[u8]>> Mldv2Report<T> {
+    #[inline]
+    pub fn adjust_reserved(&mut self) {
+        self.buf.as_mut()[4..6].fill(0);
+    }
+
+    #[inline]
+    pub fn set_nb_records(&mut self, value: u16) {
+        let data = &mut self.buf.as_mut()[6..8];
+        NetworkEndian::write_u16(data, value);
+    }
+
+    #[inline]
+    pub fn record_bytes_mut(&mut self) -> &mut [u8] {
+        &mut self.buf.as_mut()[MLDV2_REPORT_LEN..]
+    }
+}
+
+/// A multicast address record of an MLDv2 report.
+pub struct Mldv2Record<'a> {
+    buf: &'a [u8],
+}
+
+impl<'a> Mldv2Record<'a> {
+    #[inline]
+    pub fn record_type(&self) -> u8 {
+        self.buf[0]
+    }
+
+    #[inline]
+    pub fn aux_data_len(&self) -> u8 {
+        self.buf[1]
+    }
+
+    #[inline]
+    pub fn nb_sources(&self) -> u16 {
+        NetworkEndian::read_u16(&self.buf[2..4])
+    }
+
+    #[inline]
+    pub fn multicast_addr(&self) -> &'a [u8] {
+        &self.buf[4..20]
+    }
+
+    #[inline]
+    pub fn source_addrs(&self) -> &'a [u8] {
+        let len = usize::from(self.nb_sources()) * 16;
+        &self.buf[MLDV2_RECORD_LEN..MLDV2_RECORD_LEN + len]
+    }
+}
+
+pub struct Mldv2RecordIter<'a> {
+    buf: &'a [u8],
+    remaining: u16,
+}
+
+impl<'a> Iterator for Mldv2RecordIter<'a> {
+    type Item = Mldv2Record<'a>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.remaining == 0 || self.buf.len() < MLDV2_RECORD_LEN {
+            return None;
+        }
+
+        let nb_sources = usize::from(NetworkEndian::read_u16(&self.buf[2..4]));
+        let aux_len = usize::from(self.buf[1]) * 4;
+        let record_len = MLDV2_RECORD_LEN + nb_sources * 16 + aux_len;
+        if record_len > self.buf.len() {
+            self.remaining = 0;
+            return None;
+        }
+
+        let (record, rest) = self.buf.split_at(record_len);
+        self.buf = rest;
+        self.remaining -= 1;
+        Some(Mldv2Record { buf: record })
+    }
+}
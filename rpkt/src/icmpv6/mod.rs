@@ -14,7 +14,7 @@ enum_sim! {
         NDP_NEIGHBOR_SOLICIT = 135,
         NDP_NEIGHBOR_ADV = 136,
         NDP_REDIRECT = 137,
-        // mld messages,
+        // mld messages, the queries of both versions share the type
         MLDV2_LISTENER_QUERY = 130,
         MLDV2_LISTENER_REPORT = 143,
         MLDV1_LISTENER_REPORT = 131,
@@ -23,10 +23,11 @@ enum_sim! {
 }
 
 mod packet;
-pub use packet::{Icmpv6Msg, Icmpv6MsgMut, Icmpv6Packet};
+pub use packet::{Icmpv6Msg, Icmpv6MsgMut, Icmpv6Packet, ICMPV6_ERROR_QUOTE_MAX};
 
 mod msg;
 pub use msg::{Icmpv6MsgEcho, Icmpv6MsgGeneric, Icmpv6MsgMtu, Icmpv6MsgPtr};
 
 pub mod mld;
+pub use mld::{MldMsg, Mldv2Report};
 pub mod ndp;
@@ -14,6 +14,12 @@ impl<T: AsRef<[u8]>> NdpMsgRouterSolicit<T> {
     pub fn option_bytes(&self) -> &[u8] {
         &self.buf.as_ref()[8..]
     }
+
+    /// Returns an iterator over the options of the message.
+    #[inline]
+    pub fn options(&self) -> NdpOptionIter<'_> {
+        NdpOptionIter::from_option_bytes(self.option_bytes())
+    }
 }
 
 impl<T: AsMut<[u8]>> NdpMsgRouterSolicit<T> {
@@ -75,6 +81,12 @@ impl<T: AsRef<[u8]>> NdpMsgRouterAdv<T> {
     pub fn option_bytes(&self) -> &[u8] {
         &self.buf.as_ref()[16..]
     }
+
+    /// Returns an iterator over the options of the message.
+    #[inline]
+    pub fn options(&self) -> NdpOptionIter<'_> {
+        NdpOptionIter::from_option_bytes(self.option_bytes())
+    }
 }
 
 impl<T: AsMut<[u8]>> NdpMsgRouterAdv<T> {
@@ -149,6 +161,12 @@ impl<T: AsRef<[u8]>> NdpMsgNeighborSolicit<T> {
     pub fn option_bytes(&self) -> &[u8] {
         &self.buf.as_ref()[24..]
     }
+
+    /// Returns an iterator over the options of the message.
+    #[inline]
+    pub fn options(&self) -> NdpOptionIter<'_> {
+        NdpOptionIter::from_option_bytes(self.option_bytes())
+    }
 }
 
 impl<T: AsMut<[u8]>> NdpMsgNeighborSolicit<T> {
@@ -203,6 +221,12 @@ impl<T: AsRef<[u8]>> NdpMsgNeighborAdv<T> {
     pub fn option_bytes(&self) -> &[u8] {
         &self.buf.as_ref()[24..]
     }
+
+    /// Returns an iterator over the options of the message.
+    #[inline]
+    pub fn options(&self) -> NdpOptionIter<'_> {
+        NdpOptionIter::from_option_bytes(self.option_bytes())
+    }
 }
 
 impl<T: AsMut<[u8]>> NdpMsgNeighborAdv<T> {
@@ -275,6 +299,12 @@ impl<T: AsRef<[u8]>> NdpMsgRedirect<T> {
     pub fn option_bytes(&self) -> &[u8] {
         &self.buf.as_ref()[40..]
     }
+
+    /// Returns an iterator over the options of the message.
+    #[inline]
+    pub fn options(&self) -> NdpOptionIter<'_> {
+        NdpOptionIter::from_option_bytes(self.option_bytes())
+    }
 }
 
 impl<T: AsMut<[u8]>> NdpMsgRedirect<T> {
@@ -417,7 +417,7 @@ pub struct NdpOptionWriter<'a> {
 impl<'a> NdpOptionWriter<'a> {
     #[inline]
     pub fn src_link_addr(&mut self) -> NdpOptionLinkAddr<&'a mut [u8]> {
-        assert!(self.buf.len() > 8);
+        assert!(self.buf.len() >= 8);
 
         self.buf[0] = SRC_LINK_ADDR;
         self.buf[1] = 1;
@@ -433,7 +433,7 @@ impl<'a> NdpOptionWriter<'a> {
 
     #[inline]
     pub fn dst_link_addr(&mut self) -> NdpOptionLinkAddr<&'a mut [u8]> {
-        assert!(self.buf.len() > 8);
+        assert!(self.buf.len() >= 8);
 
         self.buf[0] = DST_LINK_ADDR;
         self.buf[1] = 1;
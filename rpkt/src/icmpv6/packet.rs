@@ -1,13 +1,22 @@
+use crate::checksum_utils;
+use crate::ipv4::IpProtocol;
+use crate::ipv6::Ipv6Addr;
 use crate::PktMut;
 use byteorder::{ByteOrder, NetworkEndian};
 use bytes::Buf;
 
+use super::mld::{MldMsg, Mldv2Report, MLDV2_REPORT_LEN, MLD_MSG_LEN};
 use super::msg::*;
 use super::ndp::{
     NdpMsgNeighborAdv, NdpMsgNeighborSolicit, NdpMsgRedirect, NdpMsgRouterAdv, NdpMsgRouterSolicit,
 };
 use super::Icmpv6MsgType;
 
+/// The maximum number of bytes of the offending packet that are quoted in an
+/// ICMPv6 error message, so that the error message does not exceed the minimum
+/// IPv6 MTU (RFC 4443, section 2.4).
+pub const ICMPV6_ERROR_QUOTE_MAX: usize = 1280 - 40 - 8;
+
 pub enum Icmpv6Msg<'a> {
     DstUnreachable(Icmpv6MsgGeneric<&'a [u8]>),
     PktTooBig(Icmpv6MsgMtu<&'a [u8]>),
@@ -20,6 +29,10 @@ pub enum Icmpv6Msg<'a> {
     NdpRedirect(NdpMsgRedirect<&'a [u8]>),
     NdpRouterAdv(NdpMsgRouterAdv<&'a [u8]>),
     NdpRouterSolicit(NdpMsgRouterSolicit<&'a [u8]>),
+    MldQuery(MldMsg<&'a [u8]>),
+    MldReport(MldMsg<&'a [u8]>),
+    MldDone(MldMsg<&'a [u8]>),
+    Mldv2Report(Mldv2Report<&'a [u8]>),
     Invalid(u8),
 }
 
@@ -35,6 +48,10 @@ pub enum Icmpv6MsgMut<'a> {
     NdpRedirect(NdpMsgRedirect<&'a mut [u8]>),
     NdpRouterAdv(NdpMsgRouterAdv<&'a mut [u8]>),
     NdpRouterSolicit(NdpMsgRouterSolicit<&'a mut [u8]>),
+    MldQuery(MldMsg<&'a mut [u8]>),
+    MldReport(MldMsg<&'a mut [u8]>),
+    MldDone(MldMsg<&'a mut [u8]>),
+    Mldv2Report(Mldv2Report<&'a mut [u8]>),
     Invalid(u8),
 }
 
@@ -83,11 +100,32 @@ impl<T: Buf> Icmpv6Packet<T> {
 
     #[inline]
     pub fn parse(buf: T) -> Result<Icmpv6Packet<T>, T> {
-        if buf.chunk().len() >= 8 && buf.chunk().len() == buf.remaining() {
-            Ok(Icmpv6Packet { buf })
-        } else {
-            Err(buf)
-        }
+        traced_parse!("icmpv6", buf, |_: &Self| 8, {
+            if buf.chunk().len() >= 8 && buf.chunk().len() == buf.remaining() {
+                Ok(Icmpv6Packet { buf })
+            } else {
+                Err(buf)
+            }
+        })
+    }
+
+    /// Calculate the checksum of the message together with the IPv6 pseudo header.
+    #[inline]
+    pub fn calc_checksum(&self, src_ip: Ipv6Addr, dst_ip: Ipv6Addr) -> u16 {
+        let data = self.buf.chunk();
+        let len = (data.len() as u32).to_be_bytes();
+        checksum_utils::combine(&[
+            checksum_utils::from_slice(src_ip.as_bytes()),
+            checksum_utils::from_slice(dst_ip.as_bytes()),
+            checksum_utils::from_slice(&len[..]),
+            u16::from(u8::from(IpProtocol::ICMPV6)),
+            checksum_utils::from_slice(data),
+        ])
+    }
+
+    #[inline]
+    pub fn verify_checksum(&self, src_ip: Ipv6Addr, dst_ip: Ipv6Addr) -> bool {
+        self.calc_checksum(src_ip, dst_ip) == !0
     }
 
     #[inline]
@@ -159,6 +197,42 @@ impl<T: Buf> Icmpv6Packet<T> {
                             })
                         }
                     }
+                    Icmpv6MsgType::MLDV2_LISTENER_QUERY => {
+                        if pkt_len < MLD_MSG_LEN {
+                            Icmpv6Msg::Invalid(self.msg_type().into())
+                        } else {
+                            Icmpv6Msg::MldQuery(MldMsg {
+                                buf: self.buf.chunk(),
+                            })
+                        }
+                    }
+                    Icmpv6MsgType::MLDV1_LISTENER_REPORT => {
+                        if pkt_len < MLD_MSG_LEN {
+                            Icmpv6Msg::Invalid(self.msg_type().into())
+                        } else {
+                            Icmpv6Msg::MldReport(MldMsg {
+                                buf: self.buf.chunk(),
+                            })
+                        }
+                    }
+                    Icmpv6MsgType::MLDV1_LISTENER_DONE => {
+                        if pkt_len < MLD_MSG_LEN {
+                            Icmpv6Msg::Invalid(self.msg_type().into())
+                        } else {
+                            Icmpv6Msg::MldDone(MldMsg {
+                                buf: self.buf.chunk(),
+                            })
+                        }
+                    }
+                    Icmpv6MsgType::MLDV2_LISTENER_REPORT => {
+                        if pkt_len < MLDV2_REPORT_LEN {
+                            Icmpv6Msg::Invalid(self.msg_type().into())
+                        } else {
+                            Icmpv6Msg::Mldv2Report(Mldv2Report {
+                                buf: self.buf.chunk(),
+                            })
+                        }
+                    }
                     _ => Icmpv6Msg::Invalid(self.msg_type().into()),
                 }
             }
@@ -257,12 +331,149 @@ impl<T: PktMut> Icmpv6Packet<T> {
                             })
                         }
                     }
+                    Icmpv6MsgType::MLDV2_LISTENER_QUERY => {
+                        if pkt_len < MLD_MSG_LEN {
+                            Icmpv6MsgMut::Invalid(self.msg_type().into())
+                        } else {
+                            Icmpv6MsgMut::MldQuery(MldMsg {
+                                buf: self.buf.chunk_mut(),
+                            })
+                        }
+                    }
+                    Icmpv6MsgType::MLDV1_LISTENER_REPORT => {
+                        if pkt_len < MLD_MSG_LEN {
+                            Icmpv6MsgMut::Invalid(self.msg_type().into())
+                        } else {
+                            Icmpv6MsgMut::MldReport(MldMsg {
+                                buf: self.buf.chunk_mut(),
+                            })
+                        }
+                    }
+                    Icmpv6MsgType::MLDV1_LISTENER_DONE => {
+                        if pkt_len < MLD_MSG_LEN {
+                            Icmpv6MsgMut::Invalid(self.msg_type().into())
+                        } else {
+                            Icmpv6MsgMut::MldDone(MldMsg {
+                                buf: self.buf.chunk_mut(),
+                            })
+                        }
+                    }
+                    Icmpv6MsgType::MLDV2_LISTENER_REPORT => {
+                        if pkt_len < MLDV2_REPORT_LEN {
+                            Icmpv6MsgMut::Invalid(self.msg_type().into())
+                        } else {
+                            Icmpv6MsgMut::Mldv2Report(Mldv2Report {
+                                buf: self.buf.chunk_mut(),
+                            })
+                        }
+                    }
                     _ => Icmpv6MsgMut::Invalid(self.msg_type().into()),
                 }
             }
         }
     }
 
+    #[inline]
+    pub fn adjust_checksum(&mut self, src_ip: Ipv6Addr, dst_ip: Ipv6Addr) {
+        self.set_checksum(0);
+        let cksum = !self.calc_checksum(src_ip, dst_ip);
+        self.set_checksum(cksum);
+    }
+
+    /// Prepend an ICMPv6 error message that quotes the offending IPv6 packet `orig`.
+    ///
+    /// `orig` starts from the IPv6 header of the offending packet. As much of it as
+    /// possible is quoted, bounded by the payload length field of its IPv6 header and
+    /// [`ICMPV6_ERROR_QUOTE_MAX`]. The checksum of the error message is computed with
+    /// `src_ip` and `dst_ip`, which are the addresses of the IPv6 packet carrying the
+    /// error message.
+    ///
+    /// The `buf` must be empty and have enough headroom for the error message.
+    #[inline]
+    pub fn prepend_error(
+        mut buf: T,
+        msg_type: Icmpv6MsgType,
+        code: u8,
+        rest_of_header: [u8; 4],
+        orig: &[u8],
+        src_ip: Ipv6Addr,
+        dst_ip: Ipv6Addr,
+    ) -> Icmpv6Packet<T> {
+        let quote_len = icmpv6_quote_len(orig);
+        let msg_len = 8 + quote_len;
+        assert!(buf.remaining() == 0 && buf.chunk_headroom() >= msg_len);
+
+        Self::prepend_msg(&mut buf, msg_type, msg_len);
+        let data = buf.chunk_mut();
+        data[1] = code;
+        data[4..8].copy_from_slice(&rest_of_header[..]);
+        data[8..msg_len].copy_from_slice(&orig[..quote_len]);
+
+        let mut pkt = Icmpv6Packet { buf };
+        pkt.adjust_checksum(src_ip, dst_ip);
+        pkt
+    }
+
+    /// Prepend a Destination Unreachable message that quotes the offending IPv6 packet.
+    #[inline]
+    pub fn prepend_dst_unreachable(
+        buf: T,
+        code: u8,
+        orig: &[u8],
+        src_ip: Ipv6Addr,
+        dst_ip: Ipv6Addr,
+    ) -> Icmpv6Packet<T> {
+        Self::prepend_error(
+            buf,
+            Icmpv6MsgType::DST_UNREACHABLE,
+            code,
+            [0; 4],
+            orig,
+            src_ip,
+            dst_ip,
+        )
+    }
+
+    /// Prepend a Packet Too Big message that quotes the offending IPv6 packet.
+    #[inline]
+    pub fn prepend_pkt_too_big(
+        buf: T,
+        mtu: u32,
+        orig: &[u8],
+        src_ip: Ipv6Addr,
+        dst_ip: Ipv6Addr,
+    ) -> Icmpv6Packet<T> {
+        Self::prepend_error(
+            buf,
+            Icmpv6MsgType::PKT_TOO_BIG,
+            0,
+            mtu.to_be_bytes(),
+            orig,
+            src_ip,
+            dst_ip,
+        )
+    }
+
+    /// Prepend a Time Exceeded message that quotes the offending IPv6 packet.
+    #[inline]
+    pub fn prepend_time_exceeded(
+        buf: T,
+        code: u8,
+        orig: &[u8],
+        src_ip: Ipv6Addr,
+        dst_ip: Ipv6Addr,
+    ) -> Icmpv6Packet<T> {
+        Self::prepend_error(
+            buf,
+            Icmpv6MsgType::TIME_EXCEED,
+            code,
+            [0; 4],
+            orig,
+            src_ip,
+            dst_ip,
+        )
+    }
+
     #[inline]
     fn prepend_msg(buf: &mut T, msg_type: Icmpv6MsgType, msg_len: usize) {
         buf.move_back(msg_len);
@@ -384,3 +595,150 @@ impl<T: PktMut> Icmpv6Packet<T> {
         }
     }
 }
+
+// Calculate the number of bytes of the offending packet quoted by the error message.
+fn icmpv6_quote_len(orig: &[u8]) -> usize {
+    let mut len = orig.len().min(ICMPV6_ERROR_QUOTE_MAX);
+    if orig.len() >= 6 && orig[0] >> 4 == 6 {
+        let payload_len = usize::from(u16::from_be_bytes([orig[4], orig[5]]));
+        len = len.min(40 + payload_len);
+    }
+    len
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::icmpv6::ndp::NdpOption;
+    use crate::{Cursor, CursorMut};
+
+    #[test]
+    fn mld_msgs() {
+        // An MLDv2 query for a group, with one source.
+        let mut bytes = [0; 44];
+        bytes[0] = 130;
+        bytes[4..6].copy_from_slice(&1000u16.to_be_bytes());
+        bytes[8] = 0xff;
+        bytes[23] = 0x01;
+        bytes[24] = 0x0a;
+        bytes[25] = 125;
+        bytes[27] = 1;
+        bytes[43] = 0x02;
+        let pkt = Icmpv6Packet::parse(Cursor::new(&bytes[..])).unwrap();
+        let msg = match pkt.msg() {
+            Icmpv6Msg::MldQuery(msg) => msg,
+            _ => panic!("not an mld query"),
+        };
+        assert!(msg.is_v2_query() && msg.s_flag());
+        assert_eq!((msg.max_resp_code(), msg.qrv(), msg.qqic()), (1000, 2, 125));
+        assert_eq!(msg.multicast_addr()[0], 0xff);
+        assert_eq!(msg.source_addrs(), &bytes[28..44]);
+
+        // An MLDv1 done message is not a v2 query, and a truncated one is
+        // invalid.
+        bytes[0] = 132;
+        let pkt = Icmpv6Packet::parse(Cursor::new(&bytes[..24])).unwrap();
+        assert!(matches!(pkt.msg(), Icmpv6Msg::MldDone(msg) if !msg.is_v2_query()));
+        let pkt = Icmpv6Packet::parse(Cursor::new(&bytes[..16])).unwrap();
+        assert!(matches!(pkt.msg(), Icmpv6Msg::Invalid(132)));
+
+        // An MLDv2 report with two records, the second one truncated.
+        let mut bytes = vec![143, 0, 0, 0, 0, 0, 0, 2];
+        bytes.extend_from_slice(&[4, 0, 0, 1]);
+        bytes.extend_from_slice(&[0xff; 16]);
+        bytes.extend_from_slice(&[0x20; 16]);
+        bytes.extend_from_slice(&[3, 0, 0, 1]);
+        bytes.extend_from_slice(&[0xff; 16]);
+        let pkt = Icmpv6Packet::parse(Cursor::new(&bytes[..])).unwrap();
+        let report = match pkt.msg() {
+            Icmpv6Msg::Mldv2Report(report) => report,
+            _ => panic!("not an mldv2 report"),
+        };
+        assert_eq!(report.nb_records(), 2);
+        let records = report.records().collect::<Vec<_>>();
+        assert_eq!(records.len(), 1);
+        assert_eq!(records[0].record_type(), 4);
+        assert_eq!(records[0].multicast_addr(), &[0xff; 16]);
+        assert_eq!(records[0].source_addrs(), &[0x20; 16]);
+    }
+
+    #[test]
+    fn ndp_options() {
+        // A router solicitation with a source link-layer address option.
+        let mut bytes = [0; 16];
+        bytes[0] = 133;
+        bytes[8..16].copy_from_slice(&[1, 1, 0, 1, 2, 3, 4, 5]);
+        let pkt = Icmpv6Packet::parse(Cursor::new(&bytes[..])).unwrap();
+        let msg = match pkt.msg() {
+            Icmpv6Msg::NdpRouterSolicit(msg) => msg,
+            _ => panic!("not a router solicitation"),
+        };
+        let mut options = msg.options();
+        match options.next() {
+            Some(NdpOption::SrcLinkAddr(opt)) => assert_eq!(opt.link_addr(), &[0, 1, 2, 3, 4, 5]),
+            _ => panic!("no source link-layer address option"),
+        }
+        assert!(options.next().is_none());
+    }
+
+    #[test]
+    fn error_build() {
+        let src_ip = Ipv6Addr::new(0x2001, 0xdb8, 0, 0, 0, 0, 0, 1);
+        let dst_ip = Ipv6Addr::new(0x2001, 0xdb8, 0, 0, 0, 0, 0, 2);
+
+        // An offending packet that is larger than the minimum IPv6 MTU.
+        let mut orig = vec![0xab; 1500];
+        orig[0] = 0x60;
+        orig[4..6].copy_from_slice(&1460u16.to_be_bytes());
+
+        let mut bytes = [0xff; 1500];
+        let mut buf = CursorMut::new(&mut bytes[..]);
+        buf.advance(1500);
+
+        let icmppkt = Icmpv6Packet::prepend_pkt_too_big(buf, 1400, &orig[..], src_ip, dst_ip);
+        assert_eq!(icmppkt.msg_type(), Icmpv6MsgType::PKT_TOO_BIG);
+        assert!(icmppkt.verify_checksum(src_ip, dst_ip));
+        assert!(!icmppkt.verify_checksum(src_ip, Ipv6Addr::LOOPBACK));
+        match icmppkt.msg() {
+            Icmpv6Msg::PktTooBig(msg) => {
+                assert_eq!(msg.mtu(), 1400);
+                assert_eq!(msg.data(), &orig[..ICMPV6_ERROR_QUOTE_MAX]);
+            }
+            _ => panic!("not a packet too big message"),
+        }
+        assert_eq!(icmppkt.release().chunk().len(), 8 + ICMPV6_ERROR_QUOTE_MAX);
+
+        // The quote is bounded by the payload length of the offending packet.
+        orig[4..6].copy_from_slice(&20u16.to_be_bytes());
+        let mut bytes = [0xff; 200];
+        let mut buf = CursorMut::new(&mut bytes[..]);
+        buf.advance(200);
+
+        let icmppkt = Icmpv6Packet::prepend_time_exceeded(buf, 1, &orig[..], src_ip, dst_ip);
+        assert_eq!(icmppkt.msg_type(), Icmpv6MsgType::TIME_EXCEED);
+        assert!(icmppkt.verify_checksum(src_ip, dst_ip));
+        match icmppkt.msg() {
+            Icmpv6Msg::TimeExceed(msg) => {
+                assert_eq!(msg.code(), 1);
+                assert!(msg.check_reserved());
+                assert_eq!(msg.data(), &orig[..60]);
+            }
+            _ => panic!("not a time exceeded message"),
+        }
+
+        let mut bytes = [0xff; 200];
+        let mut buf = CursorMut::new(&mut bytes[..]);
+        buf.advance(200);
+
+        let icmppkt = Icmpv6Packet::prepend_dst_unreachable(buf, 4, &orig[..], src_ip, dst_ip);
+        assert_eq!(icmppkt.msg_type(), Icmpv6MsgType::DST_UNREACHABLE);
+        assert!(icmppkt.verify_checksum(src_ip, dst_ip));
+        match icmppkt.msg() {
+            Icmpv6Msg::DstUnreachable(msg) => {
+                assert_eq!(msg.code(), 4);
+                assert_eq!(msg.data(), &orig[..60]);
+            }
+            _ => panic!("not a destination unreachable message"),
+        }
+    }
+}
@@ -0,0 +1,563 @@
+//! The messages of IKEv2 (RFC 7296).
+//!
+//! An IKE message is carried by a UDP datagram of
+//! [`IKE_PORT`](crate::ipsec::IKE_PORT), or of
+//! [`IPSEC_NAT_T_PORT`](crate::ipsec::IPSEC_NAT_T_PORT) after the non-ESP
+//! marker, see [`UdpEncap`](crate::ipsec::UdpEncap). It starts with a 28-byte
+//! [`IkeHeader`], followed by a chain of payloads: the next payload field of
+//! the header gives the type of the first payload, and the one of every
+//! payload the type of the following payload. [`IkeMessage::payloads`]
+//! follows the chain.
+//!
+//! An [`IkePayloadType::SK`] payload holds the other payloads encrypted, and
+//! is always the last one, so that only the payloads of the `IKE_SA_INIT`
+//! exchange are readable on the wire.
+//!
+//! # Examples
+//! ```
+//! use rpkt::ikev2::*;
+//!
+//! // An IKE_SA_INIT request with an empty nonce payload.
+//! let mut buf = [0; IKE_HEADER_LEN + 4];
+//! let mut header = IkeHeader::new(&mut buf[..]).unwrap();
+//! header.set_initiator_spi(0x0123_4567_89ab_cdef);
+//! header.set_next_payload(IkePayloadType::NONCE);
+//! header.adjust_version();
+//! header.set_exchange_type(IkeExchangeType::IKE_SA_INIT);
+//! header.set_flags(IKE_FLAG_INITIATOR);
+//! header.set_length(32);
+//! buf[IKE_HEADER_LEN + 3] = 4;
+//!
+//! let msg = IkeMessage::parse(&buf).unwrap();
+//! assert_eq!(msg.header().exchange_type(), IkeExchangeType::IKE_SA_INIT);
+//! assert!(msg.header().is_initiator() && !msg.header().is_response());
+//! let types: Vec<_> = msg.payloads().map(|payload| payload.payload_type).collect();
+//! assert_eq!(types, [IkePayloadType::NONCE]);
+//! ```
+
+use byteorder::{ByteOrder, NetworkEndian};
+
+/// The length of the IKE header.
+pub const IKE_HEADER_LEN: usize = 28;
+
+/// The length of the generic header of the payloads.
+pub const IKE_PAYLOAD_HEADER_LEN: usize = 4;
+
+/// The flag set by the original initiator of the IKE SA.
+pub const IKE_FLAG_INITIATOR: u8 = 0x08;
+
+/// The flag set by an implementation of a higher major version.
+pub const IKE_FLAG_VERSION: u8 = 0x10;
+
+/// The flag set in the responses.
+pub const IKE_FLAG_RESPONSE: u8 = 0x20;
+
+/// The version 2.0, as the major version in the high 4 bits and the minor
+/// version in the low 4 bits.
+pub const IKE_VERSION: u8 = 0x20;
+
+enum_sim! {
+    /// The type of an IKEv2 payload.
+    ///
+    /// See https://www.iana.org/assignments/ikev2-parameters/ikev2-parameters.xhtml
+    pub struct IkePayloadType (u8) {
+        /// No next payload.
+        NONE = 0,
+        SA = 33,
+        KE = 34,
+        IDI = 35,
+        IDR = 36,
+        CERT = 37,
+        CERTREQ = 38,
+        AUTH = 39,
+        NONCE = 40,
+        NOTIFY = 41,
+        DELETE = 42,
+        VENDOR_ID = 43,
+        TSI = 44,
+        TSR = 45,
+        /// The encrypted and authenticated payload.
+        SK = 46,
+        CP = 47,
+        EAP = 48,
+        /// An encrypted and authenticated fragment (RFC 7383).
+        SKF = 53,
+    }
+}
+
+enum_sim! {
+    /// The exchange type of an IKEv2 message.
+    pub struct IkeExchangeType (u8) {
+        IKE_SA_INIT = 34,
+        IKE_AUTH = 35,
+        CREATE_CHILD_SA = 36,
+        INFORMATIONAL = 37,
+    }
+}
+
+enum_sim! {
+    /// The type of a notify payload, below 16384 for the errors.
+    pub struct IkeNotifyType (u16) {
+        UNSUPPORTED_CRITICAL_PAYLOAD = 1,
+        INVALID_IKE_SPI = 4,
+        INVALID_MAJOR_VERSION = 5,
+        INVALID_SYNTAX = 7,
+        INVALID_MESSAGE_ID = 9,
+        INVALID_SPI = 11,
+        NO_PROPOSAL_CHOSEN = 14,
+        INVALID_KE_PAYLOAD = 17,
+        AUTHENTICATION_FAILED = 24,
+        TEMPORARY_FAILURE = 43,
+        INITIAL_CONTACT = 16384,
+        NAT_DETECTION_SOURCE_IP = 16388,
+        NAT_DETECTION_DESTINATION_IP = 16389,
+        COOKIE = 16390,
+        REKEY_SA = 16393,
+        FRAGMENTATION_SUPPORTED = 16430,
+    }
+}
+
+impl IkeNotifyType {
+    /// Returns whether the notification reports an error.
+    #[inline]
+    pub fn is_error(&self) -> bool {
+        self.0 < 16384
+    }
+}
+
+header_field_range_accessors! {
+    (initiator_spi, initiator_spi_mut, 0..8),
+    (responder_spi, responder_spi_mut, 8..16),
+    (message_id, message_id_mut, 20..24),
+    (length, length_mut, 24..28),
+}
+
+header_field_val_accessors! {
+    (next_payload, next_payload_mut, 16),
+    (version, version_mut, 17),
+    (exchange_type, exchange_type_mut, 18),
+    (flags, flags_mut, 19),
+}
+
+/// The header of an IKEv2 message.
+#[derive(Clone, Copy, Debug)]
+pub struct IkeHeader<T> {
+    buf: T,
+}
+
+impl<T: AsRef<[u8]>> IkeHeader<T> {
+    #[inline]
+    pub fn new(buf: T) -> Result<Self, T> {
+        if buf.as_ref().len() >= IKE_HEADER_LEN {
+            Ok(Self { buf })
+        } else {
+            Err(buf)
+        }
+    }
+
+    #[inline]
+    pub fn new_unchecked(buf: T) -> Self {
+        Self { buf }
+    }
+
+    #[inline]
+    pub fn as_bytes(&self) -> &[u8] {
+        &self.buf.as_ref()[0..IKE_HEADER_LEN]
+    }
+
+    #[inline]
+    pub fn to_owned(&self) -> IkeHeader<[u8; IKE_HEADER_LEN]> {
+        let mut buf = [0; IKE_HEADER_LEN];
+        buf.copy_from_slice(self.as_bytes());
+        IkeHeader { buf }
+    }
+
+    #[inline]
+    pub fn initiator_spi(&self) -> u64 {
+        NetworkEndian::read_u64(initiator_spi(self.buf.as_ref()))
+    }
+
+    /// Returns the SPI of the responder, 0 in the first message of an
+    /// exchange.
+    #[inline]
+    pub fn responder_spi(&self) -> u64 {
+        NetworkEndian::read_u64(responder_spi(self.buf.as_ref()))
+    }
+
+    #[inline]
+    pub fn next_payload(&self) -> IkePayloadType {
+        (*next_payload(self.buf.as_ref())).into()
+    }
+
+    #[inline]
+    pub fn major_version(&self) -> u8 {
+        *version(self.buf.as_ref()) >> 4
+    }
+
+    #[inline]
+    pub fn minor_version(&self) -> u8 {
+        *version(self.buf.as_ref()) & 0x0f
+    }
+
+    #[inline]
+    pub fn exchange_type(&self) -> IkeExchangeType {
+        (*exchange_type(self.buf.as_ref())).into()
+    }
+
+    #[inline]
+    pub fn flags(&self) -> u8 {
+        *flags(self.buf.as_ref())
+    }
+
+    #[inline]
+    pub fn is_initiator(&self) -> bool {
+        self.flags() & IKE_FLAG_INITIATOR != 0
+    }
+
+    #[inline]
+    pub fn is_response(&self) -> bool {
+        self.flags() & IKE_FLAG_RESPONSE != 0
+    }
+
+    #[inline]
+    pub fn message_id(&self) -> u32 {
+        NetworkEndian::read_u32(message_id(self.buf.as_ref()))
+    }
+
+    /// Returns the length of the message, including the header.
+    #[inline]
+    pub fn length(&self) -> u32 {
+        NetworkEndian::read_u32(length(self.buf.as_ref()))
+    }
+}
+
+impl<T: AsMut<[u8]>> IkeHeader<T> {
+    #[inline]
+    pub fn set_initiator_spi(&mut self, value: u64) {
+        NetworkEndian::write_u64(initiator_spi_mut(self.buf.as_mut()), value);
+    }
+
+    #[inline]
+    pub fn set_responder_spi(&mut self, value: u64) {
+        NetworkEndian::write_u64(responder_spi_mut(self.buf.as_mut()), value);
+    }
+
+    #[inline]
+    pub fn set_next_payload(&mut self, value: IkePayloadType) {
+        *next_payload_mut(self.buf.as_mut()) = value.into();
+    }
+
+    /// Set the version to 2.0.
+    #[inline]
+    pub fn adjust_version(&mut self) {
+        *version_mut(self.buf.as_mut()) = IKE_VERSION;
+    }
+
+    #[inline]
+    pub fn set_exchange_type(&mut self, value: IkeExchangeType) {
+        *exchange_type_mut(self.buf.as_mut()) = value.into();
+    }
+
+    #[inline]
+    pub fn set_flags(&mut self, value: u8) {
+        *flags_mut(self.buf.as_mut()) = value;
+    }
+
+    #[inline]
+    pub fn set_message_id(&mut self, value: u32) {
+        NetworkEndian::write_u32(message_id_mut(self.buf.as_mut()), value);
+    }
+
+    #[inline]
+    pub fn set_length(&mut self, value: u32) {
+        NetworkEndian::write_u32(length_mut(self.buf.as_mut()), value);
+    }
+}
+
+/// A payload yielded by [`IkePayloads`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct IkePayload<'a> {
+    pub payload_type: IkePayloadType,
+    /// Whether the receiver must reject the message if it does not know the
+    /// type of the payload.
+    pub critical: bool,
+    /// The body of the payload, without the generic header.
+    pub body: &'a [u8],
+}
+
+impl<'a> IkePayload<'a> {
+    /// Returns the body of a notify payload.
+    pub fn notify(&self) -> Option<IkeNotify<'a>> {
+        if self.payload_type != IkePayloadType::NOTIFY {
+            return None;
+        }
+        IkeNotify::parse(self.body)
+    }
+}
+
+/// Iterates over the chain of payloads of a message.
+///
+/// The iteration stops at the end of the chain, after an
+/// [`IkePayloadType::SK`] or [`IkePayloadType::SKF`] payload whose next
+/// payload field gives the type of the first encrypted payload, or at the
+/// first malformed payload, in which case [`IkePayloads::is_valid`] returns
+/// `false`.
+#[derive(Debug, Clone)]
+pub struct IkePayloads<'a> {
+    buf: &'a [u8],
+    next: IkePayloadType,
+    valid: bool,
+}
+
+impl<'a> IkePayloads<'a> {
+    /// Iterate over the payloads in `buf`, the first one of `first` type.
+    #[inline]
+    pub fn new(buf: &'a [u8], first: IkePayloadType) -> Self {
+        Self {
+            buf,
+            next: first,
+            valid: true,
+        }
+    }
+
+    /// Returns whether the chain is well formed so far, and at the end of the
+    /// iteration, whether it ends exactly at the end of the buffer.
+    #[inline]
+    pub fn is_valid(&self) -> bool {
+        self.valid
+    }
+}
+
+impl<'a> Iterator for IkePayloads<'a> {
+    type Item = IkePayload<'a>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if !self.valid {
+            return None;
+        }
+        if self.next == IkePayloadType::NONE {
+            self.valid = self.buf.is_empty();
+            return None;
+        }
+        if self.buf.len() < IKE_PAYLOAD_HEADER_LEN {
+            self.valid = false;
+            return None;
+        }
+        let len = usize::from(NetworkEndian::read_u16(&self.buf[2..4]));
+        if len < IKE_PAYLOAD_HEADER_LEN || len > self.buf.len() {
+            self.valid = false;
+            return None;
+        }
+
+        let payload = IkePayload {
+            payload_type: self.next,
+            critical: self.buf[1] & 0x80 != 0,
+            body: &self.buf[IKE_PAYLOAD_HEADER_LEN..len],
+        };
+        self.next = if matches!(self.next, IkePayloadType::SK | IkePayloadType::SKF) {
+            IkePayloadType::NONE
+        } else {
+            self.buf[0].into()
+        };
+        self.buf = &self.buf[len..];
+        Some(payload)
+    }
+}
+
+/// An IKEv2 message, the payload of a UDP datagram.
+#[derive(Debug, Clone, Copy)]
+pub struct IkeMessage<'a> {
+    buf: &'a [u8],
+}
+
+impl<'a> IkeMessage<'a> {
+    /// Parse the IKE message in `buf`, ignoring the bytes after its length.
+    ///
+    /// Returns `None` if the major version is not 2, or if the length or the
+    /// chain of payloads is invalid.
+    pub fn parse(buf: &'a [u8]) -> Option<Self> {
+        let header = IkeHeader::new(buf).ok()?;
+        let len = usize::try_from(header.length()).ok()?;
+        if header.major_version() != 2 || len < IKE_HEADER_LEN || len > buf.len() {
+            return None;
+        }
+        let msg = Self { buf: &buf[..len] };
+        let mut payloads = msg.payloads();
+        payloads.by_ref().for_each(drop);
+        payloads.is_valid().then_some(msg)
+    }
+
+    #[inline]
+    pub fn header(&self) -> IkeHeader<&'a [u8]> {
+        IkeHeader::new_unchecked(self.buf)
+    }
+
+    #[inline]
+    pub fn payloads(&self) -> IkePayloads<'a> {
+        IkePayloads::new(&self.buf[IKE_HEADER_LEN..], self.header().next_payload())
+    }
+
+    /// Returns the first payload of `payload_type`.
+    pub fn payload(&self, payload_type: IkePayloadType) -> Option<IkePayload<'a>> {
+        self.payloads()
+            .find(|payload| payload.payload_type == payload_type)
+    }
+
+    /// Returns the notify payloads of the message.
+    pub fn notifies(&self) -> impl Iterator<Item = IkeNotify<'a>> {
+        self.payloads().filter_map(|payload| payload.notify())
+    }
+
+    /// Returns whether the payloads after the header are encrypted.
+    pub fn is_encrypted(&self) -> bool {
+        matches!(
+            self.header().next_payload(),
+            IkePayloadType::SK | IkePayloadType::SKF
+        )
+    }
+}
+
+/// The body of a notify payload.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct IkeNotify<'a> {
+    /// The protocol of the SA, 1 for IKE, 2 for AH and 3 for ESP, or 0.
+    pub protocol_id: u8,
+    pub notify_type: IkeNotifyType,
+    pub spi: &'a [u8],
+    pub data: &'a [u8],
+}
+
+impl<'a> IkeNotify<'a> {
+    /// Parse the body of a notify payload.
+    pub fn parse(body: &'a [u8]) -> Option<Self> {
+        if body.len() < 4 {
+            return None;
+        }
+        let spi_end = 4 + usize::from(body[1]);
+        Some(Self {
+            protocol_id: body[0],
+            notify_type: NetworkEndian::read_u16(&body[2..4]).into(),
+            spi: body.get(4..spi_end)?,
+            data: &body[spi_end..],
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // An IKE_SA_INIT request with a KE payload of group 19 and 4 bytes of key
+    // data, a nonce of 4 bytes and a NAT_DETECTION_SOURCE_IP notification with
+    // 4 bytes of hash.
+    fn sa_init() -> Vec<u8> {
+        let mut bytes = vec![
+            0x01, 0x02, 0x03, 0x04, 0x05, 0x06, 0x07, 0x08, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+            0x00, 0x00, 0x22, 0x20, 0x22, 0x08, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+        ];
+        // KE: the group and a reserved field, then the key data.
+        bytes.extend([
+            0x28, 0x00, 0x00, 0x0c, 0x00, 0x13, 0x00, 0x00, 0xaa, 0xbb, 0xcc, 0xdd,
+        ]);
+        // Nonce.
+        bytes.extend([0x29, 0x00, 0x00, 0x08, 0x11, 0x22, 0x33, 0x44]);
+        // Notify, without an SPI.
+        bytes.extend([
+            0x00, 0x00, 0x00, 0x0c, 0x00, 0x00, 0x40, 0x04, 0xde, 0xad, 0xbe, 0xef,
+        ]);
+        let len = bytes.len() as u32;
+        IkeHeader::new(&mut bytes[..]).unwrap().set_length(len);
+        bytes
+    }
+
+    #[test]
+    fn ike_header() {
+        let bytes = sa_init();
+        let header = IkeHeader::new(&bytes[..]).unwrap();
+        assert_eq!(header.initiator_spi(), 0x0102030405060708);
+        assert_eq!(header.responder_spi(), 0);
+        assert_eq!(header.next_payload(), IkePayloadType::KE);
+        assert_eq!((header.major_version(), header.minor_version()), (2, 0));
+        assert_eq!(header.exchange_type(), IkeExchangeType::IKE_SA_INIT);
+        assert!(header.is_initiator() && !header.is_response());
+        assert_eq!(header.message_id(), 0);
+        assert_eq!(header.length(), 60);
+
+        let mut built = header.to_owned();
+        built.set_responder_spi(0x1112131415161718);
+        built.set_flags(IKE_FLAG_RESPONSE);
+        built.set_message_id(1);
+        built.set_exchange_type(IkeExchangeType::IKE_AUTH);
+        built.set_next_payload(IkePayloadType::SK);
+        assert_eq!(built.responder_spi(), 0x1112131415161718);
+        assert!(built.is_response() && !built.is_initiator());
+        assert_eq!(built.flags() & IKE_FLAG_VERSION, 0);
+        assert_eq!(built.message_id(), 1);
+        assert_eq!(built.as_bytes()[16..20], [0x2e, 0x20, 0x23, 0x20]);
+    }
+
+    #[test]
+    fn payload_chain() {
+        let bytes = sa_init();
+        let msg = IkeMessage::parse(&bytes).unwrap();
+        assert!(!msg.is_encrypted());
+        let types: Vec<_> = msg.payloads().map(|p| p.payload_type).collect();
+        assert_eq!(
+            types,
+            [
+                IkePayloadType::KE,
+                IkePayloadType::NONCE,
+                IkePayloadType::NOTIFY
+            ]
+        );
+        assert_eq!(
+            msg.payload(IkePayloadType::NONCE).unwrap().body,
+            &[0x11, 0x22, 0x33, 0x44]
+        );
+
+        let notify = msg.notifies().next().unwrap();
+        assert_eq!(notify.notify_type, IkeNotifyType::NAT_DETECTION_SOURCE_IP);
+        assert!(!notify.notify_type.is_error());
+        assert_eq!(
+            (notify.spi, notify.data),
+            (&[][..], &[0xde, 0xad, 0xbe, 0xef][..])
+        );
+
+        // A payload length past the message, a chain that ends early, and
+        // another major version.
+        let mut bad = bytes.clone();
+        bad[28 + 3] = 0x40;
+        assert!(IkeMessage::parse(&bad).is_none());
+        let mut bad = bytes.clone();
+        bad[28 + 12] = 0x00;
+        assert!(IkeMessage::parse(&bad).is_none());
+        let mut bad = bytes;
+        bad[17] = 0x10;
+        assert!(IkeMessage::parse(&bad).is_none());
+    }
+
+    #[test]
+    fn encrypted_payload() {
+        // An IKE_AUTH response with an SK payload of 8 bytes, whose first
+        // inner payload is IDr.
+        let mut bytes = [0; IKE_HEADER_LEN + 12];
+        let mut header = IkeHeader::new(&mut bytes[..]).unwrap();
+        header.adjust_version();
+        header.set_next_payload(IkePayloadType::SK);
+        header.set_exchange_type(IkeExchangeType::IKE_AUTH);
+        header.set_flags(IKE_FLAG_RESPONSE);
+        header.set_message_id(1);
+        header.set_length(40);
+        bytes[IKE_HEADER_LEN..IKE_HEADER_LEN + 4].copy_from_slice(&[0x24, 0x80, 0x00, 0x0c]);
+
+        let msg = IkeMessage::parse(&bytes).unwrap();
+        assert!(msg.is_encrypted());
+        let mut payloads = msg.payloads();
+        let sk = payloads.next().unwrap();
+        assert_eq!(sk.payload_type, IkePayloadType::SK);
+        assert!(sk.critical);
+        assert_eq!(sk.body.len(), 8);
+        assert!(payloads.next().is_none());
+        assert!(payloads.is_valid());
+    }
+}
@@ -0,0 +1,359 @@
+//! IP-in-IP encapsulation: IPv4 in IPv4 (RFC 2003), IPv6 in IPv4, i.e. 6in4
+//! (RFC 4213), and IPv4 or IPv6 in IPv6 (RFC 2473).
+//!
+//! The outer header is identified by the protocol number 4 for an IPv4
+//! payload, and 41 for an IPv6 payload. [`encap_ipv4`] and [`encap_ipv6`]
+//! prepend the outer header to an IP packet, and set the outer TTL or hop
+//! limit and the DSCP according to an [`EncapConf`]. The ECN field is always
+//! copied from the inner header, as in the normal mode of RFC 6040.
+//! [`InnerIp::parse`] parses the inner packet back from the payload.
+//!
+//! 6in4 is also the transport of ISATAP (RFC 5214), whose addresses embed
+//! the IPv4 address of the interface, see [`isatap_addr`] and
+//! [`isatap_ipv4`].
+//!
+//! # Examples
+//! ```
+//! use rpkt::ipip::{encap_ipv4, EncapConf, InnerIp};
+//! use rpkt::ipv4::{IpProtocol, Ipv4Addr, Ipv4Packet, IPV4_HEADER_LEN, IPV4_HEADER_TEMPLATE};
+//! use rpkt::{Buf, CursorMut};
+//!
+//! let mut packet = [0; 2 * IPV4_HEADER_LEN];
+//! let mut buf = CursorMut::new(&mut packet[..]);
+//! buf.advance(2 * IPV4_HEADER_LEN);
+//! let inner = Ipv4Packet::prepend_header(buf, &IPV4_HEADER_TEMPLATE);
+//!
+//! let src = Ipv4Addr::new(192, 0, 2, 1);
+//! let dst = Ipv4Addr::new(198, 51, 100, 1);
+//! let outer = encap_ipv4(inner.release(), src, dst, &EncapConf::default());
+//! assert_eq!(outer.protocol(), IpProtocol::IPIP);
+//!
+//! match InnerIp::parse(outer.payload(), IpProtocol::IPIP) {
+//!     Ok(InnerIp::Ipv4(inner)) => assert_eq!(inner.packet_len(), 20),
+//!     _ => panic!("no inner ipv4 packet"),
+//! }
+//! ```
+
+use crate::ipv4::{
+    Ecn, IpProtocol, Ipv4Addr, Ipv4Header, Ipv4Packet, IPV4_HEADER_LEN, IPV4_HEADER_TEMPLATE,
+};
+use crate::ipv6::{Ipv6Addr, Ipv6Header, Ipv6Packet, IPV6_HEADER_LEN};
+use crate::{Buf, PktMut};
+
+/// How the outer TTL or hop limit is set.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TtlPolicy {
+    /// Copy the TTL or hop limit of the inner header, so that the tunnel is
+    /// counted as hops of the inner path.
+    Copy,
+    /// Use a fixed value, so that the tunnel looks like a single hop.
+    Fixed(u8),
+}
+
+/// How the outer DSCP is set.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DscpPolicy {
+    /// Copy the DSCP of the inner header, the uniform model of RFC 2983.
+    Copy,
+    /// Use a fixed value, the pipe model of RFC 2983.
+    Fixed(u8),
+}
+
+/// The policies of the outer header fields.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct EncapConf {
+    pub ttl: TtlPolicy,
+    pub dscp: DscpPolicy,
+}
+
+impl Default for EncapConf {
+    fn default() -> Self {
+        Self {
+            ttl: TtlPolicy::Fixed(64),
+            dscp: DscpPolicy::Copy,
+        }
+    }
+}
+
+/// The inner packet of an IP-in-IP tunnel.
+#[derive(Debug)]
+pub enum InnerIp<T> {
+    Ipv4(Ipv4Packet<T>),
+    Ipv6(Ipv6Packet<T>),
+}
+
+impl<T: Buf> InnerIp<T> {
+    /// Parse the payload of an outer header with the protocol or next header
+    /// field `protocol`.
+    ///
+    /// Returns the payload back if `protocol` is neither 4 nor 41, or if the
+    /// inner packet fails to parse.
+    pub fn parse(buf: T, protocol: IpProtocol) -> Result<Self, T> {
+        match protocol {
+            IpProtocol::IPIP => Ipv4Packet::parse(buf).map(InnerIp::Ipv4),
+            IpProtocol::IPV6 => Ipv6Packet::parse(buf).map(InnerIp::Ipv6),
+            _ => Err(buf),
+        }
+    }
+}
+
+// The protocol number, the TTL or hop limit, the DSCP and the ECN of the IP
+// packet starting at `buf`.
+fn inner_fields<T: Buf>(buf: &T) -> (IpProtocol, u8, u8, Ecn) {
+    let data = buf.chunk();
+    match data.first().map(|b| b >> 4) {
+        Some(4) if data.len() >= IPV4_HEADER_LEN => {
+            let header = Ipv4Header::new_unchecked(data);
+            let ttl = header.time_to_live();
+            (IpProtocol::IPIP, ttl, header.dscp(), header.ecn_typed())
+        }
+        Some(6) if data.len() >= IPV6_HEADER_LEN => {
+            let header = Ipv6Header::new_unchecked(data);
+            let hop_limit = header.hop_limit();
+            (IpProtocol::IPV6, hop_limit, header.dscp(), header.ecn_typed())
+        }
+        _ => panic!("the inner packet is neither an ipv4 nor an ipv6 packet"),
+    }
+}
+
+fn outer_fields(conf: &EncapConf, ttl: u8, dscp: u8) -> (u8, u8) {
+    let ttl = match conf.ttl {
+        TtlPolicy::Copy => ttl,
+        TtlPolicy::Fixed(ttl) => ttl,
+    };
+    let dscp = match conf.dscp {
+        DscpPolicy::Copy => dscp,
+        DscpPolicy::Fixed(dscp) => dscp,
+    };
+    (ttl, dscp)
+}
+
+/// Encapsulate the IPv4 or IPv6 packet starting at `buf` in an IPv4 header
+/// from `src` to `dst`.
+///
+/// The outer header has no options, and its checksum is adjusted.
+///
+/// # Panics
+///
+/// This function panics if `buf` does not start with an IPv4 or IPv6
+/// header, or if it does not have enough headroom.
+pub fn encap_ipv4<T: PktMut>(
+    buf: T,
+    src: Ipv4Addr,
+    dst: Ipv4Addr,
+    conf: &EncapConf,
+) -> Ipv4Packet<T> {
+    let (protocol, ttl, dscp, ecn) = inner_fields(&buf);
+    let (ttl, dscp) = outer_fields(conf, ttl, dscp);
+
+    let mut header = IPV4_HEADER_TEMPLATE;
+    header.set_protocol(protocol);
+    header.set_time_to_live(ttl);
+    header.set_dscp(dscp);
+    header.set_ecn_typed(ecn);
+    header.set_source_ip(src);
+    header.set_dest_ip(dst);
+
+    let mut ippkt = Ipv4Packet::prepend_header(buf, &header);
+    ippkt.adjust_checksum();
+    ippkt
+}
+
+/// Encapsulate the IPv4 or IPv6 packet starting at `buf` in an IPv6 header
+/// from `src` to `dst`.
+///
+/// # Panics
+///
+/// This function panics if `buf` does not start with an IPv4 or IPv6
+/// header, or if it does not have enough headroom.
+pub fn encap_ipv6<T: PktMut>(
+    buf: T,
+    src: &Ipv6Addr,
+    dst: &Ipv6Addr,
+    conf: &EncapConf,
+) -> Ipv6Packet<T> {
+    let (protocol, hop_limit, dscp, ecn) = inner_fields(&buf);
+    let (hop_limit, dscp) = outer_fields(conf, hop_limit, dscp);
+
+    let mut header = Ipv6Header::new_unchecked([0; IPV6_HEADER_LEN]);
+    header.adjust_version();
+    header.set_next_header(protocol);
+    header.set_hop_limit(hop_limit);
+    header.set_dscp(dscp);
+    header.set_ecn_typed(ecn);
+    header.set_source_ip(src);
+    header.set_dest_ip(dst);
+
+    Ipv6Packet::prepend_header(buf, &header)
+}
+
+/// The interface identifier of an ISATAP address (RFC 5214) without the
+/// IPv4 address, for an IPv4 address that is not globally unique.
+pub const ISATAP_IID_PREFIX: [u8; 4] = [0x00, 0x00, 0x5e, 0xfe];
+
+/// The same as [`ISATAP_IID_PREFIX`], with the universal/local bit set for
+/// a globally unique IPv4 address.
+pub const ISATAP_IID_PREFIX_GLOBAL: [u8; 4] = [0x02, 0x00, 0x5e, 0xfe];
+
+/// Build the ISATAP address from the 64-bit `prefix` and the IPv4 address
+/// of the interface, the interface identifier is `::0:5efe:a.b.c.d` or
+/// `::200:5efe:a.b.c.d` if `global` is set.
+pub fn isatap_addr(prefix: &Ipv6Addr, ipv4: Ipv4Addr, global: bool) -> Ipv6Addr {
+    let mut data = [0; 16];
+    data[0..8].copy_from_slice(&prefix.0[0..8]);
+    if global {
+        data[8..12].copy_from_slice(&ISATAP_IID_PREFIX_GLOBAL);
+    } else {
+        data[8..12].copy_from_slice(&ISATAP_IID_PREFIX);
+    }
+    data[12..16].copy_from_slice(&ipv4.0);
+    Ipv6Addr(data)
+}
+
+/// Returns the embedded IPv4 address if `addr` has an ISATAP interface
+/// identifier.
+///
+/// The group bit and the reserved bits of the interface identifier are
+/// ignored, as required of the receivers.
+pub fn isatap_ipv4(addr: &Ipv6Addr) -> Option<Ipv4Addr> {
+    let iid = &addr.0[8..16];
+    if iid[0] & 0xfc == 0 && iid[1..4] == ISATAP_IID_PREFIX[1..4] {
+        Some(Ipv4Addr::from_bytes(&iid[4..8]))
+    } else {
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Cursor, CursorMut};
+
+    fn inner_ipv4(packet: &mut [u8]) -> CursorMut<'_> {
+        let len = packet.len();
+        let mut buf = CursorMut::new(packet);
+        buf.advance(len - 4);
+        let mut ippkt = Ipv4Packet::prepend_header(buf, &IPV4_HEADER_TEMPLATE);
+        ippkt.set_time_to_live(20);
+        ippkt.set_dscp(46);
+        ippkt.set_ecn_typed(Ecn::Ect0);
+        ippkt.release()
+    }
+
+    #[test]
+    fn ipv4_in_ipv4() {
+        let mut packet = [0; 2 * IPV4_HEADER_LEN + 4];
+        let buf = inner_ipv4(&mut packet[..]);
+        let conf = EncapConf {
+            ttl: TtlPolicy::Copy,
+            dscp: DscpPolicy::Fixed(8),
+        };
+        encap_ipv4(
+            buf,
+            Ipv4Addr::new(10, 0, 0, 1),
+            Ipv4Addr::new(10, 0, 0, 2),
+            &conf,
+        );
+
+        let outer = Ipv4Packet::parse(Cursor::new(&packet[..])).unwrap();
+        assert!(outer.verify_checksum());
+        assert_eq!(outer.protocol(), IpProtocol::IPIP);
+        assert_eq!(usize::from(outer.packet_len()), packet.len());
+        assert_eq!((outer.time_to_live(), outer.dscp()), (20, 8));
+        assert_eq!(outer.ecn_typed(), Ecn::Ect0);
+        assert_eq!(outer.dest_ip(), Ipv4Addr::new(10, 0, 0, 2));
+
+        match InnerIp::parse(outer.payload(), IpProtocol::IPIP).unwrap() {
+            InnerIp::Ipv4(inner) => assert_eq!(inner.dscp(), 46),
+            _ => panic!("no inner ipv4 packet"),
+        }
+    }
+
+    #[test]
+    fn ipv4_in_ipv6() {
+        let mut packet = [0; IPV6_HEADER_LEN + IPV4_HEADER_LEN + 4];
+        let buf = inner_ipv4(&mut packet[..]);
+        let src = Ipv6Addr::new(0x2001, 0xdb8, 0, 0, 0, 0, 0, 1);
+        let dst = Ipv6Addr::new(0x2001, 0xdb8, 0, 0, 0, 0, 0, 2);
+        encap_ipv6(buf, &src, &dst, &EncapConf::default());
+
+        let outer = Ipv6Packet::parse(Cursor::new(&packet[..])).unwrap();
+        assert_eq!(outer.next_header(), IpProtocol::IPIP);
+        assert_eq!(usize::from(outer.payload_len()), IPV4_HEADER_LEN + 4);
+        assert_eq!((outer.hop_limit(), outer.dscp()), (64, 46));
+        assert_eq!(outer.ecn_typed(), Ecn::Ect0);
+        assert_eq!((outer.source_ip(), outer.dest_ip()), (src, dst));
+        assert!(matches!(
+            InnerIp::parse(outer.payload(), IpProtocol::IPIP),
+            Ok(InnerIp::Ipv4(_))
+        ));
+    }
+
+    #[test]
+    fn ipv6_in_ipv4() {
+        let mut packet = [0; IPV4_HEADER_LEN + IPV6_HEADER_LEN];
+        let mut buf = CursorMut::new(&mut packet[..]);
+        buf.advance(IPV4_HEADER_LEN + IPV6_HEADER_LEN);
+        let mut header = Ipv6Header::new_unchecked([0; IPV6_HEADER_LEN]);
+        header.adjust_version();
+        header.set_hop_limit(255);
+        header.set_next_header(IpProtocol::IPV6_NO_NXT);
+        let inner = Ipv6Packet::prepend_header(buf, &header);
+
+        let conf = EncapConf {
+            ttl: TtlPolicy::Copy,
+            dscp: DscpPolicy::Copy,
+        };
+        let outer = encap_ipv4(
+            inner.release(),
+            Ipv4Addr::UNSPECIFIED,
+            Ipv4Addr::UNSPECIFIED,
+            &conf,
+        );
+        assert_eq!(outer.protocol(), IpProtocol::IPV6);
+        assert_eq!(outer.time_to_live(), 255);
+
+        match InnerIp::parse(outer.payload(), IpProtocol::IPV6).unwrap() {
+            InnerIp::Ipv6(inner) => assert_eq!(inner.next_header(), IpProtocol::IPV6_NO_NXT),
+            _ => panic!("no inner ipv6 packet"),
+        }
+
+        let payload = Cursor::new(&packet[IPV4_HEADER_LEN..]);
+        assert!(InnerIp::parse(payload, IpProtocol::IPIP).is_err());
+    }
+
+    #[test]
+    #[should_panic]
+    fn not_ip() {
+        let mut packet = [0; IPV4_HEADER_LEN * 2];
+        let mut buf = CursorMut::new(&mut packet[..]);
+        buf.advance(IPV4_HEADER_LEN);
+        encap_ipv4(
+            buf,
+            Ipv4Addr::UNSPECIFIED,
+            Ipv4Addr::UNSPECIFIED,
+            &EncapConf::default(),
+        );
+    }
+
+    #[test]
+    fn isatap() {
+        let prefix = Ipv6Addr::new(0xfe80, 0, 0, 0, 0, 0, 0, 0);
+        let ipv4 = Ipv4Addr::new(192, 0, 2, 143);
+        let addr = isatap_addr(&prefix, ipv4, false);
+        assert_eq!(
+            addr,
+            Ipv6Addr::new(0xfe80, 0, 0, 0, 0, 0x5efe, 0xc000, 0x028f)
+        );
+        assert_eq!(isatap_ipv4(&addr), Some(ipv4));
+
+        let addr = isatap_addr(&prefix, ipv4, true);
+        assert_eq!(addr.0[8], 0x02);
+        assert_eq!(isatap_ipv4(&addr), Some(ipv4));
+
+        assert_eq!(isatap_ipv4(&Ipv6Addr::LOOPBACK), None);
+        let mut addr = addr;
+        addr.0[8] = 0x04;
+        assert_eq!(isatap_ipv4(&addr), None);
+    }
+}
@@ -128,19 +128,21 @@ packet_base! {
 impl<T: Buf> IpsecAuthHdrPacket<T> {
     #[inline]
     pub fn parse(buf: T) -> Result<IpsecAuthHdrPacket<T>, T> {
-        if buf.chunk().len() < IPSEC_AUTH_HEADER_LEN {
-            return Err(buf);
-        }
-
-        let packet = IpsecAuthHdrPacket::parse_unchecked(buf);
-
-        if packet.header_len() >= IPSEC_AUTH_HEADER_LEN
-            && packet.header_len() <= packet.buf.chunk().len()
-        {
-            Ok(packet)
-        } else {
-            Err(packet.release())
-        }
+        traced_parse!("ipsec-ah", buf, |pkt: &Self| pkt.header_len(), {
+            if buf.chunk().len() < IPSEC_AUTH_HEADER_LEN {
+                return Err(buf);
+            }
+
+            let packet = IpsecAuthHdrPacket::parse_unchecked(buf);
+
+            if packet.header_len() >= IPSEC_AUTH_HEADER_LEN
+                && packet.header_len() <= packet.buf.chunk().len()
+            {
+                Ok(packet)
+            } else {
+                Err(packet.release())
+            }
+        })
     }
 
     #[inline]
@@ -0,0 +1,126 @@
+use bytes::Buf;
+
+use crate::ipv4::IpProtocol;
+
+use super::{IpsecAuthHdrPacket, IpsecEspPacket, IPSEC_ESP_HEADER_LEN};
+
+/// The UDP port of IKE.
+pub const IKE_PORT: u16 = 500;
+
+/// The UDP port of IKE and of the ESP packets encapsulated in UDP to traverse
+/// NATs (RFC 3948).
+pub const IPSEC_NAT_T_PORT: u16 = 4500;
+
+/// The zero SPI that tells IKE messages apart from ESP packets on
+/// [`IPSEC_NAT_T_PORT`].
+pub const NON_ESP_MARKER: [u8; 4] = [0; 4];
+
+/// The single byte of the keepalive datagrams of NAT traversal.
+pub const NAT_KEEPALIVE: u8 = 0xff;
+
+/// An IPsec packet in the payload of an IP packet.
+#[derive(Debug)]
+pub enum IpsecGroup<T> {
+    Esp(IpsecEspPacket<T>),
+    Ah(IpsecAuthHdrPacket<T>),
+}
+
+impl<T: Buf> IpsecGroup<T> {
+    /// Parse the payload of an IP packet of `protocol`.
+    ///
+    /// Returns `buf` back if `protocol` is neither ESP nor AH, or if the
+    /// packet is malformed.
+    #[inline]
+    pub fn parse(protocol: IpProtocol, buf: T) -> Result<IpsecGroup<T>, T> {
+        match protocol {
+            IpProtocol::ESP => IpsecEspPacket::parse(buf).map(IpsecGroup::Esp),
+            IpProtocol::AH => IpsecAuthHdrPacket::parse(buf).map(IpsecGroup::Ah),
+            _ => Err(buf),
+        }
+    }
+}
+
+/// The payload of a UDP datagram of [`IPSEC_NAT_T_PORT`].
+#[derive(Debug)]
+pub enum UdpEncap<T> {
+    Esp(IpsecEspPacket<T>),
+    /// An IKE message, after the [`NON_ESP_MARKER`].
+    Ike(T),
+    /// A NAT keepalive.
+    Keepalive(T),
+}
+
+impl<T: Buf> UdpEncap<T> {
+    /// Parse the payload of a UDP datagram of [`IPSEC_NAT_T_PORT`].
+    ///
+    /// Returns `buf` back if it is too short to be an ESP packet, or an IKE
+    /// message after the non-ESP marker.
+    #[inline]
+    pub fn parse(mut buf: T) -> Result<UdpEncap<T>, T> {
+        let chunk = buf.chunk();
+        if buf.remaining() == 1 && chunk[0] == NAT_KEEPALIVE {
+            return Ok(UdpEncap::Keepalive(buf));
+        }
+        if chunk.len() < NON_ESP_MARKER.len() {
+            return Err(buf);
+        }
+        if chunk[..NON_ESP_MARKER.len()] == NON_ESP_MARKER {
+            buf.advance(NON_ESP_MARKER.len());
+            return Ok(UdpEncap::Ike(buf));
+        }
+        if chunk.len() < IPSEC_ESP_HEADER_LEN {
+            return Err(buf);
+        }
+        IpsecEspPacket::parse(buf).map(UdpEncap::Esp)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ipsec::IPSEC_AUTH_HEADER_LEN;
+    use crate::Cursor;
+
+    #[test]
+    fn ip_dispatch() {
+        let mut bytes = [0; 24];
+        bytes[..4].copy_from_slice(&[0x11, 0x04, 0x00, 0x00]);
+        bytes[4..8].copy_from_slice(&[0x00, 0x00, 0x01, 0x00]);
+
+        match IpsecGroup::parse(IpProtocol::AH, Cursor::new(&bytes[..])).unwrap() {
+            IpsecGroup::Ah(ah) => {
+                assert_eq!(ah.spi(), 0x100);
+                assert_eq!(ah.header_len(), IPSEC_AUTH_HEADER_LEN + 12);
+                assert_eq!(ah.next_header(), IpProtocol::UDP);
+            }
+            IpsecGroup::Esp(_) => panic!("not an ah packet"),
+        }
+        match IpsecGroup::parse(IpProtocol::ESP, Cursor::new(&bytes[..])).unwrap() {
+            IpsecGroup::Esp(esp) => assert_eq!(esp.spi(), 0x11040000),
+            IpsecGroup::Ah(_) => panic!("not an esp packet"),
+        }
+        assert!(IpsecGroup::parse(IpProtocol::UDP, Cursor::new(&bytes[..])).is_err());
+    }
+
+    #[test]
+    fn udp_encap() {
+        let esp = [0x00, 0x00, 0x01, 0x00, 0x00, 0x00, 0x00, 0x07, 0xab];
+        match UdpEncap::parse(Cursor::new(&esp[..])).unwrap() {
+            UdpEncap::Esp(esp) => assert_eq!((esp.spi(), esp.seq_num()), (0x100, 7)),
+            _ => panic!("not an esp packet"),
+        }
+
+        let ike = [0x00, 0x00, 0x00, 0x00, 0x12, 0x34];
+        match UdpEncap::parse(Cursor::new(&ike[..])).unwrap() {
+            UdpEncap::Ike(buf) => assert_eq!(buf.chunk(), &[0x12, 0x34]),
+            _ => panic!("not an ike message"),
+        }
+
+        assert!(matches!(
+            UdpEncap::parse(Cursor::new(&[NAT_KEEPALIVE][..])),
+            Ok(UdpEncap::Keepalive(_))
+        ));
+        assert!(UdpEncap::parse(Cursor::new(&esp[..6])).is_err());
+        assert!(UdpEncap::parse(Cursor::new(&[0x01][..])).is_err());
+    }
+}
@@ -1,8 +1,9 @@
 use byteorder::{ByteOrder, NetworkEndian};
 use bytes::Buf;
 
-use crate::PktMut;
+use crate::ipv4::IpProtocol;
 use crate::{Cursor, CursorMut};
+use crate::{PktBuf, PktMut};
 
 header_field_range_accessors! {
     (spi, spi_mut, 0..4),
@@ -10,6 +11,20 @@ header_field_range_accessors! {
 }
 pub const IPSEC_ESP_HEADER_LEN: usize = 8;
 
+/// The length of the pad length and next header fields, which end the
+/// encrypted part of an ESP packet.
+pub const IPSEC_ESP_TRAILER_LEN: usize = 2;
+
+/// The trailer of an ESP packet that is not encrypted, i.e. with the NULL
+/// encryption of RFC 4303 or after the decryption.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct EspTrailer<'a> {
+    pub padding: &'a [u8],
+    pub next_header: IpProtocol,
+    /// The integrity check value after the trailer.
+    pub icv: &'a [u8],
+}
+
 /// RFC2460 - Sec. 4.5
 #[derive(Clone, Copy, Debug)]
 pub struct Ipv6EspHeader<T> {
@@ -88,11 +103,13 @@ packet_base! {
 impl<T: Buf> IpsecEspPacket<T> {
     #[inline]
     pub fn parse(buf: T) -> Result<IpsecEspPacket<T>, T> {
-        if buf.chunk().len() >= IPSEC_ESP_HEADER_LEN {
-            Ok(IpsecEspPacket::parse_unchecked(buf))
-        } else {
-            Err(buf)
-        }
+        traced_parse!("ipsec-esp", buf, |_: &Self| IPSEC_ESP_HEADER_LEN, {
+            if buf.chunk().len() >= IPSEC_ESP_HEADER_LEN {
+                Ok(IpsecEspPacket::parse_unchecked(buf))
+            } else {
+                Err(buf)
+            }
+        })
     }
 
     #[inline]
@@ -101,6 +118,60 @@ impl<T: Buf> IpsecEspPacket<T> {
         buf.advance(IPSEC_ESP_HEADER_LEN);
         buf
     }
+
+    /// Returns the trailer of the packet, followed by an ICV of `icv_len`
+    /// bytes, whose length depends on the negotiated integrity algorithm.
+    ///
+    /// Returns `None` if the packet is not contiguous, or too short for the
+    /// padding of the pad length field.
+    pub fn trailer(&self, icv_len: usize) -> Option<EspTrailer<'_>> {
+        let chunk = self.buf.chunk();
+        if chunk.len() != self.buf.remaining() {
+            return None;
+        }
+        let trailer_end = chunk.len().checked_sub(icv_len)?;
+        let trailer_start = trailer_end.checked_sub(IPSEC_ESP_TRAILER_LEN)?;
+        if trailer_start < IPSEC_ESP_HEADER_LEN {
+            return None;
+        }
+        let pad_len = usize::from(chunk[trailer_start]);
+        let pad_start = trailer_start.checked_sub(pad_len)?;
+        if pad_start < IPSEC_ESP_HEADER_LEN {
+            return None;
+        }
+        Some(EspTrailer {
+            padding: &chunk[pad_start..trailer_start],
+            next_header: chunk[trailer_start + 1].into(),
+            icv: &chunk[trailer_end..],
+        })
+    }
+
+    /// Returns the length of the payload data, without the padding, the
+    /// trailer and the ICV of `icv_len` bytes.
+    pub fn payload_data_len(&self, icv_len: usize) -> Option<usize> {
+        let trailer = self.trailer(icv_len)?;
+        Some(
+            self.buf.remaining()
+                - IPSEC_ESP_HEADER_LEN
+                - trailer.padding.len()
+                - IPSEC_ESP_TRAILER_LEN
+                - icv_len,
+        )
+    }
+}
+
+impl<T: PktBuf> IpsecEspPacket<T> {
+    /// Returns the payload data, without the padding, the trailer and the ICV
+    /// of `icv_len` bytes, or the packet back if its trailer is invalid.
+    pub fn payload_data(self, icv_len: usize) -> Result<T, Self> {
+        let Some(data_len) = self.payload_data_len(icv_len) else {
+            return Err(self);
+        };
+        let mut buf = self.payload();
+        let trim_size = buf.remaining() - data_len;
+        buf.trim_off(trim_size);
+        Ok(buf)
+    }
 }
 
 impl<T: PktMut> IpsecEspPacket<T> {
@@ -117,6 +188,27 @@ impl<T: PktMut> IpsecEspPacket<T> {
 
         IpsecEspPacket { buf }
     }
+
+    /// Write the trailer of a packet that ends with an ICV of `icv_len`
+    /// bytes, with `pad_len` bytes of padding in the default pattern of
+    /// RFC 4303, i.e. 1, 2, 3 and so on.
+    ///
+    /// The padding, the trailer and the ICV must be part of the packet.
+    pub fn set_trailer(&mut self, icv_len: usize, pad_len: u8, next_header: IpProtocol) {
+        let len = self.buf.remaining();
+        assert!(self.buf.chunk().len() == len);
+        assert!(
+            IPSEC_ESP_HEADER_LEN + usize::from(pad_len) + IPSEC_ESP_TRAILER_LEN + icv_len <= len
+        );
+        let trailer_start = len - icv_len - IPSEC_ESP_TRAILER_LEN;
+        let data = &mut self.buf.chunk_mut()[..trailer_start + IPSEC_ESP_TRAILER_LEN];
+        let pad_start = trailer_start - usize::from(pad_len);
+        for (i, b) in data[pad_start..trailer_start].iter_mut().enumerate() {
+            *b = i as u8 + 1;
+        }
+        data[trailer_start] = pad_len;
+        data[trailer_start + 1] = next_header.into();
+    }
 }
 
 impl<'a> IpsecEspPacket<Cursor<'a>> {
@@ -140,3 +232,43 @@ impl<'a> IpsecEspPacket<CursorMut<'a>> {
         (Ipv6EspHeader::new_unchecked(hdr), CursorMut::new(payload))
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn esp_trailer() {
+        // A 4-byte payload, 2 bytes of padding, the trailer and a 12-byte ICV.
+        let icv_len = 12;
+        let mut bytes = [0; IPSEC_ESP_HEADER_LEN + 4 + 2 + IPSEC_ESP_TRAILER_LEN + 12];
+        bytes[IPSEC_ESP_HEADER_LEN..IPSEC_ESP_HEADER_LEN + 4].copy_from_slice(b"data");
+        let len = bytes.len();
+        bytes[len - icv_len..].fill(0xaa);
+
+        let mut pkt = IpsecEspPacket::parse(CursorMut::new(&mut bytes[..])).unwrap();
+        pkt.set_spi(0x100);
+        pkt.set_seq_num(1);
+        pkt.set_trailer(icv_len, 2, IpProtocol::UDP);
+        assert_eq!(
+            &bytes[IPSEC_ESP_HEADER_LEN + 4..IPSEC_ESP_HEADER_LEN + 8],
+            &[1, 2, 2, 17]
+        );
+
+        let pkt = IpsecEspPacket::parse(Cursor::new(&bytes[..])).unwrap();
+        let trailer = pkt.trailer(icv_len).unwrap();
+        assert_eq!(trailer.padding, &[1, 2]);
+        assert_eq!(trailer.next_header, IpProtocol::UDP);
+        assert_eq!(trailer.icv, &[0xaa; 12]);
+        assert_eq!(pkt.payload_data_len(icv_len), Some(4));
+        assert_eq!(pkt.payload_data(icv_len).unwrap().chunk(), b"data");
+
+        // The padding or the ICV runs into the header.
+        let pkt = IpsecEspPacket::parse(Cursor::new(&bytes[..])).unwrap();
+        assert!(pkt.trailer(20).is_none());
+        bytes[IPSEC_ESP_HEADER_LEN + 6] = 7;
+        let pkt = IpsecEspPacket::parse(Cursor::new(&bytes[..])).unwrap();
+        assert!(pkt.trailer(icv_len).is_none());
+        assert!(pkt.payload_data(icv_len).is_err());
+    }
+}
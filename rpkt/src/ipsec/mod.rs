@@ -2,4 +2,11 @@ mod ah;
 pub use ah::{IpsecAuthHdrPacket, IpsecAuthHeader, IPSEC_AUTH_HEADER_LEN};
 
 mod esp;
-pub use esp::{IpsecEspPacket, Ipv6EspHeader, IPSEC_ESP_HEADER_LEN};
+pub use esp::{
+    EspTrailer, IpsecEspPacket, Ipv6EspHeader, IPSEC_ESP_HEADER_LEN, IPSEC_ESP_TRAILER_LEN,
+};
+
+mod dispatch;
+pub use dispatch::{
+    IpsecGroup, UdpEncap, IKE_PORT, IPSEC_NAT_T_PORT, NAT_KEEPALIVE, NON_ESP_MARKER,
+};
@@ -1,6 +1,8 @@
 use byteorder::{ByteOrder, NetworkEndian};
 
-use super::{IpProtocol, Ipv4Addr};
+use crate::checksum_utils;
+
+use super::{Ecn, IpProtocol, Ipv4Addr};
 
 header_field_val_accessors! {
     (ver_ihl, ver_ihl_mut, 0),
@@ -86,6 +88,12 @@ impl<T: AsRef<[u8]>> Ipv4Header<T> {
         data & 0x03
     }
 
+    /// Returns the ECN codepoint as an [`Ecn`].
+    #[inline]
+    pub fn ecn_typed(&self) -> Ecn {
+        Ecn::from_bits(self.ecn())
+    }
+
     #[inline]
     pub fn packet_len(&self) -> u16 {
         let data = length(self.buf.as_ref());
@@ -175,6 +183,33 @@ impl<T: AsMut<[u8]>> Ipv4Header<T> {
         *data = (*data & !0x03) | (value & 0x03)
     }
 
+    #[inline]
+    pub fn set_ecn_typed(&mut self, value: Ecn) {
+        self.set_ecn(value.into())
+    }
+
+    /// Mark congestion experienced on a packet of an ECN-capable transport,
+    /// updating the header checksum incrementally (RFC 1624).
+    ///
+    /// Returns `false` if the transport is not ECN-capable, in which case the
+    /// packet is left untouched and an AQM should drop it instead.
+    #[inline]
+    pub fn mark_ce(&mut self) -> bool {
+        match Ecn::from_bits(*dscp_ecn_mut(self.buf.as_mut())) {
+            Ecn::NotEct => false,
+            Ecn::Ce => true,
+            _ => {
+                let old = NetworkEndian::read_u16(&self.buf.as_mut()[0..2]);
+                self.set_ecn_typed(Ecn::Ce);
+                let new = NetworkEndian::read_u16(&self.buf.as_mut()[0..2]);
+                let data = checksum_mut(self.buf.as_mut());
+                let sum = checksum_utils::combine(&[!NetworkEndian::read_u16(data), !old, new]);
+                NetworkEndian::write_u16(data, !sum);
+                true
+            }
+        }
+    }
+
     #[inline]
     pub fn set_packet_len(&mut self, value: u16) {
         let data = length_mut(self.buf.as_mut());
@@ -252,3 +287,74 @@ impl<T: AsMut<[u8]>> Ipv4Header<T> {
         data.copy_from_slice(value.as_bytes())
     }
 }
+
+/// Const builders for the header template, so that the presets of the header can
+/// be evaluated at compile time and stored in `const`s or `static`s.
+///
+/// # Examples
+/// ```
+/// use rpkt::ipv4::*;
+///
+/// static TCP_TTL64: Ipv4Header<[u8; IPV4_HEADER_LEN]> = IPV4_HEADER_TEMPLATE
+///     .with_protocol(IpProtocol::TCP)
+///     .with_time_to_live(64);
+/// assert_eq!(TCP_TTL64.protocol(), IpProtocol::TCP);
+/// assert_eq!(TCP_TTL64.time_to_live(), 64);
+/// ```
+impl Ipv4Header<[u8; IPV4_HEADER_LEN]> {
+    #[inline]
+    pub const fn with_dscp(mut self, value: u8) -> Self {
+        assert!(value < 64, "invalid dscp value");
+        self.buf[1] = (self.buf[1] & !0xfc) | (value << 2);
+        self
+    }
+
+    #[inline]
+    pub const fn with_ecn(mut self, value: u8) -> Self {
+        assert!(value < 4, "invalid ecn value");
+        self.buf[1] = (self.buf[1] & !0x03) | value;
+        self
+    }
+
+    #[inline]
+    pub const fn with_dont_frag(mut self, value: bool) -> Self {
+        if value {
+            self.buf[6] |= 0x40;
+        } else {
+            self.buf[6] &= !0x40;
+        }
+        self
+    }
+
+    #[inline]
+    pub const fn with_time_to_live(mut self, value: u8) -> Self {
+        self.buf[8] = value;
+        self
+    }
+
+    #[inline]
+    pub const fn with_protocol(mut self, value: IpProtocol) -> Self {
+        self.buf[9] = value.0;
+        self
+    }
+
+    #[inline]
+    pub const fn with_source_ip(mut self, value: Ipv4Addr) -> Self {
+        let mut i = 0;
+        while i < 4 {
+            self.buf[12 + i] = value.0[i];
+            i += 1;
+        }
+        self
+    }
+
+    #[inline]
+    pub const fn with_dest_ip(mut self, value: Ipv4Addr) -> Self {
+        let mut i = 0;
+        while i < 4 {
+            self.buf[16 + i] = value.0[i];
+            i += 1;
+        }
+        self
+    }
+}
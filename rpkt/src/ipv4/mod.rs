@@ -2,6 +2,13 @@ use std::fmt;
 
 use bytes::Buf;
 
+use crate::gre::GrePacket;
+use crate::icmpv4::Icmpv4Packet;
+use crate::icmpv6::Icmpv6Packet;
+use crate::ipsec::{IpsecAuthHdrPacket, IpsecEspPacket};
+use crate::ipv6::extentions::{FragPacket, RoutingPacket};
+use crate::ipv6::Ipv6Packet;
+use crate::sctp::SctpPacket;
 use crate::tcp::TcpPacket;
 use crate::udp::UdpPacket;
 
@@ -87,16 +94,24 @@ enum_sim! {
     /// See https://www.iana.org/assignments/protocol-numbers/protocol-numbers.xhtml
     pub struct IpProtocol (u8) {
         ICMP = 1,
+        IGMP = 2,
+        /// IPv4 encapsulated in IP.
+        IPIP = 4,
         TCP = 6,
         UDP =  17,
         /// The IPv6 Hop-by-hop extention number
         HOPOPT = 0,
+        /// IPv6 encapsulated in IP.
+        IPV6 = 41,
         IPV6_ROUTE = 43,
         IPV6_FRAG = 44,
+        GRE = 47,
         ESP = 50,
         AH = 51,
+        ICMPV6 = 58,
         IPV6_NO_NXT = 59,
         IPV6_OPTS = 60,
+        SCTP = 132,
     }
 }
 
@@ -111,6 +126,106 @@ impl fmt::Display for IpProtocol {
     }
 }
 
+/// The ECN codepoint of the IPv4 TOS byte or the IPv6 traffic class (RFC 3168).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum Ecn {
+    /// The transport is not ECN-capable.
+    #[default]
+    NotEct = 0,
+    /// ECN-capable transport, ECT(1).
+    Ect1 = 1,
+    /// ECN-capable transport, ECT(0).
+    Ect0 = 2,
+    /// Congestion experienced.
+    Ce = 3,
+}
+
+impl Ecn {
+    /// Convert from the two low-order bits of `value`.
+    #[inline]
+    pub const fn from_bits(value: u8) -> Self {
+        match value & 0x03 {
+            0 => Ecn::NotEct,
+            1 => Ecn::Ect1,
+            2 => Ecn::Ect0,
+            _ => Ecn::Ce,
+        }
+    }
+
+    /// Returns whether the packet is sent by an ECN-capable transport, i.e.
+    /// the codepoint is ECT(0), ECT(1) or CE.
+    #[inline]
+    pub fn is_ect(&self) -> bool {
+        *self != Ecn::NotEct
+    }
+}
+
+impl From<Ecn> for u8 {
+    #[inline]
+    fn from(value: Ecn) -> u8 {
+        value as u8
+    }
+}
+
+/// A packet that can be carried in the payload of an IP packet, or follow an
+/// IPv6 extension header.
+///
+/// It allows the layer chaining code to set the protocol field with
+/// [`Ipv4Packet::set_protocol_for`] or the next header field with
+/// [`Ipv6Packet::set_next_header_for`](crate::ipv6::Ipv6Packet::set_next_header_for)
+/// instead of remembering the constants.
+pub trait IpPayload {
+    const IP_PROTOCOL: IpProtocol;
+}
+
+impl<T> IpPayload for Icmpv4Packet<T> {
+    const IP_PROTOCOL: IpProtocol = IpProtocol::ICMP;
+}
+
+impl<T> IpPayload for TcpPacket<T> {
+    const IP_PROTOCOL: IpProtocol = IpProtocol::TCP;
+}
+
+impl<T> IpPayload for UdpPacket<T> {
+    const IP_PROTOCOL: IpProtocol = IpProtocol::UDP;
+}
+
+impl<T> IpPayload for SctpPacket<T> {
+    const IP_PROTOCOL: IpProtocol = IpProtocol::SCTP;
+}
+
+impl<T> IpPayload for RoutingPacket<T> {
+    const IP_PROTOCOL: IpProtocol = IpProtocol::IPV6_ROUTE;
+}
+
+impl<T> IpPayload for FragPacket<T> {
+    const IP_PROTOCOL: IpProtocol = IpProtocol::IPV6_FRAG;
+}
+
+impl<T> IpPayload for Ipv4Packet<T> {
+    const IP_PROTOCOL: IpProtocol = IpProtocol::IPIP;
+}
+
+impl<T> IpPayload for Ipv6Packet<T> {
+    const IP_PROTOCOL: IpProtocol = IpProtocol::IPV6;
+}
+
+impl<T> IpPayload for GrePacket<T> {
+    const IP_PROTOCOL: IpProtocol = IpProtocol::GRE;
+}
+
+impl<T> IpPayload for IpsecEspPacket<T> {
+    const IP_PROTOCOL: IpProtocol = IpProtocol::ESP;
+}
+
+impl<T> IpPayload for IpsecAuthHdrPacket<T> {
+    const IP_PROTOCOL: IpProtocol = IpProtocol::AH;
+}
+
+impl<T> IpPayload for Icmpv6Packet<T> {
+    const IP_PROTOCOL: IpProtocol = IpProtocol::ICMPV6;
+}
+
 pub struct Ipv4PseudoHeader {
     src_ip: Ipv4Addr,
     dst_ip: Ipv4Addr,
@@ -129,33 +129,26 @@ impl<T: AsMut<[u8]>> Ipv4OptionRa<T> {
 
 pub struct Ipv4OptionWriter<'a> {
     buf: &'a mut [u8],
+    written: usize,
 }
 
 impl<'a> Ipv4OptionWriter<'a> {
     pub fn eol(&mut self) {
-        self.buf[0] = END_OF_LIST;
-
-        let (_, remaining) = std::mem::replace(&mut self.buf, &mut []).split_at_mut(1);
-        self.buf = remaining;
+        self.advance(1)[0] = END_OF_LIST;
     }
 
     pub fn nop(&mut self) {
-        self.buf[0] = NOP;
-
-        let (_, remaining) = std::mem::replace(&mut self.buf, &mut []).split_at_mut(1);
-        self.buf = remaining;
+        self.advance(1)[0] = NOP;
     }
 
     pub fn ts(&mut self, len: usize) -> Ipv4OptionTs<&'a mut [u8]> {
         assert!(len >= 4 && len <= 40 && len % 4 == 0);
 
-        self.buf[0] = TIMESTAMP;
-        self.buf[1] = len as u8;
-        self.buf[2] = 5;
-        (&mut self.buf[3..len]).fill(0);
-
-        let (buf, remaining) = std::mem::replace(&mut self.buf, &mut []).split_at_mut(len);
-        self.buf = remaining;
+        let buf = self.advance(len);
+        buf[0] = TIMESTAMP;
+        buf[1] = len as u8;
+        buf[2] = 5;
+        buf[3..].fill(0);
 
         Ipv4OptionTs { buf }
     }
@@ -163,36 +156,58 @@ impl<'a> Ipv4OptionWriter<'a> {
     pub fn rr(&mut self, len: usize) -> Ipv4OptionRr<&'a mut [u8]> {
         assert!(len >= 3 && len <= 40 && (len - 3) % 4 == 0);
 
-        self.buf[0] = RECORD_ROUTE;
-        self.buf[1] = len as u8;
-        self.buf[2] = 4;
-        (&mut self.buf[3..len]).fill(0);
-
-        let (buf, remaining) = std::mem::replace(&mut self.buf, &mut []).split_at_mut(len);
-        self.buf = remaining;
+        let buf = self.advance(len);
+        buf[0] = RECORD_ROUTE;
+        buf[1] = len as u8;
+        buf[2] = 4;
+        buf[3..].fill(0);
 
         Ipv4OptionRr { buf }
     }
 
     pub fn ra(&mut self) -> Ipv4OptionRa<&'a mut [u8]> {
-        self.buf[0] = ROUTE_ALERT;
-        self.buf[1] = 4;
-
-        let (buf, remaining) = std::mem::replace(&mut self.buf, &mut []).split_at_mut(4);
-        self.buf = remaining;
+        let buf = self.advance(4);
+        buf[0] = ROUTE_ALERT;
+        buf[1] = 4;
 
         Ipv4OptionRa { buf }
     }
 
+    /// Terminate the option list and pad it to a 4-byte boundary.
+    ///
+    /// If the written options are not 4-byte aligned, an end-of-list option is
+    /// appended, followed by zero padding. Returns the padded length of the
+    /// option list, which is the value to add to the fixed header length.
+    pub fn pad(&mut self) -> usize {
+        let pad_len = (4 - self.written % 4) % 4;
+        // The end-of-list option is 0, so it is written along with the padding.
+        self.advance(pad_len).fill(END_OF_LIST);
+        self.written
+    }
+
     #[inline]
     pub fn from_option_bytes_mut(buf: &'a mut [u8]) -> Self {
-        Self { buf }
+        Self { buf, written: 0 }
     }
 
     #[inline]
     pub fn remaining_bytes(&self) -> usize {
         self.buf.len()
     }
+
+    /// Returns the number of bytes written so far.
+    #[inline]
+    pub fn written_bytes(&self) -> usize {
+        self.written
+    }
+
+    #[inline]
+    fn advance(&mut self, len: usize) -> &'a mut [u8] {
+        let (buf, remaining) = std::mem::take(&mut self.buf).split_at_mut(len);
+        self.buf = remaining;
+        self.written += len;
+        buf
+    }
 }
 
 pub struct Ipv4OptionIter<'a> {
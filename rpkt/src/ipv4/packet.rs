@@ -4,8 +4,9 @@ use crate::checksum_utils;
 use crate::{Cursor, CursorMut};
 use crate::{PktBuf, PktMut};
 
-use super::header::{Ipv4Header, IPV4_HEADER_LEN};
-use super::{IpProtocol, Ipv4Addr};
+use super::header::{Ipv4Header, IPV4_HEADER_LEN, IPV4_HEADER_LEN_MAX};
+use super::option::Ipv4OptionWriter;
+use super::{Ecn, IpPayload, IpProtocol, Ipv4Addr};
 
 packet_base! {
     pub struct Ipv4Packet: Ipv4Header {
@@ -15,6 +16,7 @@ packet_base! {
             (header_len, u8),
             (dscp, u8),
             (ecn, u8),
+            (ecn_typed, Ecn),
             (packet_len, u16),
             (ident, u16),
             (dont_frag, bool),
@@ -30,6 +32,7 @@ packet_base! {
             (adjust_version),
             (set_dscp, value: u8),
             (set_ecn, value: u8),
+            (set_ecn_typed, value: Ecn),
             (set_ident, value: u16),
             (clear_flags),
             (set_dont_frag, value: bool),
@@ -51,20 +54,22 @@ packet_base! {
 impl<T: Buf> Ipv4Packet<T> {
     #[inline]
     pub fn parse(buf: T) -> Result<Ipv4Packet<T>, T> {
-        if buf.chunk().len() < IPV4_HEADER_LEN {
-            return Err(buf);
-        }
-
-        let packet = Ipv4Packet::parse_unchecked(buf);
-        if usize::from(packet.header_len()) >= IPV4_HEADER_LEN
-            && usize::from(packet.header_len()) <= usize::from(packet.packet_len())
-            && usize::from(packet.header_len()) <= packet.buf.chunk().len()
-            && usize::from(packet.packet_len()) <= packet.buf.remaining()
-        {
-            Ok(packet)
-        } else {
-            Err(packet.release())
-        }
+        traced_parse!("ipv4", buf, |pkt: &Self| usize::from(pkt.header_len()), {
+            if buf.chunk().len() < IPV4_HEADER_LEN {
+                return Err(buf);
+            }
+
+            let packet = Ipv4Packet::parse_unchecked(buf);
+            if usize::from(packet.header_len()) >= IPV4_HEADER_LEN
+                && usize::from(packet.header_len()) <= usize::from(packet.packet_len())
+                && usize::from(packet.header_len()) <= packet.buf.chunk().len()
+                && usize::from(packet.packet_len()) <= packet.buf.remaining()
+            {
+                Ok(packet)
+            } else {
+                Err(packet.release())
+            }
+        })
     }
 
     #[inline]
@@ -103,6 +108,13 @@ impl<T: PktBuf> Ipv4Packet<T> {
 }
 
 impl<T: PktMut> Ipv4Packet<T> {
+    /// Mark congestion experienced on a packet of an ECN-capable transport,
+    /// see [`Ipv4Header::mark_ce`].
+    #[inline]
+    pub fn mark_ce(&mut self) -> bool {
+        Ipv4Header::new_unchecked(self.buf.chunk_mut()).mark_ce()
+    }
+
     #[inline]
     pub fn option_bytes_mut(&mut self) -> &mut [u8] {
         let header_len = self.header_len();
@@ -115,6 +127,12 @@ impl<T: PktMut> Ipv4Packet<T> {
         self.set_checksum(checksum)
     }
 
+    /// Set the protocol field to the one that identifies the payload packet type `P`.
+    #[inline]
+    pub fn set_protocol_for<P: IpPayload>(&mut self) {
+        self.set_protocol(P::IP_PROTOCOL)
+    }
+
     #[inline]
     pub fn prepend_header<HT: AsRef<[u8]>>(mut buf: T, header: &Ipv4Header<HT>) -> Ipv4Packet<T> {
         let header_len: usize = header.header_len().into();
@@ -129,6 +147,42 @@ impl<T: PktMut> Ipv4Packet<T> {
         ippkt.set_packet_len_unchecked(u16::try_from(ippkt.buf().remaining()).unwrap());
         ippkt
     }
+
+    /// Prepend an IPv4 header carrying the options written by `f`.
+    ///
+    /// Only the fixed part of `header` is copied. The options are padded to a
+    /// 4-byte boundary, and the header length field is set accordingly.
+    ///
+    /// # Panics
+    ///
+    /// This function panics if the options exceed 40 bytes, or if the buffer
+    /// does not have enough headroom.
+    pub fn prepend_header_with_options<HT, F>(
+        mut buf: T,
+        header: &Ipv4Header<HT>,
+        f: F,
+    ) -> Ipv4Packet<T>
+    where
+        HT: AsRef<[u8]>,
+        F: FnOnce(&mut Ipv4OptionWriter<'_>),
+    {
+        let mut options = [0; IPV4_HEADER_LEN_MAX - IPV4_HEADER_LEN];
+        let mut writer = Ipv4OptionWriter::from_option_bytes_mut(&mut options[..]);
+        f(&mut writer);
+        let header_len = IPV4_HEADER_LEN + writer.pad();
+        assert!(header_len <= buf.chunk_headroom());
+
+        buf.move_back(header_len);
+
+        let data = &mut buf.chunk_mut()[0..header_len];
+        data[..IPV4_HEADER_LEN].copy_from_slice(header.as_bytes());
+        data[IPV4_HEADER_LEN..].copy_from_slice(&options[..header_len - IPV4_HEADER_LEN]);
+
+        let mut ippkt = Ipv4Packet::parse_unchecked(buf);
+        ippkt.set_header_len_unchecked(header_len as u8);
+        ippkt.set_packet_len_unchecked(u16::try_from(ippkt.buf().remaining()).unwrap());
+        ippkt
+    }
 }
 
 impl<'a> Ipv4Packet<Cursor<'a>> {
@@ -178,6 +232,7 @@ mod tests {
     use super::*;
     use crate::ether::*;
     use crate::ipv4::IPV4_HEADER_TEMPLATE;
+    use crate::udp::UdpPacket;
     use crate::{Cursor, CursorMut};
     use bytes::BufMut;
 
@@ -253,4 +308,103 @@ mod tests {
 
         assert_eq!(ethpkt.buf().chunk(), &FRAME_BYTES[..108]);
     }
+
+    #[test]
+    fn packet_build_with_options() {
+        let mut bytes = [0xff; 100];
+        let mut buf = CursorMut::new(&mut bytes[..]);
+        buf.advance(60);
+
+        let mut ippkt =
+            Ipv4Packet::prepend_header_with_options(buf, &IPV4_HEADER_TEMPLATE, |writer| {
+                writer.ra().set_alert_value(0);
+                writer.rr(7);
+            });
+        assert_eq!(ippkt.header_len(), 32);
+        assert_eq!(ippkt.packet_len(), 72);
+        assert_eq!(ippkt.buf().chunk_headroom(), 28);
+        ippkt.adjust_checksum();
+
+        let ippkt = Ipv4Packet::parse(Cursor::new(ippkt.buf().chunk())).unwrap();
+        assert_eq!(
+            ippkt.option_bytes(),
+            &[0x94, 4, 0, 0, 7, 7, 4, 0, 0, 0, 0, 0][..]
+        );
+        assert!(ippkt.verify_checksum());
+        assert_eq!(ippkt.payload().chunk(), &[0xff; 40][..]);
+    }
+
+    #[cfg(all(feature = "debug-bounds", debug_assertions))]
+    #[test]
+    #[should_panic(expected = "packet `Ipv4Packet` requires 20 bytes")]
+    fn parse_unchecked_short_buffer() {
+        let bytes = [0x45; 10];
+        let _ = Ipv4Packet::parse_unchecked(Cursor::new(&bytes[..]));
+    }
+
+    #[cfg(all(feature = "debug-bounds", debug_assertions))]
+    #[test]
+    #[should_panic(expected = "field `dest_ip` requires 20 bytes")]
+    fn header_accessor_short_buffer() {
+        let bytes = [0x45; 18];
+        let _ = Ipv4Header::new_unchecked(&bytes[..]).dest_ip();
+    }
+
+    #[test]
+    fn parse_batch() {
+        let frame = &FRAME_BYTES[ETHER_HEADER_LEN..];
+        let bufs = [
+            Cursor::new(frame),
+            Cursor::new(&frame[..10]),
+            Cursor::new(&frame[..40]),
+            Cursor::new(frame),
+        ];
+
+        let mut out: [Option<Ipv4Packet<Cursor>>; 5] = Default::default();
+        out[4] = Some(Ipv4Packet::parse(Cursor::new(frame)).unwrap());
+        assert_eq!(Ipv4Packet::parse_batch(bufs, &mut out), 2);
+        let parsed: Vec<bool> = out.iter().map(Option::is_some).collect();
+        assert_eq!(parsed, [true, false, false, true, false]);
+        assert_eq!(out[3].as_ref().unwrap().ident(), 0x5c65);
+
+        // The buffers past the capacity of the output are not taken.
+        let mut bufs = (0..3).map(|_| Cursor::new(frame));
+        let mut out: [Option<Ipv4Packet<Cursor>>; 2] = Default::default();
+        assert_eq!(Ipv4Packet::parse_batch(&mut bufs, &mut out), 2);
+        assert_eq!(bufs.count(), 1);
+    }
+
+    #[test]
+    fn payload_protocol_setters() {
+        let mut bytes = [0xff; 100];
+        let mut buf = CursorMut::new(&mut bytes[..]);
+        buf.advance(ETHER_HEADER_LEN + IPV4_HEADER_LEN);
+
+        let mut ippkt = Ipv4Packet::prepend_header(buf, &IPV4_HEADER_TEMPLATE);
+        ippkt.set_protocol_for::<UdpPacket<CursorMut>>();
+        assert_eq!(ippkt.protocol(), IpProtocol::UDP);
+
+        let mut ethpkt = EtherPacket::prepend_header(ippkt.release(), &ETHER_HEADER_TEMPLATE);
+        ethpkt.set_ethertype_for::<Ipv4Packet<CursorMut>>();
+        assert_eq!(ethpkt.ethertype(), EtherType::IPV4);
+    }
+
+    #[test]
+    fn packet_mark_ce() {
+        let mut bytes = FRAME_BYTES;
+        let mut buf = CursorMut::new(&mut bytes[ETHER_HEADER_LEN..]);
+        let mut ippkt = Ipv4Packet::parse(&mut buf).unwrap();
+        ippkt.set_dscp(46);
+        assert!(!ippkt.mark_ce());
+        assert_eq!(ippkt.ecn_typed(), Ecn::NotEct);
+
+        for ecn in [Ecn::Ect0, Ecn::Ect1, Ecn::Ce] {
+            ippkt.set_ecn_typed(ecn);
+            ippkt.adjust_checksum();
+            assert!(ippkt.mark_ce());
+            assert_eq!(ippkt.ecn_typed(), Ecn::Ce);
+            assert_eq!(ippkt.dscp(), 46);
+            assert!(ippkt.verify_checksum());
+        }
+    }
 }
@@ -140,11 +140,13 @@ packet_base! {
 impl<T: Buf> FragPacket<T> {
     #[inline]
     pub fn parse(buf: T) -> Result<FragPacket<T>, T> {
-        if buf.chunk().len() >= FRAG_HEADER_LEN {
-            Ok(FragPacket::parse_unchecked(buf))
-        } else {
-            Err(buf)
-        }
+        traced_parse!("ipv6-frag", buf, |_: &Self| FRAG_HEADER_LEN, {
+            if buf.chunk().len() >= FRAG_HEADER_LEN {
+                Ok(FragPacket::parse_unchecked(buf))
+            } else {
+                Err(buf)
+            }
+        })
     }
 
     #[inline]
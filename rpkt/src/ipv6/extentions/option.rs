@@ -274,16 +274,18 @@ impl<T: Buf> Ipv6OptionPacket<T> {
 
     #[inline]
     pub fn parse(buf: T) -> Result<Ipv6OptionPacket<T>, T> {
-        if buf.chunk().len() >= 2 {
-            let header_len = usize::from(buf.chunk()[1]) * 8 + 8;
-            if buf.chunk().len() >= header_len {
-                Ok(Self { buf })
+        traced_parse!("ipv6-option", buf, |pkt: &Self| pkt.header_len(), {
+            if buf.chunk().len() >= 2 {
+                let header_len = usize::from(buf.chunk()[1]) * 8 + 8;
+                if buf.chunk().len() >= header_len {
+                    Ok(Self { buf })
+                } else {
+                    Err(buf)
+                }
             } else {
                 Err(buf)
             }
-        } else {
-            Err(buf)
-        }
+        })
     }
 
     #[inline]
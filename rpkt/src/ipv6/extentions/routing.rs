@@ -241,16 +241,18 @@ impl<T: Buf> RoutingPacket<T> {
 
     #[inline]
     pub fn parse(buf: T) -> Result<RoutingPacket<T>, T> {
-        if buf.chunk().len() >= 2 {
-            let pkt = RoutingPacket::parse_unchecked(buf);
-            if pkt.buf.chunk().len() >= pkt.header_len() {
-                Ok(pkt)
+        traced_parse!("ipv6-routing", buf, |pkt: &Self| pkt.header_len(), {
+            if buf.chunk().len() >= 2 {
+                let pkt = RoutingPacket::parse_unchecked(buf);
+                if pkt.buf.chunk().len() >= pkt.header_len() {
+                    Ok(pkt)
+                } else {
+                    Err(pkt.release())
+                }
             } else {
-                Err(pkt.release())
+                Err(buf)
             }
-        } else {
-            Err(buf)
-        }
+        })
     }
 
     #[inline]
@@ -1,6 +1,6 @@
 use byteorder::{ByteOrder, NetworkEndian};
 
-use crate::ipv4::IpProtocol;
+use crate::ipv4::{Ecn, IpProtocol};
 
 use super::Ipv6Addr;
 
@@ -18,6 +18,13 @@ header_field_range_accessors! {
 
 pub const IPV6_HEADER_LEN: usize = 40;
 
+// The traffic class spans the lower 4 bits of byte 0 and the upper 4 bits of
+// byte 1.
+#[inline]
+fn traffic_class(data: &[u8]) -> u8 {
+    (data[0] << 4) | (data[1] >> 4)
+}
+
 #[derive(Clone, Copy, Debug)]
 pub struct Ipv6Header<T> {
     buf: T,
@@ -57,7 +64,25 @@ impl<T: AsRef<[u8]>> Ipv6Header<T> {
 
     #[inline]
     pub fn traffic_class(&self) -> u8 {
-        (self.buf.as_ref()[0] << 4) | (self.buf.as_ref()[1] >> 4)
+        traffic_class(self.buf.as_ref())
+    }
+
+    /// Returns the DSCP, the upper 6 bits of the traffic class.
+    #[inline]
+    pub fn dscp(&self) -> u8 {
+        self.traffic_class() >> 2
+    }
+
+    /// Returns the ECN codepoint, the lower 2 bits of the traffic class.
+    #[inline]
+    pub fn ecn(&self) -> u8 {
+        self.traffic_class() & 0x03
+    }
+
+    /// Returns the ECN codepoint as an [`Ecn`].
+    #[inline]
+    pub fn ecn_typed(&self) -> Ecn {
+        Ecn::from_bits(self.traffic_class())
     }
 
     #[inline]
@@ -109,6 +134,39 @@ impl<T: AsMut<[u8]>> Ipv6Header<T> {
         self.buf.as_mut()[1] = (self.buf.as_mut()[1] & 0x0f) | (value << 4);
     }
 
+    #[inline]
+    pub fn set_dscp(&mut self, value: u8) {
+        assert!(value < 64, "invalid dscp value: {}", value);
+        let tc = traffic_class(self.buf.as_mut());
+        self.set_traffic_class((tc & 0x03) | (value << 2));
+    }
+
+    #[inline]
+    pub fn set_ecn(&mut self, value: u8) {
+        assert!(value < 4, "invalid ecn value: {}", value);
+        let tc = traffic_class(self.buf.as_mut());
+        self.set_traffic_class((tc & !0x03) | value);
+    }
+
+    #[inline]
+    pub fn set_ecn_typed(&mut self, value: Ecn) {
+        self.set_ecn(value.into())
+    }
+
+    /// Mark congestion experienced on a packet of an ECN-capable transport.
+    ///
+    /// Returns `false` if the transport is not ECN-capable, in which case the
+    /// packet is left untouched and an AQM should drop it instead.
+    #[inline]
+    pub fn mark_ce(&mut self) -> bool {
+        if Ecn::from_bits(traffic_class(self.buf.as_mut())).is_ect() {
+            self.set_ecn_typed(Ecn::Ce);
+            true
+        } else {
+            false
+        }
+    }
+
     #[inline]
     pub fn set_flow_label(&mut self, value: u32) {
         assert!(value <= 0xfffff);
@@ -147,7 +147,7 @@ impl From<::std::net::Ipv6Addr> for Ipv6Addr {
 
 impl From<Ipv6Addr> for ::std::net::Ipv6Addr {
     fn from(x: Ipv6Addr) -> ::std::net::Ipv6Addr {
-        x.into()
+        x.0.into()
     }
 }
 
@@ -1,6 +1,6 @@
 use bytes::Buf;
 
-use crate::ipv4::IpProtocol;
+use crate::ipv4::{Ecn, IpPayload, IpProtocol};
 use crate::{Cursor, CursorMut};
 use crate::{PktBuf, PktMut};
 
@@ -13,6 +13,9 @@ packet_base! {
         get_methods: [
             (check_version, bool),
             (traffic_class, u8),
+            (dscp, u8),
+            (ecn, u8),
+            (ecn_typed, Ecn),
             (flow_label, u32),
             (payload_len, u16),
             (next_header, IpProtocol),
@@ -23,6 +26,9 @@ packet_base! {
         set_methods: [
             (adjust_version),
             (set_traffic_class, value: u8),
+            (set_dscp, value: u8),
+            (set_ecn, value: u8),
+            (set_ecn_typed, value: Ecn),
             (set_flow_label, value: u32),
             (set_next_header, value: IpProtocol),
             (set_hop_limit, value: u8),
@@ -38,16 +44,18 @@ packet_base! {
 impl<T: Buf> Ipv6Packet<T> {
     #[inline]
     pub fn parse(buf: T) -> Result<Ipv6Packet<T>, T> {
-        if buf.chunk().len() < IPV6_HEADER_LEN {
-            return Err(buf);
-        }
-
-        let packet = Ipv6Packet::parse_unchecked(buf);
-        if packet.buf.remaining() >= IPV6_HEADER_LEN + usize::from(packet.payload_len()) {
-            Ok(packet)
-        } else {
-            Err(packet.release())
-        }
+        traced_parse!("ipv6", buf, |_: &Self| IPV6_HEADER_LEN, {
+            if buf.chunk().len() < IPV6_HEADER_LEN {
+                return Err(buf);
+            }
+
+            let packet = Ipv6Packet::parse_unchecked(buf);
+            if packet.buf.remaining() >= IPV6_HEADER_LEN + usize::from(packet.payload_len()) {
+                Ok(packet)
+            } else {
+                Err(packet.release())
+            }
+        })
     }
 }
 
@@ -69,6 +77,13 @@ impl<T: PktBuf> Ipv6Packet<T> {
 }
 
 impl<T: PktMut> Ipv6Packet<T> {
+    /// Mark congestion experienced on a packet of an ECN-capable transport,
+    /// see [`Ipv6Header::mark_ce`].
+    #[inline]
+    pub fn mark_ce(&mut self) -> bool {
+        Ipv6Header::new_unchecked(self.buf.chunk_mut()).mark_ce()
+    }
+
     #[inline]
     pub fn prepend_header<HT: AsRef<[u8]>>(mut buf: T, header: &Ipv6Header<HT>) -> Ipv6Packet<T> {
         let payload_len = buf.remaining();
@@ -81,6 +96,13 @@ impl<T: PktMut> Ipv6Packet<T> {
         ippkt.set_payload_len_unchecked(u16::try_from(payload_len).unwrap());
         ippkt
     }
+
+    /// Set the next header field to the one that identifies the payload packet
+    /// type `P`.
+    #[inline]
+    pub fn set_next_header_for<P: IpPayload>(&mut self) {
+        self.set_next_header(P::IP_PROTOCOL)
+    }
 }
 
 impl<'a> Ipv6Packet<Cursor<'a>> {
@@ -113,3 +135,62 @@ impl<'a> Ipv6Packet<CursorMut<'a>> {
         (Ipv6Header::new_unchecked(hdr), CursorMut::new(payload))
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ether::*;
+    use crate::udp::*;
+
+    fn prepend_ipv6(buf: CursorMut<'_>) -> Ipv6Packet<CursorMut<'_>> {
+        let mut header = Ipv6Header::new_unchecked([0; IPV6_HEADER_LEN]);
+        header.adjust_version();
+        header.set_hop_limit(64);
+        header.set_next_header(IpProtocol::IPV6_NO_NXT);
+        Ipv6Packet::prepend_header(buf, &header)
+    }
+
+    #[test]
+    fn packet_mark_ce() {
+        let mut bytes = [0; IPV6_HEADER_LEN];
+        let mut buf = CursorMut::new(&mut bytes[..]);
+        buf.advance(IPV6_HEADER_LEN);
+        let mut ippkt = prepend_ipv6(buf);
+        ippkt.set_dscp(46);
+        assert!(!ippkt.mark_ce());
+        assert_eq!(ippkt.ecn_typed(), Ecn::NotEct);
+
+        for ecn in [Ecn::Ect0, Ecn::Ect1, Ecn::Ce] {
+            ippkt.set_ecn_typed(ecn);
+            assert!(ippkt.mark_ce());
+            assert_eq!(ippkt.ecn_typed(), Ecn::Ce);
+            assert_eq!(ippkt.ecn(), 3);
+            assert_eq!(ippkt.dscp(), 46);
+        }
+    }
+
+    #[test]
+    fn payload_protocol_setters() {
+        let mut bytes = [0xff; ETHER_HEADER_LEN + IPV6_HEADER_LEN + UDP_HEADER_LEN + 4];
+        let mut buf = CursorMut::new(&mut bytes[..]);
+        buf.advance(ETHER_HEADER_LEN + IPV6_HEADER_LEN + UDP_HEADER_LEN);
+
+        let udppkt = UdpPacket::prepend_header(buf, &UDP_HEADER_TEMPLATE);
+        let mut ippkt = prepend_ipv6(udppkt.release());
+        ippkt.set_next_header_for::<UdpPacket<CursorMut>>();
+        assert_eq!(ippkt.next_header(), IpProtocol::UDP);
+
+        let mut ethpkt = EtherPacket::prepend_header(ippkt.release(), &ETHER_HEADER_TEMPLATE);
+        ethpkt.set_ethertype_for::<Ipv6Packet<CursorMut>>();
+        assert_eq!(ethpkt.ethertype(), EtherType::IPV6);
+
+        // The chain parses back through the fields set above.
+        let ethpkt = EtherPacket::parse(Cursor::new(&bytes[..])).unwrap();
+        assert_eq!(ethpkt.ethertype(), EtherType::IPV6);
+        let ippkt = Ipv6Packet::parse(ethpkt.payload()).unwrap();
+        assert_eq!(ippkt.next_header(), IpProtocol::UDP);
+        assert_eq!(usize::from(ippkt.payload_len()), UDP_HEADER_LEN + 4);
+        let udppkt = UdpPacket::parse(ippkt.payload()).unwrap();
+        assert_eq!(usize::from(udppkt.packet_len()), UDP_HEADER_LEN + 4);
+    }
+}
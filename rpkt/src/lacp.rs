@@ -0,0 +1,427 @@
+//! The Slow Protocols of IEEE 802.3 and the Link Aggregation Control Protocol
+//! of IEEE 802.1AX.
+//!
+//! The Slow Protocols share the ethertype 0x8809 and the destination
+//! [`SLOW_PROTOCOLS_MULTICAST`], and are told apart by the subtype in the first
+//! byte of the payload. [`SlowProtoGroup`] dispatches on the subtype.
+//!
+//! A LACPDU has a fixed size of 110 bytes. It carries the actor, the partner
+//! and the collector information TLVs, followed by the terminator TLV and
+//! reserved bytes. The actor and the partner are read and written as a whole
+//! with [`LacpInfo`].
+//!
+//! # Examples
+//! ```
+//! use rpkt::ether::MacAddr;
+//! use rpkt::lacp::*;
+//! use rpkt::{Buf, Cursor, CursorMut};
+//!
+//! let mut bytes = [0; LACPDU_LEN];
+//! let mut buf = CursorMut::new(&mut bytes[..]);
+//! buf.advance(LACPDU_LEN);
+//!
+//! let mut pkt = LacpPacket::prepend_header(buf, &LACPDU_TEMPLATE);
+//! pkt.set_actor(LacpInfo {
+//!     system_priority: 0x8000,
+//!     system: MacAddr([0x02, 0, 0, 0, 0, 0x01]),
+//!     key: 1,
+//!     port_priority: 0x8000,
+//!     port: 3,
+//!     state: LacpState(LacpState::ACTIVITY | LacpState::AGGREGATION),
+//! });
+//!
+//! match SlowProtoGroup::parse(Cursor::new(&bytes[..])).unwrap() {
+//!     SlowProtoGroup::Lacp(pkt) => assert_eq!(pkt.actor().port, 3),
+//!     _ => unreachable!(),
+//! }
+//! ```
+
+use byteorder::{ByteOrder, NetworkEndian};
+use bytes::Buf;
+
+use crate::ether::{EtherPayload, EtherType, MacAddr};
+use crate::PktMut;
+
+/// The destination address of the Slow Protocols frames.
+pub const SLOW_PROTOCOLS_MULTICAST: MacAddr = MacAddr([0x01, 0x80, 0xc2, 0x00, 0x00, 0x02]);
+
+/// The length of a LACPDU, from the subtype to the end of the reserved bytes.
+pub const LACPDU_LEN: usize = 110;
+
+/// A LACPDU of version 1 with zeroed information.
+pub const LACPDU_TEMPLATE: LacpHeader<[u8; LACPDU_LEN]> = LacpHeader {
+    buf: lacpdu_template(),
+};
+
+// The TLV types and lengths of a LACPDU.
+const ACTOR_TLV: [u8; 2] = [0x01, 0x14];
+const PARTNER_TLV: [u8; 2] = [0x02, 0x14];
+const COLLECTOR_TLV: [u8; 2] = [0x03, 0x10];
+
+// The offsets of the actor and the partner information, behind their TLV
+// headers.
+const ACTOR_OFFSET: usize = 4;
+const PARTNER_OFFSET: usize = 24;
+
+const fn lacpdu_template() -> [u8; LACPDU_LEN] {
+    let mut buf = [0; LACPDU_LEN];
+    buf[0] = 1;
+    buf[1] = 1;
+    buf[2] = ACTOR_TLV[0];
+    buf[3] = ACTOR_TLV[1];
+    buf[22] = PARTNER_TLV[0];
+    buf[23] = PARTNER_TLV[1];
+    buf[42] = COLLECTOR_TLV[0];
+    buf[43] = COLLECTOR_TLV[1];
+    buf
+}
+
+enum_sim! {
+    /// The subtype of a Slow Protocols frame.
+    pub struct SlowProtoSubtype (u8) {
+        LACP = 1,
+        MARKER = 2,
+        /// Ethernet OAM of IEEE 802.3 clause 57.
+        OAM = 3,
+        /// The Organization Specific Slow Protocol, e.g. ESMC of ITU-T G.8264.
+        OSSP = 10,
+    }
+}
+
+header_field_range_accessors! {
+    (collector_max_delay, collector_max_delay_mut, 44..46),
+}
+
+header_field_val_accessors! {
+    (version, version_mut, 1),
+}
+
+/// The state bits of an actor or a partner.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub struct LacpState(pub u8);
+
+impl LacpState {
+    /// The port takes the initiative to send LACPDUs.
+    pub const ACTIVITY: u8 = 0x01;
+    /// The port asks for the fast rate of 1 LACPDU per second.
+    pub const TIMEOUT: u8 = 0x02;
+    /// The link can be aggregated with others.
+    pub const AGGREGATION: u8 = 0x04;
+    /// The link is attached to the right aggregator.
+    pub const SYNCHRONIZATION: u8 = 0x08;
+    pub const COLLECTING: u8 = 0x10;
+    pub const DISTRIBUTING: u8 = 0x20;
+    /// The partner information is the administrative default.
+    pub const DEFAULTED: u8 = 0x40;
+    /// The receive machine is in the expired state.
+    pub const EXPIRED: u8 = 0x80;
+
+    /// Returns whether all the bits of `bits` are set.
+    #[inline]
+    pub fn contains(&self, bits: u8) -> bool {
+        self.0 & bits == bits
+    }
+
+    #[inline]
+    pub fn insert(&mut self, bits: u8) {
+        self.0 |= bits;
+    }
+
+    #[inline]
+    pub fn remove(&mut self, bits: u8) {
+        self.0 &= !bits;
+    }
+}
+
+/// The information of an actor or a partner carried by a LACPDU.
+///
+/// The system and the key identify the aggregation, the port identifies the
+/// link in it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct LacpInfo {
+    pub system_priority: u16,
+    pub system: MacAddr,
+    pub key: u16,
+    pub port_priority: u16,
+    pub port: u16,
+    pub state: LacpState,
+}
+
+impl LacpInfo {
+    // The length of the information, without the reserved bytes.
+    const LEN: usize = 15;
+
+    fn read(data: &[u8]) -> Self {
+        Self {
+            system_priority: NetworkEndian::read_u16(&data[0..2]),
+            system: MacAddr::from_bytes(&data[2..8]),
+            key: NetworkEndian::read_u16(&data[8..10]),
+            port_priority: NetworkEndian::read_u16(&data[10..12]),
+            port: NetworkEndian::read_u16(&data[12..14]),
+            state: LacpState(data[14]),
+        }
+    }
+
+    fn write(&self, data: &mut [u8]) {
+        NetworkEndian::write_u16(&mut data[0..2], self.system_priority);
+        data[2..8].copy_from_slice(self.system.as_bytes());
+        NetworkEndian::write_u16(&mut data[8..10], self.key);
+        NetworkEndian::write_u16(&mut data[10..12], self.port_priority);
+        NetworkEndian::write_u16(&mut data[12..14], self.port);
+        data[14] = self.state.0;
+    }
+}
+
+/// A LACPDU.
+#[derive(Clone, Copy, Debug)]
+pub struct LacpHeader<T> {
+    buf: T,
+}
+
+impl<T: AsRef<[u8]>> LacpHeader<T> {
+    #[inline]
+    pub fn new(buf: T) -> Result<Self, T> {
+        if buf.as_ref().len() >= LACPDU_LEN {
+            Ok(Self { buf })
+        } else {
+            Err(buf)
+        }
+    }
+
+    #[inline]
+    pub fn new_unchecked(buf: T) -> Self {
+        Self { buf }
+    }
+
+    #[inline]
+    pub fn as_bytes(&self) -> &[u8] {
+        &self.buf.as_ref()[0..LACPDU_LEN]
+    }
+
+    #[inline]
+    pub fn to_owned(&self) -> LacpHeader<[u8; LACPDU_LEN]> {
+        let mut buf = [0; LACPDU_LEN];
+        buf.copy_from_slice(self.as_bytes());
+        LacpHeader { buf }
+    }
+
+    #[inline]
+    pub fn subtype(&self) -> SlowProtoSubtype {
+        SlowProtoSubtype::from(self.buf.as_ref()[0])
+    }
+
+    #[inline]
+    pub fn version(&self) -> u8 {
+        *version(self.buf.as_ref())
+    }
+
+    #[inline]
+    pub fn actor(&self) -> LacpInfo {
+        LacpInfo::read(&self.buf.as_ref()[ACTOR_OFFSET..ACTOR_OFFSET + LacpInfo::LEN])
+    }
+
+    #[inline]
+    pub fn partner(&self) -> LacpInfo {
+        LacpInfo::read(&self.buf.as_ref()[PARTNER_OFFSET..PARTNER_OFFSET + LacpInfo::LEN])
+    }
+
+    /// Returns the maximum delay of the collector in tens of microseconds.
+    #[inline]
+    pub fn collector_max_delay(&self) -> u16 {
+        let data = collector_max_delay(self.buf.as_ref());
+        NetworkEndian::read_u16(data)
+    }
+
+    // Returns whether the TLVs are at their places.
+    #[inline]
+    fn check_tlvs(&self) -> bool {
+        let data = self.buf.as_ref();
+        data[2..4] == ACTOR_TLV && data[22..24] == PARTNER_TLV && data[42..44] == COLLECTOR_TLV
+    }
+}
+
+impl<T: AsMut<[u8]>> LacpHeader<T> {
+    #[inline]
+    pub fn set_version(&mut self, value: u8) {
+        *version_mut(self.buf.as_mut()) = value;
+    }
+
+    #[inline]
+    pub fn set_actor(&mut self, value: LacpInfo) {
+        value.write(&mut self.buf.as_mut()[ACTOR_OFFSET..ACTOR_OFFSET + LacpInfo::LEN]);
+    }
+
+    #[inline]
+    pub fn set_partner(&mut self, value: LacpInfo) {
+        value.write(&mut self.buf.as_mut()[PARTNER_OFFSET..PARTNER_OFFSET + LacpInfo::LEN]);
+    }
+
+    #[inline]
+    pub fn set_collector_max_delay(&mut self, value: u16) {
+        let data = collector_max_delay_mut(self.buf.as_mut());
+        NetworkEndian::write_u16(data, value);
+    }
+}
+
+packet_base! {
+    /// A LACPDU carried by a Slow Protocols frame.
+    pub struct LacpPacket: LacpHeader {
+        header_len: LACPDU_LEN,
+        get_methods: [
+            (subtype, SlowProtoSubtype),
+            (version, u8),
+            (actor, LacpInfo),
+            (partner, LacpInfo),
+            (collector_max_delay, u16),
+        ],
+        set_methods: [
+            (set_version, value: u8),
+            (set_actor, value: LacpInfo),
+            (set_partner, value: LacpInfo),
+            (set_collector_max_delay, value: u16),
+        ],
+        unchecked_set_methods: []
+    }
+}
+
+impl<T: Buf> LacpPacket<T> {
+    /// Parse a LACPDU, whose actor, partner and collector TLVs must be at
+    /// their places.
+    #[inline]
+    pub fn parse(buf: T) -> Result<LacpPacket<T>, T> {
+        traced_parse!("lacp", buf, |_: &Self| LACPDU_LEN, {
+            if buf.chunk().len() < LACPDU_LEN {
+                return Err(buf);
+            }
+
+            let header = LacpHeader::new_unchecked(buf.chunk());
+            if header.subtype() == SlowProtoSubtype::LACP
+                && header.version() >= 1
+                && header.check_tlvs()
+            {
+                Ok(LacpPacket { buf })
+            } else {
+                Err(buf)
+            }
+        })
+    }
+}
+
+impl<T: PktMut> LacpPacket<T> {
+    #[inline]
+    pub fn prepend_header<HT: AsRef<[u8]>>(mut buf: T, header: &LacpHeader<HT>) -> LacpPacket<T> {
+        assert!(buf.chunk_headroom() >= LACPDU_LEN);
+        buf.move_back(LACPDU_LEN);
+
+        let data = &mut buf.chunk_mut()[0..LACPDU_LEN];
+        data.copy_from_slice(header.as_bytes());
+
+        LacpPacket { buf }
+    }
+}
+
+impl<T> EtherPayload for LacpPacket<T> {
+    const ETHERTYPE: EtherType = EtherType::SLOW_PROTOCOLS;
+}
+
+/// A Slow Protocols frame, told apart by its subtype.
+#[derive(Debug)]
+pub enum SlowProtoGroup<T> {
+    Lacp(LacpPacket<T>),
+    /// A frame of another subtype, e.g. a marker PDU, left unparsed.
+    Other(SlowProtoSubtype, T),
+}
+
+impl<T: Buf> SlowProtoGroup<T> {
+    /// Parse the payload of a Slow Protocols frame.
+    ///
+    /// Returns an error if the payload is empty, or if it is a malformed
+    /// LACPDU.
+    #[inline]
+    pub fn parse(buf: T) -> Result<SlowProtoGroup<T>, T> {
+        let subtype = match buf.chunk().first() {
+            Some(subtype) => SlowProtoSubtype::from(*subtype),
+            None => return Err(buf),
+        };
+        match subtype {
+            SlowProtoSubtype::LACP => LacpPacket::parse(buf).map(SlowProtoGroup::Lacp),
+            _ => Ok(SlowProtoGroup::Other(subtype, buf)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ether::{EtherPacket, ETHER_HEADER_LEN, ETHER_HEADER_TEMPLATE};
+    use crate::{Cursor, CursorMut};
+
+    fn actor() -> LacpInfo {
+        LacpInfo {
+            system_priority: 0x8000,
+            system: MacAddr([0x02, 0, 0, 0, 0, 0x01]),
+            key: 0x0d,
+            port_priority: 0xff,
+            port: 0x0102,
+            state: LacpState(LacpState::ACTIVITY | LacpState::TIMEOUT | LacpState::AGGREGATION),
+        }
+    }
+
+    #[test]
+    fn lacpdu_build_and_parse() {
+        let mut frame = [0xff; ETHER_HEADER_LEN + LACPDU_LEN];
+        let mut buf = CursorMut::new(&mut frame[..]);
+        buf.advance(ETHER_HEADER_LEN + LACPDU_LEN);
+
+        let mut pkt = LacpPacket::prepend_header(buf, &LACPDU_TEMPLATE);
+        pkt.set_actor(actor());
+        let mut partner = actor();
+        partner.system = MacAddr([0x02, 0, 0, 0, 0, 0x02]);
+        partner.state.insert(LacpState::SYNCHRONIZATION);
+        pkt.set_partner(partner);
+        pkt.set_collector_max_delay(5);
+
+        let mut ethpkt = EtherPacket::prepend_header(pkt.release(), &ETHER_HEADER_TEMPLATE);
+        ethpkt.set_dest_mac(SLOW_PROTOCOLS_MULTICAST);
+        ethpkt.set_ethertype_for::<LacpPacket<()>>();
+
+        assert_eq!(&frame[12..18], &[0x88, 0x09, 0x01, 0x01, 0x01, 0x14]);
+        assert_eq!(
+            &frame[18..34],
+            &[0x80, 0x00, 0x02, 0, 0, 0, 0, 0x01, 0x00, 0x0d, 0x00, 0xff, 0x01, 0x02, 0x07, 0x00]
+        );
+        assert_eq!(&frame[72..74], &[0x00, 0x00]);
+        assert!(frame[74..].iter().all(|b| *b == 0));
+
+        let ethpkt = EtherPacket::parse(Cursor::new(&frame[..])).unwrap();
+        assert_eq!(ethpkt.ethertype(), EtherType::SLOW_PROTOCOLS);
+        let pkt = match SlowProtoGroup::parse(ethpkt.payload()).unwrap() {
+            SlowProtoGroup::Lacp(pkt) => pkt,
+            _ => panic!(),
+        };
+        assert_eq!(pkt.version(), 1);
+        assert_eq!(pkt.actor(), actor());
+        assert_eq!(pkt.partner(), partner);
+        assert!(pkt.partner().state.contains(LacpState::SYNCHRONIZATION));
+        assert!(!pkt.actor().state.contains(LacpState::SYNCHRONIZATION));
+        assert_eq!(pkt.collector_max_delay(), 5);
+    }
+
+    #[test]
+    fn slow_proto_dispatch() {
+        let mut bytes = LACPDU_TEMPLATE.as_bytes().to_vec();
+        assert!(LacpPacket::parse(Cursor::new(&bytes[..LACPDU_LEN - 1])).is_err());
+
+        // A misplaced partner TLV.
+        bytes[22] = 0x03;
+        assert!(SlowProtoGroup::parse(Cursor::new(&bytes[..])).is_err());
+
+        // A marker PDU.
+        bytes[0] = 2;
+        match SlowProtoGroup::parse(Cursor::new(&bytes[..])).unwrap() {
+            SlowProtoGroup::Other(subtype, _) => assert_eq!(subtype, SlowProtoSubtype::MARKER),
+            _ => panic!(),
+        }
+        assert!(SlowProtoGroup::parse(Cursor::new(&bytes[..0])).is_err());
+    }
+}
@@ -1,10 +1,15 @@
 #[macro_use]
 mod macros;
+#[doc(hidden)]
+pub use macros::debug_bounds_check;
 
 mod traits;
 pub use traits::{Buf, PktBuf, PktMut};
+#[doc(hidden)]
+pub use traits::prefetch;
 
 pub(crate) mod checksum_utils;
+pub use checksum_utils::ChecksumAccumulator;
 
 mod cursors;
 pub use cursors::{Cursor, CursorMut};
@@ -13,10 +18,56 @@ pub mod cursors_old;
 
 pub mod arp;
 pub mod ether;
+pub mod gre;
 pub mod icmpv4;
 pub mod icmpv6;
+pub mod ipip;
 pub mod ipsec;
 pub mod ipv4;
 pub mod ipv6;
+pub mod ppp;
+pub mod pppoe;
+pub mod sctp;
 pub mod tcp;
 pub mod udp;
+
+pub mod acl;
+pub mod announce;
+pub mod bgp;
+pub mod cdp;
+pub mod corpus;
+pub mod cow;
+pub mod dhcp;
+pub mod dhcpv6;
+pub mod dns;
+pub mod fill;
+pub mod flow;
+pub mod fmt;
+pub mod frag;
+pub mod goose;
+pub mod hsrp;
+pub mod http;
+pub mod ikev2;
+pub mod lacp;
+pub mod lldp;
+pub mod mirror;
+pub mod mutate;
+pub mod pcap;
+pub mod ports;
+pub mod ptp;
+pub mod quic;
+pub mod scan;
+pub mod stamp;
+pub mod sv;
+pub mod tbcd;
+pub mod teredo;
+pub mod testing;
+pub mod tls;
+pub mod tlv;
+pub mod tunnel;
+
+#[cfg(feature = "mitigate")]
+pub mod mitigate;
+
+#[cfg(feature = "neigh")]
+pub mod neigh;
@@ -0,0 +1,278 @@
+//! A parser of the Link Layer Discovery Protocol (IEEE 802.1AB).
+//!
+//! An LLDP frame is sent periodically by a device on each of its ports to
+//! advertise the identity of the device and of the port. The payload is a
+//! list of TLVs, starting with the mandatory chassis ID, port ID and TTL
+//! TLVs, and ending with the end TLV. Each TLV has a 7-bit type and a 9-bit
+//! length, which is why [`LldpTlvIter`] is used instead of
+//! [`crate::tlv::TlvIter`].
+//!
+//! # Examples
+//! ```
+//! use rpkt::lldp::LldpPdu;
+//!
+//! let pdu = [
+//!     0x02, 0x07, 0x04, 0x02, 0x00, 0x00, 0x00, 0x00, 0x01, // chassis ID, a MAC address
+//!     0x04, 0x03, 0x05, b'e', b'1', // port ID, an interface name
+//!     0x06, 0x02, 0x00, 0x78, // TTL
+//!     0x0a, 0x02, b's', b'w', // system name
+//!     0x00, 0x00, // end
+//! ];
+//! let pdu = LldpPdu::parse(&pdu).unwrap();
+//! assert_eq!(pdu.port_id(), (5, &b"e1"[..]));
+//! assert_eq!(pdu.ttl(), 120);
+//! assert_eq!(pdu.system_name(), Some(&b"sw"[..]));
+//! ```
+
+use byteorder::{ByteOrder, NetworkEndian};
+
+use crate::ether::MacAddr;
+
+/// The nearest bridge multicast address, the destination of the LLDP frames.
+pub const LLDP_MULTICAST: MacAddr = MacAddr([0x01, 0x80, 0xc2, 0x00, 0x00, 0x0e]);
+
+/// The length of the header of a TLV.
+pub const LLDP_TLV_HEADER_LEN: usize = 2;
+
+enum_sim! {
+    /// The type of an LLDP TLV.
+    pub struct LldpTlvType (u8) {
+        END = 0,
+        CHASSIS_ID = 1,
+        PORT_ID = 2,
+        TTL = 3,
+        PORT_DESC = 4,
+        SYSTEM_NAME = 5,
+        SYSTEM_DESC = 6,
+        SYSTEM_CAPS = 7,
+        MGMT_ADDR = 8,
+        ORG_SPECIFIC = 127,
+    }
+}
+
+/// A TLV yielded by [`LldpTlvIter`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct LldpTlv<'a> {
+    pub tlv_type: LldpTlvType,
+    pub value: &'a [u8],
+}
+
+/// Iterates over the TLVs of an LLDP frame.
+///
+/// The iteration stops after the end TLV, or at the first truncated TLV, in
+/// which case [`LldpTlvIter::is_valid`] returns `false`.
+#[derive(Debug, Clone)]
+pub struct LldpTlvIter<'a> {
+    buf: &'a [u8],
+    valid: bool,
+    ended: bool,
+}
+
+impl<'a> LldpTlvIter<'a> {
+    #[inline]
+    pub fn new(buf: &'a [u8]) -> Self {
+        Self {
+            buf,
+            valid: true,
+            ended: false,
+        }
+    }
+
+    /// Returns whether no truncated TLV has been found.
+    #[inline]
+    pub fn is_valid(&self) -> bool {
+        self.valid
+    }
+}
+
+impl<'a> Iterator for LldpTlvIter<'a> {
+    type Item = LldpTlv<'a>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if !self.valid || self.ended || self.buf.is_empty() {
+            return None;
+        }
+
+        if self.buf.len() < LLDP_TLV_HEADER_LEN {
+            self.valid = false;
+            return None;
+        }
+        let header = NetworkEndian::read_u16(self.buf);
+        let tlv_type = LldpTlvType::from((header >> 9) as u8);
+        let end = LLDP_TLV_HEADER_LEN + usize::from(header & 0x01ff);
+        if end > self.buf.len() {
+            self.valid = false;
+            return None;
+        }
+
+        let value = &self.buf[LLDP_TLV_HEADER_LEN..end];
+        self.buf = &self.buf[end..];
+        // The padding of the frame follows the end TLV.
+        self.ended = tlv_type == LldpTlvType::END;
+        Some(LldpTlv { tlv_type, value })
+    }
+}
+
+/// The payload of an LLDP frame.
+#[derive(Debug, Clone, Copy)]
+pub struct LldpPdu<'a> {
+    buf: &'a [u8],
+    chassis_id: (u8, &'a [u8]),
+    port_id: (u8, &'a [u8]),
+    ttl: u16,
+}
+
+impl<'a> LldpPdu<'a> {
+    /// Parse the payload of an LLDP frame, which may be followed by the padding
+    /// of the frame.
+    ///
+    /// Returns `None` if the payload does not start with the chassis ID, the
+    /// port ID and the TTL TLVs, or if a TLV is truncated.
+    pub fn parse(buf: &'a [u8]) -> Option<Self> {
+        let mut iter = LldpTlvIter::new(buf);
+        let chassis_id = subtyped(iter.next()?, LldpTlvType::CHASSIS_ID)?;
+        let port_id = subtyped(iter.next()?, LldpTlvType::PORT_ID)?;
+        let ttl = iter.next()?;
+        if ttl.tlv_type != LldpTlvType::TTL || ttl.value.len() < 2 {
+            return None;
+        }
+        (&mut iter).for_each(drop);
+        iter.is_valid().then_some(Self {
+            buf,
+            chassis_id,
+            port_id,
+            ttl: NetworkEndian::read_u16(ttl.value),
+        })
+    }
+
+    /// Returns the subtype and the value of the chassis ID, e.g. subtype 4 for
+    /// a MAC address.
+    #[inline]
+    pub fn chassis_id(&self) -> (u8, &'a [u8]) {
+        self.chassis_id
+    }
+
+    /// Returns the subtype and the value of the port ID, e.g. subtype 5 for
+    /// an interface name.
+    #[inline]
+    pub fn port_id(&self) -> (u8, &'a [u8]) {
+        self.port_id
+    }
+
+    /// Returns how long the information is valid in seconds, 0 tells to
+    /// remove it right away.
+    #[inline]
+    pub fn ttl(&self) -> u16 {
+        self.ttl
+    }
+
+    #[inline]
+    pub fn tlvs(&self) -> LldpTlvIter<'a> {
+        LldpTlvIter::new(self.buf)
+    }
+
+    /// Returns the value of the first TLV of `tlv_type`.
+    pub fn tlv(&self, tlv_type: LldpTlvType) -> Option<&'a [u8]> {
+        self.tlvs()
+            .find(|tlv| tlv.tlv_type == tlv_type)
+            .map(|tlv| tlv.value)
+    }
+
+    #[inline]
+    pub fn port_desc(&self) -> Option<&'a [u8]> {
+        self.tlv(LldpTlvType::PORT_DESC)
+    }
+
+    #[inline]
+    pub fn system_name(&self) -> Option<&'a [u8]> {
+        self.tlv(LldpTlvType::SYSTEM_NAME)
+    }
+
+    #[inline]
+    pub fn system_desc(&self) -> Option<&'a [u8]> {
+        self.tlv(LldpTlvType::SYSTEM_DESC)
+    }
+
+    /// Returns the supported and the enabled capabilities, e.g. bit 2 for a
+    /// bridge and bit 4 for a router.
+    pub fn capabilities(&self) -> Option<(u16, u16)> {
+        let value = self.tlv(LldpTlvType::SYSTEM_CAPS)?;
+        if value.len() < 4 {
+            return None;
+        }
+        Some((
+            NetworkEndian::read_u16(&value[0..2]),
+            NetworkEndian::read_u16(&value[2..4]),
+        ))
+    }
+
+    /// Returns the address family number and the address of the first
+    /// management address, e.g. family 1 for an IPv4 address.
+    pub fn mgmt_addr(&self) -> Option<(u8, &'a [u8])> {
+        let value = self.tlv(LldpTlvType::MGMT_ADDR)?;
+        // The length counts the family and the address.
+        let len = usize::from(*value.first()?);
+        let addr = value.get(1..1 + len)?;
+        let (&family, addr) = addr.split_first()?;
+        Some((family, addr))
+    }
+}
+
+fn subtyped(tlv: LldpTlv<'_>, tlv_type: LldpTlvType) -> Option<(u8, &[u8])> {
+    if tlv.tlv_type != tlv_type {
+        return None;
+    }
+    let (&subtype, value) = tlv.value.split_first()?;
+    Some((subtype, value))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn pdu() -> Vec<u8> {
+        let mut pdu = vec![0x02, 0x07, 0x04, 0x02, 0, 0, 0, 0, 0x01];
+        pdu.extend_from_slice(&[0x04, 0x05, 0x07, b'G', b'i', b'0', b'/']);
+        pdu.extend_from_slice(&[0x06, 0x02, 0x00, 0x78]);
+        pdu.extend_from_slice(&[0x08, 0x04]);
+        pdu.extend_from_slice(b"up 1");
+        pdu.extend_from_slice(&[0x0e, 0x04, 0x00, 0x14, 0x00, 0x04]);
+        // A management address of 192.0.2.1 on ifindex 3, without OID.
+        pdu.extend_from_slice(&[0x10, 0x0c, 0x05, 0x01, 192, 0, 2, 1, 0x02, 0, 0, 0, 3, 0]);
+        pdu.extend_from_slice(&[0xfe, 0x04, 0x00, 0x12, 0x0f, 0x01]);
+        pdu.extend_from_slice(&[0x00, 0x00, 0x00, 0x00]);
+        pdu
+    }
+
+    #[test]
+    fn lldp_pdu() {
+        let bytes = pdu();
+        let pdu = LldpPdu::parse(&bytes).unwrap();
+        assert_eq!(pdu.chassis_id(), (4, &[0x02, 0, 0, 0, 0, 0x01][..]));
+        assert_eq!(pdu.port_id(), (7, &b"Gi0/"[..]));
+        assert_eq!(pdu.ttl(), 120);
+        assert_eq!(pdu.port_desc(), Some(&b"up 1"[..]));
+        assert_eq!(pdu.system_name(), None);
+        assert_eq!(pdu.capabilities(), Some((0x14, 0x04)));
+        assert_eq!(pdu.mgmt_addr(), Some((1, &[192, 0, 2, 1][..])));
+
+        // The padding behind the end TLV is not iterated.
+        let types: Vec<_> = pdu.tlvs().map(|tlv| u8::from(tlv.tlv_type)).collect();
+        assert_eq!(types, [1, 2, 3, 4, 7, 8, 127, 0]);
+    }
+
+    #[test]
+    fn lldp_invalid() {
+        let bytes = pdu();
+
+        // The mandatory TLVs out of order, a missing TTL, and a truncated TLV.
+        let mut swapped = bytes.clone();
+        swapped[0] = 0x04;
+        assert!(LldpPdu::parse(&swapped).is_none());
+        assert!(LldpPdu::parse(&bytes[..16]).is_none());
+        assert!(LldpPdu::parse(&bytes[..25]).is_none());
+        let mut iter = LldpTlvIter::new(&bytes[..25]);
+        assert_eq!(iter.by_ref().count(), 3);
+        assert!(!iter.is_valid());
+    }
+}
@@ -1,3 +1,59 @@
+// With the `debug-bounds` feature, check that `buf` holds at least `len` bytes
+// before a header field is accessed in debug builds, and panic with a message
+// that names the protocol module and the field. This turns the misuse of
+// `parse_unchecked` into an actionable panic instead of a raw index error.
+//
+// The exported macros call it through `$crate`, so that the feature is
+// resolved against rpkt rather than the crate that expands them. Without the
+// feature, or in release builds, this does nothing.
+#[doc(hidden)]
+#[inline(always)]
+#[track_caller]
+#[allow(unused_variables)]
+pub fn debug_bounds_check(buf: &[u8], len: usize, module: &str, what: &str) {
+    #[cfg(all(feature = "debug-bounds", debug_assertions))]
+    assert!(
+        buf.len() >= len,
+        "{}: {} requires {} bytes, but the buffer only has {} bytes",
+        module,
+        what,
+        len,
+        buf.len(),
+    );
+}
+
+// Wrap the body of a `parse` function. With the `trace` feature, a `tracing`
+// event is emitted for every parse, carrying the protocol name, the length of
+// the input chunk, the outcome and the header length of an accepted packet.
+// This helps to find out why a packet is rejected deep inside a parse chain.
+//
+// Without the feature, the body is expanded as is.
+#[cfg(not(feature = "trace"))]
+macro_rules! traced_parse {
+    ($proto: literal, $buf: ident, $hlen: expr, $body: block) => {
+        $body
+    };
+}
+
+#[cfg(feature = "trace")]
+macro_rules! traced_parse {
+    ($proto: literal, $buf: ident, $hlen: expr, $body: block) => {{
+        let chunk_len = $buf.chunk().len();
+        #[allow(clippy::redundant_closure_call)]
+        let res: Result<Self, T> = (move || $body)();
+        match &res {
+            Ok(pkt) => ::tracing::trace!(
+                protocol = $proto,
+                chunk_len,
+                header_len = ($hlen)(pkt),
+                "parse accepted"
+            ),
+            Err(_) => ::tracing::trace!(protocol = $proto, chunk_len, "parse rejected"),
+        }
+        res
+    }};
+}
+
 #[macro_export]
 macro_rules! header_field_range_accessors {
     ( $(($get_range: ident, $get_range_mut: ident, $left: literal..$right: literal $(,)?)),* $(,)? )
@@ -5,6 +61,7 @@ macro_rules! header_field_range_accessors {
         $(
             #[inline]
             fn $get_range(buf: &[u8]) -> &[u8] {
+                $crate::debug_bounds_check(buf, $right, module_path!(), concat!("field `", stringify!($get_range), "`"));
                 &buf[$left..$right]
             }
         )*
@@ -12,6 +69,7 @@ macro_rules! header_field_range_accessors {
         $(
             #[inline]
             fn $get_range_mut(buf: &mut [u8]) -> &mut [u8] {
+                $crate::debug_bounds_check(buf, $right, module_path!(), concat!("field `", stringify!($get_range), "`"));
                 &mut buf[$left..$right]
             }
         )*
@@ -25,6 +83,7 @@ macro_rules! header_field_val_accessors {
         $(
             #[inline]
             fn $get_val(buf: &[u8]) -> &u8 {
+                $crate::debug_bounds_check(buf, $val + 1, module_path!(), concat!("field `", stringify!($get_val), "`"));
                 &buf[$val]
             }
         )*
@@ -32,6 +91,7 @@ macro_rules! header_field_val_accessors {
         $(
             #[inline]
             fn $get_val_mut(buf: &mut [u8]) -> &mut u8 {
+                $crate::debug_bounds_check(buf, $val + 1, module_path!(), concat!("field `", stringify!($get_val), "`"));
                 &mut buf[$val]
             }
         )*
@@ -115,9 +175,38 @@ macro_rules! packet_base {
         impl<T: ::bytes::Buf> $packet<T> {
             #[inline]
             pub fn parse_unchecked(buf: T) -> Self {
+                $crate::debug_bounds_check(buf.chunk(), $hlen, module_path!(), concat!("packet `", stringify!($packet), "`"));
                 Self { buf }
             }
 
+            /// Parse a batch of buffers, e.g. a burst of an rx queue, into
+            /// `out`, one slot per buffer in order.
+            ///
+            /// The chunks of the whole batch are prefetched before any of them is
+            /// parsed, so that the header reads of consecutive packets overlap.
+            /// At most `out.len()` buffers are taken from `bufs`. A buffer rejected
+            /// by `parse` is dropped, leaving `None` in its slot, and the slots
+            /// past the batch are cleared. Returns the number of parsed packets.
+            pub fn parse_batch<I: IntoIterator<Item = T>>(bufs: I, out: &mut [Option<Self>]) -> usize {
+                let mut len = 0;
+                for (slot, buf) in out.iter_mut().zip(bufs) {
+                    $crate::prefetch(buf.chunk());
+                    *slot = Some(Self { buf });
+                    len += 1;
+                }
+
+                let mut parsed = 0;
+                for slot in out[..len].iter_mut() {
+                    let buf = slot.take().unwrap().buf;
+                    if let Ok(pkt) = Self::parse(buf) {
+                        *slot = Some(pkt);
+                        parsed += 1;
+                    }
+                }
+                out[len..].iter_mut().for_each(|slot| *slot = None);
+                parsed
+            }
+
             #[inline]
             pub fn buf(&self) -> &T {
                 &self.buf
@@ -0,0 +1,400 @@
+//! The transports of remote packet mirrors: TZSP and ERSPAN.
+//!
+//! A remote mirror sends a copy of the captured frames to a collector,
+//! encapsulated in UDP by TZSP (the TaZmen Sniffer Protocol, e.g. the packet
+//! sniffer of MikroTik RouterOS) or in GRE by ERSPAN (e.g. the remote SPAN of
+//! Cisco switches and of Open vSwitch). [`decap_tzsp`] and [`decap_erspan`]
+//! strip the encapsulation, so that the captured frames can be fed to the
+//! same pipelines as the local ones.
+//!
+//! # Examples
+//! ```
+//! use rpkt::mirror::{decap_tzsp, TzspEncap, TzspHeader, TzspType, TZSP_TAG_RX_CHANNEL};
+//! use rpkt::{Buf, Cursor};
+//!
+//! // A received Ethernet frame, with the channel tag.
+//! let mut packet = vec![0x01, 0x00, 0x00, 0x01, 0x12, 0x01, 0x06, 0x01];
+//! packet.extend_from_slice(&[0; 14]);
+//!
+//! let header = TzspHeader::parse(&packet).unwrap();
+//! assert_eq!(header.packet_type(), TzspType::RX_PACKET);
+//! assert_eq!(header.tag(TZSP_TAG_RX_CHANNEL), Some(&[0x06][..]));
+//!
+//! let (encap, frame) = decap_tzsp(Cursor::new(&packet[..])).unwrap();
+//! assert_eq!(encap, TzspEncap::ETHERNET);
+//! assert_eq!(frame.remaining(), 14);
+//! ```
+
+use byteorder::{ByteOrder, NetworkEndian};
+
+use crate::ether::EtherType;
+use crate::gre::GrePacket;
+use crate::PktBuf;
+
+/// The UDP port of the TZSP collectors.
+pub const TZSP_PORT: u16 = 37008;
+
+/// The length of the fixed part of the TZSP header.
+pub const TZSP_HEADER_LEN: usize = 4;
+
+const TZSP_VERSION: u8 = 1;
+
+/// A single byte of padding among the tags.
+pub const TZSP_TAG_PADDING: u8 = 0;
+/// The end of the tags, followed by the encapsulated packet.
+pub const TZSP_TAG_END: u8 = 1;
+pub const TZSP_TAG_RAW_RSSI: u8 = 10;
+pub const TZSP_TAG_SNR: u8 = 11;
+pub const TZSP_TAG_DATA_RATE: u8 = 12;
+pub const TZSP_TAG_TIMESTAMP: u8 = 13;
+pub const TZSP_TAG_FCS_ERROR: u8 = 17;
+pub const TZSP_TAG_RX_CHANNEL: u8 = 18;
+pub const TZSP_TAG_PACKET_COUNT: u8 = 40;
+pub const TZSP_TAG_RX_FRAME_LEN: u8 = 41;
+pub const TZSP_TAG_SENSOR_MAC: u8 = 60;
+
+enum_sim! {
+    /// The type of a TZSP packet.
+    pub struct TzspType (u8) {
+        RX_PACKET = 0,
+        TX_PACKET = 1,
+        CONFIG = 3,
+        NULL = 4,
+        PORT = 5,
+    }
+}
+
+enum_sim! {
+    /// The kind of the packet encapsulated by TZSP.
+    pub struct TzspEncap (u16) {
+        ETHERNET = 1,
+        IEEE_802_11 = 18,
+        PRISM = 119,
+        WLAN_AVS = 127,
+    }
+}
+
+/// The header of a TZSP packet, with its tags.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TzspHeader<'a> {
+    data: &'a [u8],
+}
+
+impl<'a> TzspHeader<'a> {
+    /// Parse the header at the start of the UDP payload `data`.
+    ///
+    /// Returns `None` if the version is not 1, or if the tags are truncated
+    /// or not ended by [`TZSP_TAG_END`].
+    pub fn parse(data: &'a [u8]) -> Option<Self> {
+        if data.len() < TZSP_HEADER_LEN || data[0] != TZSP_VERSION {
+            return None;
+        }
+
+        let mut offset = TZSP_HEADER_LEN;
+        loop {
+            match *data.get(offset)? {
+                TZSP_TAG_END => {
+                    return Some(Self {
+                        data: &data[..offset + 1],
+                    })
+                }
+                TZSP_TAG_PADDING => offset += 1,
+                _ => {
+                    let len = usize::from(*data.get(offset + 1)?);
+                    offset += 2 + len;
+                }
+            }
+        }
+    }
+
+    #[inline]
+    pub fn version(&self) -> u8 {
+        self.data[0]
+    }
+
+    #[inline]
+    pub fn packet_type(&self) -> TzspType {
+        self.data[1].into()
+    }
+
+    #[inline]
+    pub fn encap(&self) -> TzspEncap {
+        NetworkEndian::read_u16(&self.data[2..4]).into()
+    }
+
+    /// Returns the length of the header, including the tags.
+    #[inline]
+    pub fn header_len(&self) -> usize {
+        self.data.len()
+    }
+
+    /// Returns an iterator over the tags and their values, without the
+    /// padding and the end tag.
+    #[inline]
+    pub fn tags(&self) -> TzspTags<'a> {
+        TzspTags {
+            data: &self.data[TZSP_HEADER_LEN..self.data.len() - 1],
+        }
+    }
+
+    /// Returns the value of the first `tag`.
+    #[inline]
+    pub fn tag(&self, tag: u8) -> Option<&'a [u8]> {
+        self.tags().find(|(t, _)| *t == tag).map(|(_, value)| value)
+    }
+}
+
+/// An iterator over the tags of a TZSP header.
+#[derive(Debug, Clone)]
+pub struct TzspTags<'a> {
+    data: &'a [u8],
+}
+
+impl<'a> Iterator for TzspTags<'a> {
+    type Item = (u8, &'a [u8]);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        // The tags are checked by `TzspHeader::parse`.
+        loop {
+            let (&tag, rest) = self.data.split_first()?;
+            if tag == TZSP_TAG_PADDING {
+                self.data = rest;
+                continue;
+            }
+            let len = usize::from(rest[0]);
+            let value = &rest[1..1 + len];
+            self.data = &rest[1 + len..];
+            return Some((tag, value));
+        }
+    }
+}
+
+/// Strip the TZSP header of the received or transmitted packet in `buf`, a
+/// UDP payload, and return the kind of the encapsulated packet.
+///
+/// Returns `buf` back if the header is malformed, or if it is another type
+/// of TZSP packet.
+pub fn decap_tzsp<T: PktBuf>(mut buf: T) -> Result<(TzspEncap, T), T> {
+    let Some(header) = TzspHeader::parse(buf.chunk()) else {
+        return Err(buf);
+    };
+    if header.packet_type() != TzspType::RX_PACKET && header.packet_type() != TzspType::TX_PACKET {
+        return Err(buf);
+    }
+
+    let (encap, header_len) = (header.encap(), header.header_len());
+    buf.advance(header_len);
+    Ok((encap, buf))
+}
+
+/// The length of the ERSPAN type II header.
+pub const ERSPAN_TYPE2_HEADER_LEN: usize = 8;
+
+/// The length of the ERSPAN type III header, without the platform specific
+/// subheader.
+pub const ERSPAN_TYPE3_HEADER_LEN: usize = 12;
+
+/// The length of the platform specific subheader of ERSPAN type III.
+pub const ERSPAN_TYPE3_SUBHEADER_LEN: usize = 8;
+
+/// The frame type of ERSPAN type III for a mirrored Ethernet frame.
+pub const ERSPAN_FRAME_ETHER: u8 = 0;
+
+/// The frame type of ERSPAN type III for a mirrored IP packet.
+pub const ERSPAN_FRAME_IP: u8 = 2;
+
+/// The type of an ERSPAN encapsulation.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ErspanType {
+    /// A GRE header without a sequence number, and no ERSPAN header.
+    I,
+    II,
+    III,
+}
+
+impl ErspanType {
+    /// Returns the type of ERSPAN carried by a GRE packet of `protocol`.
+    pub fn from_gre(protocol: EtherType, seq_present: bool) -> Option<Self> {
+        match protocol {
+            EtherType::ERSPAN_TYPE2 if seq_present => Some(ErspanType::II),
+            EtherType::ERSPAN_TYPE2 => Some(ErspanType::I),
+            EtherType::ERSPAN_TYPE3 => Some(ErspanType::III),
+            _ => None,
+        }
+    }
+}
+
+/// The fields of an ERSPAN header.
+///
+/// The fields that are not present in the header of the type are 0.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ErspanHeader {
+    pub erspan_type: ErspanType,
+    /// The original VLAN of the mirrored frame.
+    pub vlan: u16,
+    pub cos: u8,
+    /// Whether the mirrored frame was truncated.
+    pub truncated: bool,
+    pub session_id: u16,
+    /// The port index of type II.
+    pub index: u32,
+    /// The timestamp of type III.
+    pub timestamp: u32,
+    /// The security group tag of type III.
+    pub sgt: u16,
+    /// The frame type of type III.
+    pub frame_type: u8,
+    /// The length of the header, including the subheader of type III.
+    pub header_len: usize,
+}
+
+impl ErspanHeader {
+    /// Parse the ERSPAN header of `erspan_type` at the start of the GRE
+    /// payload `data`.
+    ///
+    /// Returns `None` if the header is truncated, or if its version does not
+    /// match the type.
+    pub fn parse(erspan_type: ErspanType, data: &[u8]) -> Option<Self> {
+        let mut header = Self {
+            erspan_type,
+            vlan: 0,
+            cos: 0,
+            truncated: false,
+            session_id: 0,
+            index: 0,
+            timestamp: 0,
+            sgt: 0,
+            frame_type: ERSPAN_FRAME_ETHER,
+            header_len: 0,
+        };
+        let version = match erspan_type {
+            ErspanType::I => return Some(header),
+            ErspanType::II => 1,
+            ErspanType::III => 2,
+        };
+
+        let word = NetworkEndian::read_u32(data.get(0..4)?);
+        if (word >> 28) as u8 != version {
+            return None;
+        }
+        header.vlan = (word >> 16) as u16 & 0x0fff;
+        header.cos = (word >> 13) as u8 & 0x07;
+        header.truncated = word & 0x0400 != 0;
+        header.session_id = word as u16 & 0x03ff;
+
+        if erspan_type == ErspanType::II {
+            let word = NetworkEndian::read_u32(data.get(4..8)?);
+            header.index = word & 0x000f_ffff;
+            header.header_len = ERSPAN_TYPE2_HEADER_LEN;
+        } else {
+            let fields = data.get(4..ERSPAN_TYPE3_HEADER_LEN)?;
+            header.timestamp = NetworkEndian::read_u32(&fields[0..4]);
+            header.sgt = NetworkEndian::read_u16(&fields[4..6]);
+            let flags = NetworkEndian::read_u16(&fields[6..8]);
+            header.frame_type = (flags >> 10) as u8 & 0x1f;
+            header.header_len = ERSPAN_TYPE3_HEADER_LEN;
+            // The O bit indicates the platform specific subheader.
+            if flags & 0x0001 != 0 {
+                header.header_len += ERSPAN_TYPE3_SUBHEADER_LEN;
+                if header.header_len > data.len() {
+                    return None;
+                }
+            }
+        }
+        Some(header)
+    }
+
+    /// Returns whether the mirrored frame is an Ethernet frame.
+    #[inline]
+    pub fn is_ether(&self) -> bool {
+        self.frame_type == ERSPAN_FRAME_ETHER
+    }
+}
+
+/// Strip the GRE and the ERSPAN headers of the ERSPAN packet in `buf`, a GRE
+/// packet, and return the ERSPAN header.
+///
+/// Returns `buf` back if it is not a valid GRE packet carrying ERSPAN.
+pub fn decap_erspan<T: PktBuf>(buf: T) -> Result<(ErspanHeader, T), T> {
+    let grepkt = GrePacket::parse(buf)?;
+    let gre_len = grepkt.header_len();
+    let erspan_type = ErspanType::from_gre(grepkt.protocol_type(), grepkt.seq_present());
+    let mut buf = grepkt.release();
+
+    let Some(header) = erspan_type.and_then(|t| ErspanHeader::parse(t, &buf.chunk()[gre_len..]))
+    else {
+        return Err(buf);
+    };
+    buf.advance(gre_len + header.header_len);
+    Ok((header, buf))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Buf, Cursor};
+
+    #[test]
+    fn tzsp() {
+        // Padding, a truncated frame length and the end.
+        let mut packet = vec![0x01, 0x01, 0x00, 0x12, 0x00, 0x29, 0x02, 0x05, 0xdc, 0x01];
+        packet.extend_from_slice(&[0xaa; 24]);
+        let header = TzspHeader::parse(&packet).unwrap();
+        assert_eq!(header.packet_type(), TzspType::TX_PACKET);
+        assert_eq!(header.encap(), TzspEncap::IEEE_802_11);
+        assert_eq!(header.header_len(), 10);
+        assert_eq!(
+            header.tags().collect::<Vec<_>>(),
+            vec![(TZSP_TAG_RX_FRAME_LEN, &[0x05, 0xdc][..])]
+        );
+        let (encap, buf) = decap_tzsp(Cursor::new(&packet[..])).unwrap();
+        assert_eq!(encap, TzspEncap::IEEE_802_11);
+        assert_eq!(buf.chunk(), &[0xaa; 24]);
+
+        // A keepalive, another version, and a truncated tag.
+        assert!(decap_tzsp(Cursor::new(&[0x01, 0x04, 0x00, 0x00, 0x01][..])).is_err());
+        assert!(TzspHeader::parse(&[0x02, 0x00, 0x00, 0x01, 0x01]).is_none());
+        assert!(TzspHeader::parse(&[0x01, 0x00, 0x00, 0x01, 0x0a, 0x04, 0x01]).is_none());
+        assert!(TzspHeader::parse(&[0x01, 0x00, 0x00, 0x01]).is_none());
+    }
+
+    #[test]
+    fn erspan() {
+        // Type II, with a sequence number.
+        let mut packet = vec![0x10, 0x00, 0x88, 0xbe, 0x00, 0x00, 0x00, 0x07];
+        packet.extend_from_slice(&[0x10, 0x64, 0x24, 0x2a, 0x00, 0x00, 0x00, 0x03]);
+        packet.extend_from_slice(&[0xaa; 14]);
+        let (header, buf) = decap_erspan(Cursor::new(&packet[..])).unwrap();
+        assert_eq!(header.erspan_type, ErspanType::II);
+        assert_eq!(header.vlan, 100);
+        assert_eq!(header.cos, 1);
+        assert!(header.truncated);
+        assert_eq!(header.session_id, 42);
+        assert_eq!(header.index, 3);
+        assert_eq!(buf.chunk(), &[0xaa; 14]);
+
+        // Type III with the subheader, mirroring an IP packet.
+        let mut packet = vec![0x00, 0x00, 0x22, 0xeb];
+        packet.extend_from_slice(&[0x20, 0x00, 0x00, 0x05, 0x00, 0x00, 0x01, 0x00]);
+        packet.extend_from_slice(&[0x00, 0x10, 0x08, 0x01]);
+        packet.extend_from_slice(&[0; ERSPAN_TYPE3_SUBHEADER_LEN]);
+        packet.extend_from_slice(&[0x45; 20]);
+        let (header, buf) = decap_erspan(Cursor::new(&packet[..])).unwrap();
+        assert_eq!(header.erspan_type, ErspanType::III);
+        assert_eq!((header.session_id, header.timestamp), (5, 0x100));
+        assert_eq!((header.sgt, header.frame_type), (0x10, ERSPAN_FRAME_IP));
+        assert_eq!(buf.chunk(), &[0x45; 20]);
+
+        // Type I, a wrong version, and another protocol.
+        let packet = [0x00, 0x00, 0x88, 0xbe, 0xaa, 0xaa];
+        let (header, buf) = decap_erspan(Cursor::new(&packet[..])).unwrap();
+        assert_eq!((header.erspan_type, header.header_len), (ErspanType::I, 0));
+        assert_eq!(buf.remaining(), 2);
+        let packet = [
+            0x00, 0x00, 0x22, 0xeb, 0x10, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0,
+        ];
+        assert!(decap_erspan(Cursor::new(&packet[..])).is_err());
+        let packet = [0x00, 0x00, 0x08, 0x00, 0x45, 0x00];
+        assert!(decap_erspan(Cursor::new(&packet[..])).is_err());
+    }
+}
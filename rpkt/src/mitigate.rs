@@ -0,0 +1,484 @@
+//! Building blocks for mitigating denial of service attacks.
+//!
+//! * [`SynCookies`] lets a TCP server or a scrubbing proxy answer SYNs without
+//!   keeping any state. The connection parameters are encoded into the initial
+//!   sequence number of the SYN-ACK, and recovered from the ACK that completes
+//!   the handshake.
+//! * [`SourceLimiter`] gives each source address of the traffic its own token
+//!   bucket, and blocks the sources that exceed it for a while.
+//!
+//! # Examples
+//! ```
+//! use rpkt::flow::FiveTuple;
+//! use rpkt::mitigate::SynCookies;
+//! use rpkt_time::Instant;
+//!
+//! let cookies = SynCookies::new([7; 16]);
+//! let tuple = FiveTuple {
+//!     src_port: 40000,
+//!     dst_port: 80,
+//!     protocol: 6,
+//!     ..Default::default()
+//! };
+//!
+//! // Answer the SYN with the cookie as the initial sequence number.
+//! let client_isn = 1000;
+//! let server_isn = cookies.generate(&tuple, client_isn, 1460, Instant::now());
+//!
+//! // The ACK of the client acknowledges the cookie.
+//! let mss = cookies.validate(&tuple, client_isn + 1, server_isn.wrapping_add(1), Instant::now());
+//! assert_eq!(mss, Some(1460));
+//! ```
+
+use std::collections::HashMap;
+
+use rpkt_time::{Duration, Instant, RateLimiter, TokenBucket};
+
+use crate::flow::FiveTuple;
+
+/// The MSS values that can be encoded in a SYN cookie.
+pub const SYN_COOKIE_MSS: [u16; 8] = [536, 1200, 1220, 1280, 1360, 1400, 1440, 1460];
+
+// The cookie counter advances every 64 seconds.
+const COOKIE_PERIOD_SECS: u64 = 64;
+// The number of periods after which a cookie expires.
+const COOKIE_MAX_AGE: u32 = 2;
+const COOKIE_HASH_MASK: u32 = 0x00ff_ffff;
+
+/// Generates and validates TCP SYN cookies.
+///
+/// A cookie packs three fields into the 32-bit sequence number:
+///
+/// * the top 5 bits hold a counter that advances every 64 seconds, so that a
+///   cookie expires after 2 to 3 minutes;
+/// * the next 3 bits hold the index of the MSS in [`SYN_COOKIE_MSS`];
+/// * the low 24 bits hold a keyed hash of the 5-tuple, the client's initial
+///   sequence number, the counter and the MSS index.
+///
+/// The hash is SipHash-2-4 keyed by the secret of the generator, so the
+/// cookies can not be forged without the secret, and a cookie generated by one
+/// build is validated by another.
+#[derive(Clone)]
+pub struct SynCookies {
+    secret: [u8; 16],
+    period: u64,
+}
+
+impl SynCookies {
+    /// Create a cookie generator with the given secret.
+    ///
+    /// # Panics
+    ///
+    /// This function panics if the tsc is not stable, as the counter of the
+    /// cookies is derived from it.
+    pub fn new(secret: [u8; 16]) -> Self {
+        let period = rpkt_time::cycles_per_sec() * COOKIE_PERIOD_SECS;
+        assert!(period > 0, "SYN cookies require a stable tsc");
+        Self { secret, period }
+    }
+
+    /// Returns the initial sequence number of the SYN-ACK that answers a SYN.
+    ///
+    /// `tuple` is the 5-tuple of the SYN, `client_isn` is its sequence number
+    /// and `mss` is the MSS option of the SYN. The MSS is rounded down to an
+    /// entry of [`SYN_COOKIE_MSS`], and values below the first entry are
+    /// rounded up to it.
+    pub fn generate(&self, tuple: &FiveTuple, client_isn: u32, mss: u16, now: Instant) -> u32 {
+        let count = self.count(now);
+        let mss_idx = SYN_COOKIE_MSS
+            .iter()
+            .rposition(|value| *value <= mss)
+            .unwrap_or(0) as u32;
+        (count << 27) | (mss_idx << 24) | self.hash(tuple, client_isn, count, mss_idx)
+    }
+
+    /// Validate the ACK that completes the handshake, and return the MSS
+    /// encoded in the cookie.
+    ///
+    /// `tuple` is the 5-tuple of the ACK, which is the same as the 5-tuple of
+    /// the SYN. `seq` and `ack` are the sequence and acknowledgment numbers of
+    /// the ACK. Returns `None` if the cookie is forged or expired.
+    pub fn validate(&self, tuple: &FiveTuple, seq: u32, ack: u32, now: Instant) -> Option<u16> {
+        let cookie = ack.wrapping_sub(1);
+        let count = cookie >> 27;
+        let mss_idx = (cookie >> 24) & 0x7;
+        if self.count(now).wrapping_sub(count) & 0x1f > COOKIE_MAX_AGE {
+            return None;
+        }
+        if cookie & COOKIE_HASH_MASK != self.hash(tuple, seq.wrapping_sub(1), count, mss_idx) {
+            return None;
+        }
+        Some(SYN_COOKIE_MSS[mss_idx as usize])
+    }
+
+    fn count(&self, now: Instant) -> u32 {
+        ((now.raw() / self.period) & 0x1f) as u32
+    }
+
+    fn hash(&self, tuple: &FiveTuple, client_isn: u32, count: u32, mss_idx: u32) -> u32 {
+        let mut msg = [0; 43];
+        msg[0..16].copy_from_slice(&tuple.src_addr);
+        msg[16..32].copy_from_slice(&tuple.dst_addr);
+        msg[32..34].copy_from_slice(&tuple.src_port.to_be_bytes());
+        msg[34..36].copy_from_slice(&tuple.dst_port.to_be_bytes());
+        msg[36] = tuple.protocol;
+        msg[37..41].copy_from_slice(&client_isn.to_be_bytes());
+        msg[41] = count as u8;
+        msg[42] = mss_idx as u8;
+        siphash24(&self.secret, &msg) as u32 & COOKIE_HASH_MASK
+    }
+}
+
+// SipHash-2-4 of `msg` keyed by `key` (https://www.aumasson.jp/siphash/siphash.pdf).
+// The hasher of the standard library uses fewer rounds, a zero key and an
+// unspecified algorithm, so the MAC of the cookies is implemented here.
+fn siphash24(key: &[u8; 16], msg: &[u8]) -> u64 {
+    let k0 = u64::from_le_bytes(key[0..8].try_into().unwrap());
+    let k1 = u64::from_le_bytes(key[8..16].try_into().unwrap());
+    let mut v = [
+        k0 ^ 0x736f_6d65_7073_6575,
+        k1 ^ 0x646f_7261_6e64_6f6d,
+        k0 ^ 0x6c79_6765_6e65_7261,
+        k1 ^ 0x7465_6462_7974_6573,
+    ];
+
+    fn round(v: &mut [u64; 4]) {
+        v[0] = v[0].wrapping_add(v[1]);
+        v[1] = v[1].rotate_left(13) ^ v[0];
+        v[0] = v[0].rotate_left(32);
+        v[2] = v[2].wrapping_add(v[3]);
+        v[3] = v[3].rotate_left(16) ^ v[2];
+        v[0] = v[0].wrapping_add(v[3]);
+        v[3] = v[3].rotate_left(21) ^ v[0];
+        v[2] = v[2].wrapping_add(v[1]);
+        v[1] = v[1].rotate_left(17) ^ v[2];
+        v[2] = v[2].rotate_left(32);
+    }
+
+    let mut compress = |m: u64| {
+        v[3] ^= m;
+        round(&mut v);
+        round(&mut v);
+        v[0] ^= m;
+    };
+    let mut chunks = msg.chunks_exact(8);
+    for chunk in &mut chunks {
+        compress(u64::from_le_bytes(chunk.try_into().unwrap()));
+    }
+    let mut last = [0; 8];
+    last[..chunks.remainder().len()].copy_from_slice(chunks.remainder());
+    last[7] = msg.len() as u8;
+    compress(u64::from_le_bytes(last));
+
+    v[2] ^= 0xff;
+    for _ in 0..4 {
+        round(&mut v);
+    }
+    v[0] ^ v[1] ^ v[2] ^ v[3]
+}
+
+impl std::fmt::Debug for SynCookies {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        // Keep the secret out of the logs.
+        f.debug_struct("SynCookies").finish_non_exhaustive()
+    }
+}
+
+/// The parameters of the [`SourceLimiter`].
+#[derive(Debug, Clone, Copy)]
+pub struct SourceLimitConfig {
+    /// The number of packets per second allowed from each source.
+    pub rate: u64,
+    /// The number of packets a source can send in a burst.
+    pub burst: u64,
+    /// How long a source that exceeds its rate is blocked.
+    pub block_time: Duration,
+    /// How long an idle source is tracked before [`SourceLimiter::expire`]
+    /// removes it.
+    pub idle_time: Duration,
+    /// The maximum number of tracked sources.
+    pub max_sources: usize,
+}
+
+impl Default for SourceLimitConfig {
+    fn default() -> Self {
+        Self {
+            rate: 1000,
+            burst: 100,
+            block_time: Duration::from_secs(10),
+            idle_time: Duration::from_secs(60),
+            max_sources: 65536,
+        }
+    }
+}
+
+struct Source {
+    bucket: TokenBucket,
+    last_seen: Instant,
+    blocked_until: Option<Instant>,
+}
+
+/// A per-source rate limiter that blocks the sources exceeding their rate.
+///
+/// The sources are keyed by the source address of the [`FiveTuple`] of the
+/// packets. A source that runs out of tokens is blocked, and all its packets
+/// are dropped until [`SourceLimitConfig::block_time`] has passed.
+///
+/// When the limiter tracks [`SourceLimitConfig::max_sources`] sources, the
+/// packets of new sources are admitted without being tracked, until
+/// [`SourceLimiter::expire`] makes room for them.
+///
+/// # Examples
+/// ```
+/// use rpkt::flow::FiveTuple;
+/// use rpkt::mitigate::{SourceLimitConfig, SourceLimiter};
+/// use rpkt_time::Instant;
+///
+/// let mut limiter = SourceLimiter::new(SourceLimitConfig {
+///     rate: 10,
+///     burst: 2,
+///     ..Default::default()
+/// });
+/// let tuple = FiveTuple::default();
+/// let now = Instant::now();
+///
+/// assert!(limiter.admit(&tuple, now));
+/// assert!(limiter.admit(&tuple, now));
+/// assert!(!limiter.admit(&tuple, now));
+/// assert!(limiter.is_blocked(&tuple.src_addr, now));
+/// ```
+pub struct SourceLimiter {
+    sources: HashMap<[u8; 16], Source>,
+    config: SourceLimitConfig,
+}
+
+impl SourceLimiter {
+    /// Create a limiter that tracks no source.
+    ///
+    /// # Panics
+    ///
+    /// This function panics if `config.rate` or `config.burst` is zero.
+    pub fn new(config: SourceLimitConfig) -> Self {
+        assert!(
+            config.rate > 0 && config.burst > 0,
+            "invalid rate limiter parameters"
+        );
+        Self {
+            sources: HashMap::new(),
+            config,
+        }
+    }
+
+    /// Returns the parameters of the limiter.
+    #[inline]
+    pub fn config(&self) -> &SourceLimitConfig {
+        &self.config
+    }
+
+    /// Returns the number of tracked sources.
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.sources.len()
+    }
+
+    /// Returns whether no source is tracked.
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.sources.is_empty()
+    }
+
+    /// Account a packet with the 5-tuple `tuple` received at `now`, and return
+    /// whether the packet should be forwarded.
+    pub fn admit(&mut self, tuple: &FiveTuple, now: Instant) -> bool {
+        let full = self.sources.len() >= self.config.max_sources;
+        let source = match self.sources.get_mut(&tuple.src_addr) {
+            Some(source) => source,
+            None if full => return true,
+            None => self.sources.entry(tuple.src_addr).or_insert(Source {
+                bucket: TokenBucket::new(self.config.rate, self.config.burst),
+                last_seen: now,
+                blocked_until: None,
+            }),
+        };
+        source.last_seen = source.last_seen.max(now);
+
+        match source.blocked_until {
+            Some(until) if now < until => return false,
+            Some(_) => source.blocked_until = None,
+            None => {}
+        }
+        if source.bucket.check_at(1, now) {
+            true
+        } else {
+            source.blocked_until = Some(now + self.config.block_time);
+            false
+        }
+    }
+
+    /// Returns whether the source address `addr` is blocked at `now`.
+    pub fn is_blocked(&self, addr: &[u8; 16], now: Instant) -> bool {
+        self.sources
+            .get(addr)
+            .and_then(|source| source.blocked_until)
+            .is_some_and(|until| now < until)
+    }
+
+    /// Returns an iterator over the source addresses that are blocked at `now`.
+    pub fn blocked(&self, now: Instant) -> impl Iterator<Item = &[u8; 16]> + '_ {
+        self.sources.iter().filter_map(move |(addr, source)| {
+            source
+                .blocked_until
+                .is_some_and(|until| now < until)
+                .then_some(addr)
+        })
+    }
+
+    /// Lift the block of the source address `addr`, returning whether it was
+    /// blocked.
+    pub fn unblock(&mut self, addr: &[u8; 16]) -> bool {
+        self.sources
+            .get_mut(addr)
+            .and_then(|source| source.blocked_until.take())
+            .is_some()
+    }
+
+    /// Stop tracking the sources that are neither blocked nor seen in the last
+    /// [`SourceLimitConfig::idle_time`].
+    pub fn expire(&mut self, now: Instant) {
+        let idle_time = self.config.idle_time;
+        self.sources.retain(|_, source| {
+            source.blocked_until.is_some_and(|until| now < until)
+                || now.saturating_duration_since(source.last_seen) < idle_time.into()
+        });
+    }
+
+    /// Stop tracking all the sources.
+    pub fn clear(&mut self) {
+        self.sources.clear();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn tuple(src: u8, src_port: u16) -> FiveTuple {
+        let mut tuple = FiveTuple {
+            src_port,
+            dst_port: 443,
+            protocol: 6,
+            ..Default::default()
+        };
+        tuple.src_addr[10..].copy_from_slice(&[0xff, 0xff, 10, 0, 0, src]);
+        tuple.dst_addr[10..].copy_from_slice(&[0xff, 0xff, 10, 0, 0, 100]);
+        tuple
+    }
+
+    #[test]
+    fn siphash_vectors() {
+        // The test vectors of the reference implementation.
+        let mut key = [0; 16];
+        key.iter_mut().enumerate().for_each(|(i, b)| *b = i as u8);
+        let msg: Vec<u8> = (0..15).collect();
+        assert_eq!(siphash24(&key, &[]), 0x726f_db47_dd0e_0e31);
+        assert_eq!(siphash24(&key, &msg), 0xa129_ca61_49be_45e5);
+    }
+
+    #[test]
+    fn syn_cookies() {
+        let cookies = SynCookies::new([0x5a; 16]);
+        let syn = tuple(1, 50000);
+        let now = Instant::now();
+
+        for (mss, expected) in [
+            (1460, 1460),
+            (9000, 1460),
+            (1450, 1440),
+            (1300, 1280),
+            (100, 536),
+        ] {
+            let cookie = cookies.generate(&syn, 77, mss, now);
+            assert_eq!(
+                cookies.validate(&syn, 78, cookie.wrapping_add(1), now),
+                Some(expected)
+            );
+        }
+
+        let cookie = cookies.generate(&syn, u32::MAX, 1460, now);
+        let ack = cookie.wrapping_add(1);
+        assert_eq!(cookies.validate(&syn, 0, ack, now), Some(1460));
+        // A different client ISN, 5-tuple or secret does not validate.
+        assert_eq!(cookies.validate(&syn, 1, ack, now), None);
+        assert_eq!(cookies.validate(&tuple(2, 50000), 0, ack, now), None);
+        assert_eq!(cookies.validate(&tuple(1, 50001), 0, ack, now), None);
+        assert_eq!(
+            SynCookies::new([0xa5; 16]).validate(&syn, 0, ack, now),
+            None
+        );
+        // Neither does a tampered MSS index.
+        assert_eq!(cookies.validate(&syn, 0, ack ^ (1 << 24), now), None);
+
+        // The cookie survives for two full periods of the counter.
+        let period = Duration::from_secs(COOKIE_PERIOD_SECS);
+        let start = Instant::from_raw(now.raw() - now.raw() % cookies.period);
+        let cookie = cookies.generate(&syn, 77, 1460, start);
+        for elapsed in [period, period * 2, period * 3 - Duration::from_secs(1)] {
+            assert_eq!(
+                cookies.validate(&syn, 78, cookie.wrapping_add(1), start + elapsed),
+                Some(1460)
+            );
+        }
+        assert_eq!(
+            cookies.validate(&syn, 78, cookie.wrapping_add(1), start + period * 3),
+            None
+        );
+    }
+
+    #[test]
+    fn source_blocking() {
+        let mut limiter = SourceLimiter::new(SourceLimitConfig {
+            rate: 10,
+            burst: 5,
+            block_time: Duration::from_secs(2),
+            idle_time: Duration::from_secs(10),
+            max_sources: 2,
+        });
+        let (a, b, c) = (tuple(1, 1000), tuple(2, 1000), tuple(3, 1000));
+        let now = Instant::now();
+
+        // Any port of the source shares the same bucket.
+        for port in 0..5 {
+            assert!(limiter.admit(&tuple(1, port), now));
+        }
+        assert!(!limiter.admit(&a, now));
+        assert!(limiter.is_blocked(&a.src_addr, now));
+        assert!(limiter.admit(&b, now));
+        assert_eq!(limiter.blocked(now).collect::<Vec<_>>(), vec![&a.src_addr]);
+
+        // The bucket refills, but the block still holds.
+        let t = now + Duration::from_secs(1);
+        assert!(!limiter.admit(&a, t));
+        let t = now + Duration::from_secs(3);
+        assert!(!limiter.is_blocked(&a.src_addr, t));
+        assert!(limiter.admit(&a, t));
+
+        // New sources are not tracked once the limiter is full.
+        for _ in 0..10 {
+            assert!(limiter.admit(&c, t));
+        }
+        assert_eq!(limiter.len(), 2);
+
+        // Idle sources are expired, blocked ones are kept.
+        let t = now + Duration::from_secs(14);
+        for _ in 0..6 {
+            limiter.admit(&b, t);
+        }
+        assert!(limiter.is_blocked(&b.src_addr, t));
+        limiter.expire(t);
+        assert_eq!(limiter.len(), 1);
+        assert!(limiter.unblock(&b.src_addr));
+        assert!(!limiter.unblock(&b.src_addr));
+        limiter.expire(t + Duration::from_secs(10));
+        assert!(limiter.is_empty());
+    }
+}
@@ -0,0 +1,499 @@
+//! Field-aware packet mutators for building protocol fuzzers.
+//!
+//! A frame is first split into protocol [`Layer`]s, each of which carries a
+//! table of the header [`Field`]s. The [`Mutator`] then applies mutations that
+//! respect the header layout: flipping a flag bit, setting a field to a random
+//! or boundary value within its bit width, or truncating the frame at a layer
+//! boundary.
+//!
+//! The mutator is driven by a seeded pseudo-random generator, so a failing
+//! input can be reproduced from the seed alone.
+//!
+//! # Examples
+//! ```
+//! use rpkt::mutate::Mutator;
+//!
+//! # let frame = [0u8; 60];
+//! let mut mutator = Mutator::new(42);
+//! let mut input = frame.to_vec();
+//! let mutation = mutator.mutate(&mut input);
+//! println!("applied {:?}", mutation);
+//! ```
+
+use byteorder::{ByteOrder, NetworkEndian};
+
+use crate::arp::ARP_HEADER_LEN;
+use crate::ether::{EtherHeader, EtherType, ETHER_HEADER_LEN};
+use crate::fill::Filler;
+use crate::ipv4::{IpProtocol, Ipv4Header, IPV4_HEADER_LEN};
+use crate::ipv6::{Ipv6Header, IPV6_HEADER_LEN};
+use crate::tcp::{TcpHeader, TCP_HEADER_LEN};
+use crate::udp::UDP_HEADER_LEN;
+
+/// A header field, located by its bit offset from the start of the header.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Field {
+    pub name: &'static str,
+    pub bit_offset: usize,
+    /// The width of the field in bits, at most 64.
+    pub bit_len: usize,
+}
+
+impl Field {
+    const fn new(name: &'static str, bit_offset: usize, bit_len: usize) -> Self {
+        Self {
+            name,
+            bit_offset,
+            bit_len,
+        }
+    }
+
+    /// Returns whether the field is a single-bit flag.
+    #[inline]
+    pub fn is_flag(&self) -> bool {
+        self.bit_len == 1
+    }
+
+    /// Returns the largest value of the field.
+    #[inline]
+    pub fn max_value(&self) -> u64 {
+        u64::MAX >> (64 - self.bit_len)
+    }
+
+    /// Read the field from `header`.
+    ///
+    /// # Panics
+    ///
+    /// This function panics if `header` is too short for the field.
+    pub fn get(&self, header: &[u8]) -> u64 {
+        let (bytes, shift) = self.span();
+        let mut buf = [0; 16];
+        buf[16 - bytes.len()..].copy_from_slice(&header[bytes]);
+        let raw = NetworkEndian::read_u128(&buf[..]);
+        ((raw >> shift) as u64) & self.max_value()
+    }
+
+    /// Write `value` into the field of `header`, ignoring the bits of `value`
+    /// that do not fit in the field.
+    ///
+    /// # Panics
+    ///
+    /// This function panics if `header` is too short for the field.
+    pub fn set(&self, header: &mut [u8], value: u64) {
+        let (bytes, shift) = self.span();
+        let mut buf = [0; 16];
+        let len = bytes.len();
+        buf[16 - len..].copy_from_slice(&header[bytes.clone()]);
+        let mask = u128::from(self.max_value()) << shift;
+        let raw =
+            (NetworkEndian::read_u128(&buf[..]) & !mask) | ((u128::from(value) << shift) & mask);
+        NetworkEndian::write_u128(&mut buf[..], raw);
+        header[bytes].copy_from_slice(&buf[16 - len..]);
+    }
+
+    // Returns the bytes that the field spans, and the shift of the field within
+    // these bytes read as a big-endian integer.
+    fn span(&self) -> (std::ops::Range<usize>, usize) {
+        assert!(self.bit_len > 0 && self.bit_len <= 64);
+        let start = self.bit_offset / 8;
+        let end = (self.bit_offset + self.bit_len + 7) / 8;
+        (start..end, end * 8 - self.bit_offset - self.bit_len)
+    }
+}
+
+/// The fields of the Ethernet header.
+pub const ETHER_FIELDS: &[Field] = &[
+    Field::new("dest_mac", 0, 48),
+    Field::new("source_mac", 48, 48),
+    Field::new("ethertype", 96, 16),
+];
+
+/// The fields of the ARP header.
+pub const ARP_FIELDS: &[Field] = &[
+    Field::new("hardware_type", 0, 16),
+    Field::new("protocol_type", 16, 16),
+    Field::new("hardware_len", 32, 8),
+    Field::new("protocol_len", 40, 8),
+    Field::new("operation", 48, 16),
+    Field::new("sender_protocol_addr", 112, 32),
+    Field::new("target_protocol_addr", 192, 32),
+];
+
+/// The fields of the fixed IPv4 header.
+pub const IPV4_FIELDS: &[Field] = &[
+    Field::new("version", 0, 4),
+    Field::new("header_len", 4, 4),
+    Field::new("dscp", 8, 6),
+    Field::new("ecn", 14, 2),
+    Field::new("packet_len", 16, 16),
+    Field::new("ident", 32, 16),
+    Field::new("reserved_flag", 48, 1),
+    Field::new("dont_frag", 49, 1),
+    Field::new("more_frags", 50, 1),
+    Field::new("frag_offset", 51, 13),
+    Field::new("time_to_live", 64, 8),
+    Field::new("protocol", 72, 8),
+    Field::new("checksum", 80, 16),
+    Field::new("source_ip", 96, 32),
+    Field::new("dest_ip", 128, 32),
+];
+
+/// The fields of the fixed IPv6 header, excluding the addresses.
+pub const IPV6_FIELDS: &[Field] = &[
+    Field::new("version", 0, 4),
+    Field::new("traffic_class", 4, 8),
+    Field::new("flow_label", 12, 20),
+    Field::new("payload_len", 32, 16),
+    Field::new("next_header", 48, 8),
+    Field::new("hop_limit", 56, 8),
+];
+
+/// The fields of the UDP header.
+pub const UDP_FIELDS: &[Field] = &[
+    Field::new("source_port", 0, 16),
+    Field::new("dest_port", 16, 16),
+    Field::new("packet_len", 32, 16),
+    Field::new("checksum", 48, 16),
+];
+
+/// The fields of the fixed TCP header.
+pub const TCP_FIELDS: &[Field] = &[
+    Field::new("src_port", 0, 16),
+    Field::new("dst_port", 16, 16),
+    Field::new("seq_number", 32, 32),
+    Field::new("ack_number", 64, 32),
+    Field::new("header_len", 96, 4),
+    Field::new("reserved", 100, 3),
+    Field::new("ns", 103, 1),
+    Field::new("cwr", 104, 1),
+    Field::new("ece", 105, 1),
+    Field::new("urg", 106, 1),
+    Field::new("ack", 107, 1),
+    Field::new("psh", 108, 1),
+    Field::new("rst", 109, 1),
+    Field::new("syn", 110, 1),
+    Field::new("fin", 111, 1),
+    Field::new("window_size", 112, 16),
+    Field::new("checksum", 128, 16),
+    Field::new("urgent_pointer", 144, 16),
+];
+
+/// A protocol header found in a frame.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Layer {
+    pub name: &'static str,
+    /// The offset of the header in the frame.
+    pub offset: usize,
+    /// The length of the header, including the options.
+    pub header_len: usize,
+    pub fields: &'static [Field],
+}
+
+impl Layer {
+    /// Returns the bytes of the header in `frame`.
+    #[inline]
+    pub fn header<'a>(&self, frame: &'a [u8]) -> &'a [u8] {
+        &frame[self.offset..self.offset + self.header_len]
+    }
+
+    /// Returns the mutable bytes of the header in `frame`.
+    #[inline]
+    pub fn header_mut<'a>(&self, frame: &'a mut [u8]) -> &'a mut [u8] {
+        &mut frame[self.offset..self.offset + self.header_len]
+    }
+}
+
+/// Split an Ethernet frame into its protocol layers.
+///
+/// The walk only checks that every header fits in the frame, and ignores
+/// inconsistent length fields, so that already mutated frames can be mutated
+/// further. It stops at the first header that is unknown or truncated.
+pub fn layers(frame: &[u8]) -> Vec<Layer> {
+    let mut layers = Vec::new();
+
+    let ether = match EtherHeader::new(frame) {
+        Ok(ether) => ether,
+        Err(_) => return layers,
+    };
+    layers.push(Layer {
+        name: "ether",
+        offset: 0,
+        header_len: ETHER_HEADER_LEN,
+        fields: ETHER_FIELDS,
+    });
+
+    let offset = ETHER_HEADER_LEN;
+    let rest = &frame[offset..];
+    let protocol = match ether.ethertype() {
+        EtherType::ARP if rest.len() >= ARP_HEADER_LEN => {
+            layers.push(Layer {
+                name: "arp",
+                offset,
+                header_len: ARP_HEADER_LEN,
+                fields: ARP_FIELDS,
+            });
+            return layers;
+        }
+        EtherType::IPV4 => match Ipv4Header::new(rest) {
+            Ok(ip) => {
+                let header_len = usize::from(ip.header_len()).max(IPV4_HEADER_LEN);
+                if rest.len() < header_len {
+                    return layers;
+                }
+                layers.push(Layer {
+                    name: "ipv4",
+                    offset,
+                    header_len,
+                    fields: IPV4_FIELDS,
+                });
+                ip.protocol()
+            }
+            Err(_) => return layers,
+        },
+        EtherType::IPV6 => match Ipv6Header::new(rest) {
+            Ok(ip) => {
+                layers.push(Layer {
+                    name: "ipv6",
+                    offset,
+                    header_len: IPV6_HEADER_LEN,
+                    fields: IPV6_FIELDS,
+                });
+                ip.next_header()
+            }
+            Err(_) => return layers,
+        },
+        _ => return layers,
+    };
+
+    let last = layers[layers.len() - 1];
+    let offset = last.offset + last.header_len;
+    let rest = &frame[offset..];
+    match protocol {
+        IpProtocol::UDP if rest.len() >= UDP_HEADER_LEN => {
+            layers.push(Layer {
+                name: "udp",
+                offset,
+                header_len: UDP_HEADER_LEN,
+                fields: UDP_FIELDS,
+            });
+        }
+        IpProtocol::TCP => {
+            if let Ok(tcp) = TcpHeader::new(rest) {
+                let header_len = usize::from(tcp.header_len()).max(TCP_HEADER_LEN);
+                if rest.len() >= header_len {
+                    layers.push(Layer {
+                        name: "tcp",
+                        offset,
+                        header_len,
+                        fields: TCP_FIELDS,
+                    });
+                }
+            }
+        }
+        _ => {}
+    }
+
+    layers
+}
+
+/// A mutation applied by the [`Mutator`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Mutation {
+    /// A flag bit is toggled.
+    FlipFlag {
+        layer: &'static str,
+        field: &'static str,
+    },
+    /// A field is set to `value`.
+    SetField {
+        layer: &'static str,
+        field: &'static str,
+        value: u64,
+    },
+    /// The frame is truncated to `len` bytes.
+    Truncate { len: usize },
+    /// The frame has no known header to mutate.
+    None,
+}
+
+/// A seeded generator of field-aware mutations.
+#[derive(Debug, Clone)]
+pub struct Mutator {
+    filler: Filler,
+}
+
+impl Mutator {
+    /// Create a mutator from `seed`. The same seed always produces the same
+    /// sequence of mutations.
+    pub fn new(seed: u64) -> Self {
+        Self {
+            filler: Filler::new(seed),
+        }
+    }
+
+    /// Returns the next pseudo-random number.
+    pub fn next_u64(&mut self) -> u64 {
+        self.filler.next_u64()
+    }
+
+    fn below(&mut self, n: usize) -> usize {
+        self.filler.below(n as u64) as usize
+    }
+
+    /// Toggle a randomly chosen flag bit of a randomly chosen layer.
+    ///
+    /// Returns `Mutation::None` if the frame has no flag field.
+    pub fn flip_flag(&mut self, frame: &mut [u8]) -> Mutation {
+        let flags: Vec<(Layer, Field)> = layers(frame)
+            .into_iter()
+            .flat_map(|layer| {
+                layer
+                    .fields
+                    .iter()
+                    .filter(|field| field.is_flag())
+                    .map(move |field| (layer, *field))
+            })
+            .collect();
+        if flags.is_empty() {
+            return Mutation::None;
+        }
+
+        let (layer, field) = flags[self.below(flags.len())];
+        let header = layer.header_mut(frame);
+        field.set(header, field.get(header) ^ 1);
+        Mutation::FlipFlag {
+            layer: layer.name,
+            field: field.name,
+        }
+    }
+
+    /// Set a randomly chosen field to a value within its bit width.
+    ///
+    /// Half of the time the value is a boundary value, i.e. 0, 1, the maximum
+    /// or the maximum minus one, which are the most likely to trigger bugs.
+    pub fn randomize_field(&mut self, frame: &mut [u8]) -> Mutation {
+        let layers = layers(frame);
+        if layers.is_empty() {
+            return Mutation::None;
+        }
+        let layer = layers[self.below(layers.len())];
+        let field = layer.fields[self.below(layer.fields.len())];
+
+        let max = field.max_value();
+        let value = if self.next_u64() & 1 == 0 {
+            [0, 1, max, max - 1][self.below(4)]
+        } else {
+            self.next_u64() & max
+        };
+        field.set(layer.header_mut(frame), value);
+        Mutation::SetField {
+            layer: layer.name,
+            field: field.name,
+            value,
+        }
+    }
+
+    /// Truncate the frame at a randomly chosen layer boundary: the start of a
+    /// header, one byte short of the end of a header, or the end of a header.
+    pub fn truncate(&mut self, frame: &mut Vec<u8>) -> Mutation {
+        let mut cuts: Vec<usize> = layers(frame)
+            .iter()
+            .flat_map(|layer| {
+                let end = layer.offset + layer.header_len;
+                [layer.offset, end - 1, end]
+            })
+            .filter(|&cut| cut < frame.len())
+            .collect();
+        cuts.dedup();
+        if cuts.is_empty() {
+            return Mutation::None;
+        }
+
+        let len = cuts[self.below(cuts.len())];
+        frame.truncate(len);
+        Mutation::Truncate { len }
+    }
+
+    /// Apply one randomly chosen mutation to the frame.
+    pub fn mutate(&mut self, frame: &mut Vec<u8>) -> Mutation {
+        match self.below(4) {
+            0 => self.flip_flag(frame),
+            1 => self.truncate(frame),
+            _ => self.randomize_field(frame),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    static FRAME_BYTES: [u8; 54] = [
+        0x00, 0x0b, 0x86, 0x64, 0x8b, 0xa0, 0x00, 0x50, 0x56, 0xae, 0x76, 0xf5, 0x08, 0x00, 0x45,
+        0x00, 0x00, 0x28, 0x5c, 0x65, 0x40, 0x00, 0x40, 0x06, 0x00, 0x00, 0xc0, 0xa8, 0x1d, 0x3a,
+        0xc0, 0xa8, 0x1d, 0xa0, 0x00, 0x50, 0x1f, 0x90, 0x00, 0x00, 0x00, 0x01, 0x00, 0x00, 0x00,
+        0x00, 0x50, 0x02, 0x20, 0x00, 0x00, 0x00, 0x00, 0x00,
+    ];
+
+    #[test]
+    fn field_get_set() {
+        let mut header = [0u8; 20];
+        header.copy_from_slice(&FRAME_BYTES[14..34]);
+        let field = |name| *IPV4_FIELDS.iter().find(|f| f.name == name).unwrap();
+
+        assert_eq!(field("version").get(&header), 4);
+        assert_eq!(field("header_len").get(&header), 5);
+        assert_eq!(field("dont_frag").get(&header), 1);
+        assert_eq!(field("protocol").get(&header), 6);
+        assert_eq!(field("source_ip").get(&header), 0xc0a81d3a);
+
+        field("frag_offset").set(&mut header, 0x1fff);
+        assert_eq!(&header[6..8], &[0x5f, 0xff]);
+        field("dont_frag").set(&mut header, 0);
+        assert_eq!(&header[6..8], &[0x1f, 0xff]);
+        field("ecn").set(&mut header, 0xff);
+        assert_eq!(header[1], 0x03);
+    }
+
+    #[test]
+    fn split_layers() {
+        let layers = layers(&FRAME_BYTES);
+        let names: Vec<_> = layers.iter().map(|layer| layer.name).collect();
+        assert_eq!(names, ["ether", "ipv4", "tcp"]);
+        assert_eq!(layers[2].offset, 34);
+        assert_eq!(layers[2].header_len, 20);
+
+        // A truncated tcp header is not a layer.
+        assert_eq!(super::layers(&FRAME_BYTES[..50]).len(), 2);
+    }
+
+    #[test]
+    fn mutations() {
+        let mut mutator = Mutator::new(7);
+        let mut other = Mutator::new(7);
+        for _ in 0..64 {
+            let mut frame = FRAME_BYTES.to_vec();
+            let mut copy = FRAME_BYTES.to_vec();
+            let mutation = mutator.mutate(&mut frame);
+            assert_eq!(other.mutate(&mut copy), mutation);
+            assert_eq!(frame, copy);
+
+            match mutation {
+                Mutation::FlipFlag { .. } => {
+                    let diff: u32 = frame
+                        .iter()
+                        .zip(FRAME_BYTES.iter())
+                        .map(|(a, b)| (a ^ b).count_ones())
+                        .sum();
+                    assert_eq!(diff, 1);
+                }
+                Mutation::Truncate { len } => {
+                    assert!([0, 13, 14, 33, 34, 53].contains(&len));
+                    assert_eq!(frame.len(), len);
+                }
+                Mutation::SetField { .. } => assert_eq!(frame.len(), FRAME_BYTES.len()),
+                Mutation::None => panic!("no mutation"),
+            }
+        }
+    }
+}
@@ -0,0 +1,402 @@
+//! A neighbor cache that maps IP addresses to MAC addresses.
+//!
+//! The cache follows the neighbor state machine of NDP (RFC 4861), which also
+//! works for ARP. It is keyed on a generic address type, so the same cache can
+//! be used with `Ipv4Addr` for ARP and `Ipv6Addr` for NDP.
+//!
+//! The cache never sends packets by itself. Instead, [`NeighCache::poll`] calls
+//! a hook for every solicitation that is due, and the caller builds and sends
+//! the ARP request or the neighbor solicitation.
+//!
+//! # Examples
+//! ```
+//! use rpkt::ether::MacAddr;
+//! use rpkt::ipv4::Ipv4Addr;
+//! use rpkt::neigh::{NeighCache, NeighConfig};
+//! use rpkt_time::Instant;
+//!
+//! let mut cache = NeighCache::new(NeighConfig::default());
+//! let peer = Ipv4Addr([192, 168, 1, 2]);
+//! let now = Instant::now();
+//!
+//! // The first lookup creates an incomplete entry and schedules a solicitation.
+//! assert_eq!(cache.lookup(peer, now), None);
+//! cache.poll(now, |probe| {
+//!     assert_eq!(probe.addr, peer);
+//!     assert_eq!(probe.target, None);
+//! });
+//!
+//! // The reply resolves the entry.
+//! let mac = MacAddr([0x00, 0x0b, 0x86, 0x64, 0x8b, 0xa0]);
+//! cache.confirm(peer, mac, now);
+//! assert_eq!(cache.lookup(peer, now), Some(mac));
+//! ```
+
+use std::collections::hash_map::{Entry, HashMap};
+use std::hash::Hash;
+
+use rpkt_time::{Duration, Instant};
+
+use crate::ether::MacAddr;
+
+/// The state of a neighbor entry.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum NeighState {
+    /// The address is being resolved, and the MAC address is unknown.
+    Incomplete,
+    /// The MAC address is recently confirmed by the neighbor.
+    Reachable,
+    /// The MAC address is known, but has not been confirmed for a while. It can
+    /// still be used, but it is verified with unicast solicitations once used.
+    Stale,
+}
+
+/// The timing parameters of the [`NeighCache`].
+#[derive(Debug, Clone, Copy)]
+pub struct NeighConfig {
+    /// How long a confirmed entry stays reachable.
+    pub reachable_time: Duration,
+    /// The interval between two solicitations of the same entry.
+    pub retrans_time: Duration,
+    /// The number of solicitations sent before an entry is dropped.
+    pub max_probes: u32,
+    /// How long an unused stale entry is kept in the cache.
+    pub gc_stale_time: Duration,
+}
+
+impl Default for NeighConfig {
+    fn default() -> Self {
+        Self {
+            reachable_time: Duration::from_secs(30),
+            retrans_time: Duration::from_secs(1),
+            max_probes: 3,
+            gc_stale_time: Duration::from_secs(60),
+        }
+    }
+}
+
+/// A solicitation that is due, passed to the hook of [`NeighCache::poll`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct NeighProbe<A> {
+    /// The address to resolve.
+    pub addr: A,
+    /// The MAC address to send a unicast solicitation to. If it is `None`, the
+    /// solicitation should be broadcast for ARP, or sent to the solicited-node
+    /// multicast address for NDP.
+    pub target: Option<MacAddr>,
+    /// The number of solicitations sent before this one.
+    pub attempt: u32,
+}
+
+/// An entry of the [`NeighCache`].
+#[derive(Debug, Clone, Copy)]
+pub struct NeighEntry {
+    mac: Option<MacAddr>,
+    state: NeighState,
+    updated: Instant,
+    next_probe: Instant,
+    probes: u32,
+    in_use: bool,
+}
+
+impl NeighEntry {
+    /// Returns the state of the entry.
+    #[inline]
+    pub fn state(&self) -> NeighState {
+        self.state
+    }
+
+    /// Returns the MAC address of the entry, or `None` if it is incomplete.
+    #[inline]
+    pub fn mac(&self) -> Option<MacAddr> {
+        self.mac
+    }
+
+    /// Returns the instant at which the entry last changed its state.
+    #[inline]
+    pub fn updated(&self) -> Instant {
+        self.updated
+    }
+}
+
+/// A neighbor cache keyed on IP addresses of type `A`.
+pub struct NeighCache<A> {
+    entries: HashMap<A, NeighEntry>,
+    config: NeighConfig,
+}
+
+impl<A: Hash + Eq + Copy> NeighCache<A> {
+    /// Create an empty cache.
+    pub fn new(config: NeighConfig) -> Self {
+        Self {
+            entries: HashMap::new(),
+            config,
+        }
+    }
+
+    /// Returns the timing parameters of the cache.
+    #[inline]
+    pub fn config(&self) -> &NeighConfig {
+        &self.config
+    }
+
+    /// Returns the number of entries, including the incomplete ones.
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    /// Returns true if the cache contains no entries.
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// Returns the entry of `addr` without updating its state.
+    #[inline]
+    pub fn get(&self, addr: A) -> Option<&NeighEntry> {
+        self.entries.get(&addr)
+    }
+
+    /// Returns an iterator over all the entries.
+    pub fn iter(&self) -> impl Iterator<Item = (&A, &NeighEntry)> {
+        self.entries.iter()
+    }
+
+    /// Look up the MAC address of `addr` for sending a packet.
+    ///
+    /// If the address is unknown, an incomplete entry is created and a
+    /// solicitation is scheduled, and `None` is returned. Using a stale entry
+    /// schedules a unicast solicitation to verify it.
+    pub fn lookup(&mut self, addr: A, now: Instant) -> Option<MacAddr> {
+        let config = &self.config;
+        match self.entries.entry(addr) {
+            Entry::Vacant(vacant) => {
+                vacant.insert(NeighEntry {
+                    mac: None,
+                    state: NeighState::Incomplete,
+                    updated: now,
+                    next_probe: now,
+                    probes: 0,
+                    in_use: true,
+                });
+                None
+            }
+            Entry::Occupied(mut occupied) => {
+                let entry = occupied.get_mut();
+                age(entry, config, now);
+                if entry.state == NeighState::Stale && !entry.in_use {
+                    entry.in_use = true;
+                    entry.next_probe = now;
+                    entry.probes = 0;
+                }
+                entry.mac
+            }
+        }
+    }
+
+    /// Record a positive confirmation from the neighbor, e.g. an ARP reply or a
+    /// solicited neighbor advertisement. The entry becomes reachable.
+    pub fn confirm(&mut self, addr: A, mac: MacAddr, now: Instant) {
+        self.entries.insert(
+            addr,
+            NeighEntry {
+                mac: Some(mac),
+                state: NeighState::Reachable,
+                updated: now,
+                next_probe: now,
+                probes: 0,
+                in_use: false,
+            },
+        );
+    }
+
+    /// Record an unconfirmed MAC address of the neighbor, e.g. from an ARP
+    /// request sent by the neighbor or an unsolicited advertisement.
+    ///
+    /// A new or incomplete entry becomes stale with the MAC address. An existing
+    /// entry becomes stale only if the MAC address changes.
+    pub fn update(&mut self, addr: A, mac: MacAddr, now: Instant) {
+        match self.entries.entry(addr) {
+            Entry::Vacant(vacant) => {
+                vacant.insert(NeighEntry {
+                    mac: Some(mac),
+                    state: NeighState::Stale,
+                    updated: now,
+                    next_probe: now,
+                    probes: 0,
+                    in_use: false,
+                });
+            }
+            Entry::Occupied(mut occupied) => {
+                let entry = occupied.get_mut();
+                if entry.mac != Some(mac) {
+                    // Packets waiting for an incomplete entry can be sent now, so
+                    // keep it in use to verify the address.
+                    let in_use = entry.state == NeighState::Incomplete;
+                    *entry = NeighEntry {
+                        mac: Some(mac),
+                        state: NeighState::Stale,
+                        updated: now,
+                        next_probe: now,
+                        probes: 0,
+                        in_use,
+                    };
+                }
+            }
+        }
+    }
+
+    /// Remove the entry of `addr`, returning it if it exists.
+    #[inline]
+    pub fn remove(&mut self, addr: A) -> Option<NeighEntry> {
+        self.entries.remove(&addr)
+    }
+
+    /// Remove all the entries.
+    #[inline]
+    pub fn clear(&mut self) {
+        self.entries.clear()
+    }
+
+    /// Advance the state machine of all the entries to `now`.
+    ///
+    /// `hook` is called for every solicitation that is due. Entries that do not
+    /// answer `max_probes` solicitations, and stale entries that have not been
+    /// used for `gc_stale_time`, are removed.
+    ///
+    /// This method walks through the whole cache, so it is supposed to be
+    /// called periodically, e.g. with a timer of `retrans_time`.
+    pub fn poll<F: FnMut(NeighProbe<A>)>(&mut self, now: Instant, mut hook: F) {
+        let config = &self.config;
+        self.entries.retain(|addr, entry| {
+            age(entry, config, now);
+
+            let probing = match entry.state {
+                NeighState::Incomplete => true,
+                NeighState::Stale if entry.in_use => true,
+                NeighState::Stale => {
+                    return now.saturating_cycles_since(entry.updated) < config.gc_stale_time;
+                }
+                NeighState::Reachable => false,
+            };
+            if !probing || now < entry.next_probe {
+                return true;
+            }
+            if entry.probes >= config.max_probes {
+                return false;
+            }
+
+            hook(NeighProbe {
+                addr: *addr,
+                target: entry.mac,
+                attempt: entry.probes,
+            });
+            entry.probes += 1;
+            entry.next_probe = now + config.retrans_time;
+            true
+        });
+    }
+}
+
+// Turn a reachable entry into a stale one once it has not been confirmed for
+// `reachable_time`.
+#[inline]
+fn age(entry: &mut NeighEntry, config: &NeighConfig, now: Instant) {
+    if entry.state == NeighState::Reachable
+        && now.saturating_cycles_since(entry.updated) >= config.reachable_time
+    {
+        entry.state = NeighState::Stale;
+        entry.updated = now;
+        entry.in_use = false;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ipv4::Ipv4Addr;
+
+    const MAC: MacAddr = MacAddr([0x00, 0x0b, 0x86, 0x64, 0x8b, 0xa0]);
+    const ADDR: Ipv4Addr = Ipv4Addr([192, 168, 1, 2]);
+
+    fn probes(cache: &mut NeighCache<Ipv4Addr>, now: Instant) -> Vec<NeighProbe<Ipv4Addr>> {
+        let mut res = Vec::new();
+        cache.poll(now, |probe| res.push(probe));
+        res
+    }
+
+    #[test]
+    fn incomplete_timeout() {
+        let config = NeighConfig::default();
+        let mut cache = NeighCache::new(config);
+        let now = Instant::now();
+
+        assert_eq!(cache.lookup(ADDR, now), None);
+        assert_eq!(cache.get(ADDR).unwrap().state(), NeighState::Incomplete);
+
+        let mut t = now;
+        for attempt in 0..config.max_probes {
+            let res = probes(&mut cache, t);
+            assert_eq!(res.len(), 1);
+            assert_eq!(res[0].target, None);
+            assert_eq!(res[0].attempt, attempt);
+            // No duplicated solicitation before the retransmission timer fires.
+            assert!(probes(&mut cache, t).is_empty());
+            t += config.retrans_time;
+        }
+
+        assert!(probes(&mut cache, t).is_empty());
+        assert!(cache.is_empty());
+    }
+
+    #[test]
+    fn reachable_to_stale() {
+        let config = NeighConfig::default();
+        let mut cache = NeighCache::new(config);
+        let now = Instant::now();
+
+        cache.confirm(ADDR, MAC, now);
+        assert_eq!(cache.lookup(ADDR, now), Some(MAC));
+        assert!(probes(&mut cache, now).is_empty());
+
+        // The entry becomes stale but remains usable, and using it triggers
+        // unicast solicitations.
+        let t = now + config.reachable_time;
+        assert_eq!(cache.lookup(ADDR, t), Some(MAC));
+        assert_eq!(cache.get(ADDR).unwrap().state(), NeighState::Stale);
+        let res = probes(&mut cache, t);
+        assert_eq!(res.len(), 1);
+        assert_eq!(res[0].target, Some(MAC));
+
+        cache.confirm(ADDR, MAC, t);
+        assert_eq!(cache.get(ADDR).unwrap().state(), NeighState::Reachable);
+        assert!(probes(&mut cache, t + config.retrans_time).is_empty());
+    }
+
+    #[test]
+    fn unsolicited_update() {
+        let config = NeighConfig::default();
+        let mut cache = NeighCache::new(config);
+        let now = Instant::now();
+
+        cache.update(ADDR, MAC, now);
+        assert_eq!(cache.get(ADDR).unwrap().state(), NeighState::Stale);
+
+        // An unused stale entry is not verified, and is collected eventually.
+        assert!(probes(&mut cache, now).is_empty());
+        assert_eq!(cache.len(), 1);
+        assert!(probes(&mut cache, now + config.gc_stale_time).is_empty());
+        assert!(cache.is_empty());
+
+        // The same MAC address does not downgrade a reachable entry.
+        cache.confirm(ADDR, MAC, now);
+        cache.update(ADDR, MAC, now);
+        assert_eq!(cache.get(ADDR).unwrap().state(), NeighState::Reachable);
+
+        let new_mac = MacAddr([0x00, 0x50, 0x56, 0xae, 0x76, 0xf5]);
+        cache.update(ADDR, new_mac, now);
+        assert_eq!(cache.get(ADDR).unwrap().state(), NeighState::Stale);
+        assert_eq!(cache.lookup(ADDR, now), Some(new_mac));
+    }
+}
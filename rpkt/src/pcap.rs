@@ -0,0 +1,136 @@
+//! A minimal writer of the classic pcap file format.
+
+use std::io::{self, Write};
+
+use crate::ether::calc_fcs;
+
+/// The link type of Ethernet frames.
+pub const LINKTYPE_ETHERNET: u32 = 1;
+
+/// The link type of Ethernet frames that end with the 4-byte FCS. The upper
+/// bits of the link type flag the FCS and carry its length in 16-bit words.
+pub const LINKTYPE_ETHERNET_FCS: u32 = (2 << 29) | (1 << 28) | LINKTYPE_ETHERNET;
+
+/// The maximum number of bytes captured from a frame.
+pub const SNAPLEN: u32 = 65535;
+
+/// Writes frames into a pcap file with microsecond timestamps.
+///
+/// # Examples
+/// ```
+/// use rpkt::pcap::PcapWriter;
+///
+/// let mut writer = PcapWriter::new(Vec::new()).unwrap();
+/// writer.write_frame(0, &[0; 60]).unwrap();
+/// let file = writer.into_inner();
+/// assert_eq!(file.len(), 24 + 16 + 60);
+/// ```
+#[derive(Debug)]
+pub struct PcapWriter<W: Write> {
+    inner: W,
+}
+
+impl<W: Write> PcapWriter<W> {
+    /// Create a writer of Ethernet frames, writing the global header into
+    /// `inner`.
+    pub fn new(inner: W) -> io::Result<Self> {
+        Self::with_linktype(inner, LINKTYPE_ETHERNET)
+    }
+
+    /// Create a writer of frames of `linktype`.
+    pub fn with_linktype(mut inner: W, linktype: u32) -> io::Result<Self> {
+        let mut header = [0; 24];
+        header[0..4].copy_from_slice(&0xa1b2c3d4u32.to_le_bytes());
+        header[4..6].copy_from_slice(&2u16.to_le_bytes());
+        header[6..8].copy_from_slice(&4u16.to_le_bytes());
+        header[16..20].copy_from_slice(&SNAPLEN.to_le_bytes());
+        header[20..24].copy_from_slice(&linktype.to_le_bytes());
+        inner.write_all(&header)?;
+        Ok(Self { inner })
+    }
+
+    /// Write a frame captured at `ts_micros` microseconds since the Unix epoch.
+    ///
+    /// Frames longer than `SNAPLEN` are truncated, while the original length
+    /// is recorded.
+    pub fn write_frame(&mut self, ts_micros: u64, frame: &[u8]) -> io::Result<()> {
+        let orig_len = u32::try_from(frame.len())
+            .map_err(|_| io::Error::new(io::ErrorKind::InvalidInput, "frame is too large"))?;
+        self.write_truncated(ts_micros, frame, orig_len)
+    }
+
+    /// Write the first bytes of a frame of `orig_len` bytes, e.g. a frame
+    /// that was truncated when it was sampled.
+    pub fn write_truncated(
+        &mut self,
+        ts_micros: u64,
+        data: &[u8],
+        orig_len: u32,
+    ) -> io::Result<()> {
+        self.write_record(ts_micros, [data, &[]], orig_len)
+    }
+
+    /// Write an Ethernet frame followed by its FCS, calculated with
+    /// [`calc_fcs`], into a writer created with [`LINKTYPE_ETHERNET_FCS`].
+    pub fn write_frame_with_fcs(&mut self, ts_micros: u64, frame: &[u8]) -> io::Result<()> {
+        let fcs = calc_fcs(frame).to_le_bytes();
+        let orig_len = u32::try_from(frame.len() + fcs.len())
+            .map_err(|_| io::Error::new(io::ErrorKind::InvalidInput, "frame is too large"))?;
+        self.write_record(ts_micros, [frame, &fcs], orig_len)
+    }
+
+    // Write a record of the bytes of `parts` in order, truncated to `orig_len`
+    // and `SNAPLEN` bytes.
+    fn write_record(&mut self, ts_micros: u64, parts: [&[u8]; 2], orig_len: u32) -> io::Result<()> {
+        let data_len = parts[0].len() + parts[1].len();
+        let incl_len = u32::try_from(data_len)
+            .unwrap_or(u32::MAX)
+            .min(orig_len)
+            .min(SNAPLEN);
+
+        let mut header = [0; 16];
+        header[0..4].copy_from_slice(&((ts_micros / 1_000_000) as u32).to_le_bytes());
+        header[4..8].copy_from_slice(&((ts_micros % 1_000_000) as u32).to_le_bytes());
+        header[8..12].copy_from_slice(&incl_len.to_le_bytes());
+        header[12..16].copy_from_slice(&orig_len.to_le_bytes());
+        self.inner.write_all(&header)?;
+
+        let mut left = incl_len as usize;
+        for part in parts {
+            let len = part.len().min(left);
+            self.inner.write_all(&part[..len])?;
+            left -= len;
+        }
+        Ok(())
+    }
+
+    /// Flush the underlying writer.
+    pub fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+
+    /// Returns the underlying writer.
+    pub fn into_inner(self) -> W {
+        self.inner
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ether::verify_fcs;
+
+    #[test]
+    fn frame_with_fcs() {
+        let mut writer = PcapWriter::with_linktype(Vec::new(), LINKTYPE_ETHERNET_FCS).unwrap();
+        writer.write_frame_with_fcs(1_000_001, &[0xab; 60]).unwrap();
+        let file = writer.into_inner();
+
+        assert_eq!(file[20..24], 0x5000_0001u32.to_le_bytes());
+        let record = &file[24..];
+        assert_eq!(record[0..8], [1, 0, 0, 0, 1, 0, 0, 0]);
+        assert_eq!(record[8..16], [64, 0, 0, 0, 64, 0, 0, 0]);
+        assert_eq!(record.len(), 16 + 64);
+        assert!(verify_fcs(&record[16..]));
+    }
+}
@@ -0,0 +1,223 @@
+//! Well-known UDP and TCP ports, and a table to dispatch on them.
+//!
+//! [`PortTable`] maps a transport protocol and a port to any value, e.g. an
+//! [`AppProtocol`] to identify the payload, or a parser function to process
+//! it. [`PortTable::well_known`] is pre-populated with the IANA assignments of
+//! the protocols that are commonly found in packet processing pipelines, and
+//! both kinds of tables can be extended with [`PortTable::insert`].
+//!
+//! # Examples
+//! ```
+//! use rpkt::ipv4::IpProtocol;
+//! use rpkt::ports::{AppProtocol, PortTable};
+//!
+//! let mut table = PortTable::well_known();
+//! assert_eq!(
+//!     table.lookup(IpProtocol::UDP, 49152, 4789),
+//!     Some(&AppProtocol::Vxlan)
+//! );
+//!
+//! // A site-specific port carrying VXLAN.
+//! table.insert(IpProtocol::UDP, 8472, AppProtocol::Vxlan);
+//! assert_eq!(
+//!     table.lookup(IpProtocol::UDP, 8472, 49152),
+//!     Some(&AppProtocol::Vxlan)
+//! );
+//!
+//! // Dispatch to parser functions instead.
+//! let mut parsers: PortTable<fn(&[u8]) -> bool> = PortTable::new();
+//! parsers.insert(IpProtocol::UDP, 53, |payload| payload.len() >= 12);
+//! let parse = parsers.lookup(IpProtocol::UDP, 53, 49152).unwrap();
+//! assert!(!parse(&[0; 4]));
+//! ```
+
+use std::collections::BTreeMap;
+
+use crate::ipv4::IpProtocol;
+
+/// An application protocol identified by a well-known port.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum AppProtocol {
+    /// DNS, UDP and TCP port 53.
+    Dns,
+    /// DHCP server, UDP port 67.
+    DhcpServer,
+    /// DHCP client, UDP port 68.
+    DhcpClient,
+    /// HTTP, TCP port 80.
+    Http,
+    /// NTP, UDP port 123.
+    Ntp,
+    /// BGP, TCP port 179.
+    Bgp,
+    /// IKE, UDP port 500.
+    Ike,
+    /// PTP event messages, UDP port 319.
+    PtpEvent,
+    /// PTP general messages, UDP port 320.
+    PtpGeneral,
+    /// HTTPS, TCP port 443, or QUIC over UDP port 443.
+    Https,
+    /// DHCPv6 client, UDP port 546.
+    Dhcpv6Client,
+    /// DHCPv6 server, UDP port 547.
+    Dhcpv6Server,
+    /// HSRP, UDP port 1985.
+    Hsrp,
+    /// HSRPv2 over IPv6, UDP port 2029.
+    HsrpV6,
+    /// GTP-C, UDP port 2123.
+    GtpC,
+    /// GTP-U, UDP port 2152.
+    GtpU,
+    /// IKE and ESP encapsulated in UDP for NAT traversal, UDP port 4500.
+    IpsecNatT,
+    /// VXLAN, UDP port 4789.
+    Vxlan,
+    /// Geneve, UDP port 6081.
+    Geneve,
+    /// sFlow, UDP port 6343.
+    Sflow,
+    /// PFCP, UDP port 8805.
+    Pfcp,
+}
+
+/// The IANA assignments registered by [`PortTable::well_known`].
+const WELL_KNOWN: &[(IpProtocol, u16, AppProtocol)] = &[
+    (IpProtocol::UDP, 53, AppProtocol::Dns),
+    (IpProtocol::TCP, 53, AppProtocol::Dns),
+    (IpProtocol::UDP, 67, AppProtocol::DhcpServer),
+    (IpProtocol::UDP, 68, AppProtocol::DhcpClient),
+    (IpProtocol::TCP, 80, AppProtocol::Http),
+    (IpProtocol::UDP, 123, AppProtocol::Ntp),
+    (IpProtocol::TCP, 179, AppProtocol::Bgp),
+    (IpProtocol::UDP, 500, AppProtocol::Ike),
+    (IpProtocol::UDP, 319, AppProtocol::PtpEvent),
+    (IpProtocol::UDP, 320, AppProtocol::PtpGeneral),
+    (IpProtocol::TCP, 443, AppProtocol::Https),
+    (IpProtocol::UDP, 443, AppProtocol::Https),
+    (IpProtocol::UDP, 546, AppProtocol::Dhcpv6Client),
+    (IpProtocol::UDP, 547, AppProtocol::Dhcpv6Server),
+    (IpProtocol::UDP, 1985, AppProtocol::Hsrp),
+    (IpProtocol::UDP, 2029, AppProtocol::HsrpV6),
+    (IpProtocol::UDP, 2123, AppProtocol::GtpC),
+    (IpProtocol::UDP, 2152, AppProtocol::GtpU),
+    (IpProtocol::UDP, 4500, AppProtocol::IpsecNatT),
+    (IpProtocol::UDP, 4789, AppProtocol::Vxlan),
+    (IpProtocol::UDP, 6081, AppProtocol::Geneve),
+    (IpProtocol::UDP, 6343, AppProtocol::Sflow),
+    (IpProtocol::UDP, 8805, AppProtocol::Pfcp),
+];
+
+/// A table that maps a transport protocol and a port to a value of type `V`.
+#[derive(Debug, Clone)]
+pub struct PortTable<V> {
+    entries: BTreeMap<(IpProtocol, u16), V>,
+}
+
+impl<V> Default for PortTable<V> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl PortTable<AppProtocol> {
+    /// Create a table holding the well-known ports of [`AppProtocol`].
+    pub fn well_known() -> Self {
+        let mut table = Self::new();
+        for &(protocol, port, app) in WELL_KNOWN {
+            table.insert(protocol, port, app);
+        }
+        table
+    }
+}
+
+impl<V> PortTable<V> {
+    /// Create an empty table.
+    pub fn new() -> Self {
+        Self {
+            entries: BTreeMap::new(),
+        }
+    }
+
+    /// Register `value` for `port` of `protocol`, returning the value that is
+    /// replaced.
+    pub fn insert(&mut self, protocol: IpProtocol, port: u16, value: V) -> Option<V> {
+        self.entries.insert((protocol, port), value)
+    }
+
+    /// Unregister `port` of `protocol`, returning the registered value.
+    pub fn remove(&mut self, protocol: IpProtocol, port: u16) -> Option<V> {
+        self.entries.remove(&(protocol, port))
+    }
+
+    /// Returns the value registered for `port` of `protocol`.
+    pub fn get(&self, protocol: IpProtocol, port: u16) -> Option<&V> {
+        self.entries.get(&(protocol, port))
+    }
+
+    /// Returns the value for a segment between `src_port` and `dst_port`.
+    ///
+    /// The destination port is tried first, so that a request to a server is
+    /// classified by the port of the server even if the ephemeral source port
+    /// happens to be registered. The source port then classifies the replies.
+    pub fn lookup(&self, protocol: IpProtocol, src_port: u16, dst_port: u16) -> Option<&V> {
+        self.get(protocol, dst_port)
+            .or_else(|| self.get(protocol, src_port))
+    }
+
+    /// Returns the number of registered ports.
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    /// Returns whether no port is registered.
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// Iterate over the registered protocols, ports and values.
+    pub fn iter(&self) -> impl Iterator<Item = (IpProtocol, u16, &V)> {
+        self.entries
+            .iter()
+            .map(|(&(protocol, port), value)| (protocol, port, value))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn ports_lookup() {
+        let mut table = PortTable::well_known();
+        assert_eq!(table.len(), WELL_KNOWN.len());
+        assert_eq!(table.get(IpProtocol::UDP, 2152), Some(&AppProtocol::GtpU));
+        assert_eq!(table.get(IpProtocol::TCP, 2152), None);
+
+        // The destination port wins over the source port.
+        assert_eq!(
+            table.lookup(IpProtocol::UDP, 53, 8805),
+            Some(&AppProtocol::Pfcp)
+        );
+        assert_eq!(
+            table.lookup(IpProtocol::UDP, 53, 49152),
+            Some(&AppProtocol::Dns)
+        );
+        assert_eq!(table.lookup(IpProtocol::UDP, 49152, 49153), None);
+
+        assert_eq!(
+            table.insert(IpProtocol::UDP, 4789, AppProtocol::Geneve),
+            Some(AppProtocol::Vxlan)
+        );
+        assert_eq!(
+            table.remove(IpProtocol::UDP, 4789),
+            Some(AppProtocol::Geneve)
+        );
+        assert_eq!(table.lookup(IpProtocol::UDP, 49152, 4789), None);
+        assert!(table
+            .iter()
+            .all(|(protocol, port, _)| protocol != IpProtocol::UDP || port != 4789));
+    }
+}
@@ -0,0 +1,660 @@
+//! The control protocols of PPP, carried by a PPPoE session.
+//!
+//! The PPP frame of a [`PppoeSession`](crate::pppoe::PppoeSession) starts
+//! with a [`PppProtocol`] field. The information field of LCP (RFC 1661),
+//! IPCP (RFC 1332), IPv6CP (RFC 5072), PAP (RFC 1334) and CHAP (RFC 1994)
+//! frames starts with the same 4-byte header of a code, an identifier and a
+//! length, read and written with [`PppControlHeader`]. The configuration
+//! packets of LCP, IPCP and IPv6CP carry a list of options with 1-byte types
+//! and 1-byte lengths, iterated by [`PppControl::options`], while PAP and
+//! CHAP carry their own fields, read with [`PapPacket`] and [`ChapPacket`].
+//!
+//! [`PppFrame::parse`] dispatches the information field on the protocol
+//! field.
+//!
+//! # Examples
+//! ```
+//! use rpkt::ppp::*;
+//! use rpkt::tlv::TlvWriter;
+//!
+//! // An LCP configure request with an MRU of 1492.
+//! let mut buf = [0; 8];
+//! let mut header = PppControlHeader::new_unchecked(&mut buf[..]);
+//! header.set_code(PppCode::CONFIGURE_REQUEST.into());
+//! header.set_identifier(1);
+//! header.set_length(8);
+//! let mut writer = TlvWriter::new(&mut buf[PPP_CONTROL_HEADER_LEN..], PPP_OPTION_FORMAT);
+//! writer.write(u8::from(LcpOption::MRU).into(), &1492u16.to_be_bytes()).unwrap();
+//!
+//! match PppFrame::parse(PppProtocol::LCP, &buf).unwrap() {
+//!     PppFrame::Lcp(lcp) => {
+//!         assert_eq!(lcp.code(), PppCode::CONFIGURE_REQUEST);
+//!         assert_eq!(lcp.mru(), Some(1492));
+//!     }
+//!     _ => unreachable!(),
+//! }
+//! ```
+
+use byteorder::{ByteOrder, NetworkEndian};
+
+use crate::ipv4::Ipv4Addr;
+use crate::tlv::{TlvFormat, TlvIter};
+
+enum_sim! {
+    /// The protocol field of a PPP frame.
+    ///
+    /// See https://www.iana.org/assignments/ppp-numbers/ppp-numbers.xhtml
+    pub struct PppProtocol (u16) {
+        IPV4 = 0x0021,
+        IPV6 = 0x0057,
+        IPCP = 0x8021,
+        IPV6CP = 0x8057,
+        LCP = 0xc021,
+        PAP = 0xc023,
+        CHAP = 0xc223,
+    }
+}
+
+/// The length of the header of the control packets.
+pub const PPP_CONTROL_HEADER_LEN: usize = 4;
+
+/// The layout of the configuration options of LCP, IPCP and IPv6CP, whose
+/// length counts the type and length fields.
+pub const PPP_OPTION_FORMAT: TlvFormat = TlvFormat::RADIUS;
+
+enum_sim! {
+    /// The code of an LCP, IPCP or IPv6CP packet.
+    ///
+    /// IPCP and IPv6CP only use the codes from 1 to 7.
+    pub struct PppCode (u8) {
+        CONFIGURE_REQUEST = 1,
+        CONFIGURE_ACK = 2,
+        CONFIGURE_NAK = 3,
+        CONFIGURE_REJECT = 4,
+        TERMINATE_REQUEST = 5,
+        TERMINATE_ACK = 6,
+        CODE_REJECT = 7,
+        PROTOCOL_REJECT = 8,
+        ECHO_REQUEST = 9,
+        ECHO_REPLY = 10,
+        DISCARD_REQUEST = 11,
+        IDENTIFICATION = 12,
+        TIME_REMAINING = 13,
+    }
+}
+
+impl PppCode {
+    /// Returns whether the data of the packet is a list of options.
+    #[inline]
+    pub fn has_options(&self) -> bool {
+        (1..=4).contains(&self.0)
+    }
+}
+
+enum_sim! {
+    /// The type of an LCP configuration option.
+    pub struct LcpOption (u8) {
+        MRU = 1,
+        ACCM = 2,
+        AUTH_PROTOCOL = 3,
+        QUALITY_PROTOCOL = 4,
+        MAGIC_NUMBER = 5,
+        PROTOCOL_COMPRESSION = 7,
+        ADDRESS_CONTROL_COMPRESSION = 8,
+    }
+}
+
+enum_sim! {
+    /// The type of an IPCP configuration option, including the DNS and NBNS
+    /// extensions of RFC 1877.
+    pub struct IpcpOption (u8) {
+        IP_COMPRESSION = 2,
+        IP_ADDRESS = 3,
+        PRIMARY_DNS = 129,
+        PRIMARY_NBNS = 130,
+        SECONDARY_DNS = 131,
+        SECONDARY_NBNS = 132,
+    }
+}
+
+enum_sim! {
+    /// The type of an IPv6CP configuration option.
+    pub struct Ipv6cpOption (u8) {
+        INTERFACE_IDENTIFIER = 1,
+        IPV6_COMPRESSION = 2,
+    }
+}
+
+enum_sim! {
+    /// The code of a PAP packet.
+    pub struct PapCode (u8) {
+        AUTHENTICATE_REQUEST = 1,
+        AUTHENTICATE_ACK = 2,
+        AUTHENTICATE_NAK = 3,
+    }
+}
+
+enum_sim! {
+    /// The code of a CHAP packet.
+    pub struct ChapCode (u8) {
+        CHALLENGE = 1,
+        RESPONSE = 2,
+        SUCCESS = 3,
+        FAILURE = 4,
+    }
+}
+
+header_field_range_accessors! {
+    (length, length_mut, 2..4),
+}
+
+header_field_val_accessors! {
+    (code, code_mut, 0),
+    (identifier, identifier_mut, 1),
+}
+
+/// The header of an LCP, IPCP, IPv6CP, PAP or CHAP packet.
+///
+/// The code is kept as a `u8`, as its meaning depends on the protocol.
+#[derive(Clone, Copy, Debug)]
+pub struct PppControlHeader<T> {
+    buf: T,
+}
+
+impl<T: AsRef<[u8]>> PppControlHeader<T> {
+    #[inline]
+    pub fn new(buf: T) -> Result<Self, T> {
+        if buf.as_ref().len() >= PPP_CONTROL_HEADER_LEN {
+            Ok(Self { buf })
+        } else {
+            Err(buf)
+        }
+    }
+
+    #[inline]
+    pub fn new_unchecked(buf: T) -> Self {
+        Self { buf }
+    }
+
+    #[inline]
+    pub fn as_bytes(&self) -> &[u8] {
+        &self.buf.as_ref()[0..PPP_CONTROL_HEADER_LEN]
+    }
+
+    #[inline]
+    pub fn to_owned(&self) -> PppControlHeader<[u8; PPP_CONTROL_HEADER_LEN]> {
+        let mut buf = [0; PPP_CONTROL_HEADER_LEN];
+        buf.copy_from_slice(self.as_bytes());
+        PppControlHeader { buf }
+    }
+
+    #[inline]
+    pub fn code(&self) -> u8 {
+        *code(self.buf.as_ref())
+    }
+
+    #[inline]
+    pub fn identifier(&self) -> u8 {
+        *identifier(self.buf.as_ref())
+    }
+
+    /// Returns the length of the packet, including the header.
+    #[inline]
+    pub fn length(&self) -> u16 {
+        NetworkEndian::read_u16(length(self.buf.as_ref()))
+    }
+}
+
+impl<T: AsMut<[u8]>> PppControlHeader<T> {
+    #[inline]
+    pub fn set_code(&mut self, value: u8) {
+        *code_mut(self.buf.as_mut()) = value;
+    }
+
+    #[inline]
+    pub fn set_identifier(&mut self, value: u8) {
+        *identifier_mut(self.buf.as_mut()) = value;
+    }
+
+    #[inline]
+    pub fn set_length(&mut self, value: u16) {
+        NetworkEndian::write_u16(length_mut(self.buf.as_mut()), value)
+    }
+}
+
+// Returns the packet in `buf` without the padding after its length, and the
+// header.
+fn control_packet(buf: &[u8]) -> Option<(&[u8], PppControlHeader<&[u8]>)> {
+    let header = PppControlHeader::new(buf).ok()?;
+    let len = usize::from(header.length());
+    if len < PPP_CONTROL_HEADER_LEN || len > buf.len() {
+        return None;
+    }
+    Some((&buf[..len], header))
+}
+
+/// An LCP, IPCP or IPv6CP packet.
+#[derive(Debug, Clone, Copy)]
+pub struct PppControl<'a> {
+    buf: &'a [u8],
+}
+
+impl<'a> PppControl<'a> {
+    /// Parse the information field of a PPP frame, without the padding after
+    /// the length of the packet.
+    ///
+    /// Returns `None` if the length is invalid, or if the options of a
+    /// configuration packet are malformed.
+    pub fn parse(buf: &'a [u8]) -> Option<Self> {
+        let (buf, header) = control_packet(buf)?;
+        let packet = Self { buf };
+        if PppCode::from(header.code()).has_options()
+            && !TlvIter::check_bytes(packet.data(), PPP_OPTION_FORMAT)
+        {
+            return None;
+        }
+        Some(packet)
+    }
+
+    #[inline]
+    pub fn header(&self) -> PppControlHeader<&'a [u8]> {
+        PppControlHeader::new_unchecked(self.buf)
+    }
+
+    #[inline]
+    pub fn code(&self) -> PppCode {
+        self.header().code().into()
+    }
+
+    #[inline]
+    pub fn identifier(&self) -> u8 {
+        self.header().identifier()
+    }
+
+    /// Returns the data after the header.
+    #[inline]
+    pub fn data(&self) -> &'a [u8] {
+        &self.buf[PPP_CONTROL_HEADER_LEN..]
+    }
+
+    /// Returns the options of a configuration packet, or an empty iterator for
+    /// the other codes.
+    pub fn options(&self) -> TlvIter<'a> {
+        let data = if self.code().has_options() {
+            self.data()
+        } else {
+            &[]
+        };
+        TlvIter::new(data, PPP_OPTION_FORMAT)
+    }
+
+    /// Returns the value of the first option of `option_type`.
+    pub fn option(&self, option_type: u8) -> Option<&'a [u8]> {
+        self.options()
+            .find(|tlv| tlv.tlv_type == u32::from(option_type))
+            .map(|tlv| tlv.value)
+    }
+
+    /// Returns the maximum receive unit of an LCP configuration packet.
+    pub fn mru(&self) -> Option<u16> {
+        self.option(LcpOption::MRU.into())
+            .filter(|value| value.len() == 2)
+            .map(NetworkEndian::read_u16)
+    }
+
+    /// Returns the protocol of the authentication protocol option of an LCP
+    /// configuration packet, e.g. [`PppProtocol::CHAP`].
+    pub fn auth_protocol(&self) -> Option<PppProtocol> {
+        self.option(LcpOption::AUTH_PROTOCOL.into())
+            .filter(|value| value.len() >= 2)
+            .map(|value| NetworkEndian::read_u16(value).into())
+    }
+
+    /// Returns the magic number of an LCP configuration option, or of an LCP
+    /// echo request, echo reply or discard request.
+    pub fn magic_number(&self) -> Option<u32> {
+        match self.code() {
+            PppCode::ECHO_REQUEST | PppCode::ECHO_REPLY | PppCode::DISCARD_REQUEST => {
+                self.data().get(0..4).map(NetworkEndian::read_u32)
+            }
+            _ => self
+                .option(LcpOption::MAGIC_NUMBER.into())
+                .filter(|value| value.len() == 4)
+                .map(NetworkEndian::read_u32),
+        }
+    }
+
+    /// Returns the address of the IP address option of an IPCP configuration
+    /// packet.
+    pub fn ip_address(&self) -> Option<Ipv4Addr> {
+        self.option(IpcpOption::IP_ADDRESS.into())
+            .filter(|value| value.len() == 4)
+            .map(Ipv4Addr::from_bytes)
+    }
+
+    /// Returns the interface identifier option of an IPv6CP configuration
+    /// packet.
+    pub fn interface_identifier(&self) -> Option<[u8; 8]> {
+        self.option(Ipv6cpOption::INTERFACE_IDENTIFIER.into())
+            .and_then(|value| value.try_into().ok())
+    }
+
+    /// Returns the protocol rejected by an LCP protocol reject.
+    pub fn rejected_protocol(&self) -> Option<PppProtocol> {
+        if self.code() != PppCode::PROTOCOL_REJECT {
+            return None;
+        }
+        self.data()
+            .get(0..2)
+            .map(|data| NetworkEndian::read_u16(data).into())
+    }
+}
+
+/// A PAP packet.
+#[derive(Debug, Clone, Copy)]
+pub struct PapPacket<'a> {
+    buf: &'a [u8],
+}
+
+impl<'a> PapPacket<'a> {
+    /// Parse the information field of a PPP frame.
+    ///
+    /// Returns `None` if the length or the length-prefixed fields are
+    /// invalid.
+    pub fn parse(buf: &'a [u8]) -> Option<Self> {
+        let (buf, _) = control_packet(buf)?;
+        let packet = Self { buf };
+        match packet.code() {
+            PapCode::AUTHENTICATE_REQUEST => packet.password()?,
+            _ => packet.message()?,
+        };
+        Some(packet)
+    }
+
+    #[inline]
+    pub fn header(&self) -> PppControlHeader<&'a [u8]> {
+        PppControlHeader::new_unchecked(self.buf)
+    }
+
+    #[inline]
+    pub fn code(&self) -> PapCode {
+        self.header().code().into()
+    }
+
+    #[inline]
+    pub fn identifier(&self) -> u8 {
+        self.header().identifier()
+    }
+
+    /// Returns the peer identifier of an authenticate request.
+    pub fn peer_id(&self) -> Option<&'a [u8]> {
+        if self.code() != PapCode::AUTHENTICATE_REQUEST {
+            return None;
+        }
+        length_prefixed(&self.buf[PPP_CONTROL_HEADER_LEN..]).map(|(peer_id, _)| peer_id)
+    }
+
+    /// Returns the password of an authenticate request.
+    pub fn password(&self) -> Option<&'a [u8]> {
+        if self.code() != PapCode::AUTHENTICATE_REQUEST {
+            return None;
+        }
+        let (_, rest) = length_prefixed(&self.buf[PPP_CONTROL_HEADER_LEN..])?;
+        length_prefixed(rest).map(|(password, _)| password)
+    }
+
+    /// Returns the message of an authenticate ack or nak.
+    pub fn message(&self) -> Option<&'a [u8]> {
+        if self.code() == PapCode::AUTHENTICATE_REQUEST {
+            return None;
+        }
+        match &self.buf[PPP_CONTROL_HEADER_LEN..] {
+            // The message length may be left out of an empty message.
+            [] => Some(&[]),
+            data => length_prefixed(data).map(|(message, _)| message),
+        }
+    }
+}
+
+// Splits a field prefixed with its 1-byte length from the rest of `buf`.
+fn length_prefixed(buf: &[u8]) -> Option<(&[u8], &[u8])> {
+    let (&len, rest) = buf.split_first()?;
+    let len = usize::from(len);
+    (len <= rest.len()).then(|| rest.split_at(len))
+}
+
+/// A CHAP packet.
+#[derive(Debug, Clone, Copy)]
+pub struct ChapPacket<'a> {
+    buf: &'a [u8],
+}
+
+impl<'a> ChapPacket<'a> {
+    /// Parse the information field of a PPP frame.
+    ///
+    /// Returns `None` if the length or the value size of a challenge or a
+    /// response is invalid.
+    pub fn parse(buf: &'a [u8]) -> Option<Self> {
+        let (buf, _) = control_packet(buf)?;
+        let packet = Self { buf };
+        if packet.is_challenge_or_response() {
+            length_prefixed(packet.data())?;
+        }
+        Some(packet)
+    }
+
+    #[inline]
+    pub fn header(&self) -> PppControlHeader<&'a [u8]> {
+        PppControlHeader::new_unchecked(self.buf)
+    }
+
+    #[inline]
+    pub fn code(&self) -> ChapCode {
+        self.header().code().into()
+    }
+
+    #[inline]
+    pub fn identifier(&self) -> u8 {
+        self.header().identifier()
+    }
+
+    /// Returns the challenge value of a challenge, or the response value of a
+    /// response.
+    pub fn value(&self) -> Option<&'a [u8]> {
+        if !self.is_challenge_or_response() {
+            return None;
+        }
+        length_prefixed(self.data()).map(|(value, _)| value)
+    }
+
+    /// Returns the name of the sender of a challenge or a response.
+    pub fn name(&self) -> Option<&'a [u8]> {
+        if !self.is_challenge_or_response() {
+            return None;
+        }
+        length_prefixed(self.data()).map(|(_, name)| name)
+    }
+
+    /// Returns the message of a success or a failure.
+    pub fn message(&self) -> Option<&'a [u8]> {
+        (!self.is_challenge_or_response()).then(|| self.data())
+    }
+
+    #[inline]
+    fn data(&self) -> &'a [u8] {
+        &self.buf[PPP_CONTROL_HEADER_LEN..]
+    }
+
+    #[inline]
+    fn is_challenge_or_response(&self) -> bool {
+        matches!(self.code(), ChapCode::CHALLENGE | ChapCode::RESPONSE)
+    }
+}
+
+/// The information field of a PPP frame, dispatched on the protocol field.
+#[derive(Debug, Clone, Copy)]
+pub enum PppFrame<'a> {
+    Lcp(PppControl<'a>),
+    Ipcp(PppControl<'a>),
+    Ipv6cp(PppControl<'a>),
+    Pap(PapPacket<'a>),
+    Chap(ChapPacket<'a>),
+    /// An IPv4 packet, not parsed.
+    Ipv4(&'a [u8]),
+    /// An IPv6 packet, not parsed.
+    Ipv6(&'a [u8]),
+    Other(PppProtocol, &'a [u8]),
+}
+
+impl<'a> PppFrame<'a> {
+    /// Parse the information field `buf` of a PPP frame of `protocol`.
+    ///
+    /// Returns `None` if a control packet is malformed.
+    pub fn parse(protocol: PppProtocol, buf: &'a [u8]) -> Option<Self> {
+        let frame = match protocol {
+            PppProtocol::LCP => PppFrame::Lcp(PppControl::parse(buf)?),
+            PppProtocol::IPCP => PppFrame::Ipcp(PppControl::parse(buf)?),
+            PppProtocol::IPV6CP => PppFrame::Ipv6cp(PppControl::parse(buf)?),
+            PppProtocol::PAP => PppFrame::Pap(PapPacket::parse(buf)?),
+            PppProtocol::CHAP => PppFrame::Chap(ChapPacket::parse(buf)?),
+            PppProtocol::IPV4 => PppFrame::Ipv4(buf),
+            PppProtocol::IPV6 => PppFrame::Ipv6(buf),
+            _ => PppFrame::Other(protocol, buf),
+        };
+        Some(frame)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tlv::TlvWriter;
+
+    #[test]
+    fn lcp() {
+        // A configure request with an MRU of 1492, CHAP with MD5, and a magic
+        // number, followed by 2 bytes of padding.
+        let bytes = [
+            0x01, 0x07, 0x00, 0x13, 0x01, 0x04, 0x05, 0xd4, 0x03, 0x05, 0xc2, 0x23, 0x05, 0x05,
+            0x06, 0x12, 0x34, 0x56, 0x78, 0x00, 0x00,
+        ];
+        let lcp = PppControl::parse(&bytes).unwrap();
+        assert_eq!(
+            (lcp.code(), lcp.identifier()),
+            (PppCode::CONFIGURE_REQUEST, 7)
+        );
+        assert_eq!(lcp.data().len(), 15);
+        assert_eq!(lcp.mru(), Some(1492));
+        assert_eq!(lcp.auth_protocol(), Some(PppProtocol::CHAP));
+        assert_eq!(
+            lcp.option(LcpOption::AUTH_PROTOCOL.into()),
+            Some(&[0xc2, 0x23, 0x05][..])
+        );
+        assert_eq!(lcp.magic_number(), Some(0x12345678));
+        assert_eq!(lcp.options().count(), 3);
+        assert_eq!(lcp.rejected_protocol(), None);
+
+        // A malformed option, and a length past the buffer.
+        let mut bad = bytes;
+        bad[5] = 0x01;
+        assert!(PppControl::parse(&bad).is_none());
+        assert!(PppControl::parse(&bytes[..18]).is_none());
+
+        let echo = [0x09, 0x02, 0x00, 0x08, 0xca, 0xfe, 0xba, 0xbe];
+        let lcp = PppControl::parse(&echo).unwrap();
+        assert_eq!(lcp.code(), PppCode::ECHO_REQUEST);
+        assert_eq!(lcp.magic_number(), Some(0xcafebabe));
+        assert_eq!(lcp.options().count(), 0);
+
+        let reject = [0x08, 0x03, 0x00, 0x08, 0x80, 0x57, 0x01, 0x01];
+        let lcp = PppControl::parse(&reject).unwrap();
+        assert_eq!(lcp.rejected_protocol(), Some(PppProtocol::IPV6CP));
+    }
+
+    #[test]
+    fn ipcp_and_ipv6cp() {
+        let mut buf = [0; 20];
+        let mut writer = TlvWriter::new(&mut buf[PPP_CONTROL_HEADER_LEN..], PPP_OPTION_FORMAT);
+        writer
+            .write(u8::from(IpcpOption::IP_ADDRESS).into(), &[192, 0, 2, 7])
+            .unwrap();
+        writer
+            .write(u8::from(IpcpOption::PRIMARY_DNS).into(), &[192, 0, 2, 53])
+            .unwrap();
+        let len = PPP_CONTROL_HEADER_LEN + writer.written_bytes();
+        let mut header = PppControlHeader::new(&mut buf[..]).unwrap();
+        header.set_code(PppCode::CONFIGURE_NAK.into());
+        header.set_identifier(3);
+        header.set_length(len as u16);
+        assert_eq!(header.to_owned().as_bytes(), &[0x03, 0x03, 0x00, 0x10]);
+
+        let ipcp = match PppFrame::parse(PppProtocol::IPCP, &buf[..len]).unwrap() {
+            PppFrame::Ipcp(ipcp) => ipcp,
+            _ => panic!("not an ipcp packet"),
+        };
+        assert_eq!(ipcp.code(), PppCode::CONFIGURE_NAK);
+        assert_eq!(ipcp.ip_address(), Some(Ipv4Addr::new(192, 0, 2, 7)));
+        assert_eq!(
+            ipcp.option(IpcpOption::PRIMARY_DNS.into()),
+            Some(&[192, 0, 2, 53][..])
+        );
+
+        let bytes = [
+            0x02, 0x01, 0x00, 0x0e, 0x01, 0x0a, 0x02, 0x00, 0x00, 0xff, 0xfe, 0x00, 0x00, 0x01,
+        ];
+        let ipv6cp = match PppFrame::parse(PppProtocol::IPV6CP, &bytes).unwrap() {
+            PppFrame::Ipv6cp(ipv6cp) => ipv6cp,
+            _ => panic!("not an ipv6cp packet"),
+        };
+        assert_eq!(ipv6cp.code(), PppCode::CONFIGURE_ACK);
+        assert_eq!(
+            ipv6cp.interface_identifier(),
+            Some([0x02, 0x00, 0x00, 0xff, 0xfe, 0x00, 0x00, 0x01])
+        );
+    }
+
+    #[test]
+    fn pap_and_chap() {
+        let request = [
+            0x01, 0x01, 0x00, 0x0f, 0x04, b'u', b's', b'e', b'r', 0x05, b'p', b'a', b's', b's',
+            b'w',
+        ];
+        let pap = PapPacket::parse(&request).unwrap();
+        assert_eq!(pap.code(), PapCode::AUTHENTICATE_REQUEST);
+        assert_eq!(pap.peer_id(), Some(&b"user"[..]));
+        assert_eq!(pap.password(), Some(&b"passw"[..]));
+        assert_eq!(pap.message(), None);
+        assert!(PapPacket::parse(&[0x01, 0x01, 0x00, 0x06, 0x04, b'u']).is_none());
+
+        let ack = [0x02, 0x01, 0x00, 0x07, 0x02, b'o', b'k'];
+        match PppFrame::parse(PppProtocol::PAP, &ack).unwrap() {
+            PppFrame::Pap(pap) => {
+                assert_eq!(pap.code(), PapCode::AUTHENTICATE_ACK);
+                assert_eq!(pap.identifier(), 1);
+                assert_eq!(pap.message(), Some(&b"ok"[..]));
+                assert_eq!(pap.peer_id(), None);
+            }
+            _ => panic!("not a pap packet"),
+        }
+
+        let challenge = [
+            0x01, 0x09, 0x00, 0x0c, 0x04, 0xde, 0xad, 0xbe, 0xef, b'b', b'r', b'a',
+        ];
+        let chap = ChapPacket::parse(&challenge).unwrap();
+        assert_eq!((chap.code(), chap.identifier()), (ChapCode::CHALLENGE, 9));
+        assert_eq!(chap.value(), Some(&[0xde, 0xad, 0xbe, 0xef][..]));
+        assert_eq!(chap.name(), Some(&b"bra"[..]));
+        assert_eq!(chap.message(), None);
+        assert!(ChapPacket::parse(&[0x02, 0x09, 0x00, 0x06, 0x04, 0x00]).is_none());
+
+        let success = [0x03, 0x09, 0x00, 0x06, b'o', b'k'];
+        match PppFrame::parse(PppProtocol::CHAP, &success).unwrap() {
+            PppFrame::Chap(chap) => {
+                assert_eq!(chap.code(), ChapCode::SUCCESS);
+                assert_eq!(chap.message(), Some(&b"ok"[..]));
+                assert_eq!(chap.value(), None);
+            }
+            _ => panic!("not a chap packet"),
+        }
+    }
+}
@@ -0,0 +1,112 @@
+use byteorder::{ByteOrder, NetworkEndian};
+
+use super::PppProtocol;
+use super::{code, length, protocol, session_id, ver_type};
+use super::{code_mut, length_mut, protocol_mut, session_id_mut, ver_type_mut};
+
+/// The length of the PPPoE header (RFC 2516), which is counted by the
+/// enclosing frame but not by the length field.
+pub const PPPOE_HEADER_LEN: usize = 6;
+
+/// The length of the PPPoE header of the session stage, followed by the
+/// protocol field of the PPP frame.
+pub const PPPOE_SESSION_HEADER_LEN: usize = PPPOE_HEADER_LEN + 2;
+
+pub const PPPOE_SESSION_HEADER_TEMPLATE: PppoeSessionHeader<[u8; PPPOE_SESSION_HEADER_LEN]> =
+    PppoeSessionHeader {
+        buf: [0x11, 0x00, 0x00, 0x00, 0x00, 0x02, 0x00, 0x21],
+    };
+
+#[derive(Clone, Copy, Debug)]
+pub struct PppoeSessionHeader<T> {
+    buf: T,
+}
+
+impl<T: AsRef<[u8]>> PppoeSessionHeader<T> {
+    #[inline]
+    pub fn new(buf: T) -> Result<Self, T> {
+        if buf.as_ref().len() >= PPPOE_SESSION_HEADER_LEN {
+            Ok(Self { buf })
+        } else {
+            Err(buf)
+        }
+    }
+
+    #[inline]
+    pub fn new_unchecked(buf: T) -> Self {
+        Self { buf }
+    }
+
+    #[inline]
+    pub fn as_bytes(&self) -> &[u8] {
+        &self.buf.as_ref()[0..PPPOE_SESSION_HEADER_LEN]
+    }
+
+    #[inline]
+    pub fn to_owned(&self) -> PppoeSessionHeader<[u8; PPPOE_SESSION_HEADER_LEN]> {
+        let mut buf = [0; PPPOE_SESSION_HEADER_LEN];
+        buf.copy_from_slice(self.as_bytes());
+        PppoeSessionHeader { buf }
+    }
+
+    /// Returns whether both the version and the type are 1.
+    #[inline]
+    pub fn check_version(&self) -> bool {
+        *ver_type(self.buf.as_ref()) == 0x11
+    }
+
+    #[inline]
+    pub fn code(&self) -> u8 {
+        *code(self.buf.as_ref())
+    }
+
+    #[inline]
+    pub fn session_id(&self) -> u16 {
+        let data = session_id(self.buf.as_ref());
+        NetworkEndian::read_u16(data)
+    }
+
+    /// Returns the length field, which counts the PPP frame starting from its
+    /// protocol field.
+    #[inline]
+    pub fn payload_len(&self) -> u16 {
+        let data = length(self.buf.as_ref());
+        NetworkEndian::read_u16(data)
+    }
+
+    #[inline]
+    pub fn ppp_protocol(&self) -> PppProtocol {
+        let data = protocol(self.buf.as_ref());
+        NetworkEndian::read_u16(data).into()
+    }
+}
+
+impl<T: AsMut<[u8]>> PppoeSessionHeader<T> {
+    #[inline]
+    pub fn adjust_version(&mut self) {
+        *ver_type_mut(self.buf.as_mut()) = 0x11;
+    }
+
+    #[inline]
+    pub fn set_code(&mut self, value: u8) {
+        *code_mut(self.buf.as_mut()) = value;
+    }
+
+    #[inline]
+    pub fn set_session_id(&mut self, value: u16) {
+        let data = session_id_mut(self.buf.as_mut());
+        NetworkEndian::write_u16(data, value)
+    }
+
+    #[inline]
+    pub fn set_payload_len(&mut self, value: u16) {
+        let data = length_mut(self.buf.as_mut());
+        NetworkEndian::write_u16(data, value)
+    }
+
+    #[inline]
+    pub fn set_ppp_protocol(&mut self, value: PppProtocol) {
+        let data = protocol_mut(self.buf.as_mut());
+        NetworkEndian::write_u16(data, value.into())
+    }
+}
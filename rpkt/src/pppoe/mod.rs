@@ -0,0 +1,20 @@
+pub use crate::ppp::PppProtocol;
+
+header_field_range_accessors! {
+    (session_id, session_id_mut, 2..4),
+    (length, length_mut, 4..6),
+    (protocol, protocol_mut, 6..8),
+}
+
+header_field_val_accessors! {
+    (ver_type, ver_type_mut, 0),
+    (code, code_mut, 1),
+}
+
+mod header;
+pub use header::{
+    PppoeSessionHeader, PPPOE_HEADER_LEN, PPPOE_SESSION_HEADER_LEN, PPPOE_SESSION_HEADER_TEMPLATE,
+};
+
+mod packet;
+pub use self::packet::{PppGroup, PppoeSession};
@@ -0,0 +1,250 @@
+use bytes::Buf;
+
+use crate::ether::{EtherPayload, EtherType};
+use crate::ipv4::Ipv4Packet;
+use crate::ipv6::Ipv6Packet;
+use crate::ppp::PppFrame;
+use crate::{PktBuf, PktMut};
+
+use super::header::{PppoeSessionHeader, PPPOE_HEADER_LEN, PPPOE_SESSION_HEADER_LEN};
+use super::PppProtocol;
+
+packet_base! {
+    /// A PPPoE packet of the session stage, which carries a PPP frame.
+    pub struct PppoeSession: PppoeSessionHeader {
+        header_len: PPPOE_SESSION_HEADER_LEN,
+        get_methods: [
+            (check_version, bool),
+            (code, u8),
+            (session_id, u16),
+            (payload_len, u16),
+            (ppp_protocol, PppProtocol),
+        ],
+        set_methods: [
+            (adjust_version),
+            (set_session_id, value: u16),
+            (set_ppp_protocol, value: PppProtocol),
+        ],
+        unchecked_set_methods: [
+            (set_code_unchecked, set_code, value: u8),
+            (set_payload_len_unchecked, set_payload_len, value: u16),
+        ]
+    }
+}
+
+/// The payload of a [`PppoeSession`], dispatched on the PPP protocol field.
+#[derive(Debug)]
+pub enum PppGroup<T> {
+    Ipv4(Ipv4Packet<T>),
+    Ipv6(Ipv6Packet<T>),
+    /// A Link Control Protocol packet, starting from its code field.
+    Lcp(T),
+    /// The information field of a PPP frame of another protocol.
+    Other(PppProtocol, T),
+}
+
+impl<T: Buf> PppoeSession<T> {
+    #[inline]
+    pub fn parse(buf: T) -> Result<PppoeSession<T>, T> {
+        traced_parse!("pppoe", buf, |_: &Self| PPPOE_SESSION_HEADER_LEN, {
+            if buf.chunk().len() < PPPOE_SESSION_HEADER_LEN {
+                return Err(buf);
+            }
+
+            let packet = PppoeSession::parse_unchecked(buf);
+            let ppp_len = usize::from(packet.payload_len());
+            if packet.check_version()
+                && packet.code() == 0
+                && ppp_len >= PPPOE_SESSION_HEADER_LEN - PPPOE_HEADER_LEN
+                && PPPOE_HEADER_LEN + ppp_len <= packet.buf.remaining()
+            {
+                Ok(packet)
+            } else {
+                Err(packet.release())
+            }
+        })
+    }
+
+    /// Parse the PPP frame with [`PppFrame::parse`], without consuming the
+    /// packet.
+    ///
+    /// Returns `None` if the PPP frame is not contiguous in the first chunk,
+    /// or if its control packet is malformed.
+    #[inline]
+    pub fn ppp_frame(&self) -> Option<PppFrame<'_>> {
+        let packet_len = PPPOE_HEADER_LEN + usize::from(self.payload_len());
+        let payload = self.buf.chunk().get(PPPOE_SESSION_HEADER_LEN..packet_len)?;
+        PppFrame::parse(self.ppp_protocol(), payload)
+    }
+}
+
+impl<T: PktBuf> PppoeSession<T> {
+    /// Returns the information field of the PPP frame, without the padding of
+    /// the Ethernet frame.
+    #[inline]
+    pub fn payload(self) -> T {
+        let packet_len = PPPOE_HEADER_LEN + usize::from(self.payload_len());
+        assert!(packet_len <= self.buf.remaining());
+        let trim_size = self.buf.remaining() - packet_len;
+
+        let mut buf = self.release();
+        if trim_size > 0 {
+            buf.trim_off(trim_size);
+        }
+
+        buf.advance(PPPOE_SESSION_HEADER_LEN);
+
+        buf
+    }
+
+    /// Parse the payload into the packet type of the PPP protocol field.
+    ///
+    /// Returns the payload back if it is an IPv4 or IPv6 packet that fails to
+    /// parse.
+    #[inline]
+    pub fn parse_group(self) -> Result<PppGroup<T>, T> {
+        let protocol = self.ppp_protocol();
+        let payload = self.payload();
+        match protocol {
+            PppProtocol::IPV4 => Ipv4Packet::parse(payload).map(PppGroup::Ipv4),
+            PppProtocol::IPV6 => Ipv6Packet::parse(payload).map(PppGroup::Ipv6),
+            PppProtocol::LCP => Ok(PppGroup::Lcp(payload)),
+            _ => Ok(PppGroup::Other(protocol, payload)),
+        }
+    }
+}
+
+impl<T: PktMut> PppoeSession<T> {
+    /// Prepend the session header and the PPP protocol field, the length field
+    /// is set to cover the whole payload in `buf`.
+    #[inline]
+    pub fn prepend_header<HT: AsRef<[u8]>>(
+        mut buf: T,
+        header: &PppoeSessionHeader<HT>,
+    ) -> PppoeSession<T> {
+        assert!(buf.chunk_headroom() >= PPPOE_SESSION_HEADER_LEN);
+        let ppp_len = buf.remaining() + PPPOE_SESSION_HEADER_LEN - PPPOE_HEADER_LEN;
+        assert!(
+            ppp_len <= usize::from(u16::MAX),
+            "ppp frame of {} bytes exceeds the 65535-byte limit",
+            ppp_len
+        );
+        buf.move_back(PPPOE_SESSION_HEADER_LEN);
+
+        let data = &mut buf.chunk_mut()[0..PPPOE_SESSION_HEADER_LEN];
+        data.copy_from_slice(header.as_bytes());
+
+        let mut pkt = PppoeSession::parse_unchecked(buf);
+        pkt.set_payload_len_unchecked(ppp_len as u16);
+        pkt
+    }
+}
+
+impl<T> EtherPayload for PppoeSession<T> {
+    const ETHERTYPE: EtherType = EtherType::PPPOE_SESSION;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ether::*;
+    use crate::ipv4::*;
+    use crate::pppoe::PPPOE_SESSION_HEADER_TEMPLATE;
+    use crate::udp::*;
+    use crate::{Cursor, CursorMut};
+
+    // An IPv4/UDP datagram with a 4-byte payload in a PPPoE session, padded to
+    // the minimum frame.
+    fn ipv4_frame() -> ([u8; 60], usize) {
+        let frame_len = ETHER_HEADER_LEN + PPPOE_SESSION_HEADER_LEN + IPV4_HEADER_LEN + 12;
+        let mut frame = [0; 60];
+        let mut buf = CursorMut::new(&mut frame[..frame_len]);
+        buf.advance(frame_len - 4);
+        let udppkt = UdpPacket::prepend_header(buf, &UDP_HEADER_TEMPLATE);
+        let mut ippkt = Ipv4Packet::prepend_header(udppkt.release(), &IPV4_HEADER_TEMPLATE);
+        ippkt.set_protocol(IpProtocol::UDP);
+        let mut session =
+            PppoeSession::prepend_header(ippkt.release(), &PPPOE_SESSION_HEADER_TEMPLATE);
+        session.set_session_id(0x1234);
+        let mut ethpkt = EtherPacket::prepend_header(session.release(), &ETHER_HEADER_TEMPLATE);
+        ethpkt.set_ethertype_for::<PppoeSession<CursorMut>>();
+        (frame, frame_len)
+    }
+
+    #[test]
+    fn session_ipv4() {
+        let (frame, frame_len) = ipv4_frame();
+        let ethpkt = EtherPacket::parse(Cursor::new(&frame[..])).unwrap();
+        assert_eq!(ethpkt.ethertype(), EtherType::PPPOE_SESSION);
+
+        let session = PppoeSession::parse(ethpkt.payload()).unwrap();
+        assert_eq!(session.code(), 0);
+        assert_eq!(session.session_id(), 0x1234);
+        assert_eq!(session.ppp_protocol(), PppProtocol::IPV4);
+        assert_eq!(
+            usize::from(session.payload_len()),
+            frame_len - ETHER_HEADER_LEN - PPPOE_HEADER_LEN
+        );
+
+        // The padding of the frame is trimmed.
+        let ippkt = match session.parse_group().unwrap() {
+            PppGroup::Ipv4(ippkt) => ippkt,
+            _ => panic!("not an ipv4 packet"),
+        };
+        assert_eq!(ippkt.protocol(), IpProtocol::UDP);
+        assert_eq!(ippkt.buf().remaining(), IPV4_HEADER_LEN + 12);
+    }
+
+    #[test]
+    fn session_lcp() {
+        // An LCP echo request.
+        let bytes = [
+            0x11, 0x00, 0x00, 0x07, 0x00, 0x0a, 0xc0, 0x21, 0x09, 0x01, 0x00, 0x08, 0x00, 0x00,
+            0x00, 0x00,
+        ];
+        let session = PppoeSession::parse(Cursor::new(&bytes[..])).unwrap();
+        assert_eq!(session.ppp_protocol(), PppProtocol::LCP);
+        match session.ppp_frame().unwrap() {
+            PppFrame::Lcp(lcp) => assert_eq!(lcp.magic_number(), Some(0)),
+            _ => panic!("not an lcp packet"),
+        }
+        match session.parse_group().unwrap() {
+            PppGroup::Lcp(payload) => assert_eq!(payload.chunk(), &bytes[8..]),
+            _ => panic!("not an lcp packet"),
+        }
+
+        let mut ipcp = bytes;
+        ipcp[6..8].copy_from_slice(&[0x80, 0x21]);
+        let session = PppoeSession::parse(Cursor::new(&ipcp[..])).unwrap();
+        match session.parse_group().unwrap() {
+            PppGroup::Other(protocol, _) => assert_eq!(protocol, PppProtocol::IPCP),
+            _ => panic!("not an ipcp packet"),
+        }
+    }
+
+    #[test]
+    fn session_invalid() {
+        let (frame, _) = ipv4_frame();
+        let session = &frame[ETHER_HEADER_LEN..];
+
+        // A wrong version, a discovery code, or a length past the frame.
+        let mut bytes = [0; 46];
+        bytes.copy_from_slice(session);
+        bytes[0] = 0x21;
+        assert!(PppoeSession::parse(Cursor::new(&bytes[..])).is_err());
+        bytes.copy_from_slice(session);
+        bytes[1] = 0x09;
+        assert!(PppoeSession::parse(Cursor::new(&bytes[..])).is_err());
+        bytes.copy_from_slice(session);
+        bytes[4..6].copy_from_slice(&[0x00, 0x29]);
+        assert!(PppoeSession::parse(Cursor::new(&bytes[..])).is_err());
+        assert!(PppoeSession::parse(Cursor::new(&bytes[..7])).is_err());
+
+        // A malformed IPv4 packet is handed back.
+        bytes.copy_from_slice(session);
+        bytes[PPPOE_SESSION_HEADER_LEN] = 0x4f;
+        let session = PppoeSession::parse(Cursor::new(&bytes[..])).unwrap();
+        let payload = session.parse_group().unwrap_err();
+        assert_eq!(payload.chunk()[0], 0x4f);
+    }
+}
@@ -0,0 +1,819 @@
+//! The Precision Time Protocol of IEEE 1588-2008 (PTPv2).
+//!
+//! A PTP message is carried by a UDP datagram, to [`PTP_EVENT_PORT`] for the
+//! event messages that are timestamped on the wire and to [`PTP_GENERAL_PORT`]
+//! for the others, or directly by an Ethernet frame of
+//! [`EtherType::PTP`](crate::ether::EtherType::PTP). Both transports carry the
+//! same message, which [`PtpPacket`] parses from the UDP payload or from the
+//! Ethernet payload.
+//!
+//! A message starts with the 34-byte common header, followed by a body that
+//! depends on the message type. The bodies of Sync, Delay_Req, Follow_Up,
+//! Delay_Resp and Announce messages are read and written with
+//! [`PtpPacket::timestamp`], [`PtpPacket::requesting_port_identity`] and
+//! [`PtpAnnounce`], the other bodies are left to the payload.
+//!
+//! The timestamps are [`PtpTimestamp`]s of seconds and nanoseconds since the
+//! PTP epoch. The correction field holds nanoseconds scaled by 2^16, a
+//! transparent clock adds the residence time of the message to it, e.g.
+//! measured with the software timestamps of `rpkt-time`.
+//!
+//! # Examples
+//! ```
+//! use rpkt::ptp::*;
+//! use rpkt::{Buf, Cursor, CursorMut};
+//!
+//! let mut bytes = [0; PTP_HEADER_LEN + 10];
+//! let mut buf = CursorMut::new(&mut bytes[..]);
+//! buf.advance(PTP_HEADER_LEN);
+//!
+//! let mut pkt = PtpPacket::prepend_header(buf, &PTP_HEADER_TEMPLATE);
+//! pkt.set_message_type(PtpMessageType::SYNC);
+//! pkt.set_sequence_id(7);
+//! pkt.set_timestamp(PtpTimestamp::from_nanos(1_500_000_001));
+//!
+//! let pkt = PtpPacket::parse(Cursor::new(&bytes[..])).unwrap();
+//! assert_eq!(pkt.message_type(), PtpMessageType::SYNC);
+//! assert_eq!(pkt.message_len(), 44);
+//! let ts = pkt.timestamp().unwrap();
+//! assert_eq!((ts.seconds, ts.nanos), (1, 500_000_001));
+//! ```
+
+use byteorder::{ByteOrder, NetworkEndian};
+use bytes::Buf;
+
+use crate::ether::{EtherPayload, EtherType, MacAddr};
+use crate::ipv4::Ipv4Addr;
+use crate::{PktBuf, PktMut};
+
+/// The UDP port of the event messages.
+pub const PTP_EVENT_PORT: u16 = 319;
+
+/// The UDP port of the general messages.
+pub const PTP_GENERAL_PORT: u16 = 320;
+
+/// The IPv4 multicast address of the messages other than the peer delay ones.
+pub const PTP_PRIMARY_IPV4: Ipv4Addr = Ipv4Addr::new(224, 0, 1, 129);
+
+/// The IPv4 multicast address of the peer delay messages.
+pub const PTP_PDELAY_IPV4: Ipv4Addr = Ipv4Addr::new(224, 0, 0, 107);
+
+/// The Ethernet multicast address of the messages other than the peer delay
+/// ones.
+pub const PTP_PRIMARY_MULTICAST: MacAddr = MacAddr([0x01, 0x1b, 0x19, 0x00, 0x00, 0x00]);
+
+/// The Ethernet multicast address of the peer delay messages, which is not
+/// forwarded by the bridges.
+pub const PTP_PDELAY_MULTICAST: MacAddr = MacAddr([0x01, 0x80, 0xc2, 0x00, 0x00, 0x0e]);
+
+/// The length of the common header.
+pub const PTP_HEADER_LEN: usize = 34;
+
+/// A common header of PTPv2 with zeroed fields, except the message length
+/// that is set by [`PtpPacket::prepend_header`], and the log message interval
+/// of 0x7f that is reserved for the messages not sent periodically.
+pub const PTP_HEADER_TEMPLATE: PtpHeader<[u8; PTP_HEADER_LEN]> = PtpHeader {
+    buf: [
+        0x00, 0x02, 0x00, 0x22, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+        0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+        0x00, 0x00, 0x00, 0x7f,
+    ],
+};
+
+// The lengths of the fields in the bodies.
+const TIMESTAMP_LEN: usize = 10;
+const PORT_IDENTITY_LEN: usize = 10;
+const ANNOUNCE_LEN: usize = 30;
+
+enum_sim! {
+    /// The type of a PTP message.
+    pub struct PtpMessageType (u8) {
+        SYNC = 0x0,
+        DELAY_REQ = 0x1,
+        PDELAY_REQ = 0x2,
+        PDELAY_RESP = 0x3,
+        FOLLOW_UP = 0x8,
+        DELAY_RESP = 0x9,
+        PDELAY_RESP_FOLLOW_UP = 0xa,
+        ANNOUNCE = 0xb,
+        SIGNALING = 0xc,
+        MANAGEMENT = 0xd,
+    }
+}
+
+impl PtpMessageType {
+    /// Returns whether the message is an event message, which is timestamped
+    /// on the wire and sent to [`PTP_EVENT_PORT`].
+    #[inline]
+    pub fn is_event(&self) -> bool {
+        self.0 < 0x8
+    }
+
+    // Returns the minimum length of the body.
+    fn body_len(&self) -> usize {
+        match *self {
+            PtpMessageType::SYNC | PtpMessageType::DELAY_REQ | PtpMessageType::FOLLOW_UP => {
+                TIMESTAMP_LEN
+            }
+            PtpMessageType::PDELAY_REQ
+            | PtpMessageType::PDELAY_RESP
+            | PtpMessageType::DELAY_RESP
+            | PtpMessageType::PDELAY_RESP_FOLLOW_UP => TIMESTAMP_LEN + PORT_IDENTITY_LEN,
+            PtpMessageType::ANNOUNCE => ANNOUNCE_LEN,
+            PtpMessageType::SIGNALING | PtpMessageType::MANAGEMENT => PORT_IDENTITY_LEN,
+            _ => 0,
+        }
+    }
+
+    // Returns whether the body starts with a timestamp.
+    fn has_timestamp(&self) -> bool {
+        !matches!(
+            *self,
+            PtpMessageType::SIGNALING | PtpMessageType::MANAGEMENT
+        ) && self.body_len() >= TIMESTAMP_LEN
+    }
+
+    // Returns whether the timestamp of the body is followed by the identity of
+    // the requesting port.
+    fn has_requesting_port(&self) -> bool {
+        matches!(
+            *self,
+            PtpMessageType::DELAY_RESP
+                | PtpMessageType::PDELAY_RESP
+                | PtpMessageType::PDELAY_RESP_FOLLOW_UP
+        )
+    }
+}
+
+enum_sim! {
+    /// The source of the time of a grandmaster clock.
+    pub struct PtpTimeSource (u8) {
+        ATOMIC_CLOCK = 0x10,
+        GPS = 0x20,
+        TERRESTRIAL_RADIO = 0x30,
+        PTP = 0x40,
+        NTP = 0x50,
+        HAND_SET = 0x60,
+        OTHER = 0x90,
+        INTERNAL_OSCILLATOR = 0xa0,
+    }
+}
+
+header_field_range_accessors! {
+    (message_len, message_len_mut, 2..4),
+    (flags, flags_mut, 6..8),
+    (correction, correction_mut, 8..16),
+    (source_port_identity, source_port_identity_mut, 20..30),
+    (sequence_id, sequence_id_mut, 30..32),
+}
+
+header_field_val_accessors! {
+    (type_byte, type_byte_mut, 0),
+    (version_byte, version_byte_mut, 1),
+    (domain, domain_mut, 4),
+    (control, control_mut, 32),
+    (log_message_interval, log_message_interval_mut, 33),
+}
+
+/// The flags of the common header.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub struct PtpFlags(pub u16);
+
+impl PtpFlags {
+    /// The last minute of the day has 61 seconds.
+    pub const LEAP61: u16 = 0x0001;
+    /// The last minute of the day has 59 seconds.
+    pub const LEAP59: u16 = 0x0002;
+    pub const CURRENT_UTC_OFFSET_VALID: u16 = 0x0004;
+    pub const PTP_TIMESCALE: u16 = 0x0008;
+    pub const TIME_TRACEABLE: u16 = 0x0010;
+    pub const FREQUENCY_TRACEABLE: u16 = 0x0020;
+    pub const ALTERNATE_MASTER: u16 = 0x0100;
+    /// The timestamp of the event message is carried by a Follow_Up or a
+    /// Pdelay_Resp_Follow_Up message.
+    pub const TWO_STEP: u16 = 0x0200;
+    /// The message is sent to a unicast address.
+    pub const UNICAST: u16 = 0x0400;
+
+    /// Returns whether all the bits of `bits` are set.
+    #[inline]
+    pub fn contains(&self, bits: u16) -> bool {
+        self.0 & bits == bits
+    }
+
+    #[inline]
+    pub fn insert(&mut self, bits: u16) {
+        self.0 |= bits;
+    }
+
+    #[inline]
+    pub fn remove(&mut self, bits: u16) {
+        self.0 &= !bits;
+    }
+}
+
+/// The identity of a clock, usually derived from a MAC address.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default, PartialOrd, Ord)]
+pub struct ClockIdentity(pub [u8; 8]);
+
+impl ClockIdentity {
+    /// Derive the EUI-64 identity of `mac`, by inserting 0xfffe in its middle.
+    pub fn from_mac(mac: MacAddr) -> Self {
+        let m = mac.0;
+        Self([m[0], m[1], m[2], 0xff, 0xfe, m[3], m[4], m[5]])
+    }
+}
+
+/// The identity of a PTP port, the clock and the port number in it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default, PartialOrd, Ord)]
+pub struct PortIdentity {
+    pub clock_identity: ClockIdentity,
+    pub port_number: u16,
+}
+
+impl PortIdentity {
+    fn read(data: &[u8]) -> Self {
+        let mut clock_identity = [0; 8];
+        clock_identity.copy_from_slice(&data[0..8]);
+        Self {
+            clock_identity: ClockIdentity(clock_identity),
+            port_number: NetworkEndian::read_u16(&data[8..10]),
+        }
+    }
+
+    fn write(&self, data: &mut [u8]) {
+        data[0..8].copy_from_slice(&self.clock_identity.0);
+        NetworkEndian::write_u16(&mut data[8..10], self.port_number);
+    }
+}
+
+/// A timestamp of PTP, the seconds of 48 bits and the nanoseconds since the
+/// PTP epoch.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default, PartialOrd, Ord)]
+pub struct PtpTimestamp {
+    pub seconds: u64,
+    pub nanos: u32,
+}
+
+impl PtpTimestamp {
+    /// Create a timestamp from the nanoseconds since the PTP epoch.
+    #[inline]
+    pub fn from_nanos(nanos: u64) -> Self {
+        Self {
+            seconds: nanos / 1_000_000_000,
+            nanos: (nanos % 1_000_000_000) as u32,
+        }
+    }
+
+    /// Returns the nanoseconds since the PTP epoch.
+    #[inline]
+    pub fn as_nanos(&self) -> u128 {
+        u128::from(self.seconds) * 1_000_000_000 + u128::from(self.nanos)
+    }
+
+    fn read(data: &[u8]) -> Self {
+        Self {
+            seconds: NetworkEndian::read_u48(&data[0..6]),
+            nanos: NetworkEndian::read_u32(&data[6..10]),
+        }
+    }
+
+    fn write(&self, data: &mut [u8]) {
+        assert!(self.seconds < 1 << 48 && self.nanos < 1_000_000_000);
+        NetworkEndian::write_u48(&mut data[0..6], self.seconds);
+        NetworkEndian::write_u32(&mut data[6..10], self.nanos);
+    }
+}
+
+/// The quality of a grandmaster clock announced to the other clocks.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub struct ClockQuality {
+    pub clock_class: u8,
+    pub clock_accuracy: u8,
+    pub offset_scaled_log_variance: u16,
+}
+
+/// The body of an Announce message, except its origin timestamp, which is
+/// accessed with [`PtpPacket::timestamp`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PtpAnnounce {
+    pub current_utc_offset: i16,
+    pub grandmaster_priority1: u8,
+    pub grandmaster_clock_quality: ClockQuality,
+    pub grandmaster_priority2: u8,
+    pub grandmaster_identity: ClockIdentity,
+    pub steps_removed: u16,
+    pub time_source: PtpTimeSource,
+}
+
+impl PtpAnnounce {
+    // Read the fields behind the origin timestamp.
+    fn read(data: &[u8]) -> Self {
+        let mut grandmaster_identity = [0; 8];
+        grandmaster_identity.copy_from_slice(&data[9..17]);
+        Self {
+            current_utc_offset: NetworkEndian::read_i16(&data[0..2]),
+            grandmaster_priority1: data[3],
+            grandmaster_clock_quality: ClockQuality {
+                clock_class: data[4],
+                clock_accuracy: data[5],
+                offset_scaled_log_variance: NetworkEndian::read_u16(&data[6..8]),
+            },
+            grandmaster_priority2: data[8],
+            grandmaster_identity: ClockIdentity(grandmaster_identity),
+            steps_removed: NetworkEndian::read_u16(&data[17..19]),
+            time_source: PtpTimeSource::from(data[19]),
+        }
+    }
+
+    fn write(&self, data: &mut [u8]) {
+        NetworkEndian::write_i16(&mut data[0..2], self.current_utc_offset);
+        data[2] = 0;
+        data[3] = self.grandmaster_priority1;
+        data[4] = self.grandmaster_clock_quality.clock_class;
+        data[5] = self.grandmaster_clock_quality.clock_accuracy;
+        NetworkEndian::write_u16(
+            &mut data[6..8],
+            self.grandmaster_clock_quality.offset_scaled_log_variance,
+        );
+        data[8] = self.grandmaster_priority2;
+        data[9..17].copy_from_slice(&self.grandmaster_identity.0);
+        NetworkEndian::write_u16(&mut data[17..19], self.steps_removed);
+        data[19] = u8::from(self.time_source);
+    }
+}
+
+/// The common header of a PTP message.
+#[derive(Clone, Copy, Debug)]
+pub struct PtpHeader<T> {
+    buf: T,
+}
+
+impl<T: AsRef<[u8]>> PtpHeader<T> {
+    #[inline]
+    pub fn new(buf: T) -> Result<Self, T> {
+        if buf.as_ref().len() >= PTP_HEADER_LEN {
+            Ok(Self { buf })
+        } else {
+            Err(buf)
+        }
+    }
+
+    #[inline]
+    pub fn new_unchecked(buf: T) -> Self {
+        Self { buf }
+    }
+
+    #[inline]
+    pub fn as_bytes(&self) -> &[u8] {
+        &self.buf.as_ref()[0..PTP_HEADER_LEN]
+    }
+
+    #[inline]
+    pub fn to_owned(&self) -> PtpHeader<[u8; PTP_HEADER_LEN]> {
+        let mut buf = [0; PTP_HEADER_LEN];
+        buf.copy_from_slice(self.as_bytes());
+        PtpHeader { buf }
+    }
+
+    /// Returns the majorSdoId, formerly the transportSpecific field.
+    #[inline]
+    pub fn transport_specific(&self) -> u8 {
+        *type_byte(self.buf.as_ref()) >> 4
+    }
+
+    #[inline]
+    pub fn message_type(&self) -> PtpMessageType {
+        PtpMessageType::from(*type_byte(self.buf.as_ref()) & 0x0f)
+    }
+
+    #[inline]
+    pub fn version(&self) -> u8 {
+        *version_byte(self.buf.as_ref()) & 0x0f
+    }
+
+    /// Returns the length of the message, including the header.
+    #[inline]
+    pub fn message_len(&self) -> u16 {
+        NetworkEndian::read_u16(message_len(self.buf.as_ref()))
+    }
+
+    #[inline]
+    pub fn domain(&self) -> u8 {
+        *domain(self.buf.as_ref())
+    }
+
+    #[inline]
+    pub fn flags(&self) -> PtpFlags {
+        PtpFlags(NetworkEndian::read_u16(flags(self.buf.as_ref())))
+    }
+
+    /// Returns the correction field, in nanoseconds scaled by 2^16.
+    #[inline]
+    pub fn correction(&self) -> i64 {
+        NetworkEndian::read_i64(correction(self.buf.as_ref()))
+    }
+
+    /// Returns the correction field in whole nanoseconds, rounded down.
+    #[inline]
+    pub fn correction_nanos(&self) -> i64 {
+        self.correction() >> 16
+    }
+
+    #[inline]
+    pub fn source_port_identity(&self) -> PortIdentity {
+        PortIdentity::read(source_port_identity(self.buf.as_ref()))
+    }
+
+    #[inline]
+    pub fn sequence_id(&self) -> u16 {
+        NetworkEndian::read_u16(sequence_id(self.buf.as_ref()))
+    }
+
+    /// Returns the control field, which is kept for PTPv1 compatibility.
+    #[inline]
+    pub fn control(&self) -> u8 {
+        *control(self.buf.as_ref())
+    }
+
+    /// Returns the log2 of the interval between the messages in seconds.
+    #[inline]
+    pub fn log_message_interval(&self) -> i8 {
+        *log_message_interval(self.buf.as_ref()) as i8
+    }
+}
+
+impl<T: AsMut<[u8]>> PtpHeader<T> {
+    #[inline]
+    pub fn set_transport_specific(&mut self, value: u8) {
+        assert!(value <= 0x0f);
+        let data = type_byte_mut(self.buf.as_mut());
+        *data = (*data & 0x0f) | (value << 4);
+    }
+
+    #[inline]
+    pub fn set_message_type(&mut self, value: PtpMessageType) {
+        let value = u8::from(value);
+        assert!(value <= 0x0f);
+        let data = type_byte_mut(self.buf.as_mut());
+        *data = (*data & 0xf0) | value;
+    }
+
+    #[inline]
+    pub fn set_version(&mut self, value: u8) {
+        assert!(value <= 0x0f);
+        let data = version_byte_mut(self.buf.as_mut());
+        *data = (*data & 0xf0) | value;
+    }
+
+    #[inline]
+    pub fn set_message_len(&mut self, value: u16) {
+        NetworkEndian::write_u16(message_len_mut(self.buf.as_mut()), value);
+    }
+
+    #[inline]
+    pub fn set_domain(&mut self, value: u8) {
+        *domain_mut(self.buf.as_mut()) = value;
+    }
+
+    #[inline]
+    pub fn set_flags(&mut self, value: PtpFlags) {
+        NetworkEndian::write_u16(flags_mut(self.buf.as_mut()), value.0);
+    }
+
+    /// Set the correction field, in nanoseconds scaled by 2^16.
+    #[inline]
+    pub fn set_correction(&mut self, value: i64) {
+        NetworkEndian::write_i64(correction_mut(self.buf.as_mut()), value);
+    }
+
+    #[inline]
+    pub fn set_source_port_identity(&mut self, value: PortIdentity) {
+        value.write(source_port_identity_mut(self.buf.as_mut()));
+    }
+
+    #[inline]
+    pub fn set_sequence_id(&mut self, value: u16) {
+        NetworkEndian::write_u16(sequence_id_mut(self.buf.as_mut()), value);
+    }
+
+    #[inline]
+    pub fn set_control(&mut self, value: u8) {
+        *control_mut(self.buf.as_mut()) = value;
+    }
+
+    #[inline]
+    pub fn set_log_message_interval(&mut self, value: i8) {
+        *log_message_interval_mut(self.buf.as_mut()) = value as u8;
+    }
+}
+
+packet_base! {
+    /// A PTP message, carried by a UDP datagram or an Ethernet frame.
+    pub struct PtpPacket: PtpHeader {
+        header_len: PTP_HEADER_LEN,
+        get_methods: [
+            (transport_specific, u8),
+            (message_type, PtpMessageType),
+            (version, u8),
+            (message_len, u16),
+            (domain, u8),
+            (flags, PtpFlags),
+            (correction, i64),
+            (correction_nanos, i64),
+            (source_port_identity, PortIdentity),
+            (sequence_id, u16),
+            (control, u8),
+            (log_message_interval, i8),
+        ],
+        set_methods: [
+            (set_transport_specific, value: u8),
+            (set_message_type, value: PtpMessageType),
+            (set_version, value: u8),
+            (set_domain, value: u8),
+            (set_flags, value: PtpFlags),
+            (set_correction, value: i64),
+            (set_source_port_identity, value: PortIdentity),
+            (set_sequence_id, value: u16),
+            (set_control, value: u8),
+            (set_log_message_interval, value: i8),
+        ],
+        unchecked_set_methods: [
+            (set_message_len_unchecked, set_message_len, value: u16),
+        ]
+    }
+}
+
+impl<T: Buf> PtpPacket<T> {
+    /// Parse a PTPv2 message, whose body must be complete for its message
+    /// type.
+    #[inline]
+    pub fn parse(buf: T) -> Result<PtpPacket<T>, T> {
+        traced_parse!("ptp", buf, |_: &Self| PTP_HEADER_LEN, {
+            if buf.chunk().len() < PTP_HEADER_LEN {
+                return Err(buf);
+            }
+
+            let header = PtpHeader::new_unchecked(buf.chunk());
+            let message_len = usize::from(header.message_len());
+            if header.version() == 2
+                && message_len >= PTP_HEADER_LEN + header.message_type().body_len()
+                && message_len <= buf.remaining()
+                && message_len <= buf.chunk().len()
+            {
+                Ok(PtpPacket { buf })
+            } else {
+                Err(buf)
+            }
+        })
+    }
+
+    /// Returns the timestamp at the start of the body, which is the origin
+    /// timestamp of Sync, Delay_Req and Announce messages, the precise origin
+    /// timestamp of Follow_Up messages, and the receive timestamp of
+    /// Delay_Resp messages.
+    ///
+    /// Returns `None` if the message type has no timestamp.
+    #[inline]
+    pub fn timestamp(&self) -> Option<PtpTimestamp> {
+        if !self.message_type().has_timestamp() {
+            return None;
+        }
+        let data = &self.buf.chunk()[PTP_HEADER_LEN..PTP_HEADER_LEN + TIMESTAMP_LEN];
+        Some(PtpTimestamp::read(data))
+    }
+
+    /// Returns the identity of the port that sends the request, carried by
+    /// the responses of Delay_Resp, Pdelay_Resp and Pdelay_Resp_Follow_Up
+    /// messages.
+    #[inline]
+    pub fn requesting_port_identity(&self) -> Option<PortIdentity> {
+        if !self.message_type().has_requesting_port() {
+            return None;
+        }
+        let offset = PTP_HEADER_LEN + TIMESTAMP_LEN;
+        let data = &self.buf.chunk()[offset..offset + PORT_IDENTITY_LEN];
+        Some(PortIdentity::read(data))
+    }
+
+    /// Returns the body of an Announce message.
+    #[inline]
+    pub fn announce(&self) -> Option<PtpAnnounce> {
+        if self.message_type() != PtpMessageType::ANNOUNCE {
+            return None;
+        }
+        let offset = PTP_HEADER_LEN + TIMESTAMP_LEN;
+        let data = &self.buf.chunk()[offset..PTP_HEADER_LEN + ANNOUNCE_LEN];
+        Some(PtpAnnounce::read(data))
+    }
+}
+
+impl<T: PktBuf> PtpPacket<T> {
+    /// Returns the body of the message, which ends at the message length.
+    #[inline]
+    pub fn payload(self) -> T {
+        let message_len = usize::from(self.message_len());
+        assert!(message_len <= self.buf.remaining());
+        let trim_size = self.buf.remaining() - message_len;
+
+        let mut buf = self.release();
+        if trim_size > 0 {
+            buf.trim_off(trim_size);
+        }
+        buf.advance(PTP_HEADER_LEN);
+
+        buf
+    }
+}
+
+impl<T: PktMut> PtpPacket<T> {
+    /// Set the timestamp at the start of the body.
+    ///
+    /// # Panics
+    /// This function panics if the message type has no timestamp, or if the
+    /// body is too short to hold it.
+    #[inline]
+    pub fn set_timestamp(&mut self, value: PtpTimestamp) {
+        assert!(self.message_type().has_timestamp());
+        let data = &mut self.buf.chunk_mut()[PTP_HEADER_LEN..PTP_HEADER_LEN + TIMESTAMP_LEN];
+        value.write(data);
+    }
+
+    /// Set the identity of the requesting port of a response.
+    ///
+    /// # Panics
+    /// This function panics if the message type is not a response, or if the
+    /// body is too short to hold it.
+    #[inline]
+    pub fn set_requesting_port_identity(&mut self, value: PortIdentity) {
+        assert!(self.message_type().has_requesting_port());
+        let offset = PTP_HEADER_LEN + TIMESTAMP_LEN;
+        value.write(&mut self.buf.chunk_mut()[offset..offset + PORT_IDENTITY_LEN]);
+    }
+
+    /// Set the body of an Announce message, except its origin timestamp.
+    ///
+    /// # Panics
+    /// This function panics if the message is not an Announce message, or if
+    /// the body is too short to hold it.
+    #[inline]
+    pub fn set_announce(&mut self, value: PtpAnnounce) {
+        assert!(self.message_type() == PtpMessageType::ANNOUNCE);
+        let offset = PTP_HEADER_LEN + TIMESTAMP_LEN;
+        value.write(&mut self.buf.chunk_mut()[offset..PTP_HEADER_LEN + ANNOUNCE_LEN]);
+    }
+
+    /// Prepend the common header to the body in `buf`, and set the message
+    /// length to the length of the whole message.
+    #[inline]
+    pub fn prepend_header<HT: AsRef<[u8]>>(mut buf: T, header: &PtpHeader<HT>) -> PtpPacket<T> {
+        assert!(buf.chunk_headroom() >= PTP_HEADER_LEN);
+        buf.move_back(PTP_HEADER_LEN);
+
+        let data = &mut buf.chunk_mut()[0..PTP_HEADER_LEN];
+        data.copy_from_slice(header.as_bytes());
+
+        let mut pkt = PtpPacket::parse_unchecked(buf);
+        let message_len = pkt.buf().remaining();
+        assert!(message_len <= usize::from(u16::MAX));
+        pkt.set_message_len_unchecked(message_len as u16);
+
+        pkt
+    }
+}
+
+impl<T> EtherPayload for PtpPacket<T> {
+    const ETHERTYPE: EtherType = EtherType::PTP;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ether::{EtherPacket, ETHER_HEADER_LEN, ETHER_HEADER_TEMPLATE};
+    use crate::{Cursor, CursorMut};
+
+    fn port() -> PortIdentity {
+        PortIdentity {
+            clock_identity: ClockIdentity::from_mac(MacAddr([0x00, 0x1b, 0x21, 0x01, 0x02, 0x03])),
+            port_number: 1,
+        }
+    }
+
+    #[test]
+    fn delay_resp_over_ethernet() {
+        let len = ETHER_HEADER_LEN + PTP_HEADER_LEN + 20;
+        let mut frame = vec![0xff; len];
+        let mut buf = CursorMut::new(&mut frame[..]);
+        buf.advance(ETHER_HEADER_LEN + PTP_HEADER_LEN);
+
+        let mut pkt = PtpPacket::prepend_header(buf, &PTP_HEADER_TEMPLATE);
+        pkt.set_message_type(PtpMessageType::DELAY_RESP);
+        pkt.set_transport_specific(1);
+        pkt.set_domain(24);
+        pkt.set_sequence_id(0x1234);
+        pkt.set_source_port_identity(port());
+        pkt.set_correction(-(3 << 16));
+        pkt.set_control(3);
+        pkt.set_log_message_interval(-4);
+        pkt.set_timestamp(PtpTimestamp {
+            seconds: 0x0102_0304_0506,
+            nanos: 999_999_999,
+        });
+        let requesting = PortIdentity {
+            clock_identity: ClockIdentity([1, 2, 3, 4, 5, 6, 7, 8]),
+            port_number: 2,
+        };
+        pkt.set_requesting_port_identity(requesting);
+
+        let mut ethpkt = EtherPacket::prepend_header(pkt.release(), &ETHER_HEADER_TEMPLATE);
+        ethpkt.set_dest_mac(PTP_PRIMARY_MULTICAST);
+        ethpkt.set_ethertype_for::<PtpPacket<()>>();
+
+        assert_eq!(&frame[12..18], &[0x88, 0xf7, 0x19, 0x02, 0x00, 0x36]);
+        assert_eq!(
+            &frame[22..30],
+            &[0xff, 0xff, 0xff, 0xff, 0xff, 0xfd, 0x00, 0x00]
+        );
+        assert_eq!(
+            &frame[34..44],
+            &[0x00, 0x1b, 0x21, 0xff, 0xfe, 0x01, 0x02, 0x03, 0x00, 0x01]
+        );
+        assert_eq!(&frame[48..54], &[0x01, 0x02, 0x03, 0x04, 0x05, 0x06]);
+
+        let ethpkt = EtherPacket::parse(Cursor::new(&frame[..])).unwrap();
+        assert_eq!(ethpkt.ethertype(), EtherType::PTP);
+        let pkt = PtpPacket::parse(ethpkt.payload()).unwrap();
+        assert_eq!(pkt.message_type(), PtpMessageType::DELAY_RESP);
+        assert!(!pkt.message_type().is_event());
+        assert_eq!(pkt.transport_specific(), 1);
+        assert_eq!(pkt.version(), 2);
+        assert_eq!(pkt.message_len(), 54);
+        assert_eq!(pkt.domain(), 24);
+        assert_eq!(pkt.sequence_id(), 0x1234);
+        assert_eq!(pkt.source_port_identity(), port());
+        assert_eq!(pkt.correction_nanos(), -3);
+        assert_eq!(pkt.control(), 3);
+        assert_eq!(pkt.log_message_interval(), -4);
+        let ts = pkt.timestamp().unwrap();
+        assert_eq!(
+            ts.as_nanos(),
+            0x0102_0304_0506 * 1_000_000_000 + 999_999_999
+        );
+        assert_eq!(pkt.requesting_port_identity(), Some(requesting));
+        assert_eq!(pkt.announce(), None);
+        assert_eq!(pkt.payload().chunk().len(), 20);
+    }
+
+    #[test]
+    fn announce_and_malformed() {
+        let mut bytes = [0; PTP_HEADER_LEN + ANNOUNCE_LEN + 4];
+        let mut buf = CursorMut::new(&mut bytes[..]);
+        buf.advance(PTP_HEADER_LEN);
+        buf.trim_off(4);
+
+        let mut pkt = PtpPacket::prepend_header(buf, &PTP_HEADER_TEMPLATE);
+        pkt.set_message_type(PtpMessageType::ANNOUNCE);
+        let mut flags = PtpFlags::default();
+        flags.insert(PtpFlags::PTP_TIMESCALE | PtpFlags::CURRENT_UTC_OFFSET_VALID);
+        pkt.set_flags(flags);
+        let announce = PtpAnnounce {
+            current_utc_offset: 37,
+            grandmaster_priority1: 128,
+            grandmaster_clock_quality: ClockQuality {
+                clock_class: 6,
+                clock_accuracy: 0x21,
+                offset_scaled_log_variance: 0x4e5d,
+            },
+            grandmaster_priority2: 128,
+            grandmaster_identity: port().clock_identity,
+            steps_removed: 1,
+            time_source: PtpTimeSource::GPS,
+        };
+        pkt.set_announce(announce);
+        assert_eq!(pkt.message_len(), 64);
+        assert_eq!(&bytes[44..48], &[0x00, 0x25, 0x00, 0x80]);
+        assert_eq!(
+            &bytes[53..64],
+            &[0x00, 0x1b, 0x21, 0xff, 0xfe, 0x01, 0x02, 0x03, 0x00, 0x01, 0x20]
+        );
+
+        // The trailing bytes are not part of the message.
+        let pkt = PtpPacket::parse(Cursor::new(&bytes[..])).unwrap();
+        assert!(pkt.flags().contains(PtpFlags::PTP_TIMESCALE));
+        assert!(!pkt.flags().contains(PtpFlags::TWO_STEP));
+        assert_eq!(pkt.announce(), Some(announce));
+        assert_eq!(pkt.timestamp(), Some(PtpTimestamp::default()));
+        assert_eq!(pkt.requesting_port_identity(), None);
+        assert_eq!(pkt.payload().chunk().len(), ANNOUNCE_LEN);
+
+        // A truncated body.
+        assert!(PtpPacket::parse(Cursor::new(&bytes[..63])).is_err());
+        // A message length shorter than the body.
+        bytes[3] = 60;
+        assert!(PtpPacket::parse(Cursor::new(&bytes[..])).is_err());
+        bytes[3] = 64;
+        // PTPv1.
+        bytes[1] = 1;
+        assert!(PtpPacket::parse(Cursor::new(&bytes[..])).is_err());
+    }
+}
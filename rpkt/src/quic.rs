@@ -0,0 +1,341 @@
+//! Stateless routing of QUIC packets by connection ID (RFC 9000).
+//!
+//! A QUIC connection survives the migration of the client to another address,
+//! so a load balancer routes its packets by the destination connection ID
+//! (DCID) chosen by the server instead of the 5-tuple. The long header packets
+//! carry the length of the DCID, but the short header packets do not, so the
+//! length must be known for each listening port, see [`QuicListener`].
+//!
+//! A load balancer may also answer the clients without the help of a server,
+//! with the packets built by [`write_version_negotiation`] and
+//! [`write_retry`].
+//!
+//! # Examples
+//! ```
+//! use rpkt::quic::{cid_hash, QuicListener};
+//!
+//! let listener = QuicListener { port: 443, cid_len: 4 };
+//!
+//! // An Initial packet, and a short header packet of the same connection.
+//! let initial = [0xc0, 0, 0, 0, 1, 4, 0xaa, 0xbb, 0xcc, 0xdd, 0];
+//! let short = [0x40, 0xaa, 0xbb, 0xcc, 0xdd, 0, 0, 0];
+//! let dcid = listener.dest_cid(&initial).unwrap();
+//! assert_eq!(dcid, listener.dest_cid(&short).unwrap());
+//! assert_eq!(cid_hash(dcid) as usize % 3, cid_hash(&short[1..5]) as usize % 3);
+//! ```
+
+use byteorder::{ByteOrder, NetworkEndian};
+
+/// The maximum length of a QUIC connection ID.
+pub const QUIC_CID_LEN_MAX: usize = 20;
+
+/// The version number of QUIC version 1 (RFC 9000).
+pub const QUIC_VERSION_1: u32 = 0x00000001;
+
+/// The version number of QUIC version 2 (RFC 9369).
+pub const QUIC_VERSION_2: u32 = 0x6b3343cf;
+
+/// The length of the Retry integrity tag.
+pub const QUIC_RETRY_TAG_LEN: usize = 16;
+
+const HEADER_FORM_LONG: u8 = 0x80;
+const FIXED_BIT: u8 = 0x40;
+// The offset of the DCID length of the long header, after the first byte and
+// the version.
+const LONG_DCID_LEN_OFFSET: usize = 5;
+
+/// A UDP port on which a QUIC server listens, with the length of the
+/// connection IDs that the server issues.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct QuicListener {
+    pub port: u16,
+    pub cid_len: usize,
+}
+
+impl QuicListener {
+    /// Returns the DCID of the QUIC packet in the UDP `payload`, see
+    /// [`dest_cid`].
+    #[inline]
+    pub fn dest_cid<'a>(&self, payload: &'a [u8]) -> Option<&'a [u8]> {
+        dest_cid(payload, self.cid_len)
+    }
+
+    /// Returns the listener of `port` among `listeners`.
+    pub fn find(listeners: &[QuicListener], port: u16) -> Option<&QuicListener> {
+        listeners.iter().find(|listener| listener.port == port)
+    }
+}
+
+/// Returns whether the QUIC packet starting with the byte `first` has a long
+/// header.
+#[inline]
+pub fn is_long_header(first: u8) -> bool {
+    first & HEADER_FORM_LONG != 0
+}
+
+/// Returns the version of the long header packet in `buf`, 0 for a Version
+/// Negotiation packet.
+pub fn version(buf: &[u8]) -> Option<u32> {
+    if !is_long_header(*buf.first()?) {
+        return None;
+    }
+    buf.get(1..5).map(NetworkEndian::read_u32)
+}
+
+/// Returns the destination connection ID of the QUIC packet in `buf`, the
+/// payload of a UDP datagram.
+///
+/// The short header packets are assumed to carry connection IDs of
+/// `short_cid_len` bytes. Returns `None` if the packet does not have the fixed
+/// bit, or if the connection ID is empty or truncated.
+pub fn dest_cid(buf: &[u8], short_cid_len: usize) -> Option<&[u8]> {
+    let first = *buf.first()?;
+    if first & FIXED_BIT == 0 {
+        return None;
+    }
+    let (offset, len) = if is_long_header(first) {
+        let len = usize::from(*buf.get(LONG_DCID_LEN_OFFSET)?);
+        (LONG_DCID_LEN_OFFSET + 1, len)
+    } else {
+        (1, short_cid_len)
+    };
+    if len == 0 || len > QUIC_CID_LEN_MAX {
+        return None;
+    }
+    buf.get(offset..offset + len)
+}
+
+/// Returns the source connection ID of the long header packet in `buf`, which
+/// may be empty.
+pub fn source_cid(buf: &[u8]) -> Option<&[u8]> {
+    if !is_long_header(*buf.first()?) {
+        return None;
+    }
+    let dcid_len = usize::from(*buf.get(LONG_DCID_LEN_OFFSET)?);
+    let offset = LONG_DCID_LEN_OFFSET + 1 + dcid_len;
+    let len = usize::from(*buf.get(offset)?);
+    if dcid_len > QUIC_CID_LEN_MAX || len > QUIC_CID_LEN_MAX {
+        return None;
+    }
+    buf.get(offset + 1..offset + 1 + len)
+}
+
+/// Returns a 32-bit hash of the connection ID `cid`, which is stable across
+/// runs and platforms, so that the instances of a load balancer agree.
+///
+/// The hash is the same as [`FlowKey::hash32`](crate::flow::FlowKey::hash32)
+/// of the connection ID.
+pub fn cid_hash(cid: &[u8]) -> u32 {
+    let mut hash = 0x811c9dc5;
+    for b in cid {
+        hash ^= u32::from(*b);
+        hash = hash.wrapping_mul(0x01000193);
+    }
+    hash
+}
+
+/// Write a Version Negotiation packet into `buf`, in response to a packet of
+/// an unsupported version, and return its length.
+///
+/// `dcid` and `scid` are the source and the destination connection IDs of the
+/// client's packet, respectively. Returns `None` if `buf` is too small or if a
+/// connection ID is too long.
+pub fn write_version_negotiation(
+    buf: &mut [u8],
+    dcid: &[u8],
+    scid: &[u8],
+    versions: &[u32],
+) -> Option<usize> {
+    let mut len = write_long_header(buf, HEADER_FORM_LONG | FIXED_BIT, 0, dcid, scid)?;
+    for version in versions {
+        NetworkEndian::write_u32(buf.get_mut(len..len + 4)?, *version);
+        len += 4;
+    }
+    Some(len)
+}
+
+/// Write a Retry packet of `version` into `buf`, and return its length.
+///
+/// `dcid` is the source connection ID of the client's Initial packet, `scid`
+/// is the connection ID chosen for the server, and `odcid` is the destination
+/// connection ID of the client's Initial packet. The Retry integrity tag is
+/// computed by `seal` over the Retry pseudo-packet (RFC 9001, section 5.8),
+/// which requires AES-128-GCM with the key and the nonce of `version`.
+///
+/// Returns `None` if `buf` is too small, or if a connection ID is too long.
+pub fn write_retry<F>(
+    buf: &mut [u8],
+    version: u32,
+    dcid: &[u8],
+    scid: &[u8],
+    odcid: &[u8],
+    token: &[u8],
+    seal: F,
+) -> Option<usize>
+where
+    F: FnOnce(&[u8]) -> [u8; QUIC_RETRY_TAG_LEN],
+{
+    if odcid.len() > QUIC_CID_LEN_MAX {
+        return None;
+    }
+    // The long packet types of QUIC version 2 are rotated.
+    let packet_type = if version == QUIC_VERSION_2 { 0 } else { 3 };
+    let first = HEADER_FORM_LONG | FIXED_BIT | (packet_type << 4);
+
+    // The pseudo-packet is the Retry packet prefixed with the ODCID, it is
+    // written in place and then shifted to the start of `buf`.
+    let prefix_len = 1 + odcid.len();
+    let header_len = write_long_header(buf.get_mut(prefix_len..)?, first, version, dcid, scid)?;
+    let pseudo_len = prefix_len + header_len + token.len();
+    buf.get_mut(prefix_len + header_len..pseudo_len)?
+        .copy_from_slice(token);
+    buf[0] = odcid.len() as u8;
+    buf[1..prefix_len].copy_from_slice(odcid);
+    let len = pseudo_len - prefix_len + QUIC_RETRY_TAG_LEN;
+    if len > buf.len() {
+        return None;
+    }
+    let tag = seal(&buf[..pseudo_len]);
+
+    buf.copy_within(prefix_len..pseudo_len, 0);
+    buf[len - QUIC_RETRY_TAG_LEN..len].copy_from_slice(&tag);
+    Some(len)
+}
+
+// Write the long header up to the source connection ID, and return its
+// length.
+fn write_long_header(
+    buf: &mut [u8],
+    first: u8,
+    version: u32,
+    dcid: &[u8],
+    scid: &[u8],
+) -> Option<usize> {
+    if dcid.len() > QUIC_CID_LEN_MAX || scid.len() > QUIC_CID_LEN_MAX {
+        return None;
+    }
+    let len = 7 + dcid.len() + scid.len();
+    let header = buf.get_mut(..len)?;
+    header[0] = first;
+    NetworkEndian::write_u32(&mut header[1..5], version);
+    header[5] = dcid.len() as u8;
+    header[6..6 + dcid.len()].copy_from_slice(dcid);
+    header[6 + dcid.len()] = scid.len() as u8;
+    header[7 + dcid.len()..].copy_from_slice(scid);
+    Some(len)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn connection_ids() {
+        let mut initial = vec![0xc3, 0, 0, 0, 1, 4, 1, 2, 3, 4, 2, 9, 9];
+        initial.extend_from_slice(&[0; 20]);
+        assert!(is_long_header(initial[0]));
+        assert_eq!(version(&initial), Some(QUIC_VERSION_1));
+        assert_eq!(dest_cid(&initial, 8), Some(&[1, 2, 3, 4][..]));
+        assert_eq!(source_cid(&initial), Some(&[9, 9][..]));
+
+        // The CID length of the listener only applies to short headers.
+        let listeners = [
+            QuicListener {
+                port: 443,
+                cid_len: 4,
+            },
+            QuicListener {
+                port: 4433,
+                cid_len: 8,
+            },
+        ];
+        let short = [0x41, 1, 2, 3, 4, 5, 6, 7, 8];
+        let listener = QuicListener::find(&listeners, 4433).unwrap();
+        assert_eq!(listener.dest_cid(&short), Some(&short[1..9]));
+        assert_eq!(listeners[0].dest_cid(&short), Some(&short[1..5]));
+        assert_eq!(listener.dest_cid(&initial), Some(&[1, 2, 3, 4][..]));
+        assert!(QuicListener::find(&listeners, 80).is_none());
+        assert_eq!(version(&short), None);
+        assert_eq!(source_cid(&short), None);
+
+        // Without the fixed bit, an empty DCID, or truncated.
+        assert_eq!(dest_cid(&[0x01; 9], 8), None);
+        assert_eq!(dest_cid(&[0xc0, 0, 0, 0, 1, 0, 0], 8), None);
+        assert_eq!(dest_cid(&short[..5], 8), None);
+        assert_eq!(source_cid(&initial[..11]), None);
+
+        assert_eq!(cid_hash(&[]), 0x811c9dc5);
+        assert_ne!(cid_hash(&[1, 2, 3, 4]), cid_hash(&[1, 2, 3, 5]));
+    }
+
+    #[test]
+    fn version_negotiation() {
+        let mut buf = [0; 64];
+        let len =
+            write_version_negotiation(&mut buf, &[1, 2], &[3], &[QUIC_VERSION_1, QUIC_VERSION_2])
+                .unwrap();
+        assert_eq!(
+            &buf[..len],
+            &[0xc0, 0, 0, 0, 0, 2, 1, 2, 1, 3, 0x00, 0x00, 0x00, 0x01, 0x6b, 0x33, 0x43, 0xcf]
+        );
+        assert_eq!(version(&buf[..len]), Some(0));
+        assert!(write_version_negotiation(&mut buf[..17], &[1, 2], &[3], &[1, 2]).is_none());
+        assert!(write_version_negotiation(&mut buf, &[0; 21], &[], &[1]).is_none());
+    }
+
+    #[test]
+    fn retry() {
+        // The Retry of RFC 9001, appendix A.4, with the unused bits cleared.
+        let odcid = [0x83, 0x94, 0xc8, 0xf0, 0x3e, 0x51, 0x57, 0x08];
+        let scid = [0xf0, 0x67, 0xa5, 0x50, 0x2a, 0x42, 0x62, 0xb5];
+        let tag = [
+            0x04, 0xa2, 0x65, 0xba, 0x2e, 0xff, 0x4d, 0x82, 0x90, 0x58, 0xfb, 0x3f, 0x0f, 0x24,
+            0x96, 0xba,
+        ];
+        let mut buf = [0xff; 64];
+        let len = write_retry(
+            &mut buf,
+            QUIC_VERSION_1,
+            &[],
+            &scid,
+            &odcid,
+            b"token",
+            |pseudo| {
+                assert_eq!(pseudo[0], 8);
+                assert_eq!(&pseudo[1..9], &odcid);
+                assert_eq!(&pseudo[9..], &buf_retry_header(&scid)[..]);
+                tag
+            },
+        )
+        .unwrap();
+        let mut expected = buf_retry_header(&scid);
+        expected.extend_from_slice(&tag);
+        assert_eq!(&buf[..len], &expected[..]);
+        assert_eq!(dest_cid(&buf[..len], 8), None);
+        assert_eq!(source_cid(&buf[..len]), Some(&scid[..]));
+
+        // QUIC version 2 uses another packet type.
+        let len = write_retry(&mut buf, QUIC_VERSION_2, &[1], &scid, &odcid, b"", |_| tag).unwrap();
+        assert_eq!(buf[0], 0xc0);
+        assert_eq!(len, 7 + 1 + 8 + QUIC_RETRY_TAG_LEN);
+
+        // No room for the tag.
+        assert!(write_retry(
+            &mut buf[..33],
+            QUIC_VERSION_1,
+            &[],
+            &scid,
+            &odcid,
+            b"token",
+            |_| tag
+        )
+        .is_none());
+    }
+
+    fn buf_retry_header(scid: &[u8]) -> Vec<u8> {
+        let mut header = vec![0xf0, 0x00, 0x00, 0x00, 0x01, 0x00, 0x08];
+        header.extend_from_slice(scid);
+        header.extend_from_slice(b"token");
+        header
+    }
+}
@@ -0,0 +1,522 @@
+//! Stateless TCP probes for port scanners.
+//!
+//! [`TcpProber`] writes complete Ethernet frames carrying TCP probes, with
+//! the IP and TCP checksums filled in. The initial sequence number of every
+//! probe is a keyed hash of its addresses and ports, so the replies can be
+//! matched to the probes without remembering what was sent, in the style of
+//! masscan. A reply that does not acknowledge the sequence number of a probe,
+//! e.g. a forged or a stale one, is ignored.
+//!
+//! The probes can be sent from a spoofed source address. The replies are then
+//! sent to the spoofed address, and are matched if they reach the prober.
+//!
+//! # Examples
+//! ```
+//! use std::net::{Ipv4Addr, SocketAddr};
+//!
+//! use rpkt::ether::MacAddr;
+//! use rpkt::scan::{TcpProbeKind, TcpProber};
+//!
+//! let local = MacAddr([0x02, 0, 0, 0, 0, 1]);
+//! let gateway = MacAddr([0x02, 0, 0, 0, 0, 2]);
+//! let prober = TcpProber::new(local, gateway, Ipv4Addr::new(192, 0, 2, 1).into(), 42);
+//!
+//! let mut frame = [0; 128];
+//! let target: SocketAddr = "198.51.100.7:443".parse().unwrap();
+//! let len = prober.write_probe(&mut frame, TcpProbeKind::Syn, target, 40000).unwrap();
+//! assert_eq!(len, 54);
+//! ```
+
+use std::collections::hash_map::DefaultHasher;
+use std::hash::Hasher;
+use std::net::{IpAddr, SocketAddr};
+
+use crate::checksum_utils::{self, ChecksumAccumulator};
+use crate::ether::{EtherHeader, EtherType, MacAddr, ETHER_HEADER_LEN, ETHER_HEADER_TEMPLATE};
+use crate::ipv4::{IpProtocol, Ipv4Header, IPV4_HEADER_LEN, IPV4_HEADER_TEMPLATE};
+use crate::ipv6::{Ipv6Header, IPV6_HEADER_LEN};
+use crate::tcp::{TcpHeader, TCP_HEADER_LEN, TCP_HEADER_TEMPLATE};
+
+/// The length of an IPv4 probe frame.
+pub const TCP_PROBE_LEN_V4: usize = ETHER_HEADER_LEN + IPV4_HEADER_LEN + TCP_HEADER_LEN;
+
+/// The length of an IPv6 probe frame.
+pub const TCP_PROBE_LEN_V6: usize = ETHER_HEADER_LEN + IPV6_HEADER_LEN + TCP_HEADER_LEN;
+
+/// The kind of a TCP probe, which decides its flags.
+///
+/// The replies tell the state of the probed port:
+///
+/// | Probe | SYN-ACK | RST | No reply |
+/// |-------|---------|-----|----------|
+/// | `Syn` | open | closed | filtered |
+/// | `Ack` | - | unfiltered | filtered |
+/// | `Fin`, `Null`, `Xmas` | - | closed | open or filtered |
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TcpProbeKind {
+    /// A SYN segment, the first step of the handshake.
+    Syn,
+    /// An ACK segment without a connection, to map firewall rules.
+    Ack,
+    /// A FIN segment.
+    Fin,
+    /// A segment without any flag.
+    Null,
+    /// A segment with the FIN, PSH and URG flags.
+    Xmas,
+}
+
+/// The kind of a reply to a TCP probe.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TcpReplyKind {
+    SynAck,
+    Rst,
+}
+
+/// A reply matched to a TCP probe.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TcpProbeReply {
+    /// The probed address and port, which sent the reply.
+    pub target: SocketAddr,
+    /// The source address and port of the probe.
+    pub local: SocketAddr,
+    pub kind: TcpReplyKind,
+    /// The TTL or hop limit of the reply.
+    pub ttl: u8,
+    /// The window size of the reply.
+    pub window: u16,
+}
+
+/// Writes TCP probes and matches their replies.
+#[derive(Debug, Clone)]
+pub struct TcpProber {
+    src_mac: MacAddr,
+    dst_mac: MacAddr,
+    src_addr: IpAddr,
+    seed: u64,
+    ttl: u8,
+    window: u16,
+}
+
+impl TcpProber {
+    /// Create a prober that sends the probes from `src_addr`, through the
+    /// next hop `dst_mac`.
+    ///
+    /// The sequence numbers of the probes are keyed by `seed`, which should be
+    /// random and kept secret.
+    pub fn new(src_mac: MacAddr, dst_mac: MacAddr, src_addr: IpAddr, seed: u64) -> Self {
+        Self {
+            src_mac,
+            dst_mac,
+            src_addr,
+            seed,
+            ttl: 64,
+            window: 1024,
+        }
+    }
+
+    /// Set the TTL or hop limit of the probes, 64 by default.
+    pub fn set_ttl(&mut self, ttl: u8) {
+        self.ttl = ttl;
+    }
+
+    /// Set the window size of the probes, 1024 by default.
+    pub fn set_window(&mut self, window: u16) {
+        self.window = window;
+    }
+
+    /// Returns the initial sequence number of the probe from `local` to
+    /// `target`.
+    pub fn isn(&self, local: SocketAddr, target: SocketAddr) -> u32 {
+        let mut hasher = DefaultHasher::new();
+        hasher.write_u64(self.seed);
+        for addr in [local, target] {
+            match addr.ip() {
+                IpAddr::V4(ip) => hasher.write(&ip.octets()),
+                IpAddr::V6(ip) => hasher.write(&ip.octets()),
+            }
+            hasher.write_u16(addr.port());
+        }
+        hasher.finish() as u32
+    }
+
+    /// Write the probe of `kind` from port `src_port` to `target` into `buf`,
+    /// returning the length of the frame.
+    ///
+    /// Returns `None` if `buf` is too small, or if the address families of the
+    /// prober and `target` differ.
+    pub fn write_probe(
+        &self,
+        buf: &mut [u8],
+        kind: TcpProbeKind,
+        target: SocketAddr,
+        src_port: u16,
+    ) -> Option<usize> {
+        self.write_spoofed_probe(buf, kind, SocketAddr::new(self.src_addr, src_port), target)
+    }
+
+    /// Write the probe of `kind` from the spoofed address `local` to `target`
+    /// into `buf`, returning the length of the frame.
+    ///
+    /// Returns `None` if `buf` is too small, or if the address families of
+    /// `local` and `target` differ.
+    pub fn write_spoofed_probe(
+        &self,
+        buf: &mut [u8],
+        kind: TcpProbeKind,
+        local: SocketAddr,
+        target: SocketAddr,
+    ) -> Option<usize> {
+        let (ethertype, ip_header_len) = match (local.ip(), target.ip()) {
+            (IpAddr::V4(_), IpAddr::V4(_)) => (EtherType::IPV4, IPV4_HEADER_LEN),
+            (IpAddr::V6(_), IpAddr::V6(_)) => (EtherType::IPV6, IPV6_HEADER_LEN),
+            _ => return None,
+        };
+        let len = ETHER_HEADER_LEN + ip_header_len + TCP_HEADER_LEN;
+        let frame = buf.get_mut(..len)?;
+        let (ether, rest) = frame.split_at_mut(ETHER_HEADER_LEN);
+        let (ip, tcp) = rest.split_at_mut(ip_header_len);
+
+        let mut header = ETHER_HEADER_TEMPLATE;
+        header.set_dest_mac(self.dst_mac);
+        header.set_source_mac(self.src_mac);
+        header.set_ethertype(ethertype);
+        ether.copy_from_slice(header.as_bytes());
+
+        let isn = self.isn(local, target);
+        tcp.copy_from_slice(TCP_HEADER_TEMPLATE.as_bytes());
+        let mut header = TcpHeader::new_unchecked(&mut tcp[..]);
+        header.set_src_port(local.port());
+        header.set_dst_port(target.port());
+        header.set_seq_number(isn);
+        header.set_window_size(self.window);
+        match kind {
+            TcpProbeKind::Syn => header.set_syn(true),
+            TcpProbeKind::Ack => {
+                // A RST in reply to an ACK carries the acknowledgment number as
+                // its sequence number, so the ISN is repeated here.
+                header.set_ack(true);
+                header.set_ack_number(isn);
+            }
+            TcpProbeKind::Fin => header.set_fin(true),
+            TcpProbeKind::Null => {}
+            TcpProbeKind::Xmas => {
+                header.set_fin(true);
+                header.set_psh(true);
+                header.set_urg(true);
+            }
+        }
+
+        let mut pseudo = ChecksumAccumulator::new();
+        match (local.ip(), target.ip()) {
+            (IpAddr::V4(src), IpAddr::V4(dst)) => {
+                ip.copy_from_slice(IPV4_HEADER_TEMPLATE.as_bytes());
+                let mut header = Ipv4Header::new_unchecked(&mut ip[..]);
+                header.set_packet_len((IPV4_HEADER_LEN + TCP_HEADER_LEN) as u16);
+                header.set_ident((isn >> 16) as u16 ^ isn as u16);
+                header.set_time_to_live(self.ttl);
+                header.set_protocol(IpProtocol::TCP);
+                header.set_source_ip(src.into());
+                header.set_dest_ip(dst.into());
+                let checksum = !checksum_utils::from_slice(header.as_bytes());
+                header.set_checksum(checksum);
+
+                pseudo.update(&src.octets());
+                pseudo.update(&dst.octets());
+            }
+            (IpAddr::V6(src), IpAddr::V6(dst)) => {
+                ip.fill(0);
+                let mut header = Ipv6Header::new_unchecked(&mut ip[..]);
+                header.adjust_version();
+                header.set_payload_len(TCP_HEADER_LEN as u16);
+                header.set_next_header(IpProtocol::TCP);
+                header.set_hop_limit(self.ttl);
+                header.set_source_ip(&src.into());
+                header.set_dest_ip(&dst.into());
+
+                pseudo.update(&src.octets());
+                pseudo.update(&dst.octets());
+            }
+            _ => unreachable!(),
+        }
+        pseudo.update(&[0, u8::from(IpProtocol::TCP)]);
+        pseudo.update(&(TCP_HEADER_LEN as u16).to_be_bytes());
+        pseudo.update(tcp);
+        let checksum = !pseudo.finish();
+        TcpHeader::new_unchecked(&mut tcp[..]).set_checksum(checksum);
+
+        Some(len)
+    }
+
+    /// Match the Ethernet frame `frame` against the probes of this prober.
+    ///
+    /// Returns `None` if `frame` is not a SYN-ACK or a RST that acknowledges
+    /// a probe. The source address of the probe is taken from the destination
+    /// of the reply, so the replies to spoofed probes are matched as well.
+    pub fn match_reply(&self, frame: &[u8]) -> Option<TcpProbeReply> {
+        let ether = EtherHeader::new(frame).ok()?;
+        let packet = &frame[ETHER_HEADER_LEN..];
+        let (src, dst, ttl, segment) = match ether.ethertype() {
+            EtherType::IPV4 => {
+                let header = Ipv4Header::new(packet).ok()?;
+                let header_len = usize::from(header.header_len());
+                if header.protocol() != IpProtocol::TCP
+                    || header.more_frags()
+                    || header.frag_offset() != 0
+                    || header_len < IPV4_HEADER_LEN
+                {
+                    return None;
+                }
+                (
+                    IpAddr::from(std::net::Ipv4Addr::from(header.source_ip())),
+                    IpAddr::from(std::net::Ipv4Addr::from(header.dest_ip())),
+                    header.time_to_live(),
+                    packet.get(header_len..)?,
+                )
+            }
+            EtherType::IPV6 => {
+                let header = Ipv6Header::new(packet).ok()?;
+                if header.next_header() != IpProtocol::TCP {
+                    return None;
+                }
+                (
+                    IpAddr::from(std::net::Ipv6Addr::from(header.source_ip())),
+                    IpAddr::from(std::net::Ipv6Addr::from(header.dest_ip())),
+                    header.hop_limit(),
+                    &packet[IPV6_HEADER_LEN..],
+                )
+            }
+            _ => return None,
+        };
+
+        let tcp = TcpHeader::new(segment).ok()?;
+        let target = SocketAddr::new(src, tcp.src_port());
+        let local = SocketAddr::new(dst, tcp.dst_port());
+        let isn = self.isn(local, target);
+        let kind = if tcp.syn() && tcp.ack() && !tcp.rst() {
+            if tcp.ack_number() != isn.wrapping_add(1) {
+                return None;
+            }
+            TcpReplyKind::SynAck
+        } else if tcp.rst() {
+            // A RST acknowledges the SYN and FIN flags of the probe, or
+            // repeats the acknowledgment number of an ACK probe.
+            let acked =
+                tcp.ack() && (tcp.ack_number() == isn || tcp.ack_number() == isn.wrapping_add(1));
+            if !acked && tcp.seq_number() != isn {
+                return None;
+            }
+            TcpReplyKind::Rst
+        } else {
+            return None;
+        };
+
+        Some(TcpProbeReply {
+            target,
+            local,
+            kind,
+            ttl,
+            window: tcp.window_size(),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tcp::TcpPacket;
+    use crate::Cursor;
+
+    const LOCAL_MAC: MacAddr = MacAddr([0x02, 0, 0, 0, 0, 1]);
+    const PEER_MAC: MacAddr = MacAddr([0x02, 0, 0, 0, 0, 2]);
+
+    // Turn a probe into the reply of the target, with the given flags and
+    // numbers.
+    fn reply(probe: &[u8], syn: bool, rst: bool, ack: Option<u32>, seq: u32) -> Vec<u8> {
+        let mut frame = probe.to_vec();
+        let ip_header_len = match EtherHeader::new(&frame[..]).unwrap().ethertype() {
+            EtherType::IPV4 => IPV4_HEADER_LEN,
+            _ => IPV6_HEADER_LEN,
+        };
+        let ip = &mut frame[ETHER_HEADER_LEN..ETHER_HEADER_LEN + ip_header_len];
+        let (src, dst) = if ip_header_len == IPV4_HEADER_LEN {
+            (12..16, 16..20)
+        } else {
+            (8..24, 24..40)
+        };
+        let src_bytes = ip[src.clone()].to_vec();
+        ip.copy_within(dst.clone(), src.start);
+        ip[dst].copy_from_slice(&src_bytes);
+
+        let tcp_off = ETHER_HEADER_LEN + ip_header_len;
+        let mut tcp = TcpHeader::new_unchecked(&mut frame[tcp_off..]);
+        let (src_port, dst_port) = (tcp.src_port(), tcp.dst_port());
+        tcp.set_src_port(dst_port);
+        tcp.set_dst_port(src_port);
+        tcp.clear_flags();
+        tcp.set_syn(syn);
+        tcp.set_rst(rst);
+        tcp.set_ack(ack.is_some());
+        tcp.set_ack_number(ack.unwrap_or(0));
+        tcp.set_seq_number(seq);
+        frame
+    }
+
+    #[test]
+    fn ipv4_probes() {
+        let local: IpAddr = "192.0.2.1".parse().unwrap();
+        let mut prober = TcpProber::new(LOCAL_MAC, PEER_MAC, local, 7);
+        prober.set_ttl(50);
+        let target: SocketAddr = "198.51.100.7:80".parse().unwrap();
+        let isn = prober.isn(SocketAddr::new(local, 40000), target);
+        assert_ne!(
+            isn,
+            TcpProber::new(LOCAL_MAC, PEER_MAC, local, 8)
+                .isn(SocketAddr::new(local, 40000), target)
+        );
+
+        let mut buf = [0; TCP_PROBE_LEN_V4];
+        assert!(prober
+            .write_probe(&mut buf[..53], TcpProbeKind::Syn, target, 40000)
+            .is_none());
+        assert!(prober
+            .write_probe(
+                &mut buf,
+                TcpProbeKind::Syn,
+                "[2001:db8::1]:80".parse().unwrap(),
+                40000
+            )
+            .is_none());
+        let len = prober
+            .write_probe(&mut buf, TcpProbeKind::Syn, target, 40000)
+            .unwrap();
+        assert_eq!(len, TCP_PROBE_LEN_V4);
+
+        let ether = EtherHeader::new(&buf[..]).unwrap();
+        assert_eq!(ether.dest_mac(), PEER_MAC);
+        assert_eq!(ether.source_mac(), LOCAL_MAC);
+        let ip = Ipv4Header::new(&buf[ETHER_HEADER_LEN..]).unwrap();
+        assert_eq!(ip.time_to_live(), 50);
+        assert_eq!(ip.packet_len(), 40);
+        assert_eq!(
+            checksum_utils::from_slice(&buf[ETHER_HEADER_LEN..][..IPV4_HEADER_LEN]),
+            0xffff
+        );
+
+        let mut tcp =
+            TcpPacket::parse(Cursor::new(&buf[ETHER_HEADER_LEN + IPV4_HEADER_LEN..])).unwrap();
+        assert!(tcp.syn() && !tcp.ack() && !tcp.fin());
+        assert_eq!(
+            (tcp.src_port(), tcp.dst_port(), tcp.seq_number()),
+            (40000, 80, isn)
+        );
+        assert_eq!(tcp.window_size(), 1024);
+        assert!(tcp.verify_ipv4_checksum(ip.source_ip(), ip.dest_ip()));
+
+        let syn_ack = reply(&buf, true, false, Some(isn.wrapping_add(1)), 1);
+        assert_eq!(
+            prober.match_reply(&syn_ack),
+            Some(TcpProbeReply {
+                target,
+                local: SocketAddr::new(local, 40000),
+                kind: TcpReplyKind::SynAck,
+                ttl: 50,
+                window: 1024,
+            })
+        );
+        let rst = reply(&buf, false, true, Some(isn.wrapping_add(1)), 0);
+        assert_eq!(prober.match_reply(&rst).unwrap().kind, TcpReplyKind::Rst);
+        // Forged or stray replies are not matched, nor is the probe itself.
+        assert_eq!(
+            prober.match_reply(&reply(&buf, true, false, Some(isn), 1)),
+            None
+        );
+        assert_eq!(
+            prober.match_reply(&reply(&buf, false, true, Some(isn.wrapping_add(2)), 5)),
+            None
+        );
+        assert_eq!(
+            prober.match_reply(&reply(&buf, false, false, Some(isn.wrapping_add(1)), 5)),
+            None
+        );
+        assert_eq!(prober.match_reply(&buf), None);
+
+        // An ACK probe is answered by a RST without ACK.
+        prober
+            .write_probe(&mut buf, TcpProbeKind::Ack, target, 40000)
+            .unwrap();
+        let tcp = TcpHeader::new(&buf[ETHER_HEADER_LEN + IPV4_HEADER_LEN..]).unwrap();
+        assert!(tcp.ack() && !tcp.syn());
+        assert_eq!(tcp.ack_number(), isn);
+        assert_eq!(
+            prober
+                .match_reply(&reply(&buf, false, true, None, isn))
+                .unwrap()
+                .kind,
+            TcpReplyKind::Rst
+        );
+
+        prober
+            .write_probe(&mut buf, TcpProbeKind::Xmas, target, 40000)
+            .unwrap();
+        let tcp = TcpHeader::new(&buf[ETHER_HEADER_LEN + IPV4_HEADER_LEN..]).unwrap();
+        assert!(tcp.fin() && tcp.psh() && tcp.urg() && !tcp.ack());
+        prober
+            .write_probe(&mut buf, TcpProbeKind::Null, target, 40000)
+            .unwrap();
+        let tcp = TcpHeader::new(&buf[ETHER_HEADER_LEN + IPV4_HEADER_LEN..]).unwrap();
+        assert!(!tcp.fin() && !tcp.syn() && !tcp.ack() && !tcp.rst());
+        assert_eq!(
+            prober
+                .match_reply(&reply(&buf, false, true, Some(isn), 0))
+                .unwrap()
+                .kind,
+            TcpReplyKind::Rst
+        );
+
+        // The replies to a spoofed probe are matched to the spoofed address.
+        let spoofed: SocketAddr = "203.0.113.9:1234".parse().unwrap();
+        prober
+            .write_spoofed_probe(&mut buf, TcpProbeKind::Syn, spoofed, target)
+            .unwrap();
+        let ip = Ipv4Header::new(&buf[ETHER_HEADER_LEN..]).unwrap();
+        assert_eq!(ip.source_ip(), crate::ipv4::Ipv4Addr::new(203, 0, 113, 9));
+        let mut tcp =
+            TcpPacket::parse(Cursor::new(&buf[ETHER_HEADER_LEN + IPV4_HEADER_LEN..])).unwrap();
+        assert!(tcp.verify_ipv4_checksum(ip.source_ip(), ip.dest_ip()));
+        let isn = prober.isn(spoofed, target);
+        let matched = prober
+            .match_reply(&reply(&buf, true, false, Some(isn.wrapping_add(1)), 0))
+            .unwrap();
+        assert_eq!(matched.local, spoofed);
+    }
+
+    #[test]
+    fn ipv6_probes() {
+        let local: IpAddr = "2001:db8::1".parse().unwrap();
+        let prober = TcpProber::new(LOCAL_MAC, PEER_MAC, local, 7);
+        let target: SocketAddr = "[2001:db8::2]:22".parse().unwrap();
+        let isn = prober.isn(SocketAddr::new(local, 50000), target);
+
+        let mut buf = [0; TCP_PROBE_LEN_V6];
+        let len = prober
+            .write_probe(&mut buf, TcpProbeKind::Fin, target, 50000)
+            .unwrap();
+        assert_eq!(len, TCP_PROBE_LEN_V6);
+        let ip = Ipv6Header::new(&buf[ETHER_HEADER_LEN..]).unwrap();
+        assert_eq!(ip.payload_len(), TCP_HEADER_LEN as u16);
+        assert_eq!(ip.hop_limit(), 64);
+
+        // The checksum covers the pseudo header.
+        let mut accum = ChecksumAccumulator::new();
+        accum.update(&buf[ETHER_HEADER_LEN + 8..ETHER_HEADER_LEN + IPV6_HEADER_LEN]);
+        accum.update(&[0, 6, 0, TCP_HEADER_LEN as u8]);
+        accum.update(&buf[ETHER_HEADER_LEN + IPV6_HEADER_LEN..]);
+        assert_eq!(accum.finish(), 0xffff);
+
+        let rst = reply(&buf, false, true, Some(isn.wrapping_add(1)), 0);
+        let matched = prober.match_reply(&rst).unwrap();
+        assert_eq!(matched.target, target);
+        assert_eq!(matched.kind, TcpReplyKind::Rst);
+    }
+}
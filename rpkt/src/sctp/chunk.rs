@@ -0,0 +1,515 @@
+use byteorder::{ByteOrder, NetworkEndian};
+
+use super::ChunkType;
+
+/// The length of the chunk type, the chunk flags and the chunk length.
+pub const SCTP_CHUNK_HEADER_LEN: usize = 4;
+
+// The lengths of the chunks without their variable-length parts.
+const DATA_FIXED_LEN: usize = 16;
+const INIT_FIXED_LEN: usize = 20;
+const SACK_FIXED_LEN: usize = 16;
+const SHUTDOWN_LEN: usize = 8;
+
+pub enum SctpChunk<'a> {
+    Data(SctpChunkData<&'a [u8]>),
+    Init(SctpChunkInit<&'a [u8]>),
+    InitAck(SctpChunkInit<&'a [u8]>),
+    Sack(SctpChunkSack<&'a [u8]>),
+    Heartbeat(SctpChunkHeartbeat<&'a [u8]>),
+    HeartbeatAck(SctpChunkHeartbeat<&'a [u8]>),
+    Abort(SctpChunkAbort<&'a [u8]>),
+    Shutdown(SctpChunkShutdown<&'a [u8]>),
+    /// The type, the flags and the value of a chunk without a dedicated
+    /// container.
+    Other(ChunkType, u8, &'a [u8]),
+}
+
+pub struct SctpChunkData<T> {
+    buf: T,
+}
+
+impl<T: AsRef<[u8]>> SctpChunkData<T> {
+    /// Returns the I bit, which asks for an immediate SACK (RFC 7053).
+    #[inline]
+    pub fn i_flag(&self) -> bool {
+        self.buf.as_ref()[1] & 0x08 != 0
+    }
+
+    /// Returns the U bit, which marks an unordered user message.
+    #[inline]
+    pub fn u_flag(&self) -> bool {
+        self.buf.as_ref()[1] & 0x04 != 0
+    }
+
+    /// Returns the B bit, which marks the first fragment of a user message.
+    #[inline]
+    pub fn b_flag(&self) -> bool {
+        self.buf.as_ref()[1] & 0x02 != 0
+    }
+
+    /// Returns the E bit, which marks the last fragment of a user message.
+    #[inline]
+    pub fn e_flag(&self) -> bool {
+        self.buf.as_ref()[1] & 0x01 != 0
+    }
+
+    #[inline]
+    pub fn tsn(&self) -> u32 {
+        NetworkEndian::read_u32(&self.buf.as_ref()[4..8])
+    }
+
+    #[inline]
+    pub fn stream_id(&self) -> u16 {
+        NetworkEndian::read_u16(&self.buf.as_ref()[8..10])
+    }
+
+    #[inline]
+    pub fn stream_seq(&self) -> u16 {
+        NetworkEndian::read_u16(&self.buf.as_ref()[10..12])
+    }
+
+    /// Returns the payload protocol identifier.
+    #[inline]
+    pub fn ppid(&self) -> u32 {
+        NetworkEndian::read_u32(&self.buf.as_ref()[12..16])
+    }
+
+    #[inline]
+    pub fn user_data(&self) -> &[u8] {
+        &self.buf.as_ref()[DATA_FIXED_LEN..]
+    }
+}
+
+impl<T: AsMut<[u8]>> SctpChunkData<T> {
+    /// Set the I, U, B and E bits, from the most significant.
+    #[inline]
+    pub fn set_flags(&mut self, i: bool, u: bool, b: bool, e: bool) {
+        let flags = [i, u, b, e]
+            .iter()
+            .fold(0, |flags, bit| (flags << 1) | u8::from(*bit));
+        self.buf.as_mut()[1] = flags;
+    }
+
+    #[inline]
+    pub fn set_tsn(&mut self, value: u32) {
+        NetworkEndian::write_u32(&mut self.buf.as_mut()[4..8], value);
+    }
+
+    #[inline]
+    pub fn set_stream_id(&mut self, value: u16) {
+        NetworkEndian::write_u16(&mut self.buf.as_mut()[8..10], value);
+    }
+
+    #[inline]
+    pub fn set_stream_seq(&mut self, value: u16) {
+        NetworkEndian::write_u16(&mut self.buf.as_mut()[10..12], value);
+    }
+
+    #[inline]
+    pub fn set_ppid(&mut self, value: u32) {
+        NetworkEndian::write_u32(&mut self.buf.as_mut()[12..16], value);
+    }
+
+    #[inline]
+    pub fn user_data_mut(&mut self) -> &mut [u8] {
+        &mut self.buf.as_mut()[DATA_FIXED_LEN..]
+    }
+}
+
+/// The INIT or the INIT ACK chunk.
+pub struct SctpChunkInit<T> {
+    buf: T,
+}
+
+impl<T: AsRef<[u8]>> SctpChunkInit<T> {
+    #[inline]
+    pub fn initiate_tag(&self) -> u32 {
+        NetworkEndian::read_u32(&self.buf.as_ref()[4..8])
+    }
+
+    /// Returns the advertised receiver window credit.
+    #[inline]
+    pub fn a_rwnd(&self) -> u32 {
+        NetworkEndian::read_u32(&self.buf.as_ref()[8..12])
+    }
+
+    #[inline]
+    pub fn outbound_streams(&self) -> u16 {
+        NetworkEndian::read_u16(&self.buf.as_ref()[12..14])
+    }
+
+    #[inline]
+    pub fn inbound_streams(&self) -> u16 {
+        NetworkEndian::read_u16(&self.buf.as_ref()[14..16])
+    }
+
+    #[inline]
+    pub fn initial_tsn(&self) -> u32 {
+        NetworkEndian::read_u32(&self.buf.as_ref()[16..20])
+    }
+
+    /// Returns the optional or variable-length parameters, e.g. the state
+    /// cookie of an INIT ACK.
+    #[inline]
+    pub fn param_bytes(&self) -> &[u8] {
+        &self.buf.as_ref()[INIT_FIXED_LEN..]
+    }
+}
+
+impl<T: AsMut<[u8]>> SctpChunkInit<T> {
+    #[inline]
+    pub fn set_initiate_tag(&mut self, value: u32) {
+        NetworkEndian::write_u32(&mut self.buf.as_mut()[4..8], value);
+    }
+
+    #[inline]
+    pub fn set_a_rwnd(&mut self, value: u32) {
+        NetworkEndian::write_u32(&mut self.buf.as_mut()[8..12], value);
+    }
+
+    #[inline]
+    pub fn set_outbound_streams(&mut self, value: u16) {
+        NetworkEndian::write_u16(&mut self.buf.as_mut()[12..14], value);
+    }
+
+    #[inline]
+    pub fn set_inbound_streams(&mut self, value: u16) {
+        NetworkEndian::write_u16(&mut self.buf.as_mut()[14..16], value);
+    }
+
+    #[inline]
+    pub fn set_initial_tsn(&mut self, value: u32) {
+        NetworkEndian::write_u32(&mut self.buf.as_mut()[16..20], value);
+    }
+
+    #[inline]
+    pub fn param_bytes_mut(&mut self) -> &mut [u8] {
+        &mut self.buf.as_mut()[INIT_FIXED_LEN..]
+    }
+}
+
+pub struct SctpChunkSack<T> {
+    buf: T,
+}
+
+impl<T: AsRef<[u8]>> SctpChunkSack<T> {
+    #[inline]
+    pub fn cum_tsn_ack(&self) -> u32 {
+        NetworkEndian::read_u32(&self.buf.as_ref()[4..8])
+    }
+
+    #[inline]
+    pub fn a_rwnd(&self) -> u32 {
+        NetworkEndian::read_u32(&self.buf.as_ref()[8..12])
+    }
+
+    #[inline]
+    pub fn num_gap_blocks(&self) -> u16 {
+        NetworkEndian::read_u16(&self.buf.as_ref()[12..14])
+    }
+
+    #[inline]
+    pub fn num_dup_tsns(&self) -> u16 {
+        NetworkEndian::read_u16(&self.buf.as_ref()[14..16])
+    }
+
+    /// Returns the start and the end offsets of the gap ack blocks, relative
+    /// to the cumulative TSN ack.
+    #[inline]
+    pub fn gap_blocks(&self) -> impl Iterator<Item = (u16, u16)> + '_ {
+        let len = usize::from(self.num_gap_blocks()) * 4;
+        self.buf.as_ref()[SACK_FIXED_LEN..SACK_FIXED_LEN + len]
+            .chunks_exact(4)
+            .map(|block| {
+                (
+                    NetworkEndian::read_u16(&block[0..2]),
+                    NetworkEndian::read_u16(&block[2..4]),
+                )
+            })
+    }
+
+    /// Returns the duplicate TSNs.
+    #[inline]
+    pub fn dup_tsns(&self) -> impl Iterator<Item = u32> + '_ {
+        let start = SACK_FIXED_LEN + usize::from(self.num_gap_blocks()) * 4;
+        let len = usize::from(self.num_dup_tsns()) * 4;
+        self.buf.as_ref()[start..start + len]
+            .chunks_exact(4)
+            .map(NetworkEndian::read_u32)
+    }
+}
+
+impl<T: AsMut<[u8]>> SctpChunkSack<T> {
+    #[inline]
+    pub fn set_cum_tsn_ack(&mut self, value: u32) {
+        NetworkEndian::write_u32(&mut self.buf.as_mut()[4..8], value);
+    }
+
+    #[inline]
+    pub fn set_a_rwnd(&mut self, value: u32) {
+        NetworkEndian::write_u32(&mut self.buf.as_mut()[8..12], value);
+    }
+
+    /// Set the gap ack block at `index`.
+    #[inline]
+    pub fn set_gap_block(&mut self, index: usize, start: u16, end: u16) {
+        let offset = SACK_FIXED_LEN + index * 4;
+        let data = &mut self.buf.as_mut()[offset..offset + 4];
+        NetworkEndian::write_u16(&mut data[0..2], start);
+        NetworkEndian::write_u16(&mut data[2..4], end);
+    }
+
+    /// Set the duplicate TSN at `index`, which follows the gap ack blocks.
+    #[inline]
+    pub fn set_dup_tsn(&mut self, index: usize, value: u32) {
+        let gaps = usize::from(NetworkEndian::read_u16(&self.buf.as_mut()[12..14]));
+        let offset = SACK_FIXED_LEN + (gaps + index) * 4;
+        NetworkEndian::write_u32(&mut self.buf.as_mut()[offset..offset + 4], value);
+    }
+}
+
+/// The HEARTBEAT or the HEARTBEAT ACK chunk.
+pub struct SctpChunkHeartbeat<T> {
+    buf: T,
+}
+
+impl<T: AsRef<[u8]>> SctpChunkHeartbeat<T> {
+    /// Returns the heartbeat information parameter, which is echoed back by
+    /// the HEARTBEAT ACK.
+    #[inline]
+    pub fn info(&self) -> &[u8] {
+        &self.buf.as_ref()[SCTP_CHUNK_HEADER_LEN..]
+    }
+}
+
+impl<T: AsMut<[u8]>> SctpChunkHeartbeat<T> {
+    #[inline]
+    pub fn info_mut(&mut self) -> &mut [u8] {
+        &mut self.buf.as_mut()[SCTP_CHUNK_HEADER_LEN..]
+    }
+}
+
+pub struct SctpChunkAbort<T> {
+    buf: T,
+}
+
+impl<T: AsRef<[u8]>> SctpChunkAbort<T> {
+    /// Returns the T bit, which is set if the verification tag of the packet
+    /// is reflected from the aborted packet.
+    #[inline]
+    pub fn t_flag(&self) -> bool {
+        self.buf.as_ref()[1] & 0x01 != 0
+    }
+
+    #[inline]
+    pub fn error_causes(&self) -> &[u8] {
+        &self.buf.as_ref()[SCTP_CHUNK_HEADER_LEN..]
+    }
+}
+
+impl<T: AsMut<[u8]>> SctpChunkAbort<T> {
+    #[inline]
+    pub fn set_t_flag(&mut self, value: bool) {
+        self.buf.as_mut()[1] = u8::from(value);
+    }
+
+    #[inline]
+    pub fn error_causes_mut(&mut self) -> &mut [u8] {
+        &mut self.buf.as_mut()[SCTP_CHUNK_HEADER_LEN..]
+    }
+}
+
+pub struct SctpChunkShutdown<T> {
+    buf: T,
+}
+
+impl<T: AsRef<[u8]>> SctpChunkShutdown<T> {
+    #[inline]
+    pub fn cum_tsn_ack(&self) -> u32 {
+        NetworkEndian::read_u32(&self.buf.as_ref()[4..8])
+    }
+}
+
+impl<T: AsMut<[u8]>> SctpChunkShutdown<T> {
+    #[inline]
+    pub fn set_cum_tsn_ack(&mut self, value: u32) {
+        NetworkEndian::write_u32(&mut self.buf.as_mut()[4..8], value);
+    }
+}
+
+#[inline]
+fn padded(len: usize) -> usize {
+    (len + 3) & !3
+}
+
+pub struct SctpChunkIter<'a> {
+    buf: &'a [u8],
+    valid: bool,
+}
+
+impl<'a> SctpChunkIter<'a> {
+    #[inline]
+    pub fn from_chunk_bytes(buf: &'a [u8]) -> SctpChunkIter<'a> {
+        Self { buf, valid: true }
+    }
+
+    #[inline]
+    pub fn check_chunk_bytes(buf: &'a [u8]) -> bool {
+        let mut reader = Self::from_chunk_bytes(buf);
+        reader.by_ref().for_each(drop);
+        reader.valid
+    }
+}
+
+impl<'a> Iterator for SctpChunkIter<'a> {
+    type Item = SctpChunk<'a>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if !self.valid || self.buf.is_empty() {
+            return None;
+        }
+
+        if self.buf.len() < SCTP_CHUNK_HEADER_LEN {
+            self.valid = false;
+            return None;
+        }
+
+        let chunk_type = ChunkType::from(self.buf[0]);
+        let chunk_len = usize::from(NetworkEndian::read_u16(&self.buf[2..4]));
+        let valid_len = match chunk_type {
+            ChunkType::DATA => chunk_len >= DATA_FIXED_LEN,
+            ChunkType::INIT | ChunkType::INIT_ACK => chunk_len >= INIT_FIXED_LEN,
+            ChunkType::SACK => {
+                chunk_len >= SACK_FIXED_LEN && self.buf.len() >= SACK_FIXED_LEN && {
+                    let gaps = usize::from(NetworkEndian::read_u16(&self.buf[12..14]));
+                    let dups = usize::from(NetworkEndian::read_u16(&self.buf[14..16]));
+                    chunk_len == SACK_FIXED_LEN + (gaps + dups) * 4
+                }
+            }
+            ChunkType::SHUTDOWN => chunk_len == SHUTDOWN_LEN,
+            _ => chunk_len >= SCTP_CHUNK_HEADER_LEN,
+        };
+        if !valid_len || self.buf.len() < chunk_len {
+            self.valid = false;
+            return None;
+        }
+
+        let buf = &self.buf[..chunk_len];
+        // The padding of the last chunk may be left out.
+        self.buf = &self.buf[padded(chunk_len).min(self.buf.len())..];
+
+        let chunk = match chunk_type {
+            ChunkType::DATA => SctpChunk::Data(SctpChunkData { buf }),
+            ChunkType::INIT => SctpChunk::Init(SctpChunkInit { buf }),
+            ChunkType::INIT_ACK => SctpChunk::InitAck(SctpChunkInit { buf }),
+            ChunkType::SACK => SctpChunk::Sack(SctpChunkSack { buf }),
+            ChunkType::HEARTBEAT => SctpChunk::Heartbeat(SctpChunkHeartbeat { buf }),
+            ChunkType::HEARTBEAT_ACK => SctpChunk::HeartbeatAck(SctpChunkHeartbeat { buf }),
+            ChunkType::ABORT => SctpChunk::Abort(SctpChunkAbort { buf }),
+            ChunkType::SHUTDOWN => SctpChunk::Shutdown(SctpChunkShutdown { buf }),
+            _ => SctpChunk::Other(chunk_type, buf[1], &buf[SCTP_CHUNK_HEADER_LEN..]),
+        };
+        Some(chunk)
+    }
+}
+
+/// Writes chunks one after another, each padded to a 4-byte boundary.
+pub struct SctpChunkWriter<'a> {
+    buf: &'a mut [u8],
+    written: usize,
+}
+
+impl<'a> SctpChunkWriter<'a> {
+    /// Write a DATA chunk with room for `data_len` bytes of user data.
+    pub fn data(&mut self, data_len: usize) -> SctpChunkData<&'a mut [u8]> {
+        let buf = self.chunk(ChunkType::DATA, DATA_FIXED_LEN + data_len);
+        SctpChunkData { buf }
+    }
+
+    /// Write an INIT chunk with room for `params_len` bytes of parameters.
+    pub fn init(&mut self, params_len: usize) -> SctpChunkInit<&'a mut [u8]> {
+        let buf = self.chunk(ChunkType::INIT, INIT_FIXED_LEN + params_len);
+        SctpChunkInit { buf }
+    }
+
+    /// Write an INIT ACK chunk with room for `params_len` bytes of
+    /// parameters.
+    pub fn init_ack(&mut self, params_len: usize) -> SctpChunkInit<&'a mut [u8]> {
+        let buf = self.chunk(ChunkType::INIT_ACK, INIT_FIXED_LEN + params_len);
+        SctpChunkInit { buf }
+    }
+
+    /// Write a SACK chunk with room for `gaps` gap ack blocks and `dups`
+    /// duplicate TSNs.
+    pub fn sack(&mut self, gaps: u16, dups: u16) -> SctpChunkSack<&'a mut [u8]> {
+        let len = SACK_FIXED_LEN + (usize::from(gaps) + usize::from(dups)) * 4;
+        let buf = self.chunk(ChunkType::SACK, len);
+        NetworkEndian::write_u16(&mut buf[12..14], gaps);
+        NetworkEndian::write_u16(&mut buf[14..16], dups);
+        SctpChunkSack { buf }
+    }
+
+    /// Write a HEARTBEAT chunk carrying `info`.
+    pub fn heartbeat(&mut self, info: &[u8]) {
+        let buf = self.chunk(ChunkType::HEARTBEAT, SCTP_CHUNK_HEADER_LEN + info.len());
+        buf[SCTP_CHUNK_HEADER_LEN..].copy_from_slice(info);
+    }
+
+    /// Write a HEARTBEAT ACK chunk echoing `info`.
+    pub fn heartbeat_ack(&mut self, info: &[u8]) {
+        let buf = self.chunk(ChunkType::HEARTBEAT_ACK, SCTP_CHUNK_HEADER_LEN + info.len());
+        buf[SCTP_CHUNK_HEADER_LEN..].copy_from_slice(info);
+    }
+
+    /// Write an ABORT chunk with room for `causes_len` bytes of error causes.
+    pub fn abort(&mut self, causes_len: usize) -> SctpChunkAbort<&'a mut [u8]> {
+        let buf = self.chunk(ChunkType::ABORT, SCTP_CHUNK_HEADER_LEN + causes_len);
+        SctpChunkAbort { buf }
+    }
+
+    pub fn shutdown(&mut self) -> SctpChunkShutdown<&'a mut [u8]> {
+        let buf = self.chunk(ChunkType::SHUTDOWN, SHUTDOWN_LEN);
+        SctpChunkShutdown { buf }
+    }
+
+    /// Write a chunk of `chunk_type` carrying `value`.
+    pub fn other(&mut self, chunk_type: ChunkType, flags: u8, value: &[u8]) {
+        let buf = self.chunk(chunk_type, SCTP_CHUNK_HEADER_LEN + value.len());
+        buf[1] = flags;
+        buf[SCTP_CHUNK_HEADER_LEN..].copy_from_slice(value);
+    }
+
+    #[inline]
+    pub fn from_chunk_bytes_mut(buf: &'a mut [u8]) -> Self {
+        Self { buf, written: 0 }
+    }
+
+    #[inline]
+    pub fn remaining_bytes(&self) -> usize {
+        self.buf.len()
+    }
+
+    /// Returns the number of bytes written so far, including the padding.
+    #[inline]
+    pub fn written_bytes(&self) -> usize {
+        self.written
+    }
+
+    // Write the header of a chunk of `len` bytes, and zero the rest of the
+    // chunk and the padding.
+    #[inline]
+    fn chunk(&mut self, chunk_type: ChunkType, len: usize) -> &'a mut [u8] {
+        assert!(len <= usize::from(u16::MAX));
+
+        let (buf, remaining) = std::mem::take(&mut self.buf).split_at_mut(padded(len));
+        self.buf = remaining;
+        self.written += buf.len();
+
+        buf[0] = chunk_type.into();
+        NetworkEndian::write_u16(&mut buf[2..4], len as u16);
+        buf[1] = 0;
+        buf[SCTP_CHUNK_HEADER_LEN..].fill(0);
+        &mut buf[..len]
+    }
+}
@@ -0,0 +1,50 @@
+// The table of the reflected CRC-32C (Castagnoli) polynomial, for one byte
+// at a time.
+const CRC32C_TABLE: [u32; 256] = crc32c_table();
+
+const fn crc32c_table() -> [u32; 256] {
+    let mut table = [0; 256];
+    let mut i = 0;
+    while i < 256 {
+        let mut crc = i as u32;
+        let mut bit = 0;
+        while bit < 8 {
+            crc = if crc & 1 != 0 {
+                (crc >> 1) ^ 0x82f6_3b78
+            } else {
+                crc >> 1
+            };
+            bit += 1;
+        }
+        table[i] = crc;
+        i += 1;
+    }
+    table
+}
+
+/// Calculate the CRC-32C of `data`, the checksum of SCTP (RFC 9260,
+/// appendix A).
+pub fn crc32c(data: &[u8]) -> u32 {
+    !update(!0, data)
+}
+
+// Feed `data` into the running `crc`, which is neither pre- nor
+// post-inverted.
+#[inline]
+pub(super) fn update(crc: u32, data: &[u8]) -> u32 {
+    data.iter().fold(crc, |crc, byte| {
+        CRC32C_TABLE[usize::from((crc as u8) ^ byte)] ^ (crc >> 8)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn crc32c_check_value() {
+        assert_eq!(crc32c(b"123456789"), 0xe306_9283);
+        assert_eq!(crc32c(&[0; 32]), 0x8a91_36aa);
+        assert_eq!(crc32c(&[]), 0);
+    }
+}
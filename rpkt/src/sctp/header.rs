@@ -0,0 +1,98 @@
+use byteorder::{ByteOrder, LittleEndian, NetworkEndian};
+
+use super::{checksum, dest_port, source_port, verification_tag};
+use super::{checksum_mut, dest_port_mut, source_port_mut, verification_tag_mut};
+
+/// The length of the SCTP common header.
+pub const SCTP_HEADER_LEN: usize = 12;
+
+pub const SCTP_HEADER_TEMPLATE: SctpHeader<[u8; SCTP_HEADER_LEN]> = SctpHeader {
+    buf: [
+        0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+    ],
+};
+
+#[derive(Clone, Copy, Debug)]
+pub struct SctpHeader<T> {
+    buf: T,
+}
+
+impl<T: AsRef<[u8]>> SctpHeader<T> {
+    #[inline]
+    pub fn new(buf: T) -> Result<Self, T> {
+        if buf.as_ref().len() >= SCTP_HEADER_LEN {
+            Ok(Self { buf })
+        } else {
+            Err(buf)
+        }
+    }
+
+    #[inline]
+    pub fn new_unchecked(buf: T) -> Self {
+        Self { buf }
+    }
+
+    #[inline]
+    pub fn as_bytes(&self) -> &[u8] {
+        &self.buf.as_ref()[0..SCTP_HEADER_LEN]
+    }
+
+    #[inline]
+    pub fn to_owned(&self) -> SctpHeader<[u8; SCTP_HEADER_LEN]> {
+        let mut buf = [0; SCTP_HEADER_LEN];
+        buf.copy_from_slice(self.as_bytes());
+        SctpHeader { buf }
+    }
+
+    #[inline]
+    pub fn source_port(&self) -> u16 {
+        let data = source_port(self.buf.as_ref());
+        NetworkEndian::read_u16(data)
+    }
+
+    #[inline]
+    pub fn dest_port(&self) -> u16 {
+        let data = dest_port(self.buf.as_ref());
+        NetworkEndian::read_u16(data)
+    }
+
+    #[inline]
+    pub fn verification_tag(&self) -> u32 {
+        let data = verification_tag(self.buf.as_ref());
+        NetworkEndian::read_u32(data)
+    }
+
+    /// Returns the CRC-32C checksum, which is transmitted in little-endian
+    /// byte order like the Ethernet FCS.
+    #[inline]
+    pub fn checksum(&self) -> u32 {
+        let data = checksum(self.buf.as_ref());
+        LittleEndian::read_u32(data)
+    }
+}
+
+impl<T: AsMut<[u8]>> SctpHeader<T> {
+    #[inline]
+    pub fn set_source_port(&mut self, value: u16) {
+        let data = source_port_mut(self.buf.as_mut());
+        NetworkEndian::write_u16(data, value)
+    }
+
+    #[inline]
+    pub fn set_dest_port(&mut self, value: u16) {
+        let data = dest_port_mut(self.buf.as_mut());
+        NetworkEndian::write_u16(data, value)
+    }
+
+    #[inline]
+    pub fn set_verification_tag(&mut self, value: u32) {
+        let data = verification_tag_mut(self.buf.as_mut());
+        NetworkEndian::write_u32(data, value)
+    }
+
+    #[inline]
+    pub fn set_checksum(&mut self, value: u32) {
+        let data = checksum_mut(self.buf.as_mut());
+        LittleEndian::write_u32(data, value)
+    }
+}
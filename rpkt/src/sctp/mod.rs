@@ -0,0 +1,42 @@
+enum_sim! {
+    /// The type of an SCTP chunk (RFC 9260).
+    ///
+    /// See https://www.iana.org/assignments/sctp-parameters/sctp-parameters.xhtml
+    pub struct ChunkType (u8) {
+        DATA = 0,
+        INIT = 1,
+        INIT_ACK = 2,
+        SACK = 3,
+        HEARTBEAT = 4,
+        HEARTBEAT_ACK = 5,
+        ABORT = 6,
+        SHUTDOWN = 7,
+        SHUTDOWN_ACK = 8,
+        ERROR = 9,
+        COOKIE_ECHO = 10,
+        COOKIE_ACK = 11,
+        SHUTDOWN_COMPLETE = 14,
+    }
+}
+
+header_field_range_accessors! {
+    (source_port, source_port_mut, 0..2),
+    (dest_port, dest_port_mut, 2..4),
+    (verification_tag, verification_tag_mut, 4..8),
+    (checksum, checksum_mut, 8..12),
+}
+
+mod header;
+pub use header::{SctpHeader, SCTP_HEADER_LEN, SCTP_HEADER_TEMPLATE};
+
+mod packet;
+pub use packet::SctpPacket;
+
+mod chunk;
+pub use chunk::{
+    SctpChunk, SctpChunkAbort, SctpChunkData, SctpChunkHeartbeat, SctpChunkInit, SctpChunkIter,
+    SctpChunkSack, SctpChunkShutdown, SctpChunkWriter, SCTP_CHUNK_HEADER_LEN,
+};
+
+mod crc32c;
+pub use crc32c::crc32c;
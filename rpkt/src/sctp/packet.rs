@@ -0,0 +1,235 @@
+use bytes::Buf;
+
+use crate::{PktBuf, PktMut};
+
+use super::crc32c;
+use super::header::{SctpHeader, SCTP_HEADER_LEN};
+
+packet_base! {
+    pub struct SctpPacket: SctpHeader {
+        header_len: SCTP_HEADER_LEN,
+        get_methods: [
+            (source_port, u16),
+            (dest_port, u16),
+            (verification_tag, u32),
+            (checksum, u32),
+        ],
+        set_methods: [
+            (set_source_port, value: u16),
+            (set_dest_port, value: u16),
+            (set_verification_tag, value: u32),
+            (set_checksum, value: u32),
+        ],
+        unchecked_set_methods: []
+    }
+}
+
+impl<T: Buf> SctpPacket<T> {
+    #[inline]
+    pub fn parse(buf: T) -> Result<SctpPacket<T>, T> {
+        traced_parse!("sctp", buf, |_: &Self| SCTP_HEADER_LEN, {
+            if buf.chunk().len() < SCTP_HEADER_LEN {
+                return Err(buf);
+            }
+
+            Ok(SctpPacket::parse_unchecked(buf))
+        })
+    }
+
+    /// Returns the chunks in the first chunk of the buffer, which can be
+    /// iterated with [`SctpChunkIter`](super::SctpChunkIter).
+    #[inline]
+    pub fn chunk_bytes(&self) -> &[u8] {
+        &self.buf.chunk()[SCTP_HEADER_LEN..]
+    }
+}
+
+impl<T: PktBuf> SctpPacket<T> {
+    /// Calculate the CRC-32C over the whole packet, with the checksum field
+    /// taken as zero.
+    pub fn calc_checksum(&mut self) -> u32 {
+        let total_len = self.buf.remaining();
+
+        let header = &self.buf.chunk()[..SCTP_HEADER_LEN];
+        let mut crc = crc32c::update(!0, &header[..8]);
+        crc = crc32c::update(crc, &[0; 4]);
+        self.buf.advance(SCTP_HEADER_LEN);
+        while self.buf.has_remaining() {
+            let chunk = self.buf.chunk();
+            crc = crc32c::update(crc, chunk);
+            let chunk_len = chunk.len();
+            self.buf.advance(chunk_len);
+        }
+
+        self.buf.move_back(total_len);
+        !crc
+    }
+
+    #[inline]
+    pub fn verify_checksum(&mut self) -> bool {
+        self.calc_checksum() == self.checksum()
+    }
+
+    /// Returns the buffer of the chunks.
+    #[inline]
+    pub fn payload(self) -> T {
+        let mut buf = self.release();
+        buf.advance(SCTP_HEADER_LEN);
+        buf
+    }
+}
+
+impl<T: PktMut> SctpPacket<T> {
+    #[inline]
+    pub fn adjust_checksum(&mut self) {
+        let cksum = self.calc_checksum();
+        self.set_checksum(cksum)
+    }
+
+    #[inline]
+    pub fn chunk_bytes_mut(&mut self) -> &mut [u8] {
+        &mut self.buf.chunk_mut()[SCTP_HEADER_LEN..]
+    }
+
+    /// Prepend the common header to the chunks in `buf`.
+    #[inline]
+    pub fn prepend_header<HT: AsRef<[u8]>>(mut buf: T, header: &SctpHeader<HT>) -> SctpPacket<T> {
+        assert!(buf.chunk_headroom() >= SCTP_HEADER_LEN);
+        buf.move_back(SCTP_HEADER_LEN);
+
+        let data = &mut buf.chunk_mut()[0..SCTP_HEADER_LEN];
+        data.copy_from_slice(header.as_bytes());
+
+        SctpPacket::parse_unchecked(buf)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sctp::*;
+    use crate::{Cursor, CursorMut};
+
+    // An INIT chunk from port 5000 to port 36412 without parameters.
+    static INIT: [u8; 32] = [
+        0x13, 0x88, 0x8e, 0x3c, 0x00, 0x00, 0x00, 0x00, 0xdd, 0x04, 0x73, 0x3b, 0x01, 0x00, 0x00,
+        0x14, 0x12, 0x34, 0x56, 0x78, 0x00, 0x01, 0x00, 0x00, 0x00, 0x0a, 0xff, 0xff, 0x00, 0x00,
+        0x00, 0x01,
+    ];
+
+    #[test]
+    fn parse_init() {
+        let mut pkt = SctpPacket::parse(Cursor::new(&INIT[..])).unwrap();
+        assert_eq!((pkt.source_port(), pkt.dest_port()), (5000, 36412));
+        assert_eq!(pkt.verification_tag(), 0);
+        assert!(pkt.verify_checksum());
+
+        let mut chunks = SctpChunkIter::from_chunk_bytes(pkt.chunk_bytes());
+        match chunks.next() {
+            Some(SctpChunk::Init(init)) => {
+                assert_eq!(init.initiate_tag(), 0x12345678);
+                assert_eq!(init.a_rwnd(), 65536);
+                assert_eq!(
+                    (init.outbound_streams(), init.inbound_streams()),
+                    (10, 0xffff)
+                );
+                assert_eq!(init.initial_tsn(), 1);
+                assert!(init.param_bytes().is_empty());
+            }
+            _ => panic!("no init chunk"),
+        }
+        assert!(chunks.next().is_none());
+
+        let mut bytes = INIT;
+        bytes[20] ^= 0x01;
+        let mut pkt = SctpPacket::parse(Cursor::new(&bytes[..])).unwrap();
+        assert!(!pkt.verify_checksum());
+    }
+
+    #[test]
+    fn build_chunks() {
+        let mut packet = [0xff; 128];
+        let mut writer = SctpChunkWriter::from_chunk_bytes_mut(&mut packet[SCTP_HEADER_LEN..]);
+        let mut data = writer.data(5);
+        data.set_flags(false, false, true, true);
+        data.set_tsn(100);
+        data.set_stream_id(1);
+        data.set_ppid(60);
+        data.user_data_mut().copy_from_slice(b"hello");
+        let mut sack = writer.sack(1, 1);
+        sack.set_cum_tsn_ack(99);
+        sack.set_a_rwnd(4096);
+        sack.set_gap_block(0, 2, 3);
+        sack.set_dup_tsn(0, 97);
+        writer.heartbeat(&[0x00, 0x01, 0x00, 0x06, 0xab, 0xcd]);
+        writer.shutdown().set_cum_tsn_ack(101);
+        writer.abort(0).set_t_flag(true);
+        let len = SCTP_HEADER_LEN + writer.written_bytes();
+        assert_eq!(len, 12 + 24 + 24 + 12 + 8 + 4);
+
+        let mut buf = CursorMut::new(&mut packet[..len]);
+        buf.advance(SCTP_HEADER_LEN);
+        let mut pkt = SctpPacket::prepend_header(buf, &SCTP_HEADER_TEMPLATE);
+        pkt.set_verification_tag(0xdeadbeef);
+        pkt.adjust_checksum();
+        assert!(pkt.verify_checksum());
+        let cksum = pkt.checksum();
+        assert_eq!(&packet[8..12], &cksum.to_le_bytes());
+
+        let pkt = SctpPacket::parse(Cursor::new(&packet[..len])).unwrap();
+        assert!(SctpChunkIter::check_chunk_bytes(pkt.chunk_bytes()));
+        let mut chunks = SctpChunkIter::from_chunk_bytes(pkt.chunk_bytes());
+        match chunks.next() {
+            Some(SctpChunk::Data(data)) => {
+                assert!(!data.i_flag() && !data.u_flag() && data.b_flag() && data.e_flag());
+                assert_eq!(
+                    (data.tsn(), data.stream_id(), data.stream_seq()),
+                    (100, 1, 0)
+                );
+                assert_eq!(data.ppid(), 60);
+                assert_eq!(data.user_data(), b"hello");
+            }
+            _ => panic!("no data chunk"),
+        }
+        match chunks.next() {
+            Some(SctpChunk::Sack(sack)) => {
+                assert_eq!((sack.cum_tsn_ack(), sack.a_rwnd()), (99, 4096));
+                assert_eq!(sack.gap_blocks().collect::<Vec<_>>(), [(2, 3)]);
+                assert_eq!(sack.dup_tsns().collect::<Vec<_>>(), [97]);
+            }
+            _ => panic!("no sack chunk"),
+        }
+        match chunks.next() {
+            Some(SctpChunk::Heartbeat(hb)) => assert_eq!(&hb.info()[4..], &[0xab, 0xcd]),
+            _ => panic!("no heartbeat chunk"),
+        }
+        match chunks.next() {
+            Some(SctpChunk::Shutdown(shutdown)) => assert_eq!(shutdown.cum_tsn_ack(), 101),
+            _ => panic!("no shutdown chunk"),
+        }
+        match chunks.next() {
+            Some(SctpChunk::Abort(abort)) => assert!(abort.t_flag()),
+            _ => panic!("no abort chunk"),
+        }
+        assert!(chunks.next().is_none());
+    }
+
+    #[test]
+    fn invalid_chunks() {
+        // A truncated chunk, a short SACK, and a COOKIE ACK without padding.
+        let init = &INIT[SCTP_HEADER_LEN..];
+        assert!(!SctpChunkIter::check_chunk_bytes(&init[..19]));
+        assert!(!SctpChunkIter::check_chunk_bytes(&[
+            0x03, 0x00, 0x00, 0x10, 0, 0, 0, 0, 0, 0, 0, 0, 0x00, 0x01, 0x00, 0x00
+        ]));
+        let mut chunks = SctpChunkIter::from_chunk_bytes(&[0x0b, 0x00, 0x00, 0x04]);
+        match chunks.next() {
+            Some(SctpChunk::Other(chunk_type, 0, value)) => {
+                assert_eq!(chunk_type, ChunkType::COOKIE_ACK);
+                assert!(value.is_empty());
+            }
+            _ => panic!("no cookie ack chunk"),
+        }
+        assert!(chunks.next().is_none());
+    }
+}
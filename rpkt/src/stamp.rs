@@ -0,0 +1,371 @@
+//! Sequence stamps in generated payloads, to detect loss, reordering and
+//! corruption on the receive side.
+//!
+//! The [`Stamper`] of the generator writes a stamp into every payload: a
+//! magic number, the ID of the flow and its next sequence number, followed by
+//! a fill [`Pattern`] seeded with the sequence number and by the CRC-32C of
+//! everything before it in the last 4 bytes:
+//!
+//! ```text
+//! 0      4        8               16                   len - 4    len
+//! | magic | flow ID | sequence number | pattern ...      | CRC-32C |
+//! ```
+//!
+//! The [`StampVerifier`] of the receiver checks the stamps and keeps the
+//! [`FlowStats`] of every flow. The payloads are found by the caller, e.g.
+//! the UDP payloads, so that any transport can carry them.
+//!
+//! # Examples
+//! ```
+//! use rpkt::fill::Pattern;
+//! use rpkt::stamp::{StampVerdict, StampVerifier, Stamper};
+//!
+//! let mut stamper = Stamper::new(Pattern::Incrementing);
+//! let mut payloads = vec![[0u8; 64]; 4];
+//! for payload in payloads.iter_mut() {
+//!     stamper.stamp(payload, 7);
+//! }
+//!
+//! // The second payload is lost and the last two are swapped.
+//! let mut verifier = StampVerifier::new(Pattern::Incrementing);
+//! for i in [0, 3, 2] {
+//!     verifier.check(&payloads[i]);
+//! }
+//! let stats = verifier.flow(7).unwrap();
+//! assert_eq!((stats.received, stats.lost, stats.reordered), (3, 1, 1));
+//! ```
+
+use std::collections::HashMap;
+
+use byteorder::{ByteOrder, NetworkEndian};
+
+use crate::fill::Pattern;
+use crate::sctp::crc32c;
+
+/// The magic number that starts a stamp, "rpkt" in ASCII.
+pub const STAMP_MAGIC: u32 = 0x7270_6b74;
+
+/// The length of the stamp without the pattern, the shortest payload that
+/// can be stamped.
+pub const STAMP_LEN: usize = 20;
+
+// The offset of the pattern in the payload.
+const PATTERN_OFFSET: usize = 16;
+
+// The number of sequence numbers below the highest one received whose
+// reception is remembered, to tell the duplicates from the late payloads.
+const WINDOW_LEN: u64 = 64;
+
+/// Writes the stamps of the generated payloads, with the sequence numbers of
+/// every flow starting from 0.
+#[derive(Debug, Clone)]
+pub struct Stamper {
+    pattern: Pattern,
+    next_seqs: HashMap<u32, u64>,
+}
+
+impl Stamper {
+    /// Create a stamper that fills the payloads with `pattern`.
+    pub fn new(pattern: Pattern) -> Self {
+        Self {
+            pattern,
+            next_seqs: HashMap::new(),
+        }
+    }
+
+    /// Stamp `payload` with the next sequence number of `flow`, returns the
+    /// sequence number.
+    ///
+    /// # Panics
+    ///
+    /// This function panics if `payload` is shorter than [`STAMP_LEN`].
+    pub fn stamp(&mut self, payload: &mut [u8], flow: u32) -> u64 {
+        let next_seq = self.next_seqs.entry(flow).or_insert(0);
+        let seq = *next_seq;
+        *next_seq += 1;
+        write_stamp(payload, self.pattern, flow, seq);
+        seq
+    }
+}
+
+/// Stamp `payload` with the sequence number `seq` of `flow`, filling it with
+/// `pattern`.
+///
+/// # Panics
+///
+/// This function panics if `payload` is shorter than [`STAMP_LEN`].
+pub fn write_stamp(payload: &mut [u8], pattern: Pattern, flow: u32, seq: u64) {
+    assert!(payload.len() >= STAMP_LEN);
+    let crc_offset = payload.len() - 4;
+    NetworkEndian::write_u32(&mut payload[0..4], STAMP_MAGIC);
+    NetworkEndian::write_u32(&mut payload[4..8], flow);
+    NetworkEndian::write_u64(&mut payload[8..16], seq);
+    pattern.fill(&mut payload[PATTERN_OFFSET..crc_offset], seq);
+    let crc = crc32c(&payload[..crc_offset]);
+    NetworkEndian::write_u32(&mut payload[crc_offset..], crc);
+}
+
+/// The outcome of checking a payload.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StampVerdict {
+    /// The sequence number is the one following the highest one received.
+    InOrder { flow: u32, seq: u64 },
+    /// The sequence number skips the ones that are counted as lost until they
+    /// are received.
+    Gap { flow: u32, seq: u64, missing: u64 },
+    /// The sequence number is below the highest one received, and it was
+    /// counted as lost.
+    Reordered { flow: u32, seq: u64 },
+    /// The sequence number was already received.
+    Duplicate { flow: u32, seq: u64 },
+    /// The CRC does not match. `offset` is the first byte that differs from
+    /// the pattern, if the corruption is in the pattern.
+    Corrupted { flow: u32, offset: Option<usize> },
+    /// The payload does not start with a stamp.
+    Unstamped,
+}
+
+/// The statistics of a flow.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct FlowStats {
+    /// The payloads received with a valid stamp, including the duplicates.
+    pub received: u64,
+    /// The sequence numbers below the highest one received that are not
+    /// received yet.
+    pub lost: u64,
+    /// The payloads received after a payload of a higher sequence number.
+    pub reordered: u64,
+    pub duplicated: u64,
+    /// The payloads whose CRC does not match, which are not counted as
+    /// received.
+    pub corrupted: u64,
+    /// The highest sequence number received, if any.
+    pub max_seq: Option<u64>,
+    // Bit `i` is set if `max_seq - i` is received.
+    window: u64,
+}
+
+impl FlowStats {
+    fn record(&mut self, flow: u32, seq: u64) -> StampVerdict {
+        self.received += 1;
+        let max_seq = match self.max_seq {
+            Some(max_seq) => max_seq,
+            None => {
+                // The sequence numbers start from 0.
+                self.lost = seq;
+                self.max_seq = Some(seq);
+                self.window = 1;
+                return match seq {
+                    0 => StampVerdict::InOrder { flow, seq },
+                    missing => StampVerdict::Gap { flow, seq, missing },
+                };
+            }
+        };
+
+        if seq > max_seq {
+            let missing = seq - max_seq - 1;
+            self.lost += missing;
+            self.max_seq = Some(seq);
+            let shift = seq - max_seq;
+            self.window = if shift < WINDOW_LEN {
+                (self.window << shift) | 1
+            } else {
+                1
+            };
+            return match missing {
+                0 => StampVerdict::InOrder { flow, seq },
+                missing => StampVerdict::Gap { flow, seq, missing },
+            };
+        }
+
+        let age = max_seq - seq;
+        if age < WINDOW_LEN {
+            if self.window & (1 << age) != 0 {
+                self.duplicated += 1;
+                return StampVerdict::Duplicate { flow, seq };
+            }
+            self.window |= 1 << age;
+        }
+        // Beyond the window, a duplicate can not be told from a late payload.
+        self.lost = self.lost.saturating_sub(1);
+        self.reordered += 1;
+        StampVerdict::Reordered { flow, seq }
+    }
+}
+
+/// Checks the stamps of the received payloads and keeps the statistics of
+/// every flow.
+#[derive(Debug, Clone)]
+pub struct StampVerifier {
+    pattern: Pattern,
+    flows: HashMap<u32, FlowStats>,
+    unstamped: u64,
+}
+
+impl StampVerifier {
+    /// Create a verifier of the payloads filled with `pattern`.
+    pub fn new(pattern: Pattern) -> Self {
+        Self {
+            pattern,
+            flows: HashMap::new(),
+            unstamped: 0,
+        }
+    }
+
+    /// Check the stamp of `payload` and update the statistics of its flow.
+    pub fn check(&mut self, payload: &[u8]) -> StampVerdict {
+        if payload.len() < STAMP_LEN || NetworkEndian::read_u32(&payload[0..4]) != STAMP_MAGIC {
+            self.unstamped += 1;
+            return StampVerdict::Unstamped;
+        }
+        let flow = NetworkEndian::read_u32(&payload[4..8]);
+        let seq = NetworkEndian::read_u64(&payload[8..16]);
+        let stats = self.flows.entry(flow).or_default();
+
+        let crc_offset = payload.len() - 4;
+        if crc32c(&payload[..crc_offset]) != NetworkEndian::read_u32(&payload[crc_offset..]) {
+            stats.corrupted += 1;
+            // The sequence number may be corrupted as well.
+            let offset = self
+                .pattern
+                .check(&payload[PATTERN_OFFSET..crc_offset], seq)
+                .map(|offset| PATTERN_OFFSET + offset);
+            return StampVerdict::Corrupted { flow, offset };
+        }
+        stats.record(flow, seq)
+    }
+
+    /// Returns the statistics of `flow`, if any payload of it is received.
+    pub fn flow(&self, flow: u32) -> Option<&FlowStats> {
+        self.flows.get(&flow)
+    }
+
+    /// Returns the flows and their statistics, in no particular order.
+    pub fn flows(&self) -> impl Iterator<Item = (u32, &FlowStats)> {
+        self.flows.iter().map(|(flow, stats)| (*flow, stats))
+    }
+
+    /// Returns the number of payloads without a stamp.
+    pub fn unstamped(&self) -> u64 {
+        self.unstamped
+    }
+
+    /// Reset the statistics of all the flows.
+    pub fn clear(&mut self) {
+        self.flows.clear();
+        self.unstamped = 0;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn stamped(count: usize, flow: u32) -> Vec<Vec<u8>> {
+        let mut stamper = Stamper::new(Pattern::Prbs15);
+        (0..count)
+            .map(|_| {
+                let mut payload = vec![0; 40];
+                stamper.stamp(&mut payload, flow);
+                payload
+            })
+            .collect()
+    }
+
+    #[test]
+    fn stamp_layout() {
+        let payload = &stamped(2, 9)[1];
+        assert_eq!(&payload[..4], b"rpkt");
+        assert_eq!(&payload[4..16], &[0, 0, 0, 9, 0, 0, 0, 0, 0, 0, 0, 1]);
+        assert_eq!(Pattern::Prbs15.check(&payload[16..36], 1), None);
+        assert_eq!(
+            NetworkEndian::read_u32(&payload[36..]),
+            crc32c(&payload[..36])
+        );
+    }
+
+    #[test]
+    fn loss_and_reordering() {
+        let payloads = stamped(200, 1);
+        let mut verifier = StampVerifier::new(Pattern::Prbs15);
+
+        assert_eq!(
+            verifier.check(&payloads[0]),
+            StampVerdict::InOrder { flow: 1, seq: 0 }
+        );
+        assert_eq!(
+            verifier.check(&payloads[3]),
+            StampVerdict::Gap {
+                flow: 1,
+                seq: 3,
+                missing: 2
+            }
+        );
+        assert_eq!(
+            verifier.check(&payloads[1]),
+            StampVerdict::Reordered { flow: 1, seq: 1 }
+        );
+        assert_eq!(
+            verifier.check(&payloads[1]),
+            StampVerdict::Duplicate { flow: 1, seq: 1 }
+        );
+        let stats = *verifier.flow(1).unwrap();
+        assert_eq!((stats.received, stats.lost), (4, 1));
+        assert_eq!((stats.reordered, stats.duplicated), (1, 1));
+        assert_eq!(stats.max_seq, Some(3));
+
+        // A payload later than the window of duplicates.
+        verifier.check(&payloads[199]);
+        assert_eq!(verifier.flow(1).unwrap().lost, 196);
+        assert_eq!(
+            verifier.check(&payloads[2]),
+            StampVerdict::Reordered { flow: 1, seq: 2 }
+        );
+        assert_eq!(verifier.flow(1).unwrap().lost, 195);
+
+        // A flow whose first payloads are lost.
+        let other = stamped(3, 2);
+        assert_eq!(
+            verifier.check(&other[2]),
+            StampVerdict::Gap {
+                flow: 2,
+                seq: 2,
+                missing: 2
+            }
+        );
+        assert_eq!(verifier.flows().count(), 2);
+    }
+
+    #[test]
+    fn corruption() {
+        let mut payload = stamped(1, 5).remove(0);
+        let mut verifier = StampVerifier::new(Pattern::Prbs15);
+
+        payload[20] ^= 0x01;
+        assert_eq!(
+            verifier.check(&payload),
+            StampVerdict::Corrupted {
+                flow: 5,
+                offset: Some(20)
+            }
+        );
+        payload[20] ^= 0x01;
+        payload[39] ^= 0x01;
+        assert_eq!(
+            verifier.check(&payload),
+            StampVerdict::Corrupted {
+                flow: 5,
+                offset: None
+            }
+        );
+        let stats = verifier.flow(5).unwrap();
+        assert_eq!((stats.received, stats.corrupted), (0, 2));
+
+        assert_eq!(verifier.check(&[0; 64]), StampVerdict::Unstamped);
+        assert_eq!(verifier.check(&payload[..10]), StampVerdict::Unstamped);
+        assert_eq!(verifier.unstamped(), 2);
+
+        verifier.clear();
+        assert!(verifier.flow(5).is_none());
+    }
+}
@@ -0,0 +1,324 @@
+//! Sampled Values of IEC 61850-9-2, which stream the measurements of the
+//! merging units of a substation.
+//!
+//! An SV message has the same 8-byte header as a GOOSE message, followed by
+//! the `savPdu` encoded with the Basic Encoding Rules. The PDU carries a
+//! sequence of ASDUs, each holding a sample of a data set, which are
+//! iterated by [`SvPacket::asdus`].
+//!
+//! # Examples
+//! ```
+//! use rpkt::sv::SvPacket;
+//! use rpkt::Cursor;
+//!
+//! let bytes = [
+//!     0x40, 0x00, 0x00, 0x1e, 0x00, 0x00, 0x00, 0x00, // header
+//!     0x60, 0x14, // savPdu
+//!     0x80, 0x01, 0x01, // noASDU
+//!     0xa2, 0x0f, // seqASDU
+//!     0x30, 0x0d, // ASDU
+//!     0x80, 0x02, b'M', b'U', // svID
+//!     0x82, 0x02, 0x0f, 0x9f, // smpCnt
+//!     0x87, 0x03, 0x00, 0x00, 0x2a, // seqData
+//! ];
+//! let pkt = SvPacket::parse(Cursor::new(&bytes[..])).unwrap();
+//! assert_eq!(pkt.no_asdu(), Some(1));
+//! let asdu = pkt.asdus().unwrap().next().unwrap();
+//! assert_eq!(asdu.sv_id(), Some(&b"MU"[..]));
+//! assert_eq!(asdu.smp_cnt(), Some(3999));
+//! ```
+
+use bytes::Buf;
+
+use crate::ether::{EtherPayload, EtherType};
+use crate::goose::{apdu_bytes, pdu_fields, prepend_apdu_header};
+use crate::tlv::{BerIter, BerTlv};
+use crate::PktBuf;
+
+pub use crate::goose::{
+    GooseHeader as SvHeader, GOOSE_HEADER_LEN as SV_HEADER_LEN,
+    GOOSE_HEADER_TEMPLATE as SV_HEADER_TEMPLATE,
+};
+
+/// The tag of the `savPdu`, `[APPLICATION 0]` constructed.
+pub const SV_PDU_TAG: u32 = 0x60;
+
+// The tag of an ASDU in the sequence, a universal SEQUENCE.
+const ASDU_TAG: u32 = 0x30;
+
+enum_sim! {
+    /// The tag of a field of the `savPdu`.
+    pub struct SvField (u32) {
+        NO_ASDU = 0x80,
+        SECURITY = 0x81,
+        /// The sequence of ASDUs, a constructed record.
+        SEQ_ASDU = 0xa2,
+    }
+}
+
+enum_sim! {
+    /// The tag of a field of an ASDU.
+    pub struct SvAsduField (u32) {
+        SV_ID = 0x80,
+        DAT_SET = 0x81,
+        SMP_CNT = 0x82,
+        CONF_REV = 0x83,
+        REFR_TM = 0x84,
+        SMP_SYNCH = 0x85,
+        SMP_RATE = 0x86,
+        /// The values of the data set, e.g. 8 currents and voltages with their
+        /// quality for the 9-2 LE profile.
+        SEQ_DATA = 0x87,
+        SMP_MOD = 0x88,
+    }
+}
+
+packet_base! {
+    /// A Sampled Values message.
+    pub struct SvPacket: SvHeader {
+        header_len: SV_HEADER_LEN,
+        get_methods: [
+            (appid, u16),
+            (packet_len, u16),
+            (reserved1, u16),
+            (reserved2, u16),
+            (simulation, bool),
+        ],
+        set_methods: [
+            (set_appid, value: u16),
+            (set_reserved1, value: u16),
+            (set_reserved2, value: u16),
+        ],
+        unchecked_set_methods: [
+            (set_packet_len_unchecked, set_packet_len, value: u16),
+        ]
+    }
+}
+
+impl<T: Buf> SvPacket<T> {
+    /// Parse an SV message, whose length field must cover the header and fit
+    /// in `buf`.
+    #[inline]
+    pub fn parse(buf: T) -> Result<SvPacket<T>, T> {
+        traced_parse!("sv", buf, |_: &Self| SV_HEADER_LEN, {
+            if buf.chunk().len() < SV_HEADER_LEN {
+                return Err(buf);
+            }
+
+            let packet = SvPacket::parse_unchecked(buf);
+            let packet_len = usize::from(packet.packet_len());
+            if packet_len >= SV_HEADER_LEN && packet_len <= packet.buf.remaining() {
+                Ok(packet)
+            } else {
+                Err(packet.release())
+            }
+        })
+    }
+
+    /// Returns the encoded PDU in the chunk of the buffer, without the
+    /// padding of the Ethernet frame.
+    #[inline]
+    pub fn pdu_bytes(&self) -> &[u8] {
+        apdu_bytes(self.buf.chunk(), self.packet_len())
+    }
+
+    /// Iterate over the fields of the `savPdu`.
+    ///
+    /// Returns `None` if the PDU does not start with a well-formed record
+    /// tagged with [`SV_PDU_TAG`].
+    #[inline]
+    pub fn pdu(&self) -> Option<BerIter<'_>> {
+        pdu_fields(self.pdu_bytes(), SV_PDU_TAG)
+    }
+
+    /// Returns the first field of the PDU with `tag`.
+    #[inline]
+    pub fn field(&self, tag: SvField) -> Option<BerTlv<'_>> {
+        self.pdu()?.find_tag(tag.into())
+    }
+
+    /// Returns the number of ASDUs announced by the PDU.
+    #[inline]
+    pub fn no_asdu(&self) -> Option<u64> {
+        self.field(SvField::NO_ASDU)?.as_uint()
+    }
+
+    /// Iterate over the ASDUs of the PDU, skipping the records of the
+    /// sequence that are not ASDUs.
+    #[inline]
+    pub fn asdus(&self) -> Option<impl Iterator<Item = SvAsdu<'_>>> {
+        let seq = self.field(SvField::SEQ_ASDU)?;
+        Some(
+            seq.children()
+                .filter(|tlv| tlv.tag == ASDU_TAG)
+                .map(|tlv| SvAsdu { fields: tlv.value }),
+        )
+    }
+}
+
+impl<T: PktBuf> SvPacket<T> {
+    /// Returns the encoded PDU, without the padding of the Ethernet frame.
+    #[inline]
+    pub fn payload(self) -> T {
+        let packet_len = usize::from(self.packet_len());
+        assert!(packet_len <= self.buf.remaining());
+        let trim_size = self.buf.remaining() - packet_len;
+
+        let mut buf = self.release();
+        if trim_size > 0 {
+            buf.trim_off(trim_size);
+        }
+
+        buf.advance(SV_HEADER_LEN);
+
+        buf
+    }
+}
+
+impl<T: crate::PktMut> SvPacket<T> {
+    /// Prepend the header, the length field is set to cover the whole PDU
+    /// in `buf`.
+    #[inline]
+    pub fn prepend_header<HT: AsRef<[u8]>>(mut buf: T, header: &SvHeader<HT>) -> SvPacket<T> {
+        let packet_len = prepend_apdu_header(&mut buf, header);
+        let mut pkt = SvPacket::parse_unchecked(buf);
+        pkt.set_packet_len_unchecked(packet_len);
+        pkt
+    }
+}
+
+impl<T> EtherPayload for SvPacket<T> {
+    const ETHERTYPE: EtherType = EtherType::SV;
+}
+
+/// An ASDU of an SV message, a sample of a data set.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SvAsdu<'a> {
+    fields: &'a [u8],
+}
+
+impl<'a> SvAsdu<'a> {
+    /// Iterate over the fields of the ASDU.
+    #[inline]
+    pub fn fields(&self) -> BerIter<'a> {
+        BerIter::new(self.fields)
+    }
+
+    /// Returns the first field of the ASDU with `tag`.
+    #[inline]
+    pub fn field(&self, tag: SvAsduField) -> Option<BerTlv<'a>> {
+        self.fields().find_tag(tag.into())
+    }
+
+    #[inline]
+    pub fn sv_id(&self) -> Option<&'a [u8]> {
+        self.field(SvAsduField::SV_ID).map(|tlv| tlv.value)
+    }
+
+    /// Returns the sample counter, which wraps at the sample rate.
+    #[inline]
+    pub fn smp_cnt(&self) -> Option<u16> {
+        let value = self.field(SvAsduField::SMP_CNT)?.as_uint()?;
+        u16::try_from(value).ok()
+    }
+
+    #[inline]
+    pub fn conf_rev(&self) -> Option<u32> {
+        let value = self.field(SvAsduField::CONF_REV)?.as_uint()?;
+        u32::try_from(value).ok()
+    }
+
+    /// Returns the synchronization of the sample, 0 for none, 1 for a local
+    /// clock and 2 for a global clock.
+    #[inline]
+    pub fn smp_synch(&self) -> Option<u8> {
+        let value = self.field(SvAsduField::SMP_SYNCH)?.as_uint()?;
+        u8::try_from(value).ok()
+    }
+
+    /// Returns the encoded values of the data set.
+    #[inline]
+    pub fn seq_data(&self) -> Option<&'a [u8]> {
+        self.field(SvAsduField::SEQ_DATA).map(|tlv| tlv.value)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ether::*;
+    use crate::{Cursor, CursorMut};
+
+    fn asdu(smp_cnt: u16) -> Vec<u8> {
+        let mut asdu = vec![0x30, 0x00, 0x80, 0x04];
+        asdu.extend_from_slice(b"MU01");
+        asdu.extend_from_slice(&[0x82, 0x02]);
+        asdu.extend_from_slice(&smp_cnt.to_be_bytes());
+        asdu.extend_from_slice(&[0x83, 0x04, 0x00, 0x00, 0x00, 0x01]);
+        asdu.extend_from_slice(&[0x85, 0x01, 0x02, 0x87, 0x08]);
+        asdu.extend_from_slice(&[0, 0, 0x03, 0xe8, 0, 0, 0, 0]);
+        asdu[1] = (asdu.len() - 2) as u8;
+        asdu
+    }
+
+    // A savPdu with two ASDUs.
+    fn sv_pdu() -> Vec<u8> {
+        let mut seq = vec![0xa2, 0x00];
+        seq.extend_from_slice(&asdu(100));
+        seq.extend_from_slice(&asdu(101));
+        seq[1] = (seq.len() - 2) as u8;
+        let mut pdu = vec![0x60, 0x00, 0x80, 0x01, 0x02];
+        pdu.extend_from_slice(&seq);
+        pdu[1] = (pdu.len() - 2) as u8;
+        pdu
+    }
+
+    #[test]
+    fn sv_roundtrip() {
+        let pdu = sv_pdu();
+        let frame_len = ETHER_HEADER_LEN + SV_HEADER_LEN + pdu.len();
+        let mut frame = vec![0; frame_len];
+        frame[frame_len - pdu.len()..].copy_from_slice(&pdu);
+
+        let mut buf = CursorMut::new(&mut frame[..]);
+        buf.advance(frame_len - pdu.len());
+        let mut pkt = SvPacket::prepend_header(buf, &SV_HEADER_TEMPLATE);
+        pkt.set_appid(0x4000);
+        let mut ethpkt = EtherPacket::prepend_header(pkt.release(), &ETHER_HEADER_TEMPLATE);
+        ethpkt.set_ethertype_for::<SvPacket<CursorMut>>();
+
+        let ethpkt = EtherPacket::parse(Cursor::new(&frame[..])).unwrap();
+        assert_eq!(ethpkt.ethertype(), EtherType::SV);
+        let pkt = SvPacket::parse(ethpkt.payload()).unwrap();
+        assert_eq!(pkt.appid(), 0x4000);
+        assert!(!pkt.simulation());
+        assert_eq!(pkt.no_asdu(), Some(2));
+
+        let asdus: Vec<_> = pkt.asdus().unwrap().collect();
+        assert_eq!(asdus.len(), 2);
+        assert_eq!(asdus[0].sv_id(), Some(&b"MU01"[..]));
+        assert_eq!(asdus[0].smp_cnt(), Some(100));
+        assert_eq!(asdus[1].smp_cnt(), Some(101));
+        assert_eq!(asdus[1].conf_rev(), Some(1));
+        assert_eq!(asdus[1].smp_synch(), Some(2));
+        assert_eq!(asdus[1].seq_data().map(|data| data.len()), Some(8));
+        assert_eq!(asdus[0].fields().count(), 5);
+
+        assert_eq!(pkt.payload().chunk(), &pdu[..]);
+    }
+
+    #[test]
+    fn sv_truncated() {
+        let pdu = sv_pdu();
+        let mut bytes = vec![0x40, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00];
+        bytes.extend_from_slice(&pdu);
+        bytes[3] = bytes.len() as u8;
+        assert!(SvPacket::parse(Cursor::new(&bytes[..bytes.len() - 1])).is_err());
+
+        // A sequence of ASDUs running past the PDU.
+        bytes[SV_HEADER_LEN + 6] += 1;
+        let pkt = SvPacket::parse(Cursor::new(&bytes[..])).unwrap();
+        assert!(pkt.asdus().is_none());
+        assert_eq!(pkt.no_asdu(), Some(2));
+    }
+}
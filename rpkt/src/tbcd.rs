@@ -0,0 +1,213 @@
+//! Telephony binary coded decimal (TBCD) digit strings.
+//!
+//! 3GPP protocols, e.g. GTPv1, GTPv2 and Diameter, encode IMSI, MSISDN and
+//! IMEI as TBCD strings (3GPP TS 29.002): two digits per byte with the first
+//! digit in the low nibble, and an odd number of digits is padded with a `0xf`
+//! filler in the high nibble of the last byte.
+//!
+//! The helpers work on caller-provided buffers and never allocate.
+//!
+//! # Examples
+//! ```
+//! use rpkt::tbcd::{self, Imsi};
+//!
+//! let mut buf = [0; 8];
+//! let len = tbcd::encode("001010123456789", &mut buf).unwrap();
+//! assert_eq!(&buf[..len], &[0x00, 0x01, 0x01, 0x21, 0x43, 0x65, 0x87, 0xf9]);
+//!
+//! let imsi = Imsi::decode(&buf[..len]).unwrap();
+//! assert_eq!(imsi.as_str(), "001010123456789");
+//! ```
+
+use std::fmt;
+
+const FILLER: u8 = 0x0f;
+
+/// Returns the number of bytes that encode `digits` digits.
+pub const fn encoded_len(digits: usize) -> usize {
+    (digits + 1) / 2
+}
+
+fn to_nibble(c: u8) -> Option<u8> {
+    match c {
+        b'0'..=b'9' => Some(c - b'0'),
+        b'*' => Some(0x0a),
+        b'#' => Some(0x0b),
+        b'a' => Some(0x0c),
+        b'b' => Some(0x0d),
+        b'c' => Some(0x0e),
+        _ => None,
+    }
+}
+
+fn to_char(nibble: u8) -> Option<u8> {
+    match nibble {
+        0x00..=0x09 => Some(b'0' + nibble),
+        0x0a => Some(b'*'),
+        0x0b => Some(b'#'),
+        0x0c => Some(b'a'),
+        0x0d => Some(b'b'),
+        0x0e => Some(b'c'),
+        _ => None,
+    }
+}
+
+/// Encode `digits` into `buf`, returning the number of bytes written.
+///
+/// Besides decimal digits, `digits` may contain the TBCD characters `*`, `#`,
+/// `a`, `b` and `c`. Returns `None` if `digits` contains other characters, or
+/// if `buf` is shorter than [`encoded_len`] of the digits.
+pub fn encode(digits: &str, buf: &mut [u8]) -> Option<usize> {
+    let digits = digits.as_bytes();
+    let len = encoded_len(digits.len());
+    if buf.len() < len {
+        return None;
+    }
+    for (pair, b) in digits.chunks(2).zip(buf.iter_mut()) {
+        let low = to_nibble(pair[0])?;
+        let high = match pair.get(1) {
+            Some(&c) => to_nibble(c)?,
+            None => FILLER,
+        };
+        *b = (high << 4) | low;
+    }
+    Some(len)
+}
+
+/// Decode `bytes` into `out`, returning the digit string.
+///
+/// Returns `None` if `bytes` holds a nibble that is not a TBCD digit, a
+/// filler anywhere other than the high nibble of the last byte, or if `out`
+/// is too short to hold the digits.
+pub fn decode<'a>(bytes: &[u8], out: &'a mut [u8]) -> Option<&'a str> {
+    let mut len = 0;
+    for (i, &b) in bytes.iter().enumerate() {
+        let nibbles = [b & 0x0f, b >> 4];
+        for (j, &nibble) in nibbles.iter().enumerate() {
+            if nibble == FILLER && j == 1 && i + 1 == bytes.len() {
+                break;
+            }
+            *out.get_mut(len)? = to_char(nibble)?;
+            len += 1;
+        }
+    }
+    // The output only holds the ASCII characters produced by `to_char`.
+    std::str::from_utf8(&out[..len]).ok()
+}
+
+/// A TBCD digit string of at most `N` digits, stored inline.
+#[derive(Clone, Copy, PartialEq, Eq, Hash)]
+pub struct TbcdDigits<const N: usize> {
+    digits: [u8; N],
+    len: usize,
+}
+
+/// An IMSI of at most 15 digits.
+pub type Imsi = TbcdDigits<15>;
+
+/// An MSISDN of at most 15 digits.
+pub type Msisdn = TbcdDigits<15>;
+
+/// An IMEI of 15 digits, or an IMEISV of 16 digits.
+pub type Imei = TbcdDigits<16>;
+
+impl<const N: usize> TbcdDigits<N> {
+    /// Create from a digit string.
+    ///
+    /// Returns `None` if `digits` is longer than `N` or contains characters
+    /// that are not TBCD digits.
+    pub fn new(digits: &str) -> Option<Self> {
+        let bytes = digits.as_bytes();
+        if bytes.len() > N || !bytes.iter().all(|&c| to_nibble(c).is_some()) {
+            return None;
+        }
+        let mut res = Self {
+            digits: [0; N],
+            len: bytes.len(),
+        };
+        res.digits[..bytes.len()].copy_from_slice(bytes);
+        Some(res)
+    }
+
+    /// Decode from the TBCD encoded `bytes`.
+    pub fn decode(bytes: &[u8]) -> Option<Self> {
+        let mut digits = [0; N];
+        let len = decode(bytes, &mut digits)?.len();
+        Some(Self { digits, len })
+    }
+
+    /// Encode into `buf`, returning the number of bytes written.
+    pub fn encode(&self, buf: &mut [u8]) -> Option<usize> {
+        encode(self.as_str(), buf)
+    }
+
+    /// Returns the number of digits.
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    /// Returns whether there are no digits.
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Returns the digits as a string.
+    pub fn as_str(&self) -> &str {
+        // Only TBCD characters, which are ASCII, are ever stored.
+        std::str::from_utf8(&self.digits[..self.len]).unwrap()
+    }
+}
+
+impl<const N: usize> fmt::Display for TbcdDigits<N> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl<const N: usize> fmt::Debug for TbcdDigits<N> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "\"{}\"", self.as_str())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn tbcd_roundtrip() {
+        let mut buf = [0; 8];
+        assert_eq!(encode("1234", &mut buf), Some(2));
+        assert_eq!(&buf[..2], &[0x21, 0x43]);
+        assert_eq!(encode("*#abc", &mut buf), Some(3));
+        assert_eq!(&buf[..3], &[0xba, 0xdc, 0xfe]);
+
+        let mut out = [0; 16];
+        assert_eq!(decode(&[0x21, 0x43], &mut out), Some("1234"));
+        assert_eq!(decode(&[0xba, 0xdc, 0xfe], &mut out), Some("*#abc"));
+        assert_eq!(decode(&[], &mut out), Some(""));
+
+        let imei = Imei::new("4901542032375181").unwrap();
+        assert_eq!(imei.encode(&mut buf), Some(8));
+        assert_eq!(Imei::decode(&buf), Some(imei));
+        assert_eq!(imei.to_string(), "4901542032375181");
+    }
+
+    #[test]
+    fn tbcd_invalid() {
+        let mut buf = [0; 2];
+        assert_eq!(encode("12345", &mut buf), None);
+        assert_eq!(encode("12x", &mut buf), None);
+
+        let mut out = [0; 4];
+        // A filler before the last nibble.
+        assert_eq!(decode(&[0xf1, 0x32], &mut out), None);
+        assert_eq!(decode(&[0x0f], &mut out), None);
+        // Too many digits for the output.
+        assert_eq!(decode(&[0x21, 0x43, 0x65], &mut out), None);
+
+        assert_eq!(Msisdn::new("1234567890123456"), None);
+        assert_eq!(Msisdn::new("+8613800138000"), None);
+        assert_eq!(Imsi::decode(&[0x11; 8]), None);
+    }
+}
@@ -351,3 +351,63 @@ impl<T: AsMut<[u8]>> TcpHeader<T> {
         NetworkEndian::write_u16(data, value)
     }
 }
+
+/// Const builders for the header template, so that the presets of the header can
+/// be evaluated at compile time and stored in `const`s or `static`s.
+///
+/// # Examples
+/// ```
+/// use rpkt::tcp::*;
+///
+/// static SYN: TcpHeader<[u8; TCP_HEADER_LEN]> = TCP_HEADER_TEMPLATE
+///     .with_dst_port(80)
+///     .with_syn(true)
+///     .with_window_size(65535);
+/// assert!(SYN.syn());
+/// assert_eq!(SYN.dst_port(), 80);
+/// ```
+impl TcpHeader<[u8; TCP_HEADER_LEN]> {
+    #[inline]
+    pub const fn with_src_port(mut self, value: u16) -> Self {
+        let bytes = value.to_be_bytes();
+        self.buf[0] = bytes[0];
+        self.buf[1] = bytes[1];
+        self
+    }
+
+    #[inline]
+    pub const fn with_dst_port(mut self, value: u16) -> Self {
+        let bytes = value.to_be_bytes();
+        self.buf[2] = bytes[0];
+        self.buf[3] = bytes[1];
+        self
+    }
+
+    #[inline]
+    pub const fn with_syn(self, value: bool) -> Self {
+        self.with_flag(FLG_SYN, value)
+    }
+
+    #[inline]
+    pub const fn with_ack(self, value: bool) -> Self {
+        self.with_flag(FLG_ACK, value)
+    }
+
+    #[inline]
+    pub const fn with_window_size(mut self, value: u16) -> Self {
+        let bytes = value.to_be_bytes();
+        self.buf[14] = bytes[0];
+        self.buf[15] = bytes[1];
+        self
+    }
+
+    #[inline]
+    const fn with_flag(mut self, flag: u16, value: bool) -> Self {
+        let raw = u16::from_be_bytes([self.buf[12], self.buf[13]]);
+        let raw = if value { raw | flag } else { raw & !flag };
+        let bytes = raw.to_be_bytes();
+        self.buf[12] = bytes[0];
+        self.buf[13] = bytes[1];
+        self
+    }
+}
@@ -2,7 +2,7 @@ mod header;
 pub use header::{TcpHeader, TCP_HEADER_LEN, TCP_HEADER_LEN_MAX, TCP_HEADER_TEMPLATE};
 
 mod packet;
-pub use packet::TcpPacket;
+pub use packet::{mss_for_mtu, TcpPacket};
 
 mod option;
 pub use option::{TcpOptionSack, TcpOption, TcpOptionIter, TcpOptionWriter};
@@ -52,90 +52,99 @@ impl<T: AsMut<[u8]> + AsRef<[u8]>> TcpOptionSack<T> {
 
 pub struct TcpOptionWriter<'a> {
     buf: &'a mut [u8],
+    written: usize,
 }
 
 impl<'a> TcpOptionWriter<'a> {
     pub fn eol(&mut self) {
-        self.buf[0] = END_OF_LIST;
-
-        let (_, remaining) = std::mem::replace(&mut self.buf, &mut []).split_at_mut(1);
-        self.buf = remaining;
+        self.advance(1)[0] = END_OF_LIST;
     }
 
     pub fn nop(&mut self) {
-        self.buf[0] = NOP;
-
-        let (_, remaining) = std::mem::replace(&mut self.buf, &mut []).split_at_mut(1);
-        self.buf = remaining;
+        self.advance(1)[0] = NOP;
     }
 
     pub fn mss(&mut self, mss: u16) {
-        self.buf[0] = MAX_SEG_SIZE;
-        self.buf[1] = 4;
-        NetworkEndian::write_u16(&mut self.buf[2..4], mss);
-
-        let (_, remaining) = std::mem::replace(&mut self.buf, &mut []).split_at_mut(4);
-        self.buf = remaining;
+        let buf = self.advance(4);
+        buf[0] = MAX_SEG_SIZE;
+        buf[1] = 4;
+        NetworkEndian::write_u16(&mut buf[2..4], mss);
     }
 
     pub fn wsopt(&mut self, value: u8) {
-        self.buf[0] = WINDOW_SCALE;
-        self.buf[1] = 3;
-        self.buf[2] = value;
-
-        let (_, remaining) = std::mem::replace(&mut self.buf, &mut []).split_at_mut(3);
-        self.buf = remaining;
+        let buf = self.advance(3);
+        buf[0] = WINDOW_SCALE;
+        buf[1] = 3;
+        buf[2] = value;
     }
 
     pub fn sack_perm(&mut self) {
-        self.buf[0] = SACK_PERMITTED;
-        self.buf[1] = 2;
-
-        let (_, remaining) = std::mem::replace(&mut self.buf, &mut []).split_at_mut(2);
-        self.buf = remaining;
+        let buf = self.advance(2);
+        buf[0] = SACK_PERMITTED;
+        buf[1] = 2;
     }
 
     pub fn sack(&mut self, num_blocks: usize) -> TcpOptionSack<&'a mut [u8]> {
         assert!(num_blocks <= 4);
         let opt_len = 2 + num_blocks * 8;
 
-        self.buf[0] = SELECTIVE_ACK;
-        self.buf[1] = opt_len as u8;
-
-        let (buf, remaining) = std::mem::replace(&mut self.buf, &mut []).split_at_mut(opt_len);
-        self.buf = remaining;
+        let buf = self.advance(opt_len);
+        buf[0] = SELECTIVE_ACK;
+        buf[1] = opt_len as u8;
 
         TcpOptionSack { buf }
     }
 
     pub fn ts(&mut self, ts: u32, ts_echo: u32) {
-        self.buf[0] = TIMESTAMPS;
-        self.buf[1] = 10;
-        NetworkEndian::write_u32(&mut self.buf[2..6], ts);
-        NetworkEndian::write_u32(&mut self.buf[6..10], ts_echo);
-
-        let (_, remaining) = std::mem::replace(&mut self.buf, &mut []).split_at_mut(10);
-        self.buf = remaining;
+        let buf = self.advance(10);
+        buf[0] = TIMESTAMPS;
+        buf[1] = 10;
+        NetworkEndian::write_u32(&mut buf[2..6], ts);
+        NetworkEndian::write_u32(&mut buf[6..10], ts_echo);
     }
 
     pub fn fo(&mut self, cookie: &[u8]) {
-        self.buf[0] = TCP_FASTOPEN;
-        self.buf[1] = 18;
-        (&mut self.buf[2..18]).copy_from_slice(cookie);
+        let buf = self.advance(18);
+        buf[0] = TCP_FASTOPEN;
+        buf[1] = 18;
+        buf[2..18].copy_from_slice(cookie);
+    }
 
-        let (_, remaining) = std::mem::replace(&mut self.buf, &mut []).split_at_mut(18);
-        self.buf = remaining;
+    /// Terminate the option list and pad it to a 4-byte boundary.
+    ///
+    /// If the written options are not 4-byte aligned, an end-of-list option is
+    /// appended, followed by zero padding. Returns the padded length of the
+    /// option list.
+    pub fn pad(&mut self) -> usize {
+        let pad_len = (4 - self.written % 4) % 4;
+        // The end-of-list option is 0, so it is written along with the padding.
+        self.advance(pad_len).fill(END_OF_LIST);
+        self.written
     }
 
     #[inline]
     pub fn from_option_bytes_mut(buf: &'a mut [u8]) -> Self {
-        Self { buf }
+        Self { buf, written: 0 }
     }
 
     #[inline]
     pub fn remaining_bytes(&self) -> usize {
         self.buf.len()
     }
+
+    /// Returns the number of bytes written so far.
+    #[inline]
+    pub fn written_bytes(&self) -> usize {
+        self.written
+    }
+
+    #[inline]
+    fn advance(&mut self, len: usize) -> &'a mut [u8] {
+        let (buf, remaining) = std::mem::take(&mut self.buf).split_at_mut(len);
+        self.buf = remaining;
+        self.written += len;
+        buf
+    }
 }
 
 pub struct TcpOptionIter<'a> {
@@ -1,3 +1,4 @@
+use byteorder::{ByteOrder, NetworkEndian};
 use bytes::Buf;
 
 use crate::checksum_utils;
@@ -5,7 +6,7 @@ use crate::ipv4::{Ipv4Addr, Ipv4PseudoHeader};
 use crate::{Cursor, CursorMut};
 use crate::{PktBuf, PktMut};
 
-use super::{TcpHeader, TCP_HEADER_LEN};
+use super::{TcpHeader, TcpOptionWriter, TCP_HEADER_LEN, TCP_HEADER_LEN_MAX};
 
 packet_base! {
     pub struct TcpPacket: TcpHeader {
@@ -58,19 +59,21 @@ packet_base! {
 
 impl<T: Buf> TcpPacket<T> {
     pub fn parse(buf: T) -> Result<TcpPacket<T>, T> {
-        let chunk_len = buf.chunk().len();
-        if chunk_len < TCP_HEADER_LEN {
-            return Err(buf);
-        }
+        traced_parse!("tcp", buf, |pkt: &Self| usize::from(pkt.header_len()), {
+            let chunk_len = buf.chunk().len();
+            if chunk_len < TCP_HEADER_LEN {
+                return Err(buf);
+            }
 
-        let packet = TcpPacket::parse_unchecked(buf);
-        let header_len = usize::from(packet.header_len());
+            let packet = TcpPacket::parse_unchecked(buf);
+            let header_len = usize::from(packet.header_len());
 
-        if header_len < TCP_HEADER_LEN || header_len > chunk_len {
-            return Err(packet.release());
-        }
+            if header_len < TCP_HEADER_LEN || header_len > chunk_len {
+                return Err(packet.release());
+            }
 
-        Ok(packet)
+            Ok(packet)
+        })
     }
 
     #[inline]
@@ -115,6 +118,56 @@ impl<T: PktMut> TcpPacket<T> {
         &mut self.buf.chunk_mut()[TCP_HEADER_LEN..header_len]
     }
 
+    /// Clamp the MSS option of a SYN segment to `max_mss`, e.g. the value
+    /// returned by [`mss_for_mtu`], and update the checksum incrementally
+    /// (RFC 1624).
+    ///
+    /// Returns the original MSS if it is rewritten. Segments without the SYN
+    /// flag or the MSS option, and segments whose MSS does not exceed
+    /// `max_mss`, are left untouched.
+    pub fn clamp_mss(&mut self, max_mss: u16) -> Option<u16> {
+        if !self.syn() {
+            return None;
+        }
+
+        let options = self.option_bytes_mut();
+        let mut pos = 0;
+        while pos < options.len() {
+            match options[pos] {
+                // End of option list.
+                0 => return None,
+                // No operation.
+                1 => pos += 1,
+                kind => {
+                    let len = usize::from(*options.get(pos + 1)?);
+                    if len < 2 || pos + len > options.len() {
+                        return None;
+                    }
+                    if kind == 2 && len == 4 {
+                        let mss = NetworkEndian::read_u16(&options[pos + 2..pos + 4]);
+                        if mss <= max_mss {
+                            return None;
+                        }
+                        NetworkEndian::write_u16(&mut options[pos + 2..pos + 4], max_mss);
+
+                        // The checksum sums 16-bit words, a value at an odd
+                        // offset contributes with its bytes swapped.
+                        let (old, new) = if (TCP_HEADER_LEN + pos + 2) % 2 == 0 {
+                            (mss, max_mss)
+                        } else {
+                            (mss.swap_bytes(), max_mss.swap_bytes())
+                        };
+                        let sum = checksum_utils::combine(&[!self.checksum(), !old, new]);
+                        self.set_checksum(!sum);
+                        return Some(mss);
+                    }
+                    pos += len;
+                }
+            }
+        }
+        None
+    }
+
     #[inline]
     pub fn adjust_ipv4_checksum(&mut self, src_addr: Ipv4Addr, dst_addr: Ipv4Addr) {
         self.set_checksum(0);
@@ -138,6 +191,56 @@ impl<T: PktMut> TcpPacket<T> {
 
         TcpPacket::parse_unchecked(buf)
     }
+
+    /// Prepend a TCP header carrying the options written by `f`.
+    ///
+    /// Only the fixed part of `header` is copied. The options are padded to a
+    /// 4-byte boundary, and the data offset is set accordingly.
+    ///
+    /// # Panics
+    ///
+    /// This function panics if the options exceed 40 bytes, or if the buffer
+    /// does not have enough headroom.
+    pub fn prepend_header_with_options<HT, F>(
+        mut buf: T,
+        header: &TcpHeader<HT>,
+        f: F,
+    ) -> TcpPacket<T>
+    where
+        HT: AsRef<[u8]>,
+        F: FnOnce(&mut TcpOptionWriter<'_>),
+    {
+        let mut options = [0; TCP_HEADER_LEN_MAX - TCP_HEADER_LEN];
+        let mut writer = TcpOptionWriter::from_option_bytes_mut(&mut options[..]);
+        f(&mut writer);
+        let header_len = TCP_HEADER_LEN + writer.pad();
+        assert!(header_len <= buf.chunk_headroom());
+
+        buf.move_back(header_len);
+
+        let data = &mut buf.chunk_mut()[0..header_len];
+        data[..TCP_HEADER_LEN].copy_from_slice(header.as_bytes());
+        data[TCP_HEADER_LEN..].copy_from_slice(&options[..header_len - TCP_HEADER_LEN]);
+
+        let mut tcppkt = TcpPacket::parse_unchecked(buf);
+        tcppkt.set_header_len_unchecked(header_len as u8);
+        tcppkt
+    }
+}
+
+/// Returns the MSS that fits a TCP segment without options in a link of
+/// `mtu` bytes, behind an IP header of `ip_header_len` bytes.
+///
+/// # Examples
+/// ```
+/// use rpkt::tcp::mss_for_mtu;
+///
+/// assert_eq!(mss_for_mtu(1500, 20), 1460);
+/// assert_eq!(mss_for_mtu(1280, 40), 1220);
+/// ```
+#[inline]
+pub fn mss_for_mtu(mtu: u16, ip_header_len: usize) -> u16 {
+    mtu.saturating_sub((ip_header_len + TCP_HEADER_LEN) as u16)
 }
 
 impl<'a> TcpPacket<Cursor<'a>> {
@@ -334,4 +437,76 @@ mod tests {
 
         assert_eq!(pkt.release().chunk(), &FRAME_BYTES[..]);
     }
+
+    #[test]
+    fn packet_build_with_options() {
+        let mut bytes = [0xff; 60];
+        let mut buf = CursorMut::new(&mut bytes[..]);
+        buf.advance(60);
+
+        let mut tcppkt = TcpPacket::prepend_header_with_options(buf, &TCP_HEADER_TEMPLATE, |w| {
+            w.mss(1460);
+            w.sack_perm();
+            w.ts(1, 0);
+            w.nop();
+            w.wsopt(7);
+        });
+        tcppkt.set_syn(true);
+        assert_eq!(tcppkt.header_len(), 40);
+        tcppkt.adjust_ipv4_checksum(Ipv4Addr([10, 0, 0, 1]), Ipv4Addr([10, 0, 0, 2]));
+
+        let tcppkt = TcpPacket::parse(Cursor::new(tcppkt.buf().chunk())).unwrap();
+        assert_eq!(
+            tcppkt.option_bytes(),
+            &[2, 4, 0x05, 0xb4, 4, 2, 8, 10, 0, 0, 0, 1, 0, 0, 0, 0, 1, 3, 3, 7][..]
+        );
+        assert!(TcpOptionIter::check_option_bytes(tcppkt.option_bytes()));
+        assert!(tcppkt.syn());
+
+        let mut bytes = [0xff; 60];
+        let mut buf = CursorMut::new(&mut bytes[..]);
+        buf.advance(60);
+
+        let tcppkt = TcpPacket::prepend_header_with_options(buf, &TCP_HEADER_TEMPLATE, |w| {
+            w.mss(1460);
+            w.wsopt(7);
+        });
+        assert_eq!(tcppkt.header_len(), 28);
+        assert_eq!(tcppkt.option_bytes(), &[2, 4, 0x05, 0xb4, 3, 3, 7, 0][..]);
+    }
+
+    #[test]
+    fn packet_clamp_mss() {
+        let (src, dst) = (Ipv4Addr([10, 0, 0, 1]), Ipv4Addr([10, 0, 0, 2]));
+        let max_mss = mss_for_mtu(1400, IPV4_HEADER_LEN);
+
+        // The MSS value sits at an even offset without the leading nop, and
+        // at an odd offset with it.
+        for leading_nop in [false, true] {
+            let mut bytes = [0xff; 60];
+            let mut buf = CursorMut::new(&mut bytes[..]);
+            buf.advance(60);
+            let mut tcppkt =
+                TcpPacket::prepend_header_with_options(buf, &TCP_HEADER_TEMPLATE, |w| {
+                    if leading_nop {
+                        w.nop();
+                    }
+                    w.wsopt(7);
+                    w.mss(1460);
+                });
+            tcppkt.set_seq_number(0x12345678);
+            tcppkt.adjust_ipv4_checksum(src, dst);
+            assert_eq!(tcppkt.clamp_mss(max_mss), None);
+
+            tcppkt.set_syn(true);
+            tcppkt.adjust_ipv4_checksum(src, dst);
+            assert_eq!(tcppkt.clamp_mss(max_mss), Some(1460));
+            assert!(tcppkt.verify_ipv4_checksum(src, dst));
+            assert!(TcpOptionIter::from_option_bytes(tcppkt.option_bytes())
+                .any(|opt| matches!(opt, TcpOption::Mss(1360))));
+
+            // The MSS is already below the limit.
+            assert_eq!(tcppkt.clamp_mss(1400), None);
+        }
+    }
 }
@@ -0,0 +1,264 @@
+//! Teredo, the tunneling of IPv6 over UDP through NATs (RFC 4380).
+//!
+//! A Teredo packet is an IPv6 packet carried by a UDP datagram, which may be
+//! preceded by an authentication indicator and an origin indication.
+//! [`parse`] strips the indicators and returns the IPv6 packet, and
+//! [`TeredoAddr`] decodes the server, the flags and the mapped address of
+//! the client that are embedded in a Teredo IPv6 address.
+//!
+//! # Examples
+//! ```
+//! use rpkt::ipv4::Ipv4Addr;
+//! use rpkt::ipv6::Ipv6Addr;
+//! use rpkt::teredo::TeredoAddr;
+//!
+//! // The example address of RFC 4380, section 4.
+//! let addr = Ipv6Addr::new(0x2001, 0, 0x4136, 0xe378, 0x8000, 0x63bf, 0x3fff, 0xfdd2);
+//! let teredo = TeredoAddr::from_ipv6(&addr).unwrap();
+//! assert_eq!(teredo.server, Ipv4Addr::new(65, 54, 227, 120));
+//! assert!(teredo.is_cone());
+//! assert_eq!(teredo.client_port, 40000);
+//! assert_eq!(teredo.client_ip, Ipv4Addr::new(192, 0, 2, 45));
+//! assert_eq!(teredo.to_ipv6(), addr);
+//! ```
+
+use byteorder::{ByteOrder, NetworkEndian};
+
+use crate::ipv4::Ipv4Addr;
+use crate::ipv6::{Ipv6Addr, Ipv6Packet};
+use crate::PktBuf;
+
+/// The UDP port of the Teredo servers.
+pub const TEREDO_PORT: u16 = 3544;
+
+/// The prefix of the Teredo addresses, 2001::/32.
+pub const TEREDO_PREFIX: [u8; 4] = [0x20, 0x01, 0x00, 0x00];
+
+// The cone bit of the flags.
+const CONE_FLAG: u16 = 0x8000;
+
+// The indicator types, which are told apart from an IPv6 header by the
+// version field of 0.
+const AUTH_INDICATOR: u16 = 0x0001;
+const ORIGIN_INDICATION: u16 = 0x0000;
+
+const AUTH_FIXED_LEN: usize = 13;
+const ORIGIN_LEN: usize = 8;
+
+/// The fields embedded in a Teredo IPv6 address.
+///
+/// The port and the address of the client are stored de-obfuscated.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct TeredoAddr {
+    pub server: Ipv4Addr,
+    pub flags: u16,
+    pub client_port: u16,
+    pub client_ip: Ipv4Addr,
+}
+
+impl TeredoAddr {
+    /// Decode `addr`, returns `None` if it is not in the Teredo prefix.
+    pub fn from_ipv6(addr: &Ipv6Addr) -> Option<Self> {
+        let data = addr.as_bytes();
+        if data[0..4] != TEREDO_PREFIX {
+            return None;
+        }
+        Some(Self {
+            server: Ipv4Addr::from_bytes(&data[4..8]),
+            flags: NetworkEndian::read_u16(&data[8..10]),
+            client_port: !NetworkEndian::read_u16(&data[10..12]),
+            client_ip: deobfuscate_ip(&data[12..16]),
+        })
+    }
+
+    pub fn to_ipv6(&self) -> Ipv6Addr {
+        let mut data = [0; 16];
+        data[0..4].copy_from_slice(&TEREDO_PREFIX);
+        data[4..8].copy_from_slice(&self.server.0);
+        NetworkEndian::write_u16(&mut data[8..10], self.flags);
+        NetworkEndian::write_u16(&mut data[10..12], !self.client_port);
+        data[12..16].copy_from_slice(&deobfuscate_ip(&self.client_ip.0).0);
+        Ipv6Addr(data)
+    }
+
+    /// Returns whether the client is behind a cone NAT.
+    pub fn is_cone(&self) -> bool {
+        self.flags & CONE_FLAG != 0
+    }
+}
+
+// The obfuscation flips all the bits, so it is its own inverse.
+fn deobfuscate_ip(data: &[u8]) -> Ipv4Addr {
+    let mut ip = Ipv4Addr::from_bytes(data);
+    ip.0.iter_mut().for_each(|b| *b = !*b);
+    ip
+}
+
+/// The authentication indicator, sent between a client and its server.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TeredoAuth {
+    pub client_id_len: u8,
+    pub auth_value_len: u8,
+    pub nonce: [u8; 8],
+    pub confirmation: u8,
+}
+
+/// The origin indication, which tells a client the mapped address of a peer
+/// that is relayed by the server.
+///
+/// The port and the address are stored de-obfuscated.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TeredoOrigin {
+    pub port: u16,
+    pub ip: Ipv4Addr,
+}
+
+/// The indicators in front of the IPv6 packet of a Teredo packet.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct TeredoIndicators {
+    pub auth: Option<TeredoAuth>,
+    pub origin: Option<TeredoOrigin>,
+}
+
+impl TeredoIndicators {
+    /// Parse the indicators at the start of the UDP payload `data`, and
+    /// return them with their total length.
+    ///
+    /// Returns `None` if an indicator is truncated.
+    pub fn parse(data: &[u8]) -> Option<(Self, usize)> {
+        let mut indicators = Self::default();
+        let mut offset = 0;
+
+        if data.get(0..2)? == AUTH_INDICATOR.to_be_bytes() {
+            let client_id_len = *data.get(2)?;
+            let auth_value_len = *data.get(3)?;
+            let len = AUTH_FIXED_LEN + usize::from(client_id_len) + usize::from(auth_value_len);
+            let auth = data.get(..len)?;
+            let mut nonce = [0; 8];
+            nonce.copy_from_slice(&auth[len - 9..len - 1]);
+            indicators.auth = Some(TeredoAuth {
+                client_id_len,
+                auth_value_len,
+                nonce,
+                confirmation: auth[len - 1],
+            });
+            offset = len;
+        }
+
+        if data.get(offset..offset + 2)? == ORIGIN_INDICATION.to_be_bytes() {
+            let origin = data.get(offset..offset + ORIGIN_LEN)?;
+            indicators.origin = Some(TeredoOrigin {
+                port: !NetworkEndian::read_u16(&origin[2..4]),
+                ip: deobfuscate_ip(&origin[4..8]),
+            });
+            offset += ORIGIN_LEN;
+        }
+
+        Some((indicators, offset))
+    }
+}
+
+/// Strip the indicators of the Teredo packet in `buf`, a UDP payload, and
+/// parse the IPv6 packet.
+///
+/// Returns `buf` back if an indicator is truncated, or if the packet does
+/// not carry an IPv6 packet.
+pub fn parse<T: PktBuf>(mut buf: T) -> Result<(TeredoIndicators, Ipv6Packet<T>), T> {
+    let Some((indicators, len)) = TeredoIndicators::parse(buf.chunk()) else {
+        return Err(buf);
+    };
+    if buf.chunk().get(len).map(|b| b >> 4) != Some(6) {
+        return Err(buf);
+    }
+
+    buf.advance(len);
+    match Ipv6Packet::parse(buf) {
+        Ok(ippkt) => Ok((indicators, ippkt)),
+        Err(mut buf) => {
+            buf.move_back(len);
+            Err(buf)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ipv4::IpProtocol;
+    use crate::{Buf, Cursor};
+
+    // An IPv6 header without payload, e.g. a bubble.
+    fn bubble() -> [u8; 40] {
+        let mut header = [0; 40];
+        header[0] = 0x60;
+        header[6] = IpProtocol::IPV6_NO_NXT.into();
+        header[7] = 21;
+        header
+    }
+
+    #[test]
+    fn plain_packet() {
+        let packet = bubble();
+        let (indicators, ippkt) = parse(Cursor::new(&packet[..])).unwrap();
+        assert_eq!(indicators, TeredoIndicators::default());
+        assert_eq!(ippkt.next_header(), IpProtocol::IPV6_NO_NXT);
+        assert_eq!(ippkt.buf().remaining(), 40);
+    }
+
+    #[test]
+    fn indicators() {
+        // An authentication indicator without client identifier and
+        // authentication value, and an origin indication of 192.0.2.45:40000.
+        let mut packet = vec![0x00, 0x01, 0x00, 0x00, 1, 2, 3, 4, 5, 6, 7, 8, 0x00];
+        packet.extend_from_slice(&[0x00, 0x00, 0x63, 0xbf, 0x3f, 0xff, 0xfd, 0xd2]);
+        packet.extend_from_slice(&bubble());
+
+        let (indicators, ippkt) = parse(Cursor::new(&packet[..])).unwrap();
+        let auth = indicators.auth.unwrap();
+        assert_eq!((auth.client_id_len, auth.auth_value_len), (0, 0));
+        assert_eq!(auth.nonce, [1, 2, 3, 4, 5, 6, 7, 8]);
+        assert_eq!(auth.confirmation, 0);
+        let origin = indicators.origin.unwrap();
+        assert_eq!(origin.port, 40000);
+        assert_eq!(origin.ip, Ipv4Addr::new(192, 0, 2, 45));
+        assert_eq!(ippkt.hop_limit(), 21);
+
+        // Only the origin indication.
+        let (indicators, _) = parse(Cursor::new(&packet[13..])).unwrap();
+        assert!(indicators.auth.is_none() && indicators.origin.is_some());
+    }
+
+    #[test]
+    fn invalid_packets() {
+        let mut packet = vec![0x00, 0x01, 0x00, 0x04, 1, 2, 3, 4, 5, 6, 7, 8, 0x00];
+        packet.extend_from_slice(&bubble());
+        // The client identifier runs into the IPv6 header.
+        assert!(parse(Cursor::new(&packet[..])).is_err());
+
+        // A truncated origin indication, an IPv4 packet, and a truncated
+        // IPv6 packet.
+        assert!(parse(Cursor::new(&[0x00, 0x00, 0x63, 0xbf][..])).is_err());
+        let mut packet = bubble();
+        packet[0] = 0x45;
+        assert!(parse(Cursor::new(&packet[..])).is_err());
+        let packet = bubble();
+        let buf = parse(Cursor::new(&packet[..20])).unwrap_err();
+        assert_eq!(buf.remaining(), 20);
+    }
+
+    #[test]
+    fn teredo_addr() {
+        let addr = TeredoAddr {
+            server: Ipv4Addr::new(65, 54, 227, 120),
+            flags: 0,
+            client_port: 1234,
+            client_ip: Ipv4Addr::new(203, 0, 113, 7),
+        };
+        let ipv6 = addr.to_ipv6();
+        assert_eq!(&ipv6.0[..4], &TEREDO_PREFIX);
+        assert_eq!(&ipv6.0[12..], &[52, 255, 142, 248]);
+        assert_eq!(TeredoAddr::from_ipv6(&ipv6), Some(addr));
+        assert!(!addr.is_cone());
+        assert!(TeredoAddr::from_ipv6(&Ipv6Addr::LOOPBACK).is_none());
+    }
+}
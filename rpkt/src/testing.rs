@@ -0,0 +1,287 @@
+//! Assertions for the tests of packet builders.
+//!
+//! [`assert_packet_eq!`] compares a built frame with the expected one, e.g. a
+//! frame exported from a capture, and on mismatch reports the differences
+//! layer by layer instead of two raw byte arrays. The frames are split into
+//! layers with [`mutate::layers`](crate::mutate::layers), and the differing
+//! header fields are listed by name:
+//!
+//! ```text
+//! packets differ, actual 60 bytes, expected 60 bytes
+//! ipv4 @14
+//!   time_to_live: 0x3f != 0x40
+//!   checksum: 0xf7d3 != 0xf6d3
+//! ```
+//!
+//! The header bytes that no field covers, such as the options or the IPv6
+//! addresses, and the payload following the last layer are reported as a
+//! [`hexdiff`](crate::fmt::hexdiff).
+//!
+//! # Examples
+//! ```
+//! use rpkt::assert_packet_eq;
+//!
+//! let built = vec![0x02, 0x00, 0x00, 0x00, 0x00, 0x01];
+//! let expected = [0x02, 0x00, 0x00, 0x00, 0x00, 0x01];
+//! assert_packet_eq!(built, expected);
+//! ```
+
+use std::fmt::Write;
+
+use crate::fmt::hexdiff;
+use crate::mutate::{layers, Field, Layer};
+
+pub use crate::assert_packet_eq;
+
+/// Asserts that two frames are equal, and panics with the [`packet_diff`] of
+/// the frames otherwise.
+///
+/// Both frames can be anything that is `AsRef<[u8]>`. Like `assert_eq!`, an
+/// optional message can follow the frames.
+///
+/// # Examples
+/// ```should_panic
+/// use rpkt::assert_packet_eq;
+///
+/// let built = [0x02, 0x00, 0x00, 0x00, 0x00, 0x01];
+/// assert_packet_eq!(built, [0x02, 0x00, 0x00, 0x00, 0x00, 0x02], "frame {}", 0);
+/// ```
+#[macro_export]
+macro_rules! assert_packet_eq {
+    ($actual:expr, $expected:expr $(,)?) => {
+        match (
+            ::std::convert::AsRef::<[u8]>::as_ref(&$actual),
+            ::std::convert::AsRef::<[u8]>::as_ref(&$expected),
+        ) {
+            (actual, expected) => {
+                if actual != expected {
+                    ::std::panic!(
+                        "assertion `actual == expected` failed\n{}",
+                        $crate::testing::packet_diff(actual, expected)
+                    );
+                }
+            }
+        }
+    };
+    ($actual:expr, $expected:expr, $($arg:tt)+) => {
+        match (
+            ::std::convert::AsRef::<[u8]>::as_ref(&$actual),
+            ::std::convert::AsRef::<[u8]>::as_ref(&$expected),
+        ) {
+            (actual, expected) => {
+                if actual != expected {
+                    ::std::panic!(
+                        "assertion `actual == expected` failed: {}\n{}",
+                        ::std::format_args!($($arg)+),
+                        $crate::testing::packet_diff(actual, expected)
+                    );
+                }
+            }
+        }
+    };
+}
+
+/// Describe the differences between the `actual` and the `expected` frames,
+/// layer by layer. Returns an empty string if the frames are equal.
+///
+/// The layers of both frames are compared in order. For a layer found at the
+/// same offset with the same length in both frames, the differing fields are
+/// listed as `<name>: <actual> != <expected>`, followed by a hexdiff of the
+/// header if the bytes that no field covers differ. The first mismatched
+/// layer ends the comparison of the headers, and the rest of the frames from
+/// this layer on is compared as the payload.
+pub fn packet_diff(actual: &[u8], expected: &[u8]) -> String {
+    if actual == expected {
+        return String::new();
+    }
+
+    let mut out = format!(
+        "packets differ, actual {} bytes, expected {} bytes\n",
+        actual.len(),
+        expected.len()
+    );
+    let (actual_layers, expected_layers) = (layers(actual), layers(expected));
+
+    let mut payload_offset = 0;
+    for i in 0..actual_layers.len().max(expected_layers.len()) {
+        match (actual_layers.get(i), expected_layers.get(i)) {
+            (Some(a), Some(e))
+                if a.name == e.name && a.offset == e.offset && a.header_len == e.header_len =>
+            {
+                layer_diff(&mut out, a, actual, expected);
+                payload_offset = a.offset + a.header_len;
+            }
+            (a, e) => {
+                let _ = writeln!(
+                    out,
+                    "layer {}: actual {}, expected {}",
+                    i,
+                    describe(a),
+                    describe(e)
+                );
+                break;
+            }
+        }
+    }
+
+    let (actual_payload, expected_payload) = (
+        &actual[payload_offset.min(actual.len())..],
+        &expected[payload_offset.min(expected.len())..],
+    );
+    if actual_payload != expected_payload {
+        let _ = writeln!(out, "payload @{}", payload_offset);
+        out.push_str(&hexdiff(actual_payload, expected_payload));
+    }
+    out
+}
+
+// Write the differing fields of a layer that is at the same place in both
+// frames.
+fn layer_diff(out: &mut String, layer: &Layer, actual: &[u8], expected: &[u8]) {
+    let (a, e) = (layer.header(actual), layer.header(expected));
+    if a == e {
+        return;
+    }
+    let _ = writeln!(out, "{} @{}", layer.name, layer.offset);
+
+    for field in layer.fields {
+        let (va, ve) = (field.get(a), field.get(e));
+        if va != ve {
+            let _ = writeln!(
+                out,
+                "  {}: {} != {}",
+                field.name,
+                value(field, va),
+                value(field, ve)
+            );
+        }
+    }
+
+    // The bytes that are not covered by the fields.
+    let mut covered = vec![false; layer.header_len];
+    for field in layer.fields {
+        let end = (field.bit_offset + field.bit_len + 7) / 8;
+        for byte in &mut covered[field.bit_offset / 8..end] {
+            *byte = true;
+        }
+    }
+    if (0..layer.header_len).any(|i| !covered[i] && a[i] != e[i]) {
+        out.push_str("  other header bytes:\n");
+        for line in hexdiff(a, e).lines() {
+            let _ = writeln!(out, "  {}", line);
+        }
+    }
+}
+
+// Format a field value in hex, padded to the width of the field.
+fn value(field: &Field, v: u64) -> String {
+    format!("{:#0width$x}", v, width = 2 + (field.bit_len + 3) / 4)
+}
+
+fn describe(layer: Option<&Layer>) -> String {
+    match layer {
+        Some(layer) => format!(
+            "{} @{} with {} header bytes",
+            layer.name, layer.offset, layer.header_len
+        ),
+        None => "none".to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::corpus;
+
+    fn sample(label: &str) -> Vec<u8> {
+        corpus::generate()
+            .into_iter()
+            .find(|s| s.label() == label)
+            .unwrap()
+            .frame
+    }
+
+    #[test]
+    fn diff_fields() {
+        let expected = sample("udp/empty-payload/valid");
+        assert_eq!(packet_diff(&expected, &expected), "");
+
+        let mut actual = expected.clone();
+        // The time to live and the udp destination port.
+        actual[22] ^= 0x01;
+        actual[36] ^= 0x01;
+        let diff = packet_diff(&actual, &expected);
+        let lines: Vec<_> = diff.lines().collect();
+        assert_eq!(
+            lines,
+            [
+                format!(
+                    "packets differ, actual {} bytes, expected {} bytes",
+                    actual.len(),
+                    expected.len()
+                ),
+                "ipv4 @14".to_string(),
+                format!(
+                    "  time_to_live: {:#04x} != {:#04x}",
+                    actual[22], expected[22]
+                ),
+                "udp @34".to_string(),
+                format!(
+                    "  dest_port: {:#06x} != {:#06x}",
+                    u16::from_be_bytes([actual[36], actual[37]]),
+                    u16::from_be_bytes([expected[36], expected[37]])
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn diff_uncovered_bytes_and_payload() {
+        let expected = sample("udp/over-ipv6/valid");
+        let mut actual = expected.clone();
+        // The last byte of the destination address, and of the payload.
+        actual[14 + 39] ^= 0xff;
+        *actual.last_mut().unwrap() ^= 0xff;
+        let diff = packet_diff(&actual, &expected);
+        assert!(diff.contains("ipv6 @14\n  other header bytes:\n  - 0020"));
+        assert!(!diff.contains("hop_limit"));
+        assert!(diff.contains("payload @62\n- 0000"), "{}", diff);
+
+        // A longer frame only differs in the payload.
+        let mut actual = expected.clone();
+        actual.push(0);
+        let diff = packet_diff(&actual, &expected);
+        assert!(
+            diff.starts_with("packets differ, actual 79 bytes, expected 78 bytes\npayload @62\n")
+        );
+    }
+
+    #[test]
+    fn diff_layers() {
+        let expected = sample("udp/empty-payload/valid");
+        let mut actual = expected.clone();
+        // The ipv4 protocol is changed to tcp.
+        actual[23] = 6;
+        let diff = packet_diff(&actual, &expected);
+        assert!(diff.contains("  protocol: 0x06 != 0x11\n"));
+        assert!(diff.contains("layer 2: actual none, expected udp @34 with 8 header bytes\n"));
+
+        let diff = packet_diff(&expected[..10], &expected);
+        assert!(diff.contains("layer 0: actual none, expected ether @0"));
+        assert!(diff.contains("payload @0\n"));
+    }
+
+    #[test]
+    fn assert_equal_packets() {
+        let frame = sample("udp/empty-payload/valid");
+        assert_packet_eq!(frame, frame.clone());
+        assert_packet_eq!(&frame[..], frame, "frame {}", 0);
+    }
+
+    #[test]
+    #[should_panic(expected = "failed: frame 0\npackets differ")]
+    fn assert_different_packets() {
+        let frame = sample("udp/empty-payload/valid");
+        assert_packet_eq!(frame, &frame[1..], "frame {}", 0);
+    }
+}
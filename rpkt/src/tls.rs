@@ -0,0 +1,340 @@
+//! A parser of the TLS ClientHello message (RFC 8446, section 4.1.2).
+//!
+//! The ClientHello is the first message sent by a TLS client, and carries the
+//! name of the server in the server name indication (SNI) extension (RFC
+//! 6066) and the application protocols in the ALPN extension (RFC 7301).
+//! [`TlsClientHello`] reads them from the first TCP segment of a connection,
+//! so that the connection can be steered without terminating TLS.
+//!
+//! # Examples
+//! ```
+//! use rpkt::tls::TlsClientHello;
+//!
+//! // A ClientHello with a single cipher suite and the SNI "example.com".
+//! let mut hello = vec![0x03, 0x03];
+//! hello.extend_from_slice(&[0; 33]);
+//! hello.extend_from_slice(&[0x00, 0x02, 0x13, 0x01, 0x01, 0x00]);
+//! hello.extend_from_slice(&[0x00, 0x14, 0x00, 0x00, 0x00, 0x10, 0x00, 0x0e, 0x00, 0x00, 0x0b]);
+//! hello.extend_from_slice(b"example.com");
+//!
+//! let mut record = vec![0x16, 0x03, 0x01, 0x00, hello.len() as u8 + 4];
+//! record.extend_from_slice(&[0x01, 0x00, 0x00, hello.len() as u8]);
+//! record.extend_from_slice(&hello);
+//!
+//! let hello = TlsClientHello::parse(&record).unwrap();
+//! assert_eq!(hello.cipher_suites().collect::<Vec<_>>(), [0x1301]);
+//! assert_eq!(hello.server_name(), Some("example.com"));
+//! ```
+
+use byteorder::{ByteOrder, NetworkEndian};
+
+/// The length of the header of a TLS record.
+pub const TLS_RECORD_HEADER_LEN: usize = 5;
+
+/// The length of the header of a TLS handshake message.
+pub const TLS_HANDSHAKE_HEADER_LEN: usize = 4;
+
+/// The content type of the handshake records.
+pub const TLS_CONTENT_HANDSHAKE: u8 = 22;
+
+/// The type of the ClientHello handshake message.
+pub const TLS_HANDSHAKE_CLIENT_HELLO: u8 = 1;
+
+/// The type of the server name extension.
+pub const TLS_EXT_SERVER_NAME: u16 = 0;
+
+/// The type of the ALPN extension.
+pub const TLS_EXT_ALPN: u16 = 16;
+
+// The name type of a DNS host name in the server name extension.
+const SERVER_NAME_HOST: u8 = 0;
+
+const RANDOM_LEN: usize = 32;
+const SESSION_ID_LEN_MAX: usize = 32;
+
+/// A ClientHello message.
+#[derive(Debug, Clone, Copy)]
+pub struct TlsClientHello<'a> {
+    // The body of the message, after the handshake header.
+    buf: &'a [u8],
+    suites_start: usize,
+    compression_start: usize,
+    extensions_start: usize,
+}
+
+impl<'a> TlsClientHello<'a> {
+    /// Parse the ClientHello in the TLS record at the start of `buf`, e.g. the
+    /// payload of the first TCP segment sent by a client.
+    ///
+    /// Returns `None` if `buf` does not start with a handshake record holding
+    /// a ClientHello, or if the message is malformed. A ClientHello that is
+    /// split across several records or several segments is not supported.
+    pub fn parse(buf: &'a [u8]) -> Option<Self> {
+        if buf.len() < TLS_RECORD_HEADER_LEN || buf[0] != TLS_CONTENT_HANDSHAKE || buf[1] != 0x03 {
+            return None;
+        }
+        let record_len = usize::from(NetworkEndian::read_u16(&buf[3..5]));
+        let record = buf.get(TLS_RECORD_HEADER_LEN..TLS_RECORD_HEADER_LEN + record_len)?;
+        Self::parse_handshake(record)
+    }
+
+    /// Parse the ClientHello handshake message at the start of `buf`, without
+    /// the record header, e.g. from a QUIC CRYPTO frame.
+    pub fn parse_handshake(buf: &'a [u8]) -> Option<Self> {
+        if buf.len() < TLS_HANDSHAKE_HEADER_LEN || buf[0] != TLS_HANDSHAKE_CLIENT_HELLO {
+            return None;
+        }
+        let len = NetworkEndian::read_u24(&buf[1..4]) as usize;
+        let buf = buf.get(TLS_HANDSHAKE_HEADER_LEN..TLS_HANDSHAKE_HEADER_LEN + len)?;
+
+        let session_id_len = usize::from(*buf.get(2 + RANDOM_LEN)?);
+        if session_id_len > SESSION_ID_LEN_MAX {
+            return None;
+        }
+        let suites_start = 2 + RANDOM_LEN + 1 + session_id_len;
+        let suites_len = usize::from(NetworkEndian::read_u16(
+            buf.get(suites_start..suites_start + 2)?,
+        ));
+        if suites_len == 0 || suites_len % 2 != 0 {
+            return None;
+        }
+        let compression_start = suites_start + 2 + suites_len;
+        let compression_len = usize::from(*buf.get(compression_start)?);
+        let extensions_start = compression_start + 1 + compression_len;
+        if extensions_start > buf.len() {
+            return None;
+        }
+
+        // The extensions are optional, but must fill the rest of the message.
+        if extensions_start < buf.len() {
+            let extensions = buf.get(extensions_start..extensions_start + 2)?;
+            let extensions_len = usize::from(NetworkEndian::read_u16(extensions));
+            if extensions_start + 2 + extensions_len != buf.len() {
+                return None;
+            }
+            let mut iter = TlsExtIter {
+                buf: &buf[extensions_start + 2..],
+            };
+            for _ in iter.by_ref() {}
+            if !iter.buf.is_empty() {
+                return None;
+            }
+        }
+
+        Some(Self {
+            buf,
+            suites_start,
+            compression_start,
+            extensions_start,
+        })
+    }
+
+    /// Returns the legacy version field, 0x0303 for TLS 1.2 and TLS 1.3.
+    #[inline]
+    pub fn legacy_version(&self) -> u16 {
+        NetworkEndian::read_u16(&self.buf[0..2])
+    }
+
+    #[inline]
+    pub fn random(&self) -> &'a [u8] {
+        &self.buf[2..2 + RANDOM_LEN]
+    }
+
+    #[inline]
+    pub fn session_id(&self) -> &'a [u8] {
+        &self.buf[2 + RANDOM_LEN + 1..self.suites_start]
+    }
+
+    /// Returns an iterator over the offered cipher suites.
+    #[inline]
+    pub fn cipher_suites(&self) -> impl Iterator<Item = u16> + 'a {
+        self.buf[self.suites_start + 2..self.compression_start]
+            .chunks_exact(2)
+            .map(NetworkEndian::read_u16)
+    }
+
+    #[inline]
+    pub fn compression_methods(&self) -> &'a [u8] {
+        &self.buf[self.compression_start + 1..self.extensions_start]
+    }
+
+    /// Returns an iterator over the types and the data of the extensions.
+    #[inline]
+    pub fn extensions(&self) -> TlsExtIter<'a> {
+        TlsExtIter {
+            buf: self.buf.get(self.extensions_start + 2..).unwrap_or(&[]),
+        }
+    }
+
+    /// Returns the data of the first extension of type `ext_type`.
+    pub fn extension(&self, ext_type: u16) -> Option<&'a [u8]> {
+        self.extensions()
+            .find(|(t, _)| *t == ext_type)
+            .map(|(_, data)| data)
+    }
+
+    /// Returns the host name of the server name extension.
+    ///
+    /// Returns `None` if the extension is absent or malformed, or if the name
+    /// is not printable ASCII.
+    pub fn server_name(&self) -> Option<&'a str> {
+        let data = self.extension(TLS_EXT_SERVER_NAME)?;
+        let list_len = usize::from(NetworkEndian::read_u16(data.get(0..2)?));
+        let mut list = data.get(2..2 + list_len)?;
+        while list.len() >= 3 {
+            let name_len = usize::from(NetworkEndian::read_u16(&list[1..3]));
+            let name = list.get(3..3 + name_len)?;
+            if list[0] == SERVER_NAME_HOST {
+                if name.is_empty() || !name.iter().all(|b| b.is_ascii_graphic()) {
+                    return None;
+                }
+                return std::str::from_utf8(name).ok();
+            }
+            list = &list[3 + name_len..];
+        }
+        None
+    }
+
+    /// Returns an iterator over the protocols of the ALPN extension, which is
+    /// empty if the extension is absent.
+    pub fn alpn(&self) -> impl Iterator<Item = &'a [u8]> {
+        let mut list = self
+            .extension(TLS_EXT_ALPN)
+            .and_then(|data| {
+                let list_len = usize::from(NetworkEndian::read_u16(data.get(0..2)?));
+                data.get(2..2 + list_len)
+            })
+            .unwrap_or(&[]);
+        std::iter::from_fn(move || {
+            let len = usize::from(*list.first()?);
+            let protocol = list.get(1..1 + len)?;
+            list = &list[1 + len..];
+            Some(protocol)
+        })
+    }
+}
+
+/// An iterator over the extensions of a [`TlsClientHello`].
+#[derive(Debug, Clone)]
+pub struct TlsExtIter<'a> {
+    buf: &'a [u8],
+}
+
+impl<'a> Iterator for TlsExtIter<'a> {
+    type Item = (u16, &'a [u8]);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.buf.len() < 4 {
+            return None;
+        }
+        let ext_type = NetworkEndian::read_u16(&self.buf[0..2]);
+        let len = usize::from(NetworkEndian::read_u16(&self.buf[2..4]));
+        let data = self.buf.get(4..4 + len)?;
+        self.buf = &self.buf[4 + len..];
+        Some((ext_type, data))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn extension(ext_type: u16, data: &[u8]) -> Vec<u8> {
+        let mut ext = ext_type.to_be_bytes().to_vec();
+        ext.extend_from_slice(&(data.len() as u16).to_be_bytes());
+        ext.extend_from_slice(data);
+        ext
+    }
+
+    fn server_name(name: &[u8]) -> Vec<u8> {
+        let mut data = ((name.len() + 3) as u16).to_be_bytes().to_vec();
+        data.push(SERVER_NAME_HOST);
+        data.extend_from_slice(&(name.len() as u16).to_be_bytes());
+        data.extend_from_slice(name);
+        extension(TLS_EXT_SERVER_NAME, &data)
+    }
+
+    fn client_hello(extensions: &[Vec<u8>]) -> Vec<u8> {
+        let mut body = vec![0x03, 0x03];
+        body.extend(0..32);
+        body.push(4);
+        body.extend_from_slice(&[0xaa, 0xbb, 0xcc, 0xdd]);
+        body.extend_from_slice(&[0x00, 0x04, 0x13, 0x01, 0x13, 0x02, 0x01, 0x00]);
+        let extensions = extensions.concat();
+        body.extend_from_slice(&(extensions.len() as u16).to_be_bytes());
+        body.extend_from_slice(&extensions);
+
+        let mut record = vec![TLS_CONTENT_HANDSHAKE, 0x03, 0x01];
+        record.extend_from_slice(&((body.len() + 4) as u16).to_be_bytes());
+        record.push(TLS_HANDSHAKE_CLIENT_HELLO);
+        record.extend_from_slice(&(body.len() as u32).to_be_bytes()[1..]);
+        record.extend_from_slice(&body);
+        record
+    }
+
+    #[test]
+    fn parse_fields() {
+        let alpn = extension(
+            16,
+            &[
+                0x00, 0x0c, 0x02, b'h', b'2', 0x08, b'h', b't', b't', b'p', b'/', b'1', b'.', b'1',
+            ],
+        );
+        let record = client_hello(&[
+            extension(0x000a, &[0, 2, 0, 0x1d]),
+            server_name(b"www.example.com"),
+            alpn,
+        ]);
+        let hello = TlsClientHello::parse(&record).unwrap();
+        assert_eq!(hello.legacy_version(), 0x0303);
+        assert_eq!(hello.random()[31], 31);
+        assert_eq!(hello.session_id(), &[0xaa, 0xbb, 0xcc, 0xdd]);
+        assert_eq!(hello.cipher_suites().collect::<Vec<_>>(), [0x1301, 0x1302]);
+        assert_eq!(hello.compression_methods(), &[0]);
+        assert_eq!(
+            hello.extensions().map(|(t, _)| t).collect::<Vec<_>>(),
+            [0x000a, TLS_EXT_SERVER_NAME, TLS_EXT_ALPN]
+        );
+        assert_eq!(hello.server_name(), Some("www.example.com"));
+        assert_eq!(
+            hello.alpn().collect::<Vec<_>>(),
+            [&b"h2"[..], &b"http/1.1"[..]]
+        );
+
+        // Without extensions, and with a trailing record.
+        let mut record = client_hello(&[]);
+        let len = record.len();
+        record.truncate(len - 2);
+        record[4] -= 2;
+        record[8] -= 2;
+        record.extend_from_slice(&[0x17, 0x03, 0x03, 0x00, 0x00]);
+        let hello = TlsClientHello::parse(&record).unwrap();
+        assert_eq!(hello.extensions().count(), 0);
+        assert_eq!(hello.server_name(), None);
+        assert_eq!(hello.alpn().count(), 0);
+    }
+
+    #[test]
+    fn invalid_messages() {
+        let record = client_hello(&[server_name(b"example.com")]);
+        // Truncated, not a handshake record, and not a ClientHello.
+        assert!(TlsClientHello::parse(&record[..record.len() - 1]).is_none());
+        let mut bytes = record.clone();
+        bytes[0] = 0x17;
+        assert!(TlsClientHello::parse(&bytes).is_none());
+        let mut bytes = record.clone();
+        bytes[5] = 2;
+        assert!(TlsClientHello::parse(&bytes).is_none());
+
+        // An extension running past the extension block.
+        let mut bytes = record.clone();
+        let len = bytes.len();
+        bytes[len - 17] += 1;
+        assert!(TlsClientHello::parse(&bytes).is_none());
+
+        // A name that is not printable.
+        let record = client_hello(&[server_name(b"exa mple")]);
+        let hello = TlsClientHello::parse(&record).unwrap();
+        assert_eq!(hello.server_name(), None);
+    }
+}
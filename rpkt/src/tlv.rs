@@ -0,0 +1,594 @@
+//! A reusable iterator and writer of type-length-value records.
+//!
+//! Many protocols carry a list of length-prefixed records: IP and TCP
+//! options, DHCP options, RADIUS attributes and so on. They only differ in the
+//! widths of the type and length fields, in what the length counts, and in
+//! the padding rules. [`TlvFormat`] describes these differences, so that
+//! [`TlvIter`] and [`TlvWriter`] can be used for a new protocol instead of
+//! writing the iteration logic again.
+//!
+//! The records of ASN.1 encoded with the Basic Encoding Rules, as used by
+//! GOOSE and SNMP, have variable-length tags and lengths, and are iterated by
+//! [`BerIter`] instead.
+//!
+//! # Examples
+//! ```
+//! use rpkt::tlv::{TlvFormat, TlvIter, TlvWriter};
+//!
+//! let mut buf = [0; 16];
+//! let mut writer = TlvWriter::new(&mut buf[..], TlvFormat::DHCP);
+//! writer.write(53, &[1]).unwrap();
+//! writer.write(12, b"rpkt").unwrap();
+//! writer.end().unwrap();
+//! let len = writer.written_bytes();
+//! assert_eq!(&buf[..len], &[53, 1, 1, 12, 4, b'r', b'p', b'k', b't', 255]);
+//!
+//! let mut iter = TlvIter::new(&buf[..len], TlvFormat::DHCP);
+//! let types: Vec<_> = (&mut iter).map(|tlv| tlv.tlv_type).collect();
+//! assert_eq!(types, [53, 12, 255]);
+//! assert!(iter.is_valid());
+//! ```
+
+/// The layout of the records of a protocol.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TlvFormat {
+    /// The width of the type field, 1, 2 or 4 bytes.
+    pub type_width: usize,
+    /// The width of the length field, 1, 2 or 4 bytes.
+    pub len_width: usize,
+    /// Whether the length counts the type and length fields.
+    pub len_includes_header: bool,
+    /// The unit of the length field in bytes, e.g. 8 for the IPv6 extension
+    /// headers.
+    pub len_unit: usize,
+    /// Records are padded to a multiple of `align` bytes, the padding is not
+    /// counted by the length field.
+    pub align: usize,
+    /// The type of a one-byte padding record without length and value.
+    pub pad_type: Option<u32>,
+    /// The type of the one-byte record that ends the list.
+    pub end_type: Option<u32>,
+}
+
+impl TlvFormat {
+    /// IPv4 options (RFC 791).
+    pub const IPV4_OPTIONS: TlvFormat = TlvFormat {
+        type_width: 1,
+        len_width: 1,
+        len_includes_header: true,
+        len_unit: 1,
+        align: 1,
+        pad_type: Some(1),
+        end_type: Some(0),
+    };
+
+    /// TCP options (RFC 9293).
+    pub const TCP_OPTIONS: TlvFormat = TlvFormat::IPV4_OPTIONS;
+
+    /// IPv6 hop-by-hop and destination options (RFC 8200).
+    pub const IPV6_OPTIONS: TlvFormat = TlvFormat {
+        type_width: 1,
+        len_width: 1,
+        len_includes_header: false,
+        len_unit: 1,
+        align: 1,
+        pad_type: Some(0),
+        end_type: None,
+    };
+
+    /// DHCP options (RFC 2132).
+    pub const DHCP: TlvFormat = TlvFormat {
+        type_width: 1,
+        len_width: 1,
+        len_includes_header: false,
+        len_unit: 1,
+        align: 1,
+        pad_type: Some(0),
+        end_type: Some(255),
+    };
+
+    /// RADIUS attributes (RFC 2865).
+    pub const RADIUS: TlvFormat = TlvFormat {
+        type_width: 1,
+        len_width: 1,
+        len_includes_header: true,
+        len_unit: 1,
+        align: 1,
+        pad_type: None,
+        end_type: None,
+    };
+
+    /// A 2-byte type and a 2-byte length of the value, e.g. the TLVs of
+    /// PFCP and the information elements of GTPv2 without the instance.
+    pub const TYPE16_LEN16: TlvFormat = TlvFormat {
+        type_width: 2,
+        len_width: 2,
+        len_includes_header: false,
+        len_unit: 1,
+        align: 1,
+        pad_type: None,
+        end_type: None,
+    };
+
+    fn header_len(&self) -> usize {
+        self.type_width + self.len_width
+    }
+
+    fn is_single_byte(&self, tlv_type: u32) -> bool {
+        self.type_width == 1 && (self.pad_type == Some(tlv_type) || self.end_type == Some(tlv_type))
+    }
+
+    fn padded(&self, len: usize) -> usize {
+        (len + self.align - 1) / self.align * self.align
+    }
+}
+
+fn read_uint(data: &[u8]) -> u32 {
+    data.iter().fold(0, |acc, &b| (acc << 8) | u32::from(b))
+}
+
+fn write_uint(data: &mut [u8], value: u32) -> Option<()> {
+    let width = data.len();
+    if width < 4 && value >> (width * 8) != 0 {
+        return None;
+    }
+    for (i, b) in data.iter_mut().enumerate() {
+        *b = (value >> ((width - 1 - i) * 8)) as u8;
+    }
+    Some(())
+}
+
+/// A record yielded by [`TlvIter`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Tlv<'a> {
+    /// The type of the record.
+    pub tlv_type: u32,
+    /// The value of the record, empty for the padding and end records.
+    pub value: &'a [u8],
+    /// The whole record including the header, without the alignment padding.
+    pub bytes: &'a [u8],
+}
+
+/// Iterates over the records of a buffer.
+///
+/// The iteration stops after the end record, or at the first malformed
+/// record, in which case [`TlvIter::is_valid`] returns `false`.
+#[derive(Debug, Clone)]
+pub struct TlvIter<'a> {
+    buf: &'a [u8],
+    format: TlvFormat,
+    valid: bool,
+    ended: bool,
+}
+
+impl<'a> TlvIter<'a> {
+    #[inline]
+    pub fn new(buf: &'a [u8], format: TlvFormat) -> Self {
+        assert!(format.align > 0 && format.len_unit > 0);
+        assert!(matches!(format.type_width, 1 | 2 | 4) && matches!(format.len_width, 1 | 2 | 4));
+        Self {
+            buf,
+            format,
+            valid: true,
+            ended: false,
+        }
+    }
+
+    /// Returns whether all the records of `buf` are well-formed.
+    pub fn check_bytes(buf: &'a [u8], format: TlvFormat) -> bool {
+        let mut iter = Self::new(buf, format);
+        (&mut iter).for_each(drop);
+        iter.valid
+    }
+
+    /// Returns whether no malformed record has been found.
+    #[inline]
+    pub fn is_valid(&self) -> bool {
+        self.valid
+    }
+
+    /// Returns the bytes that are not iterated yet, e.g. the bytes that follow
+    /// the end record.
+    #[inline]
+    pub fn remaining(&self) -> &'a [u8] {
+        self.buf
+    }
+}
+
+impl<'a> Iterator for TlvIter<'a> {
+    type Item = Tlv<'a>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if !self.valid || self.ended || self.buf.is_empty() {
+            return None;
+        }
+        let format = &self.format;
+
+        if self.buf.len() < format.type_width {
+            self.valid = false;
+            return None;
+        }
+        let tlv_type = read_uint(&self.buf[..format.type_width]);
+        if format.is_single_byte(tlv_type) {
+            let (bytes, rest) = self.buf.split_at(1);
+            self.buf = rest;
+            if format.end_type == Some(tlv_type) {
+                // Nothing is iterated after the end record.
+                self.ended = true;
+            }
+            return Some(Tlv {
+                tlv_type,
+                value: &bytes[1..],
+                bytes,
+            });
+        }
+
+        let header_len = format.header_len();
+        if self.buf.len() < header_len {
+            self.valid = false;
+            return None;
+        }
+        let len = read_uint(&self.buf[format.type_width..header_len]) as usize * format.len_unit;
+        let record_len = if format.len_includes_header {
+            len
+        } else {
+            header_len + len
+        };
+        if record_len < header_len || record_len > self.buf.len() {
+            self.valid = false;
+            return None;
+        }
+
+        let bytes = &self.buf[..record_len];
+        let padded = format.padded(record_len).min(self.buf.len());
+        self.buf = &self.buf[padded..];
+        Some(Tlv {
+            tlv_type,
+            value: &bytes[header_len..],
+            bytes,
+        })
+    }
+}
+
+/// Writes records into a buffer.
+#[derive(Debug)]
+pub struct TlvWriter<'a> {
+    buf: &'a mut [u8],
+    format: TlvFormat,
+    written: usize,
+}
+
+impl<'a> TlvWriter<'a> {
+    #[inline]
+    pub fn new(buf: &'a mut [u8], format: TlvFormat) -> Self {
+        assert!(format.align > 0 && format.len_unit > 0);
+        assert!(matches!(format.type_width, 1 | 2 | 4) && matches!(format.len_width, 1 | 2 | 4));
+        Self {
+            buf,
+            format,
+            written: 0,
+        }
+    }
+
+    /// Write a record, padding it to the alignment of the format.
+    ///
+    /// Returns `None` and leaves the buffer untouched if the record does not
+    /// fit in the remaining bytes, if the type or the length overflows its
+    /// field, or if the length is not a multiple of the length unit.
+    pub fn write(&mut self, tlv_type: u32, value: &[u8]) -> Option<()> {
+        let format = self.format;
+        let header_len = format.header_len();
+        let record_len = header_len + value.len();
+        let len = if format.len_includes_header {
+            record_len
+        } else {
+            value.len()
+        };
+        if len % format.len_unit != 0 {
+            return None;
+        }
+        let padded = format.padded(record_len);
+        let data = self.buf.get_mut(self.written..self.written + padded)?;
+
+        write_uint(&mut data[..format.type_width], tlv_type)?;
+        write_uint(
+            &mut data[format.type_width..header_len],
+            u32::try_from(len / format.len_unit).ok()?,
+        )?;
+        data[header_len..record_len].copy_from_slice(value);
+        data[record_len..].fill(0);
+        self.written += padded;
+        Some(())
+    }
+
+    /// Write the one-byte padding record.
+    pub fn pad1(&mut self) -> Option<()> {
+        let pad_type = self.format.pad_type?;
+        self.write_single_byte(pad_type)
+    }
+
+    /// Write the one-byte end record.
+    pub fn end(&mut self) -> Option<()> {
+        let end_type = self.format.end_type?;
+        self.write_single_byte(end_type)
+    }
+
+    fn write_single_byte(&mut self, tlv_type: u32) -> Option<()> {
+        let b = self.buf.get_mut(self.written)?;
+        *b = tlv_type as u8;
+        self.written += 1;
+        Some(())
+    }
+
+    /// Returns the number of bytes written.
+    #[inline]
+    pub fn written_bytes(&self) -> usize {
+        self.written
+    }
+
+    /// Returns the number of bytes that can still be written.
+    #[inline]
+    pub fn remaining_bytes(&self) -> usize {
+        self.buf.len() - self.written
+    }
+}
+
+/// A record of the Basic Encoding Rules of ASN.1 (ITU-T X.690), yielded by
+/// [`BerIter`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BerTlv<'a> {
+    /// The identifier octets read as a big-endian number, which keeps the
+    /// class and the constructed bit, e.g. 0x61 for `[APPLICATION 1]`.
+    pub tag: u32,
+    /// The contents octets.
+    pub value: &'a [u8],
+    /// The whole record including the identifier and the length octets.
+    pub bytes: &'a [u8],
+}
+
+impl<'a> BerTlv<'a> {
+    /// Returns whether the contents are a list of records.
+    #[inline]
+    pub fn is_constructed(&self) -> bool {
+        self.bytes[0] & 0x20 != 0
+    }
+
+    /// Iterate over the records in the contents of a constructed record.
+    #[inline]
+    pub fn children(&self) -> BerIter<'a> {
+        BerIter::new(self.value)
+    }
+
+    /// Read the contents as an unsigned integer.
+    ///
+    /// Returns `None` if the contents are empty or longer than 8 bytes, not
+    /// counting a leading zero byte.
+    pub fn as_uint(&self) -> Option<u64> {
+        let value = match self.value {
+            [0, rest @ ..] if !rest.is_empty() => rest,
+            value => value,
+        };
+        if value.is_empty() || value.len() > 8 {
+            return None;
+        }
+        Some(value.iter().fold(0, |acc, &b| (acc << 8) | u64::from(b)))
+    }
+}
+
+/// Iterates over the BER records of a buffer, e.g. the contents of a
+/// constructed record.
+///
+/// Tags of up to 4 identifier octets and definite lengths of up to 4 bytes
+/// are supported. The iteration stops at the first malformed record, or at a
+/// record with an indefinite length, in which case [`BerIter::is_valid`]
+/// returns `false`.
+#[derive(Debug, Clone)]
+pub struct BerIter<'a> {
+    buf: &'a [u8],
+    valid: bool,
+}
+
+impl<'a> BerIter<'a> {
+    #[inline]
+    pub fn new(buf: &'a [u8]) -> Self {
+        Self { buf, valid: true }
+    }
+
+    /// Returns whether all the records of `buf` are well-formed, without
+    /// descending into the constructed records.
+    pub fn check_bytes(buf: &'a [u8]) -> bool {
+        let mut iter = Self::new(buf);
+        (&mut iter).for_each(drop);
+        iter.valid
+    }
+
+    /// Returns whether no malformed record has been found.
+    #[inline]
+    pub fn is_valid(&self) -> bool {
+        self.valid
+    }
+
+    /// Returns the bytes that are not iterated yet.
+    #[inline]
+    pub fn remaining(&self) -> &'a [u8] {
+        self.buf
+    }
+
+    /// Returns the first record with `tag`.
+    pub fn find_tag(mut self, tag: u32) -> Option<BerTlv<'a>> {
+        self.find(|tlv| tlv.tag == tag)
+    }
+
+    // Returns the tag and the length of the identifier octets.
+    fn read_tag(&self) -> Option<(u32, usize)> {
+        let first = *self.buf.first()?;
+        if first & 0x1f != 0x1f {
+            return Some((u32::from(first), 1));
+        }
+        // The high tag number form, where the last octet has the top bit
+        // cleared.
+        let end = self.buf[1..].iter().position(|b| b & 0x80 == 0)? + 2;
+        if end > 4 {
+            return None;
+        }
+        Some((read_uint(&self.buf[..end]), end))
+    }
+
+    // Returns the length of the contents and the end of the length octets.
+    fn read_len(&self, offset: usize) -> Option<(usize, usize)> {
+        let first = *self.buf.get(offset)?;
+        if first & 0x80 == 0 {
+            return Some((usize::from(first), offset + 1));
+        }
+        // The long form, 0x80 alone is the indefinite length.
+        let width = usize::from(first & 0x7f);
+        if width == 0 || width > 4 {
+            return None;
+        }
+        let data = self.buf.get(offset + 1..offset + 1 + width)?;
+        Some((read_uint(data) as usize, offset + 1 + width))
+    }
+}
+
+impl<'a> Iterator for BerIter<'a> {
+    type Item = BerTlv<'a>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if !self.valid || self.buf.is_empty() {
+            return None;
+        }
+
+        let record = self.read_tag().and_then(|(tag, tag_len)| {
+            let (len, header_len) = self.read_len(tag_len)?;
+            let record_len = header_len.checked_add(len)?;
+            (record_len <= self.buf.len()).then_some((tag, header_len, record_len))
+        });
+        let (tag, header_len, record_len) = match record {
+            Some(record) => record,
+            None => {
+                self.valid = false;
+                return None;
+            }
+        };
+
+        let (bytes, rest) = self.buf.split_at(record_len);
+        self.buf = rest;
+        Some(BerTlv {
+            tag,
+            value: &bytes[header_len..],
+            bytes,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn tlv_iter() {
+        // TCP options: mss, nop, wsopt, eol and the trailing zeros.
+        let options = [2, 4, 0x05, 0xb4, 1, 3, 3, 7, 0, 0, 0, 0];
+        let mut iter = TlvIter::new(&options[..], TlvFormat::TCP_OPTIONS);
+        assert_eq!(
+            iter.next(),
+            Some(Tlv {
+                tlv_type: 2,
+                value: &[0x05, 0xb4],
+                bytes: &options[..4]
+            })
+        );
+        assert_eq!(iter.next().map(|tlv| tlv.tlv_type), Some(1));
+        assert_eq!(iter.next().map(|tlv| tlv.value), Some(&[7][..]));
+        assert_eq!(iter.next().map(|tlv| tlv.tlv_type), Some(0));
+        assert_eq!(iter.next(), None);
+        assert!(iter.is_valid());
+
+        // A length below the header, and a record running past the buffer.
+        assert!(!TlvIter::check_bytes(&[2, 1, 0, 0], TlvFormat::TCP_OPTIONS));
+        assert!(!TlvIter::check_bytes(&[2, 4, 0], TlvFormat::TCP_OPTIONS));
+        assert!(TlvIter::check_bytes(&[], TlvFormat::TCP_OPTIONS));
+    }
+
+    #[test]
+    fn tlv_aligned_roundtrip() {
+        // 2-byte types and lengths counting the header, padded to 4 bytes.
+        let format = TlvFormat {
+            type_width: 2,
+            len_width: 2,
+            len_includes_header: true,
+            len_unit: 1,
+            align: 4,
+            pad_type: None,
+            end_type: None,
+        };
+        let mut buf = [0xff; 16];
+        let mut writer = TlvWriter::new(&mut buf[..], format);
+        writer.write(0x0102, &[0xaa]).unwrap();
+        writer.write(0x0304, &[0xbb; 4]).unwrap();
+        assert_eq!(writer.write(5, &[0; 1]), None);
+        assert_eq!(writer.pad1(), None);
+        assert_eq!(writer.written_bytes(), 16);
+        assert_eq!(
+            buf,
+            [1, 2, 0, 5, 0xaa, 0, 0, 0, 3, 4, 0, 8, 0xbb, 0xbb, 0xbb, 0xbb]
+        );
+
+        let tlvs: Vec<_> = TlvIter::new(&buf[..], format)
+            .map(|tlv| (tlv.tlv_type, tlv.value.len()))
+            .collect();
+        assert_eq!(tlvs, [(0x0102, 1), (0x0304, 4)]);
+
+        // The length must be a multiple of the length unit.
+        let format = TlvFormat {
+            len_unit: 8,
+            ..TlvFormat::IPV6_OPTIONS
+        };
+        let mut buf = [0; 16];
+        let mut writer = TlvWriter::new(&mut buf[..], format);
+        assert_eq!(writer.write(1, &[0; 6]), None);
+        writer.write(1, &[0; 8]).unwrap();
+        assert_eq!(buf[..2], [1, 1]);
+        assert_eq!(TlvIter::new(&buf[..10], format).count(), 1);
+    }
+
+    #[test]
+    fn ber_iter() {
+        // A constructed record holding a short, a long-form length and a
+        // high tag number record.
+        let mut buf = vec![
+            0x61, 0x00, 0x80, 0x02, 0x00, 0xff, 0x81, 0x81, 0x03, 1, 2, 3,
+        ];
+        buf.extend_from_slice(&[0x9f, 0x81, 0x01, 0x01, 0x07]);
+        buf[1] = (buf.len() - 2) as u8;
+
+        let mut iter = BerIter::new(&buf[..]);
+        let pdu = iter.next().unwrap();
+        assert_eq!(iter.next(), None);
+        assert!(iter.is_valid());
+        assert!(pdu.is_constructed());
+        assert_eq!(pdu.tag, 0x61);
+
+        let children: Vec<_> = pdu.children().map(|tlv| (tlv.tag, tlv.value)).collect();
+        assert_eq!(
+            children,
+            [
+                (0x80, &[0x00, 0xff][..]),
+                (0x81, &[1, 2, 3][..]),
+                (0x9f8101, &[7][..])
+            ]
+        );
+        assert_eq!(
+            pdu.children().find_tag(0x80).and_then(|tlv| tlv.as_uint()),
+            Some(255)
+        );
+        assert_eq!(pdu.children().find_tag(0x82), None);
+        assert!(BerIter::check_bytes(pdu.value));
+
+        // An indefinite length, and contents running past the buffer.
+        assert!(!BerIter::check_bytes(&[0x30, 0x80, 0x00, 0x00]));
+        assert!(!BerIter::check_bytes(&[0x80, 0x82, 0x01]));
+        assert!(!BerIter::check_bytes(&[0x80, 0x03, 0x01]));
+        assert!(BerIter::check_bytes(&[]));
+    }
+}
@@ -33,3 +33,20 @@ impl<T: PktMut + ?Sized> PktMut for &mut T {
         (**self).chunk_headroom()
     }
 }
+
+// Prefetch the cache line at the start of `data` into all the cache levels.
+// It is public for `packet_base!`, which may be expanded by other crates.
+#[cfg(target_arch = "x86_64")]
+#[doc(hidden)]
+#[inline(always)]
+pub fn prefetch(data: &[u8]) {
+    use std::arch::x86_64::{_mm_prefetch, _MM_HINT_T0};
+    // SAFETY: SSE is part of the x86_64 baseline, and a prefetch never faults.
+    unsafe { _mm_prefetch::<_MM_HINT_T0>(data.as_ptr() as *const i8) }
+}
+
+// There is no stable prefetch intrinsic on the other architectures.
+#[cfg(not(target_arch = "x86_64"))]
+#[doc(hidden)]
+#[inline(always)]
+pub fn prefetch(_: &[u8]) {}
@@ -0,0 +1,628 @@
+//! Decapsulation of tunneled traffic.
+//!
+//! [`decap`] looks through the tunnels of a frame, possibly nested, and
+//! strips them to reach the innermost frame, recording the stripped layers
+//! so that the tunnel identifiers can be accounted for. The recognized
+//! encapsulations are:
+//!
+//! * VXLAN and Geneve, on their well-known UDP ports.
+//! * GRE carrying Ethernet, IPv4 or IPv6, NVGRE, and ERSPAN mirroring
+//!   Ethernet frames.
+//! * GTP-U T-PDUs, on the well-known UDP port.
+//! * IPv4 and IPv6 in IPv4 or IPv6.
+//! * L2TPv2 data messages carrying IPv4 or IPv6 in PPP, on the well-known
+//!   UDP port.
+//! * Teredo, from or to the UDP port of the Teredo servers.
+//! * TZSP carrying Ethernet frames, on the well-known UDP port.
+//!
+//! The headers are looked up in the first chunk of the buffer, and the
+//! decapsulation stops at the first layer that is not a recognized tunnel,
+//! e.g. a fragment or a malformed header.
+//!
+//! # Examples
+//! ```
+//! use rpkt::tunnel::{decap_from, InnerFrame, TunnelLayer};
+//! use rpkt::{Buf, Cursor};
+//!
+//! // An IPv4 packet carrying an IPv4 packet without payload.
+//! let mut packet = [0; 40];
+//! packet[..4].copy_from_slice(&[0x45, 0, 0, 40]);
+//! packet[9] = 4;
+//! packet[20..24].copy_from_slice(&[0x45, 0, 0, 20]);
+//!
+//! let decapped = decap_from(Cursor::new(&packet[..]), InnerFrame::Ipv4);
+//! assert_eq!(decapped.layers(), &[TunnelLayer::IpInIp]);
+//! assert_eq!(decapped.frame(), InnerFrame::Ipv4);
+//! assert_eq!(decapped.into_buf().remaining(), 20);
+//! ```
+
+use byteorder::{ByteOrder, NetworkEndian};
+
+use crate::ether::{EtherType, VlanStack};
+use crate::flow::{gtpu_tpdu, skip_ipv6_extensions};
+use crate::gre::{GreGroup, NVGRE_HEADER_LEN};
+use crate::ipv4::{IpProtocol, Ipv4Header, IPV4_HEADER_LEN};
+use crate::ipv6::{Ipv6Header, IPV6_HEADER_LEN};
+use crate::mirror::{ErspanHeader, ErspanType, TzspEncap, TzspHeader, TzspType, TZSP_PORT};
+use crate::pppoe::PppProtocol;
+use crate::teredo::{TeredoIndicators, TEREDO_PORT};
+use crate::{Cursor, PktBuf};
+
+/// The maximum number of tunnel layers stripped by [`decap`].
+pub const DECAP_MAX_DEPTH: usize = 4;
+
+const VXLAN_PORT: u16 = 4789;
+const GENEVE_PORT: u16 = 6081;
+const GTPU_PORT: u16 = 2152;
+const L2TP_PORT: u16 = 1701;
+
+const UDP_HEADER_LEN: usize = 8;
+const VXLAN_HEADER_LEN: usize = 8;
+const GENEVE_HEADER_LEN: usize = 8;
+
+/// The kind of a frame.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum InnerFrame {
+    Ether,
+    Ipv4,
+    Ipv6,
+}
+
+/// A stripped tunnel layer and its identifier.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum TunnelLayer {
+    Vxlan {
+        vni: u32,
+    },
+    Geneve {
+        vni: u32,
+    },
+    Gre {
+        key: Option<u32>,
+    },
+    Nvgre {
+        vsid: u32,
+        flow_id: u8,
+    },
+    GtpU {
+        teid: u32,
+    },
+    /// IPv4 or IPv6 carried directly by IPv4 or IPv6.
+    IpInIp,
+    L2tp {
+        tunnel_id: u16,
+        session_id: u16,
+    },
+    /// IPv6 carried by UDP through NATs, with the indicators stripped.
+    Teredo,
+    /// A mirrored frame, the session is 0 for type I.
+    Erspan {
+        session_id: u16,
+    },
+    /// A mirrored frame.
+    Tzsp,
+}
+
+/// The innermost frame found by [`decap`], and the stripped tunnel layers.
+#[derive(Debug)]
+pub struct Decap<T> {
+    buf: T,
+    frame: InnerFrame,
+    layers: [TunnelLayer; DECAP_MAX_DEPTH],
+    depth: usize,
+}
+
+impl<T> Decap<T> {
+    /// Returns the kind of the innermost frame.
+    pub fn frame(&self) -> InnerFrame {
+        self.frame
+    }
+
+    /// Returns the stripped layers, from the outermost to the innermost.
+    pub fn layers(&self) -> &[TunnelLayer] {
+        &self.layers[..self.depth]
+    }
+
+    /// Returns whether any tunnel was stripped.
+    pub fn is_tunneled(&self) -> bool {
+        self.depth > 0
+    }
+
+    pub fn buf(&self) -> &T {
+        &self.buf
+    }
+
+    /// Returns the buffer starting at the innermost frame.
+    pub fn into_buf(self) -> T {
+        self.buf
+    }
+}
+
+/// Strip the tunnels of the Ethernet frame in `buf`, see [`decap_from`].
+pub fn decap<T: PktBuf>(buf: T) -> Decap<T> {
+    decap_from(buf, InnerFrame::Ether)
+}
+
+/// Strip the tunnels of the `frame` in `buf`, up to [`DECAP_MAX_DEPTH`] of
+/// them.
+///
+/// The returned buffer starts at the innermost frame, and ends with the
+/// payload of the innermost IP header of the tunnels, so that the padding of
+/// the outer frames is trimmed. If no tunnel is found, `buf` is returned
+/// unchanged.
+pub fn decap_from<T: PktBuf>(mut buf: T, mut frame: InnerFrame) -> Decap<T> {
+    let mut layers = [TunnelLayer::IpInIp; DECAP_MAX_DEPTH];
+    let mut depth = 0;
+
+    let data = buf.chunk();
+    let (mut start, mut end) = (0, data.len());
+    while depth < DECAP_MAX_DEPTH {
+        let data = &data[start..end];
+        let (ip_start, ip_frame) = match frame {
+            InnerFrame::Ether => match skip_ether(data) {
+                Some(next) => next,
+                None => break,
+            },
+            ip_frame => (0, ip_frame),
+        };
+        let Some((layer, inner, (inner_start, inner_end))) = strip_ip(&data[ip_start..], ip_frame)
+        else {
+            break;
+        };
+
+        layers[depth] = layer;
+        depth += 1;
+        frame = inner;
+        end = start + ip_start + inner_end;
+        start += ip_start + inner_start;
+    }
+
+    if depth > 0 {
+        let trim_size = buf.remaining() - end;
+        if trim_size > 0 {
+            buf.trim_off(trim_size);
+        }
+        buf.advance(start);
+    }
+
+    Decap {
+        buf,
+        frame,
+        layers,
+        depth,
+    }
+}
+
+// Returns the offset and the kind of the IP packet in an Ethernet frame.
+fn skip_ether(data: &[u8]) -> Option<(usize, InnerFrame)> {
+    let stack = VlanStack::parse(data)?;
+    let frame = ether_frame(stack.ethertype()).filter(|frame| *frame != InnerFrame::Ether)?;
+    Some((stack.header_len(), frame))
+}
+
+fn ether_frame(ethertype: EtherType) -> Option<InnerFrame> {
+    match ethertype {
+        EtherType::TRANS_ETHER_BRIDGING => Some(InnerFrame::Ether),
+        EtherType::IPV4 => Some(InnerFrame::Ipv4),
+        EtherType::IPV6 => Some(InnerFrame::Ipv6),
+        _ => None,
+    }
+}
+
+fn ip_version_frame(data: &[u8]) -> Option<InnerFrame> {
+    match data.first()? >> 4 {
+        4 => Some(InnerFrame::Ipv4),
+        6 => Some(InnerFrame::Ipv6),
+        _ => None,
+    }
+}
+
+// Strip the tunnel carried by the IP packet in `data`, and return the layer,
+// the kind of the inner frame and its range in `data`.
+fn strip_ip(data: &[u8], frame: InnerFrame) -> Option<(TunnelLayer, InnerFrame, (usize, usize))> {
+    let (protocol, payload_start, payload_end) = match frame {
+        InnerFrame::Ipv4 => {
+            let header = Ipv4Header::new(data).ok()?;
+            let header_len = usize::from(header.header_len());
+            let packet_len = usize::from(header.packet_len());
+            if header_len < IPV4_HEADER_LEN
+                || packet_len < header_len
+                || packet_len > data.len()
+                || header.more_frags()
+                || header.frag_offset() != 0
+            {
+                return None;
+            }
+            (header.protocol(), header_len, packet_len)
+        }
+        InnerFrame::Ipv6 => {
+            let header = Ipv6Header::new(data).ok()?;
+            let packet_len = IPV6_HEADER_LEN + usize::from(header.payload_len());
+            if packet_len > data.len() {
+                return None;
+            }
+            let (protocol, payload, is_frag) =
+                skip_ipv6_extensions(header.next_header(), &data[IPV6_HEADER_LEN..packet_len]);
+            if is_frag {
+                return None;
+            }
+            (protocol, packet_len - payload.len(), packet_len)
+        }
+        InnerFrame::Ether => return None,
+    };
+
+    let payload = &data[payload_start..payload_end];
+    let (layer, inner, header_len) = match protocol {
+        IpProtocol::IPIP | IpProtocol::IPV6 => {
+            let inner = ip_version_frame(payload)?;
+            if (protocol == IpProtocol::IPIP) != (inner == InnerFrame::Ipv4) {
+                return None;
+            }
+            (TunnelLayer::IpInIp, inner, 0)
+        }
+        IpProtocol::GRE => strip_gre(payload)?,
+        IpProtocol::UDP => {
+            if payload.len() < UDP_HEADER_LEN {
+                return None;
+            }
+            let src_port = NetworkEndian::read_u16(&payload[0..2]);
+            let dst_port = NetworkEndian::read_u16(&payload[2..4]);
+            let (layer, inner, header_len) =
+                strip_udp(&payload[UDP_HEADER_LEN..], src_port, dst_port)?;
+            (layer, inner, UDP_HEADER_LEN + header_len)
+        }
+        _ => return None,
+    };
+    Some((layer, inner, (payload_start + header_len, payload_end)))
+}
+
+fn strip_gre(data: &[u8]) -> Option<(TunnelLayer, InnerFrame, usize)> {
+    match GreGroup::parse(Cursor::new(data)).ok()? {
+        GreGroup::Nvgre(nvgrepkt) => {
+            let layer = TunnelLayer::Nvgre {
+                vsid: nvgrepkt.vsid(),
+                flow_id: nvgrepkt.flow_id(),
+            };
+            Some((layer, InnerFrame::Ether, NVGRE_HEADER_LEN))
+        }
+        GreGroup::Gre(grepkt) => {
+            if let Some(erspan_type) =
+                ErspanType::from_gre(grepkt.protocol_type(), grepkt.seq_present())
+            {
+                let gre_len = grepkt.header_len();
+                let header = ErspanHeader::parse(erspan_type, &data[gre_len..])
+                    .filter(ErspanHeader::is_ether)?;
+                let layer = TunnelLayer::Erspan {
+                    session_id: header.session_id,
+                };
+                return Some((layer, InnerFrame::Ether, gre_len + header.header_len));
+            }
+            let inner = ether_frame(grepkt.protocol_type())?;
+            let layer = TunnelLayer::Gre { key: grepkt.key() };
+            Some((layer, inner, grepkt.header_len()))
+        }
+    }
+}
+
+fn strip_udp(
+    data: &[u8],
+    src_port: u16,
+    dst_port: u16,
+) -> Option<(TunnelLayer, InnerFrame, usize)> {
+    // The replies of a Teredo server come from its port.
+    if src_port == TEREDO_PORT || dst_port == TEREDO_PORT {
+        return strip_teredo(data);
+    }
+
+    match dst_port {
+        VXLAN_PORT => {
+            // The I flag indicates a valid VNI.
+            if data.len() < VXLAN_HEADER_LEN || data[0] & 0x08 == 0 {
+                return None;
+            }
+            let vni = NetworkEndian::read_u24(&data[4..7]);
+            Some((
+                TunnelLayer::Vxlan { vni },
+                InnerFrame::Ether,
+                VXLAN_HEADER_LEN,
+            ))
+        }
+        GENEVE_PORT => {
+            if data.len() < GENEVE_HEADER_LEN || data[0] >> 6 != 0 {
+                return None;
+            }
+            let header_len = GENEVE_HEADER_LEN + usize::from(data[0] & 0x3f) * 4;
+            let inner = ether_frame(NetworkEndian::read_u16(&data[2..4]).into())?;
+            if header_len > data.len() {
+                return None;
+            }
+            let vni = NetworkEndian::read_u24(&data[4..7]);
+            Some((TunnelLayer::Geneve { vni }, inner, header_len))
+        }
+        GTPU_PORT => {
+            let tpdu = gtpu_tpdu(data)?;
+            let teid = NetworkEndian::read_u32(&data[4..8]);
+            let inner = ip_version_frame(tpdu)?;
+            Some((TunnelLayer::GtpU { teid }, inner, data.len() - tpdu.len()))
+        }
+        L2TP_PORT => strip_l2tp(data),
+        TZSP_PORT => {
+            let header = TzspHeader::parse(data)?;
+            let packet_type = header.packet_type();
+            if (packet_type != TzspType::RX_PACKET && packet_type != TzspType::TX_PACKET)
+                || header.encap() != TzspEncap::ETHERNET
+            {
+                return None;
+            }
+            Some((TunnelLayer::Tzsp, InnerFrame::Ether, header.header_len()))
+        }
+        _ => None,
+    }
+}
+
+fn strip_teredo(data: &[u8]) -> Option<(TunnelLayer, InnerFrame, usize)> {
+    let (_, len) = TeredoIndicators::parse(data)?;
+    if ip_version_frame(&data[len..])? != InnerFrame::Ipv6 {
+        return None;
+    }
+    Some((TunnelLayer::Teredo, InnerFrame::Ipv6, len))
+}
+
+// Strip the header of an L2TPv2 data message (RFC 2661) and the header of
+// the PPP frame it carries.
+fn strip_l2tp(data: &[u8]) -> Option<(TunnelLayer, InnerFrame, usize)> {
+    let flags = NetworkEndian::read_u16(data.get(0..2)?);
+    // Control messages, and other versions.
+    if flags & 0x8000 != 0 || flags & 0x000f != 2 {
+        return None;
+    }
+
+    let mut offset = 2;
+    if flags & 0x4000 != 0 {
+        offset += 2;
+    }
+    let ids = data.get(offset..offset + 4)?;
+    let tunnel_id = NetworkEndian::read_u16(&ids[0..2]);
+    let session_id = NetworkEndian::read_u16(&ids[2..4]);
+    offset += 4;
+    if flags & 0x0800 != 0 {
+        offset += 4;
+    }
+    if flags & 0x0200 != 0 {
+        let offset_size = NetworkEndian::read_u16(data.get(offset..offset + 2)?);
+        offset += 2 + usize::from(offset_size);
+    }
+
+    // The address and control fields may be omitted, and the protocol field
+    // may be compressed to a single byte.
+    if data.get(offset..offset + 2) == Some(&[0xff, 0x03]) {
+        offset += 2;
+    }
+    let protocol = match *data.get(offset)? {
+        first if first & 0x01 != 0 => {
+            offset += 1;
+            PppProtocol::from(u16::from(first))
+        }
+        _ => {
+            let protocol = NetworkEndian::read_u16(data.get(offset..offset + 2)?);
+            offset += 2;
+            PppProtocol::from(protocol)
+        }
+    };
+    let inner = match protocol {
+        PppProtocol::IPV4 => InnerFrame::Ipv4,
+        PppProtocol::IPV6 => InnerFrame::Ipv6,
+        _ => return None,
+    };
+    if offset > data.len() {
+        return None;
+    }
+
+    let layer = TunnelLayer::L2tp {
+        tunnel_id,
+        session_id,
+    };
+    Some((layer, inner, offset))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Buf;
+
+    const INNER_IPV4: [u8; 24] = [
+        0x45, 0x00, 0x00, 0x18, 0x00, 0x00, 0x40, 0x00, 0x40, 0x11, 0x00, 0x00, 0x0a, 0x00, 0x00,
+        0x01, 0x0a, 0x00, 0x00, 0x02, 0xde, 0xad, 0xbe, 0xef,
+    ];
+
+    fn ipv4(protocol: u8, payload: &[u8]) -> Vec<u8> {
+        let mut packet = vec![0; 20];
+        packet[0] = 0x45;
+        packet[2..4].copy_from_slice(&((20 + payload.len()) as u16).to_be_bytes());
+        packet[9] = protocol;
+        packet.extend_from_slice(payload);
+        packet
+    }
+
+    fn ipv6(next_header: u8, payload: &[u8]) -> Vec<u8> {
+        let mut packet = vec![0; 40];
+        packet[0] = 0x60;
+        packet[4..6].copy_from_slice(&(payload.len() as u16).to_be_bytes());
+        packet[6] = next_header;
+        packet.extend_from_slice(payload);
+        packet
+    }
+
+    fn udp(dst_port: u16, payload: &[u8]) -> Vec<u8> {
+        let mut datagram = vec![0xc0, 0x00, 0, 0, 0, 0, 0, 0];
+        datagram[2..4].copy_from_slice(&dst_port.to_be_bytes());
+        datagram[4..6].copy_from_slice(&((8 + payload.len()) as u16).to_be_bytes());
+        datagram.extend_from_slice(payload);
+        datagram
+    }
+
+    fn ether(ethertype: u16, payload: &[u8]) -> Vec<u8> {
+        let mut frame = vec![0; 12];
+        frame.extend_from_slice(&ethertype.to_be_bytes());
+        frame.extend_from_slice(payload);
+        frame
+    }
+
+    #[test]
+    fn vxlan_in_gtpu() {
+        let inner = ether(0x0800, &INNER_IPV4);
+        let mut vxlan = vec![0x08, 0, 0, 0, 0x00, 0x01, 0x02, 0x00];
+        vxlan.extend_from_slice(&inner);
+        let tpdu = ipv4(17, &udp(VXLAN_PORT, &vxlan));
+        let mut gtpu = vec![0x30, 0xff, 0x00, tpdu.len() as u8, 0x12, 0x34, 0x56, 0x78];
+        gtpu.extend_from_slice(&tpdu);
+        let mut frame = ether(0x0800, &ipv4(17, &udp(GTPU_PORT, &gtpu)));
+        // The padding of the outer frame.
+        frame.extend_from_slice(&[0; 6]);
+
+        let decapped = decap(Cursor::new(&frame[..]));
+        assert_eq!(
+            decapped.layers(),
+            &[
+                TunnelLayer::GtpU { teid: 0x12345678 },
+                TunnelLayer::Vxlan { vni: 0x000102 }
+            ]
+        );
+        assert_eq!(decapped.frame(), InnerFrame::Ether);
+        assert_eq!(decapped.into_buf().chunk(), &inner[..]);
+    }
+
+    #[test]
+    fn gre_and_ip_in_ip() {
+        // NVGRE carrying an Ethernet frame.
+        let inner = ether(0x0800, &INNER_IPV4);
+        let mut nvgre = vec![0x20, 0x00, 0x65, 0x58, 0x00, 0x00, 0x10, 0x07];
+        nvgre.extend_from_slice(&inner);
+        let frame = ether(0x86dd, &ipv6(47, &nvgre));
+        let decapped = decap(Cursor::new(&frame[..]));
+        assert_eq!(
+            decapped.layers(),
+            &[TunnelLayer::Nvgre {
+                vsid: 0x10,
+                flow_id: 0x07
+            }]
+        );
+        assert_eq!(decapped.into_buf().chunk(), &inner[..]);
+
+        // 4in6 in GRE with a sequence number.
+        let mut gre = vec![0x10, 0x00, 0x86, 0xdd, 0, 0, 0, 1];
+        gre.extend_from_slice(&ipv6(4, &INNER_IPV4));
+        let packet = ipv4(47, &gre);
+        let decapped = decap_from(Cursor::new(&packet[..]), InnerFrame::Ipv4);
+        assert_eq!(
+            decapped.layers(),
+            &[TunnelLayer::Gre { key: None }, TunnelLayer::IpInIp]
+        );
+        assert_eq!(decapped.frame(), InnerFrame::Ipv4);
+        assert_eq!(decapped.into_buf().chunk(), &INNER_IPV4[..]);
+    }
+
+    #[test]
+    fn geneve_and_l2tp() {
+        let mut geneve = vec![0x01, 0x00, 0x08, 0x00, 0x00, 0x00, 0x2a, 0x00];
+        geneve.extend_from_slice(&[0x01, 0x02, 0x03, 0x00]);
+        geneve.extend_from_slice(&INNER_IPV4);
+        let packet = ipv4(17, &udp(GENEVE_PORT, &geneve));
+        let decapped = decap_from(Cursor::new(&packet[..]), InnerFrame::Ipv4);
+        assert_eq!(decapped.layers(), &[TunnelLayer::Geneve { vni: 0x2a }]);
+        assert_eq!(decapped.into_buf().chunk(), &INNER_IPV4[..]);
+
+        // With the length field, and a compressed PPP header.
+        let mut l2tp = vec![0x40, 0x02, 0x00, 0x00, 0x00, 0x05, 0x00, 0x09, 0x21];
+        l2tp.extend_from_slice(&INNER_IPV4);
+        let packet = ipv4(17, &udp(L2TP_PORT, &l2tp));
+        let decapped = decap_from(Cursor::new(&packet[..]), InnerFrame::Ipv4);
+        assert_eq!(
+            decapped.layers(),
+            &[TunnelLayer::L2tp {
+                tunnel_id: 5,
+                session_id: 9
+            }]
+        );
+        assert_eq!(decapped.into_buf().chunk(), &INNER_IPV4[..]);
+
+        // A control message is not a tunnel.
+        l2tp[0] = 0xc8;
+        let packet = ipv4(17, &udp(L2TP_PORT, &l2tp));
+        assert!(!decap_from(Cursor::new(&packet[..]), InnerFrame::Ipv4).is_tunneled());
+    }
+
+    #[test]
+    fn not_tunneled() {
+        let frame = ether(0x0800, &INNER_IPV4);
+        let decapped = decap(Cursor::new(&frame[..]));
+        assert!(!decapped.is_tunneled());
+        assert_eq!(decapped.frame(), InnerFrame::Ether);
+        assert_eq!(decapped.into_buf().remaining(), frame.len());
+
+        // A fragment, or a protocol that does not match the inner version.
+        let mut packet = ipv4(4, &ipv4(4, &INNER_IPV4));
+        packet[6] = 0x20;
+        assert!(!decap_from(Cursor::new(&packet[..]), InnerFrame::Ipv4).is_tunneled());
+        let packet = ipv4(41, &INNER_IPV4);
+        assert!(!decap_from(Cursor::new(&packet[..]), InnerFrame::Ipv4).is_tunneled());
+    }
+
+    #[test]
+    fn max_depth() {
+        let mut packet = INNER_IPV4.to_vec();
+        for _ in 0..DECAP_MAX_DEPTH + 1 {
+            packet = ipv4(4, &packet);
+        }
+        let decapped = decap_from(Cursor::new(&packet[..]), InnerFrame::Ipv4);
+        assert_eq!(decapped.layers().len(), DECAP_MAX_DEPTH);
+        assert_eq!(decapped.into_buf().remaining(), 20 + INNER_IPV4.len());
+    }
+
+    #[test]
+    fn teredo() {
+        // An IPv6 packet behind an origin indication, sent by a server.
+        let inner = ipv6(59, &[]);
+        let mut teredo = vec![0x00, 0x00, 0x63, 0xbf, 0x3f, 0xff, 0xfd, 0xd2];
+        teredo.extend_from_slice(&inner);
+        let mut datagram = udp(40000, &teredo);
+        datagram[0..2].copy_from_slice(&TEREDO_PORT.to_be_bytes());
+        let frame = ether(0x0800, &ipv4(17, &datagram));
+
+        let decapped = decap(Cursor::new(&frame[..]));
+        assert_eq!(decapped.layers(), &[TunnelLayer::Teredo]);
+        assert_eq!(decapped.frame(), InnerFrame::Ipv6);
+        assert_eq!(decapped.into_buf().chunk(), &inner[..]);
+
+        // Other traffic on the port.
+        let frame = ether(0x0800, &ipv4(17, &udp(TEREDO_PORT, &INNER_IPV4)));
+        assert!(!decap(Cursor::new(&frame[..])).is_tunneled());
+    }
+
+    #[test]
+    fn mirrors() {
+        // ERSPAN type II mirroring a frame.
+        let inner = ether(0x0800, &INNER_IPV4);
+        let mut erspan = vec![0x10, 0x00, 0x88, 0xbe, 0x00, 0x00, 0x00, 0x01];
+        erspan.extend_from_slice(&[0x10, 0x00, 0x00, 0x2a, 0x00, 0x00, 0x00, 0x00]);
+        erspan.extend_from_slice(&inner);
+        let frame = ether(0x0800, &ipv4(47, &erspan));
+        let decapped = decap(Cursor::new(&frame[..]));
+        assert_eq!(decapped.layers(), &[TunnelLayer::Erspan { session_id: 42 }]);
+        assert_eq!(decapped.into_buf().chunk(), &inner[..]);
+
+        // TZSP carrying a received frame.
+        let mut tzsp = vec![0x01, 0x00, 0x00, 0x01, 0x01];
+        tzsp.extend_from_slice(&inner);
+        let frame = ether(0x0800, &ipv4(17, &udp(TZSP_PORT, &tzsp)));
+        let decapped = decap(Cursor::new(&frame[..]));
+        assert_eq!(decapped.layers(), &[TunnelLayer::Tzsp]);
+        assert_eq!(decapped.frame(), InnerFrame::Ether);
+        assert_eq!(decapped.into_buf().chunk(), &inner[..]);
+
+        // An 802.11 frame is not decapsulated.
+        tzsp[3] = 18;
+        let frame = ether(0x0800, &ipv4(17, &udp(TZSP_PORT, &tzsp)));
+        assert!(!decap(Cursor::new(&frame[..])).is_tunneled());
+    }
+}
@@ -95,3 +95,23 @@ impl<T: AsMut<[u8]>> UdpHeader<T> {
         NetworkEndian::write_u16(data, value)
     }
 }
+
+/// Const builders for the header template, so that the presets of the header can
+/// be evaluated at compile time and stored in `const`s or `static`s.
+impl UdpHeader<[u8; UDP_HEADER_LEN]> {
+    #[inline]
+    pub const fn with_source_port(mut self, value: u16) -> Self {
+        let bytes = value.to_be_bytes();
+        self.buf[0] = bytes[0];
+        self.buf[1] = bytes[1];
+        self
+    }
+
+    #[inline]
+    pub const fn with_dest_port(mut self, value: u16) -> Self {
+        let bytes = value.to_be_bytes();
+        self.buf[2] = bytes[0];
+        self.buf[3] = bytes[1];
+        self
+    }
+}
@@ -30,19 +30,21 @@ packet_base! {
 impl<T: Buf> UdpPacket<T> {
     #[inline]
     pub fn parse(buf: T) -> Result<UdpPacket<T>, T> {
-        if buf.chunk().len() < UDP_HEADER_LEN {
-            return Err(buf);
-        }
-
-        let packet = UdpPacket::parse_unchecked(buf);
-
-        if usize::from(packet.packet_len()) >= UDP_HEADER_LEN
-            && usize::from(packet.packet_len()) <= packet.buf.remaining()
-        {
-            Ok(packet)
-        } else {
-            Err(packet.release())
-        }
+        traced_parse!("udp", buf, |_: &Self| UDP_HEADER_LEN, {
+            if buf.chunk().len() < UDP_HEADER_LEN {
+                return Err(buf);
+            }
+
+            let packet = UdpPacket::parse_unchecked(buf);
+
+            if usize::from(packet.packet_len()) >= UDP_HEADER_LEN
+                && usize::from(packet.packet_len()) <= packet.buf.remaining()
+            {
+                Ok(packet)
+            } else {
+                Err(packet.release())
+            }
+        })
     }
 }
 
@@ -0,0 +1,157 @@
+//! A conformance suite driven by reference captures.
+//!
+//! Every capture listed in `manifest.txt` is loaded, and each of its frames is
+//! parsed and re-serialized by the dissectors of `protocols.rs`. The suite
+//! fails if a frame is not dissected into the protocols listed by the
+//! manifest, or if the re-serialized frame differs from the captured one.
+//!
+//! Run the suite alone with `cargo test -p rpkt --test conformance`.
+
+mod pcap;
+mod protocols;
+
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use protocols::Dissection;
+
+/// A capture listed in the manifest.
+#[derive(Debug)]
+struct Entry {
+    line: usize,
+    path: PathBuf,
+    layers: Vec<String>,
+}
+
+fn suite_dir() -> PathBuf {
+    Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/conformance")
+}
+
+fn parse_manifest(text: &str) -> Result<Vec<Entry>, String> {
+    let mut entries = Vec::new();
+    for (index, line) in text.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let mut fields = line.split_whitespace();
+        let path = fields.next().unwrap();
+        let layers: Vec<String> = fields.map(str::to_string).collect();
+        if layers.is_empty() {
+            return Err(format!("line {}: no protocol is listed", index + 1));
+        }
+        if let Some(layer) = layers.iter().find(|l| protocols::dissector(l).is_none()) {
+            return Err(format!("line {}: unknown protocol {}", index + 1, layer));
+        }
+        entries.push(Entry {
+            line: index + 1,
+            path: PathBuf::from(path),
+            layers,
+        });
+    }
+    Ok(entries)
+}
+
+// Check every frame of a capture, returning the failures.
+fn check_capture(entry: &Entry) -> Vec<String> {
+    let path = suite_dir().join(&entry.path);
+    let capture = match fs::read(&path)
+        .map_err(|err| err.to_string())
+        .and_then(|data| pcap::read(&data))
+    {
+        Ok(capture) => capture,
+        Err(err) => return vec![format!("{}: {}", entry.path.display(), err)],
+    };
+    if entry.layers[0] == "ether" && capture.linktype != pcap::LINKTYPE_ETHERNET {
+        return vec![format!(
+            "{}: link type {} is not ethernet",
+            entry.path.display(),
+            capture.linktype
+        )];
+    }
+    if capture.frames.is_empty() {
+        return vec![format!("{}: no frame is captured", entry.path.display())];
+    }
+
+    let first: &'static str = protocols::PROTOCOLS
+        .iter()
+        .find(|(name, _)| *name == entry.layers[0])
+        .unwrap()
+        .0;
+    let mut failures = Vec::new();
+    for (index, frame) in capture.frames.iter().enumerate() {
+        let context = format!("{} frame {}", entry.path.display(), index);
+        let mut dissection = Dissection::default();
+        match dissection.run(first, frame) {
+            Ok(bytes) => {
+                if dissection.layers != entry.layers {
+                    failures.push(format!(
+                        "{}: dissected into {:?}, expected {:?}",
+                        context, dissection.layers, entry.layers
+                    ));
+                } else if let Some(offset) = first_diff(&bytes, frame) {
+                    failures.push(format!(
+                        "{}: re-serialized frame differs at byte {} \
+                         ({} bytes re-serialized, {} bytes captured)",
+                        context,
+                        offset,
+                        bytes.len(),
+                        frame.len()
+                    ));
+                }
+            }
+            Err(err) => failures.push(format!("{}: {}", context, err)),
+        }
+    }
+    failures
+}
+
+fn first_diff(a: &[u8], b: &[u8]) -> Option<usize> {
+    a.iter()
+        .zip(b)
+        .position(|(x, y)| x != y)
+        .or_else(|| (a.len() != b.len()).then_some(a.len().min(b.len())))
+}
+
+#[test]
+fn reference_captures() {
+    let text = fs::read_to_string(suite_dir().join("manifest.txt")).unwrap();
+    let entries = parse_manifest(&text).unwrap();
+    assert!(!entries.is_empty());
+
+    let failures: Vec<String> = entries.iter().flat_map(check_capture).collect();
+    assert!(
+        failures.is_empty(),
+        "{} failures:\n{}",
+        failures.len(),
+        failures.join("\n")
+    );
+}
+
+#[test]
+fn manifest_errors() {
+    let entries = parse_manifest("# comment\n\ncaptures/a.pcap  ether ipv4 \n").unwrap();
+    assert_eq!(entries.len(), 1);
+    assert_eq!(entries[0].line, 3);
+    assert_eq!(entries[0].path, PathBuf::from("captures/a.pcap"));
+    assert_eq!(entries[0].layers, ["ether", "ipv4"]);
+
+    assert!(parse_manifest("captures/a.pcap\n").is_err());
+    assert!(parse_manifest("captures/a.pcap ether sctp\n").is_err());
+}
+
+#[test]
+fn mismatch_is_reported() {
+    // A frame whose reserved IPv4 flag can not be re-serialized.
+    let mut frame = vec![0; 14 + 20 + 8];
+    frame[12..14].copy_from_slice(&[0x08, 0x00]);
+    frame[14..24].copy_from_slice(&[0x45, 0, 0, 28, 0, 1, 0x80, 0, 64, 17]);
+    frame[34..42].copy_from_slice(&[0, 1, 0, 2, 0, 8, 0, 0]);
+
+    let mut dissection = Dissection::default();
+    let bytes = dissection.run("ether", &frame).unwrap();
+    assert_eq!(dissection.layers, ["ether", "ipv4", "udp"]);
+    assert_eq!(first_diff(&bytes, &frame), Some(14 + 6));
+    assert_eq!(first_diff(&frame, &frame), None);
+    assert_eq!(first_diff(&frame[..10], &frame), Some(10));
+}
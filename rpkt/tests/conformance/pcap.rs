@@ -0,0 +1,72 @@
+//! A reader of the classic pcap file format.
+
+/// The link type of Ethernet frames.
+pub const LINKTYPE_ETHERNET: u32 = 1;
+
+const MAGIC_MICROS: u32 = 0xa1b2c3d4;
+const MAGIC_NANOS: u32 = 0xa1b23c4d;
+const GLOBAL_HEADER_LEN: usize = 24;
+const RECORD_HEADER_LEN: usize = 16;
+
+/// The frames of a pcap file.
+#[derive(Debug)]
+pub struct Capture {
+    pub linktype: u32,
+    pub frames: Vec<Vec<u8>>,
+}
+
+/// Read a pcap file with microsecond or nanosecond timestamps, in either byte
+/// order.
+///
+/// Returns an error if the file is not a classic pcap file, e.g. a pcapng
+/// file, if it is truncated, or if a frame is not captured entirely.
+pub fn read(data: &[u8]) -> Result<Capture, String> {
+    if data.len() < GLOBAL_HEADER_LEN {
+        return Err("truncated pcap header".to_string());
+    }
+    let magic = [data[0], data[1], data[2], data[3]];
+    let big_endian = match (u32::from_le_bytes(magic), u32::from_be_bytes(magic)) {
+        (MAGIC_MICROS | MAGIC_NANOS, _) => false,
+        (_, MAGIC_MICROS | MAGIC_NANOS) => true,
+        _ => return Err("not a classic pcap file, pcapng is not supported".to_string()),
+    };
+    let read_u32 = |offset: usize| {
+        let bytes = [
+            data[offset],
+            data[offset + 1],
+            data[offset + 2],
+            data[offset + 3],
+        ];
+        if big_endian {
+            u32::from_be_bytes(bytes)
+        } else {
+            u32::from_le_bytes(bytes)
+        }
+    };
+
+    let linktype = read_u32(20);
+    let mut frames = Vec::new();
+    let mut offset = GLOBAL_HEADER_LEN;
+    while offset < data.len() {
+        if data.len() - offset < RECORD_HEADER_LEN {
+            return Err(format!("truncated header of frame {}", frames.len()));
+        }
+        let incl_len = read_u32(offset + 8) as usize;
+        let orig_len = read_u32(offset + 12) as usize;
+        offset += RECORD_HEADER_LEN;
+        if data.len() - offset < incl_len {
+            return Err(format!("truncated data of frame {}", frames.len()));
+        }
+        if incl_len != orig_len {
+            return Err(format!(
+                "frame {} is captured partially, {} of {} bytes",
+                frames.len(),
+                incl_len,
+                orig_len
+            ));
+        }
+        frames.push(data[offset..offset + incl_len].to_vec());
+        offset += incl_len;
+    }
+    Ok(Capture { linktype, frames })
+}
@@ -0,0 +1,288 @@
+//! The protocols checked by the conformance suite.
+//!
+//! Every protocol has a dissector, which parses the packet at the start of its
+//! input with `rpkt`, dissects the payload as the protocol named by the header,
+//! and re-serializes the packet: a template header is prepended in front of
+//! the re-serialized payload, and every parsed field is written back with the
+//! setters. The checksums are copied rather than recomputed, so that the
+//! captures of NICs with checksum offloading can be used as references.
+//!
+//! A new protocol is supported by writing its dissector and adding it to
+//! [`PROTOCOLS`], under the name used by the manifest.
+
+use rpkt::arp::{ArpPacket, ARP_HEADER_LEN, ARP_HEADER_TEMPLATE};
+use rpkt::ether::{EtherPacket, EtherType, VlanStack, ETHER_HEADER_TEMPLATE};
+use rpkt::icmpv4::{Icmpv4Packet, ICMPV4_HEADER_LEN, ICMPV4_HEADER_TEMPLATE};
+use rpkt::icmpv6::{Icmpv6Msg, Icmpv6Packet};
+use rpkt::ipv4::{IpProtocol, Ipv4Packet, IPV4_HEADER_TEMPLATE};
+use rpkt::ipv6::{Ipv6Header, Ipv6Packet, IPV6_HEADER_LEN};
+use rpkt::tcp::{TcpPacket, TCP_HEADER_TEMPLATE};
+use rpkt::udp::{UdpPacket, UDP_HEADER_LEN, UDP_HEADER_TEMPLATE};
+use rpkt::{Buf, Cursor, CursorMut};
+
+/// Re-serializes the packet at the start of the input, dissecting its payload
+/// with the [`Dissection`].
+pub type Dissector = fn(&[u8], &mut Dissection) -> Result<Vec<u8>, String>;
+
+/// The supported protocols, by name.
+pub const PROTOCOLS: &[(&str, Dissector)] = &[
+    ("ether", ether),
+    ("arp", arp),
+    ("ipv4", ipv4),
+    ("ipv6", ipv6),
+    ("tcp", tcp),
+    ("udp", udp),
+    ("icmpv4", icmpv4),
+    ("icmpv6", icmpv6),
+];
+
+/// The name of an unknown payload, which is copied as is.
+const RAW: &str = "raw";
+
+/// Returns the dissector of the protocol named `name`.
+pub fn dissector(name: &str) -> Option<Dissector> {
+    PROTOCOLS
+        .iter()
+        .find(|(protocol, _)| *protocol == name)
+        .map(|(_, dissector)| *dissector)
+}
+
+/// The dissection of a frame.
+#[derive(Debug, Default)]
+pub struct Dissection {
+    /// The names of the dissected protocols, from the outermost.
+    pub layers: Vec<&'static str>,
+}
+
+impl Dissection {
+    /// Dissect `data` as the protocol named `name`, returning the
+    /// re-serialized packet.
+    ///
+    /// The data of an unsupported protocol is returned as is.
+    pub fn run(&mut self, name: &'static str, data: &[u8]) -> Result<Vec<u8>, String> {
+        match dissector(name) {
+            Some(dissector) => {
+                self.layers.push(name);
+                dissector(data, self).map_err(|err| format!("{}: {}", name, err))
+            }
+            None => Ok(data.to_vec()),
+        }
+    }
+}
+
+// Prepend a header of `header_len` bytes in front of `payload` with `build`,
+// which receives a cursor at the start of the payload.
+fn prepend<F>(header_len: usize, payload: &[u8], build: F) -> Vec<u8>
+where
+    F: FnOnce(CursorMut<'_>),
+{
+    let mut bytes = vec![0; header_len + payload.len()];
+    bytes[header_len..].copy_from_slice(payload);
+    let mut buf = CursorMut::new(&mut bytes[..]);
+    buf.advance(header_len);
+    build(buf);
+    bytes
+}
+
+fn ether_next(ethertype: EtherType) -> &'static str {
+    match ethertype {
+        EtherType::ARP => "arp",
+        EtherType::IPV4 => "ipv4",
+        EtherType::IPV6 => "ipv6",
+        _ => RAW,
+    }
+}
+
+fn ip_next(protocol: IpProtocol) -> &'static str {
+    match protocol {
+        IpProtocol::TCP => "tcp",
+        IpProtocol::UDP => "udp",
+        IpProtocol::ICMP => "icmpv4",
+        IpProtocol::ICMPV6 => "icmpv6",
+        _ => RAW,
+    }
+}
+
+// An Ethernet frame with optional VLAN tags.
+fn ether(data: &[u8], dissection: &mut Dissection) -> Result<Vec<u8>, String> {
+    let stack = VlanStack::parse(data).ok_or("truncated header")?;
+    let ethpkt = EtherPacket::parse(Cursor::new(data)).map_err(|_| "truncated header")?;
+    let (dest_mac, source_mac) = (ethpkt.dest_mac(), ethpkt.source_mac());
+
+    let header_len = stack.header_len();
+    let payload = dissection.run(ether_next(stack.ethertype()), &data[header_len..])?;
+    Ok(prepend(header_len, &payload, |buf| {
+        let mut ethpkt = EtherPacket::prepend_header(buf, &ETHER_HEADER_TEMPLATE);
+        ethpkt.set_dest_mac(dest_mac);
+        ethpkt.set_source_mac(source_mac);
+        ethpkt.set_ethertype(stack.ethertype());
+        let mut buf = ethpkt.release();
+        for tag in stack.tags().iter().rev() {
+            VlanStack::push(&mut buf, *tag);
+        }
+    }))
+}
+
+fn arp(data: &[u8], _: &mut Dissection) -> Result<Vec<u8>, String> {
+    let arppkt = ArpPacket::parse(Cursor::new(data)).map_err(|_| "malformed header")?;
+    // The padding of the Ethernet frame follows the header.
+    Ok(prepend(ARP_HEADER_LEN, &data[ARP_HEADER_LEN..], |buf| {
+        let mut pkt = ArpPacket::prepend_header(buf, &ARP_HEADER_TEMPLATE);
+        pkt.set_hardware_type(arppkt.hardware_type());
+        pkt.set_protocol_type(arppkt.protocol_type());
+        pkt.set_hardware_len(arppkt.hardware_len());
+        pkt.set_protocol_len(arppkt.protocol_len());
+        pkt.set_operation(arppkt.operation());
+        pkt.set_sender_hardware_addr(arppkt.sender_hardware_addr());
+        pkt.set_sender_protocol_addr(arppkt.sender_protocol_addr());
+        pkt.set_target_hardware_addr(arppkt.target_hardware_addr());
+        pkt.set_target_protocol_addr(arppkt.target_protocol_addr());
+    }))
+}
+
+fn ipv4(data: &[u8], dissection: &mut Dissection) -> Result<Vec<u8>, String> {
+    let ippkt = Ipv4Packet::parse(Cursor::new(data)).map_err(|_| "malformed header")?;
+    if !ippkt.check_version() {
+        return Err("bad version".to_string());
+    }
+    let header_len = usize::from(ippkt.header_len());
+    let packet_len = usize::from(ippkt.packet_len());
+
+    // The payload of a fragment is not parsed.
+    let next = if ippkt.more_frags() || ippkt.frag_offset() != 0 {
+        RAW
+    } else {
+        ip_next(ippkt.protocol())
+    };
+    let payload = dissection.run(next, &data[header_len..packet_len])?;
+
+    let mut header = IPV4_HEADER_TEMPLATE;
+    header.set_header_len(ippkt.header_len());
+    let mut bytes = prepend(header_len, &payload, |buf| {
+        let mut pkt = Ipv4Packet::prepend_header(buf, &header);
+        pkt.option_bytes_mut().copy_from_slice(ippkt.option_bytes());
+        pkt.set_dscp(ippkt.dscp());
+        pkt.set_ecn(ippkt.ecn());
+        pkt.set_ident(ippkt.ident());
+        pkt.clear_flags();
+        pkt.set_dont_frag(ippkt.dont_frag());
+        pkt.set_more_frags(ippkt.more_frags());
+        pkt.set_frag_offset(ippkt.frag_offset());
+        pkt.set_time_to_live(ippkt.time_to_live());
+        pkt.set_protocol(ippkt.protocol());
+        pkt.set_checksum(ippkt.checksum());
+        pkt.set_source_ip(ippkt.source_ip());
+        pkt.set_dest_ip(ippkt.dest_ip());
+    });
+    bytes.extend_from_slice(&data[packet_len..]);
+    Ok(bytes)
+}
+
+fn ipv6(data: &[u8], dissection: &mut Dissection) -> Result<Vec<u8>, String> {
+    let ippkt = Ipv6Packet::parse(Cursor::new(data)).map_err(|_| "malformed header")?;
+    if !ippkt.check_version() {
+        return Err("bad version".to_string());
+    }
+    let packet_len = IPV6_HEADER_LEN + usize::from(ippkt.payload_len());
+
+    // The extension headers are not parsed.
+    let payload = dissection.run(
+        ip_next(ippkt.next_header()),
+        &data[IPV6_HEADER_LEN..packet_len],
+    )?;
+
+    // There is no template of an IPv6 header.
+    let mut header = Ipv6Header::new_unchecked([0; IPV6_HEADER_LEN]);
+    header.adjust_version();
+    let mut bytes = prepend(IPV6_HEADER_LEN, &payload, |buf| {
+        let mut pkt = Ipv6Packet::prepend_header(buf, &header);
+        pkt.set_traffic_class(ippkt.traffic_class());
+        pkt.set_flow_label(ippkt.flow_label());
+        pkt.set_next_header(ippkt.next_header());
+        pkt.set_hop_limit(ippkt.hop_limit());
+        pkt.set_source_ip(&ippkt.source_ip());
+        pkt.set_dest_ip(&ippkt.dest_ip());
+    });
+    bytes.extend_from_slice(&data[packet_len..]);
+    Ok(bytes)
+}
+
+fn tcp(data: &[u8], _: &mut Dissection) -> Result<Vec<u8>, String> {
+    let tcppkt = TcpPacket::parse(Cursor::new(data)).map_err(|_| "malformed header")?;
+    let header_len = usize::from(tcppkt.header_len());
+
+    let mut header = TCP_HEADER_TEMPLATE;
+    header.set_header_len(tcppkt.header_len());
+    Ok(prepend(header_len, &data[header_len..], |buf| {
+        let mut pkt = TcpPacket::prepend_header(buf, &header);
+        pkt.option_bytes_mut()
+            .copy_from_slice(tcppkt.option_bytes());
+        pkt.set_src_port(tcppkt.src_port());
+        pkt.set_dst_port(tcppkt.dst_port());
+        pkt.set_seq_number(tcppkt.seq_number());
+        pkt.set_ack_number(tcppkt.ack_number());
+        pkt.set_fin(tcppkt.fin());
+        pkt.set_syn(tcppkt.syn());
+        pkt.set_rst(tcppkt.rst());
+        pkt.set_psh(tcppkt.psh());
+        pkt.set_ack(tcppkt.ack());
+        pkt.set_urg(tcppkt.urg());
+        pkt.set_ece(tcppkt.ece());
+        pkt.set_cwr(tcppkt.cwr());
+        pkt.set_ns(tcppkt.ns());
+        pkt.set_window_size(tcppkt.window_size());
+        pkt.set_checksum(tcppkt.checksum());
+        pkt.set_urgent_ptr(tcppkt.urgent_ptr());
+    }))
+}
+
+fn udp(data: &[u8], _: &mut Dissection) -> Result<Vec<u8>, String> {
+    let udppkt = UdpPacket::parse(Cursor::new(data)).map_err(|_| "malformed header")?;
+    let packet_len = usize::from(udppkt.packet_len());
+
+    let mut bytes = prepend(UDP_HEADER_LEN, &data[UDP_HEADER_LEN..packet_len], |buf| {
+        let mut pkt = UdpPacket::prepend_header(buf, &UDP_HEADER_TEMPLATE);
+        pkt.set_source_port(udppkt.source_port());
+        pkt.set_dest_port(udppkt.dest_port());
+        pkt.set_checksum(udppkt.checksum());
+    });
+    bytes.extend_from_slice(&data[packet_len..]);
+    Ok(bytes)
+}
+
+fn icmpv4(data: &[u8], _: &mut Dissection) -> Result<Vec<u8>, String> {
+    let icmppkt = Icmpv4Packet::parse(Cursor::new(data)).map_err(|_| "truncated header")?;
+
+    // The packet quoted by an error message is truncated, so it is not parsed.
+    Ok(prepend(
+        ICMPV4_HEADER_LEN,
+        &data[ICMPV4_HEADER_LEN..],
+        |buf| {
+            let mut pkt = Icmpv4Packet::prepend_header(buf, &ICMPV4_HEADER_TEMPLATE);
+            pkt.set_icmp_type(icmppkt.icmp_type());
+            pkt.set_code(icmppkt.code());
+            pkt.set_checksum(icmppkt.checksum());
+            pkt.set_rest_of_header(&icmppkt.rest_of_header()[..]);
+        },
+    ))
+}
+
+fn icmpv6(data: &[u8], _: &mut Dissection) -> Result<Vec<u8>, String> {
+    let icmppkt = Icmpv6Packet::parse(Cursor::new(data)).map_err(|_| "truncated header")?;
+    if let Icmpv6Msg::Invalid(msg_type) = icmppkt.msg() {
+        return Err(format!(
+            "unsupported or malformed message of type {}",
+            msg_type
+        ));
+    }
+
+    // There is no template of an ICMPv6 header either, the message body is
+    // copied.
+    let mut bytes = vec![0; data.len()];
+    bytes[4..].copy_from_slice(icmppkt.data());
+    let mut pkt = Icmpv6Packet::parse_unchecked(CursorMut::new(&mut bytes[..]));
+    pkt.set_msg_type(icmppkt.msg_type().into());
+    pkt.set_code(icmppkt.code());
+    pkt.set_checksum(icmppkt.checksum());
+    Ok(bytes)
+}
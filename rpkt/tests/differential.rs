@@ -0,0 +1,313 @@
+//! A differential test of the header parsers against `etherparse` and `pnet`.
+//!
+//! Every frame of the packet corpus, and many randomly mutated copies of them,
+//! are parsed by `rpkt` from the Ethernet header down to the TCP or UDP
+//! header. Every header accepted by `rpkt` is parsed again by the two
+//! reference libraries, and every field read by `rpkt` must equal the one read
+//! by the references. This catches the field offsets and masks broken in the
+//! generated header accessors.
+//!
+//! The suite depends on `etherparse`, which is only pulled by the
+//! `differential` feature:
+//!
+//! `cargo test -p rpkt --features differential --test differential`
+
+use std::collections::BTreeMap;
+
+use etherparse::{
+    Ethernet2HeaderSlice, Ipv4HeaderSlice, Ipv6HeaderSlice, TcpHeaderSlice, UdpHeaderSlice,
+};
+use pnet::packet::{ethernet, ipv4, ipv6, tcp, udp};
+
+use rpkt::corpus;
+use rpkt::ether::{EtherPacket, EtherType, ETHER_HEADER_LEN};
+use rpkt::ipv4::{IpProtocol, Ipv4Packet};
+use rpkt::ipv6::{Ipv6Packet, IPV6_HEADER_LEN};
+use rpkt::mutate::Mutator;
+use rpkt::tcp::TcpPacket;
+use rpkt::udp::UdpPacket;
+use rpkt::Cursor;
+
+/// The number of mutated frames.
+const ROUNDS: u64 = 20_000;
+
+// Compare a field read by rpkt with the one read by a reference library.
+macro_rules! check {
+    ($layer:expr, $reference:expr, $ours:expr, $theirs:expr) => {
+        let (ours, theirs) = ($ours, $theirs);
+        if ours != theirs {
+            return Err(format!(
+                "{}: rpkt reads {:?} from `{}`, {} reads {:?} from `{}`",
+                $layer,
+                ours,
+                stringify!($ours),
+                $reference,
+                theirs,
+                stringify!($theirs)
+            ));
+        }
+    };
+}
+
+const EP: &str = "etherparse";
+const PNET: &str = "pnet";
+
+fn rejected<E: std::fmt::Debug>(layer: &str, err: E) -> String {
+    format!(
+        "{}: rpkt accepts the header, etherparse rejects it with {:?}",
+        layer, err
+    )
+}
+
+/// The differential test of a frame.
+#[derive(Debug, Default)]
+struct Differ {
+    /// The number of compared headers, by protocol.
+    compared: BTreeMap<&'static str, usize>,
+}
+
+impl Differ {
+    fn compared(&mut self, layer: &'static str) {
+        *self.compared.entry(layer).or_default() += 1;
+    }
+
+    fn ether(&mut self, data: &[u8]) -> Result<(), String> {
+        let Ok(pkt) = EtherPacket::parse(Cursor::new(data)) else {
+            return Ok(());
+        };
+
+        let eth = Ethernet2HeaderSlice::from_slice(data).map_err(|err| rejected("ether", err))?;
+        check!("ether", EP, pkt.dest_mac().0, eth.destination());
+        check!("ether", EP, pkt.source_mac().0, eth.source());
+        check!("ether", EP, u16::from(pkt.ethertype()), eth.ether_type());
+
+        let eth = ethernet::EthernetPacket::new(data).unwrap();
+        check!(
+            "ether",
+            PNET,
+            pkt.dest_mac().0,
+            eth.get_destination().octets()
+        );
+        check!("ether", PNET, pkt.source_mac().0, eth.get_source().octets());
+        check!(
+            "ether",
+            PNET,
+            u16::from(pkt.ethertype()),
+            eth.get_ethertype().0
+        );
+        self.compared("ether");
+
+        match pkt.ethertype() {
+            EtherType::IPV4 => self.ipv4(&data[ETHER_HEADER_LEN..]),
+            EtherType::IPV6 => self.ipv6(&data[ETHER_HEADER_LEN..]),
+            _ => Ok(()),
+        }
+    }
+
+    fn ipv4(&mut self, data: &[u8]) -> Result<(), String> {
+        let pkt = match Ipv4Packet::parse(Cursor::new(data)) {
+            Ok(pkt) if pkt.check_version() => pkt,
+            _ => return Ok(()),
+        };
+
+        let ip = Ipv4HeaderSlice::from_slice(data).map_err(|err| rejected("ipv4", err))?;
+        check!("ipv4", EP, pkt.header_len(), ip.ihl() * 4);
+        check!("ipv4", EP, pkt.dscp(), ip.dcp());
+        check!("ipv4", EP, pkt.ecn(), ip.ecn());
+        check!("ipv4", EP, pkt.packet_len(), ip.total_len());
+        check!("ipv4", EP, pkt.ident(), ip.identification());
+        check!("ipv4", EP, pkt.dont_frag(), ip.dont_fragment());
+        check!("ipv4", EP, pkt.more_frags(), ip.more_fragments());
+        // etherparse reads the offset in units of 8 bytes.
+        check!("ipv4", EP, pkt.frag_offset(), ip.fragments_offset() * 8);
+        check!("ipv4", EP, pkt.time_to_live(), ip.ttl());
+        check!("ipv4", EP, u8::from(pkt.protocol()), ip.protocol());
+        check!("ipv4", EP, pkt.checksum(), ip.header_checksum());
+        check!("ipv4", EP, pkt.source_ip().0, ip.source());
+        check!("ipv4", EP, pkt.dest_ip().0, ip.destination());
+
+        let ip = ipv4::Ipv4Packet::new(data).unwrap();
+        check!("ipv4", PNET, pkt.header_len(), ip.get_header_length() * 4);
+        check!("ipv4", PNET, pkt.dscp(), ip.get_dscp());
+        check!("ipv4", PNET, pkt.ecn(), ip.get_ecn());
+        check!("ipv4", PNET, pkt.packet_len(), ip.get_total_length());
+        check!("ipv4", PNET, pkt.ident(), ip.get_identification());
+        check!("ipv4", PNET, pkt.dont_frag(), ip.get_flags() & 0b010 != 0);
+        check!("ipv4", PNET, pkt.more_frags(), ip.get_flags() & 0b001 != 0);
+        check!(
+            "ipv4",
+            PNET,
+            pkt.frag_offset(),
+            ip.get_fragment_offset() * 8
+        );
+        check!("ipv4", PNET, pkt.time_to_live(), ip.get_ttl());
+        check!(
+            "ipv4",
+            PNET,
+            u8::from(pkt.protocol()),
+            ip.get_next_level_protocol().0
+        );
+        check!("ipv4", PNET, pkt.checksum(), ip.get_checksum());
+        check!("ipv4", PNET, pkt.source_ip().0, ip.get_source().octets());
+        check!("ipv4", PNET, pkt.dest_ip().0, ip.get_destination().octets());
+        self.compared("ipv4");
+
+        // The payload of a fragment is not a transport header.
+        if pkt.more_frags() || pkt.frag_offset() != 0 {
+            return Ok(());
+        }
+        let payload = &data[usize::from(pkt.header_len())..usize::from(pkt.packet_len())];
+        self.transport(pkt.protocol(), payload)
+    }
+
+    fn ipv6(&mut self, data: &[u8]) -> Result<(), String> {
+        let pkt = match Ipv6Packet::parse(Cursor::new(data)) {
+            Ok(pkt) if pkt.check_version() => pkt,
+            _ => return Ok(()),
+        };
+
+        let ip = Ipv6HeaderSlice::from_slice(data).map_err(|err| rejected("ipv6", err))?;
+        check!("ipv6", EP, pkt.traffic_class(), ip.traffic_class());
+        check!("ipv6", EP, pkt.flow_label(), ip.flow_label());
+        check!("ipv6", EP, pkt.payload_len(), ip.payload_length());
+        check!("ipv6", EP, u8::from(pkt.next_header()), ip.next_header());
+        check!("ipv6", EP, pkt.hop_limit(), ip.hop_limit());
+        check!("ipv6", EP, pkt.source_ip().0, ip.source());
+        check!("ipv6", EP, pkt.dest_ip().0, ip.destination());
+
+        let ip = ipv6::Ipv6Packet::new(data).unwrap();
+        check!("ipv6", PNET, pkt.traffic_class(), ip.get_traffic_class());
+        check!("ipv6", PNET, pkt.flow_label(), ip.get_flow_label());
+        check!("ipv6", PNET, pkt.payload_len(), ip.get_payload_length());
+        check!(
+            "ipv6",
+            PNET,
+            u8::from(pkt.next_header()),
+            ip.get_next_header().0
+        );
+        check!("ipv6", PNET, pkt.hop_limit(), ip.get_hop_limit());
+        check!("ipv6", PNET, pkt.source_ip().0, ip.get_source().octets());
+        check!("ipv6", PNET, pkt.dest_ip().0, ip.get_destination().octets());
+        self.compared("ipv6");
+
+        // The extension headers are not parsed.
+        let payload = &data[IPV6_HEADER_LEN..IPV6_HEADER_LEN + usize::from(pkt.payload_len())];
+        self.transport(pkt.next_header(), payload)
+    }
+
+    fn transport(&mut self, protocol: IpProtocol, data: &[u8]) -> Result<(), String> {
+        match protocol {
+            IpProtocol::TCP => self.tcp(data),
+            IpProtocol::UDP => self.udp(data),
+            _ => Ok(()),
+        }
+    }
+
+    fn tcp(&mut self, data: &[u8]) -> Result<(), String> {
+        let Ok(pkt) = TcpPacket::parse(Cursor::new(data)) else {
+            return Ok(());
+        };
+
+        let tcp = TcpHeaderSlice::from_slice(data).map_err(|err| rejected("tcp", err))?;
+        check!("tcp", EP, pkt.src_port(), tcp.source_port());
+        check!("tcp", EP, pkt.dst_port(), tcp.destination_port());
+        check!("tcp", EP, pkt.seq_number(), tcp.sequence_number());
+        check!("tcp", EP, pkt.ack_number(), tcp.acknowledgment_number());
+        check!("tcp", EP, pkt.header_len(), tcp.data_offset() * 4);
+        check!("tcp", EP, pkt.ns(), tcp.ns());
+        check!("tcp", EP, pkt.cwr(), tcp.cwr());
+        check!("tcp", EP, pkt.ece(), tcp.ece());
+        check!("tcp", EP, pkt.urg(), tcp.urg());
+        check!("tcp", EP, pkt.ack(), tcp.ack());
+        check!("tcp", EP, pkt.psh(), tcp.psh());
+        check!("tcp", EP, pkt.rst(), tcp.rst());
+        check!("tcp", EP, pkt.syn(), tcp.syn());
+        check!("tcp", EP, pkt.fin(), tcp.fin());
+        check!("tcp", EP, pkt.window_size(), tcp.window_size());
+        check!("tcp", EP, pkt.checksum(), tcp.checksum());
+        check!("tcp", EP, pkt.urgent_ptr(), tcp.urgent_pointer());
+        check!("tcp", EP, pkt.option_bytes(), tcp.options());
+
+        // The flags of pnet are compared through etherparse only, as their
+        // width changed across the pnet releases.
+        let tcp = tcp::TcpPacket::new(data).unwrap();
+        check!("tcp", PNET, pkt.src_port(), tcp.get_source());
+        check!("tcp", PNET, pkt.dst_port(), tcp.get_destination());
+        check!("tcp", PNET, pkt.seq_number(), tcp.get_sequence());
+        check!("tcp", PNET, pkt.ack_number(), tcp.get_acknowledgement());
+        check!("tcp", PNET, pkt.header_len(), tcp.get_data_offset() * 4);
+        check!("tcp", PNET, pkt.window_size(), tcp.get_window());
+        check!("tcp", PNET, pkt.checksum(), tcp.get_checksum());
+        check!("tcp", PNET, pkt.urgent_ptr(), tcp.get_urgent_ptr());
+        self.compared("tcp");
+        Ok(())
+    }
+
+    fn udp(&mut self, data: &[u8]) -> Result<(), String> {
+        let Ok(pkt) = UdpPacket::parse(Cursor::new(data)) else {
+            return Ok(());
+        };
+
+        let udp = UdpHeaderSlice::from_slice(data).map_err(|err| rejected("udp", err))?;
+        check!("udp", EP, pkt.source_port(), udp.source_port());
+        check!("udp", EP, pkt.dest_port(), udp.destination_port());
+        check!("udp", EP, pkt.packet_len(), udp.length());
+        check!("udp", EP, pkt.checksum(), udp.checksum());
+
+        let udp = udp::UdpPacket::new(data).unwrap();
+        check!("udp", PNET, pkt.source_port(), udp.get_source());
+        check!("udp", PNET, pkt.dest_port(), udp.get_destination());
+        check!("udp", PNET, pkt.packet_len(), udp.get_length());
+        check!("udp", PNET, pkt.checksum(), udp.get_checksum());
+        self.compared("udp");
+        Ok(())
+    }
+
+    fn assert_compared(&self, layers: &[&str]) {
+        for layer in layers {
+            assert!(
+                self.compared.get(layer).copied().unwrap_or(0) > 0,
+                "no {} header is compared: {:?}",
+                layer,
+                self.compared
+            );
+        }
+    }
+}
+
+const LAYERS: &[&str] = &["ether", "ipv4", "ipv6", "tcp", "udp"];
+
+#[test]
+fn corpus_frames() {
+    let mut differ = Differ::default();
+    for sample in corpus::generate() {
+        if let Err(err) = differ.ether(&sample.frame) {
+            panic!("{}: {}", sample.label(), err);
+        }
+    }
+    differ.assert_compared(LAYERS);
+}
+
+#[test]
+fn mutated_frames() {
+    let samples = corpus::generate();
+    let mut differ = Differ::default();
+    for seed in 0..ROUNDS {
+        let mut mutator = Mutator::new(seed);
+        let sample = &samples[(mutator.next_u64() % samples.len() as u64) as usize];
+        let mut frame = sample.frame.clone();
+        let mutations: Vec<_> = (0..1 + mutator.next_u64() % 3)
+            .map(|_| mutator.mutate(&mut frame))
+            .collect();
+        if let Err(err) = differ.ether(&frame) {
+            panic!(
+                "seed {}, {} mutated by {:?}: {}",
+                seed,
+                sample.label(),
+                mutations,
+                err
+            );
+        }
+    }
+    differ.assert_compared(LAYERS);
+}